@@ -0,0 +1,445 @@
+//! Graph queries that follow the same edges layout does, for callers that
+//! work with the diagram's structure rather than its drawing
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::layout::{endpoint_nodes, kahn_layers};
+use crate::types::EdgeStyle;
+use crate::types::{Graph, NodeId};
+
+impl Graph {
+    /// Cycles in the graph as node paths that end where they start, e.g.
+    /// `["A", "B", "C", "A"]`.
+    ///
+    /// Each cycle closes with an edge that leads back to a node on the
+    /// current path; removing those closing edges leaves the graph acyclic.
+    /// The search visits nodes in the order layout breaks cycles (earliest
+    /// edge source first), so results are stable.
+    ///
+    /// Cycles follow the edges as declared, so a container is one step of
+    /// its own: `a -> c` and `c -> a` give `["a", "c", "a"]` once, however
+    /// many nodes `c` holds. Reaching a container also leads out along the
+    /// edges that leave its members, and reaching a member leads out along
+    /// the edges that leave its containers.
+    ///
+    /// # Example
+    /// ```
+    /// use graphs_tui::{parse_diagram, ParsedDiagram};
+    ///
+    /// let ParsedDiagram::Flowchart(graph) = parse_diagram("flowchart LR\nA --> B --> C --> A").unwrap() else {
+    ///     unreachable!()
+    /// };
+    /// assert_eq!(graph.find_cycles(), vec![vec!["A", "B", "C", "A"]]);
+    /// ```
+    pub fn find_cycles(&self) -> Vec<Vec<NodeId>> {
+        let mut vertices: Vec<&str> = self.nodes.keys().map(String::as_str).collect();
+        for edge in &self.edges {
+            vertices.extend([edge.from.as_str(), edge.to.as_str()]);
+        }
+        vertices.sort_unstable();
+        vertices.dedup();
+        let containers: HashMap<&str, Vec<&str>> = vertices
+            .iter()
+            .map(|&v| (v, self.containers_of(v)))
+            .collect();
+        let inside =
+            |id: &str, container: &str| containers.get(id).is_some_and(|c| c.contains(&container));
+
+        // A container and its own members are one place, so edges between
+        // them never close a cycle
+        let edges: Vec<(&str, &str)> = self
+            .edges
+            .iter()
+            .map(|e| (e.from.as_str(), e.to.as_str()))
+            .filter(|&(from, to)| !inside(from, to) && !inside(to, from))
+            .collect();
+        let mut successors: HashMap<&str, Vec<&str>> = HashMap::new();
+        for &vertex in &vertices {
+            let targets: Vec<&str> = edges
+                .iter()
+                .filter(|&&(from, to)| {
+                    from == vertex
+                        || inside(vertex, from)
+                        || (inside(from, vertex) && to != vertex && !inside(to, vertex))
+                })
+                .map(|&(_, to)| to)
+                .collect();
+            let mut seen: HashSet<&str> = HashSet::new();
+            successors.insert(
+                vertex,
+                targets.into_iter().filter(|t| seen.insert(t)).collect(),
+            );
+        }
+
+        let mut cycles = Vec::new();
+        let mut done: HashSet<&str> = HashSet::new();
+        for root in self.search_order(&vertices) {
+            if done.contains(root) {
+                continue;
+            }
+            // Iterative DFS: the current path and how far each step has
+            // got through its successors
+            let mut path: Vec<(&str, usize)> = vec![(root, 0)];
+            let mut on_path: HashSet<&str> = HashSet::from([root]);
+            while let Some((node, next)) = path.last_mut() {
+                let node = *node;
+                let Some(&succ) = successors.get(node).and_then(|s| s.get(*next)) else {
+                    path.pop();
+                    on_path.remove(node);
+                    done.insert(node);
+                    continue;
+                };
+                *next += 1;
+                if on_path.contains(succ) {
+                    let start = path.iter().position(|(n, _)| *n == succ).unwrap_or(0);
+                    let mut cycle: Vec<NodeId> =
+                        path[start..].iter().map(|(n, _)| n.to_string()).collect();
+                    cycle.push(succ.to_string());
+                    cycles.push(cycle);
+                } else if !done.contains(succ) {
+                    path.push((succ, 0));
+                    on_path.insert(succ);
+                }
+            }
+        }
+        cycles
+    }
+
+    /// Every node after the nodes that point to it, in the order layout
+    /// assigns layers, for printing ordered task lists from a diagram.
+    ///
+    /// Ties go to sources first sorted by id, then to successors in id
+    /// order. Where a cycle leaves no node free, the one that appears
+    /// earliest as an edge source goes next, just as layout breaks cycles.
+    ///
+    /// # Example
+    /// ```
+    /// use graphs_tui::{parse_diagram, ParsedDiagram};
+    ///
+    /// let ParsedDiagram::Flowchart(graph) =
+    ///     parse_diagram("flowchart LR\nTest --> Ship\nBuild --> Test\nLint --> Ship").unwrap()
+    /// else {
+    ///     unreachable!()
+    /// };
+    /// assert_eq!(graph.topological_order(), vec!["Build", "Lint", "Test", "Ship"]);
+    /// ```
+    pub fn topological_order(&self) -> Vec<NodeId> {
+        kahn_layers(self).0
+    }
+
+    /// Fewest-hops path from `from` to `to` following edge direction,
+    /// including both ends; `None` if either end is missing or `to` can't
+    /// be reached. Invisible links don't count as a way through.
+    ///
+    /// Either end may be a container: the path then starts or ends at the
+    /// container's id and runs through whichever of its nodes is nearest.
+    ///
+    /// # Example
+    /// ```
+    /// use graphs_tui::{parse_diagram, ParsedDiagram};
+    ///
+    /// let ParsedDiagram::Flowchart(graph) =
+    ///     parse_diagram("flowchart LR\nUser --> Web --> Api --> DB\nWeb --> DB").unwrap()
+    /// else {
+    ///     unreachable!()
+    /// };
+    /// assert_eq!(graph.shortest_path("User", "DB").unwrap(), vec!["User", "Web", "DB"]);
+    /// assert_eq!(graph.shortest_path("DB", "User"), None);
+    /// ```
+    pub fn shortest_path(&self, from: &str, to: &str) -> Option<Vec<NodeId>> {
+        let sources = endpoint_nodes(self, from);
+        let targets = endpoint_nodes(self, to);
+        if sources.is_empty() || targets.is_empty() {
+            return None;
+        }
+        let mut successors: HashMap<NodeId, Vec<NodeId>> = HashMap::new();
+        for edge in self
+            .edges
+            .iter()
+            .filter(|e| e.style != EdgeStyle::Invisible)
+        {
+            let targets = endpoint_nodes(self, &edge.to);
+            for source in endpoint_nodes(self, &edge.from) {
+                successors
+                    .entry(source)
+                    .or_default()
+                    .extend(targets.iter().cloned());
+            }
+        }
+
+        // Breadth-first from every node `from` stands for, remembering
+        // where each node was first reached from
+        let mut came_from: HashMap<&str, &str> = HashMap::new();
+        let mut queue: VecDeque<&str> = sources.iter().map(String::as_str).collect();
+        let mut seen: HashSet<&str> = queue.iter().copied().collect();
+        while let Some(node) = queue.pop_front() {
+            if targets.iter().any(|t| t == node) {
+                let mut path = vec![node.to_string()];
+                let mut at = node;
+                while let Some(&prev) = came_from.get(at) {
+                    path.push(prev.to_string());
+                    at = prev;
+                }
+                path.reverse();
+                // A container end stands in for the node the path reached
+                if !self.nodes.contains_key(from) {
+                    path[0] = from.to_string();
+                }
+                if !self.nodes.contains_key(to) {
+                    *path.last_mut()? = to.to_string();
+                }
+                return Some(path);
+            }
+            for next in successors.get(node).into_iter().flatten() {
+                if seen.insert(next) {
+                    came_from.insert(next, node);
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    /// Heaviest chain of edges by [`Edge::weight`](crate::Edge::weight),
+    /// e.g. the tasks that decide a project's length; empty when no edge
+    /// has a weight.
+    ///
+    /// Edges without a weight count as zero. Edges that point back
+    /// against [`topological_order`](Self::topological_order) close
+    /// cycles and are left out, so cyclic graphs still get a path.
+    ///
+    /// # Example
+    /// ```
+    /// use graphs_tui::{parse_diagram, ParsedDiagram};
+    ///
+    /// let input = "flowchart LR\nA -->|design (2d)| B -->|build (5d)| D\nA -->|docs (1d)| C --> D";
+    /// let ParsedDiagram::Flowchart(graph) = parse_diagram(input).unwrap() else {
+    ///     unreachable!()
+    /// };
+    /// assert_eq!(graph.critical_path(), vec!["A", "B", "D"]);
+    /// ```
+    pub fn critical_path(&self) -> Vec<NodeId> {
+        let order = self.topological_order();
+        let position: HashMap<&str, usize> = order
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (id.as_str(), i))
+            .collect();
+        let mut outgoing: HashMap<&str, Vec<(&str, u64)>> = HashMap::new();
+        for edge in self
+            .edges
+            .iter()
+            .filter(|e| e.style != EdgeStyle::Invisible)
+        {
+            let weight = edge.weight.unwrap_or(0);
+            for from in endpoint_nodes(self, &edge.from) {
+                for to in endpoint_nodes(self, &edge.to) {
+                    if let (Some((from, &a)), Some((to, &b))) = (
+                        position.get_key_value(from.as_str()),
+                        position.get_key_value(to.as_str()),
+                    ) {
+                        if a < b {
+                            outgoing.entry(from).or_default().push((to, weight));
+                        }
+                    }
+                }
+            }
+        }
+
+        // Longest path in topological order, keeping the first best found
+        // Summed as u128 so even `u64::MAX` weights can't overflow
+        let mut best: HashMap<&str, (u128, Option<&str>)> = HashMap::new();
+        for id in &order {
+            let (length, _) = *best.entry(id.as_str()).or_insert((0, None));
+            for &(to, weight) in outgoing.get(id.as_str()).into_iter().flatten() {
+                let entry = best.entry(to).or_insert((0, None));
+                let through = length + u128::from(weight);
+                if through > entry.0 {
+                    *entry = (through, Some(id.as_str()));
+                }
+            }
+        }
+        let Some(end) = order
+            .iter()
+            .map(|id| id.as_str())
+            .filter(|id| best[id].0 > 0)
+            .max_by(|a, b| {
+                best[a]
+                    .0
+                    .cmp(&best[b].0)
+                    .then(position[b].cmp(&position[a]))
+            })
+        else {
+            return Vec::new();
+        };
+
+        let mut path = vec![end.to_string()];
+        let mut at = end;
+        while let Some(prev) = best[at].1 {
+            path.push(prev.to_string());
+            at = prev;
+        }
+        path.reverse();
+        path
+    }
+
+    /// Indices of the edges that take `path` from each node to the next
+    pub(crate) fn path_edges(&self, path: &[NodeId]) -> Vec<usize> {
+        (0..self.edges.len())
+            .filter(|&i| {
+                let edge = &self.edges[i];
+                if edge.style == EdgeStyle::Invisible {
+                    return false;
+                }
+                let mut sources = endpoint_nodes(self, &edge.from);
+                let mut targets = endpoint_nodes(self, &edge.to);
+                sources.push(edge.from.clone());
+                targets.push(edge.to.clone());
+                path.windows(2)
+                    .any(|step| sources.contains(&step[0]) && targets.contains(&step[1]))
+            })
+            .collect()
+    }
+
+    /// `vertices` ordered as layout prefers them when breaking cycles: by
+    /// first appearance as an edge source, then by id
+    fn search_order<'a>(&self, vertices: &[&'a str]) -> Vec<&'a str> {
+        let mut first_from: HashMap<&str, usize> = HashMap::new();
+        for (i, edge) in self.edges.iter().enumerate() {
+            first_from.entry(edge.from.as_str()).or_insert(i);
+        }
+        let mut order = vertices.to_vec();
+        order.sort_by_key(|id| (first_from.get(id).copied().unwrap_or(usize::MAX), *id));
+        order
+    }
+
+    /// Subgraphs holding `id`, a node or a subgraph, innermost first
+    fn containers_of(&self, id: &str) -> Vec<&str> {
+        let mut parent = match self.nodes.get(id) {
+            Some(node) => node.subgraph.as_deref(),
+            None => self
+                .subgraphs
+                .iter()
+                .find(|sg| sg.id == id)
+                .and_then(|sg| sg.parent.as_deref()),
+        };
+        let mut containers = Vec::new();
+        while let Some(sg_id) = parent {
+            if sg_id == id || containers.contains(&sg_id) {
+                break;
+            }
+            containers.push(sg_id);
+            parent = self
+                .subgraphs
+                .iter()
+                .find(|sg| sg.id == sg_id)
+                .and_then(|sg| sg.parent.as_deref());
+        }
+        containers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::d2_parser::parse_d2;
+    use crate::parser::parse_mermaid;
+    use crate::types::Edge;
+
+    #[test]
+    fn test_find_cycles() {
+        let graph =
+            parse_mermaid("flowchart LR\nA --> B\nB --> A\nB --> C\nC --> C\nC --> D").unwrap();
+        assert_eq!(
+            graph.find_cycles(),
+            vec![vec!["A", "B", "A"], vec!["C", "C"]]
+        );
+        let graph = parse_mermaid("flowchart LR\nA --> B\nA --> C\nB --> D\nC --> D").unwrap();
+        assert!(graph.find_cycles().is_empty());
+    }
+
+    #[test]
+    fn test_find_cycles_through_containers() {
+        let graph = parse_d2("s: {\n  x\n}\na -> s\ns.x -> a").unwrap().graph;
+        assert_eq!(graph.find_cycles(), vec![vec!["a", "s", "a"]]);
+
+        let graph = parse_d2("a -> c\nc: {\n  x\n  y\n}\nc -> a").unwrap().graph;
+        assert_eq!(graph.find_cycles(), vec![vec!["a", "c", "a"]]);
+
+        let graph = parse_d2("c: {\n  x\n}\nc -> a\na -> c.x").unwrap().graph;
+        assert_eq!(graph.find_cycles().len(), 1);
+
+        let graph = crate::state_parser::parse_state_diagram(
+            "stateDiagram-v2\nIdle --> Active\nstate Active {\n  [*] --> Run\n  Run --> Pause\n}\nActive --> Idle",
+        )
+        .unwrap();
+        assert_eq!(graph.find_cycles(), vec![vec!["Idle", "Active", "Idle"]]);
+    }
+
+    #[test]
+    fn test_shortest_path_to_and_from_containers() {
+        let graph = crate::state_parser::parse_state_diagram(
+            "stateDiagram-v2\nIdle --> Active\nstate Active {\n  [*] --> Run\n}\nActive --> Idle",
+        )
+        .unwrap();
+        let there = graph.shortest_path("Idle", "Active").unwrap();
+        assert_eq!(there, vec!["Idle", "Active"]);
+        let back = graph.shortest_path("Active", "Idle").unwrap();
+        assert_eq!(back, vec!["Active", "Idle"]);
+        let edge = |path: &[String]| {
+            let edges = graph.path_edges(path);
+            edges
+                .iter()
+                .map(|&i| (graph.edges[i].from.as_str(), graph.edges[i].to.as_str()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(edge(&there), vec![("Idle", "Active")]);
+        assert_eq!(edge(&back), vec![("Active", "Idle")]);
+        assert_eq!(graph.shortest_path("Idle", "Nowhere"), None);
+    }
+
+    #[test]
+    fn test_topological_order_breaks_cycles_like_layout() {
+        let graph = parse_mermaid("flowchart LR\nC --> A\nA --> B\nB --> C\nB --> D").unwrap();
+        assert_eq!(graph.topological_order(), vec!["C", "A", "B", "D"]);
+        let graph = parse_mermaid("flowchart LR\nB --> C\nA --> C\nZ").unwrap();
+        assert_eq!(graph.topological_order(), vec!["A", "B", "Z", "C"]);
+    }
+
+    #[test]
+    fn test_shortest_path_and_its_edges() {
+        let graph =
+            parse_mermaid("flowchart LR\nA --> B\nB --> C\nC --> D\nA -.-> C\nB ~~~ D\nD --> A")
+                .unwrap();
+        let path = graph.shortest_path("A", "D").unwrap();
+        assert_eq!(path, vec!["A", "C", "D"]);
+        assert_eq!(graph.path_edges(&path), vec![2, 3]);
+        assert_eq!(graph.shortest_path("B", "B").unwrap(), vec!["B"]);
+        assert_eq!(graph.shortest_path("A", "Nope"), None);
+    }
+
+    #[test]
+    fn test_critical_path() {
+        let graph = parse_mermaid(
+            "flowchart LR\nA -->|plan (1d)| B\nB -->|build (2w)| D\nA -->|buy (9d)| C\nC --> D\nD -->|retry (1h)| A",
+        )
+        .unwrap();
+        assert_eq!(graph.edges[1].weight, Some(2 * 7 * 24 * 60));
+        assert_eq!(graph.critical_path(), vec!["A", "B", "D"]);
+        let graph = parse_mermaid("flowchart LR\nA --> B").unwrap();
+        assert!(graph.critical_path().is_empty());
+        let graph =
+            parse_mermaid("flowchart LR\nA -->|(18446744073709551615)| B -->|(5)| C\nA -->|(1)| D")
+                .unwrap();
+        assert_eq!(graph.critical_path(), vec!["A", "B", "C"]);
+    }
+
+    #[test]
+    fn test_parse_weight() {
+        assert_eq!(Edge::parse_weight("(3d)"), Some(3 * 24 * 60));
+        assert_eq!(Edge::parse_weight("review ( 90 )"), Some(90));
+        assert_eq!(Edge::parse_weight("deploy (2H)"), Some(120));
+        assert_eq!(Edge::parse_weight("see (notes)"), None);
+        assert_eq!(Edge::parse_weight("(3y)"), None);
+        assert_eq!(Edge::parse_weight("3d"), None);
+    }
+}
@@ -0,0 +1,174 @@
+//! Size and time budgets that guard against pathological inputs
+
+use std::time::{Duration, Instant};
+
+use crate::error::MermaidError;
+use crate::types::{Graph, RenderOptions};
+
+/// Render options for untrusted input, such as server requests and tool
+/// calls: at most 1000 nodes and 2000 edges, and two seconds per diagram
+pub fn limited_options() -> RenderOptions {
+    RenderOptions {
+        max_nodes: Some(1000),
+        max_edges: Some(2000),
+        time_budget: Some(Duration::from_secs(2)),
+        ..Default::default()
+    }
+}
+
+/// Most cells a graph drawing may cover, so a layout stretched far past
+/// its nodes fails instead of exhausting memory
+pub(crate) const MAX_GRID_CELLS: usize = 4_000_000;
+
+/// Tracks the time budget for a single render call, from parsing through
+/// drawing
+#[derive(Debug, Clone, Copy)]
+pub struct Budget {
+    start: Instant,
+    limit_ms: Option<u128>,
+}
+
+impl Budget {
+    /// Start the clock for the time budget configured in `options`
+    pub fn new(options: &RenderOptions) -> Self {
+        Self {
+            start: Instant::now(),
+            limit_ms: options.time_budget.map(|d| d.as_millis()),
+        }
+    }
+
+    /// A budget that never runs out
+    pub fn unlimited() -> Self {
+        Self {
+            start: Instant::now(),
+            limit_ms: None,
+        }
+    }
+
+    /// Fail with `TooLarge` once the time budget has been spent
+    pub fn check_time(&self) -> Result<(), MermaidError> {
+        let Some(limit) = self.limit_ms else {
+            return Ok(());
+        };
+        let elapsed = self.start.elapsed().as_millis();
+        if elapsed > limit {
+            return Err(MermaidError::TooLarge {
+                resource: "milliseconds".to_string(),
+                limit: limit as usize,
+                actual: elapsed as usize,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Fail with `TooLarge` if the counts exceed `max_nodes` / `max_edges`
+pub(crate) fn check_counts(
+    nodes: usize,
+    edges: usize,
+    options: &RenderOptions,
+) -> Result<(), MermaidError> {
+    if let Some(limit) = options.max_nodes {
+        if nodes > limit {
+            return Err(MermaidError::TooLarge {
+                resource: "nodes".to_string(),
+                limit,
+                actual: nodes,
+            });
+        }
+    }
+    if let Some(limit) = options.max_edges {
+        if edges > limit {
+            return Err(MermaidError::TooLarge {
+                resource: "edges".to_string(),
+                limit,
+                actual: edges,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Fail with `TooLarge` if a `width` × `height` grid exceeds
+/// [`MAX_GRID_CELLS`]
+pub(crate) fn check_grid(width: usize, height: usize) -> Result<(), MermaidError> {
+    let cells = width.saturating_mul(height);
+    if cells > MAX_GRID_CELLS {
+        return Err(MermaidError::TooLarge {
+            resource: "grid cells".to_string(),
+            limit: MAX_GRID_CELLS,
+            actual: cells,
+        });
+    }
+    Ok(())
+}
+
+/// Check a parsed graph against the configured size limits
+pub(crate) fn check_graph(graph: &Graph, options: &RenderOptions) -> Result<(), MermaidError> {
+    check_counts(graph.nodes.len(), graph.edges.len(), options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_no_limits_by_default() {
+        let options = RenderOptions::default();
+        assert!(check_counts(100_000, 100_000, &options).is_ok());
+        assert!(Budget::new(&options).check_time().is_ok());
+    }
+
+    #[test]
+    fn test_node_limit() {
+        let options = RenderOptions {
+            max_nodes: Some(10),
+            ..Default::default()
+        };
+        assert!(check_counts(10, 0, &options).is_ok());
+        assert_eq!(
+            check_counts(11, 0, &options),
+            Err(MermaidError::TooLarge {
+                resource: "nodes".to_string(),
+                limit: 10,
+                actual: 11,
+            })
+        );
+    }
+
+    #[test]
+    fn test_edge_limit() {
+        let options = RenderOptions {
+            max_edges: Some(3),
+            ..Default::default()
+        };
+        assert!(matches!(
+            check_counts(1, 4, &options),
+            Err(MermaidError::TooLarge { ref resource, .. }) if resource == "edges"
+        ));
+    }
+
+    #[test]
+    fn test_grid_limit() {
+        assert!(check_grid(2000, 2000).is_ok());
+        assert!(matches!(
+            check_grid(3_000_000_000, 3),
+            Err(MermaidError::TooLarge { ref resource, .. }) if resource == "grid cells"
+        ));
+    }
+
+    #[test]
+    fn test_time_budget_exhausted() {
+        let options = RenderOptions {
+            time_budget: Some(Duration::ZERO),
+            ..Default::default()
+        };
+        let budget = Budget::new(&options);
+        std::thread::sleep(Duration::from_millis(2));
+        assert!(matches!(
+            budget.check_time(),
+            Err(MermaidError::TooLarge { ref resource, .. }) if resource == "milliseconds"
+        ));
+    }
+}
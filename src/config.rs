@@ -0,0 +1,285 @@
+//! User defaults from a `graphs-tui.toml` file, so programs embedding the
+//! renderer don't make users repeat the same flags
+//!
+//! The crate has no command-line tool of its own: it only reads the file
+//! ([`Config::load`], [`Config::from_file`] or [`Config::parse`]), and a
+//! program applies its own flags on top through [`Config::builder`].
+//!
+//! The file is a flat list of TOML `key = value` settings:
+//!
+//! ```toml
+//! # ~/.config/graphs-tui.toml
+//! theme = "heavy"          # "unicode", "ascii" or "heavy" (not with ascii = true)
+//! colors = true
+//! width_strategy = "wrap"  # "truncate", "wrap", "scale" or "abbreviate"
+//! max_width = 100
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use crate::error::MermaidError;
+use crate::renderer::CharSet;
+use crate::types::{RenderOptions, RenderOptionsBuilder, WidthStrategy};
+
+/// Name of the file under the user's config directory
+const FILE_NAME: &str = "graphs-tui.toml";
+
+/// Settings the file may hold, for error suggestions
+const KEYS: &[&str] = &["ascii", "colors", "max_width", "theme", "width_strategy"];
+
+/// Render defaults read from a config file; unset fields keep the
+/// [`RenderOptions`] defaults
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Config {
+    /// Draw with ASCII characters (`ascii`, or `theme = "ascii"`)
+    pub ascii: Option<bool>,
+    /// Graph glyphs (`theme = "heavy"`)
+    pub charset: Option<CharSet>,
+    /// Keep ANSI colors (`colors`)
+    pub colors: Option<bool>,
+    /// How to fit wide output (`width_strategy`)
+    pub width_strategy: Option<WidthStrategy>,
+    /// Columns to fit the drawing within (`max_width`)
+    pub max_width: Option<usize>,
+}
+
+impl Config {
+    /// `$XDG_CONFIG_HOME/graphs-tui.toml`, or `~/.config/graphs-tui.toml`
+    pub fn default_path() -> Option<PathBuf> {
+        let dir = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+        Some(dir.join(FILE_NAME))
+    }
+
+    /// The config at [`default_path`](Self::default_path); no file means
+    /// no settings
+    pub fn load() -> Result<Config, MermaidError> {
+        match Self::default_path() {
+            Some(path) if path.exists() => Self::from_file(path),
+            _ => Ok(Config::default()),
+        }
+    }
+
+    /// Read settings from a file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Config, MermaidError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| MermaidError::Io(format!("{}: {}", path.display(), e)))?;
+        Self::parse(&text)
+    }
+
+    /// Read settings from the text of a config file.
+    ///
+    /// # Example
+    /// ```
+    /// use graphs_tui::Config;
+    ///
+    /// let config = Config::parse("theme = \"ascii\"\nmax_width = 80").unwrap();
+    /// let options = config.builder().colors(true).build().unwrap();
+    /// assert!(options.ascii && options.colors);
+    /// assert_eq!(options.max_width, Some(80));
+    /// ```
+    pub fn parse(text: &str) -> Result<Config, MermaidError> {
+        let mut config = Config::default();
+        for (i, raw) in text.lines().enumerate() {
+            let line = i + 1;
+            let error = |message: String, suggestion: Option<&str>| MermaidError::ConfigError {
+                line,
+                message,
+                suggestion: suggestion.map(String::from),
+            };
+            let trimmed = raw.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            if trimmed.starts_with('[') {
+                return Err(error(
+                    format!("Tables are not supported: {}", trimmed),
+                    Some("Put settings at the top of the file"),
+                ));
+            }
+            let Some((key, value)) = trimmed.split_once('=') else {
+                return Err(error(format!("Expected key = value: {}", trimmed), None));
+            };
+            let key = key.trim();
+            let value = parse_value(value).ok_or_else(|| {
+                error(
+                    format!("Invalid value for {}", key),
+                    Some("Use true/false, a number or a quoted string"),
+                )
+            })?;
+            let expected = |what: &str| error(format!("{} expects {}", key, what), None);
+            match (key, value) {
+                ("ascii", Value::Bool(b)) => config.ascii = Some(b),
+                ("colors", Value::Bool(b)) => config.colors = Some(b),
+                ("max_width", Value::Integer(n)) => config.max_width = Some(n),
+                ("theme", Value::String(s)) => match s.as_str() {
+                    "unicode" => {
+                        config.ascii = Some(false);
+                        config.charset = None;
+                    }
+                    "ascii" => {
+                        config.ascii = Some(true);
+                        config.charset = None;
+                    }
+                    "heavy" => config.charset = Some(CharSet::heavy()),
+                    _ => return Err(expected("\"unicode\", \"ascii\" or \"heavy\"")),
+                },
+                ("width_strategy", Value::String(s)) => {
+                    config.width_strategy = Some(match s.as_str() {
+                        "truncate" => WidthStrategy::Truncate,
+                        "wrap" => WidthStrategy::Wrap,
+                        "scale" => WidthStrategy::Scale,
+                        "abbreviate" => WidthStrategy::Abbreviate,
+                        _ => {
+                            return Err(expected(
+                                "\"truncate\", \"wrap\", \"scale\" or \"abbreviate\"",
+                            ))
+                        }
+                    })
+                }
+                ("ascii" | "colors", _) => return Err(expected("true or false")),
+                ("max_width", _) => return Err(expected("a number")),
+                ("theme" | "width_strategy", _) => return Err(expected("a quoted string")),
+                _ => {
+                    return Err(error(
+                        format!("Unknown setting '{}'", key),
+                        Some(&format!("Known settings: {}", KEYS.join(", "))),
+                    ))
+                }
+            }
+            // Heavy glyphs aren't ASCII, so the two can't both hold
+            if config.ascii == Some(true) && config.charset.is_some() {
+                return Err(error(
+                    "ascii = true conflicts with theme = \"heavy\"".to_string(),
+                    Some("Use theme = \"ascii\" for ASCII output"),
+                ));
+            }
+        }
+        Ok(config)
+    }
+
+    /// Options builder starting from these settings, for flags to
+    /// override before [`build`](RenderOptionsBuilder::build)
+    pub fn builder(&self) -> RenderOptionsBuilder {
+        let mut builder = RenderOptions::builder();
+        if let Some(ascii) = self.ascii {
+            builder = builder.ascii(ascii);
+        }
+        if let Some(charset) = self.charset {
+            builder = builder.charset(charset);
+        }
+        if let Some(colors) = self.colors {
+            builder = builder.colors(colors);
+        }
+        if let Some(strategy) = self.width_strategy {
+            builder = builder.width_strategy(strategy);
+        }
+        if let Some(width) = self.max_width {
+            builder = builder.max_width(width);
+        }
+        builder
+    }
+}
+
+/// A TOML value of a kind the config uses
+#[derive(Debug, PartialEq)]
+enum Value {
+    Bool(bool),
+    Integer(usize),
+    String(String),
+}
+
+/// The value after `=`, with any trailing `# comment` removed
+fn parse_value(text: &str) -> Option<Value> {
+    let text = text.trim();
+    if let Some(rest) = text.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = rest.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => return only_comment(chars.as_str()).then_some(Value::String(value)),
+                '\\' => value.push(match chars.next()? {
+                    'n' => '\n',
+                    't' => '\t',
+                    c @ ('"' | '\\') => c,
+                    _ => return None,
+                }),
+                c => value.push(c),
+            }
+        }
+        return None;
+    }
+    if let Some(rest) = text.strip_prefix('\'') {
+        let (value, after) = rest.split_once('\'')?;
+        return only_comment(after).then(|| Value::String(value.to_string()));
+    }
+    let bare = text.split('#').next().unwrap_or_default().trim();
+    match bare {
+        "true" => Some(Value::Bool(true)),
+        "false" => Some(Value::Bool(false)),
+        _ => bare.replace('_', "").parse().ok().map(Value::Integer),
+    }
+}
+
+/// Nothing but whitespace and an optional comment
+fn only_comment(text: &str) -> bool {
+    let text = text.trim_start();
+    text.is_empty() || text.starts_with('#')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_settings() {
+        let config = Config::parse(
+            "# defaults\ntheme = 'heavy'\ncolors = true  # in color\nwidth_strategy = \"wrap\"\nmax_width = 1_00\n",
+        )
+        .unwrap();
+        assert_eq!(
+            config,
+            Config {
+                ascii: None,
+                charset: Some(CharSet::heavy()),
+                colors: Some(true),
+                width_strategy: Some(WidthStrategy::Wrap),
+                max_width: Some(100),
+            }
+        );
+        let options = config.builder().build().unwrap();
+        assert_eq!(options.charset, Some(CharSet::heavy()));
+        assert_eq!(options.width_strategy, WidthStrategy::Wrap);
+        assert_eq!(Config::parse("").unwrap(), Config::default());
+    }
+
+    #[test]
+    fn test_parse_errors_name_the_line() {
+        let line = |text: &str| match Config::parse(text) {
+            Err(MermaidError::ConfigError { line, .. }) => line,
+            other => panic!("expected a config error, got {:?}", other),
+        };
+        assert_eq!(line("ascii = true\ncolour = true"), 2);
+        assert_eq!(line("[render]\nascii = true"), 1);
+        assert_eq!(line("ascii = \"yes\""), 1);
+        assert_eq!(line("theme = \"neon\""), 1);
+        assert_eq!(line("max_width = \"80"), 1);
+        assert_eq!(line("\nascii"), 2);
+        assert_eq!(line("ascii = true\ntheme = \"heavy\""), 2);
+        assert_eq!(line("theme = \"heavy\"\n\nascii = true"), 3);
+        assert!(Config::parse("theme = \"heavy\"\nascii = false").is_ok());
+    }
+
+    #[test]
+    fn test_from_file() {
+        let path = std::env::temp_dir().join(format!("graphs-tui-{}.toml", std::process::id()));
+        std::fs::write(&path, "ascii = true\n").unwrap();
+        let config = Config::from_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(config.unwrap().ascii, Some(true));
+        assert!(matches!(Config::from_file(&path), Err(MermaidError::Io(_))));
+    }
+}
@@ -0,0 +1,303 @@
+//! D2 syntax tree and recursive-descent parser
+//!
+//! A document is a map of statements. Each statement is a field
+//! (`a.b: value {map}`) or a connection chain (`a -> b: label {map}`);
+//! nested maps hold the same statements.
+
+use std::ops::Range;
+
+use super::lexer::{tokenize, ArrowKind, Token, TokenKind};
+
+/// One segment of a key path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyPart {
+    pub text: String,
+    /// Written in quotes, so never a keyword or glob
+    pub quoted: bool,
+    /// Byte range in the source, quotes included
+    pub span: Range<usize>,
+}
+
+/// A dotted key path such as `a.b.c`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyPath {
+    pub parts: Vec<KeyPart>,
+}
+
+impl KeyPath {
+    /// Whether any unquoted segment is a `*` glob pattern
+    pub fn is_glob(&self) -> bool {
+        self.parts.iter().any(|p| !p.quoted && p.text.contains('*'))
+    }
+}
+
+/// The value after a `:`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Text(String),
+    Quoted(String),
+    /// Block string, drawn verbatim
+    Block(String),
+}
+
+impl Value {
+    pub fn text(&self) -> &str {
+        match self {
+            Value::Text(t) | Value::Quoted(t) | Value::Block(t) => t,
+        }
+    }
+
+    /// The unquoted keyword `null`
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Text(t) if t == "null")
+    }
+}
+
+/// `key: value {map}`, any part optional but the key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub key: KeyPath,
+    pub value: Option<Value>,
+    pub map: Option<Map>,
+    pub line: usize,
+}
+
+/// `a -> b: label -> c: label {map}`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Connection {
+    /// Endpoints in source order
+    pub ends: Vec<KeyPath>,
+    /// Operator between `ends[i]` and `ends[i + 1]`
+    pub arrows: Vec<ArrowKind>,
+    /// Label of each hop, written after its target
+    pub labels: Vec<Option<Value>>,
+    /// Properties applied to every hop
+    pub map: Option<Map>,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Statement {
+    Field(Field),
+    Connection(Connection),
+    /// `...@file` spread import
+    Spread {
+        line: usize,
+    },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Map {
+    pub statements: Vec<Statement>,
+}
+
+/// Parse D2 source into its top-level map.
+///
+/// Parsing never fails: stray tokens are skipped up to the next statement,
+/// a `}` without a matching `{` is ignored and unclosed maps end at the end
+/// of input.
+pub fn parse(input: &str) -> Map {
+    let mut parser = Parser {
+        tokens: tokenize(input),
+        pos: 0,
+    };
+    parser.map(false)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&TokenKind> {
+        self.tokens.get(self.pos).map(|t| &t.kind)
+    }
+
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .or(self.tokens.last())
+            .map_or(1, |t| t.line)
+    }
+
+    fn eat(&mut self, kind: &TokenKind) -> bool {
+        if self.peek() == Some(kind) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Statements up to the closing `}` (nested) or end of input
+    fn map(&mut self, nested: bool) -> Map {
+        let mut statements = Vec::new();
+        while let Some(kind) = self.peek() {
+            match kind {
+                TokenKind::RBrace => {
+                    self.pos += 1;
+                    if nested {
+                        break;
+                    }
+                }
+                TokenKind::Newline | TokenKind::Semicolon => self.pos += 1,
+                _ => {
+                    statements.extend(self.statement());
+                    self.skip_to_separator();
+                }
+            }
+        }
+        Map { statements }
+    }
+
+    fn skip_to_separator(&mut self) {
+        while let Some(kind) = self.peek() {
+            if matches!(
+                kind,
+                TokenKind::Newline | TokenKind::Semicolon | TokenKind::RBrace
+            ) {
+                break;
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn statement(&mut self) -> Option<Statement> {
+        let line = self.line();
+        if self.peek() == Some(&TokenKind::Dot) {
+            return Some(Statement::Spread { line });
+        }
+
+        let key = self.key_path()?;
+        let value = self.value();
+        let mut ends = vec![key];
+        let mut arrows = Vec::new();
+        let mut labels = Vec::new();
+        while let Some(&TokenKind::Arrow(arrow)) = self.peek() {
+            self.pos += 1;
+            let Some(end) = self.key_path() else {
+                break;
+            };
+            ends.push(end);
+            arrows.push(arrow);
+            labels.push(self.value());
+        }
+        let map = self.eat(&TokenKind::LBrace).then(|| self.map(true));
+
+        Some(if arrows.is_empty() {
+            let key = ends.pop()?;
+            Statement::Field(Field {
+                key,
+                value,
+                map,
+                line,
+            })
+        } else {
+            Statement::Connection(Connection {
+                ends,
+                arrows,
+                labels,
+                map,
+                line,
+            })
+        })
+    }
+
+    /// `a.b."c d"`
+    fn key_path(&mut self) -> Option<KeyPath> {
+        let mut parts = Vec::new();
+        while let Some(token) = self.tokens.get(self.pos) {
+            let (text, quoted) = match &token.kind {
+                TokenKind::Text(t) => (t.clone(), false),
+                TokenKind::Quoted(t) => (t.clone(), true),
+                _ => break,
+            };
+            parts.push(KeyPart {
+                text,
+                quoted,
+                span: token.span.clone(),
+            });
+            self.pos += 1;
+            if !self.eat(&TokenKind::Dot) {
+                break;
+            }
+        }
+        (!parts.is_empty()).then_some(KeyPath { parts })
+    }
+
+    /// `: value`, if present
+    fn value(&mut self) -> Option<Value> {
+        if !self.eat(&TokenKind::Colon) {
+            return None;
+        }
+        let value = match self.peek()? {
+            TokenKind::Text(t) => Value::Text(t.clone()),
+            TokenKind::Quoted(t) => Value::Quoted(t.clone()),
+            TokenKind::Block(t) => Value::Block(t.clone()),
+            _ => return None,
+        };
+        self.pos += 1;
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(key: &KeyPath) -> Vec<&str> {
+        key.parts.iter().map(|p| p.text.as_str()).collect()
+    }
+
+    #[test]
+    fn test_parse_field_with_nested_map() {
+        let map = parse("a.b: Label {\n  shape: circle\n}");
+        let [Statement::Field(field)] = map.statements.as_slice() else {
+            panic!("expected one field: {map:?}");
+        };
+        assert_eq!(texts(&field.key), ["a", "b"]);
+        assert_eq!(field.key.parts[1].span, 2..3);
+        assert_eq!(field.value, Some(Value::Text("Label".to_string())));
+        let inner = field.map.as_ref().unwrap();
+        let [Statement::Field(shape)] = inner.statements.as_slice() else {
+            panic!("expected one nested field: {inner:?}");
+        };
+        assert_eq!(texts(&shape.key), ["shape"]);
+        assert_eq!(shape.value, Some(Value::Text("circle".to_string())));
+        assert_eq!(shape.line, 2);
+    }
+
+    #[test]
+    fn test_parse_connection_chain() {
+        let map = parse("x -> y: first <- z {style.opacity: 0.5}");
+        let [Statement::Connection(conn)] = map.statements.as_slice() else {
+            panic!("expected one connection: {map:?}");
+        };
+        let ends: Vec<Vec<&str>> = conn.ends.iter().map(texts).collect();
+        assert_eq!(ends, [["x"], ["y"], ["z"]]);
+        assert_eq!(conn.arrows, vec![ArrowKind::Forward, ArrowKind::Backward]);
+        assert_eq!(
+            conn.labels,
+            vec![Some(Value::Text("first".to_string())), None]
+        );
+        assert_eq!(conn.map.as_ref().unwrap().statements.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_recovers_from_stray_braces() {
+        let map = parse("}\na {\n  b\n");
+        assert_eq!(map.statements.len(), 1);
+        let Statement::Field(field) = &map.statements[0] else {
+            panic!("expected a field");
+        };
+        assert_eq!(field.map.as_ref().unwrap().statements.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_spread_import() {
+        let map = parse("...@base\na");
+        assert_eq!(map.statements[0], Statement::Spread { line: 1 });
+        assert_eq!(map.statements.len(), 2);
+    }
+}
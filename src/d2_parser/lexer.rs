@@ -0,0 +1,530 @@
+//! D2 tokenizer
+//!
+//! Splits source text into key segments, values and punctuation. Lexing is
+//! context sensitive in one place: the token after a `:` is read as a
+//! value, which may hold spaces, dots and colons, or be a `|` block string.
+
+use std::ops::Range;
+
+use crate::text::unescape;
+
+/// Connection operator between two keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArrowKind {
+    /// `->`
+    Forward,
+    /// `<-`
+    Backward,
+    /// `<->`
+    Both,
+    /// `--`
+    Line,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenKind {
+    /// Unquoted key segment or value, trimmed
+    Text(String),
+    /// Quoted string, quotes removed
+    Quoted(String),
+    /// Block string body (`|` ... `|`), dedented
+    Block(String),
+    Arrow(ArrowKind),
+    Colon,
+    Dot,
+    Semicolon,
+    Newline,
+    LBrace,
+    RBrace,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    /// 1-based line the token starts on
+    pub line: usize,
+    /// Byte range in the source, surrounding blanks excluded
+    pub span: Range<usize>,
+}
+
+/// Tokenize D2 source
+pub fn tokenize(input: &str) -> Vec<Token> {
+    let mut lexer = Lexer {
+        input,
+        chars: input.chars().collect(),
+        offsets: input
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain([input.len()])
+            .collect(),
+        pos: 0,
+        line: 1,
+        token_start: 0,
+        tokens: Vec::new(),
+    };
+    lexer.run();
+    lexer.tokens
+}
+
+struct Lexer<'a> {
+    input: &'a str,
+    chars: Vec<char>,
+    /// Byte offset of each char, plus the end of input
+    offsets: Vec<usize>,
+    pos: usize,
+    line: usize,
+    /// Char index where the token being read starts
+    token_start: usize,
+    tokens: Vec<Token>,
+}
+
+impl Lexer<'_> {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, n: usize) -> Option<char> {
+        self.chars.get(self.pos + n).copied()
+    }
+
+    fn push(&mut self, kind: TokenKind, line: usize) {
+        let start = self.offsets[self.token_start];
+        let mut end = self.offsets[self.pos];
+        if matches!(kind, TokenKind::Text(_)) {
+            end = start + self.input[start..end].trim_end().len();
+        }
+        self.tokens.push(Token {
+            kind,
+            line,
+            span: start..end,
+        });
+    }
+
+    fn run(&mut self) {
+        let mut value_next = false;
+        loop {
+            self.skip_blanks();
+            let Some(c) = self.peek() else {
+                break;
+            };
+            let line = self.line;
+            self.token_start = self.pos;
+
+            if std::mem::take(&mut value_next)
+                && !matches!(c, '\n' | ';' | '{' | '}')
+                && self.arrow_at().is_none()
+                && !self.comment_at()
+                && !self.block_comment_at()
+            {
+                self.lex_value();
+                continue;
+            }
+
+            match c {
+                '\n' => {
+                    self.pos += 1;
+                    self.push(TokenKind::Newline, line);
+                    self.line += 1;
+                }
+                '#' if self.at_statement_start() || self.comment_at() => {
+                    while self.peek().is_some_and(|c| c != '\n') {
+                        self.pos += 1;
+                    }
+                }
+                '"' if self.block_comment_at() => self.skip_block_comment(),
+                ';' | '{' | '}' | '.' | ':' => {
+                    self.pos += 1;
+                    let kind = match c {
+                        ';' => TokenKind::Semicolon,
+                        '{' => TokenKind::LBrace,
+                        '}' => TokenKind::RBrace,
+                        '.' => TokenKind::Dot,
+                        _ => {
+                            value_next = true;
+                            TokenKind::Colon
+                        }
+                    };
+                    self.push(kind, line);
+                }
+                '"' | '\'' => {
+                    let text = self.lex_quoted(c);
+                    self.push(TokenKind::Quoted(text), line);
+                }
+                _ => {
+                    if let Some((kind, len)) = self.arrow_at() {
+                        self.pos += len;
+                        self.push(TokenKind::Arrow(kind), line);
+                    } else {
+                        self.lex_key_text();
+                    }
+                }
+            }
+        }
+    }
+
+    /// Skip spaces, tabs and `\` line continuations
+    fn skip_blanks(&mut self) {
+        loop {
+            match self.peek() {
+                Some(' ' | '\t' | '\r') => self.pos += 1,
+                Some('\\') if self.skip_continuation() => {}
+                _ => break,
+            }
+        }
+    }
+
+    /// Skip a `\` at the end of a line along with the next line's indent
+    fn skip_continuation(&mut self) -> bool {
+        let newline = if self.peek_at(1) == Some('\r') { 2 } else { 1 };
+        if self.peek() != Some('\\') || self.peek_at(newline) != Some('\n') {
+            return false;
+        }
+        self.pos += newline + 1;
+        self.line += 1;
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.pos += 1;
+        }
+        true
+    }
+
+    fn at_statement_start(&self) -> bool {
+        matches!(
+            self.tokens.last().map(|t| &t.kind),
+            None | Some(
+                TokenKind::Newline | TokenKind::Semicolon | TokenKind::LBrace | TokenKind::RBrace
+            )
+        )
+    }
+
+    /// `#` after whitespace starts a trailing comment
+    fn comment_at(&self) -> bool {
+        self.peek() == Some('#')
+            && self
+                .pos
+                .checked_sub(1)
+                .is_some_and(|i| self.chars[i].is_whitespace())
+    }
+
+    fn block_comment_at(&self) -> bool {
+        self.chars[self.pos..].starts_with(&['"', '"', '"'])
+    }
+
+    /// Skip a `"""` block comment. One spanning lines still ends the
+    /// statement before it.
+    fn skip_block_comment(&mut self) {
+        let line = self.line;
+        self.pos += 3;
+        while self.pos < self.chars.len() && !self.block_comment_at() {
+            if self.chars[self.pos] == '\n' {
+                self.line += 1;
+            }
+            self.pos += 1;
+        }
+        self.pos = (self.pos + 3).min(self.chars.len());
+        if self.line > line {
+            self.push(TokenKind::Newline, line);
+        }
+    }
+
+    /// Connection operator starting at the cursor, with its length
+    fn arrow_at(&self) -> Option<(ArrowKind, usize)> {
+        match (self.peek()?, self.peek_at(1), self.peek_at(2)) {
+            ('<', Some('-'), Some('>')) => Some((ArrowKind::Both, 3)),
+            ('<', Some('-'), _) => Some((ArrowKind::Backward, 2)),
+            ('-', Some('>'), _) => Some((ArrowKind::Forward, 2)),
+            ('-', Some('-'), Some('>')) => Some((ArrowKind::Forward, 3)),
+            ('-', Some('-'), _) => Some((ArrowKind::Line, 2)),
+            _ => None,
+        }
+    }
+
+    /// Read a quoted string, translating its escapes; the cursor is on the
+    /// opening quote
+    fn lex_quoted(&mut self, quote: char) -> String {
+        self.pos += 1;
+        let mut text = String::new();
+        while let Some(c) = self.peek() {
+            if c == quote {
+                self.pos += 1;
+                break;
+            }
+            if c == '\\' && self.skip_continuation() {
+                continue;
+            }
+            if c == '\n' {
+                self.line += 1;
+            }
+            text.push(c);
+            self.pos += 1;
+            // An escaped quote doesn't close the string
+            if c == '\\' {
+                if let Some(next) = self.peek().filter(|&n| n == quote || n == '\\') {
+                    text.push(next);
+                    self.pos += 1;
+                }
+            }
+        }
+        unescape(&text)
+    }
+
+    /// Read an unquoted key segment, stopping at any key punctuation
+    fn lex_key_text(&mut self) {
+        let line = self.line;
+        let text = self.take_text(|lexer, c| {
+            matches!(c, '\n' | ';' | '{' | '}' | '.' | ':') || lexer.arrow_at().is_some()
+        });
+        if !text.is_empty() {
+            self.push(TokenKind::Text(text), line);
+        }
+    }
+
+    /// Read the value after a `:`
+    fn lex_value(&mut self) {
+        let line = self.line;
+        match self.peek() {
+            Some(q @ ('"' | '\'')) => {
+                let text = self.lex_quoted(q);
+                self.push(TokenKind::Quoted(text), line);
+            }
+            Some('|') => {
+                let text = self.lex_block_string();
+                self.push(TokenKind::Block(text), line);
+            }
+            _ => {
+                let text = self.take_text(|lexer, c| {
+                    matches!(c, '\n' | ';' | '{' | '}') || lexer.arrow_at().is_some()
+                });
+                if !text.is_empty() {
+                    self.push(TokenKind::Text(text), line);
+                }
+            }
+        }
+    }
+
+    /// Collect characters up to `stop` or a comment, joining continued lines,
+    /// trimmed
+    fn take_text(&mut self, stop: impl Fn(&Self, char) -> bool) -> String {
+        let mut text = String::new();
+        while let Some(c) = self.peek() {
+            if c == '\\' && self.skip_continuation() {
+                continue;
+            }
+            if stop(self, c) || self.comment_at() || self.block_comment_at() {
+                break;
+            }
+            text.push(c);
+            self.pos += 1;
+        }
+        text.trim().to_string()
+    }
+
+    /// Read `|lang ... |` (any run of pipes); the cursor is on the first pipe.
+    ///
+    /// Text on the opening line closes at the next matching run of pipes;
+    /// otherwise the body runs to a line holding only the closing pipes.
+    fn lex_block_string(&mut self) -> String {
+        let start = self.pos;
+        while self.peek() == Some('|') {
+            self.pos += 1;
+        }
+        let pipes: String = self.chars[start..self.pos].iter().collect();
+        while self.peek().is_some_and(|c| c.is_ascii_alphanumeric()) {
+            self.pos += 1;
+        }
+
+        let rest = self.rest_of_line();
+        if !rest.trim().is_empty() {
+            return match rest.find(&pipes) {
+                Some(end) => {
+                    self.pos += rest[..end].chars().count() + pipes.chars().count();
+                    rest[..end].trim().to_string()
+                }
+                None => {
+                    self.pos += rest.chars().count();
+                    rest.trim().to_string()
+                }
+            };
+        }
+
+        let mut body = Vec::new();
+        self.pos += rest.chars().count();
+        while self.peek() == Some('\n') {
+            self.pos += 1;
+            self.line += 1;
+            let line = self.rest_of_line();
+            self.pos += line.chars().count();
+            if line.trim() == pipes {
+                break;
+            }
+            body.push(line);
+        }
+        let body: Vec<&str> = body.iter().map(String::as_str).collect();
+        dedent_block(&body)
+    }
+
+    fn rest_of_line(&self) -> String {
+        self.chars[self.pos..]
+            .iter()
+            .take_while(|&&c| c != '\n')
+            .collect()
+    }
+}
+
+/// Join block string lines, dropping their common indentation and any
+/// blank lines at either end. Tabs become four spaces.
+pub fn dedent_block(lines: &[&str]) -> String {
+    let lines: Vec<String> = lines.iter().map(|l| l.replace('\t', "    ")).collect();
+    let indent = lines
+        .iter()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| l.len() - l.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let body: Vec<&str> = lines
+        .iter()
+        .map(|l| l.get(indent..).unwrap_or("").trim_end())
+        .collect();
+    let start = body
+        .iter()
+        .position(|l| !l.is_empty())
+        .unwrap_or(body.len());
+    let end = body
+        .iter()
+        .rposition(|l| !l.is_empty())
+        .map_or(start, |i| i + 1);
+    body[start..end].join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(input: &str) -> Vec<TokenKind> {
+        tokenize(input).into_iter().map(|t| t.kind).collect()
+    }
+
+    fn text(s: &str) -> TokenKind {
+        TokenKind::Text(s.to_string())
+    }
+
+    #[test]
+    fn test_tokenize_key_path_and_value() {
+        assert_eq!(
+            kinds("a.b: Web v1.2: beta"),
+            vec![
+                text("a"),
+                TokenKind::Dot,
+                text("b"),
+                TokenKind::Colon,
+                text("Web v1.2: beta")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_arrows() {
+        assert_eq!(
+            kinds("a -> b <- c <-> d -- e"),
+            vec![
+                text("a"),
+                TokenKind::Arrow(ArrowKind::Forward),
+                text("b"),
+                TokenKind::Arrow(ArrowKind::Backward),
+                text("c"),
+                TokenKind::Arrow(ArrowKind::Both),
+                text("d"),
+                TokenKind::Arrow(ArrowKind::Line),
+                text("e"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_quoted_braces_stay_in_string() {
+        assert_eq!(
+            kinds(r#"a: "x{y}; z" {"#),
+            vec![
+                text("a"),
+                TokenKind::Colon,
+                TokenKind::Quoted("x{y}; z".to_string()),
+                TokenKind::LBrace,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_quoted_escapes() {
+        assert_eq!(
+            kinds(r#"a: "say \"hi\"\nthen \\" -> 'it\'s'"#),
+            vec![
+                text("a"),
+                TokenKind::Colon,
+                TokenKind::Quoted("say \"hi\"\nthen \\".to_string()),
+                TokenKind::Arrow(ArrowKind::Forward),
+                TokenKind::Quoted("it's".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_comment_lines_and_line_numbers() {
+        let tokens = tokenize("# note\na\n  # indented\nb");
+        let lines: Vec<(TokenKind, usize)> = tokens.into_iter().map(|t| (t.kind, t.line)).collect();
+        assert_eq!(
+            lines,
+            vec![
+                (TokenKind::Newline, 1),
+                (text("a"), 2),
+                (TokenKind::Newline, 2),
+                (TokenKind::Newline, 3),
+                (text("b"), 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_trailing_and_block_comments() {
+        let tokens = tokenize("a: x # note\nb#c \"\"\" one\ntwo \"\"\" d");
+        let lines: Vec<(TokenKind, usize)> = tokens.into_iter().map(|t| (t.kind, t.line)).collect();
+        assert_eq!(
+            lines,
+            vec![
+                (text("a"), 1),
+                (TokenKind::Colon, 1),
+                (text("x"), 1),
+                (TokenKind::Newline, 1),
+                (text("b#c"), 2),
+                (TokenKind::Newline, 2),
+                (text("d"), 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_block_string() {
+        assert_eq!(
+            kinds("a: |md\n  # Title\n    body\n|\nb"),
+            vec![
+                text("a"),
+                TokenKind::Colon,
+                TokenKind::Block("# Title\n  body".to_string()),
+                TokenKind::Newline,
+                text("b"),
+            ]
+        );
+        assert_eq!(
+            kinds("a: || x | y ||"),
+            vec![
+                text("a"),
+                TokenKind::Colon,
+                TokenKind::Block("x | y".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dedent_block_keeps_relative_indent() {
+        assert_eq!(dedent_block(&["", "    a", "      b", "  "]), "a\n  b");
+        assert_eq!(dedent_block(&["\tx"]), "x");
+    }
+}
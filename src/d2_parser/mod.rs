@@ -0,0 +1,1636 @@
+//! D2 diagram language parser
+//!
+//! D2 syntax:
+//! - Shapes: `id` or `id: "Label"`
+//! - Connections: `->`, `<-`, `<->`, `--`
+//! - Connection chains: `A -> B -> C`
+//! - Shape types: `id.shape: circle`
+//! - Containers: `parent { child }` (multi-level nesting)
+//! - Nested keys: `a.b.c: "Label"`
+//! - Edge labels: `A -> B: "label"`
+//! - SQL tables/classes with fields
+//! - Quoted keys: `"my node" -> "other node"`
+//! - Comments: `# line`, trailing `a -> b # note`, and `"""` block `"""`
+//! - Semicolons: `A -> B; C -> D`
+//! - Null deletion: `x: null`
+//! - Position pins (extension): `id.x: 10`, `id.y: 4`
+//! - Block strings: `id: |` ... `|` (any number of pipes, optional language
+//!   tag) hold pre-formatted text drawn verbatim
+//!
+//! Source is split into tokens ([`lexer`]), parsed into a tree of key paths
+//! and maps ([`ast`]), and that tree is walked to build the [`Graph`].
+
+mod ast;
+mod lexer;
+
+use std::collections::hash_map::Entry;
+use std::collections::HashSet;
+
+use crate::error::MermaidError;
+use crate::label::strip_html;
+use crate::renderer::custom_shape;
+use crate::symbols::{line_at, Symbol, SymbolKind};
+use crate::types::{
+    Arrowhead, DiagramWarning, Direction, Edge, EdgeStyle, Graph, Node, NodeShape, Subgraph,
+    TableField,
+};
+
+use ast::{Connection, Field, KeyPart, KeyPath, Map, Statement, Value};
+use lexer::ArrowKind;
+
+/// Result of parsing D2: a graph plus any warnings
+pub struct D2ParseResult {
+    pub graph: Graph,
+    pub warnings: Vec<DiagramWarning>,
+}
+
+/// Parse D2 diagram syntax into a Graph
+pub fn parse_d2(input: &str) -> Result<D2ParseResult, MermaidError> {
+    if input.trim().is_empty() {
+        return Err(MermaidError::EmptyInput);
+    }
+    build(&[&ast::parse(input)])
+}
+
+/// Parse D2 into one graph per frame of playback: the base diagram, then
+/// each `steps` board on top of the steps before it and each `scenarios`
+/// board on top of the base alone. A base with no content of its own is
+/// left out when there are boards.
+pub(crate) fn parse_d2_frames(input: &str) -> Result<Vec<D2ParseResult>, MermaidError> {
+    if input.trim().is_empty() {
+        return Err(MermaidError::EmptyInput);
+    }
+    let root = ast::parse(input);
+    let mut base = Map::default();
+    let mut boards: Vec<(bool, &Map)> = Vec::new();
+    for statement in &root.statements {
+        if let Statement::Field(Field {
+            key,
+            map: Some(map),
+            ..
+        }) = statement
+        {
+            let part = &key.parts[0];
+            if key.parts.len() == 1 && !part.quoted {
+                match part.text.as_str() {
+                    "steps" => boards.push((true, map)),
+                    "scenarios" => boards.push((false, map)),
+                    _ => base.statements.push(statement.clone()),
+                }
+                continue;
+            }
+        }
+        base.statements.push(statement.clone());
+    }
+
+    let mut frames = Vec::new();
+    match build(&[&base]) {
+        Ok(frame) => frames.push(frame),
+        Err(err) if boards.is_empty() => return Err(err),
+        Err(_) => {}
+    }
+    for (cumulative, board) in boards {
+        let mut layers = vec![&base];
+        for statement in &board.statements {
+            let Statement::Field(Field { map: Some(map), .. }) = statement else {
+                continue;
+            };
+            if !cumulative {
+                layers.truncate(1);
+            }
+            layers.push(map);
+            frames.push(build(&layers)?);
+        }
+    }
+    Ok(frames)
+}
+
+/// Build a graph from maps applied one after another
+fn build(maps: &[&Map]) -> Result<D2ParseResult, MermaidError> {
+    let mut builder = Builder {
+        graph: Graph::new(Direction::TB),
+        warnings: Vec::new(),
+        container_stack: Vec::new(),
+        table_nodes: HashSet::new(),
+        null_nodes: Vec::new(),
+        tooltip_nodes: Vec::new(),
+    };
+    for map in maps {
+        builder.map(map);
+    }
+    let Builder {
+        mut graph,
+        warnings,
+        null_nodes,
+        tooltip_nodes,
+        ..
+    } = builder;
+
+    // Remove null-deleted nodes
+    for id in &null_nodes {
+        graph.nodes.remove(id);
+        graph.edges.retain(|e| e.from != *id && e.to != *id);
+    }
+
+    add_tooltip_markers(&mut graph, &tooltip_nodes);
+    resolve_containers(&mut graph);
+
+    if graph.nodes.is_empty() && graph.edges.is_empty() {
+        return Err(MermaidError::ParseError {
+            line: 1,
+            message: "No valid D2 content found".to_string(),
+            suggestion: Some(
+                "D2 syntax: 'A -> B' for connections, 'name: Label' for nodes".to_string(),
+            ),
+        });
+    }
+
+    Ok(D2ParseResult { graph, warnings })
+}
+
+/// Number each tooltip, marking the node's label with it and listing the
+/// text in `graph.tooltips`
+fn add_tooltip_markers(graph: &mut Graph, ids: &[String]) {
+    for id in ids {
+        let Some(text) = graph
+            .nodes
+            .get(id)
+            .and_then(|node| node.metadata.get("tooltip"))
+            .cloned()
+        else {
+            continue;
+        };
+        let marker = format!("*{}", graph.tooltips.len() + 1);
+        if let Some(sg) = graph.subgraphs.iter_mut().find(|sg| sg.id == *id) {
+            sg.label = format!("{} {}", sg.label, marker);
+        }
+        if let Some(node) = graph.nodes.get_mut(id) {
+            node.label = format!("{} {}", node.label, marker);
+        }
+        graph.tooltips.push((marker, text));
+    }
+}
+
+/// Reserved keywords that set a property rather than name a node
+const PROPERTY_KEYWORDS: &[&str] = &[
+    "shape",
+    "label",
+    "style",
+    "near",
+    "tooltip",
+    "link",
+    "icon",
+    "constraint",
+    "width",
+    "height",
+    "class",
+    "direction",
+];
+
+/// Walks the syntax tree, building the graph
+struct Builder {
+    graph: Graph,
+    warnings: Vec<DiagramWarning>,
+    /// Containers enclosing the statement being built, innermost last
+    container_stack: Vec<String>,
+    /// Nodes shaped `sql_table` or `class`, whose maps hold fields
+    table_nodes: HashSet<String>,
+    null_nodes: Vec<String>,
+    /// Nodes with a tooltip, in the order they got one
+    tooltip_nodes: Vec<String>,
+}
+
+impl Builder {
+    fn current(&self) -> Option<String> {
+        self.container_stack.last().cloned()
+    }
+
+    fn map(&mut self, map: &Map) {
+        for statement in &map.statements {
+            match statement {
+                Statement::Field(field) => self.field(field),
+                Statement::Connection(conn) => self.connection(conn),
+                Statement::Spread { line } => self.unsupported("import", *line),
+            }
+        }
+    }
+
+    fn unsupported(&mut self, feature: &str, line: usize) {
+        self.warnings.push(DiagramWarning::UnsupportedFeature {
+            feature: feature.to_string(),
+            line,
+        });
+    }
+
+    /// Warn about and skip D2 features this renderer doesn't support
+    fn check_unsupported(&mut self, key: &KeyPath, value: Option<&Value>, line: usize) -> bool {
+        let first = key.parts[0].text.to_lowercase();
+        let single = key.parts.len() == 1 && !key.parts[0].quoted;
+        let feature = if key.is_glob() {
+            "glob"
+        } else if first.starts_with("import ")
+            || matches!(value, Some(Value::Text(t)) if t.starts_with('@'))
+        {
+            "import"
+        } else if single && matches!(first.as_str(), "layers" | "scenarios" | "steps") {
+            first.as_str()
+        } else if single && matches!(first.as_str(), "grid-rows" | "grid-columns") {
+            "grid layout"
+        } else if single && first == "tooltip" && self.current().is_none() {
+            // Only a shape's tooltip has somewhere to be shown
+            "tooltip"
+        } else if single && matches!(first.as_str(), "link" | "icon") {
+            self.property(&[], std::slice::from_ref(&first), value);
+            first.as_str()
+        } else {
+            return false;
+        };
+        let feature = feature.to_string();
+        self.unsupported(&feature, line);
+        true
+    }
+
+    fn field(&mut self, field: &Field) {
+        let Field {
+            key,
+            value,
+            map,
+            line,
+        } = field;
+        if self.check_unsupported(key, value.as_ref(), *line) {
+            return;
+        }
+        let parts: Vec<String> = key.parts.iter().map(|p| p.text.clone()).collect();
+
+        if let Some(i) = keyword_index(key) {
+            self.property(&parts[..i], &parts[i..], value.as_ref());
+            return;
+        }
+
+        // Fields of a sql_table or class
+        if let Some(table) = self.current().filter(|id| self.table_nodes.contains(id)) {
+            if parts.len() == 1 {
+                let constraint = map
+                    .as_ref()
+                    .and_then(|m| flatten_map(m).into_iter().find(|(k, _)| k == "constraint"))
+                    .map(|(_, v)| v);
+                if let Some(node) = self.graph.nodes.get_mut(&table) {
+                    node.fields.push(TableField {
+                        name: parts[0].clone(),
+                        type_info: value.as_ref().map(|v| v.text().to_string()),
+                        constraint,
+                    });
+                }
+                return;
+            }
+        }
+
+        if value.as_ref().is_some_and(Value::is_null) {
+            let id = parts.last().cloned().unwrap_or_default();
+            self.null_nodes.push(id);
+            return;
+        }
+
+        let label = value.as_ref().map(label_text);
+        if let Some(map) = map {
+            let depth = self.container_stack.len();
+            self.open_container(&parts, label);
+            self.map(map);
+            self.container_stack.truncate(depth);
+            return;
+        }
+
+        let current = self.current();
+        if let Some(Value::Block(text)) = value {
+            let id = self.resolve(&parts);
+            ensure_node_exists(&mut self.graph, &id, current.as_deref());
+            if let Some(node) = self.graph.nodes.get_mut(&id) {
+                node.label = text.clone();
+                node.verbatim = true;
+            }
+            return;
+        }
+
+        // Dotted id as nested node
+        if parts.len() > 1 {
+            let id = self.resolve(&parts);
+            if let (Some(label), Some(node)) = (label, self.graph.nodes.get_mut(&id)) {
+                node.label = label;
+            }
+            return;
+        }
+
+        let id = parts[0].clone();
+        match self.graph.nodes.entry(id.clone()) {
+            Entry::Occupied(mut e) => {
+                if let Some(label) = label {
+                    e.get_mut().label = label;
+                }
+            }
+            Entry::Vacant(e) => {
+                let mut node = Node::new(id.clone(), label.unwrap_or_else(|| id.clone()));
+                node.subgraph = current.clone();
+                e.insert(node);
+                add_to_subgraph(&mut self.graph, current.as_deref(), &id);
+            }
+        }
+    }
+
+    /// Apply `target.property: value`; an empty target means the enclosing
+    /// container
+    fn property(&mut self, target: &[String], property: &[String], value: Option<&Value>) {
+        let current = self.current();
+        let Some(value) = value else {
+            return;
+        };
+        match property {
+            // Only the root map sets the layout direction
+            [p] if p == "direction" && target.is_empty() && current.is_none() => {
+                if let Some(direction) = parse_direction(value.text()) {
+                    self.graph.direction = direction;
+                }
+            }
+            [p] if p == "shape" => {
+                let name = value.text().to_lowercase();
+                let shape = parse_shape_str(&name);
+                // A registered shape only stands in for names D2 doesn't know
+                let custom =
+                    (known_shape(&name).is_none() && custom_shape(&name).is_some()).then_some(name);
+                if target.is_empty() {
+                    // Standalone shape inside a container shapes the container
+                    let Some(container) = current else {
+                        return;
+                    };
+                    if shape == NodeShape::Table {
+                        self.table_nodes.insert(container.clone());
+                    }
+                    let node = self
+                        .graph
+                        .nodes
+                        .entry(container.clone())
+                        .or_insert_with(|| Node::new(container.clone(), container));
+                    node.shape = shape;
+                    node.custom_shape = custom;
+                    return;
+                }
+                let id = self.resolve(target);
+                if shape == NodeShape::Table {
+                    self.table_nodes.insert(id.clone());
+                }
+                if let Some(node) = self.graph.nodes.get_mut(&id) {
+                    node.shape = shape;
+                    node.custom_shape = custom;
+                } else {
+                    let mut node = Node::with_shape(id.clone(), id.clone(), shape);
+                    node.custom_shape = custom;
+                    node.subgraph = current.clone();
+                    self.graph.nodes.insert(id.clone(), node);
+                    add_to_subgraph(&mut self.graph, current.as_deref(), &id);
+                }
+            }
+            [p] if p == "label" => {
+                let label = label_text(value);
+                if target.is_empty() {
+                    // Standalone label inside a container names the container
+                    let Some(container) = current else {
+                        return;
+                    };
+                    if let Some(sg) = self
+                        .graph
+                        .subgraphs
+                        .iter_mut()
+                        .find(|sg| sg.id == container)
+                    {
+                        sg.label = label.clone();
+                    }
+                    self.graph
+                        .nodes
+                        .entry(container.clone())
+                        .and_modify(|node| node.label = label.clone())
+                        .or_insert_with(|| Node::new(container, label));
+                    return;
+                }
+                let id = self.resolve(target);
+                if let Some(node) = self.graph.nodes.get_mut(&id) {
+                    node.label = label;
+                } else {
+                    let mut node = Node::new(id.clone(), label);
+                    node.subgraph = current.clone();
+                    self.graph.nodes.insert(id.clone(), node);
+                    add_to_subgraph(&mut self.graph, current.as_deref(), &id);
+                }
+            }
+            [p] if is_pin(p) && !target.is_empty() => {
+                let Ok(coord) = value.text().parse::<usize>() else {
+                    return;
+                };
+                let id = self.resolve(target);
+                ensure_node_exists(&mut self.graph, &id, current.as_deref());
+                if let Some(node) = self.graph.nodes.get_mut(&id) {
+                    if p == "x" {
+                        node.pinned.0 = Some(coord);
+                    } else {
+                        node.pinned.1 = Some(coord);
+                    }
+                }
+            }
+            [p] if is_size(p) && !target.is_empty() => {
+                let Ok(cells) = value.text().parse::<usize>() else {
+                    return;
+                };
+                let id = self.resolve(target);
+                ensure_node_exists(&mut self.graph, &id, current.as_deref());
+                if let Some(node) = self.graph.nodes.get_mut(&id) {
+                    let (width, height) = node.fixed_size.unwrap_or((0, 0));
+                    node.fixed_size = Some(if p == "width" {
+                        (cells, height)
+                    } else {
+                        (width, cells)
+                    });
+                }
+            }
+            // Styling, tooltips and the rest don't affect the drawing, so
+            // they're kept as metadata for the application
+            _ => {
+                let id = if target.is_empty() {
+                    let Some(container) = current else {
+                        return;
+                    };
+                    self.graph
+                        .nodes
+                        .entry(container.clone())
+                        .or_insert_with(|| Node::new(container.clone(), container.clone()));
+                    container
+                } else {
+                    let id = self.resolve(target);
+                    ensure_node_exists(&mut self.graph, &id, current.as_deref());
+                    id
+                };
+                if property == ["tooltip"] && !self.tooltip_nodes.contains(&id) {
+                    self.tooltip_nodes.push(id.clone());
+                }
+                if let Some(node) = self.graph.nodes.get_mut(&id) {
+                    node.metadata
+                        .insert(property.join("."), value.text().to_string());
+                }
+            }
+        }
+    }
+
+    /// Open a container map: `parent: Label {` or `a.b {`
+    fn open_container(&mut self, parts: &[String], label: Option<String>) {
+        if let [id] = parts {
+            let label = label.unwrap_or_else(|| id.clone());
+            if !self.graph.subgraphs.iter().any(|sg| sg.id == *id) {
+                let mut sg = Subgraph::new(id.clone(), label.clone());
+                sg.parent = self.current();
+                self.graph.subgraphs.push(sg);
+            }
+            self.container_stack.push(id.clone());
+            self.graph
+                .nodes
+                .entry(id.clone())
+                .or_insert_with_key(|id| Node::new(id.clone(), label));
+            return;
+        }
+
+        let mut parent = self.current();
+        for (i, part) in parts.iter().enumerate() {
+            let is_last = i == parts.len() - 1;
+            let sg_label = match &label {
+                Some(label) if is_last => label.clone(),
+                _ => part.clone(),
+            };
+            if !self.graph.subgraphs.iter().any(|sg| sg.id == *part) {
+                let mut sg = Subgraph::new(part.clone(), sg_label);
+                sg.parent = parent.clone();
+                self.graph.subgraphs.push(sg);
+            }
+            parent = Some(part.clone());
+            self.container_stack.push(part.clone());
+        }
+    }
+
+    fn connection(&mut self, conn: &Connection) {
+        if conn.ends.iter().any(KeyPath::is_glob) {
+            self.unsupported("glob", conn.line);
+            return;
+        }
+        let current = self.current();
+        let ends: Vec<Vec<String>> = conn
+            .ends
+            .iter()
+            .map(|k| k.parts.iter().map(|p| p.text.clone()).collect())
+            .collect();
+
+        let first = self.graph.edges.len();
+        for (hop, (&arrow, label)) in conn.arrows.iter().zip(&conn.labels).enumerate() {
+            let from = self.resolve(&ends[hop]);
+            let to = self.resolve(&ends[hop + 1]);
+            ensure_node_exists(&mut self.graph, &from, current.as_deref());
+            ensure_node_exists(&mut self.graph, &to, current.as_deref());
+
+            let (from, to) = if arrow == ArrowKind::Backward {
+                (to, from)
+            } else {
+                (from, to)
+            };
+            let style = if arrow == ArrowKind::Line {
+                EdgeStyle::Line
+            } else {
+                EdgeStyle::Arrow
+            };
+            self.graph.edges.push(Edge {
+                from,
+                to,
+                label: label.as_ref().map(label_text).filter(|l| !l.is_empty()),
+                style,
+                ..Default::default()
+            });
+        }
+
+        if let Some(map) = &conn.map {
+            for (key, value) in flatten_map(map) {
+                for edge in &mut self.graph.edges[first..] {
+                    apply_edge_property(edge, &key, &value);
+                }
+            }
+        }
+        for edge in &mut self.graph.edges[first..] {
+            edge.weight = edge.label.as_deref().and_then(Edge::parse_weight);
+        }
+    }
+
+    /// Node id for a key path relative to the current container
+    fn resolve(&mut self, parts: &[String]) -> String {
+        let current = self.current();
+        resolve_path(&mut self.graph, parts, current.as_deref())
+    }
+}
+
+/// Label for a value: text without HTML, and blocks like `|md ...|` as
+/// written
+fn label_text(value: &Value) -> String {
+    match value {
+        Value::Block(text) => text.clone(),
+        _ => strip_html(value.text()),
+    }
+}
+
+/// Index of the first property keyword in a key path: `a.shape`,
+/// `shape` inside a map, `a.x`
+fn keyword_index(key: &KeyPath) -> Option<usize> {
+    key.parts.iter().enumerate().position(|(i, p)| {
+        !p.quoted && (PROPERTY_KEYWORDS.contains(&p.text.as_str()) || (i > 0 && is_pin(&p.text)))
+    })
+}
+
+/// Shapes, connections and containers with their source locations
+pub(crate) fn symbols(input: &str) -> Vec<Symbol> {
+    let mut symbols = Vec::new();
+    collect_symbols(input, &ast::parse(input), &mut symbols);
+    symbols
+}
+
+fn collect_symbols(input: &str, map: &Map, out: &mut Vec<Symbol>) {
+    for statement in &map.statements {
+        match statement {
+            Statement::Field(field) => {
+                let first = &field.key.parts[0];
+                let skipped = !first.quoted
+                    && matches!(
+                        first.text.as_str(),
+                        "layers" | "scenarios" | "steps" | "vars" | "classes"
+                    );
+                if field.key.is_glob() || skipped {
+                    continue;
+                }
+                let keyword = keyword_index(&field.key);
+                let path = &field.key.parts[..keyword.unwrap_or(field.key.parts.len())];
+                match (&field.map, keyword) {
+                    (Some(inner), None) => {
+                        push_path_symbols(input, path, SymbolKind::Container, out);
+                        // A table's map holds its columns, not shapes
+                        if !is_table_map(inner) {
+                            collect_symbols(input, inner, out);
+                        }
+                    }
+                    _ => push_path_symbols(input, path, SymbolKind::Node, out),
+                }
+            }
+            Statement::Connection(conn) => {
+                if conn.ends.iter().any(KeyPath::is_glob) {
+                    continue;
+                }
+                for end in &conn.ends {
+                    push_path_symbols(input, &end.parts, SymbolKind::Node, out);
+                }
+                for (hop, &arrow) in conn.arrows.iter().enumerate() {
+                    let (from, to) = (&conn.ends[hop].parts, &conn.ends[hop + 1].parts);
+                    let (Some(first), Some(from_leaf), Some(to_leaf)) =
+                        (from.first(), from.last(), to.last())
+                    else {
+                        continue;
+                    };
+                    let (a, b) = if arrow == ArrowKind::Backward {
+                        (&to_leaf.text, &from_leaf.text)
+                    } else {
+                        (&from_leaf.text, &to_leaf.text)
+                    };
+                    let span = first.span.start..to_leaf.span.end;
+                    out.push(Symbol {
+                        name: format!("{} -> {}", a, b),
+                        kind: SymbolKind::Edge,
+                        line: line_at(input, span.start),
+                        span,
+                    });
+                }
+            }
+            Statement::Spread { .. } => {}
+        }
+    }
+}
+
+/// `a.b.c` names containers `a` and `b`, then `c` as `leaf`
+fn push_path_symbols(input: &str, parts: &[KeyPart], leaf: SymbolKind, out: &mut Vec<Symbol>) {
+    for (i, part) in parts.iter().enumerate() {
+        let kind = if i + 1 == parts.len() {
+            leaf
+        } else {
+            SymbolKind::Container
+        };
+        out.push(Symbol {
+            name: part.text.clone(),
+            kind,
+            line: line_at(input, part.span.start),
+            span: part.span.clone(),
+        });
+    }
+}
+
+/// Whether a container map declares a `sql_table` or `class` shape
+fn is_table_map(map: &Map) -> bool {
+    map.statements.iter().any(|s| {
+        matches!(s, Statement::Field(f)
+        if f.key.parts.len() == 1
+            && f.key.parts[0].text == "shape"
+            && f.value.as_ref().is_some_and(|v| {
+                parse_shape_str(&v.text().to_lowercase()) == NodeShape::Table
+            }))
+    })
+}
+
+/// Position pin keyword (extension): `id.x`, `id.y`
+fn is_pin(key: &str) -> bool {
+    key == "x" || key == "y"
+}
+
+fn is_size(key: &str) -> bool {
+    key == "width" || key == "height"
+}
+
+fn parse_direction(value: &str) -> Option<Direction> {
+    match value.to_lowercase().as_str() {
+        "right" => Some(Direction::LR),
+        "left" => Some(Direction::RL),
+        "down" => Some(Direction::TB),
+        "up" => Some(Direction::BT),
+        _ => None,
+    }
+}
+
+/// Flatten a map into dotted `key: value` pairs, so
+/// `target-arrowhead: { shape: diamond }` yields
+/// `target-arrowhead.shape: diamond`
+fn flatten_map(map: &Map) -> Vec<(String, String)> {
+    fn walk(map: &Map, prefix: &str, out: &mut Vec<(String, String)>) {
+        for statement in &map.statements {
+            let Statement::Field(field) = statement else {
+                continue;
+            };
+            let parts = field.key.parts.iter().map(|p| p.text.as_str());
+            let key: Vec<&str> = (!prefix.is_empty())
+                .then_some(prefix)
+                .into_iter()
+                .chain(parts)
+                .collect();
+            let key = key.join(".");
+            if let Some(value) = &field.value {
+                out.push((key.clone(), value.text().to_string()));
+            }
+            if let Some(inner) = &field.map {
+                walk(inner, &key, out);
+            }
+        }
+    }
+    let mut out = Vec::new();
+    walk(map, "", &mut out);
+    out
+}
+
+/// Apply one flattened connection-block property to an edge
+fn apply_edge_property(edge: &mut Edge, key: &str, value: &str) {
+    let is_line = matches!(
+        edge.style,
+        EdgeStyle::Line | EdgeStyle::DottedLine | EdgeStyle::ThickLine
+    );
+    match key {
+        "label" => edge.label = Some(value.to_string()).filter(|l| !l.is_empty()),
+        "style.stroke-dash" if value.parse::<f32>().is_ok_and(|dash| dash > 0.0) => {
+            edge.style = if is_line {
+                EdgeStyle::DottedLine
+            } else {
+                EdgeStyle::DottedArrow
+            };
+        }
+        // Undirected lines have no arrow to draw wavy
+        "style.animated" if value == "true" && !is_line => edge.style = EdgeStyle::Wavy,
+        // Wide strokes render with the thick line set
+        "style.stroke-width" if value.parse::<f32>().is_ok_and(|width| width >= 3.0) => {
+            edge.style = if is_line {
+                EdgeStyle::ThickLine
+            } else {
+                EdgeStyle::ThickArrow
+            };
+        }
+        "source-arrowhead.shape" => {
+            if let Some(head) = parse_arrowhead_str(value) {
+                edge.source_arrowhead = Some(head);
+            }
+        }
+        "target-arrowhead.shape" => {
+            if let Some(head) = parse_arrowhead_str(value) {
+                edge.target_arrowhead = Some(head);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn parse_arrowhead_str(shape_str: &str) -> Option<Arrowhead> {
+    match shape_str {
+        "triangle" | "arrow" => Some(Arrowhead::Triangle),
+        "circle" => Some(Arrowhead::Circle),
+        "diamond" => Some(Arrowhead::Diamond),
+        "cf-one" | "cf-one-required" => Some(Arrowhead::CfOne),
+        "cf-many" | "cf-many-required" => Some(Arrowhead::CfMany),
+        _ => None,
+    }
+}
+
+fn ensure_node_exists(graph: &mut Graph, id: &str, subgraph: Option<&str>) {
+    if graph.nodes.contains_key(id) {
+        return;
+    }
+    let mut node = Node::new(id.to_string(), id.to_string());
+    node.subgraph = subgraph.map(String::from);
+    graph.nodes.insert(id.to_string(), node);
+    add_to_subgraph(graph, subgraph, id);
+}
+
+/// Record `id` as a member of container `sg_id`, if any
+fn add_to_subgraph(graph: &mut Graph, sg_id: Option<&str>, id: &str) {
+    let Some(sg_id) = sg_id else {
+        return;
+    };
+    if let Some(sg) = graph.subgraphs.iter_mut().find(|sg| sg.id == sg_id) {
+        if !sg.nodes.iter().any(|n| n == id) {
+            sg.nodes.push(id.to_string());
+        }
+    }
+}
+
+/// Turn containers into boxes instead of standalone nodes.
+///
+/// Opening a container also declares a node of the same id. Once the
+/// container holds anything, that node is dropped so edges naming the
+/// container attach to its box. Empty containers (e.g. `sql_table`
+/// definitions) stay ordinary nodes inside their parent.
+fn resolve_containers(graph: &mut Graph) {
+    for i in 0..graph.subgraphs.len() {
+        let sg = &graph.subgraphs[i];
+        let has_children = graph
+            .subgraphs
+            .iter()
+            .any(|child| child.parent.as_deref() == Some(sg.id.as_str()));
+        if !sg.nodes.is_empty() || has_children {
+            continue;
+        }
+        let (id, parent) = (sg.id.clone(), sg.parent.clone());
+        if let Some(node) = graph.nodes.get_mut(&id) {
+            node.subgraph = parent.clone();
+            add_to_subgraph(graph, parent.as_deref(), &id);
+        }
+    }
+
+    let parents: std::collections::HashSet<String> = graph
+        .subgraphs
+        .iter()
+        .filter_map(|sg| sg.parent.clone())
+        .collect();
+    for sg in &mut graph.subgraphs {
+        if sg.nodes.is_empty() && !parents.contains(&sg.id) {
+            continue;
+        }
+        if let Some(node) = graph.nodes.remove(&sg.id) {
+            if sg.label == sg.id {
+                sg.label = node.label;
+            }
+        }
+    }
+}
+
+fn parse_shape_str(shape_str: &str) -> NodeShape {
+    known_shape(shape_str).unwrap_or(NodeShape::Rectangle)
+}
+
+/// The built-in shape D2 calls `shape_str`
+fn known_shape(shape_str: &str) -> Option<NodeShape> {
+    let shape = match shape_str {
+        "rectangle" | "rect" => NodeShape::Rectangle,
+        "square" => NodeShape::Rectangle,
+        "circle" => NodeShape::Circle,
+        "oval" | "ellipse" => NodeShape::Oval,
+        "diamond" => NodeShape::Diamond,
+        "cylinder" | "queue" | "stored_data" => NodeShape::Cylinder,
+        "hexagon" => NodeShape::Hexagon,
+        "parallelogram" => NodeShape::Parallelogram,
+        "step" => NodeShape::Step,
+        "callout" => NodeShape::Callout,
+        "document" | "page" => NodeShape::Document,
+        "package" => NodeShape::Package,
+        "cloud" => NodeShape::Cloud,
+        "person" => NodeShape::Person,
+        "sql_table" | "class" => NodeShape::Table,
+        _ => return None,
+    };
+    Some(shape)
+}
+
+/// Resolve a key path to its leaf node id. Dotted paths (`a.b.c`) are
+/// relative to `current` and create the containers along the way.
+fn resolve_path(graph: &mut Graph, parts: &[String], current: Option<&str>) -> String {
+    let Some((leaf, prefix)) = parts.split_last() else {
+        return String::new();
+    };
+    if prefix.is_empty() {
+        return leaf.clone();
+    }
+
+    let mut parent = current.map(String::from);
+    for part in prefix {
+        if !graph.subgraphs.iter().any(|sg| sg.id == *part) {
+            let mut sg = Subgraph::new(part.clone(), part.clone());
+            sg.parent = parent.clone();
+            graph.subgraphs.push(sg);
+        }
+        if !graph.nodes.contains_key(part) {
+            let mut node = Node::new(part.clone(), part.clone());
+            node.subgraph = parent.clone();
+            graph.nodes.insert(part.clone(), node);
+        }
+        parent = Some(part.clone());
+    }
+
+    if !graph.nodes.contains_key(leaf) {
+        let mut node = Node::new(leaf.clone(), leaf.clone());
+        node.subgraph = parent.clone();
+        graph.nodes.insert(leaf.clone(), node);
+    }
+    add_to_subgraph(graph, parent.as_deref(), leaf);
+    leaf.clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> (Graph, Vec<DiagramWarning>) {
+        let result = parse_d2(input).unwrap();
+        (result.graph, result.warnings)
+    }
+
+    #[test]
+    fn test_parse_d2_simple() {
+        let (graph, _) = parse("A -> B");
+        assert!(graph.nodes.contains_key("A"));
+        assert!(graph.nodes.contains_key("B"));
+        assert_eq!(graph.edges.len(), 1);
+        assert_eq!(graph.edges[0].from, "A");
+        assert_eq!(graph.edges[0].to, "B");
+    }
+
+    #[test]
+    fn test_parse_d2_with_labels() {
+        let (graph, _) = parse(
+            r#"
+server: "Web Server"
+db: Database
+server -> db
+"#,
+        );
+        assert_eq!(graph.nodes.get("server").unwrap().label, "Web Server");
+        assert_eq!(graph.nodes.get("db").unwrap().label, "Database");
+    }
+
+    #[test]
+    fn test_parse_d2_edge_label() {
+        let (graph, _) = parse("A -> B: \"HTTP request\"");
+        assert_eq!(graph.edges[0].label, Some("HTTP request".to_string()));
+    }
+
+    #[test]
+    fn test_parse_d2_chain_separate_lines() {
+        let (graph, _) = parse(
+            r#"
+A -> B
+B -> C
+C -> D
+"#,
+        );
+        assert_eq!(graph.edges.len(), 3);
+    }
+
+    #[test]
+    fn test_parse_d2_connection_chain() {
+        let (graph, _) = parse("A -> B -> C -> D");
+        assert_eq!(graph.edges.len(), 3);
+        assert_eq!(graph.edges[0].from, "A");
+        assert_eq!(graph.edges[0].to, "B");
+        assert_eq!(graph.edges[1].from, "B");
+        assert_eq!(graph.edges[1].to, "C");
+        assert_eq!(graph.edges[2].from, "C");
+        assert_eq!(graph.edges[2].to, "D");
+        assert_eq!(graph.nodes.len(), 4);
+    }
+
+    #[test]
+    fn test_parse_d2_backward_arrow() {
+        let (graph, _) = parse("A <- B");
+        assert_eq!(graph.edges[0].from, "B");
+        assert_eq!(graph.edges[0].to, "A");
+    }
+
+    #[test]
+    fn test_parse_d2_line() {
+        let (graph, _) = parse("A -- B");
+        assert!(matches!(graph.edges[0].style, EdgeStyle::Line));
+    }
+
+    #[test]
+    fn test_parse_d2_shape_property() {
+        let (graph, _) = parse(
+            r#"
+db: Database
+db.shape: cylinder
+"#,
+        );
+        assert!(matches!(
+            graph.nodes.get("db").unwrap().shape,
+            NodeShape::Cylinder
+        ));
+    }
+
+    #[test]
+    fn test_parse_d2_oval_shape() {
+        let (graph, _) = parse("a.shape: oval\nb.shape: ellipse");
+        assert_eq!(graph.nodes["a"].shape, NodeShape::Oval);
+        assert_eq!(graph.nodes["b"].shape, NodeShape::Oval);
+    }
+
+    #[test]
+    fn test_parse_d2_package_step_callout_shapes() {
+        let (graph, _) = parse("p.shape: package\ns.shape: step\nc.shape: callout");
+        assert_eq!(graph.nodes["p"].shape, NodeShape::Package);
+        assert_eq!(graph.nodes["s"].shape, NodeShape::Step);
+        assert_eq!(graph.nodes["c"].shape, NodeShape::Callout);
+    }
+
+    #[test]
+    fn test_parse_d2_block_string_verbatim() {
+        let (graph, _) = parse("art: |||txt\n    +--+\n    |  |\n    +--+--+\n|||\nart -> b");
+        let art = &graph.nodes["art"];
+        assert!(art.verbatim);
+        assert_eq!(art.label, "+--+\n|  |\n+--+--+");
+        // Lines after the block are parsed as usual
+        assert_eq!(graph.edges.len(), 1);
+        assert!(!graph.nodes["b"].verbatim);
+    }
+
+    #[test]
+    fn test_parse_d2_chain_per_hop_labels() {
+        let (graph, _) = parse("A -> B: first -> C: second -> D");
+        let labels: Vec<(&str, &str, Option<&str>)> = graph
+            .edges
+            .iter()
+            .map(|e| (e.from.as_str(), e.to.as_str(), e.label.as_deref()))
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                ("A", "B", Some("first")),
+                ("B", "C", Some("second")),
+                ("C", "D", None)
+            ]
+        );
+        assert_eq!(graph.nodes.len(), 4);
+    }
+
+    #[test]
+    fn test_parse_d2_arrowhead_blocks() {
+        let (graph, _) = parse(
+            "a -> b: owns {\n  source-arrowhead: {\n    shape: cf-one\n  }\n  target-arrowhead.shape: cf-many\n}\nb -> c: {target-arrowhead: {shape: circle}}",
+        );
+        assert_eq!(graph.edges.len(), 2);
+        assert!(graph.subgraphs.is_empty());
+        let owns = &graph.edges[0];
+        assert_eq!(owns.label.as_deref(), Some("owns"));
+        assert_eq!(owns.source_arrowhead, Some(Arrowhead::CfOne));
+        assert_eq!(owns.target_arrowhead, Some(Arrowhead::CfMany));
+        assert_eq!(graph.edges[1].label, None);
+        assert_eq!(graph.edges[1].target_arrowhead, Some(Arrowhead::Circle));
+    }
+
+    #[test]
+    fn test_parse_d2_edge_label_with_inline_style() {
+        let (graph, _) =
+            parse("A -> B: label {style.stroke-dash: 3}\nB -- C: wide {style.stroke-width: 4}");
+        assert!(graph.subgraphs.is_empty());
+        assert_eq!(graph.nodes.len(), 3);
+        assert_eq!(graph.edges[0].label.as_deref(), Some("label"));
+        assert_eq!(graph.edges[0].style, EdgeStyle::DottedArrow);
+        assert_eq!(graph.edges[1].label.as_deref(), Some("wide"));
+        assert_eq!(graph.edges[1].style, EdgeStyle::ThickLine);
+    }
+
+    #[test]
+    fn test_parse_d2_animated_edge() {
+        let (graph, _) = parse(
+            "a -> b {style.animated: true}
+b -- c {style.animated: true}
+c -> d",
+        );
+        assert_eq!(graph.edges[0].style, EdgeStyle::Wavy);
+        assert_eq!(graph.edges[1].style, EdgeStyle::Line);
+        assert_eq!(graph.edges[2].style, EdgeStyle::Arrow);
+    }
+
+    #[test]
+    fn test_parse_d2_single_line_maps() {
+        let (graph, _) = parse(
+            "a: Alpha {shape: circle; style.fill: red}\ngroup: {x -> y; y: {shape: diamond}}\nb.c: {shape: hexagon}\nd",
+        );
+        assert_eq!(graph.nodes["a"].label, "Alpha");
+        assert_eq!(graph.nodes["a"].shape, NodeShape::Circle);
+        assert_eq!(graph.nodes["y"].shape, NodeShape::Diamond);
+        assert_eq!(graph.nodes["y"].subgraph.as_deref(), Some("group"));
+        assert_eq!(graph.nodes["c"].shape, NodeShape::Hexagon);
+        // The map closes: later declarations land back at the root
+        assert_eq!(graph.nodes["d"].subgraph, None);
+        assert_eq!(graph.edges.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_d2_backslash_continuation() {
+        let (graph, _) = parse("server: \"Primary \\\n   web server\"\nserver -> \\\n  db");
+        assert_eq!(graph.nodes["server"].label, "Primary web server");
+        assert_eq!(graph.edges.len(), 1);
+        assert_eq!(graph.edges[0].to, "db");
+    }
+
+    #[test]
+    fn test_flatten_block_nests_keys() {
+        assert_eq!(
+            flatten_map(&ast::parse(
+                "target-arrowhead: 1 {shape: diamond}; label: \"x;y\""
+            )),
+            vec![
+                ("target-arrowhead".to_string(), "1".to_string()),
+                ("target-arrowhead.shape".to_string(), "diamond".to_string()),
+                ("label".to_string(), "x;y".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_d2_sql_table() {
+        let (graph, _) = parse(
+            r#"
+users: Users Table
+users.shape: sql_table
+"#,
+        );
+        assert!(matches!(
+            graph.nodes.get("users").unwrap().shape,
+            NodeShape::Table
+        ));
+    }
+
+    #[test]
+    fn test_parse_d2_container() {
+        let (graph, _) = parse(
+            r#"
+backend {
+    api: "API Server"
+    db: Database
+}
+api -> db
+"#,
+        );
+        assert!(graph.subgraphs.iter().any(|sg| sg.id == "backend"));
+        assert_eq!(
+            graph.nodes.get("api").unwrap().subgraph,
+            Some("backend".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_d2_container_to_container_edge() {
+        let (graph, _) = parse(
+            r#"
+backend {
+    api
+}
+frontend: Web Tier {
+    web
+}
+backend -> frontend
+"#,
+        );
+        // Containers are boxes, not nodes, so the edge names the subgraphs
+        assert!(!graph.nodes.contains_key("backend"));
+        assert!(!graph.nodes.contains_key("frontend"));
+        assert_eq!(graph.edges[0].from, "backend");
+        assert_eq!(graph.edges[0].to, "frontend");
+        let frontend = graph
+            .subgraphs
+            .iter()
+            .find(|sg| sg.id == "frontend")
+            .unwrap();
+        assert_eq!(frontend.nodes, vec!["web".to_string()]);
+        assert_eq!(frontend.label, "Web Tier");
+    }
+
+    #[test]
+    fn test_parse_d2_empty_container_stays_node() {
+        let (graph, _) = parse("users: {\n  shape: sql_table\n  id: int\n}");
+        assert_eq!(graph.nodes.get("users").unwrap().fields.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_d2_position_pin() {
+        let (graph, _) = parse("a -> b\nb.x: 30\nb.y: 2\nc.y: 5");
+        assert_eq!(graph.nodes["b"].pinned, (Some(30), Some(2)));
+        assert_eq!(graph.nodes["c"].pinned, (None, Some(5)));
+        assert_eq!(graph.nodes["a"].pinned, (None, None));
+    }
+
+    #[test]
+    fn test_parse_d2_size() {
+        let (graph, _) = parse("hub -> a\nhub.width: 30\nhub.height: 7\na.height: 5");
+        assert_eq!(graph.nodes["hub"].fixed_size, Some((30, 7)));
+        assert_eq!(graph.nodes["a"].fixed_size, Some((0, 5)));
+        assert!(graph.nodes["hub"].metadata.is_empty());
+    }
+
+    #[test]
+    fn test_parse_d2_comments() {
+        let (graph, _) = parse(
+            r#"
+# This is a comment
+A -> B
+"#,
+        );
+        assert_eq!(graph.edges.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_d2_trailing_and_block_comments() {
+        let (graph, _) = parse(
+            r#"
+A: Start # the entry point
+A -> B: go # not part of the label
+"""
+C -> D
+"""
+B.shape: circle # shape stays circle
+E: "Issue #42"
+"#,
+        );
+        assert_eq!(graph.nodes["A"].label, "Start");
+        assert_eq!(graph.edges.len(), 1);
+        assert_eq!(graph.edges[0].label.as_deref(), Some("go"));
+        assert!(!graph.nodes.contains_key("C"));
+        assert_eq!(graph.nodes["B"].shape, NodeShape::Circle);
+        assert_eq!(graph.nodes["E"].label, "Issue #42");
+    }
+
+    #[test]
+    fn test_parse_d2_empty() {
+        let result = parse_d2("");
+        assert!(matches!(result, Err(MermaidError::EmptyInput)));
+    }
+
+    #[test]
+    fn test_parse_d2_style_and_direction_not_nodes() {
+        let (graph, _) = parse(
+            r##"
+direction: right
+
+input: Raw Data Block {
+  shape: document
+}
+
+center: Statistical Center {
+  shape: diamond
+  style.fill: "#4CAF50"
+}
+
+forward: Forward Stream {
+  shape: hexagon
+  style.fill: "#2196F3"
+}
+
+input -> center: Find center
+center -> forward: center → end
+"##,
+        );
+
+        assert!(matches!(graph.direction, Direction::LR));
+        assert_eq!(graph.nodes.len(), 3);
+        assert!(graph.nodes.contains_key("input"));
+        assert!(graph.nodes.contains_key("center"));
+        assert!(graph.nodes.contains_key("forward"));
+
+        assert!(!graph.nodes.contains_key("right"));
+        assert!(!graph.nodes.contains_key("document"));
+        assert!(!graph.nodes.contains_key("diamond"));
+        assert!(!graph.nodes.contains_key("hexagon"));
+
+        assert!(matches!(
+            graph.nodes.get("center").unwrap().shape,
+            NodeShape::Diamond
+        ));
+        assert!(matches!(
+            graph.nodes.get("forward").unwrap().shape,
+            NodeShape::Hexagon
+        ));
+    }
+
+    #[test]
+    fn test_parse_d2_semicolons() {
+        let (graph, _) = parse("A -> B; C -> D");
+        assert_eq!(graph.edges.len(), 2);
+        assert_eq!(graph.edges[0].from, "A");
+        assert_eq!(graph.edges[0].to, "B");
+        assert_eq!(graph.edges[1].from, "C");
+        assert_eq!(graph.edges[1].to, "D");
+    }
+
+    #[test]
+    fn test_parse_d2_nested_containers() {
+        let (graph, _) = parse(
+            r#"
+cloud {
+    backend {
+        api: API
+        db: Database
+    }
+    frontend {
+        web: Web App
+    }
+}
+api -> db
+web -> api
+"#,
+        );
+        assert!(graph.subgraphs.iter().any(|sg| sg.id == "cloud"));
+        assert!(graph.subgraphs.iter().any(|sg| sg.id == "backend"));
+        assert!(graph.subgraphs.iter().any(|sg| sg.id == "frontend"));
+        let backend_sg = graph
+            .subgraphs
+            .iter()
+            .find(|sg| sg.id == "backend")
+            .unwrap();
+        assert_eq!(backend_sg.parent, Some("cloud".to_string()));
+    }
+
+    #[test]
+    fn test_parse_d2_dotted_key_paths() {
+        let (graph, _) = parse("a.b.c -> d.e.f");
+        assert!(graph.nodes.contains_key("c"));
+        assert!(graph.nodes.contains_key("f"));
+        assert!(graph.subgraphs.iter().any(|sg| sg.id == "a"));
+        assert!(graph.subgraphs.iter().any(|sg| sg.id == "b"));
+        assert!(graph.subgraphs.iter().any(|sg| sg.id == "d"));
+        assert!(graph.subgraphs.iter().any(|sg| sg.id == "e"));
+        assert_eq!(graph.edges.len(), 1);
+        assert_eq!(graph.edges[0].from, "c");
+        assert_eq!(graph.edges[0].to, "f");
+    }
+
+    #[test]
+    fn test_parse_d2_quoted_keys() {
+        let (graph, _) = parse(r#""my node" -> "other node""#);
+        assert!(graph.nodes.contains_key("my node"));
+        assert!(graph.nodes.contains_key("other node"));
+        assert_eq!(graph.edges[0].from, "my node");
+        assert_eq!(graph.edges[0].to, "other node");
+    }
+
+    #[test]
+    fn test_parse_d2_quoted_braces_and_semicolons() {
+        let (graph, _) = parse("a: \"x{y}\"\n\"b;c\" -> a: \"go; now\"\n\"d.e\": 'f: g'");
+        assert_eq!(graph.nodes["a"].label, "x{y}");
+        assert!(graph.nodes.contains_key("b;c"));
+        assert_eq!(graph.edges.len(), 1);
+        assert_eq!(graph.edges[0].label.as_deref(), Some("go; now"));
+        // A quoted key with a dot names one node, not a path
+        assert_eq!(graph.nodes["d.e"].label, "f: g");
+        assert!(graph.subgraphs.is_empty());
+    }
+
+    #[test]
+    fn test_parse_d2_apostrophe_in_key() {
+        let (graph, _) = parse("John's laptop -> server");
+        assert_eq!(graph.edges[0].from, "John's laptop");
+        assert_eq!(graph.edges[0].to, "server");
+    }
+
+    #[test]
+    fn test_parse_d2_null_deletion() {
+        let (graph, _) = parse(
+            r#"
+A -> B
+B -> C
+B: null
+"#,
+        );
+        assert!(!graph.nodes.contains_key("B"));
+        assert!(graph.edges.is_empty());
+    }
+
+    #[test]
+    fn test_parse_d2_sql_table_fields() {
+        let (graph, _) = parse(
+            r#"
+users {
+    shape: sql_table
+    id: int {constraint: primary_key}
+    name: varchar
+    email: varchar
+}
+"#,
+        );
+        let users = graph.nodes.get("users").unwrap();
+        assert!(matches!(users.shape, NodeShape::Table));
+        assert_eq!(users.fields.len(), 3);
+        assert_eq!(users.fields[0].name, "id");
+        assert_eq!(users.fields[0].type_info, Some("int".to_string()));
+        assert_eq!(users.fields[0].constraint, Some("primary_key".to_string()));
+        assert_eq!(users.fields[1].name, "name");
+        assert_eq!(users.fields[2].name, "email");
+    }
+
+    #[test]
+    fn test_parse_d2_unsupported_glob() {
+        let (_, warnings) = parse(
+            r#"
+A -> B
+*.style.fill: red
+"#,
+        );
+        assert!(warnings.iter().any(|w| matches!(
+            w,
+            DiagramWarning::UnsupportedFeature { feature, .. } if feature == "glob"
+        )));
+    }
+
+    #[test]
+    fn test_parse_d2_unsupported_layers() {
+        let (_, warnings) = parse(
+            r#"
+A -> B
+layers: {
+}
+"#,
+        );
+        assert!(warnings.iter().any(|w| matches!(
+            w,
+            DiagramWarning::UnsupportedFeature { feature, .. } if feature == "layers"
+        )));
+    }
+
+    #[test]
+    fn test_parse_d2_unsupported_tooltip() {
+        let (_, warnings) = parse(
+            r#"
+A -> B
+tooltip: "some tooltip"
+"#,
+        );
+        assert!(warnings.iter().any(|w| matches!(
+            w,
+            DiagramWarning::UnsupportedFeature { feature, .. } if feature == "tooltip"
+        )));
+    }
+
+    #[test]
+    fn test_parse_d2_unused_properties_become_metadata() {
+        let (graph, warnings) = parse(
+            r##"
+api: API {
+    link: https://api.example.com
+    style.fill: "#f00"
+}
+api.near: top-center
+db.link: https://db.example.com
+api -> db
+"##,
+        );
+        let api = &graph.nodes["api"].metadata;
+        assert_eq!(api["link"], "https://api.example.com");
+        assert_eq!(api["style.fill"], "#f00");
+        assert_eq!(api["near"], "top-center");
+        assert_eq!(graph.nodes["db"].metadata["link"], "https://db.example.com");
+        // Still reported, since the link isn't drawn
+        assert!(warnings.iter().any(|w| matches!(
+            w,
+            DiagramWarning::UnsupportedFeature { feature, .. } if feature == "link"
+        )));
+    }
+
+    #[test]
+    fn test_parse_d2_tooltips_become_footnotes() {
+        let (graph, warnings) = parse(
+            r#"
+api: API {
+    tooltip: "owned by payments"
+}
+cloud: {
+    tooltip: eu-west
+    db
+}
+api -> cloud.db
+cache.tooltip: flushed nightly
+"#,
+        );
+        assert_eq!(graph.nodes["api"].label, "API *1");
+        assert_eq!(graph.nodes["api"].metadata["tooltip"], "owned by payments");
+        let cloud = graph.subgraphs.iter().find(|sg| sg.id == "cloud").unwrap();
+        assert_eq!(cloud.label, "cloud *2");
+        assert_eq!(graph.nodes["cache"].label, "cache *3");
+        assert_eq!(
+            graph.tooltips,
+            [
+                ("*1".to_string(), "owned by payments".to_string()),
+                ("*2".to_string(), "eu-west".to_string()),
+                ("*3".to_string(), "flushed nightly".to_string()),
+            ]
+        );
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_parse_d2_label_update() {
+        let (graph, _) = parse(
+            r#"
+A: First Label
+A: Updated Label
+"#,
+        );
+        assert_eq!(graph.nodes.get("A").unwrap().label, "Updated Label");
+        assert_eq!(graph.nodes.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_d2_bidirectional() {
+        let (graph, _) = parse("A <-> B");
+        assert_eq!(graph.edges.len(), 1);
+        assert_eq!(graph.edges[0].from, "A");
+        assert_eq!(graph.edges[0].to, "B");
+    }
+
+    #[test]
+    fn test_parse_d2_mixed_features() {
+        let (graph, warnings) = parse(
+            r#"
+direction: right
+
+# Network diagram
+cloud: Cloud Provider {
+    api: API Gateway {
+        shape: hexagon
+    }
+    db: Database {
+        shape: cylinder
+    }
+}
+
+client: Client App
+client -> api: REST
+api -> db: SQL
+
+tooltip: "hover text"
+"#,
+        );
+
+        assert!(matches!(graph.direction, Direction::LR));
+        assert!(graph.nodes.contains_key("client"));
+        assert!(graph.nodes.contains_key("api"));
+        assert!(graph.nodes.contains_key("db"));
+        assert_eq!(graph.edges.len(), 2);
+
+        assert!(warnings.iter().any(|w| matches!(
+            w,
+            DiagramWarning::UnsupportedFeature { feature, .. } if feature == "tooltip"
+        )));
+    }
+
+    #[test]
+    fn test_parse_d2_dotted_shape_property() {
+        let (graph, _) = parse(
+            r#"
+server.shape: hexagon
+server: My Server
+"#,
+        );
+        assert!(matches!(
+            graph.nodes.get("server").unwrap().shape,
+            NodeShape::Hexagon
+        ));
+        assert_eq!(graph.nodes.get("server").unwrap().label, "My Server");
+    }
+
+    #[test]
+    fn test_parse_d2_chain_with_label() {
+        let (graph, _) = parse("A -> B -> C: final");
+        assert_eq!(graph.edges.len(), 2);
+        assert_eq!(graph.edges[0].from, "A");
+        assert_eq!(graph.edges[0].to, "B");
+        assert_eq!(graph.edges[1].from, "B");
+        assert_eq!(graph.edges[1].to, "C");
+        assert_eq!(graph.edges[1].label, Some("final".to_string()));
+    }
+
+    #[test]
+    fn test_symbols_spans() {
+        let code =
+            "a.b -> c\nbox: {\n  x <- y\n}\nt: {shape: sql_table; id: int}\nz.style.fill: red";
+        let symbols = symbols(code);
+        let found: Vec<(&str, SymbolKind, usize, &str)> = symbols
+            .iter()
+            .map(|s| (s.name.as_str(), s.kind, s.line, &code[s.span.clone()]))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("a", SymbolKind::Container, 1, "a"),
+                ("b", SymbolKind::Node, 1, "b"),
+                ("c", SymbolKind::Node, 1, "c"),
+                ("b -> c", SymbolKind::Edge, 1, "a.b -> c"),
+                ("box", SymbolKind::Container, 2, "box"),
+                ("x", SymbolKind::Node, 3, "x"),
+                ("y", SymbolKind::Node, 3, "y"),
+                ("y -> x", SymbolKind::Edge, 3, "x <- y"),
+                ("t", SymbolKind::Container, 5, "t"),
+                ("z", SymbolKind::Node, 6, "z"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_d2_frames_steps_and_scenarios() {
+        let frames = parse_d2_frames(
+            r#"
+a -> b
+steps: {
+  one: { b -> c }
+  two: { c -> d: done }
+}
+scenarios: {
+  alt: { a -> x }
+}
+"#,
+        )
+        .unwrap();
+        let ids: Vec<Vec<&str>> = frames
+            .iter()
+            .map(|f| {
+                let mut ids: Vec<&str> = f.graph.nodes.keys().map(String::as_str).collect();
+                ids.sort();
+                ids
+            })
+            .collect();
+        assert_eq!(
+            ids,
+            vec![
+                vec!["a", "b"],
+                vec!["a", "b", "c"],
+                vec!["a", "b", "c", "d"],
+                vec!["a", "b", "x"],
+            ]
+        );
+        assert!(frames.iter().all(|f| f.warnings.is_empty()));
+    }
+}
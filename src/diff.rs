@@ -0,0 +1,33 @@
+//! Row diffs between two renders, for watch-mode frontends that redraw
+//! only what changed instead of clearing the screen
+
+use crate::types::RenderResult;
+
+/// Rows of `new` that differ from `old`, as `(row, text)` with 0-based row
+/// numbers. Rows `old` had past the end of `new` come back empty, so
+/// writing each pair to its row and clearing to the end of the line turns
+/// the screen showing `old` into `new`.
+///
+/// # Example
+/// ```
+/// use graphs_tui::{diff_lines, render_mermaid_to_tui, RenderOptions};
+///
+/// let old = render_mermaid_to_tui("flowchart LR\nA --> B", RenderOptions::default()).unwrap();
+/// let new = render_mermaid_to_tui("flowchart LR\nA --> C", RenderOptions::default()).unwrap();
+/// let changed = diff_lines(&old, &new);
+/// assert_eq!(changed, vec![(1, "│ A │───────▶│ C │".to_string())]);
+/// assert!(diff_lines(&new, &new).is_empty());
+/// ```
+pub fn diff_lines(old: &RenderResult, new: &RenderResult) -> Vec<(usize, String)> {
+    let mut old_rows = old.rows();
+    let mut changed = Vec::new();
+    let mut rows = 0;
+    for line in new.rows() {
+        if old_rows.next().as_ref() != Some(&line) {
+            changed.push((rows, line));
+        }
+        rows += 1;
+    }
+    changed.extend(old_rows.enumerate().map(|(i, _)| (rows + i, String::new())));
+    changed
+}
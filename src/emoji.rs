@@ -0,0 +1,147 @@
+//! `:rocket:`-style emoji shortcodes in labels
+//!
+//! Only emoji that terminals draw two columns wide without a variation
+//! selector are listed, so layout sizes boxes to what is actually shown.
+
+/// Shortcodes and their emoji, sorted by name for binary search
+const SHORTCODES: &[(&str, &str)] = &[
+    ("+1", "👍"),
+    ("-1", "👎"),
+    ("alarm_clock", "⏰"),
+    ("bar_chart", "📊"),
+    ("battery", "🔋"),
+    ("bell", "🔔"),
+    ("books", "📚"),
+    ("boom", "💥"),
+    ("brain", "🧠"),
+    ("bug", "🐛"),
+    ("bulb", "💡"),
+    ("bust_in_silhouette", "👤"),
+    ("busts_in_silhouette", "👥"),
+    ("calendar", "📅"),
+    ("chart_with_upwards_trend", "📈"),
+    ("checkered_flag", "🏁"),
+    ("clipboard", "📋"),
+    ("coffee", "☕"),
+    ("computer", "💻"),
+    ("construction", "🚧"),
+    ("crab", "🦀"),
+    ("credit_card", "💳"),
+    ("electric_plug", "🔌"),
+    ("email", "📧"),
+    ("exclamation", "❗"),
+    ("eyes", "👀"),
+    ("file_folder", "📁"),
+    ("fire", "🔥"),
+    ("floppy_disk", "💾"),
+    ("globe_with_meridians", "🌐"),
+    ("green_circle", "🟢"),
+    ("hammer", "🔨"),
+    ("hourglass", "⌛"),
+    ("house", "🏠"),
+    ("inbox_tray", "📥"),
+    ("iphone", "📱"),
+    ("key", "🔑"),
+    ("link", "🔗"),
+    ("lock", "🔒"),
+    ("mag", "🔍"),
+    ("memo", "📝"),
+    ("moneybag", "💰"),
+    ("new", "🆕"),
+    ("office", "🏢"),
+    ("outbox_tray", "📤"),
+    ("package", "📦"),
+    ("page_facing_up", "📄"),
+    ("penguin", "🐧"),
+    ("pushpin", "📌"),
+    ("question", "❓"),
+    ("red_circle", "🔴"),
+    ("robot", "🤖"),
+    ("rocket", "🚀"),
+    ("rotating_light", "🚨"),
+    ("satellite", "📡"),
+    ("shopping_cart", "🛒"),
+    ("snake", "🐍"),
+    ("sparkles", "✨"),
+    ("star", "⭐"),
+    ("stop_sign", "🛑"),
+    ("tada", "🎉"),
+    ("test_tube", "🧪"),
+    ("thumbsdown", "👎"),
+    ("thumbsup", "👍"),
+    ("trophy", "🏆"),
+    ("unlock", "🔓"),
+    ("whale", "🐳"),
+    ("white_check_mark", "✅"),
+    ("x", "❌"),
+    ("yellow_circle", "🟡"),
+    ("zap", "⚡"),
+];
+
+/// `text` with known shortcodes replaced by their emoji, or by `[name]`
+/// when `ascii` is set. Unknown names, like the `:30:` in `10:30:00`, are
+/// kept as written.
+pub(crate) fn expand_shortcodes(text: &str, ascii: bool) -> String {
+    if !text.contains(':') {
+        return text.to_string();
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(':') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let name_len = after
+            .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '+' | '-')))
+            .unwrap_or(after.len());
+        let name = &after[..name_len];
+        let emoji = after[name_len..]
+            .starts_with(':')
+            .then(|| SHORTCODES.binary_search_by_key(&name, |&(n, _)| n).ok())
+            .flatten();
+        match emoji {
+            Some(_) if ascii => {
+                out.push('[');
+                out.push_str(name);
+                out.push(']');
+            }
+            Some(i) => out.push_str(SHORTCODES[i].1),
+            None => {
+                out.push(':');
+                rest = after;
+                continue;
+            }
+        }
+        rest = &after[name_len + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::display_width;
+
+    #[test]
+    fn test_shortcodes_sorted_and_wide() {
+        assert!(SHORTCODES.windows(2).all(|w| w[0].0 < w[1].0));
+        for (name, emoji) in SHORTCODES {
+            assert_eq!(display_width(emoji), 2, ":{}:", name);
+        }
+    }
+
+    #[test]
+    fn test_expand_shortcodes() {
+        assert_eq!(expand_shortcodes(":rocket: Deploy", false), "🚀 Deploy");
+        assert_eq!(
+            expand_shortcodes(":rocket: Deploy", true),
+            "[rocket] Deploy"
+        );
+        assert_eq!(expand_shortcodes("ok:+1::tada:", false), "ok👍🎉");
+        assert_eq!(
+            expand_shortcodes("at 10:30:00 :nope: a:zap:", false),
+            "at 10:30:00 :nope: a⚡"
+        );
+        assert_eq!(expand_shortcodes("trailing :", false), "trailing :");
+    }
+}
@@ -13,6 +13,43 @@ pub enum MermaidError {
     },
     /// Layout error (e.g., cycle detected)
     LayoutError(String),
+    /// Input exceeded a configured size or time budget
+    TooLarge {
+        /// What was counted: `"nodes"`, `"edges"`, `"grid cells"` or
+        /// `"milliseconds"`
+        resource: String,
+        limit: usize,
+        actual: usize,
+    },
+    /// No renderer is registered under the requested diagram kind
+    UnknownDiagram(String),
+    /// A file could not be read
+    Io(String),
+    /// A setting in a config file (see `Config`) is malformed, unknown or
+    /// conflicts with another
+    ConfigError {
+        line: usize,
+        message: String,
+        suggestion: Option<String>,
+    },
+    /// Data handed to an importer isn't in the expected format
+    ImportError {
+        /// What was being imported, e.g. `"cargo metadata"`
+        format: String,
+        message: String,
+    },
+    /// A rendered diagram could not be converted to another format
+    ExportError {
+        /// What was being produced, e.g. `"PNG"`
+        format: String,
+        message: String,
+    },
+    /// A render option has a value that would corrupt the layout
+    InvalidOption {
+        /// Name of the `RenderOptions` field
+        option: String,
+        message: String,
+    },
 }
 
 impl fmt::Display for MermaidError {
@@ -31,6 +68,37 @@ impl fmt::Display for MermaidError {
                 Ok(())
             }
             MermaidError::LayoutError(msg) => write!(f, "Layout error: {}", msg),
+            MermaidError::UnknownDiagram(kind) => write!(f, "Unknown diagram kind: {}", kind),
+            MermaidError::Io(msg) => write!(f, "I/O error: {}", msg),
+            MermaidError::ConfigError {
+                line,
+                message,
+                suggestion,
+            } => {
+                write!(f, "Config line {}: {}", line, message)?;
+                if let Some(sug) = suggestion {
+                    write!(f, " (Suggestion: {})", sug)?;
+                }
+                Ok(())
+            }
+            MermaidError::ImportError { format, message } => {
+                write!(f, "Invalid {} input: {}", format, message)
+            }
+            MermaidError::ExportError { format, message } => {
+                write!(f, "{} export failed: {}", format, message)
+            }
+            MermaidError::InvalidOption { option, message } => {
+                write!(f, "Invalid option {}: {}", option, message)
+            }
+            MermaidError::TooLarge {
+                resource,
+                limit,
+                actual,
+            } => write!(
+                f,
+                "Input too large: {} {} exceeds limit of {}",
+                actual, resource, limit
+            ),
         }
     }
 }
@@ -0,0 +1,749 @@
+//! Export parsed diagrams as Graphviz DOT or back to Mermaid source, and
+//! rendered frames as asciinema recordings
+
+use std::time::Duration;
+
+use crate::json::quote as json_quote;
+use crate::layout::endpoint_nodes;
+use crate::pie_parser::PieChart;
+use crate::seq_parser::{ArrowStyle, FragmentKind, NotePosition, SequenceDiagram, SequenceItem};
+use crate::text::display_width;
+use crate::types::{Arrowhead, Direction, Edge, EdgeStyle, Graph, Node, NodeShape};
+
+impl Graph {
+    /// Graphviz DOT source for the graph, for rendering images with `dot`
+    /// while keeping the terminal preview.
+    ///
+    /// Subgraphs become `cluster_` subgraphs; nodes are listed in id order
+    /// so the output is stable. An edge to or from a subgraph points at its
+    /// first member and is clipped to the cluster with `lhead`/`ltail`.
+    ///
+    /// # Example
+    /// ```
+    /// use graphs_tui::{parse_diagram, ParsedDiagram};
+    ///
+    /// let ParsedDiagram::Flowchart(graph) = parse_diagram("flowchart LR\nA[Start] -->|go| B").unwrap() else {
+    ///     unreachable!()
+    /// };
+    /// let dot = graph.to_dot();
+    /// assert!(dot.contains("rankdir=LR;"));
+    /// assert!(dot.contains("\"A\" -> \"B\" [label=\"go\"];"));
+    /// ```
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph {\n");
+        out.push_str(&format!("    rankdir={};\n", rankdir(self.direction)));
+        let cluster = |id: &str| {
+            let member = endpoint_nodes(self, id).into_iter().next()?;
+            (!self.nodes.contains_key(id)).then(|| (member, format!("cluster_{}", id)))
+        };
+        if self
+            .edges
+            .iter()
+            .any(|e| cluster(&e.from).is_some() || cluster(&e.to).is_some())
+        {
+            out.push_str("    compound=true;\n");
+        }
+        self.dot_level(None, 1, &mut out);
+        for edge in &self.edges {
+            let mut attrs = Vec::new();
+            let (mut from, mut to) = (edge.from.clone(), edge.to.clone());
+            if let Some((member, name)) = cluster(&edge.from) {
+                from = member;
+                attrs.push(format!("ltail={}", quote(&name)));
+            }
+            if let Some((member, name)) = cluster(&edge.to) {
+                to = member;
+                attrs.push(format!("lhead={}", quote(&name)));
+            }
+            if let Some(label) = edge.label.as_deref().filter(|l| !l.is_empty()) {
+                attrs.push(format!("label={}", quote(label)));
+            }
+            match edge.style {
+                EdgeStyle::DottedArrow | EdgeStyle::DottedLine => attrs.push("style=dashed".into()),
+                EdgeStyle::ThickArrow | EdgeStyle::ThickLine => attrs.push("penwidth=2".into()),
+                EdgeStyle::Invisible => attrs.push("style=invis".into()),
+                EdgeStyle::Arrow | EdgeStyle::Line | EdgeStyle::Wavy => {}
+            }
+            let plain = matches!(
+                edge.style,
+                EdgeStyle::Line
+                    | EdgeStyle::DottedLine
+                    | EdgeStyle::ThickLine
+                    | EdgeStyle::Invisible
+            );
+            if let Some(tail) = edge.source_arrowhead {
+                attrs.push("dir=both".into());
+                attrs.push(format!("arrowtail={}", arrow_name(tail)));
+            } else if plain && edge.target_arrowhead.is_none() {
+                attrs.push("dir=none".into());
+            }
+            match edge.target_arrowhead {
+                Some(head) => attrs.push(format!("arrowhead={}", arrow_name(head))),
+                None if plain && edge.source_arrowhead.is_some() => {
+                    attrs.push("arrowhead=none".into())
+                }
+                None => {}
+            }
+            out.push_str(&format!("    {} -> {}", quote(&from), quote(&to)));
+            if !attrs.is_empty() {
+                out.push_str(&format!(" [{}]", attrs.join(", ")));
+            }
+            out.push_str(";\n");
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// Nodes directly in `parent`, then its child subgraphs as clusters
+    fn dot_level(&self, parent: Option<&str>, depth: usize, out: &mut String) {
+        let indent = "    ".repeat(depth);
+        let mut nodes: Vec<&Node> = self
+            .nodes
+            .values()
+            .filter(|n| n.subgraph.as_deref() == parent)
+            .collect();
+        nodes.sort_by(|a, b| a.id.cmp(&b.id));
+        for node in nodes {
+            out.push_str(&format!(
+                "{}{} [{}];\n",
+                indent,
+                quote(&node.id),
+                node_attrs(node).join(", ")
+            ));
+        }
+        for sg in self
+            .subgraphs
+            .iter()
+            .filter(|sg| sg.parent.as_deref() == parent)
+        {
+            out.push_str(&format!(
+                "{}subgraph {} {{\n",
+                indent,
+                quote(&format!("cluster_{}", sg.id))
+            ));
+            out.push_str(&format!("{}    label={};\n", indent, quote(&sg.label)));
+            self.dot_level(Some(&sg.id), depth + 1, out);
+            out.push_str(&format!("{}}}\n", indent));
+        }
+    }
+}
+
+impl Graph {
+    /// Mermaid `stateDiagram-v2` source for a state diagram, e.g. one built
+    /// in code or parsed with `parse_diagram`.
+    ///
+    /// Start/end and history nodes are written back as `[*]`, `[H]` and
+    /// `[H*]`; subgraphs become composite states.
+    ///
+    /// # Example
+    /// ```
+    /// use graphs_tui::{parse_diagram, ParsedDiagram};
+    ///
+    /// let ParsedDiagram::State(graph) = parse_diagram("stateDiagram-v2\n[*] --> Idle\nIdle --> Busy: go").unwrap() else {
+    ///     unreachable!()
+    /// };
+    /// assert_eq!(graph.to_state_mermaid(), "stateDiagram-v2\n    [*] --> Idle\n    Idle --> Busy: go\n");
+    /// ```
+    pub fn to_state_mermaid(&self) -> String {
+        let mut out = String::from("stateDiagram-v2\n");
+        if self.direction != Direction::TB {
+            out.push_str(&format!("    direction {}\n", rankdir(self.direction)));
+        }
+        self.state_level(None, 1, &mut out);
+        out
+    }
+
+    /// States declared in `parent`, its composite states, then the
+    /// transitions between states that share it
+    fn state_level(&self, parent: Option<&str>, depth: usize, out: &mut String) {
+        let indent = "    ".repeat(depth);
+        let mut nodes: Vec<&Node> = self
+            .nodes
+            .values()
+            .filter(|n| n.subgraph.as_deref() == parent && pseudo_state(&n.id).is_none())
+            .filter(|n| !self.subgraphs.iter().any(|sg| sg.id == n.id))
+            .collect();
+        nodes.sort_by(|a, b| a.id.cmp(&b.id));
+        for node in nodes {
+            let connected = self
+                .edges
+                .iter()
+                .any(|e| e.from == node.id || e.to == node.id);
+            if node.label != node.id {
+                out.push_str(&format!(
+                    "{}state \"{}\" as {}\n",
+                    indent,
+                    one_line(&node.label),
+                    node.id
+                ));
+            } else if !connected {
+                out.push_str(&format!("{}{}\n", indent, node.id));
+            }
+        }
+        for sg in self
+            .subgraphs
+            .iter()
+            .filter(|sg| sg.parent.as_deref() == parent)
+        {
+            out.push_str(&format!("{}state {} {{\n", indent, sg.id));
+            self.state_level(Some(&sg.id), depth + 1, out);
+            out.push_str(&format!("{}}}\n", indent));
+        }
+        for edge in self.edges.iter().filter(|e| self.edge_scope(e) == parent) {
+            let state = |id: &str| pseudo_state(id).unwrap_or(id).to_string();
+            out.push_str(&format!(
+                "{}{} --> {}",
+                indent,
+                state(&edge.from),
+                state(&edge.to)
+            ));
+            if let Some(label) = edge.label.as_deref().filter(|l| !l.is_empty()) {
+                out.push_str(&format!(": {}", one_line(label)));
+            }
+            out.push('\n');
+        }
+    }
+
+    /// Composite both ends of a transition sit in; the top level otherwise
+    fn edge_scope(&self, edge: &Edge) -> Option<&str> {
+        // An end naming a composite sits in that composite's parent
+        let scope = |id: &str| match self.nodes.get(id) {
+            Some(node) => Some(node.subgraph.as_deref()),
+            None => self
+                .subgraphs
+                .iter()
+                .find(|sg| sg.id == id)
+                .map(|sg| sg.parent.as_deref()),
+        };
+        let from = scope(&edge.from)?;
+        let to = scope(&edge.to)?;
+        if from == to {
+            from
+        } else {
+            None
+        }
+    }
+}
+
+/// Marker for a node the state parser generated from `[*]`, `[H]` or `[H*]`
+fn pseudo_state(id: &str) -> Option<&'static str> {
+    if id.starts_with("__start_") || id.starts_with("__end_") {
+        Some("[*]")
+    } else if id.starts_with("__deep_history") {
+        Some("[H*]")
+    } else if id.starts_with("__history") {
+        Some("[H]")
+    } else {
+        None
+    }
+}
+
+/// Wrapped label text joined back onto one line
+fn one_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+impl SequenceDiagram {
+    /// Mermaid source for the diagram, so diagrams built in code can be
+    /// saved and parsed back.
+    ///
+    /// `+`/`-` activations stay on their messages; other activation spans
+    /// become `activate`/`deactivate` lines.
+    ///
+    /// # Example
+    /// ```
+    /// use graphs_tui::{ArrowStyle, SequenceDiagram};
+    ///
+    /// let mut diagram = SequenceDiagram::new();
+    /// diagram
+    ///     .add_participant("A", "Alice")
+    ///     .add_message("A", "B", ArrowStyle::Solid, "Hello");
+    /// assert_eq!(
+    ///     diagram.to_mermaid(),
+    ///     "sequenceDiagram\n    participant A as Alice\n    participant B\n    A->>B: Hello\n"
+    /// );
+    /// ```
+    pub fn to_mermaid(&self) -> String {
+        let mut out = String::from("sequenceDiagram\n");
+        if self.wrap {
+            out.push_str("    %%{wrap}%%\n");
+        }
+        if let Some(title) = &self.title {
+            out.push_str(&format!("    title {}\n", title));
+        }
+        if self.autonumber {
+            out.push_str("    autonumber\n");
+        }
+        for p in &self.participants {
+            if p.label == p.id {
+                out.push_str(&format!("    participant {}\n", p.id));
+            } else {
+                out.push_str(&format!("    participant {} as {}\n", p.id, p.label));
+            }
+        }
+
+        // (message index, order, statement); a span that closes at an
+        // index goes before one that opens there, unless it is empty
+        let mut toggles: Vec<(usize, u8, String)> = Vec::new();
+        for (id, start, end) in &self.activations {
+            let inline_start = self
+                .messages
+                .get(*start)
+                .is_some_and(|m| m.activate_to && m.to == *id);
+            if !inline_start {
+                toggles.push((*start, 1, format!("activate {}", id)));
+            }
+            let inline_end = end
+                .checked_sub(1)
+                .and_then(|i| self.messages.get(i))
+                .is_some_and(|m| m.deactivate_to && m.to == *id);
+            if !inline_end {
+                let order = if end > start { 0 } else { 2 };
+                toggles.push((*end, order, format!("deactivate {}", id)));
+            }
+        }
+        toggles.sort_by_key(|&(index, order, _)| (index, order));
+
+        let mut writer = SequenceWriter {
+            toggles: &toggles,
+            next_toggle: 0,
+            next_message: 0,
+            out,
+        };
+        writer.items(&self.items, 1);
+        writer.flush(usize::MAX, "    ");
+        writer.out
+    }
+}
+
+/// Writes sequence items, interleaving activation statements by message
+/// index
+struct SequenceWriter<'a> {
+    toggles: &'a [(usize, u8, String)],
+    next_toggle: usize,
+    next_message: usize,
+    out: String,
+}
+
+impl SequenceWriter<'_> {
+    fn items(&mut self, items: &[SequenceItem], depth: usize) {
+        let indent = "    ".repeat(depth);
+        for item in items {
+            match item {
+                SequenceItem::Message(msg) => {
+                    self.flush(self.next_message, &indent);
+                    self.next_message += 1;
+                    let arrow = match msg.style {
+                        ArrowStyle::Solid => "->>",
+                        ArrowStyle::Dotted => "-->>",
+                        ArrowStyle::SolidLine => "->",
+                        ArrowStyle::DottedLine => "-->",
+                        ArrowStyle::Async => "-)",
+                    };
+                    let sign = if msg.activate_to {
+                        "+"
+                    } else if msg.deactivate_to {
+                        "-"
+                    } else {
+                        ""
+                    };
+                    self.out.push_str(&format!(
+                        "{}{}{}{}{}",
+                        indent, msg.from, arrow, sign, msg.to
+                    ));
+                    if !msg.label.is_empty() {
+                        self.out.push_str(&format!(": {}", msg.label));
+                    }
+                    self.out.push('\n');
+                }
+                SequenceItem::Note(note) => {
+                    let position = match &note.position {
+                        NotePosition::RightOf(id) => format!("right of {}", id),
+                        NotePosition::LeftOf(id) => format!("left of {}", id),
+                        NotePosition::Over(ids) => format!("over {}", ids.join(",")),
+                    };
+                    self.out
+                        .push_str(&format!("{}Note {}: {}\n", indent, position, note.text));
+                }
+                SequenceItem::Delay(label) => match label {
+                    Some(label) => self.out.push_str(&format!("{}...: {}\n", indent, label)),
+                    None => self.out.push_str(&format!("{}...\n", indent)),
+                },
+                SequenceItem::Fragment(fragment) => {
+                    let (keyword, divider) = match fragment.kind {
+                        FragmentKind::Loop => ("loop", "else"),
+                        FragmentKind::Alt => ("alt", "else"),
+                        FragmentKind::Opt => ("opt", "else"),
+                        FragmentKind::Par => ("par", "and"),
+                        FragmentKind::Rect => ("rect", "else"),
+                    };
+                    self.out
+                        .push_str(&block_line(&indent, keyword, Some(&fragment.label)));
+                    for (i, section) in fragment.sections.iter().enumerate() {
+                        if i > 0 {
+                            self.out.push_str(&block_line(
+                                &indent,
+                                divider,
+                                section.label.as_deref(),
+                            ));
+                        }
+                        self.items(&section.items, depth + 1);
+                    }
+                    self.out.push_str(&format!("{}end\n", indent));
+                }
+            }
+        }
+    }
+
+    /// Write the activation statements due before message `index`
+    fn flush(&mut self, index: usize, indent: &str) {
+        while let Some((at, _, statement)) = self.toggles.get(self.next_toggle) {
+            if *at > index {
+                break;
+            }
+            self.out.push_str(&format!("{}{}\n", indent, statement));
+            self.next_toggle += 1;
+        }
+    }
+}
+
+/// `keyword` or `keyword label`
+fn block_line(indent: &str, keyword: &str, label: Option<&str>) -> String {
+    match label.filter(|l| !l.is_empty()) {
+        Some(label) => format!("{}{} {}\n", indent, keyword, label),
+        None => format!("{}{}\n", indent, keyword),
+    }
+}
+
+impl PieChart {
+    /// Mermaid source for the chart
+    ///
+    /// # Example
+    /// ```
+    /// use graphs_tui::PieChart;
+    ///
+    /// let mut chart = PieChart::new(Some("Pets"));
+    /// chart.add_slice("Dogs", 3.0).add_slice("Cats", 1.5);
+    /// assert_eq!(
+    ///     chart.to_mermaid(),
+    ///     "pie\n    title Pets\n    \"Dogs\" : 3\n    \"Cats\" : 1.5\n"
+    /// );
+    /// ```
+    pub fn to_mermaid(&self) -> String {
+        let mut out = String::from(if self.show_data {
+            "pie showData\n"
+        } else {
+            "pie\n"
+        });
+        if let Some(title) = &self.title {
+            out.push_str(&format!("    title {}\n", title));
+        }
+        for slice in &self.slices {
+            let quote = if slice.label.contains('"') { '\'' } else { '"' };
+            out.push_str(&format!(
+                "    {}{}{} : {}\n",
+                quote, slice.label, quote, slice.value
+            ));
+        }
+        out
+    }
+}
+
+/// Asciinema (asciicast v2) recording that shows `frames` one after
+/// another, `interval` apart, for sharing output of
+/// [`render_frames`](crate::render_frames).
+///
+/// The terminal size is that of the largest frame; each frame clears the
+/// screen before it is drawn, and the last one stays up for a full
+/// interval.
+///
+/// # Example
+/// ```
+/// use std::time::Duration;
+/// use graphs_tui::{frames_to_asciicast, render_frames, RenderOptions};
+///
+/// let frames = render_frames("sequenceDiagram\nA->>B: hi\nB-->>A: yo", RenderOptions::default()).unwrap();
+/// let cast = frames_to_asciicast(&frames, Duration::from_secs(1));
+/// assert!(cast.starts_with(r#"{"version":2,"#));
+/// assert_eq!(cast.lines().count(), 1 + frames.len() + 1);
+/// ```
+pub fn frames_to_asciicast(frames: &[String], interval: Duration) -> String {
+    let width = frames
+        .iter()
+        .flat_map(|f| f.lines())
+        .map(display_width)
+        .max()
+        .unwrap_or(0);
+    let height = frames.iter().map(|f| f.lines().count()).max().unwrap_or(0);
+    let mut cast = format!(
+        "{{\"version\":2,\"width\":{},\"height\":{}}}\n",
+        width.max(1),
+        height.max(1)
+    );
+    let seconds = interval.as_secs_f64();
+    for (i, frame) in frames.iter().enumerate() {
+        let data = format!(
+            "\x1b[2J\x1b[H{}",
+            frame.lines().collect::<Vec<_>>().join("\r\n")
+        );
+        cast.push_str(&format!(
+            "[{:.3}, \"o\", {}]\n",
+            i as f64 * seconds,
+            json_quote(&data)
+        ));
+    }
+    if !frames.is_empty() {
+        cast.push_str(&format!(
+            "[{:.3}, \"o\", \"\"]\n",
+            frames.len() as f64 * seconds
+        ));
+    }
+    cast
+}
+
+fn rankdir(direction: Direction) -> &'static str {
+    match direction {
+        Direction::LR => "LR",
+        Direction::RL => "RL",
+        Direction::TB => "TB",
+        Direction::BT => "BT",
+    }
+}
+
+/// Label, shape and style of a node; table fields go on their own lines
+fn node_attrs(node: &Node) -> Vec<String> {
+    let mut label = node.label.clone();
+    for field in &node.fields {
+        label.push('\n');
+        label.push_str(&field.name);
+        if let Some(type_info) = &field.type_info {
+            label.push_str(&format!(": {}", type_info));
+        }
+    }
+    let (shape, extra) = match node.shape {
+        NodeShape::Rectangle | NodeShape::Table | NodeShape::Person => ("box", None),
+        NodeShape::Rounded | NodeShape::Stadium => ("box", Some("style=rounded")),
+        NodeShape::Circle => ("circle", None),
+        NodeShape::DoubleCircle => ("doublecircle", None),
+        NodeShape::Diamond => ("diamond", None),
+        NodeShape::Cylinder => ("cylinder", None),
+        NodeShape::Subroutine => ("box", Some("peripheries=2")),
+        NodeShape::Hexagon => ("hexagon", None),
+        NodeShape::Parallelogram | NodeShape::ParallelogramAlt => ("parallelogram", None),
+        NodeShape::Trapezoid => ("trapezium", None),
+        NodeShape::TrapezoidAlt => ("invtrapezium", None),
+        NodeShape::Asymmetric | NodeShape::Step => ("cds", None),
+        NodeShape::Compartment => ("box", None),
+        NodeShape::Cloud | NodeShape::Oval => ("ellipse", None),
+        NodeShape::Document | NodeShape::Callout => ("note", None),
+        NodeShape::Package => ("tab", None),
+    };
+    let mut attrs = vec![
+        format!("label={}", quote(&label)),
+        format!("shape={}", shape),
+    ];
+    attrs.extend(extra.map(String::from));
+    attrs
+}
+
+fn arrow_name(head: Arrowhead) -> &'static str {
+    match head {
+        Arrowhead::Triangle => "normal",
+        Arrowhead::Circle => "odot",
+        Arrowhead::Diamond => "odiamond",
+        Arrowhead::CfOne => "tee",
+        Arrowhead::CfMany => "crow",
+    }
+}
+
+/// DOT double-quoted string; line breaks become `\n`
+fn quote(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            _ => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::frames_to_asciicast;
+    use crate::d2_parser::parse_d2;
+    use crate::parser::parse_mermaid;
+    use crate::pie_parser::parse_pie_chart;
+    use crate::seq_parser::parse_sequence_diagram;
+    use crate::state_parser::parse_state_diagram;
+    use std::time::Duration;
+
+    #[test]
+    fn test_to_dot_flowchart() {
+        let graph = parse_mermaid(
+            "flowchart LR\nA[\"Say \\\"hi\\\"\"] -.-> B{Ok?}\nsubgraph S[Group]\nC((c))\nend\nB --- C",
+        )
+        .unwrap();
+        assert_eq!(
+            graph.to_dot(),
+            r#"digraph {
+    rankdir=LR;
+    "A" [label="Say \"hi\"", shape=box];
+    "B" [label="Ok?", shape=diamond];
+    subgraph "cluster_S" {
+        label="Group";
+        "C" [label="c", shape=circle];
+    }
+    "A" -> "B" [style=dashed];
+    "B" -> "C" [dir=none];
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_to_dot_d2_arrowheads() {
+        let graph = parse_d2(
+            "a -> b: {\n  source-arrowhead.shape: cf-many\n  target-arrowhead.shape: cf-one\n}",
+        )
+        .unwrap()
+        .graph;
+        assert!(graph
+            .to_dot()
+            .contains("\"a\" -> \"b\" [dir=both, arrowtail=crow, arrowhead=tee];"));
+    }
+
+    #[test]
+    fn test_to_dot_container_endpoints() {
+        let graph = parse_d2("a -> c\nc: {\n  y\n  x\n}\nc -> a").unwrap().graph;
+        let dot = graph.to_dot();
+        assert!(dot.contains("    compound=true;\n"));
+        assert!(dot.contains("\"a\" -> \"x\" [lhead=\"cluster_c\"];"));
+        assert!(dot.contains("\"x\" -> \"a\" [ltail=\"cluster_c\"];"));
+        assert!(!parse_d2("a -> b")
+            .unwrap()
+            .graph
+            .to_dot()
+            .contains("compound"));
+    }
+
+    #[test]
+    fn test_sequence_to_mermaid_round_trips() {
+        let input = "sequenceDiagram
+    title Checkout
+    autonumber
+    participant C as Client
+    C->>+S: order
+    activate D
+    S-->>D: save
+    ...: later
+    alt ok
+        D-->>S: done
+        Note over S,D: stored
+    else failed
+        D-->>S: failed
+    end
+    deactivate D
+    C-->>-S: receipt
+";
+        let diagram = parse_sequence_diagram(input).unwrap();
+        let source = diagram.to_mermaid();
+        assert_eq!(
+            source,
+            "sequenceDiagram
+    title Checkout
+    autonumber
+    participant C as Client
+    participant S
+    participant D
+    C->>+S: order
+    activate D
+    S-->>D: save
+    ...: later
+    alt ok
+        D-->>S: done
+        Note over S,D: stored
+    else failed
+        D-->>S: failed
+    end
+    deactivate D
+    C-->>-S: receipt
+"
+        );
+        let reparsed = parse_sequence_diagram(&source).unwrap();
+        let mut before = diagram.activations.clone();
+        let mut after = reparsed.activations.clone();
+        before.sort();
+        after.sort();
+        assert_eq!(before, after);
+        assert_eq!(reparsed.to_mermaid(), source);
+    }
+
+    #[test]
+    fn test_state_to_mermaid_round_trips() {
+        let input = "stateDiagram-v2
+    state \"Waiting for input\" as Idle
+    [*] --> Idle
+    Idle --> Working: start [ready] / run
+    state Working {
+        [*] --> Busy
+        Busy --> [H]
+    }
+    Working --> [*]
+    Orphan
+";
+        let graph = parse_state_diagram(input).unwrap();
+        let source = graph.to_state_mermaid();
+        assert_eq!(
+            source,
+            "stateDiagram-v2
+    state \"Waiting for input\" as Idle
+    Orphan
+    state Working {
+        [*] --> Busy
+        Busy --> [H]
+    }
+    [*] --> Idle
+    Idle --> Working: start [ready] / run
+    Working --> [*]
+"
+        );
+        assert_eq!(
+            parse_state_diagram(&source).unwrap().to_state_mermaid(),
+            source
+        );
+    }
+
+    #[test]
+    fn test_pie_to_mermaid_round_trips() {
+        let chart =
+            parse_pie_chart("pie showData\ntitle Mix\n'Say \"hi\"' : 2\n\"b\" : 0.25").unwrap();
+        let source = chart.to_mermaid();
+        let reparsed = parse_pie_chart(&source).unwrap();
+        assert!(reparsed.show_data);
+        assert_eq!(reparsed.title.as_deref(), Some("Mix"));
+        let slices: Vec<_> = reparsed
+            .slices
+            .iter()
+            .map(|s| (s.label.as_str(), s.value))
+            .collect();
+        assert_eq!(slices, [("Say \"hi\"", 2.0), ("b", 0.25)]);
+    }
+
+    #[test]
+    fn test_frames_to_asciicast() {
+        let frames = vec!["a\nbb".to_string(), "数".to_string()];
+        assert_eq!(
+            frames_to_asciicast(&frames, Duration::from_millis(500)),
+            r#"{"version":2,"width":2,"height":2}
+[0.000, "o", "\u001b[2J\u001b[Ha\r\nbb"]
+[0.500, "o", "\u001b[2J\u001b[H数"]
+[1.000, "o", ""]
+"#
+        );
+    }
+}
@@ -1,9 +1,19 @@
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 
+use unicode_segmentation::UnicodeSegmentation;
+
 use crate::renderer::backend::RenderBackend;
+use crate::text::display_width;
+
+/// Filler for the second column of a double-width character.
+///
+/// A wide character occupies two grid cells; the right-hand one holds this
+/// marker so it is skipped when the grid is printed.
+pub const WIDE_CHAR_TAIL: char = '\0';
 
 /// Line direction flags for junction merging
-#[derive(Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub struct LineFlags {
     pub up: bool,
     pub down: bool,
@@ -11,6 +21,54 @@ pub struct LineFlags {
     pub right: bool,
 }
 
+impl LineFlags {
+    /// A straight horizontal run (left and right)
+    pub const HORIZONTAL: LineFlags = LineFlags {
+        up: false,
+        down: false,
+        left: true,
+        right: true,
+    };
+
+    /// A straight vertical run (up and down)
+    pub const VERTICAL: LineFlags = LineFlags {
+        up: true,
+        down: true,
+        left: false,
+        right: false,
+    };
+
+    /// Sides present in either set
+    pub fn union(self, other: LineFlags) -> LineFlags {
+        LineFlags {
+            up: self.up || other.up,
+            down: self.down || other.down,
+            left: self.left || other.left,
+            right: self.right || other.right,
+        }
+    }
+
+    /// Box-drawing glyph joining these sides.
+    ///
+    /// Straight runs (and anything with fewer than two sides) use `line`
+    /// so dotted and thick edge styles survive; corners, tees and
+    /// crossings come from `chars`.
+    pub fn glyph(self, line: char, chars: &JunctionChars) -> char {
+        match (self.up, self.down, self.left, self.right) {
+            (true, true, true, true) => chars.cross,
+            (true, false, true, true) => chars.t_up,
+            (false, true, true, true) => chars.t_down,
+            (true, true, false, true) => chars.ml,
+            (true, true, true, false) => chars.mr,
+            (false, true, false, true) => chars.tl,
+            (false, true, true, false) => chars.tr,
+            (true, false, false, true) => chars.bl,
+            (true, false, true, false) => chars.br,
+            _ => line,
+        }
+    }
+}
+
 /// 2D character grid for rendering
 pub struct Grid {
     cells: Vec<Vec<char>>,
@@ -18,6 +76,13 @@ pub struct Grid {
     protected: Vec<Vec<bool>>,
     /// Track line directions at each cell for junction merging
     line_flags: Vec<Vec<LineFlags>>,
+    /// Full grapheme clusters for cells whose text spans several code points
+    /// (the cell itself keeps the first one)
+    clusters: HashMap<(usize, usize), String>,
+    /// Arrowheads waiting to be stamped on top of all lines
+    arrows: Vec<(usize, usize, char)>,
+    /// Cells written in bold, wrapped in ANSI escapes on output
+    bold: HashSet<(usize, usize)>,
     pub width: usize,
     pub height: usize,
 }
@@ -29,15 +94,48 @@ impl Grid {
             cells: vec![vec![' '; width]; height],
             protected: vec![vec![false; width]; height],
             line_flags: vec![vec![LineFlags::default(); width]; height],
+            clusters: HashMap::new(),
+            arrows: Vec::new(),
+            bold: HashSet::new(),
             width,
             height,
         }
     }
 
+    /// Grow to at least `width` x `height`, keeping what is drawn, for
+    /// renderers that don't know their size up front
+    pub fn grow(&mut self, width: usize, height: usize) {
+        let width = width.max(self.width);
+        let height = height.max(self.height);
+        for row in &mut self.cells {
+            row.resize(width, ' ');
+        }
+        for row in &mut self.protected {
+            row.resize(width, false);
+        }
+        for row in &mut self.line_flags {
+            row.resize(width, LineFlags::default());
+        }
+        self.cells.resize(height, vec![' '; width]);
+        self.protected.resize(height, vec![false; width]);
+        self.line_flags
+            .resize(height, vec![LineFlags::default(); width]);
+        self.width = width;
+        self.height = height;
+    }
+
     /// Set a character at given position (bounds-checked)
     pub fn set(&mut self, x: usize, y: usize, c: char) {
         if x < self.width && y < self.height {
-            self.cells[y][x] = c;
+            self.write_cell(x, y, c);
+        }
+    }
+
+    /// Overwrite a cell, dropping any grapheme cluster stored for it
+    fn write_cell(&mut self, x: usize, y: usize, c: char) {
+        self.cells[y][x] = c;
+        if !self.clusters.is_empty() {
+            self.clusters.remove(&(x, y));
         }
     }
 
@@ -45,11 +143,18 @@ impl Grid {
     #[allow(dead_code)]
     pub fn set_protected(&mut self, x: usize, y: usize, c: char) {
         if x < self.width && y < self.height {
-            self.cells[y][x] = c;
+            self.write_cell(x, y, c);
             self.protected[y][x] = true;
         }
     }
 
+    /// Draw a cell in bold
+    pub fn set_bold(&mut self, x: usize, y: usize) {
+        if x < self.width && y < self.height {
+            self.bold.insert((x, y));
+        }
+    }
+
     /// Mark a cell as protected without changing its content
     pub fn mark_protected(&mut self, x: usize, y: usize) {
         if x < self.width && y < self.height {
@@ -61,12 +166,39 @@ impl Grid {
     /// Returns true if the character was set
     pub fn set_if_empty(&mut self, x: usize, y: usize, c: char) -> bool {
         if x < self.width && y < self.height && !self.protected[y][x] {
-            self.cells[y][x] = c;
+            self.write_cell(x, y, c);
             return true;
         }
         false
     }
 
+    /// Write text starting at (x, y), skipping protected cells.
+    ///
+    /// Text is laid out one grapheme cluster at a time so combining marks and
+    /// ZWJ emoji stay intact. Double-width clusters fill their second cell
+    /// with `WIDE_CHAR_TAIL`; zero-width clusters are dropped. Returns the
+    /// width drawn.
+    pub fn draw_text(&mut self, x: usize, y: usize, text: &str) -> usize {
+        let mut dx = 0;
+        for g in text.graphemes(true) {
+            let gw = display_width(g);
+            let Some(first) = g.chars().next() else {
+                continue;
+            };
+            if gw == 0 {
+                continue;
+            }
+            if self.set_if_empty(x + dx, y, first) && g.len() > first.len_utf8() {
+                self.clusters.insert((x + dx, y), g.to_string());
+            }
+            for tail in 1..gw {
+                self.set_if_empty(x + dx + tail, y, WIDE_CHAR_TAIL);
+            }
+            dx += gw;
+        }
+        dx
+    }
+
     /// Set a line character with junction merging.
     /// If the cell already has a line in a different direction, merge into a junction.
     /// `is_horizontal` indicates if this is a horizontal line.
@@ -78,32 +210,68 @@ impl Grid {
         c: char,
         is_horizontal: bool,
         chars: &JunctionChars,
+    ) -> bool {
+        let sides = if is_horizontal {
+            LineFlags::HORIZONTAL
+        } else {
+            LineFlags::VERTICAL
+        };
+        self.set_sides_with_merge(x, y, sides, c, chars)
+    }
+
+    /// Add line segments leaving the cell on `sides` and redraw it.
+    ///
+    /// Sides accumulate across calls, so an edge turning a corner where
+    /// another passes straight through becomes a tee (`┬` `┤` ...) and two
+    /// straight runs become `┼`. Returns true if the cell was written.
+    pub fn set_sides_with_merge(
+        &mut self,
+        x: usize,
+        y: usize,
+        sides: LineFlags,
+        c: char,
+        chars: &JunctionChars,
     ) -> bool {
         if x >= self.width || y >= self.height || self.protected[y][x] {
             return false;
         }
+        let merged = self.line_flags[y][x].union(sides);
+        self.line_flags[y][x] = merged;
+        self.write_cell(x, y, merged.glyph(c, chars));
+        true
+    }
 
-        // Update line flags
-        if is_horizontal {
-            self.line_flags[y][x].left = true;
-            self.line_flags[y][x].right = true;
+    /// Line sides recorded at a cell (empty out of bounds)
+    pub fn line_flags(&self, x: usize, y: usize) -> LineFlags {
+        if x < self.width && y < self.height {
+            self.line_flags[y][x]
         } else {
-            self.line_flags[y][x].up = true;
-            self.line_flags[y][x].down = true;
+            LineFlags::default()
         }
+    }
 
-        // Compute merged character based on flags
-        let flags = &self.line_flags[y][x];
-        let has_h = flags.left || flags.right;
-        let has_v = flags.up || flags.down;
+    /// Queue an arrowhead to be drawn by [`Grid::stamp_arrows`].
+    ///
+    /// Drawing arrowheads last keeps later edges from merging lines over
+    /// them.
+    pub fn queue_arrow(&mut self, x: usize, y: usize, c: char) {
+        self.arrows.push((x, y, c));
+    }
 
-        self.cells[y][x] = if has_h && has_v {
-            // Both horizontal and vertical - use cross
-            chars.cross
-        } else {
-            c
-        };
-        true
+    /// Draw all queued arrowheads on top of the lines.
+    ///
+    /// Unprotected cells are always overwritten. A protected cell is only
+    /// overwritten when `is_border` accepts its current glyph, so an arrow
+    /// can break through a subgraph or node border but never into a label.
+    pub fn stamp_arrows(&mut self, is_border: impl Fn(char) -> bool) {
+        for (x, y, c) in std::mem::take(&mut self.arrows) {
+            if x >= self.width || y >= self.height {
+                continue;
+            }
+            if !self.protected[y][x] || is_border(self.cells[y][x]) {
+                self.write_cell(x, y, c);
+            }
+        }
     }
 
     /// Check if a cell is protected
@@ -151,6 +319,17 @@ impl RenderBackend for Grid {
         Grid::set_line_with_merge(self, x, y, c, is_horizontal, chars)
     }
 
+    fn set_sides_with_merge(
+        &mut self,
+        x: usize,
+        y: usize,
+        sides: LineFlags,
+        c: char,
+        chars: &JunctionChars,
+    ) -> bool {
+        Grid::set_sides_with_merge(self, x, y, sides, c, chars)
+    }
+
     fn dimensions(&self) -> (usize, usize) {
         (self.width, self.height)
     }
@@ -161,17 +340,22 @@ impl RenderBackend for Grid {
 }
 
 /// Junction characters needed for line merging
-#[allow(dead_code)]
 pub struct JunctionChars {
     pub cross: char,  // ┼
-    pub t_up: char,   // ┴ (for future T-junction support)
-    pub t_down: char, // ┬ (for future T-junction support)
-    pub ml: char,     // ├ (for future T-junction support)
-    pub mr: char,     // ┤ (for future T-junction support)
+    pub t_up: char,   // ┴
+    pub t_down: char, // ┬
+    pub ml: char,     // ├
+    pub mr: char,     // ┤
+    pub tl: char,     // ┌
+    pub tr: char,     // ┐
+    pub bl: char,     // └
+    pub br: char,     // ┘
 }
 
-impl fmt::Display for Grid {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl Grid {
+    /// The grid as text, up to its last row with content; `trim` drops
+    /// the spaces at the end of each row
+    pub fn render(&self, trim: bool) -> String {
         // Find the last row that has non-space content
         let last_non_empty = self
             .cells
@@ -179,15 +363,36 @@ impl fmt::Display for Grid {
             .rposition(|row| row.iter().any(|&c| c != ' '))
             .unwrap_or(0);
 
+        let mut out = String::new();
         for (i, row) in self.cells[..=last_non_empty].iter().enumerate() {
-            let line: String = row.iter().collect();
-            let trimmed = line.trim_end();
-            write!(f, "{}", trimmed)?;
+            let mut line = String::with_capacity(row.len());
+            let mut bold = false;
+            for (x, &c) in row.iter().enumerate() {
+                if self.bold.contains(&(x, i)) != bold {
+                    bold = !bold;
+                    line.push_str(if bold { "\x1b[1m" } else { "\x1b[22m" });
+                }
+                match self.clusters.get(&(x, i)) {
+                    Some(cluster) => line.push_str(cluster),
+                    None if c != WIDE_CHAR_TAIL => line.push(c),
+                    None => {}
+                }
+            }
+            if bold {
+                line.push_str("\x1b[22m");
+            }
+            out.push_str(if trim { line.trim_end() } else { &line });
             if i < last_non_empty {
-                writeln!(f)?;
+                out.push('\n');
             }
         }
-        Ok(())
+        out
+    }
+}
+
+impl fmt::Display for Grid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.render(true))
     }
 }
 
@@ -210,6 +415,15 @@ mod tests {
         assert_eq!(grid.get(0, 0), Some(' '));
     }
 
+    #[test]
+    fn test_grid_bold_cells() {
+        let mut grid = Grid::new(4, 1);
+        grid.draw_text(0, 0, "abc");
+        grid.set_bold(1, 0);
+        grid.set_bold(2, 0);
+        assert_eq!(grid.to_string(), "a\x1b[1mbc\x1b[22m");
+    }
+
     #[test]
     fn test_grid_bounds_check() {
         let mut grid = Grid::new(5, 3);
@@ -226,6 +440,117 @@ mod tests {
         assert_eq!(s, "A\n  B");
     }
 
+    #[test]
+    fn test_grid_draw_wide_text() {
+        let mut grid = Grid::new(8, 1);
+        grid.set(0, 0, '│');
+        let w = grid.draw_text(1, 0, "日本x");
+        grid.set(6, 0, '│');
+        assert_eq!(w, 5);
+        assert_eq!(grid.to_string(), "│日本x│");
+    }
+
+    #[test]
+    fn test_grid_grow_keeps_content() {
+        let mut grid = Grid::new(2, 1);
+        grid.set(1, 0, 'A');
+        grid.grow(4, 3);
+        grid.set(3, 2, 'B');
+        grid.grow(1, 1);
+        assert_eq!((grid.width, grid.height), (4, 3));
+        assert_eq!(grid.to_string(), " A\n\n   B");
+        assert_eq!(grid.render(false), " A  \n    \n   B");
+    }
+
+    #[test]
+    fn test_grid_draw_grapheme_clusters() {
+        let family = "👨\u{200d}👩\u{200d}👧";
+        let mut grid = Grid::new(8, 1);
+        grid.set(0, 0, '│');
+        let w = grid.draw_text(1, 0, &format!("{family}e\u{301}"));
+        grid.set(1 + w, 0, '│');
+        assert_eq!(w, 3);
+        assert_eq!(grid.to_string(), format!("│{family}e\u{301}│"));
+
+        // Overwriting a cluster cell replaces the whole cluster
+        grid.set(1, 0, 'x');
+        assert!(grid.to_string().starts_with("│x"));
+        assert!(!grid.to_string().contains('👩'));
+    }
+
+    fn junction_chars() -> JunctionChars {
+        JunctionChars {
+            cross: '┼',
+            t_up: '┴',
+            t_down: '┬',
+            ml: '├',
+            mr: '┤',
+            tl: '┌',
+            tr: '┐',
+            bl: '└',
+            br: '┘',
+        }
+    }
+
+    #[test]
+    fn test_line_flags_glyph() {
+        let jc = junction_chars();
+        let corner = LineFlags {
+            up: true,
+            right: true,
+            ..Default::default()
+        };
+        assert_eq!(corner.glyph('─', &jc), '└');
+        assert_eq!(corner.union(LineFlags::HORIZONTAL).glyph('─', &jc), '┴');
+        assert_eq!(corner.union(LineFlags::VERTICAL).glyph('─', &jc), '├');
+        assert_eq!(LineFlags::HORIZONTAL.glyph('┄', &jc), '┄');
+        assert_eq!(
+            LineFlags::HORIZONTAL
+                .union(LineFlags::VERTICAL)
+                .glyph('─', &jc),
+            '┼'
+        );
+    }
+
+    #[test]
+    fn test_grid_corner_merges_into_tee() {
+        let jc = junction_chars();
+        let mut grid = Grid::new(3, 3);
+        for x in 0..3 {
+            grid.set_line_with_merge(x, 1, '─', true, &jc);
+        }
+        // An edge coming down and turning right where the line already runs
+        let turn = LineFlags {
+            up: true,
+            right: true,
+            ..Default::default()
+        };
+        grid.set_line_with_merge(1, 0, '│', false, &jc);
+        grid.set_sides_with_merge(1, 1, turn, '│', &jc);
+        assert_eq!(grid.get(1, 1), Some('┴'));
+        // A second branch arriving from below completes the crossing
+        grid.set_sides_with_merge(1, 1, LineFlags::VERTICAL, '│', &jc);
+        assert_eq!(grid.get(1, 1), Some('┼'));
+    }
+
+    #[test]
+    fn test_stamp_arrows_after_lines() {
+        let jc = junction_chars();
+        let mut grid = Grid::new(4, 3);
+        grid.set_protected(0, 1, '│');
+        grid.set_protected(3, 1, 'B');
+        grid.queue_arrow(1, 1, '▶');
+        grid.queue_arrow(0, 1, '▶');
+        grid.queue_arrow(3, 1, '▶');
+        // A later line through the arrow cell must not swallow it
+        grid.set_line_with_merge(1, 1, '─', true, &jc);
+        grid.stamp_arrows(|c| c == '│');
+        assert_eq!(grid.get(1, 1), Some('▶'));
+        // Protected border glyph gives way, protected text does not
+        assert_eq!(grid.get(0, 1), Some('▶'));
+        assert_eq!(grid.get(3, 1), Some('B'));
+    }
+
     #[test]
     fn test_grid_protected() {
         let mut grid = Grid::new(5, 3);
@@ -251,13 +576,7 @@ mod tests {
     #[test]
     fn test_junction_merging() {
         let mut grid = Grid::new(5, 5);
-        let jchars = JunctionChars {
-            cross: '┼',
-            t_up: '┴',
-            t_down: '┬',
-            ml: '├',
-            mr: '┤',
-        };
+        let jchars = junction_chars();
 
         // Draw horizontal line through center
         grid.set_line_with_merge(1, 2, '─', true, &jchars);
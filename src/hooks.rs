@@ -0,0 +1,163 @@
+//! Callbacks that report how long each stage of a render took, for host
+//! applications that chart rendering performance
+
+use std::fmt;
+use std::panic::RefUnwindSafe;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::text::{display_width, strip_ansi};
+
+/// Parsing finished
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseMetrics {
+    /// Time spent parsing
+    pub elapsed: Duration,
+    /// Length of the source in bytes
+    pub input_bytes: usize,
+    /// Lines in the source
+    pub input_lines: usize,
+}
+
+/// Layout and drawing finished
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LayoutMetrics {
+    /// Time spent laying out and drawing the diagram
+    pub elapsed: Duration,
+    /// Columns of the widest output line
+    pub width: usize,
+    /// Lines of output
+    pub height: usize,
+}
+
+/// `RefUnwindSafe` keeps `RenderOptions` usable inside `catch_unwind`
+type Callback<T> = Arc<dyn Fn(&T) + Send + Sync + RefUnwindSafe>;
+
+/// Callbacks run as a render passes each stage; none are set by default.
+///
+/// `on_parse_complete` runs once the source is parsed, so it is skipped by
+/// [`render_parsed`](crate::render_parsed), which starts from a parsed
+/// diagram. `on_layout_complete` runs once the diagram is laid out and
+/// drawn. [`render_partial`](crate::render_partial) may render twice when
+/// the last line is unfinished, but reports only the render it returns.
+///
+/// # Example
+/// ```
+/// use std::sync::{Arc, Mutex};
+/// use graphs_tui::{render_mermaid_to_tui, RenderHooks, RenderOptions};
+///
+/// let sizes = Arc::new(Mutex::new(Vec::new()));
+/// let log = sizes.clone();
+/// let hooks = RenderHooks::new()
+///     .on_layout_complete(move |m| log.lock().unwrap().push((m.width, m.height)));
+/// let options = RenderOptions::builder().hooks(hooks).build().unwrap();
+/// render_mermaid_to_tui("flowchart LR\nA --> B", options).unwrap();
+/// assert_eq!(*sizes.lock().unwrap(), vec![(18, 3)]);
+/// ```
+#[derive(Clone, Default)]
+pub struct RenderHooks {
+    parse_complete: Option<Callback<ParseMetrics>>,
+    layout_complete: Option<Callback<LayoutMetrics>>,
+}
+
+impl RenderHooks {
+    /// Hooks with no callbacks set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Call `callback` once the source is parsed
+    pub fn on_parse_complete(
+        mut self,
+        callback: impl Fn(&ParseMetrics) + Send + Sync + RefUnwindSafe + 'static,
+    ) -> Self {
+        self.parse_complete = Some(Arc::new(callback));
+        self
+    }
+
+    /// Call `callback` once the diagram is laid out and drawn
+    pub fn on_layout_complete(
+        mut self,
+        callback: impl Fn(&LayoutMetrics) + Send + Sync + RefUnwindSafe + 'static,
+    ) -> Self {
+        self.layout_complete = Some(Arc::new(callback));
+        self
+    }
+
+    /// Report parsing `input`, begun at `start`
+    pub(crate) fn parsed(&self, input: &str, start: Instant) {
+        if let Some(callback) = &self.parse_complete {
+            callback(&ParseMetrics {
+                elapsed: start.elapsed(),
+                input_bytes: input.len(),
+                input_lines: input.lines().count(),
+            });
+        }
+    }
+
+    /// Report drawing `output`, begun at `start`
+    pub(crate) fn laid_out(&self, output: &str, start: Instant) {
+        if let Some(callback) = &self.layout_complete {
+            let plain = strip_ansi(output);
+            callback(&LayoutMetrics {
+                elapsed: start.elapsed(),
+                width: plain.lines().map(display_width).max().unwrap_or(0),
+                height: plain.lines().count(),
+            });
+        }
+    }
+}
+
+/// Metrics held back by [`RenderHooks::recorder`] until a render is chosen
+#[derive(Debug, Default)]
+pub(crate) struct Recording {
+    parsed: Mutex<Option<ParseMetrics>>,
+    laid_out: Mutex<Option<LayoutMetrics>>,
+}
+
+impl Recording {
+    /// Forget a render that won't be returned
+    pub(crate) fn clear(&self) {
+        *lock(&self.parsed) = None;
+        *lock(&self.laid_out) = None;
+    }
+}
+
+/// The value behind `mutex`, even if a callback panicked while holding it
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl RenderHooks {
+    /// Hooks that store each stage's metrics in the returned recording
+    /// instead of calling these callbacks, for [`replay`](Self::replay)
+    /// once the caller knows which of several renders it keeps
+    pub(crate) fn recorder(&self) -> (RenderHooks, Arc<Recording>) {
+        let recording = Arc::new(Recording::default());
+        let (parsed, laid_out) = (recording.clone(), recording.clone());
+        let hooks = RenderHooks::new()
+            .on_parse_complete(move |m| *lock(&parsed.parsed) = Some(*m))
+            .on_layout_complete(move |m| *lock(&laid_out.laid_out) = Some(*m));
+        (hooks, recording)
+    }
+
+    /// Call these callbacks with the metrics `recording` holds
+    pub(crate) fn replay(&self, recording: &Recording) {
+        if let (Some(callback), Some(metrics)) = (&self.parse_complete, *lock(&recording.parsed)) {
+            callback(&metrics);
+        }
+        if let (Some(callback), Some(metrics)) = (&self.layout_complete, *lock(&recording.laid_out))
+        {
+            callback(&metrics);
+        }
+    }
+}
+
+impl fmt::Debug for RenderHooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RenderHooks")
+            .field("on_parse_complete", &self.parse_complete.is_some())
+            .field("on_layout_complete", &self.layout_complete.is_some())
+            .finish()
+    }
+}
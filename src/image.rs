@@ -0,0 +1,126 @@
+//! PNG output and iTerm2 inline images, behind the `png` feature
+//!
+//! The drawing goes through the same SVG as [`render_rich`](crate::render_rich)
+//! and is rasterized with resvg, using the system's monospace font.
+//!
+//! # Example
+//! ```
+//! use graphs_tui::image::render_iterm2;
+//! use graphs_tui::RenderOptions;
+//!
+//! let image = render_iterm2("mermaid", "flowchart LR\nA --> B", RenderOptions::default()).unwrap();
+//! assert!(image.starts_with("\x1b]1337;File=inline=1"));
+//! ```
+
+use resvg::{tiny_skia, usvg};
+
+use crate::error::MermaidError;
+use crate::render;
+use crate::rich::to_svg;
+use crate::types::RenderOptions;
+
+/// Pixels per SVG pixel, so text stays sharp on high-density displays
+const SCALE: f32 = 2.0;
+
+/// Render a diagram as a PNG image of the text drawing, black on white.
+///
+/// `lang` is dispatched like [`render`]; colors the drawing carries when
+/// `options.colors` is set show up in the image.
+pub fn render_png(lang: &str, code: &str, options: RenderOptions) -> Result<Vec<u8>, MermaidError> {
+    let result = render(lang, code, options)?;
+    svg_to_png(&to_svg(&result.output))
+}
+
+/// Render a diagram as a PNG wrapped in iTerm2's inline image escape, for
+/// printing in place of the text drawing
+pub fn render_iterm2(
+    lang: &str,
+    code: &str,
+    options: RenderOptions,
+) -> Result<String, MermaidError> {
+    Ok(iterm2_inline_image(&render_png(lang, code, options)?))
+}
+
+/// The OSC 1337 escape that makes iTerm2 (and WezTerm) show `png` inline
+pub fn iterm2_inline_image(png: &[u8]) -> String {
+    format!(
+        "\x1b]1337;File=inline=1;size={};preserveAspectRatio=1:{}\x07",
+        png.len(),
+        base64(png)
+    )
+}
+
+fn svg_to_png(svg: &str) -> Result<Vec<u8>, MermaidError> {
+    let error = |message: String| MermaidError::ExportError {
+        format: "PNG".to_string(),
+        message,
+    };
+    let mut opt = usvg::Options::default();
+    opt.fontdb_mut().load_system_fonts();
+    let tree = usvg::Tree::from_str(svg, &opt).map_err(|e| error(e.to_string()))?;
+    let size = tree.size();
+    let width = (size.width() * SCALE).ceil().max(1.0) as u32;
+    let height = (size.height() * SCALE).ceil().max(1.0) as u32;
+    let mut pixmap =
+        tiny_skia::Pixmap::new(width, height).ok_or_else(|| error("image too large".into()))?;
+    pixmap.fill(tiny_skia::Color::WHITE);
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(SCALE, SCALE),
+        &mut pixmap.as_mut(),
+    );
+    pixmap.encode_png().map_err(|e| error(e.to_string()))
+}
+
+/// Standard padded base64, as the iTerm2 escape expects
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foob"), "Zm9vYg==");
+        assert_eq!(base64(&[0xff, 0xfe, 0xfd]), "//79");
+    }
+
+    #[test]
+    fn test_render_png_and_iterm2() {
+        let png = render_png("d2", "a -> b", RenderOptions::default()).unwrap();
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+        let escape = iterm2_inline_image(&png);
+        assert!(escape.starts_with(&format!("\x1b]1337;File=inline=1;size={};", png.len())));
+        assert!(escape.ends_with('\x07'));
+        assert!(render_png("mermaid", "", RenderOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_bad_svg_is_an_export_error() {
+        assert!(matches!(
+            svg_to_png("<svg"),
+            Err(MermaidError::ExportError { ref format, .. }) if format == "PNG"
+        ));
+    }
+}
@@ -0,0 +1,235 @@
+//! Crate dependency graphs from `cargo metadata`
+
+use std::collections::HashMap;
+
+use crate::error::MermaidError;
+use crate::json::Json;
+use crate::types::{Direction, Edge, EdgeStyle, Graph, Node, NodeId, NodeShape};
+
+/// Name used in import errors
+const FORMAT: &str = "cargo metadata";
+
+/// Graph of the packages in `cargo metadata --format-version 1` output.
+///
+/// Each package becomes a node, named by its crate name (with `@version`
+/// when several versions are present); workspace members are drawn
+/// rounded. Dependencies become edges from the dependent crate, dashed when
+/// the dependency is only a dev- or build-dependency. With a `resolve`
+/// section (the default) edges follow the resolved graph; with `--no-deps`
+/// they follow each package's declared dependencies among the workspace.
+///
+/// # Example
+/// ```
+/// use graphs_tui::import::from_cargo_metadata;
+/// use graphs_tui::{render_parsed, ParsedDiagram, RenderOptions};
+///
+/// let json = r#"{
+///   "packages": [
+///     {"id": "app 0.1.0", "name": "app", "version": "0.1.0",
+///      "dependencies": [{"name": "core", "kind": null}, {"name": "testkit", "kind": "dev"}]},
+///     {"id": "core 0.1.0", "name": "core", "version": "0.1.0", "dependencies": []},
+///     {"id": "testkit 0.1.0", "name": "testkit", "version": "0.1.0", "dependencies": []}
+///   ],
+///   "workspace_members": ["app 0.1.0", "core 0.1.0", "testkit 0.1.0"],
+///   "resolve": null
+/// }"#;
+/// let graph = from_cargo_metadata(json).unwrap();
+/// assert_eq!(graph.edges.len(), 2);
+/// let result = render_parsed(ParsedDiagram::Flowchart(graph), RenderOptions::default()).unwrap();
+/// assert!(result.output.contains("testkit"));
+/// ```
+pub fn from_cargo_metadata(json: &str) -> Result<Graph, MermaidError> {
+    let error = |message: String| MermaidError::ImportError {
+        format: FORMAT.to_string(),
+        message,
+    };
+    let metadata = Json::parse(json).map_err(error)?;
+    let packages = metadata
+        .get("packages")
+        .and_then(Json::as_array)
+        .ok_or_else(|| error("expected a packages array".to_string()))?;
+    let members: Vec<&str> = metadata
+        .get("workspace_members")
+        .and_then(Json::as_array)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(Json::as_str)
+        .collect();
+
+    let field = |package: &'_ Json, name: &str| -> Result<String, MermaidError> {
+        package
+            .get(name)
+            .and_then(Json::as_str)
+            .map(String::from)
+            .ok_or_else(|| error(format!("package without a string {}", name)))
+    };
+    let mut names: HashMap<String, usize> = HashMap::new();
+    let mut listed = Vec::new();
+    for package in packages {
+        let (id, name, version) = (
+            field(package, "id")?,
+            field(package, "name")?,
+            field(package, "version")?,
+        );
+        *names.entry(name.clone()).or_default() += 1;
+        listed.push((id, name, version, package));
+    }
+
+    let mut graph = Graph::new(Direction::TB);
+    // Node for each package id, and the packages known by each crate name
+    let mut node_of: HashMap<&str, NodeId> = HashMap::new();
+    let mut by_name: HashMap<&str, NodeId> = HashMap::new();
+    for (id, name, version, _) in &listed {
+        let (node_id, label) = if names[name] > 1 {
+            (
+                format!("{}@{}", name, version),
+                format!("{} v{}", name, version),
+            )
+        } else {
+            (name.clone(), name.clone())
+        };
+        let mut node = Node::new(node_id.clone(), label);
+        if members.contains(&id.as_str()) {
+            node.shape = NodeShape::Rounded;
+        }
+        graph.nodes.insert(node_id.clone(), node);
+        by_name.entry(name).or_insert_with(|| node_id.clone());
+        node_of.insert(id, node_id);
+    }
+
+    // (from, to, only dev/build) in first-seen order; a normal dependency
+    // anywhere makes the edge solid
+    let mut deps: Vec<(NodeId, NodeId, bool)> = Vec::new();
+    let mut add = |from: &NodeId, to: &NodeId, dev_or_build: bool| match deps
+        .iter_mut()
+        .find(|(f, t, _)| f == from && t == to)
+    {
+        Some(dep) => dep.2 &= dev_or_build,
+        None => deps.push((from.clone(), to.clone(), dev_or_build)),
+    };
+    let resolved = metadata
+        .get("resolve")
+        .and_then(|r| r.get("nodes"))
+        .and_then(Json::as_array);
+    if let Some(resolved) = resolved {
+        for entry in resolved {
+            let Some(from) = entry
+                .get("id")
+                .and_then(Json::as_str)
+                .and_then(|id| node_of.get(id))
+            else {
+                continue;
+            };
+            for dep in entry
+                .get("deps")
+                .and_then(Json::as_array)
+                .unwrap_or_default()
+            {
+                let Some(to) = dep
+                    .get("pkg")
+                    .and_then(Json::as_str)
+                    .and_then(|id| node_of.get(id))
+                else {
+                    continue;
+                };
+                let kinds = dep
+                    .get("dep_kinds")
+                    .and_then(Json::as_array)
+                    .unwrap_or_default();
+                let dev_or_build =
+                    !kinds.is_empty() && kinds.iter().all(|k| is_dev_or_build(k.get("kind")));
+                add(from, to, dev_or_build);
+            }
+        }
+    } else {
+        for (id, _, _, package) in &listed {
+            let from = &node_of[id.as_str()];
+            let declared = package
+                .get("dependencies")
+                .and_then(Json::as_array)
+                .unwrap_or_default();
+            for dep in declared {
+                let Some(to) = dep
+                    .get("name")
+                    .and_then(Json::as_str)
+                    .and_then(|n| by_name.get(n))
+                else {
+                    continue;
+                };
+                add(from, to, is_dev_or_build(dep.get("kind")));
+            }
+        }
+    }
+
+    for (from, to, dev_or_build) in deps {
+        graph.edges.push(Edge {
+            from,
+            to,
+            style: if dev_or_build {
+                EdgeStyle::DottedArrow
+            } else {
+                EdgeStyle::Arrow
+            },
+            ..Default::default()
+        });
+    }
+    Ok(graph)
+}
+
+/// A dependency `kind` of `"dev"` or `"build"`; `null` is a normal one
+fn is_dev_or_build(kind: Option<&Json>) -> bool {
+    matches!(kind.and_then(Json::as_str), Some("dev" | "build"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolved_graph() {
+        let json = r#"{
+          "packages": [
+            {"id": "app", "name": "app", "version": "1.0.0", "dependencies": []},
+            {"id": "log-0.3", "name": "log", "version": "0.3.9", "dependencies": []},
+            {"id": "log-0.4", "name": "log", "version": "0.4.20", "dependencies": []},
+            {"id": "cc", "name": "cc", "version": "1.0.0", "dependencies": []}
+          ],
+          "workspace_members": ["app"],
+          "resolve": {"nodes": [
+            {"id": "app", "deps": [
+              {"pkg": "log-0.4", "dep_kinds": [{"kind": null}, {"kind": "dev"}]},
+              {"pkg": "cc", "dep_kinds": [{"kind": "build", "target": null}]}
+            ]},
+            {"id": "log-0.4", "deps": [{"pkg": "log-0.3", "dep_kinds": [{"kind": null}]}]}
+          ]}
+        }"#;
+        let graph = from_cargo_metadata(json).unwrap();
+        assert_eq!(graph.nodes["app"].shape, NodeShape::Rounded);
+        assert_eq!(graph.nodes["cc"].shape, NodeShape::Rectangle);
+        assert_eq!(graph.nodes["log@0.4.20"].label, "log v0.4.20");
+        let edges: Vec<(&str, &str, EdgeStyle)> = graph
+            .edges
+            .iter()
+            .map(|e| (e.from.as_str(), e.to.as_str(), e.style))
+            .collect();
+        assert_eq!(
+            edges,
+            vec![
+                ("app", "log@0.4.20", EdgeStyle::Arrow),
+                ("app", "cc", EdgeStyle::DottedArrow),
+                ("log@0.4.20", "log@0.3.9", EdgeStyle::Arrow),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rejects_other_json() {
+        let error = from_cargo_metadata("{\"packages\": 3}").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid cargo metadata input: expected a packages array"
+        );
+        assert!(from_cargo_metadata("not json").is_err());
+        assert!(from_cargo_metadata(r#"{"packages": [{"name": "x"}]}"#).is_err());
+    }
+}
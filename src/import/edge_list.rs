@@ -0,0 +1,180 @@
+//! Graphs from CSV or TSV edge lists
+
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::error::MermaidError;
+use crate::types::{Direction, Edge, Graph, Node};
+
+/// Graph of a `from,to,label` edge list, one edge per line, with fields
+/// split on `delimiter` (`','` for CSV, `'\t'` for TSV).
+///
+/// The label is optional, and a label ending in a duration like `(2d)`
+/// sets the edge weight as in Mermaid. Fields may be double-quoted to hold
+/// the delimiter, with `""` for a quote. Blank lines, `#` comments and a
+/// leading `from,to[,label]` header are skipped.
+///
+/// # Example
+/// ```
+/// use graphs_tui::import::parse_edge_list;
+/// use graphs_tui::{render_parsed, ParsedDiagram, RenderOptions};
+///
+/// let csv = "from,to,label\nweb,api,REST\napi,\"db, primary\",\n";
+/// let graph = parse_edge_list(csv, ',').unwrap();
+/// assert_eq!(graph.edges[1].to, "db, primary");
+/// let result = render_parsed(ParsedDiagram::Flowchart(graph), RenderOptions::default()).unwrap();
+/// assert!(result.output.contains("REST"));
+/// ```
+pub fn parse_edge_list(input: &str, delimiter: char) -> Result<Graph, MermaidError> {
+    let mut graph = Graph::new(Direction::LR);
+    let mut first = true;
+    for (i, raw) in input.lines().enumerate() {
+        let line = i + 1;
+        let error = |message: String| MermaidError::ParseError {
+            line,
+            message,
+            suggestion: Some(format!(
+                "Write each edge as from{}to{}label, with the label optional",
+                delimiter, delimiter
+            )),
+        };
+        let trimmed = raw.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let fields = split_fields(trimmed, delimiter).map_err(error)?;
+        if std::mem::take(&mut first) && is_header(&fields) {
+            continue;
+        }
+        let (from, to, label) = match fields.as_slice() {
+            [from, to] => (from, to, None),
+            [from, to, label] => (from, to, Some(label).filter(|l| !l.is_empty())),
+            _ => {
+                return Err(error(format!(
+                    "Expected 2 or 3 fields, found {}",
+                    fields.len()
+                )))
+            }
+        };
+        if from.is_empty() || to.is_empty() {
+            return Err(error("Edge endpoint is empty".to_string()));
+        }
+        for id in [from, to] {
+            graph
+                .nodes
+                .entry(id.clone())
+                .or_insert_with(|| Node::new(id.clone(), id.clone()));
+        }
+        graph.edges.push(Edge {
+            from: from.clone(),
+            to: to.clone(),
+            label: label.cloned(),
+            weight: label.and_then(|l| Edge::parse_weight(l)),
+            ..Default::default()
+        });
+    }
+    Ok(graph)
+}
+
+/// A `from`, `to`, optional `label` header row
+fn is_header(fields: &[String]) -> bool {
+    let names: Vec<String> = fields.iter().map(|f| f.to_lowercase()).collect();
+    match names.as_slice() {
+        [from, to] | [from, to, _] => {
+            (from == "from" && to == "to") || (from == "source" && to == "target")
+        }
+        _ => false,
+    }
+}
+
+/// Fields of one line, trimmed and unquoted
+fn split_fields(line: &str, delimiter: char) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut chars = line.chars().peekable();
+    loop {
+        skip_blanks(&mut chars, delimiter);
+        let mut field = String::new();
+        if chars.next_if_eq(&'"').is_some() {
+            loop {
+                match chars.next() {
+                    Some('"') if chars.next_if_eq(&'"').is_some() => field.push('"'),
+                    Some('"') => break,
+                    Some(c) => field.push(c),
+                    None => return Err("Unclosed quote".to_string()),
+                }
+            }
+            skip_blanks(&mut chars, delimiter);
+            if chars.peek().is_some_and(|&c| c != delimiter) {
+                return Err("Unexpected text after a quoted field".to_string());
+            }
+        } else {
+            while let Some(c) = chars.next_if(|&c| c != delimiter) {
+                field.push(c);
+            }
+            field.truncate(field.trim_end().len());
+        }
+        fields.push(field);
+        if chars.next().is_none() {
+            return Ok(fields);
+        }
+    }
+}
+
+/// Skip whitespace up to the next field or delimiter
+fn skip_blanks(chars: &mut Peekable<Chars>, delimiter: char) {
+    while chars
+        .next_if(|&c| c != delimiter && c.is_whitespace())
+        .is_some()
+    {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tsv_edges() {
+        let tsv = "# deploy order\nbuild\ttest\nbuild\tlint\t\ntest\tship\trelease (2h)\n\n";
+        let graph = parse_edge_list(tsv, '\t').unwrap();
+        assert_eq!(graph.nodes.len(), 4);
+        let edges: Vec<(&str, &str, Option<&str>, Option<u64>)> = graph
+            .edges
+            .iter()
+            .map(|e| (e.from.as_str(), e.to.as_str(), e.label.as_deref(), e.weight))
+            .collect();
+        assert_eq!(
+            edges,
+            vec![
+                ("build", "test", None, None),
+                ("build", "lint", None, None),
+                ("test", "ship", Some("release (2h)"), Some(120)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_fields() {
+        assert_eq!(
+            split_fields(r#" a , "b, ""c""" ,"#, ',').unwrap(),
+            vec!["a", "b, \"c\"", ""]
+        );
+        assert!(split_fields("\"open", ',').is_err());
+        assert!(split_fields("\"a\" b,c", ',').is_err());
+    }
+
+    #[test]
+    fn test_edge_list_errors() {
+        let line = |input: &str| match parse_edge_list(input, ',') {
+            Err(MermaidError::ParseError { line, .. }) => line,
+            other => panic!("expected a parse error, got {:?}", other),
+        };
+        assert_eq!(line("a,b\nc"), 2);
+        assert_eq!(line("a,b,c,d"), 1);
+        assert_eq!(line("Source,Target\n,b"), 2);
+        // Only the first row can be a header
+        assert_eq!(
+            parse_edge_list("a,b\nfrom,to", ',').unwrap().edges[1].from,
+            "from"
+        );
+    }
+}
@@ -0,0 +1,160 @@
+//! JSON structure trees, behind the `json` feature
+
+use serde_json::Value;
+
+use crate::text::truncate_with_ellipsis;
+use crate::types::{Direction, Edge, EdgeStyle, Graph, Node, NodeShape};
+
+/// Columns a scalar value's preview may take
+const PREVIEW_WIDTH: usize = 24;
+
+/// Members shown per object or array; the rest are counted
+const MAX_ENTRIES: usize = 12;
+
+/// Tree of the objects and arrays in `value`, left to right.
+///
+/// Each non-empty object or array becomes a node headed by its key and
+/// size (`address {3}`, `[0] [2]`), listing its scalar members with
+/// previews shortened to fit; nested objects and arrays hang off it as
+/// child nodes. Node ids are JSONPath-style, e.g. `$.items[0]`. Members
+/// past the first dozen are only counted, keeping large documents
+/// readable. YAML can be shown the same way once deserialized into a
+/// [`Value`].
+///
+/// # Example
+/// ```
+/// use graphs_tui::import::from_json_value;
+/// use graphs_tui::{render_parsed, ParsedDiagram, RenderOptions};
+///
+/// let value = serde_json::json!({"name": "graphs-tui", "keywords": ["tui", "d2"]});
+/// let graph = from_json_value(&value);
+/// assert_eq!(graph.nodes["$.keywords"].label, "keywords [2]\n[0]: \"tui\"\n[1]: \"d2\"");
+/// let result = render_parsed(ParsedDiagram::Flowchart(graph), RenderOptions::default()).unwrap();
+/// assert!(result.output.contains("name: \"graphs-tui\""));
+/// ```
+pub fn from_json_value(value: &Value) -> Graph {
+    let mut graph = Graph::new(Direction::LR);
+    add_node(&mut graph, "$".to_string(), "$", value);
+    graph
+}
+
+/// Add the node for `value` at `id`, with the subtree below it
+fn add_node(graph: &mut Graph, id: String, key: &str, value: &Value) {
+    let members: Vec<(String, String, &Value)> = match value {
+        Value::Object(map) if !map.is_empty() => map
+            .iter()
+            .map(|(k, v)| (member_path(&id, k), k.clone(), v))
+            .collect(),
+        Value::Array(items) if !items.is_empty() => items
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (format!("{}[{}]", id, i), format!("[{}]", i), v))
+            .collect(),
+        scalar => {
+            let label = format!("{}: {}", key, preview(scalar));
+            graph.nodes.insert(id.clone(), Node::new(id, label));
+            return;
+        }
+    };
+
+    let mut lines = vec![match value {
+        Value::Array(_) => format!("{} [{}]", key, members.len()),
+        _ => format!("{} {{{}}}", key, members.len()),
+    }];
+    let mut children = Vec::new();
+    for (path, name, member) in members.iter().take(MAX_ENTRIES) {
+        if is_container(member) {
+            children.push((path, name, member));
+        } else {
+            lines.push(format!("{}: {}", name, preview(member)));
+        }
+    }
+    if members.len() > MAX_ENTRIES {
+        lines.push(format!("… {} more", members.len() - MAX_ENTRIES));
+    }
+    let mut node = Node::new(id.clone(), lines.join("\n"));
+    node.shape = if matches!(value, Value::Array(_)) {
+        NodeShape::Rectangle
+    } else {
+        NodeShape::Rounded
+    };
+    graph.nodes.insert(id.clone(), node);
+
+    for (path, name, member) in children {
+        add_node(graph, path.clone(), name, member);
+        graph.edges.push(Edge {
+            from: id.clone(),
+            to: path.clone(),
+            style: EdgeStyle::Line,
+            ..Default::default()
+        });
+    }
+}
+
+/// A non-empty object or array, which gets a node of its own
+fn is_container(value: &Value) -> bool {
+    match value {
+        Value::Object(map) => !map.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        _ => false,
+    }
+}
+
+/// `parent.key`, or `parent["key"]` when the key isn't an identifier
+fn member_path(parent: &str, key: &str) -> String {
+    let identifier = key
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if identifier {
+        format!("{}.{}", parent, key)
+    } else {
+        format!("{}[{}]", parent, Value::from(key))
+    }
+}
+
+/// A scalar (or empty container) as single-line JSON, shortened to fit
+fn preview(value: &Value) -> String {
+    truncate_with_ellipsis(&value.to_string(), PREVIEW_WIDTH)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_structure_tree() {
+        let value = json!({
+            "name": "a very long package name that will not fit",
+            "tags": [],
+            "owner": {"login": "octo", "site admin": false},
+            "releases": [{"tag": "v1"}, 2]
+        });
+        let graph = from_json_value(&value);
+        let mut ids: Vec<&str> = graph.nodes.keys().map(String::as_str).collect();
+        ids.sort();
+        assert_eq!(ids, vec!["$", "$.owner", "$.releases", "$.releases[0]"]);
+        assert_eq!(
+            graph.nodes["$"].label,
+            "$ {4}\nname: \"a very long package na…\ntags: []"
+        );
+        assert_eq!(
+            graph.nodes["$.owner"].label,
+            "owner {2}\nlogin: \"octo\"\nsite admin: false"
+        );
+        assert_eq!(graph.nodes["$.releases[0]"].label, "[0] {1}\ntag: \"v1\"");
+        assert_eq!(graph.edges.len(), 3);
+        assert_eq!(member_path("$", "site admin"), "$[\"site admin\"]");
+    }
+
+    #[test]
+    fn test_large_and_scalar_documents() {
+        let graph = from_json_value(&json!((0..20).collect::<Vec<_>>()));
+        let label = &graph.nodes["$"].label;
+        assert!(label.starts_with("$ [20]\n[0]: 0\n"));
+        assert!(label.ends_with("[11]: 11\n… 8 more"));
+        assert_eq!(from_json_value(&json!(null)).nodes["$"].label, "$: null");
+    }
+}
@@ -0,0 +1,317 @@
+//! Cluster topology from Kubernetes manifests, behind the `k8s` feature
+
+use yaml_rust2::{Yaml, YamlLoader};
+
+use crate::error::MermaidError;
+use crate::types::{Direction, Edge, EdgeStyle, Graph, Node, NodeId, NodeShape, Subgraph};
+
+/// Name used in import errors
+const FORMAT: &str = "Kubernetes manifest";
+
+/// A Deployment, Service or Ingress read from a manifest
+struct Resource<'a> {
+    kind: &'a str,
+    namespace: String,
+    name: String,
+    doc: &'a Yaml,
+}
+
+impl Resource<'_> {
+    /// `namespace/kind/name`, unique across the cluster
+    fn node_id(&self) -> NodeId {
+        node_id(&self.namespace, self.kind, &self.name)
+    }
+}
+
+fn node_id(namespace: &str, kind: &str, name: &str) -> NodeId {
+    format!("{}/{}/{}", namespace, kind, name)
+}
+
+/// Graph of the Deployments, Services and Ingresses in YAML manifests, as
+/// `kubectl apply -f` would read them.
+///
+/// `yaml` may hold several `---`-separated documents and `kind: List`
+/// wrappers; other kinds are skipped. Each namespace becomes a container
+/// holding its resources, labeled kubectl-style (`ing/web`, `svc/web`,
+/// `deploy/web`). An Ingress points at the Services its rules route to,
+/// labeled with the host and path, and a Service points at the
+/// Deployments whose pod template labels match its selector.
+///
+/// # Example
+/// ```
+/// use graphs_tui::import::from_k8s_manifests;
+/// use graphs_tui::{render_parsed, ParsedDiagram, RenderOptions};
+///
+/// let yaml = "
+/// kind: Service
+/// metadata: {name: web}
+/// spec:
+///   selector: {app: web}
+/// ---
+/// kind: Deployment
+/// metadata: {name: web}
+/// spec:
+///   template:
+///     metadata:
+///       labels: {app: web, tier: frontend}
+/// ";
+/// let graph = from_k8s_manifests(yaml).unwrap();
+/// assert_eq!(graph.edges[0].to, "default/deploy/web");
+/// let result = render_parsed(ParsedDiagram::Flowchart(graph), RenderOptions::default()).unwrap();
+/// assert!(result.output.contains("svc/web"));
+/// ```
+pub fn from_k8s_manifests(yaml: &str) -> Result<Graph, MermaidError> {
+    let error = |message: String| MermaidError::ImportError {
+        format: FORMAT.to_string(),
+        message,
+    };
+    let docs = YamlLoader::load_from_str(yaml).map_err(|e| error(e.to_string()))?;
+    let mut objects = Vec::new();
+    for doc in &docs {
+        match doc["kind"].as_str() {
+            Some("List") => objects.extend(doc["items"].as_vec().into_iter().flatten()),
+            _ => objects.push(doc),
+        }
+    }
+
+    let mut resources = Vec::new();
+    for doc in objects {
+        let kind = match doc["kind"].as_str() {
+            Some("Deployment") => "deploy",
+            Some("Service") => "svc",
+            Some("Ingress") => "ing",
+            _ => continue,
+        };
+        let metadata = &doc["metadata"];
+        let name = metadata["name"].as_str().ok_or_else(|| {
+            error(format!(
+                "{} without metadata.name",
+                doc["kind"].as_str().unwrap_or_default()
+            ))
+        })?;
+        resources.push(Resource {
+            kind,
+            namespace: metadata["namespace"]
+                .as_str()
+                .unwrap_or("default")
+                .to_string(),
+            name: name.to_string(),
+            doc,
+        });
+    }
+
+    let mut graph = Graph::new(Direction::LR);
+    for resource in &resources {
+        let id = resource.node_id();
+        if !graph.subgraphs.iter().any(|s| s.id == resource.namespace) {
+            graph.subgraphs.push(Subgraph::new(
+                resource.namespace.clone(),
+                resource.namespace.clone(),
+            ));
+        }
+        let subgraph = graph
+            .subgraphs
+            .iter_mut()
+            .find(|s| s.id == resource.namespace)
+            .expect("namespace container was just added");
+        if !subgraph.nodes.contains(&id) {
+            subgraph.nodes.push(id.clone());
+        }
+        let mut node = Node::new(id.clone(), format!("{}/{}", resource.kind, resource.name));
+        node.shape = match resource.kind {
+            "ing" => NodeShape::Hexagon,
+            "svc" => NodeShape::Rounded,
+            _ => NodeShape::Rectangle,
+        };
+        node.subgraph = Some(resource.namespace.clone());
+        graph.nodes.insert(id, node);
+    }
+
+    let mut edges: Vec<(NodeId, NodeId, Option<String>)> = Vec::new();
+    for resource in &resources {
+        let from = resource.node_id();
+        match resource.kind {
+            "ing" => {
+                for (service, route) in ingress_backends(resource.doc) {
+                    let to = node_id(&resource.namespace, "svc", &service);
+                    let edge = (from.clone(), to, route);
+                    if graph.nodes.contains_key(&edge.1) && !edges.contains(&edge) {
+                        edges.push(edge);
+                    }
+                }
+            }
+            "svc" => {
+                let Some(selector) = resource.doc["spec"]["selector"].as_hash() else {
+                    continue;
+                };
+                if selector.is_empty() {
+                    continue;
+                }
+                for target in resources.iter().filter(|r| {
+                    r.kind == "deploy"
+                        && r.namespace == resource.namespace
+                        && selector.iter().all(|(key, value)| {
+                            let labels = &r.doc["spec"]["template"]["metadata"]["labels"];
+                            labels
+                                .as_hash()
+                                .and_then(|labels| labels.get(key))
+                                .is_some_and(|label| scalar(label) == scalar(value))
+                        })
+                }) {
+                    edges.push((from.clone(), target.node_id(), None));
+                }
+            }
+            _ => {}
+        }
+    }
+    for (from, to, label) in edges {
+        graph.edges.push(Edge {
+            from,
+            to,
+            label,
+            style: EdgeStyle::Arrow,
+            ..Default::default()
+        });
+    }
+    Ok(graph)
+}
+
+/// The Services an Ingress routes to, each with its `host/path` when the
+/// rule names one; covers both `networking.k8s.io/v1` and the older
+/// `serviceName` backends
+fn ingress_backends(ingress: &Yaml) -> Vec<(String, Option<String>)> {
+    let service = |backend: &Yaml| {
+        backend["service"]["name"]
+            .as_str()
+            .or_else(|| backend["serviceName"].as_str())
+            .map(String::from)
+    };
+    let spec = &ingress["spec"];
+    let mut backends = Vec::new();
+    for default in [&spec["defaultBackend"], &spec["backend"]] {
+        if let Some(name) = service(default) {
+            backends.push((name, None));
+        }
+    }
+    for rule in spec["rules"].as_vec().into_iter().flatten() {
+        let host = rule["host"].as_str().unwrap_or_default();
+        for path in rule["http"]["paths"].as_vec().into_iter().flatten() {
+            let Some(name) = service(&path["backend"]) else {
+                continue;
+            };
+            let route = format!("{}{}", host, path["path"].as_str().unwrap_or_default());
+            backends.push((name, (!route.is_empty()).then_some(route)));
+        }
+    }
+    backends
+}
+
+/// A label or selector value as text, however YAML typed it
+fn scalar(value: &Yaml) -> Option<String> {
+    match value {
+        Yaml::String(s) | Yaml::Real(s) => Some(s.clone()),
+        Yaml::Integer(n) => Some(n.to_string()),
+        Yaml::Boolean(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFESTS: &str = r#"
+apiVersion: networking.k8s.io/v1
+kind: Ingress
+metadata:
+  name: public
+  namespace: shop
+spec:
+  defaultBackend:
+    service: {name: web, port: {number: 80}}
+  rules:
+    - host: shop.example.com
+      http:
+        paths:
+          - path: /api
+            pathType: Prefix
+            backend:
+              service: {name: api, port: {number: 8080}}
+---
+apiVersion: v1
+kind: List
+items:
+  - kind: Service
+    metadata: {name: web, namespace: shop}
+    spec: {selector: {app: web}}
+  - kind: Service
+    metadata: {name: api, namespace: shop}
+    spec: {selector: {app: api, version: 2}}
+---
+kind: Deployment
+metadata: {name: web, namespace: shop}
+spec:
+  template:
+    metadata: {labels: {app: web}}
+---
+kind: Deployment
+metadata: {name: api-v2, namespace: shop}
+spec:
+  template:
+    metadata: {labels: {app: api, version: "2"}}
+---
+kind: Deployment
+metadata: {name: web}
+spec:
+  template:
+    metadata: {labels: {app: web}}
+---
+kind: ConfigMap
+metadata: {name: settings}
+"#;
+
+    #[test]
+    fn test_manifest_topology() {
+        let graph = from_k8s_manifests(MANIFESTS).unwrap();
+        assert_eq!(graph.nodes.len(), 6);
+        assert_eq!(graph.nodes["shop/ing/public"].label, "ing/public");
+        assert_eq!(graph.nodes["shop/svc/web"].shape, NodeShape::Rounded);
+        let namespaces: Vec<(&str, usize)> = graph
+            .subgraphs
+            .iter()
+            .map(|s| (s.id.as_str(), s.nodes.len()))
+            .collect();
+        assert_eq!(namespaces, vec![("shop", 5), ("default", 1)]);
+
+        let edges: Vec<(&str, &str, Option<&str>)> = graph
+            .edges
+            .iter()
+            .map(|e| (e.from.as_str(), e.to.as_str(), e.label.as_deref()))
+            .collect();
+        assert_eq!(
+            edges,
+            vec![
+                ("shop/ing/public", "shop/svc/web", None),
+                (
+                    "shop/ing/public",
+                    "shop/svc/api",
+                    Some("shop.example.com/api")
+                ),
+                // The selector only matches Deployments in its own namespace
+                ("shop/svc/web", "shop/deploy/web", None),
+                ("shop/svc/api", "shop/deploy/api-v2", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rejects_bad_manifests() {
+        let error = from_k8s_manifests("kind: Service\nmetadata: {}\n").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid Kubernetes manifest input: Service without metadata.name"
+        );
+        assert!(from_k8s_manifests("kind: [unclosed").is_err());
+        assert!(from_k8s_manifests("").unwrap().nodes.is_empty());
+    }
+}
@@ -0,0 +1,26 @@
+//! Graphs built from other tools' data, for rendering structures that
+//! weren't written as diagrams
+//!
+//! Each importer returns a [`Graph`](crate::Graph) ready for
+//! [`render_parsed`](crate::render_parsed) as a
+//! [`ParsedDiagram::Flowchart`](crate::ParsedDiagram::Flowchart).
+
+mod cargo;
+mod edge_list;
+#[cfg(feature = "json")]
+mod json;
+#[cfg(feature = "k8s")]
+mod k8s;
+mod paths;
+#[cfg(feature = "xml")]
+mod xml;
+
+pub use cargo::from_cargo_metadata;
+pub use edge_list::parse_edge_list;
+#[cfg(feature = "json")]
+pub use json::from_json_value;
+#[cfg(feature = "k8s")]
+pub use k8s::from_k8s_manifests;
+pub use paths::from_paths;
+#[cfg(feature = "xml")]
+pub use xml::{from_gexf, from_graphml};
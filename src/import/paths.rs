@@ -0,0 +1,119 @@
+//! Directory trees from lists of paths, like `find` or `fd` print
+
+use std::collections::HashSet;
+use std::path::{Component, Path};
+
+use crate::types::{Direction, Edge, EdgeStyle, Graph, Node};
+
+/// Containment tree of `paths`, top to bottom.
+///
+/// Every path contributes a node for itself and each directory above it,
+/// linked parent to child; the node id is the path so far (without a
+/// leading `./`) and the label is its last component. Directories, known
+/// from having children or a trailing `/`, are labeled with a `/` like
+/// `tree -F` prints them.
+///
+/// # Example
+/// ```
+/// use graphs_tui::import::from_paths;
+/// use graphs_tui::{render_parsed, ParsedDiagram, RenderOptions};
+///
+/// let graph = from_paths(["./src/lib.rs", "./src/import/mod.rs", "./Cargo.toml"]);
+/// assert_eq!(graph.nodes["src/import"].label, "import/");
+/// assert_eq!(graph.edges.len(), 3);
+/// let result = render_parsed(ParsedDiagram::Flowchart(graph), RenderOptions::default()).unwrap();
+/// assert!(result.output.contains("lib.rs"));
+/// ```
+pub fn from_paths<I, P>(paths: I) -> Graph
+where
+    I: IntoIterator<Item = P>,
+    P: AsRef<Path>,
+{
+    let mut graph = Graph::new(Direction::TB);
+    let mut directories = HashSet::new();
+    for path in paths {
+        let path = path.as_ref();
+        let mut parent: Option<String> = None;
+        for component in path.components() {
+            let name = match component {
+                Component::CurDir => continue,
+                Component::RootDir => "/".into(),
+                other => other.as_os_str().to_string_lossy(),
+            };
+            let id = match &parent {
+                Some(p) if p.ends_with('/') => format!("{}{}", p, name),
+                Some(p) => format!("{}/{}", p, name),
+                None => name.to_string(),
+            };
+            if !graph.nodes.contains_key(&id) {
+                graph
+                    .nodes
+                    .insert(id.clone(), Node::new(id.clone(), name.to_string()));
+                if let Some(p) = &parent {
+                    graph.edges.push(Edge {
+                        from: p.clone(),
+                        to: id.clone(),
+                        style: EdgeStyle::Line,
+                        ..Default::default()
+                    });
+                }
+            }
+            if let Some(p) = parent.replace(id) {
+                directories.insert(p);
+            }
+        }
+        if let Some(last) = parent {
+            if path.to_string_lossy().ends_with('/') {
+                directories.insert(last);
+            }
+        }
+    }
+    for id in directories {
+        let node = graph.nodes.get_mut(&id).expect("directory has a node");
+        if !node.label.ends_with('/') {
+            node.label.push('/');
+        }
+    }
+    graph
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_containment_tree() {
+        let graph = from_paths(["src/", "src/main.rs", "./src/main.rs", "README.md", "docs/"]);
+        let mut labels: Vec<(&str, &str)> = graph
+            .nodes
+            .values()
+            .map(|n| (n.id.as_str(), n.label.as_str()))
+            .collect();
+        labels.sort();
+        assert_eq!(
+            labels,
+            vec![
+                ("README.md", "README.md"),
+                ("docs", "docs/"),
+                ("src", "src/"),
+                ("src/main.rs", "main.rs"),
+            ]
+        );
+        let edges: Vec<(&str, &str)> = graph
+            .edges
+            .iter()
+            .map(|e| (e.from.as_str(), e.to.as_str()))
+            .collect();
+        assert_eq!(edges, vec![("src", "src/main.rs")]);
+    }
+
+    #[test]
+    fn test_absolute_paths() {
+        let graph = from_paths(["/etc/hosts", "/etc/ssh/sshd_config"]);
+        assert_eq!(graph.nodes["/"].label, "/");
+        assert_eq!(graph.nodes["/etc"].label, "etc/");
+        assert_eq!(graph.nodes["/etc/ssh/sshd_config"].label, "sshd_config");
+        assert_eq!(graph.edges.len(), 4);
+        assert!(from_paths(Vec::<&str>::new()).nodes.is_empty());
+    }
+}
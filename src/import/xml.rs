@@ -0,0 +1,276 @@
+//! Network datasets from GraphML and GEXF files, behind the `xml` feature
+
+use std::collections::HashMap;
+
+use roxmltree::{Document, Node as XmlNode};
+
+use crate::error::MermaidError;
+use crate::types::{Direction, Edge, EdgeStyle, Graph, Node};
+
+/// Graph of a GraphML document's nodes and edges.
+///
+/// Labels come from `<data>` values whose `<key>` is named `label` (or
+/// `name`, for nodes), or from yEd's `NodeLabel` and `EdgeLabel`
+/// graphics; unlabeled nodes show their id. Undirected edges, per
+/// `edgedefault` or an edge's `directed` attribute, are drawn without
+/// arrowheads. Nodes of nested graphs are included alongside the rest.
+///
+/// # Example
+/// ```
+/// use graphs_tui::import::from_graphml;
+///
+/// let xml = r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+///   <key id="d0" for="node" attr.name="label" attr.type="string"/>
+///   <graph edgedefault="directed">
+///     <node id="n0"><data key="d0">Alice</data></node>
+///     <node id="n1"/>
+///     <edge source="n0" target="n1"/>
+///   </graph>
+/// </graphml>"#;
+/// let graph = from_graphml(xml).unwrap();
+/// assert_eq!(graph.nodes["n0"].label, "Alice");
+/// assert_eq!(graph.nodes["n1"].label, "n1");
+/// ```
+pub fn from_graphml(xml: &str) -> Result<Graph, MermaidError> {
+    let error = import_error("GraphML");
+    let doc = Document::parse(xml).map_err(|e| error(e.to_string()))?;
+    let root = doc.root_element();
+    if root.tag_name().name() != "graphml" {
+        return Err(error("expected a graphml root element".to_string()));
+    }
+
+    // Keys holding labels, by id, with whether they label nodes and edges
+    let mut label_keys: HashMap<&str, (bool, bool)> = HashMap::new();
+    for key in children(root, "key") {
+        let (Some(id), Some(target)) = (key.attribute("id"), key.attribute("for")) else {
+            continue;
+        };
+        let name = key.attribute("attr.name").unwrap_or_default();
+        let graphics = key.attribute("yfiles.type").unwrap_or_default();
+        let for_node = matches!(target, "node" | "all")
+            && (name.eq_ignore_ascii_case("label")
+                || name.eq_ignore_ascii_case("name")
+                || graphics == "nodegraphics");
+        let for_edge = matches!(target, "edge" | "all")
+            && (name.eq_ignore_ascii_case("label") || graphics == "edgegraphics");
+        if for_node || for_edge {
+            label_keys.insert(id, (for_node, for_edge));
+        }
+    }
+    let label = |element: XmlNode, edge: bool| {
+        children(element, "data")
+            .filter(|data| {
+                data.attribute("key")
+                    .and_then(|key| label_keys.get(key))
+                    .is_some_and(|&(for_node, for_edge)| if edge { for_edge } else { for_node })
+            })
+            .find_map(data_text)
+    };
+
+    let mut graph = Graph::new(Direction::LR);
+    for element in root.descendants() {
+        match element.tag_name().name() {
+            "node" => {
+                let id = element
+                    .attribute("id")
+                    .ok_or_else(|| error("node without an id".to_string()))?;
+                let text = label(element, false).unwrap_or_else(|| id.to_string());
+                graph
+                    .nodes
+                    .insert(id.to_string(), Node::new(id.to_string(), text));
+            }
+            "edge" => {
+                let (Some(from), Some(to)) =
+                    (element.attribute("source"), element.attribute("target"))
+                else {
+                    return Err(error("edge without a source and target".to_string()));
+                };
+                let graph_element = element.ancestors().find(|a| a.tag_name().name() == "graph");
+                let directed = match element.attribute("directed") {
+                    Some(directed) => directed == "true",
+                    None => {
+                        graph_element.and_then(|g| g.attribute("edgedefault")) != Some("undirected")
+                    }
+                };
+                add_edge(&mut graph, from, to, label(element, true), directed);
+            }
+            _ => {}
+        }
+    }
+    Ok(graph)
+}
+
+/// Graph of a GEXF document's nodes and edges.
+///
+/// Node and edge `label` attributes become labels; unlabeled nodes show
+/// their id. Undirected edges, per the graph's `defaultedgetype` or an
+/// edge's `type`, are drawn without arrowheads.
+///
+/// # Example
+/// ```
+/// use graphs_tui::import::from_gexf;
+///
+/// let xml = r#"<gexf xmlns="http://gexf.net/1.3" version="1.3">
+///   <graph defaultedgetype="undirected">
+///     <nodes><node id="0" label="Paris"/><node id="1" label="Lyon"/></nodes>
+///     <edges><edge id="0" source="0" target="1" label="TGV"/></edges>
+///   </graph>
+/// </gexf>"#;
+/// let graph = from_gexf(xml).unwrap();
+/// assert_eq!(graph.nodes["1"].label, "Lyon");
+/// assert_eq!(graph.edges[0].label.as_deref(), Some("TGV"));
+/// ```
+pub fn from_gexf(xml: &str) -> Result<Graph, MermaidError> {
+    let error = import_error("GEXF");
+    let doc = Document::parse(xml).map_err(|e| error(e.to_string()))?;
+    let root = doc.root_element();
+    let graph_element = children(root, "graph")
+        .next()
+        .filter(|_| root.tag_name().name() == "gexf")
+        .ok_or_else(|| error("expected a gexf root element with a graph".to_string()))?;
+    let undirected = graph_element.attribute("defaultedgetype") == Some("undirected");
+
+    let mut graph = Graph::new(Direction::LR);
+    for element in graph_element.descendants() {
+        match element.tag_name().name() {
+            "node" => {
+                let id = element
+                    .attribute("id")
+                    .ok_or_else(|| error("node without an id".to_string()))?;
+                let text = element.attribute("label").unwrap_or(id);
+                graph
+                    .nodes
+                    .insert(id.to_string(), Node::new(id.to_string(), text.to_string()));
+            }
+            "edge" => {
+                let (Some(from), Some(to)) =
+                    (element.attribute("source"), element.attribute("target"))
+                else {
+                    return Err(error("edge without a source and target".to_string()));
+                };
+                let directed = match element.attribute("type") {
+                    Some(kind) => kind != "undirected",
+                    None => !undirected,
+                };
+                let label = element.attribute("label").map(String::from);
+                add_edge(&mut graph, from, to, label, directed);
+            }
+            _ => {}
+        }
+    }
+    Ok(graph)
+}
+
+/// Error constructor for invalid `format` input
+fn import_error(format: &'static str) -> impl Fn(String) -> MermaidError {
+    move |message| MermaidError::ImportError {
+        format: format.to_string(),
+        message,
+    }
+}
+
+/// Child elements of `parent` with local name `name`, whatever the namespace
+fn children<'a, 'input>(
+    parent: XmlNode<'a, 'input>,
+    name: &'static str,
+) -> impl Iterator<Item = XmlNode<'a, 'input>> {
+    parent
+        .children()
+        .filter(move |child| child.tag_name().name() == name)
+}
+
+/// Text of a `<data>` element; for yEd graphics, the text of its
+/// `NodeLabel` or `EdgeLabel`
+fn data_text(data: XmlNode) -> Option<String> {
+    let text = match data
+        .descendants()
+        .find(|d| matches!(d.tag_name().name(), "NodeLabel" | "EdgeLabel"))
+    {
+        Some(label) => label.text(),
+        None if data.has_children() && data.first_element_child().is_none() => data.text(),
+        None => None,
+    };
+    Some(text?.trim().to_string()).filter(|t| !t.is_empty())
+}
+
+/// Add an edge, with nodes for endpoints the document never declared
+fn add_edge(graph: &mut Graph, from: &str, to: &str, label: Option<String>, directed: bool) {
+    for id in [from, to] {
+        graph
+            .nodes
+            .entry(id.to_string())
+            .or_insert_with(|| Node::new(id.to_string(), id.to_string()));
+    }
+    graph.edges.push(Edge {
+        from: from.to_string(),
+        to: to.to_string(),
+        label,
+        style: if directed {
+            EdgeStyle::Arrow
+        } else {
+            EdgeStyle::Line
+        },
+        ..Default::default()
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_graphml_labels_and_direction() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<graphml xmlns="http://graphml.graphdrawing.org/xmlns" xmlns:y="http://www.yworks.com/xml/graphml">
+  <key id="name" for="node" attr.name="Name" attr.type="string"/>
+  <key id="w" for="edge" attr.name="weight" attr.type="double"/>
+  <key id="el" for="edge" attr.name="label" attr.type="string"/>
+  <key id="g" for="node" yfiles.type="nodegraphics"/>
+  <graph id="G" edgedefault="undirected">
+    <node id="a"><data key="name"> Alpha </data></node>
+    <node id="b"><data key="g"><y:ShapeNode><y:NodeLabel>Beta</y:NodeLabel></y:ShapeNode></data></node>
+    <edge source="a" target="b"><data key="w">1.5</data><data key="el">link</data></edge>
+    <edge source="b" target="c" directed="true"/>
+  </graph>
+</graphml>"#;
+        let graph = from_graphml(xml).unwrap();
+        assert_eq!(graph.nodes["a"].label, "Alpha");
+        assert_eq!(graph.nodes["b"].label, "Beta");
+        assert_eq!(graph.nodes["c"].label, "c");
+        let edges: Vec<(&str, &str, Option<&str>, EdgeStyle)> = graph
+            .edges
+            .iter()
+            .map(|e| (e.from.as_str(), e.to.as_str(), e.label.as_deref(), e.style))
+            .collect();
+        assert_eq!(
+            edges,
+            vec![
+                ("a", "b", Some("link"), EdgeStyle::Line),
+                ("b", "c", None, EdgeStyle::Arrow),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_gexf_edge_types() {
+        let xml = r#"<gexf version="1.2"><graph defaultedgetype="directed">
+            <nodes><node id="x"/><node id="y" label="Why"/></nodes>
+            <edges><edge source="x" target="y"/><edge source="y" target="x" type="undirected"/></edges>
+        </graph></gexf>"#;
+        let graph = from_gexf(xml).unwrap();
+        assert_eq!(graph.nodes["x"].label, "x");
+        let styles: Vec<EdgeStyle> = graph.edges.iter().map(|e| e.style).collect();
+        assert_eq!(styles, vec![EdgeStyle::Arrow, EdgeStyle::Line]);
+    }
+
+    #[test]
+    fn test_rejects_other_documents() {
+        assert_eq!(
+            from_gexf("<graphml/>").unwrap_err().to_string(),
+            "Invalid GEXF input: expected a gexf root element with a graph"
+        );
+        assert!(from_graphml("<gexf><graph/></gexf>").is_err());
+        assert!(from_graphml("<graphml><graph><edge source=\"a\"/></graph></graphml>").is_err());
+        assert!(from_graphml("<graphml>").is_err());
+    }
+}
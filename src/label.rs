@@ -0,0 +1,149 @@
+//! HTML in label text, shared by every parser
+//!
+//! Mermaid renders labels as HTML, so diagrams written for it use `<br/>`
+//! for line breaks, `<b>` and `<i>` for emphasis and `&nbsp;` for spacing.
+//! A terminal has none of that: breaks become newlines, entities their
+//! characters, bold text is kept for the renderer to embolden and every
+//! other tag is dropped, keeping its text.
+
+use std::ops::Range;
+
+/// Tags dropped from labels; `<br>` and the bold tags are handled apart
+const INLINE_TAGS: &[&str] = &[
+    "a", "abbr", "big", "cite", "code", "del", "em", "font", "i", "ins", "kbd", "mark", "q", "s",
+    "samp", "small", "span", "strike", "sub", "sup", "tt", "u", "var",
+];
+
+/// Plain text of `label`, with the byte ranges of that text `<b>` or
+/// `<strong>` wrapped, in order. Text that only looks like a tag, such as
+/// `a < b` or `Vec<T>`, is kept as written.
+pub(crate) fn parse_html(label: &str) -> (String, Vec<Range<usize>>) {
+    let mut text = String::with_capacity(label.len());
+    let mut bold = Vec::new();
+    let mut bold_start: Option<usize> = None;
+    let mut rest = label;
+    while let Some(c) = rest.chars().next() {
+        if c == '<' {
+            if let Some((name, closing, len)) = tag(rest) {
+                match name.as_str() {
+                    "br" => text.push('\n'),
+                    "b" | "strong" if !closing => {
+                        bold_start.get_or_insert(text.len());
+                    }
+                    "b" | "strong" => {
+                        if let Some(start) = bold_start.take() {
+                            bold.push(start..text.len());
+                        }
+                    }
+                    _ => {}
+                }
+                rest = &rest[len..];
+                continue;
+            }
+        } else if c == '&' {
+            if let Some((decoded, len)) = entity(rest) {
+                text.push(decoded);
+                rest = &rest[len..];
+                continue;
+            }
+        }
+        text.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    // An unclosed `<b>` runs to the end
+    if let Some(start) = bold_start {
+        bold.push(start..text.len());
+    }
+    bold.retain(|range| !text[range.clone()].trim().is_empty());
+    (text, bold)
+}
+
+/// Plain text of `label`, for text drawn without emphasis
+pub(crate) fn strip_html(label: &str) -> String {
+    parse_html(label).0
+}
+
+/// Plain text of `label` for places drawn on one row, with line breaks as
+/// spaces
+pub(crate) fn strip_html_line(label: &str) -> String {
+    strip_html(label).replace('\n', " ")
+}
+
+/// Lowercased name, whether it closes, and byte length of the known tag
+/// `s` starts with, like `<br/>`, `</b>` or `<font color="red">`
+fn tag(s: &str) -> Option<(String, bool, usize)> {
+    let end = s.find('>')?;
+    let inner = &s[1..end];
+    if inner.contains('<') {
+        return None;
+    }
+    let (closing, inner) = match inner.strip_prefix('/') {
+        Some(inner) => (true, inner),
+        None => (false, inner),
+    };
+    let inner = inner.strip_suffix('/').unwrap_or(inner).trim_end();
+    let name_len = inner
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(inner.len());
+    let (name, attributes) = inner.split_at(name_len);
+    if !attributes.is_empty() && !attributes.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let name = name.to_ascii_lowercase();
+    let known = matches!(name.as_str(), "br" | "b" | "strong") || INLINE_TAGS.contains(&&*name);
+    known.then_some((name, closing, end + 1))
+}
+
+/// Character and byte length of the entity `s` starts with, like `&amp;`
+/// or `&#x2192;`
+fn entity(s: &str) -> Option<(char, usize)> {
+    let (end, _) = s.char_indices().take(12).find(|&(_, c)| c == ';')?;
+    let name = &s[1..end];
+    let c = match name {
+        "nbsp" | "ensp" | "emsp" => ' ',
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        _ => {
+            let code = name.strip_prefix('#')?;
+            let code = match code.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => code.parse().ok()?,
+            };
+            char::from_u32(code).filter(|c| !c.is_control() || *c == '\n')?
+        }
+    };
+    Some((c, end + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tags_and_entities() {
+        let (text, bold) = parse_html(
+            "<b>API</b><br/>Tom&nbsp;&amp;&#32;Jerry <font color=\"red\"><i>v2</i></font>",
+        );
+        assert_eq!(text, "API\nTom & Jerry v2");
+        assert_eq!(bold, vec![0..3]);
+        assert_eq!(strip_html("<STRONG>open<BR>end"), "open\nend");
+        assert_eq!(parse_html("<strong>a</strong> <b>b").1, vec![0..1, 2..3]);
+    }
+
+    #[test]
+    fn test_text_that_is_not_html() {
+        for label in [
+            "a < b",
+            "Vec<T> & more",
+            "x <bold> y",
+            "<b2>",
+            "&unknown; &#xZZ; &日本語日本語日本語日本語;",
+            "<br",
+        ] {
+            assert_eq!(parse_html(label), (label.to_string(), Vec::new()));
+        }
+    }
+}
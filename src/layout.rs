@@ -1,6 +1,11 @@
-use crate::text::display_width;
+use crate::budget::Budget;
+use crate::emoji::expand_shortcodes;
+use crate::error::MermaidError;
+use crate::renderer::{charset, custom_shape};
+use crate::text::{abbreviate_middle, display_width};
 use crate::types::{
-    DiagramWarning, Direction, Graph, NodeId, NodeShape, RenderOptions, TableField,
+    Alignment, DiagramWarning, Direction, Edge, Graph, Layering, LayoutAlgorithm, Node, NodeId,
+    NodeShape, RenderMode, RenderOptions, Subgraph, TableField, WidthStrategy,
 };
 use std::collections::{HashMap, HashSet, VecDeque};
 
@@ -9,6 +14,10 @@ const NODE_HEIGHT: usize = 3;
 const MIN_GAP: usize = 2;
 
 const SUBGRAPH_PADDING: usize = 2;
+/// Furthest a pin may place a node past the natural layout, in cells
+const MAX_PIN_MARGIN: usize = 200;
+/// Largest width or height a node may ask for, in cells
+pub(crate) const MAX_NODE_SIZE: usize = 200;
 
 /// Compute layout for all nodes in the graph
 ///
@@ -24,6 +33,219 @@ pub fn compute_layout_with_options(
     graph: &mut Graph,
     options: &RenderOptions,
 ) -> Vec<DiagramWarning> {
+    // An unlimited budget never runs out
+    layout_within(graph, options, &Budget::unlimited()).unwrap_or_default()
+}
+
+/// [`compute_layout_with_options`], failing with `TooLarge` once `budget`
+/// runs out, checked between the passes of the costlier steps
+pub(crate) fn layout_within(
+    graph: &mut Graph,
+    options: &RenderOptions,
+    budget: &Budget,
+) -> Result<Vec<DiagramWarning>, MermaidError> {
+    if let Some(direction) = options.direction_override {
+        graph.direction = direction;
+    }
+    expand_emoji(graph, options);
+    if options.number_nodes {
+        for (i, id) in graph.node_numbers().iter().enumerate() {
+            if let Some(node) = graph.nodes.get_mut(id) {
+                let prefix = format!("{}: ", i + 1);
+                for range in &mut node.bold {
+                    *range = range.start + prefix.len()..range.end + prefix.len();
+                }
+                node.label.insert_str(0, &prefix);
+            }
+        }
+    }
+
+    let orient = options.auto_orient && graph.direction.is_horizontal();
+    let abbreviate =
+        options.width_strategy == WidthStrategy::Abbreviate && options.mode == RenderMode::Diagram;
+    let unplaced = (orient || abbreviate).then(|| graph.clone());
+    let mut warnings = layout(graph, options, budget)?;
+    let Some(unplaced) = unplaced else {
+        return Ok(warnings);
+    };
+    if orient {
+        warnings = auto_orient(graph, unplaced.clone(), warnings, options, budget)?;
+    }
+    if abbreviate {
+        abbreviate_to_fit(graph, unplaced, &mut warnings, options, budget)?;
+    }
+    Ok(warnings)
+}
+
+/// Replace emoji shortcodes in labels before anything is sized: emoji when
+/// drawing with Unicode glyphs, `[name]` with ASCII ones
+fn expand_emoji(graph: &mut Graph, options: &RenderOptions) {
+    let ascii = charset(options).h.is_ascii();
+    for node in graph.nodes.values_mut() {
+        if node.bold.is_empty() {
+            node.label = expand_shortcodes(&node.label, ascii);
+            continue;
+        }
+        // Expand between bold boundaries so the ranges can be moved along
+        let mut label = String::new();
+        let mut bold = Vec::new();
+        let mut from = 0;
+        for range in &node.bold {
+            label.push_str(&expand_shortcodes(&node.label[from..range.start], ascii));
+            let start = label.len();
+            label.push_str(&expand_shortcodes(&node.label[range.clone()], ascii));
+            bold.push(start..label.len());
+            from = range.end;
+        }
+        label.push_str(&expand_shortcodes(&node.label[from..], ascii));
+        node.label = label;
+        node.bold = bold;
+    }
+    let labels = graph
+        .edges
+        .iter_mut()
+        .filter_map(|e| e.label.as_mut())
+        .chain(graph.subgraphs.iter_mut().map(|s| &mut s.label));
+    for label in labels {
+        *label = expand_shortcodes(label, ascii);
+    }
+}
+
+/// Shortest a label line is abbreviated to, e.g. `A…z`
+const MIN_ABBREVIATED_WIDTH: usize = 3;
+
+/// Tighten gaps, then shorten node labels with a middle '…' as little as
+/// possible, until the layout fits within `max_width`. Full labels are
+/// recorded in `graph.abbreviations`.
+fn abbreviate_to_fit(
+    graph: &mut Graph,
+    mut unplaced: Graph,
+    warnings: &mut Vec<DiagramWarning>,
+    options: &RenderOptions,
+    budget: &Budget,
+) -> Result<(), MermaidError> {
+    let Some(max_width) = options.max_width else {
+        return Ok(());
+    };
+    if extent(graph).0 <= max_width {
+        return Ok(());
+    }
+    unplaced.direction = graph.direction;
+    let tight = RenderOptions {
+        padding_x: options.padding_x.min(MIN_GAP),
+        ..options.clone()
+    };
+    // Layering doesn't depend on labels or gaps, so neither do the warnings
+    let attempt = |cap: usize| {
+        let mut attempt = unplaced.clone();
+        for node in attempt.nodes.values_mut().filter(|n| !n.verbatim) {
+            let label = node
+                .label
+                .split('\n')
+                .map(|line| abbreviate_middle(line, cap))
+                .collect::<Vec<_>>()
+                .join("\n");
+            // Bold spans no longer line up with a shortened label
+            if label != node.label {
+                node.bold.clear();
+            }
+            node.label = label;
+        }
+        layout(&mut attempt, &tight, budget)?;
+        Ok::<_, MermaidError>(attempt)
+    };
+
+    let widest = unplaced
+        .nodes
+        .values()
+        .filter(|n| !n.verbatim)
+        .flat_map(|n| n.label.split('\n'))
+        .map(display_width)
+        .max()
+        .unwrap_or(0);
+    // Largest label width that fits; labels only get shorter below it
+    let (mut lo, mut hi) = (MIN_ABBREVIATED_WIDTH, widest.max(MIN_ABBREVIATED_WIDTH));
+    let mut best = attempt(hi)?;
+    if extent(&best).0 > max_width {
+        best = attempt(lo)?;
+        while lo + 1 < hi {
+            let mid = (lo + hi) / 2;
+            let candidate = attempt(mid)?;
+            if extent(&candidate).0 <= max_width {
+                lo = mid;
+                best = candidate;
+            } else {
+                hi = mid;
+            }
+        }
+    }
+
+    let mut ids: Vec<&NodeId> = best.nodes.keys().collect();
+    ids.sort();
+    for id in ids {
+        let full = &unplaced.nodes[id].label;
+        if best.nodes[id].label != *full {
+            best.abbreviations.push((id.clone(), full.clone()));
+            warnings.push(DiagramWarning::LabelAbbreviated {
+                node_id: id.clone(),
+                label: full.clone(),
+            });
+        }
+    }
+    *graph = best;
+    Ok(())
+}
+
+/// Switch a horizontal layout wider than `max_width` to top-to-bottom,
+/// when that fits within `max_width` and `max_height`
+fn auto_orient(
+    graph: &mut Graph,
+    mut transposed: Graph,
+    warnings: Vec<DiagramWarning>,
+    options: &RenderOptions,
+    budget: &Budget,
+) -> Result<Vec<DiagramWarning>, MermaidError> {
+    let Some(max_width) = options.max_width else {
+        return Ok(warnings);
+    };
+    if extent(graph).0 <= max_width {
+        return Ok(warnings);
+    }
+    transposed.direction = Direction::TB;
+    let mut transposed_warnings = layout(&mut transposed, options, budget)?;
+    let (width, height) = extent(&transposed);
+    if width > max_width || options.max_height.is_some_and(|h| height > h) {
+        return Ok(warnings);
+    }
+    transposed_warnings.push(DiagramWarning::Reoriented {
+        from: graph.direction,
+        to: Direction::TB,
+    });
+    *graph = transposed;
+    Ok(transposed_warnings)
+}
+
+/// Columns and rows covered by the laid-out nodes and subgraphs
+fn extent(graph: &Graph) -> (usize, usize) {
+    let nodes = graph
+        .nodes
+        .values()
+        .map(|n| (n.x + n.width, n.y + n.height));
+    let subgraphs = graph
+        .subgraphs
+        .iter()
+        .map(|sg| (sg.x + sg.width, sg.y + sg.height));
+    nodes
+        .chain(subgraphs)
+        .fold((0, 0), |(w, h), (x, y)| (w.max(x), h.max(y)))
+}
+
+/// Size and place every node in the graph's own direction
+fn layout(
+    graph: &mut Graph,
+    options: &RenderOptions,
+    budget: &Budget,
+) -> Result<Vec<DiagramWarning>, MermaidError> {
     let mut warnings = Vec::new();
 
     // Border padding affects node width (text + 2*border_padding)
@@ -54,6 +276,31 @@ pub fn compute_layout_with_options(
         if node.shape == NodeShape::Document {
             node.height += 1;
         }
+        if node.shape == NodeShape::Oval {
+            node.width += 2;
+        }
+        if node.shape == NodeShape::DoubleCircle {
+            node.width += 2;
+            node.height += 2;
+        }
+        if node.shape == NodeShape::Step || node.shape == NodeShape::Asymmetric {
+            // Notch and point each take a column plus a space
+            node.width += 4;
+        }
+        if node.shape == NodeShape::Package || node.shape == NodeShape::Callout {
+            node.height += 1;
+        }
+        if node.shape == NodeShape::Callout {
+            // Room for the tail beside the bottom-left corner
+            node.width = node.width.max(7);
+        }
+        if node.shape == NodeShape::Compartment {
+            // A separator row between each pair of sections
+            node.height = 2 * line_count + 1;
+        }
+        if node.shape == NodeShape::Diamond {
+            (node.width, node.height) = diamond_size(&lines, text_padding);
+        }
         // sql_table/class with fields: header + separator + fields + border
         if node.shape == NodeShape::Table && !node.fields.is_empty() {
             // Width: max of label and all field lines
@@ -62,23 +309,94 @@ pub fn compute_layout_with_options(
                 node.width = node.width.max(field_len + 2 + text_padding); // 2 for borders + padding
             }
             // Height: top border + label row + separator + field rows + bottom border
-            node.height = 3 + node.fields.len(); // 3 = top + label + separator, then 1 per field, +1 bottom handled by renderer
+            node.height = 4 + node.fields.len();
+        }
+    }
+
+    for node in graph.nodes.values_mut() {
+        if let Some(shape) = node.custom_shape.as_deref().and_then(custom_shape) {
+            (node.width, node.height) = shape.size(&node.label);
+        }
+        let requested = options
+            .node_sizes
+            .get(&node.id)
+            .copied()
+            .or(node.fixed_size);
+        if let Some((width, height)) = requested {
+            if width > MAX_NODE_SIZE || height > MAX_NODE_SIZE {
+                warnings.push(DiagramWarning::NodeSizeClamped {
+                    node_id: node.id.clone(),
+                    width,
+                    height,
+                });
+            }
+            node.width = node.width.max(width.min(MAX_NODE_SIZE));
+            node.height = node.height.max(height.min(MAX_NODE_SIZE));
         }
     }
 
     // 2. Topological layering
-    let layers = assign_layers(graph, &mut warnings);
+    let mut layers = assign_layers(graph, &mut warnings);
+    if options.layering == Layering::TightTree {
+        tighten_layers(graph, &mut layers);
+    }
 
     // 3. Calculate gaps based on available width and user-specified padding
     let (h_gap, v_gap) = calculate_gaps(graph, &layers, options);
 
-    // 4. Position assignment based on direction with calculated gaps
-    assign_coordinates_with_gaps(graph, &layers, h_gap, v_gap);
+    // 4. Position assignment based on direction with calculated gaps, wider
+    // where subgraph borders fall between layers or nodes
+    assign_coordinates_with_gaps(graph, &layers, h_gap, v_gap, options);
+    if options.layout != LayoutAlgorithm::Layered {
+        for edge in &mut graph.edges {
+            edge.waypoints.clear();
+        }
+    }
+    match options.layout {
+        LayoutAlgorithm::Layered => {}
+        LayoutAlgorithm::Grid => place_clusters(graph, &layers, |g, l| {
+            assign_coordinates_grid(g, l, h_gap, v_gap);
+            Ok(())
+        })?,
+        LayoutAlgorithm::Radial => place_clusters(graph, &layers, |g, l| {
+            assign_coordinates_radial(g, l, h_gap, v_gap);
+            Ok(())
+        })?,
+        LayoutAlgorithm::Force => {
+            place_clusters(graph, &layers, |g, _| {
+                assign_coordinates_force(g, h_gap, v_gap, budget)
+            })?;
+            // Nothing was layered, so no cycle had to be broken
+            warnings.retain(|w| !matches!(w, DiagramWarning::CycleDetected { .. }));
+        }
+    }
+    move_to_origin(graph);
+    apply_pins(graph, &options.pinned_nodes, h_gap, v_gap);
+    separate_overlaps(graph, budget)?;
 
     // 5. Compute subgraph bounding boxes
     compute_subgraph_bounds(graph);
 
-    warnings
+    Ok(warnings)
+}
+
+/// Size of a rhombus that holds every label line inside its slanted sides.
+///
+/// The sides step in two columns per row, so a line `d` rows from the middle
+/// loses `4 * d` columns; the node grows taller until the widest line fits.
+/// Width is always `4 * m + 2` for `m` rows above the middle, giving a
+/// two-cell point at top and bottom.
+fn diamond_size(lines: &[&str], text_padding: usize) -> (usize, usize) {
+    // Line `middle` sits on the middle row (see `draw_label`)
+    let middle = lines.len() / 2;
+    let needed = lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| display_width(line) + text_padding + 2 + 4 * i.abs_diff(middle))
+        .max()
+        .unwrap_or(0);
+    let half_rows = (middle + 1).max(needed.saturating_sub(2).div_ceil(4));
+    (4 * half_rows + 2, 2 * half_rows + 1)
 }
 
 /// Calculate adaptive gaps based on available width and user options
@@ -88,7 +406,19 @@ fn calculate_gaps(
     options: &RenderOptions,
 ) -> (usize, usize) {
     let h_gap = options.padding_x;
-    let v_gap = options.padding_y;
+    // Multi-line edge labels stand beside vertical edges, clear of the arrow
+    let label_lines = graph
+        .edges
+        .iter()
+        .filter_map(|e| e.label.as_deref())
+        .map(|l| l.lines().count())
+        .max()
+        .unwrap_or(0);
+    let v_gap = if label_lines > 1 && !graph.direction.is_horizontal() {
+        options.padding_y.max(label_lines + 3)
+    } else {
+        options.padding_y
+    };
 
     let max_width = match options.max_width {
         Some(w) => w,
@@ -157,6 +487,644 @@ fn constraint_abbrev(constraint: &str) -> String {
     }
 }
 
+/// Run `place` with every top-level subgraph standing in as one node.
+///
+/// Each subgraph keeps the layered arrangement of its members (nested
+/// subgraphs included) and is sized to its drawn box, so `place` moves it
+/// as a rigid block and no other node lands inside its border. Edges into
+/// or out of a subgraph attach to its block, and the block sits in the
+/// earliest layer of its members.
+fn place_clusters(
+    graph: &mut Graph,
+    layers: &HashMap<NodeId, usize>,
+    place: impl FnOnce(&mut Graph, &HashMap<NodeId, usize>) -> Result<(), MermaidError>,
+) -> Result<(), MermaidError> {
+    let is_top = |sg: &Subgraph| {
+        sg.parent
+            .as_ref()
+            .is_none_or(|p| !graph.subgraphs.iter().any(|s| &s.id == p))
+    };
+    let tops: Vec<(String, Vec<NodeId>)> = graph
+        .subgraphs
+        .iter()
+        .filter(|sg| is_top(sg))
+        .map(|sg| (sg.id.clone(), endpoint_nodes(graph, &sg.id)))
+        .filter(|(_, members)| !members.is_empty())
+        .collect();
+    if tops.is_empty() {
+        return place(graph, layers);
+    }
+
+    // Measure each subgraph's box in the layered arrangement
+    move_to_origin(graph);
+    let mut placed = Graph::new(graph.direction);
+    let mut block_of: HashMap<NodeId, NodeId> = HashMap::new();
+    let mut blocks: Vec<(NodeId, usize, usize, Vec<NodeId>)> = Vec::new();
+    let mut placed_layers: HashMap<NodeId, usize> = HashMap::new();
+    for (sg_id, members) in tops {
+        let Some(sg) = graph.subgraphs.iter().find(|sg| sg.id == sg_id) else {
+            continue;
+        };
+        let members: Vec<NodeId> = members
+            .into_iter()
+            .filter(|id| !block_of.contains_key(id))
+            .collect();
+        if members.is_empty() {
+            continue;
+        }
+        // A subgraph may share its id with a node elsewhere
+        let mut block_id = sg.id.clone();
+        while graph.nodes.contains_key(&block_id) || placed.nodes.contains_key(&block_id) {
+            block_id.push('\'');
+        }
+        let mut block = Node::new(block_id.clone(), sg.label.clone());
+        block.width = sg.width;
+        block.height = sg.height;
+        placed.nodes.insert(block_id.clone(), block);
+        let layer = members.iter().filter_map(|id| layers.get(id)).min();
+        placed_layers.insert(block_id.clone(), layer.copied().unwrap_or(0));
+        for id in &members {
+            block_of.insert(id.clone(), block_id.clone());
+        }
+        blocks.push((block_id, sg.x, sg.y, members));
+    }
+    for (id, node) in &graph.nodes {
+        if !block_of.contains_key(id) {
+            placed.nodes.insert(id.clone(), node.clone());
+            placed_layers.insert(id.clone(), layers.get(id).copied().unwrap_or(0));
+        }
+    }
+    let outer = |id: &NodeId| block_of.get(id).unwrap_or(id).clone();
+    for (from, to) in layering_edges(graph) {
+        let (from, to) = (outer(&from), outer(&to));
+        if from != to {
+            placed.edges.push(Edge {
+                from,
+                to,
+                ..Default::default()
+            });
+        }
+    }
+
+    place(&mut placed, &placed_layers)?;
+
+    for (block_id, x, y, members) in blocks {
+        let block = &placed.nodes[&block_id];
+        let (bx, by) = (block.x, block.y);
+        for id in members {
+            if let Some(node) = graph.nodes.get_mut(&id) {
+                node.x = node.x - x + bx;
+                node.y = node.y - y + by;
+            }
+        }
+    }
+    // Block ids never match a node, so this only moves nodes outside subgraphs
+    for (id, node) in placed.nodes {
+        if let Some(original) = graph.nodes.get_mut(&id) {
+            (original.x, original.y) = (node.x, node.y);
+        }
+    }
+    Ok(())
+}
+
+/// Nodes in layer order, then by id
+fn layer_order(graph: &Graph, layers: &HashMap<NodeId, usize>) -> Vec<NodeId> {
+    let mut ids: Vec<NodeId> = graph.nodes.keys().cloned().collect();
+    ids.sort_by_key(|id| (layers.get(id).copied().unwrap_or(0), id.clone()));
+    ids
+}
+
+/// Lay nodes out in rows of equal cells, roughly as wide as tall
+fn assign_coordinates_grid(
+    graph: &mut Graph,
+    layers: &HashMap<NodeId, usize>,
+    h_gap: usize,
+    v_gap: usize,
+) {
+    let ids = layer_order(graph, layers);
+    let cell_w = graph.nodes.values().map(|n| n.width).max().unwrap_or(0) + h_gap;
+    let cell_h = graph.nodes.values().map(|n| n.height).max().unwrap_or(0) + v_gap;
+
+    // Terminal cells are about twice as tall as wide, so balance visually
+    let mut columns = 1;
+    while columns * columns * cell_w < ids.len() * cell_h * 2 {
+        columns += 1;
+    }
+
+    for (i, id) in ids.iter().enumerate() {
+        if let Some(node) = graph.nodes.get_mut(id) {
+            node.x = (i % columns) * cell_w;
+            node.y = (i / columns) * cell_h;
+        }
+    }
+}
+
+/// Put roots at the center and each later layer on a ring around them.
+///
+/// Layering edges form a spanning tree (each node hangs off its first
+/// parent in the previous layer), and every subtree gets a wedge of the
+/// circle in proportion to its leaves so children sit beside their parent.
+/// Rings are ellipses twice as wide as tall to look round in a terminal,
+/// and each is made large enough for its nodes to fit side by side.
+fn assign_coordinates_radial(
+    graph: &mut Graph,
+    layers: &HashMap<NodeId, usize>,
+    h_gap: usize,
+    v_gap: usize,
+) {
+    let ids = layer_order(graph, layers);
+    let layer_of = |id: &NodeId| layers.get(id).copied().unwrap_or(0);
+
+    // Spanning tree: parent is the first predecessor one layer up
+    let mut children: HashMap<&NodeId, Vec<&NodeId>> = HashMap::new();
+    let mut roots: Vec<&NodeId> = Vec::new();
+    let edges = layering_edges(graph);
+    for id in &ids {
+        let parent = edges
+            .iter()
+            .filter(|(from, to)| to == id && layer_of(from) + 1 == layer_of(id))
+            .map(|(from, _)| from)
+            .min();
+        match parent.and_then(|p| ids.iter().find(|i| *i == p)) {
+            Some(p) => children.entry(p).or_default().push(id),
+            None => roots.push(id),
+        }
+    }
+
+    fn leaves(id: &NodeId, children: &HashMap<&NodeId, Vec<&NodeId>>) -> usize {
+        children
+            .get(id)
+            .map(|kids| kids.iter().map(|k| leaves(k, children)).sum())
+            .unwrap_or(1)
+    }
+
+    // Angle for every node, by recursively splitting wedges
+    let mut angles: HashMap<&NodeId, f64> = HashMap::new();
+    let mut pending: Vec<(&NodeId, f64, f64)> = Vec::new();
+    let total: usize = roots.iter().map(|r| leaves(r, &children)).sum();
+    let mut start = 0.0;
+    for root in &roots {
+        let span = std::f64::consts::TAU * leaves(root, &children) as f64 / total as f64;
+        pending.push((root, start, span));
+        start += span;
+    }
+    while let Some((id, start, span)) = pending.pop() {
+        angles.insert(id, start + span / 2.0 - std::f64::consts::FRAC_PI_2);
+        let kids = children.get(id).cloned().unwrap_or_default();
+        let total: usize = kids.iter().map(|k| leaves(k, &children)).sum();
+        let mut kid_start = start;
+        for kid in kids {
+            let kid_span = span * leaves(kid, &children) as f64 / total as f64;
+            pending.push((kid, kid_start, kid_span));
+            kid_start += kid_span;
+        }
+    }
+
+    // Several roots share a ring of their own instead of piling up at the center
+    let ring_offset = usize::from(roots.len() > 1);
+    let ring_count = ids.iter().map(&layer_of).max().unwrap_or(0) + 1 + ring_offset;
+    let max_h = graph.nodes.values().map(|n| n.height).max().unwrap_or(0) as f64;
+    let step = max_h + v_gap as f64;
+    let mut radii = vec![0.0_f64; ring_count];
+    for ring in 1..ring_count {
+        // Circumference (in rows) needed for the ring's nodes side by side
+        let needed: f64 = ids
+            .iter()
+            .filter(|id| layer_of(id) + ring_offset == ring)
+            .map(|id| (graph.nodes[id].width + h_gap) as f64 / 2.0)
+            .sum();
+        radii[ring] = (radii[ring - 1] + step).max(needed / std::f64::consts::TAU);
+    }
+
+    let centers: Vec<(NodeId, f64, f64)> = ids
+        .iter()
+        .map(|id| {
+            let radius = radii[layer_of(id) + ring_offset];
+            let angle = angles.get(id).copied().unwrap_or(0.0);
+            (id.clone(), 2.0 * radius * angle.cos(), radius * angle.sin())
+        })
+        .collect();
+    place_centers(graph, &centers);
+    slide_apart(graph, Vec::new(), ids, true, h_gap, v_gap);
+}
+
+/// Spring embedding (Fruchterman-Reingold) from a circular start.
+///
+/// Edges pull their ends together and every pair of nodes pushes apart, so
+/// the result ignores edge direction. Deterministic for a given graph.
+fn assign_coordinates_force(
+    graph: &mut Graph,
+    h_gap: usize,
+    v_gap: usize,
+    budget: &Budget,
+) -> Result<(), MermaidError> {
+    const ITERATIONS: usize = 200;
+
+    let mut ids: Vec<NodeId> = graph.nodes.keys().cloned().collect();
+    ids.sort();
+    if ids.len() < 2 {
+        return Ok(());
+    }
+    let index: HashMap<&str, usize> = ids
+        .iter()
+        .enumerate()
+        .map(|(i, id)| (id.as_str(), i))
+        .collect();
+    let edges: Vec<(usize, usize)> = layering_edges(graph)
+        .iter()
+        .filter_map(|(from, to)| Some((*index.get(from.as_str())?, *index.get(to.as_str())?)))
+        .filter(|(a, b)| a != b)
+        .collect();
+
+    // Work in square units: one column is half a row wide
+    let ideal = ids
+        .iter()
+        .map(|id| {
+            let n = &graph.nodes[id];
+            ((n.width + h_gap) as f64 / 2.0).max((n.height + v_gap) as f64)
+        })
+        .sum::<f64>()
+        / ids.len() as f64;
+
+    // Start from a circle so the embedding can spread in both dimensions
+    let start_radius = ideal * ids.len() as f64 / std::f64::consts::TAU;
+    let mut pos: Vec<(f64, f64)> = (0..ids.len())
+        .map(|i| {
+            let angle = std::f64::consts::TAU * i as f64 / ids.len() as f64;
+            (start_radius * angle.cos(), start_radius * angle.sin())
+        })
+        .collect();
+
+    let mut temperature = ideal * 2.0;
+    let cooling = temperature / ITERATIONS as f64;
+    for _ in 0..ITERATIONS {
+        budget.check_time()?;
+        let mut disp = vec![(0.0_f64, 0.0_f64); ids.len()];
+        for i in 0..ids.len() {
+            for j in i + 1..ids.len() {
+                let (dx, dy, d) = separation(pos[i], pos[j], i, j);
+                let force = ideal * ideal / d;
+                disp[i].0 += dx / d * force;
+                disp[i].1 += dy / d * force;
+                disp[j].0 -= dx / d * force;
+                disp[j].1 -= dy / d * force;
+            }
+        }
+        for &(a, b) in &edges {
+            let (dx, dy, d) = separation(pos[a], pos[b], a, b);
+            let force = d * d / ideal;
+            disp[a].0 -= dx / d * force;
+            disp[a].1 -= dy / d * force;
+            disp[b].0 += dx / d * force;
+            disp[b].1 += dy / d * force;
+        }
+        for (p, (dx, dy)) in pos.iter_mut().zip(disp) {
+            let len = (dx * dx + dy * dy).sqrt().max(f64::EPSILON);
+            let step = len.min(temperature);
+            p.0 += dx / len * step;
+            p.1 += dy / len * step;
+        }
+        temperature -= cooling;
+    }
+
+    let centers: Vec<(NodeId, f64, f64)> = ids
+        .iter()
+        .zip(&pos)
+        .map(|(id, &(x, y))| (id.clone(), x * 2.0, y))
+        .collect();
+    place_centers(graph, &centers);
+    slide_apart(graph, Vec::new(), ids, true, h_gap, v_gap);
+    Ok(())
+}
+
+/// Offset from `b` to `a` and its length, nudging coincident nodes apart
+fn separation(a: (f64, f64), b: (f64, f64), i: usize, j: usize) -> (f64, f64, f64) {
+    let (mut dx, mut dy) = (a.0 - b.0, a.1 - b.1);
+    if dx.abs() < 0.01 && dy.abs() < 0.01 {
+        // Deterministic tie-break so stacked nodes don't stay stuck together
+        dx = (i as f64 - j as f64) * 0.1;
+        dy = 0.1;
+    }
+    (dx, dy, (dx * dx + dy * dy).sqrt())
+}
+
+/// Position nodes by their centers, shifted so nothing lands off-grid
+fn place_centers(graph: &mut Graph, centers: &[(NodeId, f64, f64)]) {
+    let corner = |id: &NodeId, cx: f64, cy: f64| {
+        let n = &graph.nodes[id];
+        (cx - n.width as f64 / 2.0, cy - n.height as f64 / 2.0)
+    };
+    let (min_x, min_y) = centers
+        .iter()
+        .map(|(id, cx, cy)| corner(id, *cx, *cy))
+        .fold((f64::MAX, f64::MAX), |(mx, my), (x, y)| {
+            (mx.min(x), my.min(y))
+        });
+    let corners: Vec<(NodeId, f64, f64)> = centers
+        .iter()
+        .map(|(id, cx, cy)| {
+            let (x, y) = corner(id, *cx, *cy);
+            (id.clone(), x - min_x, y - min_y)
+        })
+        .collect();
+    for (id, x, y) in corners {
+        if let Some(node) = graph.nodes.get_mut(&id) {
+            node.x = x.round() as usize;
+            node.y = y.round() as usize;
+        }
+    }
+}
+
+/// Move pinned nodes to their fixed positions and push the others clear.
+///
+/// Pins from `pins` override any set on the nodes themselves. Pins are
+/// clamped to [`MAX_PIN_MARGIN`] past the natural layout, so a stray
+/// coordinate can't blow up the drawing.
+fn apply_pins(
+    graph: &mut Graph,
+    pins: &HashMap<NodeId, (usize, usize)>,
+    h_gap: usize,
+    v_gap: usize,
+) {
+    for (id, &(x, y)) in pins {
+        if let Some(node) = graph.nodes.get_mut(id) {
+            node.pinned = (Some(x), Some(y));
+        }
+    }
+    let (width, height) = extent(graph);
+
+    // (x, y, width, height) of every node already in its final place; an
+    // axis without a pin keeps the laid-out coordinate
+    let mut placed: Vec<(usize, usize, usize, usize)> = Vec::new();
+    for node in graph.nodes.values_mut() {
+        if !node.is_pinned() {
+            continue;
+        }
+        let (x, y) = node.pinned;
+        node.x = x.map_or(node.x, |x| x.min(width + MAX_PIN_MARGIN));
+        node.y = y.map_or(node.y, |y| y.min(height + MAX_PIN_MARGIN));
+        placed.push((node.x, node.y, node.width, node.height));
+    }
+    if placed.is_empty() {
+        return;
+    }
+    // Nodes are about to move, so the reserved channels no longer line up
+    for edge in &mut graph.edges {
+        edge.waypoints.clear();
+    }
+
+    let vertical = !graph.direction.is_horizontal();
+    let free: Vec<NodeId> = graph
+        .nodes
+        .values()
+        .filter(|n| !n.is_pinned())
+        .map(|n| n.id.clone())
+        .collect();
+    slide_apart(graph, placed, free, vertical, h_gap, v_gap);
+}
+
+/// Place `free` nodes one at a time, sliding each past anything it overlaps.
+///
+/// Nodes are taken in reading order along the flow. A node that would
+/// overlap one already in `placed` slides across the flow (right when
+/// `vertical`, down otherwise) until it is clear.
+fn slide_apart(
+    graph: &mut Graph,
+    mut placed: Vec<(usize, usize, usize, usize)>,
+    mut free: Vec<NodeId>,
+    vertical: bool,
+    h_gap: usize,
+    v_gap: usize,
+) {
+    free.sort_by_key(|id| {
+        let n = &graph.nodes[id];
+        if vertical {
+            (n.y, n.x, id.clone())
+        } else {
+            (n.x, n.y, id.clone())
+        }
+    });
+
+    for id in free {
+        let Some(node) = graph.nodes.get_mut(&id) else {
+            continue;
+        };
+        // Each push moves strictly past one placed node, so this terminates
+        while let Some(&(x, y, w, h)) = placed.iter().find(|&&(x, y, w, h)| {
+            node.x < x + w + MIN_GAP
+                && x < node.x + node.width + MIN_GAP
+                && node.y < y + h + MIN_GAP
+                && y < node.y + node.height + MIN_GAP
+        }) {
+            if vertical {
+                node.x = x + w + h_gap;
+            } else {
+                node.y = y + h + v_gap;
+            }
+        }
+        placed.push((node.x, node.y, node.width, node.height));
+    }
+}
+
+/// Push apart any nodes whose boxes still overlap, so nothing is drawn on
+/// top of anything else.
+///
+/// Oversized nodes, like wide tables, can spill into a neighbouring layer.
+/// A node overlapping one in an earlier layer moves down the flow past it,
+/// taking every later node and waypoint along; one overlapping a node in its
+/// own layer moves across the flow with the rest of that layer. Pinned
+/// nodes never move.
+fn separate_overlaps(graph: &mut Graph, budget: &Budget) -> Result<(), MermaidError> {
+    let vertical = !graph.direction.is_horizontal();
+    // (main, cross, main size, cross size), with main along the flow
+    let axes = |n: &Node| {
+        if vertical {
+            (n.y, n.x, n.height, n.width)
+        } else {
+            (n.x, n.y, n.width, n.height)
+        }
+    };
+    let mut ids: Vec<NodeId> = graph.nodes.keys().cloned().collect();
+    ids.sort();
+
+    // Every move strictly clears one pair, so this bound is never reached
+    // in practice; it only guards against pins that can't be honoured
+    for _ in 0..ids.len() * ids.len() {
+        budget.check_time()?;
+        let overlap = ids.iter().enumerate().find_map(|(i, a)| {
+            ids[i + 1..].iter().find_map(|b| {
+                let (na, nb) = (&graph.nodes[a], &graph.nodes[b]);
+                let overlaps = na.x < nb.x + nb.width
+                    && nb.x < na.x + na.width
+                    && na.y < nb.y + nb.height
+                    && nb.y < na.y + na.height;
+                let movable = !na.is_pinned() || !nb.is_pinned();
+                (overlaps && movable).then_some((na, nb))
+            })
+        });
+        let Some((a, b)) = overlap else {
+            return Ok(());
+        };
+        // The later node moves, unless it is pinned
+        let (fixed, mover) = if b.is_pinned() || (!a.is_pinned() && axes(a) > axes(b)) {
+            (b, a)
+        } else {
+            (a, b)
+        };
+        let (f_main, f_cross, f_main_len, f_cross_len) = axes(fixed);
+        let (m_main, m_cross, m_main_len, _) = axes(mover);
+
+        if f_main != m_main {
+            let shift = f_main + f_main_len + MIN_GAP - m_main;
+            for node in graph.nodes.values_mut() {
+                if !node.is_pinned() && axes(node).0 >= m_main {
+                    *if vertical { &mut node.y } else { &mut node.x } += shift;
+                }
+            }
+            for (x, y) in graph.edges.iter_mut().flat_map(|e| e.waypoints.iter_mut()) {
+                let main = if vertical { y } else { x };
+                if *main >= m_main {
+                    *main += shift;
+                }
+            }
+        } else {
+            let shift = f_cross + f_cross_len + MIN_GAP - m_cross;
+            let in_layer = |main: usize, cross: usize| {
+                main >= m_main && main < m_main + m_main_len && cross >= m_cross
+            };
+            for node in graph.nodes.values_mut() {
+                let (main, cross, _, _) = axes(node);
+                if !node.is_pinned() && main == m_main && cross >= m_cross {
+                    *if vertical { &mut node.x } else { &mut node.y } += shift;
+                }
+            }
+            for (x, y) in graph.edges.iter_mut().flat_map(|e| e.waypoints.iter_mut()) {
+                let (main, cross) = if vertical { (*y, x) } else { (*x, y) };
+                if in_layer(main, *cross) {
+                    *cross += shift;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Deepest nesting of subgraphs that hold anything (and so get drawn)
+fn subgraph_depth(graph: &Graph) -> usize {
+    let parents: HashSet<&str> = graph
+        .subgraphs
+        .iter()
+        .filter_map(|sg| sg.parent.as_deref())
+        .collect();
+    let is_drawn = |id: &str| {
+        graph
+            .subgraphs
+            .iter()
+            .any(|sg| sg.id == id && (!sg.nodes.is_empty() || parents.contains(id)))
+    };
+
+    let mut max_depth = 0;
+    for sg in &graph.subgraphs {
+        let mut depth = 0;
+        let mut current = Some(sg);
+        let mut seen: HashSet<&str> = HashSet::new();
+        while let Some(s) = current {
+            if !seen.insert(s.id.as_str()) {
+                break; // Malformed parent cycle
+            }
+            if is_drawn(&s.id) {
+                depth += 1;
+            }
+            current = s
+                .parent
+                .as_deref()
+                .and_then(|p| graph.subgraphs.iter().find(|sg| sg.id == p));
+        }
+        max_depth = max_depth.max(depth);
+    }
+    max_depth
+}
+
+/// Translate nodes and waypoints so the drawing, subgraph borders included,
+/// starts at the top-left corner
+fn move_to_origin(graph: &mut Graph) {
+    // Subgraph bounds stop at zero, so measure them with room to spare
+    let depth = subgraph_depth(graph);
+    let widest_label = graph
+        .subgraphs
+        .iter()
+        .map(|sg| display_width(&sg.label))
+        .max()
+        .unwrap_or(0);
+    let margin = (
+        SUBGRAPH_PADDING * depth + widest_label + 4,
+        (SUBGRAPH_PADDING + 1) * depth,
+    );
+    shift(graph, margin, (0, 0));
+    compute_subgraph_bounds(graph);
+    let boxes = graph.nodes.values().map(|n| (n.x, n.y)).chain(
+        graph
+            .subgraphs
+            .iter()
+            .filter(|sg| sg.width > 0 && sg.height > 0)
+            .map(|sg| (sg.x, sg.y)),
+    );
+    let min_x = boxes.clone().map(|(x, _)| x).min().unwrap_or(0);
+    let min_y = boxes.map(|(_, y)| y).min().unwrap_or(0);
+    shift(graph, (0, 0), (min_x, min_y));
+}
+
+/// Move every node, waypoint and subgraph box by `add` less `sub`
+fn shift(graph: &mut Graph, add: (usize, usize), sub: (usize, usize)) {
+    let positions = graph
+        .nodes
+        .values_mut()
+        .map(|n| (&mut n.x, &mut n.y))
+        .chain(graph.subgraphs.iter_mut().map(|sg| (&mut sg.x, &mut sg.y)))
+        .chain(
+            graph
+                .edges
+                .iter_mut()
+                .flat_map(|e| e.waypoints.iter_mut())
+                .map(|(x, y)| (x, y)),
+        );
+    for (x, y) in positions {
+        *x = (*x + add.0).saturating_sub(sub.0);
+        *y = (*y + add.1).saturating_sub(sub.1);
+    }
+}
+
+/// Drawn subgraphs holding each node, innermost first
+fn subgraph_chains(graph: &Graph) -> HashMap<&str, Vec<&str>> {
+    let mut chains = HashMap::new();
+    for (id, node) in &graph.nodes {
+        let mut chain = Vec::new();
+        let mut current = node.subgraph.as_deref();
+        while let Some(sg_id) = current {
+            if chain.contains(&sg_id) {
+                break; // Malformed parent cycle
+            }
+            chain.push(sg_id);
+            current = graph
+                .subgraphs
+                .iter()
+                .find(|sg| sg.id == sg_id)
+                .and_then(|sg| sg.parent.as_deref());
+        }
+        chains.insert(id.as_str(), chain);
+    }
+    chains
+}
+
+/// Gap that leaves room for `borders` subgraph borders between two layers
+/// or neighbours. Each border takes `SUBGRAPH_PADDING` cells; counting
+/// `rows`, half of them (rounded up, as either side may be on top) take one
+/// more for a title. Two cells are left over for edges.
+fn border_gap(borders: usize, rows: bool) -> usize {
+    let titles = if rows { borders.div_ceil(2) } else { 0 };
+    2 + SUBGRAPH_PADDING * borders + titles
+}
+
 /// Compute bounding boxes for all subgraphs.
 /// Process leaf subgraphs first (those with no children), then parents,
 /// so parent bounds include child subgraph bounds.
@@ -227,9 +1195,13 @@ fn compute_subgraph_bounds(graph: &mut Graph) {
             }
 
             if min_x != usize::MAX {
-                graph.subgraphs[i].x = min_x.saturating_sub(SUBGRAPH_PADDING);
+                // Widen narrow boxes so the label fits on the top border
+                let content_w = (max_x - min_x) + SUBGRAPH_PADDING * 2;
+                let width = content_w.max(display_width(&graph.subgraphs[i].label) + 4);
+                let extra = width - content_w;
+                graph.subgraphs[i].x = min_x.saturating_sub(SUBGRAPH_PADDING + extra / 2);
                 graph.subgraphs[i].y = min_y.saturating_sub(SUBGRAPH_PADDING + 1);
-                graph.subgraphs[i].width = (max_x - min_x) + SUBGRAPH_PADDING * 2;
+                graph.subgraphs[i].width = width;
                 graph.subgraphs[i].height = (max_y - min_y) + SUBGRAPH_PADDING * 2 + 1;
             }
 
@@ -242,6 +1214,62 @@ fn compute_subgraph_bounds(graph: &mut Graph) {
     }
 }
 
+/// Distinct edges as node pairs for layering.
+///
+/// An endpoint naming a container (a subgraph with no node of its own)
+/// stands for every node inside it, so containers are ordered as a whole.
+pub(crate) fn layering_edges(graph: &Graph) -> Vec<(NodeId, NodeId)> {
+    let mut pairs = Vec::new();
+    let mut seen: HashSet<(NodeId, NodeId)> = HashSet::new();
+    for edge in &graph.edges {
+        let sources = endpoint_nodes(graph, &edge.from);
+        let targets = endpoint_nodes(graph, &edge.to);
+        for from in &sources {
+            for to in &targets {
+                // Expansion must not invent self-loops between a container and its member
+                if from == to && edge.from != edge.to {
+                    continue;
+                }
+                let pair = (from.clone(), to.clone());
+                if seen.insert(pair.clone()) {
+                    pairs.push(pair);
+                }
+            }
+        }
+    }
+    pairs
+}
+
+/// Nodes an edge endpoint refers to: the node itself, or a container's members
+pub(crate) fn endpoint_nodes(graph: &Graph, id: &str) -> Vec<NodeId> {
+    if graph.nodes.contains_key(id) {
+        return vec![id.to_string()];
+    }
+    let mut members = Vec::new();
+    let mut pending = vec![id];
+    let mut seen: HashSet<&str> = HashSet::new();
+    while let Some(sg_id) = pending.pop() {
+        if !seen.insert(sg_id) {
+            continue;
+        }
+        for sg in &graph.subgraphs {
+            if sg.id == sg_id {
+                members.extend(
+                    sg.nodes
+                        .iter()
+                        .filter(|n| graph.nodes.contains_key(*n))
+                        .cloned(),
+                );
+            } else if sg.parent.as_deref() == Some(sg_id) {
+                pending.push(&sg.id);
+            }
+        }
+    }
+    members.sort();
+    members.dedup();
+    members
+}
+
 /// Assign layer numbers using Kahn's algorithm with cycle-breaking.
 ///
 /// Standard Kahn's processes nodes with in_degree=0. When the queue empties
@@ -249,6 +1277,23 @@ fn compute_subgraph_bounds(graph: &mut Graph) {
 /// node that appears earliest as a "from" in the edge list (preserving the
 /// user's intended flow direction), then continue Kahn's.
 fn assign_layers(graph: &Graph, warnings: &mut Vec<DiagramWarning>) -> HashMap<NodeId, usize> {
+    let (_, node_layers) = kahn_layers(graph);
+
+    let cycles = graph.find_cycles();
+    if !cycles.is_empty() {
+        let mut nodes: Vec<NodeId> = cycles.iter().flatten().cloned().collect();
+        nodes.sort();
+        nodes.dedup();
+        warnings.push(DiagramWarning::CycleDetected { nodes, cycles });
+    }
+
+    node_layers
+}
+
+/// Kahn's algorithm with the cycle breaking described on [`assign_layers`]:
+/// nodes in the order they were processed, and each node's layer
+pub(crate) fn kahn_layers(graph: &Graph) -> (Vec<NodeId>, HashMap<NodeId, usize>) {
+    let mut order: Vec<NodeId> = Vec::new();
     let mut node_layers: HashMap<NodeId, usize> = HashMap::new();
     let mut in_degree: HashMap<NodeId, usize> = HashMap::new();
     let mut processed: HashSet<NodeId> = HashSet::new();
@@ -259,17 +1304,19 @@ fn assign_layers(graph: &Graph, warnings: &mut Vec<DiagramWarning>) -> HashMap<N
         node_layers.insert(id.clone(), 0);
     }
 
+    let edges = layering_edges(graph);
+
     // Count in-degrees
-    for edge in &graph.edges {
-        *in_degree.entry(edge.to.clone()).or_insert(0) += 1;
+    for (_, to) in &edges {
+        *in_degree.entry(to.clone()).or_insert(0) += 1;
     }
 
     // Build first-appearance-as-from index for deterministic cycle breaking.
     // Nodes that appear earlier as edge sources are treated as more "source-like"
     // when breaking cycles.
     let mut first_from_idx: HashMap<&str, usize> = HashMap::new();
-    for (i, edge) in graph.edges.iter().enumerate() {
-        first_from_idx.entry(edge.from.as_str()).or_insert(i);
+    for (i, (from, _)) in edges.iter().enumerate() {
+        first_from_idx.entry(from.as_str()).or_insert(i);
     }
 
     // Start with nodes that have no incoming edges (sorted for determinism)
@@ -285,7 +1332,6 @@ fn assign_layers(graph: &Graph, warnings: &mut Vec<DiagramWarning>) -> HashMap<N
     }
 
     let total = graph.nodes.len();
-    let mut all_cycle_nodes: HashSet<String> = HashSet::new();
 
     loop {
         // Standard Kahn's processing
@@ -294,13 +1340,13 @@ fn assign_layers(graph: &Graph, warnings: &mut Vec<DiagramWarning>) -> HashMap<N
                 continue;
             }
             processed.insert(u.clone());
+            order.push(u.clone());
 
             // Find neighbors, skipping already-processed nodes
-            let mut neighbors: Vec<NodeId> = graph
-                .edges
+            let mut neighbors: Vec<NodeId> = edges
                 .iter()
-                .filter(|e| e.from == u && !processed.contains(&e.to))
-                .map(|e| e.to.clone())
+                .filter(|(from, to)| *from == u && !processed.contains(to))
+                .map(|(_, to)| to.clone())
                 .collect();
             neighbors.sort();
             neighbors.dedup();
@@ -330,19 +1376,6 @@ fn assign_layers(graph: &Graph, warnings: &mut Vec<DiagramWarning>) -> HashMap<N
             .map(|(id, _)| id.clone())
             .collect();
 
-        // Record only nodes that have outgoing edges to other stuck nodes
-        // (actual cycle participants, not just downstream nodes)
-        let stuck_set: HashSet<&str> = stuck.iter().map(|s| s.as_str()).collect();
-        for n in &stuck {
-            let has_outgoing_to_stuck = graph
-                .edges
-                .iter()
-                .any(|e| e.from == *n && stuck_set.contains(e.to.as_str()));
-            if has_outgoing_to_stuck {
-                all_cycle_nodes.insert(n.clone());
-            }
-        }
-
         // Force-process the stuck node that appears earliest as an edge source
         stuck.sort_by(|a, b| {
             let fa = first_from_idx
@@ -362,113 +1395,381 @@ fn assign_layers(graph: &Graph, warnings: &mut Vec<DiagramWarning>) -> HashMap<N
         }
     }
 
-    if !all_cycle_nodes.is_empty() {
-        let mut cycle_nodes: Vec<String> = all_cycle_nodes.into_iter().collect();
-        cycle_nodes.sort();
-        warnings.push(DiagramWarning::CycleDetected { nodes: cycle_nodes });
+    (order, node_layers)
+}
+
+/// Shorten edges by moving nodes between the layers of their neighbours.
+///
+/// A node with more outgoing than incoming edges sinks toward its successors,
+/// one with more incoming rises toward its predecessors, until no move shortens
+/// the total edge length. Edges reversed by cycle breaking are ignored.
+fn tighten_layers(graph: &Graph, layers: &mut HashMap<NodeId, usize>) {
+    let edges: Vec<(NodeId, NodeId)> = layering_edges(graph)
+        .into_iter()
+        .filter(|(from, to)| layers[from] < layers[to])
+        .collect();
+    let mut ids: Vec<&NodeId> = graph.nodes.keys().collect();
+    ids.sort();
+
+    // Every move shortens the edges or, if neutral, sinks a node toward its
+    // successors, so this terminates
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for &id in &ids {
+            let preds: Vec<usize> = edges
+                .iter()
+                .filter(|(_, to)| to == id)
+                .map(|(from, _)| layers[from])
+                .collect();
+            let succs: Vec<usize> = edges
+                .iter()
+                .filter(|(from, _)| from == id)
+                .map(|(_, to)| layers[to])
+                .collect();
+            let current = layers[id];
+            // Balanced nodes sink too, so chains of them can follow a sinking source
+            let target = if preds.len() > succs.len() {
+                preds.iter().max().map(|&l| l + 1)
+            } else {
+                succs.iter().min().map(|&l| l - 1)
+            };
+            if let Some(target) = target.filter(|&t| t != current) {
+                layers.insert(id.clone(), target);
+                changed = true;
+            }
+        }
+    }
+
+    // Rising nodes may leave layer 0 empty
+    let min = layers.values().copied().min().unwrap_or(0);
+    for layer in layers.values_mut() {
+        *layer -= min;
     }
+}
 
-    node_layers
+/// An entry in a layer: a real node, or the channel reserved for an edge
+/// passing through the layer
+#[derive(Debug, Clone, PartialEq)]
+enum Slot {
+    Node(NodeId),
+    /// Index into `graph.edges`
+    Waypoint(usize),
 }
 
-/// Assign x,y coordinates based on layers and direction with configurable gaps
+/// Group nodes into their layers, adding a waypoint slot in every layer a
+/// forward edge skips.
+///
+/// Nodes within a layer are sorted by id, or by where they were across the
+/// flow in `previous` (see [`Node::stable_id`]). Each waypoint slot is
+/// placed where the edge crosses the layer if drawn straight from its
+/// source's relative position to its target's, so long edges keep to their
+/// own corridor.
+fn layer_slots(
+    graph: &Graph,
+    node_layers: &HashMap<NodeId, usize>,
+    previous: &HashMap<String, (usize, usize)>,
+) -> Vec<Vec<Slot>> {
+    let max_layer = node_layers.values().copied().max().unwrap_or(0);
+    let mut nodes_by_layer: Vec<Vec<NodeId>> = vec![Vec::new(); max_layer + 1];
+    for (id, &layer) in node_layers {
+        nodes_by_layer[layer].push(id.clone());
+    }
+    for nodes in &mut nodes_by_layer {
+        nodes.sort();
+        if !previous.is_empty() {
+            order_as_before(graph, nodes, previous);
+        }
+    }
+
+    // Position of a node across its layer, from 0 to 1
+    let relative = |id: &NodeId| {
+        let nodes = &nodes_by_layer[node_layers[id]];
+        let idx = nodes.iter().position(|n| n == id).unwrap_or(0);
+        (idx as f64 + 0.5) / nodes.len() as f64
+    };
+
+    let mut keyed: Vec<Vec<(f64, Slot)>> = nodes_by_layer
+        .iter()
+        .map(|nodes| {
+            nodes
+                .iter()
+                .enumerate()
+                .map(|(i, id)| (i as f64, Slot::Node(id.clone())))
+                .collect()
+        })
+        .collect();
+    for (i, edge) in graph.edges.iter().enumerate() {
+        let (Some(&from), Some(&to)) = (node_layers.get(&edge.from), node_layers.get(&edge.to))
+        else {
+            continue;
+        };
+        if to <= from + 1 {
+            continue;
+        }
+        let (rel_from, rel_to) = (relative(&edge.from), relative(&edge.to));
+        for layer in from + 1..to {
+            let t = (layer - from) as f64 / (to - from) as f64;
+            let rel = rel_from + (rel_to - rel_from) * t;
+            let key = rel * nodes_by_layer[layer].len() as f64 - 0.5;
+            keyed[layer].push((key, Slot::Waypoint(i)));
+        }
+    }
+
+    keyed
+        .into_iter()
+        .map(|mut slots| {
+            slots.sort_by(|a, b| a.0.total_cmp(&b.0));
+            slots.into_iter().map(|(_, slot)| slot).collect()
+        })
+        .collect()
+}
+
+/// Reorder a layer's id-sorted `nodes` by their positions across the flow
+/// in `previous`. New nodes follow the node before them by id, so an added
+/// node slots in without moving the others past each other.
+fn order_as_before(
+    graph: &Graph,
+    nodes: &mut Vec<NodeId>,
+    previous: &HashMap<String, (usize, usize)>,
+) {
+    let across = |&(x, y): &(usize, usize)| {
+        if graph.direction.is_horizontal() {
+            y
+        } else {
+            x
+        }
+    };
+    let mut last = 0;
+    let mut keyed: Vec<((usize, bool), NodeId)> = nodes
+        .drain(..)
+        .map(|id| {
+            let place = graph
+                .nodes
+                .get(&id)
+                .and_then(|n| previous.get(&n.stable_id()));
+            let key = match place {
+                Some(place) => {
+                    last = across(place);
+                    (last, false)
+                }
+                None => (last, true),
+            };
+            (key, id)
+        })
+        .collect();
+    // Stable, so ties keep id order
+    keyed.sort_by_key(|(key, _)| *key);
+    nodes.extend(keyed.into_iter().map(|(_, id)| id));
+}
+
+/// Assign x,y coordinates based on layers and direction with configurable gaps.
+///
+/// Edges spanning several layers get a one-cell waypoint in each layer they
+/// skip, recorded in [`Edge::waypoints`](crate::types::Edge::waypoints).
 fn assign_coordinates_with_gaps(
     graph: &mut Graph,
     node_layers: &HashMap<NodeId, usize>,
     h_gap: usize,
     v_gap: usize,
+    options: &RenderOptions,
 ) {
     let direction = graph.direction;
+    let align = options.align;
+    // Offset of a layer of size `len` within `total`
+    let offset = |total: usize, len: usize| {
+        let slack = total.saturating_sub(len);
+        match align {
+            Alignment::Start => 0,
+            Alignment::Center => slack / 2,
+            Alignment::End => slack,
+        }
+    };
 
-    // Group nodes by layer, sort within each layer for determinism
-    let mut layers_map: HashMap<usize, Vec<NodeId>> = HashMap::new();
-    let mut max_layer = 0;
-
-    for (id, &layer) in node_layers {
-        layers_map.entry(layer).or_default().push(id.clone());
-        max_layer = max_layer.max(layer);
-    }
-    for nodes in layers_map.values_mut() {
-        nodes.sort();
+    let layers = layer_slots(graph, node_layers, &options.previous_positions);
+    let max_layer = layers.len() - 1;
+
+    // Subgraph borders widen the gaps they fall in: between layers where a
+    // subgraph's first or last layer meets the next, and between neighbours
+    // in a layer that sit in different subgraphs
+    let chains = subgraph_chains(graph);
+    let slot_chain = |slot: &Slot| -> Vec<&str> {
+        match slot {
+            Slot::Node(id) => chains.get(id.as_str()).cloned().unwrap_or_default(),
+            Slot::Waypoint(edge) => {
+                let edge = &graph.edges[*edge];
+                let from = chains.get(edge.from.as_str());
+                let to = chains.get(edge.to.as_str());
+                match (from, to) {
+                    (Some(from), Some(to)) => {
+                        from.iter().filter(|sg| to.contains(sg)).copied().collect()
+                    }
+                    _ => Vec::new(),
+                }
+            }
+        }
+    };
+    let mut spans: HashMap<&str, (usize, usize)> = HashMap::new();
+    for (layer, slots) in layers.iter().enumerate() {
+        for sg in slots.iter().flat_map(&slot_chain) {
+            let span = spans.entry(sg).or_insert((layer, layer));
+            span.1 = layer;
+        }
     }
+    let (along_gap, across_gap, rows_along) = if direction.is_horizontal() {
+        (h_gap, v_gap, false)
+    } else {
+        (v_gap, h_gap, true)
+    };
+    // Gap after layer `l`, in layer order
+    let layer_gaps: Vec<usize> = (0..max_layer)
+        .map(|l| {
+            let borders = spans
+                .values()
+                .filter(|&&(first, last)| last == l || first == l + 1)
+                .count();
+            if borders == 0 {
+                along_gap
+            } else {
+                along_gap.max(border_gap(borders, rows_along))
+            }
+        })
+        .collect();
+    // Gap after each slot of each layer
+    let slot_gaps: Vec<Vec<usize>> = layers
+        .iter()
+        .map(|slots| {
+            let chains: Vec<Vec<&str>> = slots.iter().map(&slot_chain).collect();
+            chains
+                .iter()
+                .zip(chains.iter().skip(1))
+                .map(|(a, b)| {
+                    let borders = a.iter().filter(|sg| !b.contains(sg)).count()
+                        + b.iter().filter(|sg| !a.contains(sg)).count();
+                    if borders == 0 {
+                        across_gap
+                    } else {
+                        across_gap.max(border_gap(borders, !rows_along))
+                    }
+                })
+                .chain(std::iter::once(0))
+                .collect()
+        })
+        .collect();
+    let size = |slot: &Slot| match slot {
+        Slot::Node(id) => graph.nodes.get(id).map(|n| (n.width, n.height)),
+        Slot::Waypoint(_) => Some((1, 1)),
+    };
 
-    // Calculate layer dimensions
-    let mut layer_widths: HashMap<usize, usize> = HashMap::new();
-    let mut layer_heights: HashMap<usize, usize> = HashMap::new();
+    // Calculate layer dimensions; waypoints take no room along the flow
+    let mut layer_widths: Vec<usize> = Vec::with_capacity(layers.len());
+    let mut layer_heights: Vec<usize> = Vec::with_capacity(layers.len());
 
-    for l in 0..=max_layer {
-        let nodes_in_layer = layers_map.get(&l).map(|v| v.as_slice()).unwrap_or(&[]);
+    for (slots, gaps) in layers.iter().zip(&slot_gaps) {
         let mut max_w = 0;
         let mut max_h = 0;
         let mut total_w = 0;
         let mut total_h = 0;
 
-        for id in nodes_in_layer {
-            if let Some(node) = graph.nodes.get(id) {
-                max_w = max_w.max(node.width);
-                max_h = max_h.max(node.height);
-                total_w += node.width + h_gap;
-                total_h += node.height + v_gap;
+        for (slot, &gap) in slots.iter().zip(gaps) {
+            if let Some((w, h)) = size(slot) {
+                if matches!(slot, Slot::Node(_)) {
+                    max_w = max_w.max(w);
+                    max_h = max_h.max(h);
+                }
+                total_w += w + gap;
+                total_h += h + gap;
             }
         }
 
         if direction.is_horizontal() {
-            layer_widths.insert(l, max_w);
-            layer_heights.insert(l, total_h.saturating_sub(v_gap));
+            layer_widths.push(max_w);
+            layer_heights.push(total_h);
         } else {
-            layer_widths.insert(l, total_w.saturating_sub(h_gap));
-            layer_heights.insert(l, max_h);
+            layer_widths.push(total_w);
+            layer_heights.push(max_h);
         }
     }
 
-    let max_total_width = layer_widths.values().copied().max().unwrap_or(0);
-    let max_total_height = layer_heights.values().copied().max().unwrap_or(0);
+    let max_total_width = layer_widths.iter().copied().max().unwrap_or(0);
+    let max_total_height = layer_heights.iter().copied().max().unwrap_or(0);
+
+    // Gap from a layer to the next one placed, which comes before it for
+    // BT and RL
+    let gap_after = |layer_idx: usize| match direction {
+        Direction::BT | Direction::RL => layer_idx.checked_sub(1).map_or(0, |l| layer_gaps[l]),
+        _ => layer_gaps.get(layer_idx).copied().unwrap_or(0),
+    };
+
+    // Waypoint cell per (edge index, layer)
+    let mut waypoints: HashMap<(usize, usize), (usize, usize)> = HashMap::new();
 
     if direction.is_horizontal() {
         let mut current_x = 0;
         for l in 0..=max_layer {
             let layer_idx = match direction {
-                Direction::LR => l,
                 Direction::RL => max_layer - l,
                 _ => l,
             };
 
-            let nodes_in_layer = layers_map.get(&layer_idx).cloned().unwrap_or_default();
-            let layer_h = *layer_heights.get(&layer_idx).unwrap_or(&0);
-            let mut start_y = (max_total_height.saturating_sub(layer_h)) / 2;
-
-            for id in nodes_in_layer {
-                if let Some(node) = graph.nodes.get_mut(&id) {
-                    node.x = current_x;
-                    node.y = start_y;
-                    start_y += node.height + v_gap;
+            let layer_w = layer_widths[layer_idx];
+            let mut start_y = offset(max_total_height, layer_heights[layer_idx]);
+
+            for (slot, &gap) in layers[layer_idx].iter().zip(&slot_gaps[layer_idx]) {
+                match slot {
+                    Slot::Node(id) => {
+                        if let Some(node) = graph.nodes.get_mut(id) {
+                            node.x = current_x;
+                            node.y = start_y;
+                            start_y += node.height + gap;
+                        }
+                    }
+                    Slot::Waypoint(edge) => {
+                        waypoints.insert((*edge, layer_idx), (current_x + layer_w / 2, start_y));
+                        start_y += 1 + gap;
+                    }
                 }
             }
 
-            current_x += layer_widths.get(&layer_idx).unwrap_or(&0) + h_gap;
+            current_x += layer_w + gap_after(layer_idx);
         }
     } else {
         let mut current_y = 0;
         for l in 0..=max_layer {
             let layer_idx = match direction {
-                Direction::TB => l,
                 Direction::BT => max_layer - l,
                 _ => l,
             };
 
-            let nodes_in_layer = layers_map.get(&layer_idx).cloned().unwrap_or_default();
-            let layer_w = *layer_widths.get(&layer_idx).unwrap_or(&0);
-            let mut start_x = (max_total_width.saturating_sub(layer_w)) / 2;
-
-            for id in nodes_in_layer {
-                if let Some(node) = graph.nodes.get_mut(&id) {
-                    node.x = start_x;
-                    node.y = current_y;
-                    start_x += node.width + h_gap;
+            let layer_h = layer_heights[layer_idx];
+            let mut start_x = offset(max_total_width, layer_widths[layer_idx]);
+
+            for (slot, &gap) in layers[layer_idx].iter().zip(&slot_gaps[layer_idx]) {
+                match slot {
+                    Slot::Node(id) => {
+                        if let Some(node) = graph.nodes.get_mut(id) {
+                            node.x = start_x;
+                            node.y = current_y;
+                            start_x += node.width + gap;
+                        }
+                    }
+                    Slot::Waypoint(edge) => {
+                        waypoints.insert((*edge, layer_idx), (start_x, current_y + layer_h / 2));
+                        start_x += 1 + gap;
+                    }
                 }
             }
 
-            current_y += layer_heights.get(&layer_idx).unwrap_or(&0) + v_gap;
+            current_y += layer_h + gap_after(layer_idx);
+        }
+    }
+
+    for (i, edge) in graph.edges.iter_mut().enumerate() {
+        edge.waypoints.clear();
+        if let (Some(&from), Some(&to)) = (node_layers.get(&edge.from), node_layers.get(&edge.to)) {
+            edge.waypoints = (from + 1..to)
+                .filter_map(|layer| waypoints.get(&(i, layer)).copied())
+                .collect();
         }
     }
 }
@@ -477,6 +1778,7 @@ fn assign_coordinates_with_gaps(
 mod tests {
     use super::*;
     use crate::parser::parse_mermaid;
+    use crate::types::Node;
 
     #[test]
     fn test_layout_lr() {
@@ -502,6 +1804,345 @@ mod tests {
         assert!(warnings.is_empty());
     }
 
+    #[test]
+    fn test_duplicate_edges_no_cycle_warning() {
+        let mut graph = parse_mermaid("flowchart TB\nA --> B\nA --> B").unwrap();
+        let warnings = compute_layout(&mut graph);
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_align_start_and_end() {
+        let input = "flowchart TB\nA --> B\nA --> C";
+        let layout_with = |align| {
+            let mut graph = parse_mermaid(input).unwrap();
+            let options = RenderOptions {
+                align,
+                ..Default::default()
+            };
+            compute_layout_with_options(&mut graph, &options);
+            graph
+        };
+
+        let start = layout_with(Alignment::Start);
+        assert_eq!(start.nodes["A"].x, 0);
+        let end = layout_with(Alignment::End);
+        let c = &end.nodes["C"];
+        let a = &end.nodes["A"];
+        assert_eq!(a.x + a.width, c.x + c.width);
+    }
+
+    #[test]
+    fn test_tight_tree_layering_sinks_sources() {
+        let graph = parse_mermaid("flowchart TB\nA --> B\nB --> C\nC --> D\nX --> D").unwrap();
+        let mut warnings = Vec::new();
+        let mut layers = assign_layers(&graph, &mut warnings);
+        assert_eq!(layers["X"], 0);
+
+        tighten_layers(&graph, &mut layers);
+        assert_eq!(layers["X"], 2);
+        assert_eq!(layers["A"], 0);
+        assert_eq!(layers["D"], 3);
+    }
+
+    #[test]
+    fn test_tight_tree_layering_shortens_diagram() {
+        // Two roots feeding the same chain at different depths
+        let input = "flowchart TB\nA --> B\nB --> C\nC --> D\nP --> Q\nQ --> D";
+        let height = |layering| {
+            let mut graph = parse_mermaid(input).unwrap();
+            let options = RenderOptions {
+                layering,
+                ..Default::default()
+            };
+            compute_layout_with_options(&mut graph, &options);
+            let (p, q) = (&graph.nodes["P"], &graph.nodes["Q"]);
+            (q.y - p.y, graph.nodes["D"].y - q.y)
+        };
+        let (_, longest) = height(Layering::LongestPath);
+        let (pq, tight) = height(Layering::TightTree);
+        assert!(tight < longest);
+        assert_eq!(pq, tight);
+    }
+
+    #[test]
+    fn test_long_edge_gets_waypoint_per_skipped_layer() {
+        let mut graph = parse_mermaid("flowchart TB\nA --> B\nB --> C\nC --> D\nA --> D").unwrap();
+        compute_layout(&mut graph);
+        let long = graph
+            .edges
+            .iter()
+            .find(|e| e.to == "D" && e.from == "A")
+            .unwrap();
+        assert_eq!(long.waypoints.len(), 2);
+        for (&(x, y), id) in long.waypoints.iter().zip(["B", "C"]) {
+            let node = &graph.nodes[id];
+            // Level with the skipped node but beside it
+            assert!(y >= node.y && y < node.y + node.height);
+            assert!(x >= node.x + node.width || x < node.x);
+        }
+        assert!(graph
+            .edges
+            .iter()
+            .filter(|e| e.to != "D" || e.from != "A")
+            .all(|e| e.waypoints.is_empty()));
+    }
+
+    #[test]
+    fn test_diamond_grows_to_fit_label() {
+        // Short labels keep the three-row rhombus
+        assert_eq!(diamond_size(&["Ok"], 2), (6, 3));
+        // The middle row holds `width - 2` columns, so longer labels grow taller
+        let (w, h) = diamond_size(&["Decide"], 2);
+        assert_eq!((w, h), (10, 5));
+        assert!(w - 2 >= "Decide".len() + 2);
+        // A line above the middle loses four columns
+        let (w, h) = diamond_size(&["Is it", "valid"], 2);
+        assert_eq!(h, 2 * (w - 2) / 4 + 1);
+        assert!(w - 2 - 4 >= "Is it".len() + 2);
+    }
+
+    #[test]
+    fn test_verbatim_node_sized_to_block() {
+        let mut graph = Graph::new(Direction::TB);
+        let art = Node::with_verbatim("art".to_string(), " /\\\n/__\\\n |  |");
+        graph.nodes.insert("art".to_string(), art);
+        compute_layout(&mut graph);
+        let art = &graph.nodes["art"];
+        assert_eq!((art.width, art.height), (5 + 2, 3 + 2));
+    }
+
+    #[test]
+    fn test_pinned_node_keeps_position() {
+        let mut graph = parse_mermaid("flowchart TB\nA --> B\nA --> C").unwrap();
+        let mut options = RenderOptions::default();
+        // Pin B right where C would otherwise go
+        options.pinned_nodes.insert("B".to_string(), (13, 7));
+        compute_layout_with_options(&mut graph, &options);
+
+        let b = &graph.nodes["B"];
+        let c = &graph.nodes["C"];
+        assert_eq!((b.x, b.y), (13, 7));
+        // C slides right past B
+        assert_eq!(c.y, b.y);
+        assert!(c.x >= b.x + b.width + MIN_GAP);
+    }
+
+    #[test]
+    fn test_pin_on_one_axis_keeps_the_other() {
+        let input = "flowchart LR\nA --> B --> C";
+        let mut free = parse_mermaid(input).unwrap();
+        compute_layout(&mut free);
+        let mut graph = parse_mermaid(input).unwrap();
+        graph.nodes.get_mut("C").unwrap().pinned = (None, Some(9));
+        compute_layout(&mut graph);
+
+        let c = &graph.nodes["C"];
+        assert_eq!((c.x, c.y), (free.nodes["C"].x, 9));
+    }
+
+    #[test]
+    fn test_force_layout_stops_when_budget_runs_out() {
+        let mut graph = parse_mermaid("flowchart LR\nA --> B\nB --> C").unwrap();
+        let options = RenderOptions {
+            layout: LayoutAlgorithm::Force,
+            time_budget: Some(std::time::Duration::ZERO),
+            ..Default::default()
+        };
+        let budget = Budget::new(&options);
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        assert!(matches!(
+            layout_within(&mut graph, &options, &budget),
+            Err(MermaidError::TooLarge { ref resource, .. }) if resource == "milliseconds"
+        ));
+    }
+
+    #[test]
+    fn test_huge_node_size_is_clamped() {
+        let mut graph = parse_mermaid("flowchart LR\nA --> B").unwrap();
+        let options = RenderOptions {
+            node_sizes: HashMap::from([("A".to_string(), (3_000_000_000, 4))]),
+            ..Default::default()
+        };
+        let warnings = compute_layout_with_options(&mut graph, &options);
+        assert_eq!(graph.nodes["A"].width, MAX_NODE_SIZE);
+        assert!(warnings.contains(&DiagramWarning::NodeSizeClamped {
+            node_id: "A".to_string(),
+            width: 3_000_000_000,
+            height: 4,
+        }));
+    }
+
+    #[test]
+    fn test_huge_pin_is_clamped() {
+        let mut graph = parse_mermaid("flowchart LR\n%% @pin A 3000000000 30000\nA --> B").unwrap();
+        compute_layout(&mut graph);
+        let a = &graph.nodes["A"];
+        assert!(a.x <= 50 + MAX_PIN_MARGIN && a.y <= 10 + MAX_PIN_MARGIN);
+        assert_no_overlap(&graph);
+    }
+
+    #[test]
+    fn test_previous_positions_keep_order() {
+        let order = |input: &str, options: &RenderOptions| {
+            let mut graph = parse_mermaid(input).unwrap();
+            compute_layout_with_options(&mut graph, options);
+            let mut ids: Vec<&str> = graph.nodes.keys().map(String::as_str).collect();
+            ids.sort_by_key(|id| (graph.nodes[*id].y, graph.nodes[*id].x));
+            ids.into_iter().map(String::from).collect::<Vec<_>>()
+        };
+        let id = |id: &str| Node::new(id.to_string(), String::new()).stable_id();
+        // An earlier render that had C left of B
+        let options = RenderOptions {
+            previous_positions: HashMap::from([(id("B"), (20, 7)), (id("C"), (0, 7))]),
+            ..Default::default()
+        };
+
+        let input = "flowchart TB\nA --> B\nA --> C";
+        assert_eq!(order(input, &options), vec!["A", "C", "B"]);
+        // A new node goes after its neighbour by id
+        let input = "flowchart TB\nA --> B\nA --> C\nA --> D";
+        assert_eq!(order(input, &options), vec!["A", "C", "D", "B"]);
+        assert_eq!(
+            order(input, &RenderOptions::default()),
+            vec!["A", "B", "C", "D"]
+        );
+    }
+
+    #[test]
+    fn test_separate_overlaps() {
+        let mut graph = parse_mermaid("flowchart TB\nA --> C\nB --> D").unwrap();
+        // A is a wide table reaching down into C's layer and across into B
+        let place = |graph: &mut Graph, id: &str, x, y, width, height| {
+            let node = graph.nodes.get_mut(id).unwrap();
+            (node.x, node.y, node.width, node.height) = (x, y, width, height);
+        };
+        place(&mut graph, "A", 0, 0, 30, 8);
+        place(&mut graph, "B", 20, 0, 5, 3);
+        place(&mut graph, "C", 0, 6, 5, 3);
+        // D is pinned where C ends up once it clears A
+        place(&mut graph, "D", 3, 10, 5, 3);
+        graph.nodes.get_mut("D").unwrap().pinned = (Some(3), Some(10));
+        separate_overlaps(&mut graph, &Budget::unlimited()).unwrap();
+
+        assert_no_overlap(&graph);
+        let node = |id: &str| (graph.nodes[id].x, graph.nodes[id].y);
+        assert_eq!(node("A"), (0, 0));
+        assert_eq!(node("B"), (32, 0));
+        assert_eq!(node("C"), (10, 10));
+        assert_eq!(node("D"), (3, 10));
+    }
+
+    fn layout_with(input: &str, layout: LayoutAlgorithm) -> (Graph, Vec<DiagramWarning>) {
+        let mut graph = parse_mermaid(input).unwrap();
+        let options = RenderOptions {
+            layout,
+            ..Default::default()
+        };
+        let warnings = compute_layout_with_options(&mut graph, &options);
+        (graph, warnings)
+    }
+
+    fn assert_no_overlap(graph: &Graph) {
+        let nodes: Vec<_> = graph.nodes.values().collect();
+        for (i, a) in nodes.iter().enumerate() {
+            for b in &nodes[i + 1..] {
+                let apart = a.x + a.width <= b.x
+                    || b.x + b.width <= a.x
+                    || a.y + a.height <= b.y
+                    || b.y + b.height <= a.y;
+                assert!(apart, "{} overlaps {}", a.id, b.id);
+            }
+        }
+    }
+
+    #[test]
+    fn test_grid_layout_wraps_rows() {
+        let input = "flowchart LR\nA --> B\nB --> C\nC --> D\nD --> E";
+        let (graph, _) = layout_with(input, LayoutAlgorithm::Grid);
+        assert_no_overlap(&graph);
+        let rows: HashSet<usize> = graph.nodes.values().map(|n| n.y).collect();
+        assert!(rows.len() > 1);
+        assert_eq!((graph.nodes["A"].x, graph.nodes["A"].y), (0, 0));
+    }
+
+    #[test]
+    fn test_radial_layout_surrounds_root() {
+        let input = "flowchart TB\nR --> A\nR --> B\nR --> C\nR --> D";
+        let (graph, _) = layout_with(input, LayoutAlgorithm::Radial);
+        assert_no_overlap(&graph);
+        let root = &graph.nodes["R"];
+        let others: Vec<_> = graph.nodes.values().filter(|n| n.id != "R").collect();
+        assert!(others.iter().any(|n| n.y + n.height <= root.y));
+        assert!(others.iter().any(|n| n.y >= root.y + root.height));
+        assert!(others.iter().any(|n| n.x + n.width <= root.x));
+        assert!(others.iter().any(|n| n.x >= root.x + root.width));
+    }
+
+    #[test]
+    fn test_force_layout_ignores_cycles() {
+        let input = "flowchart LR\nA --- B\nB --- C\nC --- A\nC --- D\nD --- A";
+        let (graph, warnings) = layout_with(input, LayoutAlgorithm::Force);
+        assert_no_overlap(&graph);
+        assert!(warnings.is_empty());
+        // Spread in two dimensions rather than a single row or column
+        let xs: HashSet<usize> = graph.nodes.values().map(|n| n.x).collect();
+        let ys: HashSet<usize> = graph.nodes.values().map(|n| n.y).collect();
+        assert!(xs.len() > 1 && ys.len() > 1);
+    }
+
+    /// No node outside a subgraph falls inside its box, and the drawing
+    /// starts at the origin
+    fn assert_subgraphs_kept_together(graph: &Graph) {
+        for sg in &graph.subgraphs {
+            let members = endpoint_nodes(graph, &sg.id);
+            for node in graph.nodes.values() {
+                let inside = node.x < sg.x + sg.width
+                    && sg.x < node.x + node.width
+                    && node.y < sg.y + sg.height
+                    && sg.y < node.y + node.height;
+                assert_eq!(
+                    inside,
+                    members.contains(&node.id),
+                    "{} in {}",
+                    node.id,
+                    sg.id
+                );
+            }
+        }
+        let boxes = graph
+            .nodes
+            .values()
+            .map(|n| (n.x, n.y))
+            .chain(graph.subgraphs.iter().map(|sg| (sg.x, sg.y)));
+        assert_eq!(boxes.clone().map(|(x, _)| x).min(), Some(0));
+        assert_eq!(boxes.map(|(_, y)| y).min(), Some(0));
+    }
+
+    /// Subgraph members spread across branches and layers
+    const CLUSTERED: &str = "flowchart TB\nsubgraph S[Services]\nA\nF\nsubgraph T[Inner]\nC\nend\nend\nR --> A\nR --> B\nR --> D\nB --> C\nD --> E\nE --> F\nA --- E";
+
+    #[test]
+    fn test_grid_layout_keeps_subgraphs_together() {
+        let (graph, _) = layout_with(CLUSTERED, LayoutAlgorithm::Grid);
+        assert_no_overlap(&graph);
+        assert_subgraphs_kept_together(&graph);
+    }
+
+    #[test]
+    fn test_radial_layout_keeps_subgraphs_together() {
+        let (graph, _) = layout_with(CLUSTERED, LayoutAlgorithm::Radial);
+        assert_no_overlap(&graph);
+        assert_subgraphs_kept_together(&graph);
+    }
+
+    #[test]
+    fn test_force_layout_keeps_subgraphs_together() {
+        let (graph, _) = layout_with(CLUSTERED, LayoutAlgorithm::Force);
+        assert_no_overlap(&graph);
+        assert_subgraphs_kept_together(&graph);
+    }
+
     #[test]
     fn test_node_sizes() {
         let mut graph = parse_mermaid("flowchart LR\nA[Hello World]").unwrap();
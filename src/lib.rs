@@ -63,32 +63,76 @@
 //! let _ = render_diagram(d2_input, RenderOptions::default());
 //! ```
 
+mod analysis;
+mod budget;
+mod config;
 mod d2_parser;
+mod diff;
+mod emoji;
 mod error;
+mod export;
 mod grid;
+mod hooks;
+#[cfg(feature = "png")]
+pub mod image;
+pub mod import;
+mod json;
+mod label;
 mod layout;
+mod overlay;
 mod parser;
 mod pathfinding;
 mod pie_parser;
+mod registry;
 mod renderer;
+mod rich;
+mod sanitize;
 mod seq_parser;
+#[cfg(feature = "server")]
+pub mod server;
 mod state_parser;
+mod summary;
+mod symbols;
+mod term;
+pub mod testing;
 mod text;
+pub mod tools;
 mod types;
+mod validate;
 
+pub use budget::{limited_options, Budget};
+pub use config::Config;
+pub use diff::diff_lines;
 pub use error::MermaidError;
+pub use export::frames_to_asciicast;
+pub use hooks::{LayoutMetrics, ParseMetrics, RenderHooks};
 pub use layout::{compute_layout, compute_layout_with_options};
+pub use overlay::render_with_error_overlay;
+pub use pie_parser::{PieChart, PieSlice};
+pub use registry::{DiagramRenderer, Registry};
+pub use renderer::{register_shape, CharSet, ShapeRenderer};
+pub use rich::{render_rich, RichOutput};
+pub use seq_parser::{
+    ArrowStyle, Fragment, FragmentKind, FragmentSection, Message, Note, NotePosition, Participant,
+    SequenceDiagram, SequenceItem,
+};
+pub use symbols::{extract_symbols, Symbol, SymbolKind};
+pub use term::{ColorDepth, TermCaps};
 pub use types::{
-    DiagramWarning, Direction, Edge, EdgeStyle, Graph, Node, NodeId, NodeShape, RenderOptions,
-    RenderResult, Subgraph, TableField,
+    Alignment, Arrowhead, DiagramWarning, Direction, Drawing, Edge, EdgeLabels, EdgeRouting,
+    EdgeStyle, Graph, LabelAlong, LabelPlacement, LabelSide, Layering, LayoutAlgorithm,
+    LegendPosition, LineEnding, Node, NodeId, NodeShape, RenderMode, RenderOptions,
+    RenderOptionsBuilder, RenderResult, SourceMap, Subgraph, TableField, WidthStrategy,
 };
+pub use validate::{validate, Diagnostic, Severity};
 
-use d2_parser::{parse_d2, D2ParseResult};
+use d2_parser::{parse_d2, parse_d2_frames, D2ParseResult};
 use parser::parse_mermaid;
-use pie_parser::{parse_pie_chart as parse_pie, render_pie_chart as render_pie};
-use renderer::render_graph;
-use seq_parser::{parse_sequence_diagram as parse_seq, render_sequence_diagram as render_seq};
-use state_parser::parse_state_diagram;
+use pie_parser::parse_pie_chart as parse_pie;
+use registry::{render_model, FlowchartRenderer, PieRenderer, SequenceRenderer};
+use sanitize::render_sanitized;
+use seq_parser::parse_sequence_diagram as parse_seq;
+use state_parser::{parse_state_diagram, parse_state_diagram_with_warnings};
 
 /// Languages supported by graphs-tui.
 ///
@@ -151,12 +195,114 @@ pub fn detect_format(input: &str) -> DiagramFormat {
     DiagramFormat::D2
 }
 
+/// A parsed diagram model, one variant per supported diagram kind
+#[derive(Debug, Clone)]
+pub enum ParsedDiagram {
+    /// Mermaid flowchart
+    Flowchart(Graph),
+    /// Mermaid state diagram
+    State(Graph),
+    /// Mermaid sequence diagram
+    Sequence(SequenceDiagram),
+    /// Mermaid pie chart
+    Pie(PieChart),
+    /// D2 diagram
+    D2(Graph),
+}
+
+/// Parse arbitrary bytes with format auto-detection.
+///
+/// This is the fuzzing entry point: it covers all five parsers and never
+/// panics, returning `MermaidError` for anything it cannot parse
+/// (including invalid UTF-8).
+///
+/// # Example
+/// ```
+/// use graphs_tui::{parse_any, ParsedDiagram};
+///
+/// let parsed = parse_any(b"flowchart LR\nA --> B").unwrap();
+/// assert!(matches!(parsed, ParsedDiagram::Flowchart(_)));
+/// assert!(parse_any(&[0xff, 0xfe]).is_err());
+/// ```
+pub fn parse_any(input: &[u8]) -> Result<ParsedDiagram, MermaidError> {
+    let input = std::str::from_utf8(input).map_err(|e| {
+        let valid = &input[..e.valid_up_to()];
+        MermaidError::ParseError {
+            line: valid.iter().filter(|&&b| b == b'\n').count() + 1,
+            message: "Input is not valid UTF-8".to_string(),
+            suggestion: None,
+        }
+    })?;
+    parse_diagram(input)
+}
+
+/// Parse a diagram with format auto-detection, without rendering it.
+///
+/// The model can be inspected or changed before handing it to
+/// [`render_parsed`], e.g. to put live metrics into node labels.
+///
+/// # Example
+/// ```
+/// use graphs_tui::{parse_diagram, render_parsed, ParsedDiagram, RenderOptions};
+///
+/// let mut parsed = parse_diagram("flowchart LR\nA[api] --> B[db]").unwrap();
+/// if let ParsedDiagram::Flowchart(graph) = &mut parsed {
+///     graph.nodes.get_mut("A").unwrap().label = "api 42 rps".to_string();
+/// }
+/// let result = render_parsed(parsed, RenderOptions::default()).unwrap();
+/// assert!(result.output.contains("api 42 rps"));
+/// ```
+pub fn parse_diagram(input: &str) -> Result<ParsedDiagram, MermaidError> {
+    if input.trim().is_empty() {
+        return Err(MermaidError::EmptyInput);
+    }
+    Ok(match detect_format(input) {
+        DiagramFormat::Mermaid => ParsedDiagram::Flowchart(parse_mermaid(input)?),
+        DiagramFormat::StateDiagram => ParsedDiagram::State(parse_state_diagram(input)?),
+        DiagramFormat::SequenceDiagram => ParsedDiagram::Sequence(parse_seq(input)?),
+        DiagramFormat::PieChart => ParsedDiagram::Pie(parse_pie(input)?),
+        DiagramFormat::D2 => ParsedDiagram::D2(parse_d2(input)?.graph),
+    })
+}
+
+/// Render a diagram returned by [`parse_diagram`].
+///
+/// Graphs are laid out afresh, so nodes and edges may be added or removed
+/// before rendering. The source map is empty since the model may no
+/// longer match any source text.
+pub fn render_parsed(
+    diagram: ParsedDiagram,
+    options: RenderOptions,
+) -> Result<RenderResult, MermaidError> {
+    let budget = Budget::new(&options);
+    match diagram {
+        ParsedDiagram::Flowchart(graph)
+        | ParsedDiagram::State(graph)
+        | ParsedDiagram::D2(graph) => {
+            render_model(&FlowchartRenderer, graph, Vec::new(), &options, &budget)
+        }
+        ParsedDiagram::Sequence(diagram) => {
+            budget::check_counts(diagram.participants.len(), diagram.messages.len(), &options)?;
+            let warnings = diagram.warnings.clone();
+            render_model(&SequenceRenderer, diagram, warnings, &options, &budget)
+        }
+        ParsedDiagram::Pie(chart) => {
+            budget::check_counts(chart.slices.len(), 0, &options)?;
+            let warnings = chart.warnings.clone();
+            render_model(&PieRenderer, chart, warnings, &options, &budget)
+        }
+    }
+}
+
 /// Unified entry point — render a diagram by language name.
 ///
 /// Dispatches to the correct parser based on `lang`:
 /// - `"d2"` → D2 parser
 /// - `"mermaid"` (or any other value) → Mermaid auto-detect (flowchart, state, sequence, pie)
 ///
+/// With [`RenderOptions::sanitize`], chat model mistakes in `code` are
+/// repaired first and listed at the start of the warnings.
+///
 /// # Example
 /// ```
 /// use graphs_tui::{render, RenderOptions};
@@ -169,6 +315,11 @@ pub fn render(
     code: &str,
     options: RenderOptions,
 ) -> Result<RenderResult, MermaidError> {
+    if options.sanitize {
+        return render_sanitized(code, lang, options, |code, options| {
+            render(lang, code, options)
+        });
+    }
     match lang.to_lowercase().as_str() {
         "d2" => render_d2_to_tui(code, options),
         _ => render_diagram(code, options),
@@ -224,17 +375,12 @@ fn check_mermaid(code: &str) -> Result<Vec<DiagramWarning>, MermaidError> {
             Ok(compute_layout(&mut graph))
         }
         DiagramFormat::StateDiagram => {
-            let mut graph = parse_state_diagram(code)?;
-            Ok(compute_layout(&mut graph))
-        }
-        DiagramFormat::SequenceDiagram => {
-            parse_seq(code)?;
-            Ok(Vec::new())
-        }
-        DiagramFormat::PieChart => {
-            parse_pie(code)?;
-            Ok(Vec::new())
+            let (mut graph, mut warnings) = parse_state_diagram_with_warnings(code)?;
+            warnings.extend(compute_layout(&mut graph));
+            Ok(warnings)
         }
+        DiagramFormat::SequenceDiagram => Ok(parse_seq(code)?.warnings),
+        DiagramFormat::PieChart => Ok(parse_pie(code)?.warnings),
     }
 }
 
@@ -248,12 +394,146 @@ fn check_mermaid(code: &str) -> Result<Vec<DiagramWarning>, MermaidError> {
 /// * `Ok(RenderResult)` - Rendered diagram with any warnings
 /// * `Err(MermaidError)` - Parse or layout error
 pub fn render_diagram(input: &str, options: RenderOptions) -> Result<RenderResult, MermaidError> {
+    if options.sanitize {
+        return render_sanitized(input, "mermaid", options, render_diagram);
+    }
+    registry::builtin().render_detected(input, options)
+}
+
+/// Render a diagram that is still being written, e.g. streamed from a
+/// model token by token, so a TUI can redraw it as it grows.
+///
+/// Blocks still open at the end, like a subgraph without `end` or a D2
+/// container without `}`, are drawn as if closed. A last line without a
+/// newline is taken as unfinished: when it fails to parse or is skipped as
+/// invalid, the diagram is drawn without it. Before the first statement is
+/// complete there may be nothing to draw yet, and the error is returned.
+/// The [`RenderHooks`] run once, for the render that is returned.
+///
+/// # Example
+/// ```
+/// use graphs_tui::{render_partial, RenderOptions};
+///
+/// let streamed = "flowchart LR\nsubgraph Backend\nApi --> Db\nDb --> Cache[Red";
+/// let result = render_partial(streamed, RenderOptions::default()).unwrap();
+/// assert!(result.output.contains("Backend") && result.output.contains("Db"));
+/// assert!(!result.output.contains("Red"));
+/// ```
+pub fn render_partial(input: &str, options: RenderOptions) -> Result<RenderResult, MermaidError> {
+    let complete = &input[..input.rfind('\n').map_or(0, |i| i + 1)];
+    if complete.len() == input.len() {
+        return render_diagram(input, options);
+    }
+    let last_line = input.lines().count();
+    // Hold the hook calls back until we know which attempt is kept
+    let (recorder, recording) = options.hooks.recorder();
+    let attempt = RenderOptions {
+        hooks: recorder,
+        ..options.clone()
+    };
+    let result = match render_diagram(input, attempt.clone()) {
+        Ok(result)
+            if !result.warnings.iter().any(
+                |w| matches!(w, DiagramWarning::LineSkipped { line, .. } if *line == last_line),
+            ) =>
+        {
+            Ok(result)
+        }
+        full if complete.trim().is_empty() => full,
+        _ => {
+            recording.clear();
+            render_diagram(complete, attempt)
+        }
+    };
+    options.hooks.replay(&recording);
+    result
+}
+
+/// Render a graph diagram with the shortest path from `from` to `to`
+/// highlighted, e.g. to show how a request reaches the database.
+///
+/// The path's nodes get double borders and its edges are drawn thick (see
+/// [`RenderOptions::highlight_path`]). Fails with
+/// `MermaidError::InvalidOption` if either node is missing, there is no
+/// path, or the input is a sequence diagram or pie chart.
+///
+/// # Example
+/// ```
+/// use graphs_tui::{render_with_path, RenderOptions};
+///
+/// let input = "flowchart LR\nUser --> Web --> DB\nUser --> Cache";
+/// let result = render_with_path(input, "User", "DB", RenderOptions::default()).unwrap();
+/// assert!(result.output.contains("║User║"));
+/// assert!(result.output.contains("═▶"));
+/// ```
+pub fn render_with_path(
+    input: &str,
+    from: &str,
+    to: &str,
+    options: RenderOptions,
+) -> Result<RenderResult, MermaidError> {
+    if options.sanitize {
+        return render_sanitized(input, "mermaid", options, |input, options| {
+            render_with_path(input, from, to, options)
+        });
+    }
+    if matches!(
+        detect_format(input),
+        DiagramFormat::SequenceDiagram | DiagramFormat::PieChart
+    ) {
+        return Err(MermaidError::InvalidOption {
+            option: "highlight_path".to_string(),
+            message: "only flowcharts, state diagrams and D2 graphs have paths".to_string(),
+        });
+    }
+    let options = RenderOptions {
+        highlight_path: Some((from.to_string(), to.to_string())),
+        ..options
+    };
+    render_diagram(input, options)
+}
+
+/// Render a diagram as frames for playing it back as an animation.
+///
+/// D2 gives the base diagram, then one frame per `steps` board (each
+/// building on the steps before it) and one per `scenarios` board.
+/// Sequence diagrams give one frame per message, showing the conversation
+/// up to that message. Other diagrams give a single frame.
+///
+/// # Example
+/// ```
+/// use graphs_tui::{render_frames, RenderOptions};
+///
+/// let input = "sequenceDiagram\nAlice->>Bob: Hello\nBob-->>Alice: Hi";
+/// let frames = render_frames(input, RenderOptions::default()).unwrap();
+/// assert_eq!(frames.len(), 2);
+/// assert!(!frames[0].contains("Hi") && frames[1].contains("Hi"));
+/// ```
+pub fn render_frames(input: &str, options: RenderOptions) -> Result<Vec<String>, MermaidError> {
+    let repaired;
+    let input = if options.sanitize {
+        repaired = sanitize::sanitize(input, "mermaid").0;
+        &repaired
+    } else {
+        input
+    };
+    let options = RenderOptions {
+        sanitize: false,
+        ..options
+    };
+    let frame = |diagram: ParsedDiagram| Ok(render_parsed(diagram, options.clone())?.output);
     match detect_format(input) {
-        DiagramFormat::Mermaid => render_mermaid_to_tui(input, options),
-        DiagramFormat::StateDiagram => render_state_diagram(input, options),
-        DiagramFormat::SequenceDiagram => render_sequence_diagram(input, options),
-        DiagramFormat::PieChart => render_pie_chart(input, options),
-        DiagramFormat::D2 => render_d2_to_tui(input, options),
+        DiagramFormat::D2 => parse_d2_frames(input)?
+            .into_iter()
+            .map(|result| frame(ParsedDiagram::D2(result.graph)))
+            .collect(),
+        DiagramFormat::SequenceDiagram => {
+            let diagram = parse_seq(input)?;
+            (1..=diagram.messages.len().max(1))
+                .map(|count| frame(ParsedDiagram::Sequence(diagram.prefix(count))))
+                .collect()
+        }
+        _ => Ok(vec![render_diagram(input, options)?.output]),
     }
 }
 
@@ -270,12 +550,10 @@ pub fn render_mermaid_to_tui(
     input: &str,
     options: RenderOptions,
 ) -> Result<RenderResult, MermaidError> {
-    let mut graph = parse_mermaid(input)?;
-    let mut warnings = compute_layout_with_options(&mut graph, &options);
-    Ok(RenderResult {
-        output: render_graph(&graph, &options, &mut warnings),
-        warnings,
-    })
+    if options.sanitize {
+        return render_sanitized(input, "mermaid", options, render_mermaid_to_tui);
+    }
+    registry::builtin().render("flowchart", input, options)
 }
 
 /// Render mermaid state diagram to terminal-displayable text
@@ -291,12 +569,10 @@ pub fn render_state_diagram(
     input: &str,
     options: RenderOptions,
 ) -> Result<RenderResult, MermaidError> {
-    let mut graph = parse_state_diagram(input)?;
-    let mut warnings = compute_layout_with_options(&mut graph, &options);
-    Ok(RenderResult {
-        output: render_graph(&graph, &options, &mut warnings),
-        warnings,
-    })
+    if options.sanitize {
+        return render_sanitized(input, "mermaid", options, render_state_diagram);
+    }
+    registry::builtin().render("state", input, options)
 }
 
 /// Render mermaid pie chart to terminal-displayable text
@@ -311,11 +587,10 @@ pub fn render_state_diagram(
 /// * `Ok(RenderResult)` - Rendered chart with any warnings
 /// * `Err(MermaidError)` - Parse error
 pub fn render_pie_chart(input: &str, options: RenderOptions) -> Result<RenderResult, MermaidError> {
-    let chart = parse_pie(input)?;
-    Ok(RenderResult {
-        output: render_pie(&chart, &options),
-        warnings: Vec::new(),
-    })
+    if options.sanitize {
+        return render_sanitized(input, "mermaid", options, render_pie_chart);
+    }
+    registry::builtin().render("pie", input, options)
 }
 
 /// Render D2 diagram syntax to terminal-displayable text
@@ -328,15 +603,10 @@ pub fn render_pie_chart(input: &str, options: RenderOptions) -> Result<RenderRes
 /// * `Ok(RenderResult)` - Rendered diagram with any warnings
 /// * `Err(MermaidError)` - Parse or layout error
 pub fn render_d2_to_tui(input: &str, options: RenderOptions) -> Result<RenderResult, MermaidError> {
-    let D2ParseResult {
-        mut graph,
-        mut warnings,
-    } = parse_d2(input)?;
-    warnings.extend(compute_layout_with_options(&mut graph, &options));
-    Ok(RenderResult {
-        output: render_graph(&graph, &options, &mut warnings),
-        warnings,
-    })
+    if options.sanitize {
+        return render_sanitized(input, "d2", options, render_d2_to_tui);
+    }
+    registry::builtin().render("d2", input, options)
 }
 
 /// Render mermaid sequence diagram to terminal-displayable text
@@ -352,9 +622,17 @@ pub fn render_sequence_diagram(
     input: &str,
     options: RenderOptions,
 ) -> Result<RenderResult, MermaidError> {
-    let diagram = parse_seq(input)?;
-    Ok(RenderResult {
-        output: render_seq(&diagram, &options),
-        warnings: Vec::new(),
-    })
+    if options.sanitize {
+        return render_sanitized(input, "mermaid", options, render_sequence_diagram);
+    }
+    registry::builtin().render("sequence", input, options)
+}
+
+/// Numbering layout gave the graph's nodes, if any
+pub(crate) fn node_numbers(graph: &Graph, options: &RenderOptions) -> Vec<NodeId> {
+    if options.number_nodes {
+        graph.node_numbers()
+    } else {
+        Vec::new()
+    }
 }
@@ -0,0 +1,161 @@
+//! Best-effort rendering of diagrams that fail to parse, for live previews
+
+use crate::d2_parser::parse_d2;
+use crate::error::MermaidError;
+use crate::sanitize::sanitize;
+use crate::text::truncate_with_ellipsis;
+use crate::types::{
+    DiagramWarning, Direction, Graph, Node, NodeStyle, RenderOptions, RenderResult,
+};
+use crate::{parse_diagram, render, render_parsed, ParsedDiagram};
+
+/// Id and style class of the error node, chosen not to clash with
+/// diagram ids
+const ERROR_NODE: &str = "__error__";
+
+/// Columns the error message may take in its box
+const ERROR_WIDTH: usize = 60;
+
+/// Render a diagram, or if it fails to parse, the lines before the error
+/// with a `⚠ line 7: …` box drawn into the diagram, so a live preview
+/// doesn't go blank while the source is being typed.
+///
+/// `lang` is dispatched like [`render`]. In flowcharts, state diagrams and
+/// D2 the box is a node of its own; sequence diagrams and pie charts get it
+/// under the drawing. The parse error is also the first warning, as
+/// `DiagramWarning::LineSkipped`. Other errors, like `TooLarge`, are
+/// returned as they are.
+///
+/// # Example
+/// ```
+/// use graphs_tui::{render_with_error_overlay, RenderOptions};
+///
+/// let input = "flowchart LR\nA --> B\nB -->|oops C";
+/// let result = render_with_error_overlay("mermaid", input, RenderOptions::default()).unwrap();
+/// assert!(result.output.contains("│ B │"));
+/// assert!(result.output.contains("⚠ line 3"));
+/// ```
+pub fn render_with_error_overlay(
+    lang: &str,
+    code: &str,
+    options: RenderOptions,
+) -> Result<RenderResult, MermaidError> {
+    let (line, message) = match render(lang, code, options.clone()) {
+        Err(MermaidError::ParseError { line, message, .. }) => (line, message),
+        result => return result,
+    };
+    // Repairs keep line numbers, so the error line is the same either way
+    let (code, mut warnings) = if options.sanitize {
+        sanitize(code, lang)
+    } else {
+        (code.to_string(), Vec::new())
+    };
+
+    // The lines before the error, if they parse on their own
+    let prefix: Vec<&str> = code.lines().take(line.saturating_sub(1)).collect();
+    let prefix = prefix.join("\n");
+    let recovered = if prefix.trim().is_empty() {
+        None
+    } else if lang.eq_ignore_ascii_case("d2") {
+        parse_d2(&prefix)
+            .ok()
+            .map(|result| ParsedDiagram::D2(result.graph))
+    } else {
+        parse_diagram(&prefix).ok()
+    };
+
+    let marker = if options.ascii { "!" } else { "⚠" };
+    let add_error = |graph: &mut Graph| {
+        let label = format!("{} line {}: {}", marker, line, message);
+        let mut node = Node::new(
+            ERROR_NODE.to_string(),
+            truncate_with_ellipsis(&label, ERROR_WIDTH),
+        );
+        node.style_class = Some(ERROR_NODE.to_string());
+        graph.nodes.insert(ERROR_NODE.to_string(), node);
+        graph.style_classes.insert(
+            ERROR_NODE.to_string(),
+            NodeStyle {
+                color: Some("\x1b[38;2;255;85;85m".to_string()),
+            },
+        );
+    };
+    let mut result = match recovered {
+        Some(ParsedDiagram::Flowchart(mut graph)) => {
+            add_error(&mut graph);
+            render_parsed(ParsedDiagram::Flowchart(graph), options)?
+        }
+        Some(ParsedDiagram::State(mut graph)) => {
+            add_error(&mut graph);
+            render_parsed(ParsedDiagram::State(graph), options)?
+        }
+        Some(ParsedDiagram::D2(mut graph)) => {
+            add_error(&mut graph);
+            render_parsed(ParsedDiagram::D2(graph), options)?
+        }
+        other => {
+            let mut graph = Graph::new(Direction::TB);
+            add_error(&mut graph);
+            let error_box = render_parsed(ParsedDiagram::Flowchart(graph), options.clone())?;
+            match other {
+                Some(diagram) => {
+                    let ending = options.line_ending.as_str();
+                    let mut result = render_parsed(diagram, options)?;
+                    result.output = format!(
+                        "{}{}{}{}",
+                        result.output.trim_end_matches(['\r', '\n']),
+                        ending,
+                        ending,
+                        error_box.output
+                    );
+                    result
+                }
+                None => error_box,
+            }
+        }
+    };
+    warnings.push(DiagramWarning::LineSkipped { line, message });
+    result.warnings.splice(0..0, warnings);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines_before_error_are_drawn() {
+        let input = "flowchart LR\nA --> B\nB -->|oops C\nC --> D";
+        let result = render_with_error_overlay("mermaid", input, RenderOptions::default()).unwrap();
+        assert!(result.output.contains("│ B │"));
+        assert!(!result.output.contains("│ D │"));
+        assert!(result
+            .output
+            .contains("│⚠ line 3: Invalid syntax: \"|oops C\"│"));
+        assert_eq!(
+            result.warnings[0],
+            DiagramWarning::LineSkipped {
+                line: 3,
+                message: "Invalid syntax: \"|oops C\"".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_error_box_alone() {
+        let options = RenderOptions::builder().ascii(true).build().unwrap();
+        let result =
+            render_with_error_overlay("mermaid", "flowchart LR\nA --> B[", options).unwrap();
+        assert!(result.output.contains("! line 2:"));
+
+        let result =
+            render_with_error_overlay("mermaid", "flowcharts\nA --> B", RenderOptions::default())
+                .unwrap();
+        assert!(result.output.contains("⚠ line 1: Unsupported diagram type"));
+
+        // Valid input renders as usual
+        let plain = render_with_error_overlay("d2", "x -> y", RenderOptions::default()).unwrap();
+        assert!(plain.warnings.is_empty());
+        assert!(render_with_error_overlay("d2", "  ", RenderOptions::default()).is_err());
+    }
+}
@@ -1,13 +1,18 @@
-use winnow::ascii::{space0, space1, Caseless};
-use winnow::combinator::{alt, delimited};
+use std::ops::Range;
+
+use winnow::ascii::{dec_uint, space0, space1, Caseless};
+use winnow::combinator::{alt, delimited, eof, opt, peek, preceded, repeat};
 use winnow::error::{ErrMode, ParserError};
 use winnow::token::{rest, take_until, take_while};
 use winnow::ModalResult;
 use winnow::Parser;
 
 use crate::error::MermaidError;
+use crate::label::{parse_html, strip_html, strip_html_line};
+use crate::symbols::{find_word, source_lines, Symbol, SymbolKind};
+use crate::text::unescape;
 use crate::types::{
-    Direction, Edge, EdgeStyle, Graph, Node, NodeId, NodeShape, NodeStyle, Subgraph,
+    DiagramWarning, Direction, Edge, EdgeStyle, Graph, Node, NodeId, NodeShape, NodeStyle, Subgraph,
 };
 
 /// Content of a single line (after trimming)
@@ -31,18 +36,29 @@ enum MermaidLine {
 
 // ===== Winnow parsers =====
 
-/// Parse flowchart/graph keyword + direction
+/// Parse flowchart/graph keyword + direction, which defaults to TB;
+/// `flowchart-elk` (ELK layout) is read as a plain flowchart
 fn w_header(input: &mut &str) -> ModalResult<Direction> {
-    let _ = alt((Caseless("flowchart"), Caseless("graph"))).parse_next(input)?;
-    let _ = space1.parse_next(input)?;
-    alt((
-        "LR".value(Direction::LR),
-        "RL".value(Direction::RL),
-        "TB".value(Direction::TB),
-        "TD".value(Direction::TB),
-        "BT".value(Direction::BT),
+    let _ = alt((
+        Caseless("flowchart-elk"),
+        Caseless("flowchart"),
+        Caseless("graph"),
     ))
-    .parse_next(input)
+    .parse_next(input)?;
+    // The keyword must end here, not start a word like `graphql`
+    let _ = peek(alt((space1, ";", eof))).parse_next(input)?;
+    let direction = opt(preceded(
+        space1,
+        alt((
+            "LR".value(Direction::LR),
+            "RL".value(Direction::RL),
+            "TB".value(Direction::TB),
+            "TD".value(Direction::TB),
+            "BT".value(Direction::BT),
+        )),
+    ))
+    .parse_next(input)?;
+    Ok(direction.unwrap_or(Direction::TB))
 }
 
 /// Parse classDef: classDef name props...
@@ -79,7 +95,7 @@ fn w_subgraph(input: &mut &str) -> ModalResult<(String, String)> {
     // Check for label in brackets: ID [Label]
     if let Some(bracket_start) = rest_str.find('[') {
         let id = rest_str[..bracket_start].trim();
-        if let Some(bracket_end) = rest_str.rfind(']') {
+        if let Some(bracket_end) = rest_str.rfind(']').filter(|&end| end > bracket_start) {
             let label = &rest_str[bracket_start + 1..bracket_end];
             return Ok((id.to_string(), label.to_string()));
         }
@@ -97,10 +113,39 @@ fn w_subgraph(input: &mut &str) -> ModalResult<(String, String)> {
     Err(ErrMode::from_input(input))
 }
 
+/// Parse position pragma: %% @pin ID X Y
+fn w_pin_pragma(input: &mut &str) -> ModalResult<(String, (usize, usize))> {
+    let _ = "%%".parse_next(input)?;
+    let _ = space0.parse_next(input)?;
+    let _ = "@pin".parse_next(input)?;
+    let _ = space1.parse_next(input)?;
+    let id: &str = take_while(1.., |c: char| !c.is_whitespace()).parse_next(input)?;
+    let _ = space1.parse_next(input)?;
+    let x: usize = dec_uint.parse_next(input)?;
+    let _ = space1.parse_next(input)?;
+    let y: usize = dec_uint.parse_next(input)?;
+    let _ = space0.parse_next(input)?;
+    Ok((id.to_string(), (x, y)))
+}
+
+/// Parse metadata pragma: %% @meta ID KEY VALUE
+fn w_meta_pragma(input: &mut &str) -> ModalResult<(String, String, String)> {
+    let _ = "%%".parse_next(input)?;
+    let _ = space0.parse_next(input)?;
+    let _ = "@meta".parse_next(input)?;
+    let _ = space1.parse_next(input)?;
+    let id: &str = take_while(1.., |c: char| !c.is_whitespace()).parse_next(input)?;
+    let _ = space1.parse_next(input)?;
+    let key: &str = take_while(1.., |c: char| !c.is_whitespace()).parse_next(input)?;
+    let _ = space1.parse_next(input)?;
+    let value: &str = rest.parse_next(input)?;
+    Ok((id.to_string(), key.to_string(), value.trim().to_string()))
+}
+
 /// Parse edge label: |label|
 fn w_edge_label(input: &mut &str) -> ModalResult<String> {
     delimited('|', take_until(0.., "|"), '|')
-        .map(|s: &str| s.to_string())
+        .map(strip_html_line)
         .parse_next(input)
 }
 
@@ -108,6 +153,12 @@ fn w_edge_label(input: &mut &str) -> ModalResult<String> {
 fn classify_line(line: &str) -> Result<MermaidLine, MermaidError> {
     let trimmed = line.trim();
 
+    // Keywords are valid node ids: `class --> end` is an edge, not a class
+    // assignment
+    if starts_with_edge(trimmed) {
+        return Ok(MermaidLine::Content(trimmed.to_string()));
+    }
+
     // Try classdef (must be before class)
     let mut input = trimmed;
     if let Ok((name, style)) = w_classdef(&mut input) {
@@ -138,25 +189,80 @@ fn classify_line(line: &str) -> Result<MermaidLine, MermaidError> {
     Ok(MermaidLine::Content(trimmed.to_string()))
 }
 
+/// Whether a line opens with a single-word node followed by a link or `&`
+fn starts_with_edge(line: &str) -> bool {
+    let mut input = line;
+    let Ok(first) = w_node.parse_next(&mut input) else {
+        return false;
+    };
+    !first.contains(char::is_whitespace)
+        && preceded(
+            space0,
+            alt((
+                w_link.void(),
+                w_labeled_link.void(),
+                '&'.void(),
+                w_edge_id.void(),
+            )),
+        )
+        .parse_peek(input)
+        .is_ok()
+}
+
 // ===== Main parse function =====
 
+/// Class that draws a node as compartments, one per `<br/>`-separated line
+const COMPARTMENT_CLASS: &str = "compartment";
+
 /// Parse mermaid flowchart syntax into a Graph
 pub fn parse_mermaid(input: &str) -> Result<Graph, MermaidError> {
-    let lines: Vec<&str> = input
+    parse_flowchart(input, None)
+}
+
+/// Parse a flowchart, skipping lines that fail to parse and reporting
+/// them as warnings
+pub(crate) fn parse_mermaid_lenient(
+    input: &str,
+) -> Result<(Graph, Vec<DiagramWarning>), MermaidError> {
+    let mut warnings = Vec::new();
+    let graph = parse_flowchart(input, Some(&mut warnings))?;
+    Ok((graph, warnings))
+}
+
+/// Shared flowchart parser; content line errors go to `skipped` when given
+fn parse_flowchart(
+    input: &str,
+    mut skipped: Option<&mut Vec<DiagramWarning>>,
+) -> Result<Graph, MermaidError> {
+    // Position pragmas live in comments, so collect them before those are dropped
+    let pins: Vec<(String, (usize, usize))> = input
+        .lines()
+        .filter_map(|l| w_pin_pragma.parse(l.trim()).ok())
+        .collect();
+    let metadata: Vec<(String, String, String)> = input
         .lines()
-        .map(|l| l.trim())
-        .filter(|l| !l.is_empty() && !l.starts_with("%%"))
+        .filter_map(|l| w_meta_pragma.parse(l.trim()).ok())
+        .collect();
+
+    // (1-based source line, text), without the optional `;` ending a
+    // statement
+    let lines: Vec<(usize, &str)> = input
+        .lines()
+        .map(|l| l.trim().trim_end_matches(';').trim_end())
+        .enumerate()
+        .filter(|(_, l)| !l.is_empty() && !l.starts_with("%%"))
+        .map(|(i, l)| (i + 1, l))
         .collect();
 
     if lines.is_empty() {
         return Err(MermaidError::EmptyInput);
     }
 
-    let direction = parse_flowchart_header(lines[0])?;
+    let direction = parse_flowchart_header(lines[0].1)?;
     let mut graph = Graph::new(direction);
     let mut current_subgraph: Option<String> = None;
 
-    for (i, line) in lines.iter().enumerate().skip(1) {
+    for &(line_num, line) in &lines[1..] {
         match classify_line(line)? {
             MermaidLine::ClassDef { name, style } => {
                 graph.style_classes.insert(name, style);
@@ -173,17 +279,41 @@ pub fn parse_mermaid(input: &str) -> Result<Graph, MermaidError> {
             }
             MermaidLine::SubgraphStart { id, label } => {
                 current_subgraph = Some(id.clone());
-                graph.subgraphs.push(Subgraph::new(id, label));
+                graph.subgraphs.push(Subgraph::new(id, strip_html(&label)));
             }
             MermaidLine::SubgraphEnd => {
                 current_subgraph = None;
             }
             MermaidLine::Content(content) => {
-                parse_content_line(&mut graph, &content, i + 1, current_subgraph.as_deref())?;
+                let parsed =
+                    parse_content_line(&mut graph, &content, line_num, current_subgraph.as_deref());
+                match (parsed, skipped.as_deref_mut()) {
+                    (Err(MermaidError::ParseError { line, message, .. }), Some(skipped)) => {
+                        skipped.push(DiagramWarning::LineSkipped { line, message });
+                    }
+                    (result, _) => result?,
+                }
             }
         }
     }
 
+    for (id, pos) in pins {
+        if let Some(node) = graph.nodes.get_mut(&id) {
+            node.pinned = (Some(pos.0), Some(pos.1));
+        }
+    }
+    for (id, key, value) in metadata {
+        if let Some(node) = graph.nodes.get_mut(&id) {
+            node.metadata.insert(key, value);
+        }
+    }
+
+    for node in graph.nodes.values_mut() {
+        if node.style_class.as_deref() == Some(COMPARTMENT_CLASS) {
+            node.shape = NodeShape::Compartment;
+        }
+    }
+
     Ok(graph)
 }
 
@@ -192,137 +322,270 @@ fn parse_flowchart_header(line: &str) -> Result<Direction, MermaidError> {
     let mut input = line;
     w_header(&mut input).map_err(|_| MermaidError::ParseError {
         line: 1,
-        message: "Unsupported diagram type or missing direction".to_string(),
+        message: "Unsupported diagram type".to_string(),
         suggestion: Some("Use 'flowchart LR', 'graph TD', etc.".to_string()),
     })
 }
 
-// ===== Edge patterns =====
+// ===== Content line tokens =====
+
+/// Token of a content line
+#[derive(Debug, Clone, PartialEq)]
+enum ContentToken<'a> {
+    /// Node id with its optional shape and `:::class`
+    Node(&'a str),
+    Link(EdgeStyle),
+    /// `|label|` for the link before or after it
+    Label(String),
+    /// `&` between nodes sharing a link
+    Amp,
+    /// `e1@` naming the link right after it
+    EdgeId(&'a str),
+    /// Link with its label written inside it, `-- label -->`
+    LabeledLink(EdgeStyle, String),
+}
 
-/// Edge pattern with style
-struct EdgePattern {
-    pattern: &'static str,
-    style: EdgeStyle,
+/// Parse a link operator; longer forms like `---->` and `-..->` are allowed
+fn w_link(input: &mut &str) -> ModalResult<EdgeStyle> {
+    alt((
+        ('-', take_while(1.., '.'), "->").value(EdgeStyle::DottedArrow),
+        ('-', take_while(1.., '.'), '-').value(EdgeStyle::DottedLine),
+        (take_while(2.., '='), '>').value(EdgeStyle::ThickArrow),
+        take_while(3.., '=').value(EdgeStyle::ThickLine),
+        (take_while(2.., '-'), '>').value(EdgeStyle::Arrow),
+        take_while(3.., '-').value(EdgeStyle::Line),
+        take_while(3.., '~').value(EdgeStyle::Invisible),
+    ))
+    .parse_next(input)
 }
 
-const EDGE_PATTERNS: &[EdgePattern] = &[
-    // Order matters - check longer/more specific patterns first
-    EdgePattern {
-        pattern: "-.->",
-        style: EdgeStyle::DottedArrow,
-    },
-    EdgePattern {
-        pattern: "-.-",
-        style: EdgeStyle::DottedLine,
-    },
-    EdgePattern {
-        pattern: "==>",
-        style: EdgeStyle::ThickArrow,
-    },
-    EdgePattern {
-        pattern: "===",
-        style: EdgeStyle::ThickLine,
-    },
-    EdgePattern {
-        pattern: "-->",
-        style: EdgeStyle::Arrow,
-    },
-    EdgePattern {
-        pattern: "---",
-        style: EdgeStyle::Line,
-    },
-];
+/// Parse a link with its label inline: `-- label -->`, `-- label ---`,
+/// `-. label .->`, `-. label .-`, `== label ==>` or `== label ===`
+fn w_labeled_link(input: &mut &str) -> ModalResult<(EdgeStyle, String)> {
+    let opener = alt(("--", "-.", "==")).parse_next(input)?;
+    // The label runs to the first operator that closes this kind of link
+    let closes = |rest: &str| match opener {
+        "--" => matches!(
+            w_link.parse_peek(rest),
+            Ok((_, EdgeStyle::Arrow | EdgeStyle::Line))
+        ),
+        "-." => w_dotted_close.parse_peek(rest).is_ok(),
+        _ => matches!(
+            w_link.parse_peek(rest),
+            Ok((_, EdgeStyle::ThickArrow | EdgeStyle::ThickLine))
+        ),
+    };
+    let Some(end) = input
+        .char_indices()
+        .map(|(i, _)| i)
+        .find(|&i| closes(&input[i..]))
+    else {
+        return Err(ErrMode::from_input(input));
+    };
+    let label = input[..end].trim();
+    if label.is_empty() || label.contains('|') {
+        return Err(ErrMode::from_input(input));
+    }
+    let label = strip_html_line(
+        label
+            .strip_prefix('"')
+            .and_then(|l| l.strip_suffix('"'))
+            .unwrap_or(label),
+    );
+    *input = &input[end..];
+    let style = if opener == "-." {
+        w_dotted_close.parse_next(input)?
+    } else {
+        w_link.parse_next(input)?
+    };
+    Ok((style, label))
+}
+
+/// Closing half of a dotted link with an inline label: `.->` or `.-`
+fn w_dotted_close(input: &mut &str) -> ModalResult<EdgeStyle> {
+    preceded(
+        take_while(1.., '.'),
+        alt((
+            "->".value(EdgeStyle::DottedArrow),
+            "-".value(EdgeStyle::DottedLine),
+        )),
+    )
+    .parse_next(input)
+}
+
+/// Parse an edge id: `e1@` directly before a link
+fn w_edge_id<'a>(input: &mut &'a str) -> ModalResult<&'a str> {
+    (
+        take_while(1.., |c: char| c.is_alphanumeric() || c == '_'),
+        '@',
+        peek(w_link),
+    )
+        .map(|(id, _, _)| id)
+        .parse_next(input)
+}
 
-/// Find edge pattern in line and return (pattern, style)
-fn find_edge_pattern(line: &str) -> Option<(&'static str, EdgeStyle)> {
-    for ep in EDGE_PATTERNS {
-        if line.contains(ep.pattern) {
-            return Some((ep.pattern, ep.style));
+/// Parse a node segment, up to the next link, `|` or `&` outside its
+/// brackets and quotes
+fn w_node<'a>(input: &mut &'a str) -> ModalResult<&'a str> {
+    let mut depth = 0usize;
+    let mut quoted = false;
+    let mut escaped = false;
+    let mut end = input.len();
+    for (i, c) in input.char_indices() {
+        if quoted {
+            quoted = escaped || c != '"';
+            escaped = !escaped && c == '\\';
+            continue;
+        }
+        match c {
+            '"' => quoted = true,
+            '[' | '(' | '{' => depth += 1,
+            ']' | ')' | '}' => depth = depth.saturating_sub(1),
+            _ if depth > 0 => {}
+            '|' | '&' => {
+                end = i;
+                break;
+            }
+            _ if w_link.parse_peek(&input[i..]).is_ok()
+                || w_labeled_link.parse_peek(&input[i..]).is_ok() =>
+            {
+                end = i;
+                break;
+            }
+            _ if c.is_whitespace()
+                && preceded(space1, w_edge_id).parse_peek(&input[i..]).is_ok() =>
+            {
+                end = i;
+                break;
+            }
+            // `id>label]` opens an asymmetric shape
+            '>' => depth += 1,
+            _ => {}
         }
     }
-    None
+    let (node, rest) = input.split_at(end);
+    if node.trim().is_empty() {
+        return Err(ErrMode::from_input(input));
+    }
+    *input = rest;
+    Ok(node.trim_end())
+}
+
+fn w_content_token<'a>(input: &mut &'a str) -> ModalResult<ContentToken<'a>> {
+    preceded(
+        space0,
+        alt((
+            w_link.map(ContentToken::Link),
+            w_labeled_link.map(|(style, label)| ContentToken::LabeledLink(style, label)),
+            w_edge_label.map(ContentToken::Label),
+            '&'.value(ContentToken::Amp),
+            w_edge_id.map(ContentToken::EdgeId),
+            w_node.map(ContentToken::Node),
+        )),
+    )
+    .parse_next(input)
+}
+
+/// Split a content line into tokens
+fn tokenize_content(line: &str, line_num: usize) -> Result<Vec<ContentToken<'_>>, MermaidError> {
+    let mut input = line;
+    let tokens: Vec<ContentToken> = repeat(0.., w_content_token)
+        .parse_next(&mut input)
+        .unwrap_or_default();
+    let rest = input.trim();
+    if !rest.is_empty() {
+        return Err(MermaidError::ParseError {
+            line: line_num,
+            message: format!("Invalid syntax: \"{}\"", rest),
+            suggestion: Some(
+                "Edge labels are written A -->|label| B or A -- label --> B".to_string(),
+            ),
+        });
+    }
+    Ok(tokens)
 }
 
 // ===== Content line parsing =====
 
-/// Parse a content line (node declaration or edge)
+/// Parse a content line (node declarations and edges).
+///
+/// Every node segment is parsed before anything is added, so a line that
+/// fails leaves the graph as it was.
 fn parse_content_line(
     graph: &mut Graph,
     line: &str,
     line_num: usize,
     current_subgraph: Option<&str>,
 ) -> Result<(), MermaidError> {
-    if let Some((pattern, style)) = find_edge_pattern(line) {
-        let segments: Vec<&str> = line.split(pattern).map(|s| s.trim()).collect();
-
-        if segments.len() > 1 {
-            let mut prev_ids: Vec<NodeId> = Vec::new();
-            let mut pending_edge_label: Option<String> = None;
-
-            for segment in segments {
-                let (edge_label, node_part) = extract_edge_label_prefix(segment);
-                let current_edge_label = edge_label.or(pending_edge_label.take());
-                let (node_segment, next_edge_label) = extract_edge_label_suffix(node_part);
-                pending_edge_label = next_edge_label;
-
-                if node_segment.is_empty() {
+    // Nodes before the last link, and the nodes after it
+    let mut prev_ids: Vec<NodeId> = Vec::new();
+    let mut current_ids: Vec<NodeId> = Vec::new();
+    // Style, label and id of the last link
+    let mut link: Option<(EdgeStyle, Option<String>, Option<String>)> = None;
+    let mut pending_edge_label: Option<String> = None;
+    let mut pending_edge_id: Option<String> = None;
+    let mut after_link = false;
+
+    let tokens = tokenize_content(line, line_num)?
+        .into_iter()
+        .map(|token| match token {
+            ContentToken::Node(segment) => {
+                parse_node_segment(segment, line_num).map(|node| (token, Some(node)))
+            }
+            _ => Ok((token, None)),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for (token, node) in tokens {
+        let is_link = matches!(token, ContentToken::Link(_) | ContentToken::LabeledLink(..));
+        match token {
+            ContentToken::Node(_) => {
+                let Some((id, node_label, shape, style_class)) = node else {
                     continue;
-                }
-
-                let targets = parse_multi_target(node_segment);
-                let mut current_ids: Vec<NodeId> = Vec::new();
-
-                for target in targets {
-                    let target = target.trim();
-                    if target.is_empty() {
-                        continue;
-                    }
-
-                    let (id, node_label, shape, style_class) =
-                        parse_node_segment(target, line_num)?;
-
-                    add_or_update_node(
-                        graph,
-                        &id,
-                        node_label,
-                        shape,
-                        current_subgraph,
-                        style_class,
-                    );
+                };
+                add_or_update_node(graph, &id, node_label, shape, current_subgraph, style_class);
 
+                if let Some((style, label, edge_id)) = &mut link {
                     for from_id in &prev_ids {
+                        // An explicit id names only the first edge it fans out to
                         graph.edges.push(Edge {
+                            id: edge_id.take(),
                             from: from_id.clone(),
                             to: id.clone(),
-                            label: current_edge_label.clone(),
-                            style,
+                            label: label.clone(),
+                            weight: label.as_deref().and_then(Edge::parse_weight),
+                            style: *style,
+                            ..Default::default()
                         });
                     }
-
-                    current_ids.push(id);
                 }
-
-                prev_ids = current_ids;
+                current_ids.push(id);
+            }
+            ContentToken::Link(style) => {
+                prev_ids = std::mem::take(&mut current_ids);
+                link = Some((style, pending_edge_label.take(), pending_edge_id.take()));
             }
+            ContentToken::LabeledLink(style, label) => {
+                prev_ids = std::mem::take(&mut current_ids);
+                pending_edge_label = None;
+                link = Some((style, Some(label), pending_edge_id.take()));
+            }
+            // `A -->|label| B` labels the link just read, `A |label| --> B` the next
+            ContentToken::Label(label) if after_link => {
+                if let Some((_, link_label, _)) = &mut link {
+                    *link_label = Some(label);
+                }
+            }
+            ContentToken::Label(label) => pending_edge_label = Some(label),
+            ContentToken::EdgeId(edge_id) => pending_edge_id = Some(edge_id.to_string()),
+            ContentToken::Amp => {}
         }
-    } else {
-        // Single node declaration
-        let (id, label, shape, style_class) = parse_node_segment(line, line_num)?;
-        add_or_update_node(graph, &id, label, shape, current_subgraph, style_class);
+        after_link = is_link;
     }
 
     Ok(())
 }
 
-/// Parse multi-target syntax: "A & B & C" -> vec!["A", "B", "C"]
-fn parse_multi_target(segment: &str) -> Vec<&str> {
-    if segment.contains(" & ") {
-        segment.split(" & ").collect()
-    } else {
-        vec![segment]
-    }
-}
-
 /// Add a node to the graph or update it if it exists
 fn add_or_update_node(
     graph: &mut Graph,
@@ -332,9 +595,11 @@ fn add_or_update_node(
     current_subgraph: Option<&str>,
     style_class: Option<String>,
 ) {
+    let label = label.map(|l| parse_html(&l));
     if !graph.nodes.contains_key(id) {
-        let node_label = label.unwrap_or_else(|| id.to_string());
+        let (node_label, bold) = label.unwrap_or_else(|| (id.to_string(), Vec::new()));
         let mut node = Node::with_shape(id.to_string(), node_label, shape);
+        node.bold = bold;
         node.subgraph = current_subgraph.map(|s| s.to_string());
         node.style_class = style_class;
         graph.nodes.insert(id.to_string(), node);
@@ -345,8 +610,9 @@ fn add_or_update_node(
             }
         }
     } else if let Some(node) = graph.nodes.get_mut(id) {
-        if let Some(lbl) = label {
+        if let Some((lbl, bold)) = label {
             node.label = lbl;
+            node.bold = bold;
             node.shape = shape;
         }
         if style_class.is_some() {
@@ -355,34 +621,6 @@ fn add_or_update_node(
     }
 }
 
-/// Extract edge label prefix: |label| Node -> (Some(label), "Node")
-fn extract_edge_label_prefix(segment: &str) -> (Option<String>, &str) {
-    let segment = segment.trim();
-    let mut input = segment;
-    if let Ok(label) = w_edge_label(&mut input) {
-        let rest = input.trim();
-        return (Some(label), rest);
-    }
-    (None, segment)
-}
-
-/// Extract edge label suffix: Node |label| -> ("Node", Some(label))
-fn extract_edge_label_suffix(segment: &str) -> (&str, Option<String>) {
-    let segment = segment.trim();
-    // Look for trailing |label| pattern
-    if let Some(start_pipe) = segment.rfind('|') {
-        let before = &segment[..start_pipe];
-        if let Some(open_pipe) = before.rfind('|') {
-            let node_part = segment[..open_pipe].trim();
-            let label = segment[open_pipe + 1..start_pipe].to_string();
-            if !node_part.is_empty() && !node_part.ends_with('[') {
-                return (node_part, Some(label));
-            }
-        }
-    }
-    (segment, None)
-}
-
 // ===== Node segment parsing =====
 
 /// Parse a node segment: ID + optional shape(label) + optional :::class
@@ -404,6 +642,7 @@ fn parse_node_segment(
     // Try each shape pattern (order matters: longer/more specific first)
     let shape_attempts: &[(&str, &str, NodeShape)] = &[
         ("{{", "}}", NodeShape::Hexagon),
+        ("(((", ")))", NodeShape::DoubleCircle),
         ("((", "))", NodeShape::Circle),
         ("[(", ")]", NodeShape::Cylinder),
         ("([", "])", NodeShape::Stadium),
@@ -415,6 +654,7 @@ fn parse_node_segment(
         ("{", "}", NodeShape::Diamond),
         ("(", ")", NodeShape::Rounded),
         ("[", "]", NodeShape::Rectangle),
+        (">", "]", NodeShape::Asymmetric),
     ];
 
     for &(open, close, shape) in shape_attempts {
@@ -432,7 +672,7 @@ fn parse_node_segment(
         line: line_num,
         message: format!("Invalid syntax: \"{}\"", segment),
         suggestion: Some(
-            "Supported: [Label], (Label), ((Label)), {{Label}}, {Label}, [(Label)], [/Label/], etc."
+            "Supported: [Label], (Label), ((Label)), {{Label}}, {Label}, [(Label)], [/Label/], >Label], etc."
                 .to_string(),
         ),
     })
@@ -445,7 +685,11 @@ fn try_parse_shape(
     close: &str,
     shape: NodeShape,
 ) -> Option<(String, String, NodeShape)> {
-    let start = segment.find(open)?;
+    // The shape opens right after the id, whatever brackets the label holds
+    let start = segment.find(['[', '(', '{', '>'])?;
+    if !segment[start..].starts_with(open) {
+        return None;
+    }
     let id = &segment[..start];
     let end = segment.rfind(close)?;
     if end > start + open.len() {
@@ -475,9 +719,13 @@ fn validate_node_result(
     Ok((id, Some(label), shape, style_class))
 }
 
-/// Normalize label text (handle <br/> tags as line breaks)
+/// Normalize label text: unquote `"..."` labels, translating their
+/// escapes; HTML is left for [`parse_html`]
 fn normalize_label(label: &str) -> String {
-    label.replace("<br/>", "\n").replace("<br>", "\n")
+    match label.strip_prefix('"').and_then(|l| l.strip_suffix('"')) {
+        Some(quoted) => unescape(quoted),
+        None => label.to_string(),
+    }
 }
 
 /// Check if string is a valid node ID (alphanumeric + underscore)
@@ -485,6 +733,77 @@ fn is_valid_id(s: &str) -> bool {
     !s.is_empty() && s.chars().all(|c| c.is_alphanumeric() || c == '_')
 }
 
+// ===== Symbols =====
+
+/// Nodes, edges and subgraphs with their source locations
+pub(crate) fn symbols(input: &str) -> Vec<Symbol> {
+    let mut symbols = Vec::new();
+    let mut lines = source_lines(input)
+        .filter(|(_, _, l)| !l.trim().is_empty() && !l.trim_start().starts_with("%%"));
+    // Skip the header
+    lines.next();
+
+    for (line, offset, text) in lines {
+        let symbol = |name: &str, kind, span: Range<usize>| Symbol {
+            name: name.to_string(),
+            kind,
+            line,
+            span: offset + span.start..offset + span.end,
+        };
+        match classify_line(text) {
+            Ok(MermaidLine::SubgraphStart { id, .. }) => {
+                if let Some(at) = find_word(text, &id, 0) {
+                    symbols.push(symbol(&id, SymbolKind::Container, at..at + id.len()));
+                }
+            }
+            Ok(MermaidLine::ClassAssignment { node_ids, .. }) => {
+                let mut from = 0;
+                for id in &node_ids {
+                    if let Some(at) = find_word(text, id, from) {
+                        symbols.push(symbol(id, SymbolKind::Node, at..at + id.len()));
+                        from = at + id.len();
+                    }
+                }
+            }
+            Ok(MermaidLine::Content(_)) => {
+                let Ok(tokens) = tokenize_content(text, line) else {
+                    continue;
+                };
+                // (id, segment start) before and after the last link
+                let mut prev: Vec<(NodeId, usize)> = Vec::new();
+                let mut current: Vec<(NodeId, usize)> = Vec::new();
+                let mut linked = false;
+                for token in tokens {
+                    match token {
+                        ContentToken::Node(segment) => {
+                            let Ok((id, ..)) = parse_node_segment(segment, line) else {
+                                continue;
+                            };
+                            let start = segment.as_ptr() as usize - text.as_ptr() as usize;
+                            let end = start + segment.len();
+                            symbols.push(symbol(&id, SymbolKind::Node, start..start + id.len()));
+                            if linked {
+                                for (from_id, from_start) in &prev {
+                                    let name = format!("{} -> {}", from_id, id);
+                                    symbols.push(symbol(&name, SymbolKind::Edge, *from_start..end));
+                                }
+                            }
+                            current.push((id, start));
+                        }
+                        ContentToken::Link(_) | ContentToken::LabeledLink(..) => {
+                            prev = std::mem::take(&mut current);
+                            linked = true;
+                        }
+                        ContentToken::Label(_) | ContentToken::Amp | ContentToken::EdgeId(_) => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    symbols
+}
+
 // ===== Color parsing =====
 
 /// Extract color value from classDef properties
@@ -504,7 +823,7 @@ fn extract_color(props: &str) -> Option<String> {
 /// Convert hex color to ANSI escape code
 fn hex_to_ansi(hex: &str) -> String {
     let hex = hex.trim_start_matches('#');
-    if hex.len() >= 6 {
+    if hex.len() >= 6 && hex.is_char_boundary(6) && hex[..6].is_ascii() {
         if let (Ok(r), Ok(g), Ok(b)) = (
             u8::from_str_radix(&hex[0..2], 16),
             u8::from_str_radix(&hex[2..4], 16),
@@ -537,6 +856,20 @@ mod tests {
         assert_eq!(graph.nodes.len(), 2);
     }
 
+    #[test]
+    fn test_parse_header_variants() {
+        let graph = parse_mermaid("flowchart-elk LR\nA --> B").unwrap();
+        assert_eq!(graph.direction, Direction::LR);
+        let graph = parse_mermaid("graph TD;\nA --> B;\nsubgraph S;\nC;\nend;").unwrap();
+        assert_eq!(graph.edges.len(), 1);
+        assert_eq!(graph.subgraphs[0].nodes, vec!["C".to_string()]);
+        for header in ["flowchart", "graph;", "FLOWCHART-ELK"] {
+            let graph = parse_mermaid(&format!("{}\nA --> B", header)).unwrap();
+            assert_eq!(graph.direction, Direction::TB);
+        }
+        assert!(parse_mermaid("graphql\nA --> B").is_err());
+    }
+
     #[test]
     fn test_parse_with_labels() {
         let input = "flowchart TB\nA[Start] --> B[End]";
@@ -556,7 +889,8 @@ mod tests {
                 from: "A".to_string(),
                 to: "B".to_string(),
                 label: None,
-                style: EdgeStyle::Arrow
+                style: EdgeStyle::Arrow,
+                ..Default::default()
             }
         );
         assert_eq!(
@@ -565,7 +899,8 @@ mod tests {
                 from: "B".to_string(),
                 to: "C".to_string(),
                 label: None,
-                style: EdgeStyle::Arrow
+                style: EdgeStyle::Arrow,
+                ..Default::default()
             }
         );
         assert_eq!(
@@ -574,11 +909,24 @@ mod tests {
                 from: "C".to_string(),
                 to: "D".to_string(),
                 label: None,
-                style: EdgeStyle::Arrow
+                style: EdgeStyle::Arrow,
+                ..Default::default()
             }
         );
     }
 
+    #[test]
+    fn test_parse_edge_ids() {
+        let input = "flowchart LR\nA e1@--> B\nB fast_path@-->|go| C --> D\nA & B e9@--- D";
+        let graph = parse_mermaid(input).unwrap();
+        let ids: Vec<_> = graph.edges.iter().map(|e| e.id.as_deref()).collect();
+        assert_eq!(ids, [Some("e1"), Some("fast_path"), None, Some("e9"), None]);
+        assert_eq!(graph.edges[1].label.as_deref(), Some("go"));
+        assert_eq!(graph.nodes.len(), 4);
+        assert_eq!(graph.edge_id(0), "e1");
+        assert_eq!(graph.edge_id(2), "#2");
+    }
+
     #[test]
     fn test_parse_edge_labels() {
         let input = "flowchart LR\nA -->|sends| B";
@@ -587,6 +935,54 @@ mod tests {
         assert_eq!(graph.edges[0].label, Some("sends".to_string()));
     }
 
+    #[test]
+    fn test_parse_inline_edge_labels() {
+        let input = "flowchart LR\nA -- text1 --> B -. maybe later .-> C\nC == \"big one\" === D\nD -- a-b ---- E\nE--x-->F";
+        let graph = parse_mermaid(input).unwrap();
+        let edges: Vec<_> = graph
+            .edges
+            .iter()
+            .map(|e| (e.from.as_str(), e.to.as_str(), e.label.as_deref(), e.style))
+            .collect();
+        assert_eq!(
+            edges,
+            [
+                ("A", "B", Some("text1"), EdgeStyle::Arrow),
+                ("B", "C", Some("maybe later"), EdgeStyle::DottedArrow),
+                ("C", "D", Some("big one"), EdgeStyle::ThickLine),
+                ("D", "E", Some("a-b"), EdgeStyle::Line),
+                ("E", "F", Some("x"), EdgeStyle::Arrow),
+            ]
+        );
+        assert_eq!(graph.nodes.len(), 6);
+    }
+
+    #[test]
+    fn test_parse_keywords_and_numbers_as_ids() {
+        let input = "flowchart LR\nsubgraph S\nend --> class\nend\nclass --> style & classDef\nclassDef -- x --> 1\nsubgraph --> 2[Two]\nclass 1,2 hot\nclassDef hot fill:#f00";
+        let graph = parse_mermaid(input).unwrap();
+        let edges: Vec<_> = graph
+            .edges
+            .iter()
+            .map(|e| (e.from.as_str(), e.to.as_str()))
+            .collect();
+        assert_eq!(
+            edges,
+            [
+                ("end", "class"),
+                ("class", "style"),
+                ("class", "classDef"),
+                ("classDef", "1"),
+                ("subgraph", "2"),
+            ]
+        );
+        assert_eq!(graph.subgraphs.len(), 1);
+        assert_eq!(graph.subgraphs[0].nodes, ["end", "class"]);
+        assert_eq!(graph.nodes["2"].label, "Two");
+        assert_eq!(graph.nodes["1"].style_class.as_deref(), Some("hot"));
+        assert!(graph.style_classes.contains_key("hot"));
+    }
+
     #[test]
     fn test_parse_edge_labels_chain() {
         let input = "flowchart LR\nA -->|first| B -->|second| C";
@@ -603,6 +999,25 @@ mod tests {
         assert_eq!(graph.nodes.len(), 2);
     }
 
+    #[test]
+    fn test_parse_pin_pragma() {
+        let input = "flowchart LR\n%% @pin B 20 3\nA --> B\n%% @pin missing 1 1";
+        let graph = parse_mermaid(input).unwrap();
+        assert_eq!(graph.nodes["B"].pinned, (Some(20), Some(3)));
+        assert_eq!(graph.nodes["A"].pinned, (None, None));
+        assert_eq!(graph.nodes.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_meta_pragma() {
+        let input = "flowchart LR\n%% @meta A owner team payments\nA --> B\n%% @meta A link https://x.io\n%% @meta missing k v";
+        let graph = parse_mermaid(input).unwrap();
+        assert_eq!(graph.nodes["A"].metadata["owner"], "team payments");
+        assert_eq!(graph.nodes["A"].metadata["link"], "https://x.io");
+        assert!(graph.nodes["B"].metadata.is_empty());
+        assert_eq!(graph.nodes.len(), 2);
+    }
+
     #[test]
     fn test_parse_empty_input() {
         let result = parse_mermaid("");
@@ -638,6 +1053,36 @@ mod tests {
         assert_eq!(graph.nodes.get("A").unwrap().label, "Circle");
     }
 
+    #[test]
+    fn test_compartment_class_sets_shape() {
+        let input = "flowchart LR\nA[User<br/>+name]:::compartment --> B[Plain<br/>text]\nC[Two<br/>parts]\nclass C compartment";
+        let graph = parse_mermaid(input).unwrap();
+        assert_eq!(graph.nodes["A"].shape, NodeShape::Compartment);
+        assert_eq!(graph.nodes["A"].label, "User\n+name");
+        assert_eq!(graph.nodes["B"].shape, NodeShape::Rectangle);
+        assert_eq!(graph.nodes["C"].shape, NodeShape::Compartment);
+    }
+
+    #[test]
+    fn test_shape_delimiters_inside_label() {
+        let graph = parse_mermaid("flowchart LR\nA[run() then stop()] --> B(see [docs])").unwrap();
+        assert_eq!(graph.nodes["A"].shape, NodeShape::Rectangle);
+        assert_eq!(graph.nodes["A"].label, "run() then stop()");
+        assert_eq!(graph.nodes["B"].shape, NodeShape::Rounded);
+        assert_eq!(graph.nodes["B"].label, "see [docs]");
+    }
+
+    #[test]
+    fn test_double_circle_and_asymmetric_shapes() {
+        let graph = parse_mermaid("flowchart LR\nA(((Done))) --> B>Flag]\nB --> C[a > b]").unwrap();
+        assert_eq!(graph.nodes["A"].shape, NodeShape::DoubleCircle);
+        assert_eq!(graph.nodes["A"].label, "Done");
+        assert_eq!(graph.nodes["B"].shape, NodeShape::Asymmetric);
+        assert_eq!(graph.nodes["B"].label, "Flag");
+        assert_eq!(graph.nodes["C"].shape, NodeShape::Rectangle);
+        assert_eq!(graph.nodes["C"].label, "a > b");
+    }
+
     #[test]
     fn test_parse_diamond_shape() {
         let input = "flowchart LR\nA{Decision}";
@@ -695,6 +1140,17 @@ mod tests {
         assert_eq!(graph.nodes.get("A").unwrap().label, "Line1\nLine2");
     }
 
+    #[test]
+    fn test_parse_html_labels() {
+        let input = "flowchart LR\nsubgraph S [<i>Zone</i>]\nA[\"<b>API</b><br>v2&nbsp;&lt;beta&gt;\"] -->|<em>a</em><br/>b| B\nend";
+        let graph = parse_mermaid(input).unwrap();
+        let a = &graph.nodes["A"];
+        assert_eq!(a.label, "API\nv2 <beta>");
+        assert_eq!(a.bold, vec![0..3]);
+        assert_eq!(graph.edges[0].label.as_deref(), Some("a b"));
+        assert_eq!(graph.subgraphs[0].label, "Zone");
+    }
+
     // ===== NEW SHAPE TESTS (TDD) =====
 
     #[test]
@@ -782,6 +1238,15 @@ mod tests {
         assert_eq!(graph.edges[0].style, EdgeStyle::ThickLine);
     }
 
+    #[test]
+    fn test_parse_invisible_link() {
+        let input = "flowchart LR\nA ~~~ B\nB ~~~~ C";
+        let graph = parse_mermaid(input).unwrap();
+        assert_eq!(graph.edges.len(), 2);
+        assert_eq!(graph.edges[0].style, EdgeStyle::Invisible);
+        assert_eq!(graph.edges[1].style, EdgeStyle::Invisible);
+    }
+
     #[test]
     fn test_parse_dotted_arrow_with_label() {
         let input = "flowchart LR\nA -.->|async| B";
@@ -790,6 +1255,53 @@ mod tests {
         assert_eq!(graph.edges[0].label, Some("async".to_string()));
     }
 
+    #[test]
+    fn test_parse_link_inside_quoted_label() {
+        let input = "flowchart LR\nA[\"x --> y\"] -->|go| B(a | b & c)";
+        let graph = parse_mermaid(input).unwrap();
+        assert_eq!(graph.nodes.len(), 2);
+        assert_eq!(graph.nodes["A"].label, "x --> y");
+        assert_eq!(graph.nodes["B"].label, "a | b & c");
+        assert_eq!(graph.edges.len(), 1);
+        assert_eq!(graph.edges[0].label, Some("go".to_string()));
+    }
+
+    #[test]
+    fn test_parse_quoted_label_escapes() {
+        let input = r#"flowchart LR
+A["say \"hi\" --> B"] --> B("two\nlines \\ ok")"#;
+        let graph = parse_mermaid(input).unwrap();
+        assert_eq!(graph.nodes.len(), 2);
+        assert_eq!(graph.nodes["A"].label, "say \"hi\" --> B");
+        assert_eq!(graph.nodes["B"].label, "two\nlines \\ ok");
+    }
+
+    #[test]
+    fn test_parse_mixed_and_long_links() {
+        let input = "flowchart LR\nA ----> B -.- C==>D\nD |back| --- A";
+        let graph = parse_mermaid(input).unwrap();
+        let styles: Vec<EdgeStyle> = graph.edges.iter().map(|e| e.style).collect();
+        assert_eq!(
+            styles,
+            vec![
+                EdgeStyle::Arrow,
+                EdgeStyle::DottedLine,
+                EdgeStyle::ThickArrow,
+                EdgeStyle::Line
+            ]
+        );
+        assert_eq!(graph.edges[3].label, Some("back".to_string()));
+    }
+
+    #[test]
+    fn test_parse_unclosed_edge_label_is_error() {
+        let result = parse_mermaid("flowchart LR\nA -->|oops B");
+        assert!(matches!(
+            result,
+            Err(MermaidError::ParseError { line: 2, .. })
+        ));
+    }
+
     // ===== MULTI-TARGET EDGE TESTS =====
 
     #[test]
@@ -855,4 +1367,63 @@ mod tests {
         );
         assert_eq!(graph.nodes.get("A").unwrap().label, "Label");
     }
+
+    #[test]
+    fn test_symbols_spans() {
+        let code = "flowchart LR\nsubgraph S\nA[Start] --> B & C\nend";
+        let symbols = symbols(code);
+        let found: Vec<(&str, SymbolKind, usize, &str)> = symbols
+            .iter()
+            .map(|s| (s.name.as_str(), s.kind, s.line, &code[s.span.clone()]))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("S", SymbolKind::Container, 2, "S"),
+                ("A", SymbolKind::Node, 3, "A"),
+                ("B", SymbolKind::Node, 3, "B"),
+                ("A -> B", SymbolKind::Edge, 3, "A[Start] --> B"),
+                ("C", SymbolKind::Node, 3, "C"),
+                ("A -> C", SymbolKind::Edge, 3, "A[Start] --> B & C"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_error_line_counts_blank_lines() {
+        let result = parse_mermaid("flowchart LR\n\n%% note\nA -->|oops B");
+        assert!(matches!(
+            result,
+            Err(MermaidError::ParseError { line: 4, .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_lenient_skips_bad_lines() {
+        let (graph, warnings) =
+            parse_mermaid_lenient("flowchart LR\nA --> B\nB -->|oops C\nB --> D").unwrap();
+        assert_eq!(graph.edges.len(), 2);
+        assert!(!graph.nodes.contains_key("C"));
+        assert_eq!(warnings.len(), 1);
+        assert!(matches!(
+            &warnings[0],
+            DiagramWarning::LineSkipped { line: 3, .. }
+        ));
+        // The header is still required
+        assert!(parse_mermaid_lenient("A --> B").is_err());
+    }
+
+    #[test]
+    fn test_parse_lenient_skipped_line_adds_nothing() {
+        for line in ["A --> B --> C[bad", "A --> B & 1bad! --> D"] {
+            let input = format!("flowchart LR\nX --> Y\n{line}");
+            let (graph, warnings) = parse_mermaid_lenient(&input).unwrap();
+            assert_eq!(graph.edges.len(), 1, "{line}");
+            assert_eq!(graph.nodes.len(), 2, "{line}");
+            assert!(matches!(
+                &warnings[..],
+                [DiagramWarning::LineSkipped { line: 3, .. }]
+            ));
+        }
+    }
 }
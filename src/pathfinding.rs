@@ -66,7 +66,6 @@ impl PathGrid {
     }
 
     /// Unblock specific positions (for edge start/end points)
-    #[allow(dead_code)]
     pub fn unblock(&mut self, pos: Pos) {
         self.blocked.remove(&pos);
     }
@@ -166,6 +165,188 @@ impl PathGrid {
 
         None // No path found
     }
+
+    /// Find a path that may also take 45° diagonal steps.
+    ///
+    /// Each change of direction costs extra, so routes come out as a few long
+    /// straight or diagonal runs rather than staircases. Diagonal steps may
+    /// not squeeze between two blocked cells.
+    pub fn find_path_diagonal(&self, start: Pos, goal: Pos) -> Option<Vec<Pos>> {
+        self.find_path_with_steps(start, goal, &DIAGONAL_STEPS, None, 0)
+    }
+
+    /// Find an orthogonal path with as few turns as possible.
+    ///
+    /// Unlike [`PathGrid::find_path`], which happily returns staircases, this
+    /// charges for every change of direction, including turning away from
+    /// `heading` (the direction the edge is already travelling at `start`)
+    /// and arriving at `goal` along anything but `heading`.
+    /// Among equally short routes it keeps clear of obstacles rather than
+    /// hugging their borders.
+    pub fn find_path_fewest_turns(
+        &self,
+        start: Pos,
+        goal: Pos,
+        heading: (isize, isize),
+    ) -> Option<Vec<Pos>> {
+        let steps = &DIAGONAL_STEPS[..4];
+        let heading = steps.iter().position(|&step| step == heading);
+        self.find_path_with_steps(start, goal, steps, heading, HUG_COST)
+    }
+
+    /// Turn-penalised A* over the given step directions.
+    ///
+    /// Steps onto a cell next to an obstacle cost `hug_cost` extra.
+    fn find_path_with_steps(
+        &self,
+        start: Pos,
+        goal: Pos,
+        steps: &[(isize, isize)],
+        heading: Option<usize>,
+        hug_cost: usize,
+    ) -> Option<Vec<Pos>> {
+        if !self.is_valid(start) || !self.is_valid(goal) {
+            return None;
+        }
+
+        // State is (position, index into `steps` of the step taken to get there)
+        const NO_DIR: usize = usize::MAX;
+        let mut open_set = BinaryHeap::new();
+        let mut came_from: HashMap<(Pos, usize), (Pos, usize)> = HashMap::new();
+        let mut g_score: HashMap<(Pos, usize), usize> = HashMap::new();
+
+        let start_dir = heading.unwrap_or(NO_DIR);
+        g_score.insert((start, start_dir), 0);
+        open_set.push(StepNode {
+            pos: start,
+            dir: start_dir,
+            f_score: Self::octile(start, goal),
+        });
+
+        while let Some(current) = open_set.pop() {
+            let state = (current.pos, current.dir);
+            if current.pos == goal {
+                let mut path = vec![current.pos];
+                let mut cursor = state;
+                while let Some(&prev) = came_from.get(&cursor) {
+                    path.push(prev.0);
+                    cursor = prev;
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            let current_g = *g_score.get(&state).unwrap_or(&usize::MAX);
+            if current.f_score > current_g + Self::octile(current.pos, goal) {
+                continue; // Stale queue entry
+            }
+
+            for (dir, &(dx, dy)) in steps.iter().enumerate() {
+                let Some(next) = self.step(current.pos, dx, dy) else {
+                    continue;
+                };
+                let diagonal = dx != 0 && dy != 0;
+                if diagonal
+                    && (!self.is_valid(Pos::new(next.x, current.pos.y))
+                        || !self.is_valid(Pos::new(current.pos.x, next.y)))
+                {
+                    continue;
+                }
+                let mut cost = if diagonal {
+                    DIAGONAL_COST
+                } else {
+                    STRAIGHT_COST
+                };
+                if current.dir != NO_DIR && current.dir != dir {
+                    cost += TURN_COST;
+                }
+                if next == goal && heading.is_some_and(|h| h != dir) {
+                    // The arrowhead points along `heading`, so arriving sideways
+                    // is a turn with no room to draw its corner
+                    cost += 2 * TURN_COST;
+                }
+                if hug_cost > 0 && next != goal && self.touches_obstacle(next) {
+                    cost += hug_cost;
+                }
+                let tentative_g = current_g + cost;
+                let next_state = (next, dir);
+                if tentative_g < *g_score.get(&next_state).unwrap_or(&usize::MAX) {
+                    came_from.insert(next_state, state);
+                    g_score.insert(next_state, tentative_g);
+                    open_set.push(StepNode {
+                        pos: next,
+                        dir,
+                        f_score: tentative_g + Self::octile(next, goal),
+                    });
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Move one step from `pos`, returning the new position if it is free
+    fn step(&self, pos: Pos, dx: isize, dy: isize) -> Option<Pos> {
+        let x = pos.x.checked_add_signed(dx)?;
+        let y = pos.y.checked_add_signed(dy)?;
+        let next = Pos::new(x, y);
+        self.is_valid(next).then_some(next)
+    }
+
+    /// Whether any orthogonal neighbour of `pos` is blocked
+    fn touches_obstacle(&self, pos: Pos) -> bool {
+        DIAGONAL_STEPS[..4].iter().any(|&(dx, dy)| {
+            match (pos.x.checked_add_signed(dx), pos.y.checked_add_signed(dy)) {
+                (Some(x), Some(y)) => self.blocked.contains(&Pos::new(x, y)),
+                _ => false,
+            }
+        })
+    }
+
+    /// Octile distance heuristic scaled to the step costs
+    fn octile(from: Pos, to: Pos) -> usize {
+        let dx = from.x.abs_diff(to.x);
+        let dy = from.y.abs_diff(to.y);
+        STRAIGHT_COST * dx.max(dy) + (DIAGONAL_COST - STRAIGHT_COST) * dx.min(dy)
+    }
+}
+
+/// Step costs for turn-aware routing (diagonal ≈ √2 × straight)
+const STRAIGHT_COST: usize = 10;
+const DIAGONAL_COST: usize = 14;
+const TURN_COST: usize = 8;
+const HUG_COST: usize = 3;
+
+/// The eight step directions, orthogonal first
+const DIAGONAL_STEPS: [(isize, isize); 8] = [
+    (1, 0),
+    (-1, 0),
+    (0, 1),
+    (0, -1),
+    (1, 1),
+    (-1, 1),
+    (1, -1),
+    (-1, -1),
+];
+
+/// Search state for turn-aware A*: position plus the direction of arrival
+#[derive(Clone, Copy, Eq, PartialEq)]
+struct StepNode {
+    pos: Pos,
+    dir: usize,
+    f_score: usize,
+}
+
+impl Ord for StepNode {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.f_score.cmp(&self.f_score)
+    }
+}
+
+impl PartialOrd for StepNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
 }
 
 #[cfg(test)]
@@ -210,4 +391,78 @@ mod tests {
         let path = grid.find_path(Pos::new(3, 5), Pos::new(7, 5));
         assert!(path.is_none());
     }
+
+    #[test]
+    fn test_diagonal_path_is_a_single_run() {
+        let grid = PathGrid::new(10, 10);
+        let path = grid
+            .find_path_diagonal(Pos::new(0, 0), Pos::new(4, 4))
+            .unwrap();
+        // Pure 45° line: 4 diagonal steps
+        assert_eq!(path.len(), 5);
+        assert!(path
+            .windows(2)
+            .all(|w| w[1].x == w[0].x + 1 && w[1].y == w[0].y + 1));
+    }
+
+    #[test]
+    fn test_diagonal_path_mixes_straight_and_diagonal() {
+        let grid = PathGrid::new(20, 10);
+        let path = grid
+            .find_path_diagonal(Pos::new(0, 0), Pos::new(3, 8))
+            .unwrap();
+        assert_eq!(path.last(), Some(&Pos::new(3, 8)));
+        // 3 diagonal + 5 straight steps, at most two direction changes
+        assert_eq!(path.len(), 9);
+        let dirs: Vec<(isize, isize)> = path
+            .windows(2)
+            .map(|w| {
+                (
+                    w[1].x as isize - w[0].x as isize,
+                    w[1].y as isize - w[0].y as isize,
+                )
+            })
+            .collect();
+        let turns = dirs.windows(2).filter(|d| d[0] != d[1]).count();
+        assert!(turns <= 2, "too many turns: {:?}", dirs);
+    }
+
+    #[test]
+    fn test_diagonal_path_does_not_cut_corners() {
+        let mut grid = PathGrid::new(6, 6);
+        grid.block_rect(1, 0, 1, 1);
+        grid.block_rect(0, 1, 1, 1);
+        assert!(grid
+            .find_path_diagonal(Pos::new(0, 0), Pos::new(3, 3))
+            .is_none());
+    }
+
+    #[test]
+    fn test_fewest_turns_path_is_a_single_jog() {
+        let grid = PathGrid::new(20, 20);
+        let path = grid
+            .find_path_fewest_turns(Pos::new(0, 0), Pos::new(6, 9), (0, 1))
+            .unwrap();
+        assert_eq!(path.len(), 16);
+        let turns = path
+            .windows(3)
+            .filter(|w| (w[0].x == w[1].x) != (w[1].x == w[2].x))
+            .count();
+        assert_eq!(turns, 2);
+        // Arrives heading down, the way the arrowhead points
+        let last = &path[path.len() - 2..];
+        assert_eq!(last[0].x, last[1].x);
+    }
+
+    #[test]
+    fn test_fewest_turns_through_gap() {
+        let mut grid = PathGrid::new(10, 10);
+        // Wall across row 5 with a single opening at x = 7
+        grid.block_rect(0, 5, 10, 1);
+        grid.unblock(Pos::new(7, 5));
+        let path = grid
+            .find_path_fewest_turns(Pos::new(2, 0), Pos::new(2, 9), (0, 1))
+            .unwrap();
+        assert!(path.contains(&Pos::new(7, 5)));
+    }
 }
@@ -10,7 +10,9 @@ use winnow::ModalResult;
 use winnow::Parser;
 
 use crate::error::MermaidError;
-use crate::types::RenderOptions;
+use crate::label::strip_html_line;
+use crate::text::{display_width, pad_to_width};
+use crate::types::{DiagramWarning, RenderOptions};
 
 /// A slice of the pie chart
 #[derive(Debug, Clone)]
@@ -24,16 +26,47 @@ pub struct PieSlice {
 pub struct PieChart {
     pub title: Option<String>,
     pub slices: Vec<PieSlice>,
-    #[allow(dead_code)] // Parsed but not yet used in rendering
     pub show_data: bool,
+    /// Warnings generated during parsing
+    pub warnings: Vec<DiagramWarning>,
+}
+
+impl PieChart {
+    /// Create a chart with no slices yet
+    pub fn new(title: Option<&str>) -> Self {
+        Self {
+            title: title.map(String::from),
+            slices: Vec::new(),
+            show_data: false,
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Append a slice
+    pub fn add_slice(&mut self, label: &str, value: f64) -> &mut Self {
+        self.slices.push(PieSlice {
+            label: label.to_string(),
+            value,
+        });
+        self
+    }
 }
 
 /// Content of a single line (after trimming)
 #[derive(Debug)]
 enum PieLine {
-    Header { show_data: bool },
+    Header {
+        show_data: bool,
+    },
     Title(String),
-    Slice { label: String, value: f64 },
+    Slice {
+        label: String,
+        value: f64,
+    },
+    /// `accTitle` or `accDescr`, which have no effect in a terminal
+    Ignored(&'static str),
+    /// A line that isn't any known statement
+    Unknown,
     Comment,
     Empty,
 }
@@ -120,8 +153,17 @@ fn parse_line(line: &str) -> PieLine {
         return PieLine::Slice { label, value };
     }
 
-    // Unknown line - treat as empty
-    PieLine::Empty
+    // Accessibility statements
+    for keyword in ["accTitle", "accDescr"] {
+        if trimmed
+            .strip_prefix(keyword)
+            .is_some_and(|rest| rest.trim_start().starts_with([':', '{']))
+        {
+            return PieLine::Ignored(keyword);
+        }
+    }
+
+    PieLine::Unknown
 }
 
 /// Parse pie chart syntax
@@ -136,8 +178,15 @@ pub fn parse_pie_chart(input: &str) -> Result<PieChart, MermaidError> {
     let mut title = None;
     let mut slices = Vec::new();
     let mut found_header = false;
-
-    for line in lines.iter() {
+    let mut warnings = Vec::new();
+    // Inside a multi-line `accDescr { ... }`
+    let mut in_block = false;
+
+    for (i, line) in lines.iter().enumerate() {
+        if in_block {
+            in_block = !line.contains('}');
+            continue;
+        }
         match parse_line(line) {
             PieLine::Header { show_data: sd } => {
                 if !found_header {
@@ -146,11 +195,25 @@ pub fn parse_pie_chart(input: &str) -> Result<PieChart, MermaidError> {
                 }
             }
             PieLine::Title(t) => {
-                title = Some(t);
+                title = Some(strip_html_line(&t));
             }
             PieLine::Slice { label, value } => {
+                let label = strip_html_line(&label);
                 slices.push(PieSlice { label, value });
             }
+            PieLine::Ignored(keyword) => {
+                in_block = line.contains('{') && !line.contains('}');
+                warnings.push(DiagramWarning::IgnoredStatement {
+                    statement: keyword.to_string(),
+                    line: i + 1,
+                });
+            }
+            PieLine::Unknown => {
+                warnings.push(DiagramWarning::LineSkipped {
+                    line: i + 1,
+                    message: format!("Invalid syntax: \"{}\"", line.trim()),
+                });
+            }
             PieLine::Comment | PieLine::Empty => {}
         }
     }
@@ -175,6 +238,7 @@ pub fn parse_pie_chart(input: &str) -> Result<PieChart, MermaidError> {
         title,
         slices,
         show_data,
+        warnings,
     })
 }
 
@@ -191,14 +255,14 @@ pub fn render_pie_chart(chart: &PieChart, _options: &RenderOptions) -> String {
     // Title
     if let Some(ref title) = chart.title {
         output.push_str(&format!("  {}\n", title));
-        output.push_str(&format!("  {}\n\n", "─".repeat(title.len())));
+        output.push_str(&format!("  {}\n\n", "─".repeat(display_width(title))));
     }
 
     // Find max label width for alignment
     let max_label_width = chart
         .slices
         .iter()
-        .map(|s| s.label.len())
+        .map(|s| display_width(&s.label))
         .max()
         .unwrap_or(10);
     let bar_width = 30;
@@ -224,13 +288,12 @@ pub fn render_pie_chart(chart: &PieChart, _options: &RenderOptions) -> String {
 
         // Format: Label  |████████████| value (percentage%)
         output.push_str(&format!(
-            "  {:width$}  │{}{}│ {:.0} ({:.1}%)\n",
-            slice.label,
+            "  {}  │{}{}│ {:.0} ({:.1}%)\n",
+            pad_to_width(&slice.label, max_label_width),
             bar,
             padding,
             slice.value,
             percentage,
-            width = max_label_width
         ));
     }
 
@@ -249,6 +312,26 @@ pub fn render_pie_chart(chart: &PieChart, _options: &RenderOptions) -> String {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_pie_reports_unsupported_lines() {
+        let input = "pie\naccDescr {\n  Browser share\n}\n\"a\" : 1\nb = 2";
+        let chart = parse_pie_chart(input).unwrap();
+        assert_eq!(chart.slices.len(), 1);
+        assert_eq!(
+            chart.warnings,
+            [
+                DiagramWarning::IgnoredStatement {
+                    statement: "accDescr".to_string(),
+                    line: 2
+                },
+                DiagramWarning::LineSkipped {
+                    line: 6,
+                    message: "Invalid syntax: \"b = 2\"".to_string()
+                },
+            ]
+        );
+    }
+
     #[test]
     fn test_parse_pie_simple() {
         let input = r#"pie
@@ -295,6 +378,7 @@ mod tests {
                 },
             ],
             show_data: false,
+            warnings: Vec::new(),
         };
         let output = render_pie_chart(&chart, &RenderOptions::default());
         assert!(output.contains("Test"));
@@ -317,6 +401,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn test_parse_number() {
         assert_eq!(parse_number.parse("42").unwrap(), 42.0);
         assert_eq!(parse_number.parse("3.14").unwrap(), 3.14);
@@ -0,0 +1,597 @@
+//! Pluggable diagram kinds behind one parse → layout → render pipeline
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use crate::budget::{self, Budget};
+use crate::d2_parser::{self, parse_d2, D2ParseResult};
+use crate::error::MermaidError;
+use crate::layout::layout_within;
+use crate::parser::{self, parse_mermaid, parse_mermaid_lenient};
+use crate::pie_parser::{parse_pie_chart, render_pie_chart, PieChart};
+use crate::renderer::{draw_graph, finish_drawing, separate_legend};
+use crate::sanitize::render_sanitized;
+use crate::seq_parser::{self, parse_sequence_diagram, render_sequence_diagram, SequenceDiagram};
+use crate::state_parser::{self, parse_state_diagram_with_warnings};
+use crate::summary::{graph_summary, pie_summary, sequence_summary};
+use crate::types::{
+    DiagramWarning, Drawing, Graph, NodeId, RenderOptions, RenderResult, SourceMap,
+};
+use crate::{detect_format, node_numbers, DiagramFormat};
+
+/// One diagram kind, split into parse, layout and render stages.
+///
+/// Implementors only produce a [`Drawing`]; the pipeline that runs them
+/// takes care of the options every kind shares: one [`Budget`] is started
+/// per render, handed to the layout and render stages and checked between
+/// them, ANSI escapes are dropped unless `colors` is set, and the
+/// body is fitted to `max_width` with the chosen `width_strategy`. `ascii`
+/// and the other options are passed through for the renderer to honor.
+///
+/// # Example
+/// ```
+/// use graphs_tui::{
+///     Budget, DiagramRenderer, DiagramWarning, Drawing, MermaidError, Registry, RenderOptions,
+/// };
+///
+/// struct Banner;
+///
+/// impl DiagramRenderer for Banner {
+///     type Model = String;
+///
+///     fn detect(&self, input: &str) -> bool {
+///         input.trim_start().starts_with("banner")
+///     }
+///
+///     fn parse(
+///         &self,
+///         input: &str,
+///         _options: &RenderOptions,
+///     ) -> Result<(String, Vec<DiagramWarning>), MermaidError> {
+///         Ok((input.trim_start()["banner".len()..].trim().to_string(), Vec::new()))
+///     }
+///
+///     fn render(
+///         &self,
+///         model: &String,
+///         _options: &RenderOptions,
+///         _budget: &Budget,
+///         _warnings: &mut Vec<DiagramWarning>,
+///     ) -> Result<Drawing, MermaidError> {
+///         Ok(format!("*** {model} ***").into())
+///     }
+/// }
+///
+/// let mut registry = Registry::default();
+/// registry.register("banner", Banner);
+/// let result = registry.render_detected("banner Hello", RenderOptions::default()).unwrap();
+/// assert_eq!(result.output, "*** Hello ***");
+/// ```
+pub trait DiagramRenderer: Send + Sync {
+    /// Parsed form of the diagram
+    type Model;
+
+    /// Whether `input` is written in this kind's syntax
+    fn detect(&self, input: &str) -> bool;
+
+    /// Build the model from source, along with any warnings
+    fn parse(
+        &self,
+        input: &str,
+        options: &RenderOptions,
+    ) -> Result<(Self::Model, Vec<DiagramWarning>), MermaidError>;
+
+    /// Position the model for `options`, within the render's `budget`
+    /// (default: nothing to lay out)
+    fn layout(
+        &self,
+        _model: &mut Self::Model,
+        _options: &RenderOptions,
+        _budget: &Budget,
+    ) -> Result<Vec<DiagramWarning>, MermaidError> {
+        Ok(Vec::new())
+    }
+
+    /// Draw the laid-out model within the render's `budget`
+    fn render(
+        &self,
+        model: &Self::Model,
+        options: &RenderOptions,
+        budget: &Budget,
+        warnings: &mut Vec<DiagramWarning>,
+    ) -> Result<Drawing, MermaidError>;
+
+    /// Source lines of the diagram's nodes and edges (default: none)
+    fn source_map(&self, _input: &str) -> SourceMap {
+        SourceMap::default()
+    }
+
+    /// Stable ids of the model's edges, in order (default: none)
+    fn edge_ids(&self, _model: &Self::Model) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Plain-English description for screen readers (default: empty)
+    fn summary(&self, _model: &Self::Model) -> String {
+        String::new()
+    }
+
+    /// Node ids in numbering order when `number_nodes` is set (default:
+    /// none)
+    fn node_numbers(&self, _model: &Self::Model, _options: &RenderOptions) -> Vec<NodeId> {
+        Vec::new()
+    }
+
+    /// Positions of the model's nodes by
+    /// [`Node::stable_id`](crate::Node::stable_id) (default: none)
+    fn node_positions(&self, _model: &Self::Model) -> HashMap<String, (usize, usize)> {
+        HashMap::new()
+    }
+}
+
+/// Object-safe view of a `DiagramRenderer` that runs the whole pipeline
+trait Pipeline: Send + Sync {
+    fn detect(&self, input: &str) -> bool;
+    fn run(&self, input: &str, options: &RenderOptions) -> Result<RenderResult, MermaidError>;
+}
+
+impl<R: DiagramRenderer> Pipeline for R {
+    fn detect(&self, input: &str) -> bool {
+        DiagramRenderer::detect(self, input)
+    }
+
+    fn run(&self, input: &str, options: &RenderOptions) -> Result<RenderResult, MermaidError> {
+        let budget = Budget::new(options);
+        let start = Instant::now();
+        let (model, warnings) = self.parse(input, options)?;
+        options.hooks.parsed(input, start);
+        let mut result = render_model(self, model, warnings, options, &budget)?;
+        result.source_map = self.source_map(input);
+        Ok(result)
+    }
+}
+
+/// Lay out and draw a parsed `model`, the stages every entry point shares;
+/// the source map is left empty for the caller to fill in
+pub(crate) fn render_model<R: DiagramRenderer + ?Sized>(
+    renderer: &R,
+    mut model: R::Model,
+    mut warnings: Vec<DiagramWarning>,
+    options: &RenderOptions,
+    budget: &Budget,
+) -> Result<RenderResult, MermaidError> {
+    budget.check_time()?;
+    let start = Instant::now();
+    warnings.extend(renderer.layout(&mut model, options, budget)?);
+    budget.check_time()?;
+    let drawing = renderer.render(&model, options, budget, &mut warnings)?;
+    budget.check_time()?;
+    let legend = separate_legend(&drawing, options);
+    let output = finish_drawing(drawing, options);
+    options.hooks.laid_out(&output, start);
+    Ok(RenderResult {
+        legend,
+        output,
+        warnings,
+        source_map: SourceMap::default(),
+        edge_ids: renderer.edge_ids(&model),
+        summary: renderer.summary(&model),
+        node_numbers: renderer.node_numbers(&model, options),
+        node_positions: renderer.node_positions(&model),
+    })
+}
+
+/// Diagram kinds available for rendering, looked up by name or detected
+/// from the input.
+///
+/// `Registry::default()` holds the built-in kinds: `"flowchart"`,
+/// `"state"`, `"sequence"`, `"pie"` and `"d2"`. Kinds registered later are
+/// tried first during detection, so a plugin can claim input that would
+/// otherwise fall through to D2.
+pub struct Registry {
+    kinds: Vec<(String, Box<dyn Pipeline>)>,
+}
+
+impl Registry {
+    /// A registry with no diagram kinds
+    pub fn new() -> Self {
+        Self { kinds: Vec::new() }
+    }
+
+    /// Add `renderer` under `name`, replacing any kind of the same name
+    pub fn register<R: DiagramRenderer + 'static>(&mut self, name: &str, renderer: R) -> &mut Self {
+        self.kinds.retain(|(n, _)| !n.eq_ignore_ascii_case(name));
+        self.kinds.push((name.to_string(), Box::new(renderer)));
+        self
+    }
+
+    /// Names of the registered kinds, in registration order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.kinds.iter().map(|(n, _)| n.as_str())
+    }
+
+    /// Render `input` with the kind registered under `name`.
+    ///
+    /// Fails with `MermaidError::UnknownDiagram` if there is none.
+    pub fn render(
+        &self,
+        name: &str,
+        input: &str,
+        options: RenderOptions,
+    ) -> Result<RenderResult, MermaidError> {
+        if options.sanitize {
+            return render_sanitized(input, name, options, |input, options| {
+                self.render(name, input, options)
+            });
+        }
+        let (_, kind) = self
+            .kinds
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .ok_or_else(|| MermaidError::UnknownDiagram(name.to_string()))?;
+        kind.run(input, &options)
+    }
+
+    /// Render `input` with the most recently registered kind that detects it
+    pub fn render_detected(
+        &self,
+        input: &str,
+        options: RenderOptions,
+    ) -> Result<RenderResult, MermaidError> {
+        if options.sanitize {
+            return render_sanitized(input, "mermaid", options, |input, options| {
+                self.render_detected(input, options)
+            });
+        }
+        if input.trim().is_empty() {
+            return Err(MermaidError::EmptyInput);
+        }
+        let (_, kind) = self
+            .kinds
+            .iter()
+            .rev()
+            .find(|(_, k)| k.detect(input))
+            .ok_or_else(|| MermaidError::UnknownDiagram(first_line(input).to_string()))?;
+        kind.run(input, &options)
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        let mut registry = Self::new();
+        registry
+            .register("d2", D2Renderer)
+            .register("flowchart", FlowchartRenderer)
+            .register("pie", PieRenderer)
+            .register("state", StateRenderer)
+            .register("sequence", SequenceRenderer);
+        registry
+    }
+}
+
+/// The built-in kinds, shared by the crate's own entry points
+pub(crate) fn builtin() -> &'static Registry {
+    static BUILTIN: OnceLock<Registry> = OnceLock::new();
+    BUILTIN.get_or_init(Registry::default)
+}
+
+fn first_line(input: &str) -> &str {
+    input.trim().lines().next().unwrap_or("")
+}
+
+/// Layout shared by the graph-based kinds
+fn layout_graph(
+    graph: &mut Graph,
+    options: &RenderOptions,
+    budget: &Budget,
+) -> Result<Vec<DiagramWarning>, MermaidError> {
+    budget::check_graph(graph, options)?;
+    layout_within(graph, options, budget)
+}
+
+/// Rendering shared by the graph-based kinds
+fn draw(
+    graph: &Graph,
+    options: &RenderOptions,
+    budget: &Budget,
+    warnings: &mut Vec<DiagramWarning>,
+) -> Result<Drawing, MermaidError> {
+    draw_graph(graph, options, budget, warnings)
+}
+
+pub(crate) struct FlowchartRenderer;
+
+impl DiagramRenderer for FlowchartRenderer {
+    type Model = Graph;
+
+    fn detect(&self, input: &str) -> bool {
+        detect_format(input) == DiagramFormat::Mermaid
+    }
+
+    fn parse(
+        &self,
+        input: &str,
+        options: &RenderOptions,
+    ) -> Result<(Graph, Vec<DiagramWarning>), MermaidError> {
+        if options.lenient {
+            parse_mermaid_lenient(input)
+        } else {
+            Ok((parse_mermaid(input)?, Vec::new()))
+        }
+    }
+
+    fn layout(
+        &self,
+        graph: &mut Graph,
+        options: &RenderOptions,
+        budget: &Budget,
+    ) -> Result<Vec<DiagramWarning>, MermaidError> {
+        layout_graph(graph, options, budget)
+    }
+
+    fn render(
+        &self,
+        graph: &Graph,
+        options: &RenderOptions,
+        budget: &Budget,
+        warnings: &mut Vec<DiagramWarning>,
+    ) -> Result<Drawing, MermaidError> {
+        draw(graph, options, budget, warnings)
+    }
+
+    fn edge_ids(&self, graph: &Graph) -> Vec<String> {
+        graph.edge_ids()
+    }
+
+    fn summary(&self, graph: &Graph) -> String {
+        graph_summary(graph)
+    }
+
+    fn node_numbers(&self, graph: &Graph, options: &RenderOptions) -> Vec<NodeId> {
+        node_numbers(graph, options)
+    }
+
+    fn node_positions(&self, graph: &Graph) -> HashMap<String, (usize, usize)> {
+        graph.node_positions()
+    }
+
+    fn source_map(&self, input: &str) -> SourceMap {
+        SourceMap::from_symbols(&parser::symbols(input))
+    }
+}
+
+pub(crate) struct StateRenderer;
+
+impl DiagramRenderer for StateRenderer {
+    type Model = Graph;
+
+    fn detect(&self, input: &str) -> bool {
+        detect_format(input) == DiagramFormat::StateDiagram
+    }
+
+    fn parse(
+        &self,
+        input: &str,
+        _options: &RenderOptions,
+    ) -> Result<(Graph, Vec<DiagramWarning>), MermaidError> {
+        parse_state_diagram_with_warnings(input)
+    }
+
+    fn layout(
+        &self,
+        graph: &mut Graph,
+        options: &RenderOptions,
+        budget: &Budget,
+    ) -> Result<Vec<DiagramWarning>, MermaidError> {
+        layout_graph(graph, options, budget)
+    }
+
+    fn render(
+        &self,
+        graph: &Graph,
+        options: &RenderOptions,
+        budget: &Budget,
+        warnings: &mut Vec<DiagramWarning>,
+    ) -> Result<Drawing, MermaidError> {
+        draw(graph, options, budget, warnings)
+    }
+
+    fn edge_ids(&self, graph: &Graph) -> Vec<String> {
+        graph.edge_ids()
+    }
+
+    fn summary(&self, graph: &Graph) -> String {
+        graph_summary(graph)
+    }
+
+    fn node_numbers(&self, graph: &Graph, options: &RenderOptions) -> Vec<NodeId> {
+        node_numbers(graph, options)
+    }
+
+    fn node_positions(&self, graph: &Graph) -> HashMap<String, (usize, usize)> {
+        graph.node_positions()
+    }
+
+    fn source_map(&self, input: &str) -> SourceMap {
+        SourceMap::from_symbols(&state_parser::symbols(input))
+    }
+}
+
+pub(crate) struct D2Renderer;
+
+impl DiagramRenderer for D2Renderer {
+    type Model = Graph;
+
+    fn detect(&self, input: &str) -> bool {
+        detect_format(input) == DiagramFormat::D2
+    }
+
+    fn parse(
+        &self,
+        input: &str,
+        _options: &RenderOptions,
+    ) -> Result<(Graph, Vec<DiagramWarning>), MermaidError> {
+        let D2ParseResult { graph, warnings } = parse_d2(input)?;
+        Ok((graph, warnings))
+    }
+
+    fn layout(
+        &self,
+        graph: &mut Graph,
+        options: &RenderOptions,
+        budget: &Budget,
+    ) -> Result<Vec<DiagramWarning>, MermaidError> {
+        layout_graph(graph, options, budget)
+    }
+
+    fn render(
+        &self,
+        graph: &Graph,
+        options: &RenderOptions,
+        budget: &Budget,
+        warnings: &mut Vec<DiagramWarning>,
+    ) -> Result<Drawing, MermaidError> {
+        draw(graph, options, budget, warnings)
+    }
+
+    fn edge_ids(&self, graph: &Graph) -> Vec<String> {
+        graph.edge_ids()
+    }
+
+    fn summary(&self, graph: &Graph) -> String {
+        graph_summary(graph)
+    }
+
+    fn node_numbers(&self, graph: &Graph, options: &RenderOptions) -> Vec<NodeId> {
+        node_numbers(graph, options)
+    }
+
+    fn node_positions(&self, graph: &Graph) -> HashMap<String, (usize, usize)> {
+        graph.node_positions()
+    }
+
+    fn source_map(&self, input: &str) -> SourceMap {
+        SourceMap::from_symbols(&d2_parser::symbols(input))
+    }
+}
+
+pub(crate) struct SequenceRenderer;
+
+impl DiagramRenderer for SequenceRenderer {
+    type Model = SequenceDiagram;
+
+    fn detect(&self, input: &str) -> bool {
+        detect_format(input) == DiagramFormat::SequenceDiagram
+    }
+
+    fn parse(
+        &self,
+        input: &str,
+        options: &RenderOptions,
+    ) -> Result<(SequenceDiagram, Vec<DiagramWarning>), MermaidError> {
+        let diagram = parse_sequence_diagram(input)?;
+        budget::check_counts(diagram.participants.len(), diagram.messages.len(), options)?;
+        let warnings = diagram.warnings.clone();
+        Ok((diagram, warnings))
+    }
+
+    fn render(
+        &self,
+        diagram: &SequenceDiagram,
+        options: &RenderOptions,
+        _budget: &Budget,
+        _warnings: &mut Vec<DiagramWarning>,
+    ) -> Result<Drawing, MermaidError> {
+        Ok(render_sequence_diagram(diagram, options).into())
+    }
+
+    fn summary(&self, diagram: &SequenceDiagram) -> String {
+        sequence_summary(diagram)
+    }
+
+    fn source_map(&self, input: &str) -> SourceMap {
+        SourceMap::from_symbols(&seq_parser::symbols(input))
+    }
+}
+
+pub(crate) struct PieRenderer;
+
+impl DiagramRenderer for PieRenderer {
+    type Model = PieChart;
+
+    fn detect(&self, input: &str) -> bool {
+        detect_format(input) == DiagramFormat::PieChart
+    }
+
+    fn parse(
+        &self,
+        input: &str,
+        options: &RenderOptions,
+    ) -> Result<(PieChart, Vec<DiagramWarning>), MermaidError> {
+        let chart = parse_pie_chart(input)?;
+        budget::check_counts(chart.slices.len(), 0, options)?;
+        let warnings = chart.warnings.clone();
+        Ok((chart, warnings))
+    }
+
+    fn render(
+        &self,
+        chart: &PieChart,
+        options: &RenderOptions,
+        _budget: &Budget,
+        _warnings: &mut Vec<DiagramWarning>,
+    ) -> Result<Drawing, MermaidError> {
+        Ok(render_pie_chart(chart, options).into())
+    }
+
+    fn summary(&self, chart: &PieChart) -> String {
+        pie_summary(chart)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_kinds_match_direct_rendering() {
+        let registry = Registry::default();
+        let inputs = [
+            "flowchart LR\nA --> B",
+            "stateDiagram-v2\n[*] --> Idle",
+            "sequenceDiagram\nAlice->>Bob: Hi",
+            "pie\n\"A\" : 1",
+            "a -> b",
+        ];
+        for input in inputs {
+            let via_registry = registry
+                .render_detected(input, RenderOptions::default())
+                .unwrap();
+            let direct = crate::render_diagram(input, RenderOptions::default()).unwrap();
+            assert_eq!(via_registry, direct, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_unknown_kind_is_an_error() {
+        let registry = Registry::new();
+        assert_eq!(
+            registry.render("pie", "pie\n\"A\" : 1", RenderOptions::default()),
+            Err(MermaidError::UnknownDiagram("pie".to_string()))
+        );
+        assert!(registry
+            .render_detected("A -> B", RenderOptions::default())
+            .is_err());
+    }
+
+    #[test]
+    fn test_register_replaces_same_name() {
+        let mut registry = Registry::default();
+        registry.register("PIE", PieRenderer);
+        assert_eq!(
+            registry.names().collect::<Vec<_>>(),
+            ["d2", "flowchart", "state", "sequence", "PIE"]
+        );
+    }
+}
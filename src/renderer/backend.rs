@@ -1,6 +1,6 @@
 //! Render backend trait for abstracting rendering operations
 
-use crate::grid::JunctionChars;
+use crate::grid::{JunctionChars, LineFlags};
 
 /// Trait for render backends that can draw characters to a 2D surface
 #[allow(dead_code)]
@@ -27,6 +27,17 @@ pub trait RenderBackend {
         chars: &JunctionChars,
     ) -> bool;
 
+    /// Add line segments on `sides` of a cell, redrawing it as the matching
+    /// corner, tee or crossing
+    fn set_sides_with_merge(
+        &mut self,
+        x: usize,
+        y: usize,
+        sides: LineFlags,
+        c: char,
+        chars: &JunctionChars,
+    ) -> bool;
+
     /// Get the dimensions of the rendering surface
     fn dimensions(&self) -> (usize, usize);
 
@@ -0,0 +1,353 @@
+//! Edge bundling: edges converging on one node share a single trunk
+//!
+//! Each incoming edge runs straight out of its source to a bus line just
+//! past the furthest source. The bus feeds one trunk that carries every
+//! edge the rest of the way to a single arrowhead, and every branch meets
+//! the bus at a tee so joins stay visible.
+
+use std::ops::Range;
+
+use crate::grid::{Grid, LineFlags};
+use crate::text::display_width;
+use crate::types::{Direction, Edge, Node};
+
+use super::charset::CharSet;
+use super::edges::{draw_label, get_edge_chars, single_row, style_has_arrow, DroppedLabel};
+
+/// Closest distance (in cells) from the target border to the bus line
+const BUS_OFFSET: usize = 2;
+
+/// Coordinates along the flow axis and across it, independent of direction
+struct Axes {
+    direction: Direction,
+}
+
+impl Axes {
+    /// Grid position of a point given as (along, across)
+    fn at(&self, along: usize, across: usize) -> (usize, usize) {
+        if self.direction.is_horizontal() {
+            (along, across)
+        } else {
+            (across, along)
+        }
+    }
+
+    /// Flow-axis coordinate of the first cell outside `node` on its exit side
+    fn exit(&self, node: &Node) -> Option<usize> {
+        match self.direction {
+            Direction::TB => Some(node.y + node.height),
+            Direction::BT => node.y.checked_sub(1),
+            Direction::LR => Some(node.x + node.width),
+            Direction::RL => node.x.checked_sub(1),
+        }
+    }
+
+    /// Flow-axis coordinate `offset` cells outside `node` on its entry side
+    fn entry(&self, node: &Node, offset: usize) -> Option<usize> {
+        match self.direction {
+            Direction::TB => node.y.checked_sub(offset),
+            Direction::BT => Some(node.y + node.height - 1 + offset),
+            Direction::LR => node.x.checked_sub(offset),
+            Direction::RL => Some(node.x + node.width - 1 + offset),
+        }
+    }
+
+    /// Cross-axis coordinate of the node's centre line
+    fn center(&self, node: &Node) -> usize {
+        if self.direction.is_horizontal() {
+            node.y + node.height / 2
+        } else {
+            node.x + node.width / 2
+        }
+    }
+
+    /// Flow-axis coordinate `steps` cells downstream of `along`
+    fn advance(&self, along: usize, steps: usize) -> Option<usize> {
+        match self.direction {
+            Direction::TB | Direction::LR => Some(along + steps),
+            Direction::BT | Direction::RL => along.checked_sub(steps),
+        }
+    }
+
+    /// Whether `a` comes strictly before `b` in flow order
+    fn before(&self, a: usize, b: usize) -> bool {
+        match self.direction {
+            Direction::TB | Direction::LR => a < b,
+            Direction::BT | Direction::RL => a > b,
+        }
+    }
+}
+
+/// Flow-axis cells of the straight branch from `start` up to (not
+/// including) the bus line
+fn branch_run(start: usize, bus: usize) -> Range<usize> {
+    if start <= bus {
+        start..bus
+    } else {
+        bus + 1..start + 1
+    }
+}
+
+/// Whether an edge from `from` can join a bundle into `to`.
+///
+/// The source must lie entirely upstream of the bus line; back edges and
+/// edges between tightly packed layers keep their own routes. So do edges
+/// whose straight branch would run through another of `nodes`.
+pub fn can_bundle<'a>(
+    from: &Node,
+    to: &Node,
+    direction: Direction,
+    mut nodes: impl Iterator<Item = &'a Node>,
+) -> bool {
+    let axes = Axes { direction };
+    let (Some(start), Some(bus)) = (axes.exit(from), axes.entry(to, BUS_OFFSET)) else {
+        return false;
+    };
+    if start != bus && !axes.before(start, bus) {
+        return false;
+    }
+    let across = axes.center(from);
+    let cells: Vec<_> = branch_run(start, bus)
+        .map(|along| axes.at(along, across))
+        .collect();
+    !nodes.any(|node| {
+        cells.iter().any(|&(x, y)| {
+            (node.x..node.x + node.width).contains(&x)
+                && (node.y..node.y + node.height).contains(&y)
+        })
+    })
+}
+
+/// Flow-axis coordinate of the bus line for `sources` into `to`.
+///
+/// The bus goes just past the furthest source, leaving room for branch
+/// labels, so the edges share one trunk for the rest of the way. It falls
+/// back to [`BUS_OFFSET`] cells before the target when that would put the
+/// bus or trunk through another of `nodes`.
+fn bus_line<'a>(
+    axes: &Axes,
+    to: &Node,
+    sources: &[(&Node, &Edge)],
+    mut nodes: impl Iterator<Item = &'a Node>,
+) -> Option<usize> {
+    let last = axes.entry(to, BUS_OFFSET)?;
+    let arrow_at = axes.entry(to, 1)?;
+    let trunk = axes.center(to);
+    let branch_len = if axes.direction.is_horizontal() {
+        sources
+            .iter()
+            .filter_map(|(_, e)| e.label.as_deref())
+            .map(|l| display_width(&single_row(l)) + 2)
+            .max()
+            .unwrap_or(1)
+    } else {
+        1
+    };
+    let Some(bus) = sources
+        .iter()
+        .filter_map(|(from, _)| axes.exit(from))
+        .reduce(|a, b| if axes.before(a, b) { b } else { a })
+        .and_then(|furthest| axes.advance(furthest, branch_len))
+        .filter(|&bus| axes.before(bus, last))
+    else {
+        return Some(last);
+    };
+
+    let across = sources.iter().map(|(from, _)| axes.center(from));
+    let min = across.clone().fold(trunk, usize::min);
+    let max = across.fold(trunk, usize::max);
+    let cells: Vec<_> = (min..=max)
+        .map(|across| axes.at(bus, across))
+        .chain(branch_run(arrow_at, bus).map(|along| axes.at(along, trunk)))
+        .collect();
+    let blocked = nodes.any(|node| {
+        cells.iter().any(|&(x, y)| {
+            (node.x..node.x + node.width).contains(&x)
+                && (node.y..node.y + node.height).contains(&y)
+        })
+    });
+    Some(if blocked { last } else { bus })
+}
+
+/// Draw all `sources` edges into `to` as one bundle, keeping the bus and
+/// trunk clear of `nodes`.
+///
+/// Callers must check each source with [`can_bundle`] first.
+#[allow(clippy::too_many_arguments)]
+pub fn draw_bundle<'a>(
+    grid: &mut Grid,
+    to: &Node,
+    sources: &[(&Node, &Edge)],
+    nodes: impl Iterator<Item = &'a Node>,
+    chars: &CharSet,
+    direction: Direction,
+    ascii: bool,
+    dropped_labels: &mut Vec<DroppedLabel>,
+    next_marker: &mut usize,
+) {
+    let axes = Axes { direction };
+    let (Some(bus), Some(arrow_at)) = (bus_line(&axes, to, sources, nodes), axes.entry(to, 1))
+    else {
+        return;
+    };
+    let trunk = axes.center(to);
+    let horizontal = direction.is_horizontal();
+    let jchars = chars.to_junction_chars();
+
+    // Branches: straight runs from each source up to (not including) the bus
+    let mut branches = Vec::new();
+    for (from, edge) in sources {
+        let Some(start) = axes.exit(from) else {
+            continue;
+        };
+        let across = axes.center(from);
+        let (h_char, v_char) = get_edge_chars(edge.style, chars, ascii);
+        let line = if horizontal { h_char } else { v_char };
+        let run = branch_run(start, bus);
+        for along in run.clone() {
+            let (x, y) = axes.at(along, across);
+            grid.set_line_with_merge(x, y, line, horizontal, &jchars);
+        }
+        branches.push(across);
+
+        if let Some(lbl) = &edge.label {
+            let len = run.len();
+            let row = single_row(lbl);
+            let width = display_width(&row);
+            if !horizontal && len > 0 {
+                let (x, y) = axes.at(run.start + len / 2, across + 1);
+                draw_label(grid, x, y, lbl);
+            } else if horizontal && len >= width + 2 {
+                let (x, y) = axes.at(run.start + (len - width) / 2, across);
+                grid.draw_text(x, y, &row);
+            } else {
+                dropped_labels.push(DroppedLabel {
+                    marker: format!("[{}]", *next_marker),
+                    label: lbl.clone(),
+                    from: edge.from.clone(),
+                    to: edge.to.clone(),
+                });
+                *next_marker += 1;
+            }
+        }
+    }
+
+    // Bus: one line across all branches, with a tee at every join
+    let min = branches.iter().copied().fold(trunk, usize::min);
+    let max = branches.iter().copied().fold(trunk, usize::max);
+    let upstream_first = matches!(direction, Direction::TB | Direction::LR);
+    for across in min..=max {
+        let back = branches.contains(&across);
+        let forward = across == trunk;
+        let (first, second) = if upstream_first {
+            (back, forward)
+        } else {
+            (forward, back)
+        };
+        let (before, after) = (across > min, across < max);
+        let (sides, line) = if horizontal {
+            (
+                LineFlags {
+                    up: before,
+                    down: after,
+                    left: first,
+                    right: second,
+                },
+                chars.v,
+            )
+        } else {
+            (
+                LineFlags {
+                    up: first,
+                    down: second,
+                    left: before,
+                    right: after,
+                },
+                chars.h,
+            )
+        };
+        let (x, y) = axes.at(bus, across);
+        grid.set_sides_with_merge(x, y, sides, line, &jchars);
+    }
+
+    // Trunk: one line from the bus to a single arrowhead into the target
+    let line = if horizontal { chars.h } else { chars.v };
+    for along in branch_run(arrow_at, bus).filter(|&along| along != arrow_at) {
+        let (x, y) = axes.at(along, trunk);
+        grid.set_line_with_merge(x, y, line, horizontal, &jchars);
+    }
+    let has_arrow = sources.iter().any(|(_, e)| style_has_arrow(e.style));
+    let arrow = match direction {
+        Direction::TB => chars.arr_d,
+        Direction::BT => chars.arr_u,
+        Direction::LR => chars.arr_r,
+        Direction::RL => chars.arr_l,
+    };
+    let (x, y) = axes.at(arrow_at, trunk);
+    if has_arrow {
+        grid.queue_arrow(x, y, arrow);
+    } else {
+        grid.set_line_with_merge(x, y, line, horizontal, &jchars);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node_at(id: &str, x: usize, y: usize) -> Node {
+        let mut node = Node::new(id.to_string(), id.to_string());
+        node.x = x;
+        node.y = y;
+        node.width = 5;
+        node.height = 3;
+        node
+    }
+
+    #[test]
+    fn test_can_bundle_requires_room_upstream() {
+        let target = node_at("T", 0, 10);
+        assert!(can_bundle(
+            &node_at("A", 0, 0),
+            &target,
+            Direction::TB,
+            [].iter()
+        ));
+        // Source border ends right where the bus would be
+        assert!(can_bundle(
+            &node_at("A", 0, 5),
+            &target,
+            Direction::TB,
+            [].iter()
+        ));
+        assert!(!can_bundle(
+            &node_at("A", 0, 6),
+            &target,
+            Direction::TB,
+            [].iter()
+        ));
+        // Back edge
+        assert!(!can_bundle(
+            &node_at("A", 0, 20),
+            &target,
+            Direction::TB,
+            [].iter()
+        ));
+        assert!(can_bundle(
+            &node_at("A", 0, 20),
+            &target,
+            Direction::BT,
+            [].iter()
+        ));
+    }
+
+    #[test]
+    fn test_can_bundle_refuses_branches_through_nodes() {
+        let target = node_at("T", 0, 20);
+        let source = node_at("A", 0, 0);
+        let beside = [node_at("B", 10, 8)];
+        assert!(can_bundle(&source, &target, Direction::TB, beside.iter()));
+        let between = [node_at("B", 1, 8)];
+        assert!(!can_bundle(&source, &target, Direction::TB, between.iter()));
+    }
+}
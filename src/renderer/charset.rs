@@ -2,42 +2,97 @@
 
 use crate::grid::JunctionChars;
 
-/// Unicode box-drawing characters
+/// Glyphs the graph renderer draws boxes, lines and arrows with.
+///
+/// [`CharSet::unicode`] and [`CharSet::ascii`] are what the `ascii` option
+/// picks between; set [`RenderOptions::charset`](crate::RenderOptions::charset)
+/// to draw with [`CharSet::heavy`] or a set of your own instead. Glyphs
+/// outside the set, like dotted lines and D2 arrowheads, still follow
+/// `ascii`.
+///
+/// # Example
+/// ```
+/// use graphs_tui::{render_mermaid_to_tui, CharSet, RenderOptions};
+///
+/// let options = RenderOptions {
+///     charset: Some(CharSet::heavy()),
+///     ..Default::default()
+/// };
+/// let result = render_mermaid_to_tui("flowchart LR\nA --> B", options).unwrap();
+/// assert!(result.output.starts_with("┏━━━┓"));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct CharSet {
-    pub tl: char,    // top-left corner
-    pub tr: char,    // top-right corner
-    pub bl: char,    // bottom-left corner
-    pub br: char,    // bottom-right corner
-    pub h: char,     // horizontal line
-    pub v: char,     // vertical line
-    pub arr_r: char, // arrow right
-    pub arr_l: char, // arrow left
-    pub arr_d: char, // arrow down
-    pub arr_u: char, // arrow up
-    // Diagonal arrows for non-orthogonal edges
-    pub arr_dr: char, // arrow down-right (◢)
-    pub arr_dl: char, // arrow down-left (◣)
-    pub arr_ur: char, // arrow up-right (◥)
-    pub arr_ul: char, // arrow up-left (◤)
-    // Rounded corners
+    /// Top-left corner (┌)
+    pub tl: char,
+    /// Top-right corner (┐)
+    pub tr: char,
+    /// Bottom-left corner (└)
+    pub bl: char,
+    /// Bottom-right corner (┘)
+    pub br: char,
+    /// Horizontal line (─)
+    pub h: char,
+    /// Vertical line (│)
+    pub v: char,
+    /// Arrow right (▶)
+    pub arr_r: char,
+    /// Arrow left (◀)
+    pub arr_l: char,
+    /// Arrow down (▼)
+    pub arr_d: char,
+    /// Arrow up (▲)
+    pub arr_u: char,
+    /// Arrow down-right, for diagonal edges (◢)
+    pub arr_dr: char,
+    /// Arrow down-left (◣)
+    pub arr_dl: char,
+    /// Arrow up-right (◥)
+    pub arr_ur: char,
+    /// Arrow up-left (◤)
+    pub arr_ul: char,
+    /// Diagonal descending left-to-right (╲)
+    pub diag_down: char,
+    /// Diagonal ascending left-to-right (╱)
+    pub diag_up: char,
+    /// Rounded top-left corner (╭)
     pub rtl: char,
+    /// Rounded top-right corner (╮)
     pub rtr: char,
+    /// Rounded bottom-left corner (╰)
     pub rbl: char,
+    /// Rounded bottom-right corner (╯)
     pub rbr: char,
-    // T-junctions (for cylinder separators)
-    pub ml: char, // middle-left (├)
-    pub mr: char, // middle-right (┤)
-    // Double lines for subgraphs
+    /// Tee into the left side, for dividers (├)
+    pub ml: char,
+    /// Tee into the right side (┤)
+    pub mr: char,
+    /// Double horizontal line, for subgraphs, tables and thick edges (═)
     pub dh: char,
+    /// Double vertical line (║)
     pub dv: char,
+    /// Double top-left corner (╔)
     pub dtl: char,
+    /// Double top-right corner (╗)
     pub dtr: char,
+    /// Double bottom-left corner (╚)
     pub dbl: char,
+    /// Double bottom-right corner (╝)
     pub dbr: char,
-    // Junction characters for overlapping lines
-    pub cross: char,  // cross (┼)
-    pub t_up: char,   // T pointing up (┴)
-    pub t_down: char, // T pointing down (┬)
+    /// Double tee into the left side (╠)
+    pub dml: char,
+    /// Double tee into the right side (╣)
+    pub dmr: char,
+    /// Edge crossing a double top or bottom border (╪)
+    pub gate_h: char,
+    /// Edge crossing a double left or right border (╫)
+    pub gate_v: char,
+    /// Lines crossing (┼)
+    pub cross: char,
+    /// Tee pointing up (┴)
+    pub t_up: char,
+    /// Tee pointing down (┬)
+    pub t_down: char,
 }
 
 pub const UNICODE_CHARS: CharSet = CharSet {
@@ -55,6 +110,8 @@ pub const UNICODE_CHARS: CharSet = CharSet {
     arr_dl: '◣',
     arr_ur: '◥',
     arr_ul: '◤',
+    diag_down: '╲',
+    diag_up: '╱',
     rtl: '╭',
     rtr: '╮',
     rbl: '╰',
@@ -67,6 +124,10 @@ pub const UNICODE_CHARS: CharSet = CharSet {
     dtr: '╗',
     dbl: '╚',
     dbr: '╝',
+    dml: '╠',
+    dmr: '╣',
+    gate_h: '╪',
+    gate_v: '╫',
     cross: '┼',
     t_up: '┴',
     t_down: '┬',
@@ -87,6 +148,8 @@ pub const ASCII_CHARS: CharSet = CharSet {
     arr_dl: '/',
     arr_ur: '/',
     arr_ul: '\\',
+    diag_down: '\\',
+    diag_up: '/',
     rtl: '+',
     rtr: '+',
     rbl: '+',
@@ -99,12 +162,100 @@ pub const ASCII_CHARS: CharSet = CharSet {
     dtr: '#',
     dbl: '#',
     dbr: '#',
+    dml: '#',
+    dmr: '#',
+    gate_h: '+',
+    gate_v: '+',
     cross: '+',
     t_up: '+',
     t_down: '+',
 };
 
+/// Heavy box-drawing characters, for emphasized diagrams
+pub const HEAVY_CHARS: CharSet = CharSet {
+    tl: '┏',
+    tr: '┓',
+    bl: '┗',
+    br: '┛',
+    h: '━',
+    v: '┃',
+    rtl: '┏',
+    rtr: '┓',
+    rbl: '┗',
+    rbr: '┛',
+    ml: '┣',
+    mr: '┫',
+    cross: '╋',
+    t_up: '┻',
+    t_down: '┳',
+    ..UNICODE_CHARS
+};
+
 impl CharSet {
+    /// Light Unicode box drawing (`┌─┐`), the default
+    pub fn unicode() -> Self {
+        UNICODE_CHARS
+    }
+
+    /// Plain ASCII (`+-+`), as drawn with the `ascii` option
+    pub fn ascii() -> Self {
+        ASCII_CHARS
+    }
+
+    /// Heavy Unicode box drawing (`┏━┓`)
+    pub fn heavy() -> Self {
+        HEAVY_CHARS
+    }
+
+    /// This set with double lines for box borders, for highlighted nodes
+    pub(crate) fn doubled(&self) -> CharSet {
+        CharSet {
+            tl: self.dtl,
+            tr: self.dtr,
+            bl: self.dbl,
+            br: self.dbr,
+            h: self.dh,
+            v: self.dv,
+            rtl: self.dtl,
+            rtr: self.dtr,
+            rbl: self.dbl,
+            rbr: self.dbr,
+            ml: self.dml,
+            mr: self.dmr,
+            ..*self
+        }
+    }
+
+    /// Whether `c` is one of this set's border or line glyphs
+    pub fn is_border_glyph(&self, c: char) -> bool {
+        [
+            self.tl,
+            self.tr,
+            self.bl,
+            self.br,
+            self.h,
+            self.v,
+            self.rtl,
+            self.rtr,
+            self.rbl,
+            self.rbr,
+            self.ml,
+            self.mr,
+            self.dh,
+            self.dv,
+            self.dtl,
+            self.dtr,
+            self.dbl,
+            self.dbr,
+            self.dml,
+            self.dmr,
+            self.cross,
+            self.t_up,
+            self.t_down,
+        ]
+        .contains(&c)
+    }
+
     /// Convert to JunctionChars for grid line merging
     pub fn to_junction_chars(&self) -> JunctionChars {
         JunctionChars {
@@ -113,6 +264,10 @@ impl CharSet {
             t_down: self.t_down,
             ml: self.ml,
             mr: self.mr,
+            tl: self.tl,
+            tr: self.tr,
+            bl: self.bl,
+            br: self.br,
         }
     }
 }
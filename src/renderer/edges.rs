@@ -1,9 +1,11 @@
 //! Edge drawing and routing functions
 
-use crate::grid::Grid;
+use crate::grid::{Grid, LineFlags};
 use crate::pathfinding::{PathGrid, Pos};
 use crate::text::display_width;
-use crate::types::{Direction, Edge, EdgeStyle, Node};
+use crate::types::{
+    Arrowhead, Direction, Edge, EdgeRouting, EdgeStyle, LabelAlong, LabelPlacement, LabelSide, Node,
+};
 
 use super::charset::CharSet;
 
@@ -15,6 +17,70 @@ pub struct DroppedLabel {
     pub to: String,
 }
 
+/// Display width of a label's widest line
+pub fn label_width(label: &str) -> usize {
+    label.lines().map(display_width).max().unwrap_or(0)
+}
+
+/// A multi-line label joined onto one row, for spots only one row tall
+pub fn single_row(label: &str) -> String {
+    label.lines().collect::<Vec<_>>().join(" ")
+}
+
+/// Top row of a label whose lines are centered on row `mid`
+fn label_top(mid: usize, label: &str) -> usize {
+    mid.saturating_sub(label.lines().count().saturating_sub(1) / 2)
+}
+
+/// Offset of a label `size` cells long within a run of `len` cells that
+/// starts at the source end, or at the target end when `reversed`.
+///
+/// Off-center labels keep one cell of line by the source and two by the
+/// target, leaving the arrowhead clear.
+fn along_offset(len: usize, size: usize, along: LabelAlong, reversed: bool) -> usize {
+    let slack = len.saturating_sub(size);
+    let offset = match along {
+        LabelAlong::Source => slack.min(1),
+        LabelAlong::Center => return slack / 2,
+        LabelAlong::Target => slack - slack.min(2),
+    };
+    if reversed {
+        slack - offset
+    } else {
+        offset
+    }
+}
+
+/// Row to center a label on beside a vertical run of `len` rows from `from`
+fn beside_mid(from: usize, len: usize, label: &str, along: LabelAlong, reversed: bool) -> usize {
+    if along == LabelAlong::Center {
+        return from + len / 2;
+    }
+    let rows = label.lines().count().max(1);
+    from + along_offset(len, rows, along, reversed) + (rows - 1) / 2
+}
+
+/// Draw a one-row label over a horizontal line, or on the row above it
+/// when `side` asks for that and the cells there are free
+fn draw_row_label(grid: &mut Grid, x: usize, y: usize, row: &str, side: LabelSide) {
+    let above_free = y > 0
+        && (0..display_width(row))
+            .all(|dx| grid.get(x + dx, y - 1) == Some(' ') && !grid.is_protected(x + dx, y - 1));
+    if side == LabelSide::Above && above_free {
+        grid.draw_text(x, y - 1, row);
+    } else {
+        grid.draw_text(x, y, row);
+    }
+}
+
+/// Draw a label's lines on successive rows, centered on row `mid`
+pub fn draw_label(grid: &mut Grid, x: usize, mid: usize, label: &str) {
+    let top = label_top(mid, label);
+    for (dy, line) in label.lines().enumerate() {
+        grid.draw_text(x, top + dy, line);
+    }
+}
+
 /// Get line characters for edge style
 pub fn get_edge_chars(style: EdgeStyle, chars: &CharSet, ascii: bool) -> (char, char) {
     match style {
@@ -27,6 +93,38 @@ pub fn get_edge_chars(style: EdgeStyle, chars: &CharSet, ascii: bool) -> (char,
             }
         }
         EdgeStyle::ThickArrow | EdgeStyle::ThickLine => (chars.dh, chars.dv),
+        EdgeStyle::Wavy => {
+            if ascii {
+                ('~', '(')
+            } else {
+                ('∿', '≀')
+            }
+        }
+        // Never drawn
+        EdgeStyle::Invisible => (' ', ' '),
+    }
+}
+
+/// Get the `(descending, ascending)` diagonal characters for edge style
+pub fn get_diagonal_chars(style: EdgeStyle, chars: &CharSet, ascii: bool) -> (char, char) {
+    match style {
+        EdgeStyle::Arrow | EdgeStyle::Line | EdgeStyle::Wavy => (chars.diag_down, chars.diag_up),
+        EdgeStyle::DottedArrow | EdgeStyle::DottedLine => {
+            if ascii {
+                ('.', '.')
+            } else {
+                ('⋱', '⋰')
+            }
+        }
+        EdgeStyle::ThickArrow | EdgeStyle::ThickLine => {
+            if ascii {
+                ('#', '#')
+            } else {
+                ('⧹', '⧸')
+            }
+        }
+        // Never drawn
+        EdgeStyle::Invisible => (' ', ' '),
     }
 }
 
@@ -34,17 +132,46 @@ pub fn get_edge_chars(style: EdgeStyle, chars: &CharSet, ascii: bool) -> (char,
 pub fn style_has_arrow(style: EdgeStyle) -> bool {
     matches!(
         style,
-        EdgeStyle::Arrow | EdgeStyle::DottedArrow | EdgeStyle::ThickArrow
+        EdgeStyle::Arrow | EdgeStyle::DottedArrow | EdgeStyle::ThickArrow | EdgeStyle::Wavy
     )
 }
 
+/// Glyph for an arrowhead on an edge travelling `heading` into a node
+pub fn arrowhead_char(
+    head: Arrowhead,
+    heading: (isize, isize),
+    chars: &CharSet,
+    ascii: bool,
+) -> char {
+    let (unicode, plain) = match (head, heading.0.signum(), heading.1.signum()) {
+        (Arrowhead::Triangle, _, 1) => (chars.arr_d, chars.arr_d),
+        (Arrowhead::Triangle, _, -1) => (chars.arr_u, chars.arr_u),
+        (Arrowhead::Triangle, -1, _) => (chars.arr_l, chars.arr_l),
+        (Arrowhead::Triangle, _, _) => (chars.arr_r, chars.arr_r),
+        (Arrowhead::Circle, _, _) => ('○', 'o'),
+        (Arrowhead::Diamond, _, _) => ('◇', '*'),
+        (Arrowhead::CfOne, _, 0) => ('┼', '|'),
+        (Arrowhead::CfOne, _, _) => ('┼', '-'),
+        // The prongs open toward the node
+        (Arrowhead::CfMany, _, 1) => ('∧', '^'),
+        (Arrowhead::CfMany, _, -1) => ('∨', 'v'),
+        (Arrowhead::CfMany, -1, _) => ('>', '>'),
+        (Arrowhead::CfMany, _, _) => ('<', '<'),
+    };
+    if ascii {
+        plain
+    } else {
+        unicode
+    }
+}
+
 /// Draw a path found by A* pathfinding
 pub fn draw_astar_path(
     grid: &mut Grid,
     path: &[Pos],
     h_char: char,
     v_char: char,
-    arrow_char: char,
+    arrow_char: Option<char>,
     chars: &CharSet,
 ) {
     if path.is_empty() {
@@ -57,14 +184,18 @@ pub fn draw_astar_path(
         let pos = path[i];
 
         if i == path.len() - 1 {
-            // Last position - draw arrow, check if diagonal
-            let final_arrow = if i > 0 {
-                let prev = path[i - 1];
-                get_arrow_for_direction(prev, pos, arrow_char, chars)
-            } else {
-                arrow_char
-            };
-            grid.set_if_empty(pos.x, pos.y, final_arrow);
+            // Last position is the cell in front of the target: the arrow
+            // points into the target whichever way the path arrived
+            let prev = i.checked_sub(1).map(|j| path[j]);
+            match (arrow_char, prev) {
+                (Some(arrow), _) => grid.queue_arrow(pos.x, pos.y, arrow),
+                (None, Some(prev)) if prev.y == pos.y => {
+                    grid.set_line_with_merge(pos.x, pos.y, h_char, true, &jchars);
+                }
+                (None, _) => {
+                    grid.set_line_with_merge(pos.x, pos.y, v_char, false, &jchars);
+                }
+            }
         } else {
             // Determine direction
             let next = path[i + 1];
@@ -75,8 +206,8 @@ pub fn draw_astar_path(
 
             if let (true, Some(prev_pos)) = (is_turn, prev) {
                 // Draw corner
-                let corner = determine_corner(prev_pos, pos, next, chars);
-                grid.set_if_empty(pos.x, pos.y, corner);
+                let sides = corner_sides(prev_pos, pos, next);
+                grid.set_sides_with_merge(pos.x, pos.y, sides, h_char, &jchars);
             } else if is_horizontal {
                 grid.set_line_with_merge(pos.x, pos.y, h_char, true, &jchars);
             } else {
@@ -104,32 +235,21 @@ pub fn get_arrow_for_direction(from: Pos, to: Pos, default_arrow: char, chars: &
     }
 }
 
-/// Determine the corner character based on path direction
-fn determine_corner(prev: Pos, curr: Pos, next: Pos, chars: &CharSet) -> char {
-    let from_left = prev.x < curr.x;
-    let from_right = prev.x > curr.x;
-    let from_above = prev.y < curr.y;
-    let from_below = prev.y > curr.y;
-
-    let to_right = next.x > curr.x;
-    let to_left = next.x < curr.x;
-    let to_below = next.y > curr.y;
-    let to_above = next.y < curr.y;
-
-    // Determine corner type
-    if (from_left && to_below) || (from_above && to_right) {
-        chars.tr // ┐ or coming from left going down, or from above going right
-    } else if (from_right && to_below) || (from_above && to_left) {
-        chars.tl // ┌
-    } else if (from_left && to_above) || (from_below && to_right) {
-        chars.br // ┘
-    } else if (from_right && to_above) || (from_below && to_left) {
-        chars.bl // └
-    } else {
-        chars.cross // Default to cross if unclear
+/// Side of `curr` that faces the orthogonally adjacent cell `other`
+fn side_toward(curr: Pos, other: Pos) -> LineFlags {
+    LineFlags {
+        up: other.y < curr.y,
+        down: other.y > curr.y,
+        left: other.x < curr.x,
+        right: other.x > curr.x,
     }
 }
 
+/// Sides of a turn cell: toward where the path came from and where it goes
+fn corner_sides(prev: Pos, curr: Pos, next: Pos) -> LineFlags {
+    side_toward(curr, prev).union(side_toward(curr, next))
+}
+
 /// Draw an edge between two nodes using A* pathfinding when beneficial
 pub fn draw_edge(
     grid: &mut Grid,
@@ -140,43 +260,166 @@ pub fn draw_edge(
     chars: &CharSet,
     direction: Direction,
     ascii: bool,
+    routing: EdgeRouting,
+    cross_border: bool,
+    placement: LabelPlacement,
+    dropped_labels: &mut Vec<DroppedLabel>,
+    next_marker: &mut usize,
+) {
+    draw_edge_line(
+        grid,
+        path_grid,
+        from,
+        to,
+        edge,
+        chars,
+        direction,
+        ascii,
+        routing,
+        cross_border,
+        placement,
+        dropped_labels,
+        next_marker,
+    );
+
+    // The source terminator points back into the source from the cell the
+    // edge leaves it by
+    if let Some(head) = edge.source_arrowhead {
+        let (start, _) = route_endpoints(from, to, direction);
+        if grid.get(start.x, start.y).is_some_and(|c| c != ' ') {
+            let (dx, dy) = direction_heading(direction);
+            grid.queue_arrow(
+                start.x,
+                start.y,
+                arrowhead_char(head, (-dx, -dy), chars, ascii),
+            );
+        }
+    }
+}
+
+/// Unit step along the flow direction
+fn direction_heading(direction: Direction) -> (isize, isize) {
+    match direction {
+        Direction::TB => (0, 1),
+        Direction::BT => (0, -1),
+        Direction::LR => (1, 0),
+        Direction::RL => (-1, 0),
+    }
+}
+
+/// Draw the line of an edge and its target arrowhead
+fn draw_edge_line(
+    grid: &mut Grid,
+    path_grid: &PathGrid,
+    from: &Node,
+    to: &Node,
+    edge: &Edge,
+    chars: &CharSet,
+    direction: Direction,
+    ascii: bool,
+    routing: EdgeRouting,
+    cross_border: bool,
+    placement: LabelPlacement,
     dropped_labels: &mut Vec<DroppedLabel>,
     next_marker: &mut usize,
 ) {
-    let has_arrow = style_has_arrow(edge.style);
+    let has_arrow = style_has_arrow(edge.style) || edge.target_arrowhead.is_some();
     let (h_char, v_char) = get_edge_chars(edge.style, chars, ascii);
 
-    let (start_x, start_y, end_x, end_y, arrow_char) = match direction {
+    let heading = direction_heading(direction);
+    let head = edge.target_arrowhead.unwrap_or(Arrowhead::Triangle);
+    let arrow_char = has_arrow.then(|| arrowhead_char(head, heading, chars, ascii));
+
+    let (start_x, start_y, end_x, end_y) = match direction {
         Direction::LR => (
             from.x + from.width,
             from.y + from.height / 2,
             to.x,
             to.y + to.height / 2,
-            if has_arrow { chars.arr_r } else { h_char },
         ),
         Direction::RL => (
             from.x,
             from.y + from.height / 2,
             to.x + to.width,
             to.y + to.height / 2,
-            if has_arrow { chars.arr_l } else { h_char },
         ),
         Direction::TB => (
             from.x + from.width / 2,
             from.y + from.height,
             to.x + to.width / 2,
             to.y,
-            if has_arrow { chars.arr_d } else { v_char },
         ),
         Direction::BT => (
             from.x + from.width / 2,
             from.y,
             to.x + to.width / 2,
             to.y + to.height,
-            if has_arrow { chars.arr_u } else { v_char },
         ),
     };
 
+    if routing == EdgeRouting::Diagonal
+        && start_x != end_x
+        && start_y != end_y
+        && draw_diagonal_edge(
+            grid,
+            path_grid,
+            from,
+            to,
+            edge,
+            chars,
+            direction,
+            ascii,
+            h_char,
+            v_char,
+            has_arrow,
+            placement.along,
+            dropped_labels,
+            next_marker,
+        )
+    {
+        return;
+    }
+
+    // The arrowhead sits on the cell the flow direction enters the target from
+    let arrow_cell = match direction {
+        Direction::LR => to.x.checked_sub(1).map(|x| (x, end_y)),
+        Direction::RL => Some((end_x, end_y)),
+        Direction::TB => to.y.checked_sub(1).map(|y| (end_x, y)),
+        Direction::BT => Some((end_x, end_y)),
+    };
+    let arrow = arrow_char.zip(arrow_cell).map(|(c, (x, y))| (x, y, c));
+
+    // Edges entering or leaving a subgraph go around its border and cross
+    // it only at gateway cells; long edges run through their reserved
+    // waypoints
+    if cross_border || !edge.waypoints.is_empty() {
+        let (start, goal) = route_endpoints(from, to, direction);
+        let route = if edge.waypoints.is_empty() {
+            path_grid.find_path_fewest_turns(start, goal, heading)
+        } else {
+            route_via_waypoints(path_grid, start, goal, &edge.waypoints, heading)
+        };
+        if let Some(mut path) = route {
+            // Lead in from the source border so the first turn gets a corner
+            let anchor = Pos::new(
+                start.x.saturating_add_signed(-heading.0),
+                start.y.saturating_add_signed(-heading.1),
+            );
+            path.insert(0, anchor);
+            draw_astar_path(grid, &path, h_char, v_char, arrow_char, chars);
+            draw_path_label(
+                grid,
+                &path,
+                edge,
+                placement.along,
+                false,
+                dropped_labels,
+                next_marker,
+            );
+            return;
+        }
+    }
+
     // Try A* pathfinding for non-straight edges
     let use_astar = start_x != end_x && start_y != end_y;
     if use_astar {
@@ -184,29 +427,15 @@ pub fn draw_edge(
         {
             // Draw the A* path
             draw_astar_path(grid, &path, h_char, v_char, arrow_char, chars);
-
-            // Handle label for A* path
-            if let Some(lbl) = &edge.label {
-                // Try to place label in the middle of the path
-                if path.len() > 2 {
-                    let mid_idx = path.len() / 2;
-                    let mid_pos = path[mid_idx];
-                    // Draw label to the right/below the mid point
-                    for (i, c) in lbl.chars().enumerate() {
-                        grid.set_if_empty(mid_pos.x + 1 + i, mid_pos.y, c);
-                    }
-                } else {
-                    // Path too short for inline label - drop to legend
-                    let marker_text = format!("[{}]", *next_marker);
-                    dropped_labels.push(DroppedLabel {
-                        marker: marker_text,
-                        label: lbl.clone(),
-                        from: edge.from.clone(),
-                        to: edge.to.clone(),
-                    });
-                    *next_marker += 1;
-                }
-            }
+            draw_path_label(
+                grid,
+                &path,
+                edge,
+                placement.along,
+                false,
+                dropped_labels,
+                next_marker,
+            );
             return;
         }
     }
@@ -221,9 +450,10 @@ pub fn draw_edge(
             end_y,
             h_char,
             v_char,
-            arrow_char,
+            arrow,
             direction,
             edge.label.as_deref(),
+            placement,
             chars,
             &edge.from,
             &edge.to,
@@ -239,9 +469,10 @@ pub fn draw_edge(
             end_y,
             h_char,
             v_char,
-            arrow_char,
+            arrow,
             direction,
             edge.label.as_deref(),
+            placement,
             chars,
             &edge.from,
             &edge.to,
@@ -251,6 +482,222 @@ pub fn draw_edge(
     }
 }
 
+/// Label an A*-routed edge beside its midpoint, or drop it to the legend
+/// when the path is too short
+fn draw_path_label(
+    grid: &mut Grid,
+    path: &[Pos],
+    edge: &Edge,
+    along: LabelAlong,
+    apart: bool,
+    dropped_labels: &mut Vec<DroppedLabel>,
+    next_marker: &mut usize,
+) {
+    let Some(lbl) = &edge.label else {
+        return;
+    };
+    // Draw label to the right of a path cell, starting at the midpoint and
+    // moving outward, where it neither crosses a border nor hides the arrow.
+    // A label kept `apart` leaves a gap and covers no part of the path.
+    let arrow = path.last().copied();
+    let gap = usize::from(apart);
+    let fits = |pos: Pos| {
+        let top = label_top(pos.y, lbl);
+        lbl.lines().enumerate().all(|(dy, line)| {
+            (1..=display_width(line) + gap).all(|dx| {
+                let cell = Pos::new(pos.x + dx, top + dy);
+                !grid.is_protected(cell.x, cell.y)
+                    && Some(cell) != arrow
+                    && !(apart && path.contains(&cell))
+            })
+        })
+    };
+    let mid = match along {
+        LabelAlong::Source => 1,
+        LabelAlong::Center => path.len() / 2,
+        LabelAlong::Target => path.len().saturating_sub(2),
+    };
+    let spot = (0..path.len())
+        .flat_map(|d| [mid.checked_sub(d), Some(mid + d)])
+        .flatten()
+        .filter(|&i| i > 0 && i + 1 < path.len())
+        .map(|i| path[i])
+        .find(|&pos| fits(pos));
+    if let Some(pos) = spot {
+        draw_label(grid, pos.x + 1 + gap, pos.y, lbl);
+    } else {
+        // No room for an inline label - drop to legend
+        dropped_labels.push(DroppedLabel {
+            marker: format!("[{}]", *next_marker),
+            label: lbl.clone(),
+            from: edge.from.clone(),
+            to: edge.to.clone(),
+        });
+        *next_marker += 1;
+    }
+}
+
+/// Chain fewest-turn paths from `start` through each waypoint to `goal`.
+///
+/// Returns `None` if any leg is blocked.
+fn route_via_waypoints(
+    path_grid: &PathGrid,
+    start: Pos,
+    goal: Pos,
+    waypoints: &[(usize, usize)],
+    heading: (isize, isize),
+) -> Option<Vec<Pos>> {
+    let stops = waypoints.iter().map(|&(x, y)| Pos::new(x, y));
+    let mut path = vec![start];
+    for stop in stops.chain(std::iter::once(goal)) {
+        let from = *path.last()?;
+        let leg = path_grid.find_path_fewest_turns(from, stop, heading)?;
+        path.extend(leg.into_iter().skip_while(|&p| p == from));
+    }
+    Some(path)
+}
+
+/// First cell outside `from` and last cell before `to` along the flow direction
+fn route_endpoints(from: &Node, to: &Node, direction: Direction) -> (Pos, Pos) {
+    let from_cx = from.x + from.width / 2;
+    let from_cy = from.y + from.height / 2;
+    let to_cx = to.x + to.width / 2;
+    let to_cy = to.y + to.height / 2;
+    match direction {
+        Direction::TB => (
+            Pos::new(from_cx, from.y + from.height),
+            Pos::new(to_cx, to.y.saturating_sub(1)),
+        ),
+        Direction::BT => (
+            Pos::new(from_cx, from.y.saturating_sub(1)),
+            Pos::new(to_cx, to.y + to.height),
+        ),
+        Direction::LR => (
+            Pos::new(from.x + from.width, from_cy),
+            Pos::new(to.x.saturating_sub(1), to_cy),
+        ),
+        Direction::RL => (
+            Pos::new(from.x.saturating_sub(1), from_cy),
+            Pos::new(to.x + to.width, to_cy),
+        ),
+    }
+}
+
+/// Route an edge with 45° diagonal segments allowed.
+///
+/// The path leaves `from` with a straight step out of its border, so it
+/// stays attached to the box, and ends on the cell just outside `to`; the
+/// final cell carries the arrowhead, which may itself be diagonal.
+/// Returns false (drawing nothing) if no route exists.
+fn draw_diagonal_edge(
+    grid: &mut Grid,
+    path_grid: &PathGrid,
+    from: &Node,
+    to: &Node,
+    edge: &Edge,
+    chars: &CharSet,
+    direction: Direction,
+    ascii: bool,
+    h_char: char,
+    v_char: char,
+    has_arrow: bool,
+    along: LabelAlong,
+    dropped_labels: &mut Vec<DroppedLabel>,
+    next_marker: &mut usize,
+) -> bool {
+    let (start, goal) = route_endpoints(from, to, direction);
+    let (to_cx, to_cy) = (to.x + to.width / 2, to.y + to.height / 2);
+    let heading = direction_heading(direction);
+    let lead = Pos::new(
+        start.x.saturating_add_signed(heading.0),
+        start.y.saturating_add_signed(heading.1),
+    );
+    let route = if start == goal {
+        Some(vec![start])
+    } else {
+        path_grid.find_path_diagonal(lead, goal).map(|leg| {
+            let mut path = vec![start];
+            path.extend(leg);
+            path
+        })
+    };
+    let Some(path) = route else {
+        return false;
+    };
+
+    let jchars = chars.to_junction_chars();
+    let (diag_down, diag_up) = get_diagonal_chars(edge.style, chars, ascii);
+    let step_char = |a: Pos, b: Pos| -> char {
+        match (a.x == b.x, a.y == b.y) {
+            (true, _) => v_char,
+            (_, true) => h_char,
+            _ if (b.x > a.x) == (b.y > a.y) => diag_down,
+            _ => diag_up,
+        }
+    };
+
+    for (i, &pos) in path.iter().enumerate() {
+        let prev = i.checked_sub(1).map(|j| path[j]);
+        let next = path.get(i + 1).copied();
+        match (prev, next) {
+            (Some(p), None) => {
+                if has_arrow {
+                    let arrow = match edge.target_arrowhead {
+                        Some(head) if head != Arrowhead::Triangle => {
+                            let heading =
+                                (pos.x as isize - p.x as isize, pos.y as isize - p.y as isize);
+                            arrowhead_char(head, heading, chars, ascii)
+                        }
+                        _ => get_arrow_for_direction(p, pos, step_char(p, pos), chars),
+                    };
+                    grid.queue_arrow(pos.x, pos.y, arrow);
+                } else {
+                    grid.set_if_empty(pos.x, pos.y, step_char(p, pos));
+                }
+            }
+            (Some(p), Some(n)) => {
+                let in_diag = p.x != pos.x && p.y != pos.y;
+                let out_diag = pos.x != n.x && pos.y != n.y;
+                if !in_diag && !out_diag && (p.y == pos.y) != (pos.y == n.y) {
+                    let sides = corner_sides(p, pos, n);
+                    grid.set_sides_with_merge(pos.x, pos.y, sides, h_char, &jchars);
+                } else if in_diag {
+                    grid.set_if_empty(pos.x, pos.y, step_char(p, pos));
+                } else if out_diag {
+                    // Only the straight run in reaches this cell, so a
+                    // crossing line makes a tee rather than `┼`
+                    let sides = side_toward(pos, p);
+                    grid.set_sides_with_merge(pos.x, pos.y, sides, step_char(p, pos), &jchars);
+                } else {
+                    let c = step_char(p, pos);
+                    grid.set_line_with_merge(pos.x, pos.y, c, p.y == pos.y, &jchars);
+                }
+            }
+            (None, Some(n)) => {
+                let c = step_char(pos, n);
+                if pos.x != n.x && pos.y != n.y {
+                    grid.set_if_empty(pos.x, pos.y, c);
+                } else {
+                    grid.set_line_with_merge(pos.x, pos.y, c, pos.y == n.y, &jchars);
+                }
+            }
+            (None, None) => {
+                if has_arrow {
+                    let arrow =
+                        get_arrow_for_direction(start, Pos::new(to_cx, to_cy), v_char, chars);
+                    grid.queue_arrow(pos.x, pos.y, arrow);
+                } else {
+                    grid.set_if_empty(pos.x, pos.y, v_char);
+                }
+            }
+        }
+    }
+
+    // Diagonal glyphs don't read through text, so keep the label off them
+    draw_path_label(grid, &path, edge, along, true, dropped_labels, next_marker);
+    true
+}
+
 /// Draw edge for LR/RL directions (respects protected node cells)
 fn draw_horizontal_edge(
     grid: &mut Grid,
@@ -260,9 +707,10 @@ fn draw_horizontal_edge(
     end_y: usize,
     h_char: char,
     v_char: char,
-    arrow_char: char,
+    arrow: Option<(usize, usize, char)>,
     direction: Direction,
     label: Option<&str>,
+    placement: LabelPlacement,
     chars: &CharSet,
     from_id: &str,
     to_id: &str,
@@ -281,21 +729,19 @@ fn draw_horizontal_edge(
         for x in from_x..to_x {
             grid.set_line_with_merge(x, start_y, h_char, true, &jchars);
         }
-        // Arrow just before the end
-        if end_x > start_x {
-            grid.set_if_empty(end_x - 1, end_y, arrow_char);
-        } else {
-            grid.set_if_empty(end_x + 1, end_y, arrow_char);
+        if let Some((x, y, c)) = arrow {
+            grid.queue_arrow(x, y, c);
         }
 
         // Draw label in the middle of the edge
         if let Some(lbl) = label {
             let edge_len = to_x.saturating_sub(from_x);
-            if edge_len >= display_width(lbl) {
-                let label_x = from_x + (edge_len - display_width(lbl)) / 2;
-                for (i, c) in lbl.chars().enumerate() {
-                    grid.set_if_empty(label_x + i, start_y, c);
-                }
+            let row = single_row(lbl);
+            if edge_len >= display_width(&row) {
+                let reversed = end_x < start_x;
+                let label_x =
+                    from_x + along_offset(edge_len, display_width(&row), placement.along, reversed);
+                draw_row_label(grid, label_x, start_y, &row, placement.side);
             } else {
                 // Label doesn't fit — try rendering marker, record for legend
                 let marker_text = format!("[{}]", *next_marker);
@@ -329,19 +775,14 @@ fn draw_horizontal_edge(
             grid.set_line_with_merge(x, start_y, h_char, true, &jchars);
         }
 
-        // Turn 1 at (mid_x, start_y)
-        let corner1 = if end_y > start_y {
-            if is_lr {
-                chars.tr
-            } else {
-                chars.tl
-            }
-        } else if is_lr {
-            chars.br
-        } else {
-            chars.bl
+        // Turn 1 at (mid_x, start_y): back toward the source, on toward end_y
+        let turn1 = LineFlags {
+            up: end_y < start_y,
+            down: end_y > start_y,
+            left: is_lr,
+            right: !is_lr,
         };
-        grid.set_if_empty(mid_x, start_y, corner1);
+        grid.set_sides_with_merge(mid_x, start_y, turn1, h_char, &jchars);
 
         // Vertical from start_y to end_y
         let (from_y, to_y) = if end_y > start_y {
@@ -357,11 +798,9 @@ fn draw_horizontal_edge(
         if let Some(lbl) = label {
             let vert_len = to_y.saturating_sub(from_y);
             if vert_len > 0 {
-                let label_y = from_y + vert_len / 2;
+                let label_y = beside_mid(from_y, vert_len, lbl, placement.along, end_y < start_y);
                 // Draw label to the right of the vertical line
-                for (i, c) in lbl.chars().enumerate() {
-                    grid.set_if_empty(mid_x + 1 + i, label_y, c);
-                }
+                draw_label(grid, mid_x + 1, label_y, lbl);
             } else {
                 // Vertical segment too short for label
                 let marker_text = format!("[{}]", *next_marker);
@@ -375,19 +814,14 @@ fn draw_horizontal_edge(
             }
         }
 
-        // Turn 2 at (mid_x, end_y)
-        let corner2 = if end_y > start_y {
-            if is_lr {
-                chars.bl
-            } else {
-                chars.br
-            }
-        } else if is_lr {
-            chars.tl
-        } else {
-            chars.tr
+        // Turn 2 at (mid_x, end_y): back toward start_y, on toward the target
+        let turn2 = LineFlags {
+            up: end_y > start_y,
+            down: end_y < start_y,
+            left: !is_lr,
+            right: is_lr,
         };
-        grid.set_if_empty(mid_x, end_y, corner2);
+        grid.set_sides_with_merge(mid_x, end_y, turn2, h_char, &jchars);
 
         // Horizontal from mid to end
         let (from_x, to_x) = if end_x > mid_x {
@@ -399,11 +833,8 @@ fn draw_horizontal_edge(
             grid.set_line_with_merge(x, end_y, h_char, true, &jchars);
         }
 
-        // Arrow
-        if end_x > mid_x {
-            grid.set_if_empty(end_x - 1, end_y, arrow_char);
-        } else {
-            grid.set_if_empty(end_x + 1, end_y, arrow_char);
+        if let Some((x, y, c)) = arrow {
+            grid.queue_arrow(x, y, c);
         }
     }
 }
@@ -417,9 +848,10 @@ fn draw_vertical_edge(
     end_y: usize,
     h_char: char,
     v_char: char,
-    arrow_char: char,
+    arrow: Option<(usize, usize, char)>,
     direction: Direction,
     label: Option<&str>,
+    placement: LabelPlacement,
     chars: &CharSet,
     from_id: &str,
     to_id: &str,
@@ -438,21 +870,16 @@ fn draw_vertical_edge(
         for y in from_y..to_y {
             grid.set_line_with_merge(start_x, y, v_char, false, &jchars);
         }
-        // Arrow just before the end
-        if end_y > start_y {
-            grid.set_if_empty(end_x, end_y - 1, arrow_char);
-        } else {
-            grid.set_if_empty(end_x, end_y + 1, arrow_char);
+        if let Some((x, y, c)) = arrow {
+            grid.queue_arrow(x, y, c);
         }
 
         // Draw label to the right of the vertical line
         if let Some(lbl) = label {
             let edge_len = to_y.saturating_sub(from_y);
             if edge_len > 0 {
-                let label_y = from_y + edge_len / 2;
-                for (i, c) in lbl.chars().enumerate() {
-                    grid.set_if_empty(start_x + 1 + i, label_y, c);
-                }
+                let label_y = beside_mid(from_y, edge_len, lbl, placement.along, end_y < start_y);
+                draw_label(grid, start_x + 1, label_y, lbl);
             } else {
                 // Edge too short for label
                 let marker_text = format!("[{}]", *next_marker);
@@ -480,19 +907,14 @@ fn draw_vertical_edge(
             grid.set_line_with_merge(start_x, y, v_char, false, &jchars);
         }
 
-        // Turn 1 at (start_x, mid_y)
-        let corner1 = if end_x > start_x {
-            if is_tb {
-                chars.bl
-            } else {
-                chars.tl
-            }
-        } else if is_tb {
-            chars.br
-        } else {
-            chars.tr
+        // Turn 1 at (start_x, mid_y): back toward the source, on toward end_x
+        let turn1 = LineFlags {
+            up: is_tb,
+            down: !is_tb,
+            left: end_x < start_x,
+            right: end_x > start_x,
         };
-        grid.set_if_empty(start_x, mid_y, corner1);
+        grid.set_sides_with_merge(start_x, mid_y, turn1, v_char, &jchars);
 
         // Horizontal from start_x to end_x
         let (from_x, to_x) = if end_x > start_x {
@@ -507,19 +929,23 @@ fn draw_vertical_edge(
         // Draw label — try horizontal segment first, fall back to vertical segment
         if let Some(lbl) = label {
             let horiz_len = to_x.saturating_sub(from_x);
-            if horiz_len >= display_width(lbl) {
-                let label_x = from_x + (horiz_len - display_width(lbl)) / 2;
-                for (i, c) in lbl.chars().enumerate() {
-                    grid.set_if_empty(label_x + i, mid_y, c);
-                }
+            let row = single_row(lbl);
+            if horiz_len >= display_width(&row) {
+                let reversed = end_x < start_x;
+                let label_x = from_x
+                    + along_offset(horiz_len, display_width(&row), placement.along, reversed);
+                draw_row_label(grid, label_x, mid_y, &row, placement.side);
             } else {
-                // Try placing label alongside the first vertical segment
+                // Try placing label alongside the first vertical segment,
+                // then the last (the only one on a back edge)
                 let vert_len = mid_y.saturating_sub(start_y);
+                let end_len = end_y.abs_diff(mid_y);
                 if vert_len > 0 {
                     let label_y = start_y + vert_len / 2;
-                    for (i, c) in lbl.chars().enumerate() {
-                        grid.set_if_empty(start_x + 1 + i, label_y, c);
-                    }
+                    draw_label(grid, start_x + 1, label_y, lbl);
+                } else if end_len > 1 {
+                    let label_y = end_y.min(mid_y) + end_len / 2;
+                    draw_label(grid, end_x + 1, label_y, lbl);
                 } else {
                     // Label doesn't fit anywhere — drop to legend
                     let marker_text = format!("[{}]", *next_marker);
@@ -540,19 +966,14 @@ fn draw_vertical_edge(
             }
         }
 
-        // Turn 2 at (end_x, mid_y)
-        let corner2 = if end_x > start_x {
-            if is_tb {
-                chars.tr
-            } else {
-                chars.br
-            }
-        } else if is_tb {
-            chars.tl
-        } else {
-            chars.bl
+        // Turn 2 at (end_x, mid_y): back toward start_x, on toward the target
+        let turn2 = LineFlags {
+            up: !is_tb,
+            down: is_tb,
+            left: end_x > start_x,
+            right: end_x < start_x,
         };
-        grid.set_if_empty(end_x, mid_y, corner2);
+        grid.set_sides_with_merge(end_x, mid_y, turn2, v_char, &jchars);
 
         // Vertical from mid to end
         let (from_y, to_y) = if end_y > mid_y {
@@ -564,11 +985,8 @@ fn draw_vertical_edge(
             grid.set_line_with_merge(end_x, y, v_char, false, &jchars);
         }
 
-        // Arrow
-        if end_y > mid_y {
-            grid.set_if_empty(end_x, end_y - 1, arrow_char);
-        } else {
-            grid.set_if_empty(end_x, end_y + 1, arrow_char);
+        if let Some((x, y, c)) = arrow {
+            grid.queue_arrow(x, y, c);
         }
     }
 }
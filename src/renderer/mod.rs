@@ -1,31 +1,45 @@
 //! Renderer module for converting graphs to text output
 
 pub mod backend;
+mod bundle;
 mod charset;
 mod edges;
+mod outline;
 mod shapes;
 mod subgraph;
 
-use crate::grid::Grid;
-use crate::pathfinding::PathGrid;
-use crate::types::{DiagramWarning, Graph, Node, RenderOptions};
+use std::borrow::Cow;
+use std::collections::HashSet;
 
+use crate::budget::{self, Budget};
+use crate::error::MermaidError;
+use crate::grid::Grid;
+use crate::pathfinding::{PathGrid, Pos};
+use crate::text::{
+    display_width, scale_to_width, split_to_width, strip_ansi, truncate_with_ellipsis,
+};
+use crate::types::{
+    Alignment, DiagramWarning, Direction, Drawing, Edge, EdgeLabels, EdgeStyle, Graph,
+    LayoutAlgorithm, LegendPosition, LineEnding, Node, RenderMode, RenderOptions, WidthStrategy,
+};
+
+use bundle::{can_bundle, draw_bundle};
 use charset::{ASCII_CHARS, UNICODE_CHARS};
 
-use edges::draw_edge;
+pub use charset::CharSet;
+use edges::{draw_edge, label_width, single_row};
+pub(crate) use shapes::custom_shape;
 use shapes::draw_node;
-use subgraph::{draw_subgraph, protect_subgraph_borders};
+pub use shapes::{register_shape, ShapeRenderer};
+use subgraph::{
+    draw_gateways, draw_subgraph, protect_subgraph_borders, subgraph_gateways, Gateway,
+};
 
 /// Build a PathGrid with all nodes marked as obstacles
-fn build_path_grid(graph: &Graph, width: usize, height: usize) -> PathGrid {
+fn build_path_grid(graph: &Graph, width: usize, height: usize, gateways: &[Gateway]) -> PathGrid {
     let mut path_grid = PathGrid::new(width, height);
 
-    // Mark all nodes as obstacles
-    for node in graph.nodes.values() {
-        path_grid.block_rect(node.x, node.y, node.width, node.height);
-    }
-
-    // Mark subgraph borders as obstacles too
+    // Mark subgraph borders as obstacles, except where edges may cross
     for sg in &graph.subgraphs {
         if sg.width > 0 && sg.height > 0 {
             // Top border
@@ -38,21 +52,195 @@ fn build_path_grid(graph: &Graph, width: usize, height: usize) -> PathGrid {
             path_grid.block_rect(sg.x + sg.width.saturating_sub(1), sg.y, 1, sg.height);
         }
     }
+    for gw in gateways {
+        path_grid.unblock(Pos::new(gw.x, gw.y));
+    }
+
+    // Mark all nodes as obstacles
+    for node in graph.nodes.values() {
+        path_grid.block_rect(node.x, node.y, node.width, node.height);
+    }
 
     path_grid
 }
 
-/// Render the graph to a string
-pub fn render_graph(
+/// Drawn subgraphs containing `node`, innermost first
+fn subgraph_chain<'a>(graph: &'a Graph, node: &'a Node) -> Vec<&'a str> {
+    let mut chain = Vec::new();
+    let mut seen = Vec::new();
+    let mut current = node.subgraph.as_deref();
+    while let Some(id) = current {
+        if seen.contains(&id) {
+            break; // Malformed parent cycle
+        }
+        seen.push(id);
+        let sg = graph.subgraphs.iter().find(|sg| sg.id == id);
+        if sg.is_some_and(|sg| sg.width > 0 && sg.height > 0) {
+            chain.push(id);
+        }
+        current = sg.and_then(|sg| sg.parent.as_deref());
+    }
+    chain
+}
+
+/// Resolve an edge endpoint to the node it attaches to.
+///
+/// A container endpoint becomes a stand-in node covering the container's
+/// box, so the edge runs border to border.
+fn edge_endpoint<'a>(graph: &'a Graph, id: &str) -> Option<Cow<'a, Node>> {
+    if let Some(node) = graph.nodes.get(id) {
+        return Some(Cow::Borrowed(node));
+    }
+    let sg = graph
+        .subgraphs
+        .iter()
+        .find(|sg| sg.id == id && sg.width > 0 && sg.height > 0)?;
+    let mut node = Node::new(sg.id.clone(), sg.label.clone());
+    node.subgraph = sg.parent.clone();
+    node.x = sg.x;
+    node.y = sg.y;
+    node.width = sg.width;
+    node.height = sg.height;
+    Some(Cow::Owned(node))
+}
+
+/// Flow direction used to route one edge.
+///
+/// Layered layouts share the graph's direction; elsewhere nodes can sit
+/// anywhere, so each edge heads whichever way its target lies.
+fn edge_direction(options: &RenderOptions, graph: &Graph, from: &Node, to: &Node) -> Direction {
+    if options.layout == LayoutAlgorithm::Layered {
+        return graph.direction;
+    }
+    let dx = (to.x + to.width / 2) as isize - (from.x + from.width / 2) as isize;
+    let dy = (to.y + to.height / 2) as isize - (from.y + from.height / 2) as isize;
+    // Columns are about half as wide as rows are tall
+    if dx.abs() > dy.abs() * 2 {
+        if dx > 0 {
+            Direction::LR
+        } else {
+            Direction::RL
+        }
+    } else if dy > 0 {
+        Direction::TB
+    } else {
+        Direction::BT
+    }
+}
+
+/// Group incoming edges by target for bundling.
+///
+/// A target gets a bundle when at least two edges can reach it from
+/// upstream; other edges are routed individually. Bundles are returned in
+/// order of their first edge.
+fn collect_bundles<'a>(graph: &'a Graph, path_edges: &[usize]) -> Vec<(&'a Node, Vec<&'a Edge>)> {
+    let mut bundles: Vec<(&Node, Vec<&Edge>)> = Vec::new();
+    for (index, edge) in graph.edges.iter().enumerate() {
+        let (Some(from), Some(to)) = (graph.nodes.get(&edge.from), graph.nodes.get(&edge.to))
+        else {
+            continue;
+        };
+        // Edges with their own terminators or on a highlighted path keep
+        // their own lines
+        let custom_heads = edge.source_arrowhead.is_some() || edge.target_arrowhead.is_some();
+        if edge.from == edge.to
+            || custom_heads
+            || path_edges.contains(&index)
+            || edge.style == EdgeStyle::Invisible
+            || !can_bundle(from, to, graph.direction, graph.nodes.values())
+        {
+            continue;
+        }
+        match bundles.iter_mut().find(|(n, _)| n.id == to.id) {
+            Some((_, members)) => members.push(edge),
+            None => bundles.push((to, vec![edge])),
+        }
+    }
+    bundles.retain(|(_, members)| members.len() >= 2);
+    bundles
+}
+
+/// Glyphs to draw with: the caller's charset, or the one `ascii` picks
+pub(crate) fn charset(options: &RenderOptions) -> &CharSet {
+    match &options.charset {
+        Some(chars) => chars,
+        None if options.ascii => &ASCII_CHARS,
+        None => &UNICODE_CHARS,
+    }
+}
+
+/// Draw the graph, with the dropped-label legend beside it.
+///
+/// Fails with `MermaidError::TooLarge` if the time budget runs out while
+/// routing edges.
+pub(crate) fn draw_graph(
     graph: &Graph,
     options: &RenderOptions,
+    budget: &Budget,
     warnings: &mut Vec<DiagramWarning>,
-) -> String {
-    let chars = if options.ascii {
-        &ASCII_CHARS
-    } else {
-        &UNICODE_CHARS
+) -> Result<Drawing, MermaidError> {
+    if options.mode == RenderMode::Outline {
+        return Ok(Drawing {
+            body: outline::render_outline(graph, options),
+            footer: tooltip_footer(graph),
+            legend: Vec::new(),
+        });
+    }
+
+    // Hidden drops edge labels; Legend draws each as a numbered marker,
+    // listed below with the label it stands for
+    let mut listed: Vec<(edges::DroppedLabel, String)> = Vec::new();
+    let relabeled;
+    let graph = match options.edge_labels {
+        EdgeLabels::Inline => graph,
+        EdgeLabels::Legend | EdgeLabels::Hidden => {
+            let mut copy = graph.clone();
+            for (index, edge) in copy.edges.iter_mut().enumerate() {
+                let Some(label) = edge.label.take() else {
+                    continue;
+                };
+                if options.edge_labels == EdgeLabels::Legend {
+                    let marker = format!("[{}]", listed.len() + 1);
+                    edge.label = Some(marker.clone());
+                    let listing = edges::DroppedLabel {
+                        marker,
+                        label,
+                        from: edge.from.clone(),
+                        to: edge.to.clone(),
+                    };
+                    listed.push((listing, graph.edge_id(index)));
+                }
+            }
+            relabeled = copy;
+            &relabeled
+        }
+    };
+
+    let chars = charset(options);
+    let (path, path_edges) = match &options.highlight_path {
+        Some((from, to)) => {
+            let path = graph.shortest_path(from, to).ok_or_else(|| {
+                let missing = [from, to].into_iter().find(|id| {
+                    !graph.nodes.contains_key(*id)
+                        && !graph.subgraphs.iter().any(|sg| sg.id == **id)
+                });
+                MermaidError::InvalidOption {
+                    option: "highlight_path".to_string(),
+                    message: match missing {
+                        Some(id) => format!("no node {}", id),
+                        None => format!("no path from {} to {}", from, to),
+                    },
+                }
+            })?;
+            let edges = graph.path_edges(&path);
+            (path, edges)
+        }
+        None => (Vec::new(), Vec::new()),
     };
+    let mut thick_edges = path_edges;
+    if options.emphasize_critical_path {
+        thick_edges.extend(graph.path_edges(&graph.critical_path()));
+    }
 
     // Find grid bounds
     let mut max_x = 0;
@@ -71,98 +259,392 @@ pub fn render_graph(
         max_y = max_y.max(sg.y + sg.height);
     }
 
-    // Add padding
-    let mut grid = Grid::new(max_x + 2, max_y + 2);
+    // Add padding, with room for a label beside an edge along the right side
+    let label_room = graph
+        .edges
+        .iter()
+        .filter_map(|e| e.label.as_deref())
+        .map(label_width)
+        .max()
+        .unwrap_or(0);
+    budget::check_grid(max_x + 2 + label_room, max_y + 2)?;
+    let mut grid = Grid::new(max_x + 2 + label_room, max_y + 2);
 
     // 1. Render subgraphs first (background) and protect their borders
+    let gateways: Vec<Gateway> = graph
+        .subgraphs
+        .iter()
+        .flat_map(|sg| subgraph_gateways(sg, graph))
+        .collect();
     for sg in &graph.subgraphs {
         draw_subgraph(&mut grid, sg, chars);
-        protect_subgraph_borders(&mut grid, sg);
+        protect_subgraph_borders(&mut grid, sg, &gateways);
     }
 
     // 2. Render nodes in deterministic order
     for node in &sorted_nodes {
-        draw_node(&mut grid, node, chars);
+        if path.contains(&node.id) {
+            draw_node(&mut grid, node, &chars.doubled(), options.ascii);
+        } else {
+            draw_node(&mut grid, node, chars, options.ascii);
+        }
     }
 
+    warnings.extend(overlap_warnings(&sorted_nodes));
+    warnings.extend(truncation_warnings(&sorted_nodes, options));
+
     // 3. Build pathfinding grid for A* edge routing
-    let path_grid = build_path_grid(graph, grid.width, grid.height);
+    let path_grid = build_path_grid(graph, grid.width, grid.height, &gateways);
 
     // 4. Render edges, tracking dropped labels
-    let mut dropped_labels: Vec<edges::DroppedLabel> = Vec::new();
-    let mut next_marker: usize = 1;
+    let listed_count = listed.len();
+    // Stable id of the edge behind each dropped label in `dropped_ids`
+    let (mut dropped_labels, mut dropped_ids): (Vec<edges::DroppedLabel>, Vec<String>) =
+        listed.into_iter().unzip();
+    let mut next_marker: usize = listed_count + 1;
+
+    let bundles = if options.bundle_edges && options.layout == LayoutAlgorithm::Layered {
+        collect_bundles(graph, &thick_edges)
+    } else {
+        Vec::new()
+    };
 
-    for edge in &graph.edges {
-        if let (Some(from), Some(to)) = (graph.nodes.get(&edge.from), graph.nodes.get(&edge.to)) {
+    for (index, edge) in graph.edges.iter().enumerate() {
+        budget.check_time()?;
+        if edge.style == EdgeStyle::Invisible {
+            continue;
+        }
+        if bundles
+            .iter()
+            .any(|(_, members)| members.iter().any(|m| std::ptr::eq(*m, edge)))
+        {
+            continue;
+        }
+        let thick;
+        let edge = if thick_edges.contains(&index) {
+            thick = Edge {
+                style: match edge.style {
+                    EdgeStyle::Line | EdgeStyle::DottedLine | EdgeStyle::ThickLine => {
+                        EdgeStyle::ThickLine
+                    }
+                    _ => EdgeStyle::ThickArrow,
+                },
+                ..edge.clone()
+            };
+            &thick
+        } else {
+            edge
+        };
+        if let (Some(from), Some(to)) = (
+            edge_endpoint(graph, &edge.from),
+            edge_endpoint(graph, &edge.to),
+        ) {
             draw_edge(
                 &mut grid,
                 &path_grid,
-                from,
-                to,
+                &from,
+                &to,
                 edge,
                 chars,
-                graph.direction,
+                edge_direction(options, graph, &from, &to),
                 options.ascii,
+                options.edge_routing,
+                subgraph_chain(graph, &from) != subgraph_chain(graph, &to)
+                    || matches!(from, Cow::Owned(_))
+                    || matches!(to, Cow::Owned(_))
+                    || options.layout != LayoutAlgorithm::Layered,
+                edge.label_placement.unwrap_or(options.label_placement),
                 &mut dropped_labels,
                 &mut next_marker,
             );
+            dropped_ids.resize(dropped_labels.len(), graph.edge_id(index));
         }
     }
 
-    let output = grid.to_string();
-
-    // Apply max_width constraint if set (only to grid lines, not legend)
-    let output = if let Some(max_width) = options.max_width {
-        output
-            .lines()
-            .map(|line| {
-                let char_count = line.chars().count();
-                if char_count > max_width {
-                    let mut truncated: String =
-                        line.chars().take(max_width.saturating_sub(1)).collect();
-                    truncated.push('…');
-                    truncated
-                } else {
-                    line.to_string()
-                }
-            })
-            .collect::<Vec<_>>()
-            .join("\n")
-    } else {
-        output
-    };
+    for (target, members) in &bundles {
+        budget.check_time()?;
+        let sources: Vec<(&Node, &Edge)> = members
+            .iter()
+            .filter_map(|e| graph.nodes.get(&e.from).map(|n| (n, *e)))
+            .collect();
+        draw_bundle(
+            &mut grid,
+            target,
+            &sources,
+            graph.nodes.values(),
+            chars,
+            graph.direction,
+            options.ascii,
+            &mut dropped_labels,
+            &mut next_marker,
+        );
+        for dl in &dropped_labels[dropped_ids.len()..] {
+            let index = members
+                .iter()
+                .find(|e| e.from == dl.from)
+                .and_then(|m| graph.edges.iter().position(|e| std::ptr::eq(e, *m)));
+            dropped_ids.push(index.map(|i| graph.edge_id(i)).unwrap_or_default());
+        }
+    }
 
-    // Append legend for dropped labels
+    // A Legend marker too long for its edge is still listed under its own
+    // number, so the fallback entry for the marker itself is redundant
+    if listed_count > 0 {
+        let markers: HashSet<String> = dropped_labels[..listed_count]
+            .iter()
+            .map(|dl| dl.marker.clone())
+            .collect();
+        let (labels, ids) = dropped_labels
+            .into_iter()
+            .zip(dropped_ids)
+            .enumerate()
+            .filter(|(i, (dl, _))| *i < listed_count || !markers.contains(&dl.label))
+            .map(|(_, pair)| pair)
+            .unzip();
+        (dropped_labels, dropped_ids) = (labels, ids);
+    }
+
+    // 5. Show where edges crossed subgraph borders
+    draw_gateways(&mut grid, &gateways, chars);
+
+    // 6. Arrowheads go on last so no later line can swallow them
+    grid.stamp_arrows(|c| chars.is_border_glyph(c));
+
+    // Legend for dropped labels, placed by `finish_drawing`
+    let mut legend = Vec::new();
+    let mut footer = Vec::new();
     if !dropped_labels.is_empty() {
-        let mut result = output;
-        result.push_str("\nLabels:");
-        for dl in &dropped_labels {
-            result.push_str(&format!("\n  {} {}", dl.marker, dl.label));
+        for (i, (dl, edge_id)) in dropped_labels.iter().zip(dropped_ids).enumerate() {
+            legend.push(format!("{} {}", dl.marker, single_row(&dl.label)));
+            // Listing labels was asked for, so only fallbacks are warned about
+            if i < listed_count {
+                continue;
+            }
             warnings.push(DiagramWarning::LabelDropped {
                 marker: dl.marker.clone(),
+                edge_id,
                 edge_from: dl.from.clone(),
                 edge_to: dl.to.clone(),
                 label: dl.label.clone(),
             });
         }
-        result
+    }
+    if !graph.abbreviations.is_empty() {
+        footer.push("Abbreviations:".to_string());
+        for (id, full) in &graph.abbreviations {
+            if let Some(node) = graph.nodes.get(id) {
+                footer.push(format!(
+                    "  {} = {}",
+                    single_row(&node.label),
+                    single_row(full)
+                ));
+            }
+        }
+    }
+    footer.extend(tooltip_footer(graph));
+    Ok(Drawing {
+        body: grid.render(options.trim_trailing),
+        footer,
+        legend,
+    })
+}
+
+/// A warning for each pair of nodes whose boxes share cells
+fn overlap_warnings(nodes: &[&Node]) -> Vec<DiagramWarning> {
+    let mut warnings = Vec::new();
+    for (i, a) in nodes.iter().enumerate() {
+        for b in &nodes[i + 1..] {
+            let apart = a.x + a.width <= b.x
+                || b.x + b.width <= a.x
+                || a.y + a.height <= b.y
+                || b.y + b.height <= a.y;
+            if !apart {
+                warnings.push(DiagramWarning::NodeOverlap {
+                    nodes: vec![a.id.clone(), b.id.clone()],
+                });
+            }
+        }
+    }
+    warnings
+}
+
+/// A warning for each node that cutting lines at `max_width` will clip
+fn truncation_warnings(nodes: &[&Node], options: &RenderOptions) -> Vec<DiagramWarning> {
+    let Some(max_width) = options.max_width else {
+        return Vec::new();
+    };
+    if !matches!(
+        options.width_strategy,
+        WidthStrategy::Truncate | WidthStrategy::Abbreviate
+    ) {
+        return Vec::new();
+    }
+    nodes
+        .iter()
+        .filter(|n| n.x + n.width > max_width)
+        .map(|n| DiagramWarning::LabelTruncated {
+            node_id: n.id.clone(),
+        })
+        .collect()
+}
+
+/// Footnote section listing the graph's tooltips
+fn tooltip_footer(graph: &Graph) -> Vec<String> {
+    if graph.tooltips.is_empty() {
+        return Vec::new();
+    }
+    let mut footer = vec!["Tooltips:".to_string()];
+    for (marker, text) in &graph.tooltips {
+        footer.push(format!("  {} {}", marker, single_row(text)));
+    }
+    footer
+}
+
+/// Turn a drawing into final output: drop ANSI escapes unless colors are
+/// on, fit the body to `max_width`, add the legend and footer untouched,
+/// and end the lines as `options` asks
+pub(crate) fn finish_drawing(drawing: Drawing, options: &RenderOptions) -> String {
+    let body = if options.colors {
+        drawing.body
+    } else {
+        strip_ansi(&drawing.body)
+    };
+    let mut legend = Vec::new();
+    if !drawing.legend.is_empty() {
+        legend.push(options.legend_heading.clone());
+        legend.extend(drawing.legend.iter().map(|entry| format!("  {}", entry)));
+    }
+    let mut lines = Vec::new();
+    if options.legend_position == LegendPosition::Top {
+        lines.append(&mut legend);
+    }
+    lines.push(fit_to_width(body, options));
+    if options.legend_position == LegendPosition::Bottom {
+        lines.append(&mut legend);
+    }
+    lines.extend(drawing.footer);
+    end_lines(lines.join("\n"), options)
+}
+
+/// Trim trailing spaces unless `min_width` padding was asked for, and end
+/// lines with `options.line_ending`
+pub(crate) fn end_lines(output: String, options: &RenderOptions) -> String {
+    let trim = options.trim_trailing && options.min_width.is_none();
+    if !trim && options.line_ending == LineEnding::Lf {
+        return output;
+    }
+    output
+        .split('\n')
+        .map(|line| if trim { line.trim_end() } else { line })
+        .collect::<Vec<_>>()
+        .join(options.line_ending.as_str())
+}
+
+/// Legend entries to return beside the output rather than in it
+pub(crate) fn separate_legend(drawing: &Drawing, options: &RenderOptions) -> Vec<String> {
+    if options.legend_position == LegendPosition::Separate {
+        drawing.legend.clone()
     } else {
-        output
+        Vec::new()
     }
 }
 
+/// Bring every line of `output` within `options.max_width` using its
+/// `width_strategy`, then pad the block out to `options.min_width`
+pub(crate) fn fit_to_width(output: String, options: &RenderOptions) -> String {
+    let Some(max_width) = options.max_width else {
+        return pad_to_min_width(output, options);
+    };
+    let lines = output.lines();
+    let fitted: Vec<String> = match options.width_strategy {
+        WidthStrategy::Truncate | WidthStrategy::Abbreviate => lines
+            .map(|line| truncate_with_ellipsis(line, max_width))
+            .collect(),
+        WidthStrategy::Wrap => {
+            // Band by band, so each band keeps the drawing's rows lined up
+            let rows: Vec<Vec<&str>> = lines.map(|line| split_to_width(line, max_width)).collect();
+            let bands = rows.iter().map(Vec::len).max().unwrap_or(0);
+            let mut fitted = Vec::new();
+            for band in 0..bands {
+                if band > 0 {
+                    fitted.push(String::new());
+                }
+                fitted.extend(
+                    rows.iter()
+                        .map(|row| row.get(band).copied().unwrap_or_default().to_string()),
+                );
+            }
+            fitted
+        }
+        WidthStrategy::Scale => {
+            let widest = output.lines().map(display_width).max().unwrap_or(0);
+            lines
+                .map(|line| scale_to_width(line, widest, max_width))
+                .collect()
+        }
+    };
+    pad_to_min_width(fitted.join("\n"), options)
+}
+
+/// Pad every line of `output` to `options.min_width` columns (capped at
+/// `max_width`), shifting the block as a whole by `options.h_align`
+fn pad_to_min_width(output: String, options: &RenderOptions) -> String {
+    let Some(min_width) = options.min_width else {
+        return output;
+    };
+    let min_width = options
+        .max_width
+        .map_or(min_width, |max| min_width.min(max));
+    let widths: Vec<usize> = output
+        .lines()
+        .map(|line| display_width(&strip_ansi(line)))
+        .collect();
+    let block = widths.iter().copied().max().unwrap_or(0);
+    let indent = match options.h_align {
+        Alignment::Start => 0,
+        Alignment::Center => min_width.saturating_sub(block) / 2,
+        Alignment::End => min_width.saturating_sub(block),
+    };
+    output
+        .lines()
+        .zip(widths)
+        .map(|(line, width)| {
+            let fill = min_width.saturating_sub(indent + width);
+            format!("{}{}{}", " ".repeat(indent), line, " ".repeat(fill))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::layout::compute_layout;
     use crate::parser::parse_mermaid;
 
+    /// Render the graph to a string
+    fn render_graph(
+        graph: &Graph,
+        options: &RenderOptions,
+        budget: &Budget,
+        warnings: &mut Vec<DiagramWarning>,
+    ) -> Result<String, MermaidError> {
+        let drawing = draw_graph(graph, options, budget, warnings)?;
+        Ok(finish_drawing(drawing, options))
+    }
+
     #[test]
     fn test_render_lr() {
         let mut graph = parse_mermaid("flowchart LR\nA[Start] --> B[End]").unwrap();
         compute_layout(&mut graph);
         let mut warnings = Vec::new();
-        let output = render_graph(&graph, &RenderOptions::default(), &mut warnings);
+        let output = render_graph(
+            &graph,
+            &RenderOptions::default(),
+            &Budget::unlimited(),
+            &mut warnings,
+        )
+        .unwrap();
         assert!(output.contains("Start"));
         assert!(output.contains("End"));
         assert!(output.contains("▶"));
@@ -173,7 +655,13 @@ mod tests {
         let mut graph = parse_mermaid("flowchart TB\nA[Start] --> B[End]").unwrap();
         compute_layout(&mut graph);
         let mut warnings = Vec::new();
-        let output = render_graph(&graph, &RenderOptions::default(), &mut warnings);
+        let output = render_graph(
+            &graph,
+            &RenderOptions::default(),
+            &Budget::unlimited(),
+            &mut warnings,
+        )
+        .unwrap();
         assert!(output.contains("Start"));
         assert!(output.contains("End"));
         assert!(output.contains("▼"));
@@ -190,8 +678,10 @@ mod tests {
                 ascii: true,
                 ..Default::default()
             },
+            &Budget::unlimited(),
             &mut warnings,
-        );
+        )
+        .unwrap();
         assert!(output.contains("+---+"));
         assert!(output.contains(">"));
         assert!(!output.contains("┌"));
@@ -202,7 +692,13 @@ mod tests {
         let mut graph = parse_mermaid("flowchart RL\nA --> B").unwrap();
         compute_layout(&mut graph);
         let mut warnings = Vec::new();
-        let output = render_graph(&graph, &RenderOptions::default(), &mut warnings);
+        let output = render_graph(
+            &graph,
+            &RenderOptions::default(),
+            &Budget::unlimited(),
+            &mut warnings,
+        )
+        .unwrap();
         assert!(output.contains("◀"));
     }
 
@@ -211,7 +707,13 @@ mod tests {
         let mut graph = parse_mermaid("flowchart BT\nA --> B").unwrap();
         compute_layout(&mut graph);
         let mut warnings = Vec::new();
-        let output = render_graph(&graph, &RenderOptions::default(), &mut warnings);
+        let output = render_graph(
+            &graph,
+            &RenderOptions::default(),
+            &Budget::unlimited(),
+            &mut warnings,
+        )
+        .unwrap();
         assert!(output.contains("▲"));
     }
 
@@ -220,7 +722,13 @@ mod tests {
         let mut graph = parse_mermaid("flowchart LR\nA(Rounded)").unwrap();
         compute_layout(&mut graph);
         let mut warnings = Vec::new();
-        let output = render_graph(&graph, &RenderOptions::default(), &mut warnings);
+        let output = render_graph(
+            &graph,
+            &RenderOptions::default(),
+            &Budget::unlimited(),
+            &mut warnings,
+        )
+        .unwrap();
         assert!(output.contains("Rounded"));
         assert!(output.contains("╭")); // Rounded corner
     }
@@ -230,7 +738,13 @@ mod tests {
         let mut graph = parse_mermaid("flowchart LR\nA((Circle))").unwrap();
         compute_layout(&mut graph);
         let mut warnings = Vec::new();
-        let output = render_graph(&graph, &RenderOptions::default(), &mut warnings);
+        let output = render_graph(
+            &graph,
+            &RenderOptions::default(),
+            &Budget::unlimited(),
+            &mut warnings,
+        )
+        .unwrap();
         assert!(output.contains("Circle"));
         assert!(output.contains("(")); // Circle sides
     }
@@ -240,7 +754,13 @@ mod tests {
         let mut graph = parse_mermaid("flowchart LR\nA{Decision}").unwrap();
         compute_layout(&mut graph);
         let mut warnings = Vec::new();
-        let output = render_graph(&graph, &RenderOptions::default(), &mut warnings);
+        let output = render_graph(
+            &graph,
+            &RenderOptions::default(),
+            &Budget::unlimited(),
+            &mut warnings,
+        )
+        .unwrap();
         assert!(output.contains("Decision"));
         assert!(output.contains("<")); // Diamond sides
     }
@@ -250,10 +770,40 @@ mod tests {
         let mut graph = parse_mermaid("flowchart LR\nDB[(Database)]").unwrap();
         compute_layout(&mut graph);
         let mut warnings = Vec::new();
-        let output = render_graph(&graph, &RenderOptions::default(), &mut warnings);
+        let output = render_graph(
+            &graph,
+            &RenderOptions::default(),
+            &Budget::unlimited(),
+            &mut warnings,
+        )
+        .unwrap();
         assert!(output.contains("Database"));
     }
 
+    #[test]
+    fn test_render_table_double_border() {
+        let mut graph =
+            crate::d2_parser::parse_d2("users: {\n  shape: sql_table\n  id: int\n  name: text\n}")
+                .unwrap()
+                .graph;
+        compute_layout(&mut graph);
+        let mut warnings = Vec::new();
+        let output = render_graph(
+            &graph,
+            &RenderOptions::default(),
+            &Budget::unlimited(),
+            &mut warnings,
+        )
+        .unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 6);
+        assert!(lines[0].starts_with('╔'));
+        assert!(lines[2].starts_with('╠') && lines[2].ends_with('╣'));
+        assert!(lines[3].starts_with("║ id: int"));
+        assert!(lines[4].starts_with("║ name: text"));
+        assert!(lines[5].starts_with('╚') && lines[5].ends_with('╝'));
+    }
+
     #[test]
     fn test_render_max_width() {
         let mut graph = parse_mermaid("flowchart LR\nA[Start] --> B[End]").unwrap();
@@ -265,8 +815,10 @@ mod tests {
                 max_width: Some(15),
                 ..Default::default()
             },
+            &Budget::unlimited(),
             &mut warnings,
-        );
+        )
+        .unwrap();
         // All lines should be truncated to max_width
         for line in output.lines() {
             assert!(
@@ -290,8 +842,10 @@ mod tests {
                 max_width: Some(100), // Wide enough to not truncate
                 ..Default::default()
             },
+            &Budget::unlimited(),
             &mut warnings,
-        );
+        )
+        .unwrap();
         // Should not contain ellipsis when no truncation needed
         assert!(!output.contains('…'));
     }
@@ -354,4 +908,20 @@ mod tests {
             '◤'
         );
     }
+
+    #[test]
+    fn test_end_lines() {
+        let output = "a  \nb\n".to_string();
+        let options = RenderOptions::default();
+        assert_eq!(end_lines(output.clone(), &options), "a\nb\n");
+        let options = RenderOptions::builder()
+            .trim_trailing(false)
+            .line_ending(LineEnding::CrLf)
+            .build()
+            .unwrap();
+        assert_eq!(end_lines(output.clone(), &options), "a  \r\nb\r\n");
+        // Padding asked for is kept
+        let options = RenderOptions::builder().min_width(3).build().unwrap();
+        assert_eq!(end_lines(output, &options), "a  \nb\n");
+    }
 }
@@ -0,0 +1,122 @@
+//! Outline mode: graphs as an indented adjacency list
+
+use crate::types::{Edge, EdgeStyle, Graph, Node, RenderOptions};
+
+use super::edges::single_row;
+
+/// List every node with its outgoing edges beneath it, nested under its
+/// subgraphs, in the order the layout reads
+pub fn render_outline(graph: &Graph, options: &RenderOptions) -> String {
+    let mut lines = Vec::new();
+    outline_level(graph, options, None, 0, &mut lines);
+    lines.join("\n")
+}
+
+fn outline_level(
+    graph: &Graph,
+    options: &RenderOptions,
+    parent: Option<&str>,
+    depth: usize,
+    lines: &mut Vec<String>,
+) {
+    let indent = "  ".repeat(depth);
+
+    let mut nodes: Vec<&Node> = graph
+        .nodes
+        .values()
+        .filter(|n| n.subgraph.as_deref() == parent)
+        .collect();
+    nodes.sort_by_key(|n| {
+        if graph.direction.is_horizontal() {
+            (n.x, n.y, n.id.clone())
+        } else {
+            (n.y, n.x, n.id.clone())
+        }
+    });
+    for node in nodes {
+        lines.push(format!("{}{}", indent, single_row(&node.label)));
+        for edge in graph
+            .edges
+            .iter()
+            .filter(|e| e.from == node.id && e.style != EdgeStyle::Invisible)
+        {
+            let target = graph
+                .nodes
+                .get(&edge.to)
+                .map_or(edge.to.clone(), |n| single_row(&n.label));
+            let mut line = format!("{}  {} {}", indent, arrow(edge, options.ascii), target);
+            if let Some(label) = edge.label.as_deref().filter(|l| !l.is_empty()) {
+                line.push_str(&format!(" ({})", single_row(label)));
+            }
+            lines.push(line);
+        }
+    }
+
+    for sg in graph
+        .subgraphs
+        .iter()
+        .filter(|sg| sg.parent.as_deref() == parent)
+    {
+        lines.push(format!("{}{}:", indent, single_row(&sg.label)));
+        outline_level(graph, options, Some(&sg.id), depth + 1, lines);
+    }
+}
+
+/// Glyph for which ends of `edge` have arrowheads, read the way
+/// [`Graph::to_dot`] picks `dir`
+fn arrow(edge: &Edge, ascii: bool) -> &'static str {
+    let plain = matches!(
+        edge.style,
+        EdgeStyle::Line | EdgeStyle::DottedLine | EdgeStyle::ThickLine | EdgeStyle::Invisible
+    );
+    let head = !plain || edge.target_arrowhead.is_some();
+    match (edge.source_arrowhead.is_some(), head, ascii) {
+        (true, true, false) => "↔",
+        (true, true, true) => "<->",
+        (true, false, false) => "←",
+        (true, false, true) => "<-",
+        (false, true, false) => "→",
+        (false, true, true) => "->",
+        (false, false, false) => "—",
+        (false, false, true) => "--",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::compute_layout;
+    use crate::parser::parse_mermaid;
+
+    #[test]
+    fn test_outline_lists_edges_under_nodes() {
+        let mut graph = parse_mermaid(
+            "flowchart TB\nA[Start] -->|go| B[API]\nsubgraph Cloud\nB --> C[(DB)]\nend",
+        )
+        .unwrap();
+        compute_layout(&mut graph);
+        assert_eq!(
+            render_outline(&graph, &RenderOptions::default()),
+            "Start\n  → API (go)\nAPI\n  → DB\nCloud:\n  DB"
+        );
+        let ascii = RenderOptions {
+            ascii: true,
+            ..Default::default()
+        };
+        assert!(render_outline(&graph, &ascii).contains("  -> API (go)"));
+    }
+
+    #[test]
+    fn test_outline_arrow_follows_arrowheads() {
+        let mut graph = crate::d2_parser::parse_d2(
+            "a -- b\nb -> c: {source-arrowhead.shape: arrow}\nc -- d: {source-arrowhead.shape: arrow}\nd -> e",
+        )
+        .unwrap()
+        .graph;
+        compute_layout(&mut graph);
+        let outline = render_outline(&graph, &RenderOptions::default());
+        for line in ["a\n  — b", "b\n  ↔ c", "c\n  ← d", "d\n  → e"] {
+            assert!(outline.contains(line), "{outline}");
+        }
+    }
+}
@@ -1,18 +1,101 @@
 //! Shape drawing functions for nodes
 
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock};
+
 use crate::grid::Grid;
-use crate::text::display_width;
+use crate::text::{display_width, truncate_to_width};
 use crate::types::{Node, NodeShape};
-use unicode_width::UnicodeWidthChar;
 
 use super::charset::CharSet;
 
+/// A node shape added by another crate, such as a Kubernetes pod glyph.
+///
+/// Once registered with [`register_shape`], a D2 `shape:` naming it draws
+/// nodes with it instead of a built-in shape.
+pub trait ShapeRenderer: Send + Sync {
+    /// `(width, height)` of a node showing `label` (default: a box just
+    /// big enough for it)
+    fn size(&self, label: &str) -> (usize, usize) {
+        let lines = label.split('\n');
+        let width = lines.clone().map(display_width).max().unwrap_or(0);
+        (width + 4, lines.count() + 2)
+    }
+
+    /// Rows of text filling the node's `width` x `height` cells; spaces
+    /// are left transparent. `ascii` asks for ASCII-only glyphs.
+    fn draw(&self, label: &str, width: usize, height: usize, ascii: bool) -> Vec<String>;
+}
+
+type ShapeMap = HashMap<String, Arc<dyn ShapeRenderer>>;
+
+fn custom_shapes() -> &'static RwLock<ShapeMap> {
+    static SHAPES: OnceLock<RwLock<ShapeMap>> = OnceLock::new();
+    SHAPES.get_or_init(Default::default)
+}
+
+/// Make `renderer` available under `name` (case-insensitive) to D2's
+/// `shape:` keyword, replacing any shape registered under that name before.
+///
+/// Registration is process-wide and applies to diagrams parsed afterwards.
+/// Built-in shape names can't be overridden.
+///
+/// # Example
+/// ```
+/// use graphs_tui::{register_shape, render_d2_to_tui, RenderOptions, ShapeRenderer};
+///
+/// struct Pod;
+///
+/// impl ShapeRenderer for Pod {
+///     fn draw(&self, label: &str, width: usize, _height: usize, _ascii: bool) -> Vec<String> {
+///         vec![
+///             format!("⎈{}", "⎺".repeat(width - 1)),
+///             format!("⎸ {:<1$}", label, width - 2),
+///             "⎽".repeat(width),
+///         ]
+///     }
+/// }
+///
+/// register_shape("pod", Box::new(Pod));
+/// let result = render_d2_to_tui("web.shape: pod", RenderOptions::default()).unwrap();
+/// assert!(result.output.contains("⎈"));
+/// ```
+pub fn register_shape(name: &str, renderer: Box<dyn ShapeRenderer>) {
+    let mut shapes = custom_shapes().write().unwrap_or_else(|e| e.into_inner());
+    shapes.insert(name.to_lowercase(), Arc::from(renderer));
+}
+
+/// The registered shape called `name`, if any
+pub(crate) fn custom_shape(name: &str) -> Option<Arc<dyn ShapeRenderer>> {
+    let shapes = custom_shapes().read().unwrap_or_else(|e| e.into_inner());
+    shapes.get(&name.to_lowercase()).cloned()
+}
+
 /// Draw a node with its shape
-pub fn draw_node(grid: &mut Grid, node: &Node, chars: &CharSet) {
+pub fn draw_node(grid: &mut Grid, node: &Node, chars: &CharSet, ascii: bool) {
+    if let Some(shape) = node.custom_shape.as_deref().and_then(custom_shape) {
+        let rows = shape.draw(&node.label, node.width, node.height, ascii);
+        for (dy, row) in rows.iter().take(node.height).enumerate() {
+            let mut x = node.x;
+            for c in row.chars() {
+                if x >= node.x + node.width {
+                    break;
+                }
+                if c != ' ' {
+                    grid.set_if_empty(x, node.y + dy, c);
+                }
+                x += display_width(c.encode_utf8(&mut [0; 4])).max(1);
+            }
+        }
+        protect_node_area(grid, node);
+        return;
+    }
+
     match node.shape {
         NodeShape::Rectangle => draw_rectangle(grid, node, chars),
         NodeShape::Rounded => draw_rounded(grid, node, chars),
         NodeShape::Circle => draw_circle(grid, node, chars),
+        NodeShape::DoubleCircle => draw_double_circle(grid, node, chars),
         NodeShape::Diamond => draw_diamond(grid, node, chars),
         NodeShape::Cylinder => draw_cylinder(grid, node, chars),
         NodeShape::Stadium => draw_stadium(grid, node, chars),
@@ -22,16 +105,53 @@ pub fn draw_node(grid: &mut Grid, node: &Node, chars: &CharSet) {
         NodeShape::ParallelogramAlt => draw_parallelogram(grid, node, chars, true),
         NodeShape::Trapezoid => draw_trapezoid(grid, node, chars, false),
         NodeShape::TrapezoidAlt => draw_trapezoid(grid, node, chars, true),
+        NodeShape::Asymmetric => draw_asymmetric(grid, node, chars),
         NodeShape::Table => draw_table(grid, node, chars),
+        NodeShape::Compartment => draw_compartment(grid, node, chars),
         NodeShape::Person => draw_person(grid, node, chars),
         NodeShape::Cloud => draw_cloud(grid, node, chars),
         NodeShape::Document => draw_document(grid, node, chars),
+        NodeShape::Oval => draw_oval(grid, node, chars),
+        NodeShape::Package => draw_package(grid, node, chars),
+        NodeShape::Step => draw_step(grid, node, chars),
+        NodeShape::Callout => draw_callout(grid, node, chars),
     }
 
     // Protect the node bounding box from edge overwriting
     protect_node_area(grid, node);
 }
 
+/// Lines of the node's label, each with its byte offset into the label
+fn label_lines(node: &Node) -> Vec<(usize, &str)> {
+    let mut offset = 0;
+    node.label
+        .split('\n')
+        .map(|line| {
+            let start = offset;
+            offset += line.len() + 1;
+            (start, line)
+        })
+        .collect()
+}
+
+/// Draw one label line starting at byte `offset` of the label, marking the
+/// cells of any `<b>` span in it bold
+fn draw_label_line(grid: &mut Grid, node: &Node, x: usize, y: usize, offset: usize, line: &str) {
+    grid.draw_text(x, y, line);
+    let end = offset + line.len();
+    for range in &node.bold {
+        let from = range.start.clamp(offset, end) - offset;
+        let to = range.end.clamp(offset, end) - offset;
+        let (Some(before), Some(span)) = (line.get(..from), line.get(from..to)) else {
+            continue;
+        };
+        let left = x + display_width(before);
+        for bx in left..left + display_width(span) {
+            grid.set_bold(bx, y);
+        }
+    }
+}
+
 /// Protect the entire node bounding box from being overwritten by edges
 fn protect_node_area(grid: &mut Grid, node: &Node) {
     for y in node.y..node.y + node.height {
@@ -41,59 +161,123 @@ fn protect_node_area(grid: &mut Grid, node: &Node) {
     }
 }
 
-/// Draw a rectangle node [Label]
-fn draw_rectangle(grid: &mut Grid, node: &Node, chars: &CharSet) {
-    let x = node.x;
-    let y = node.y;
-    let width = node.width;
-    let height = node.height;
+/// Line style of one band of an outline
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Line {
+    Single,
+    Double,
+    /// Single lines with rounded corners
+    Rounded,
+}
 
-    // Corners
-    grid.set_if_empty(x, y, chars.tl);
-    grid.set_if_empty(x + width - 1, y, chars.tr);
-    grid.set_if_empty(x, y + height - 1, chars.bl);
-    grid.set_if_empty(x + width - 1, y + height - 1, chars.br);
+/// One band of rows in an outline, listed top to bottom
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Band {
+    /// Top edge: corners joined by a rule
+    Top(Line),
+    /// Side walls for a number of rows; `None` takes the rows left over
+    Sides(Line, Option<usize>),
+    /// Rule across the shape, joined to the side walls
+    Divider(Line),
+    /// Bottom edge: corners joined by a rule
+    Bottom(Line),
+}
 
-    // Horizontal lines
-    for i in 1..width - 1 {
-        grid.set_if_empty(x + i, y, chars.h);
-        grid.set_if_empty(x + i, y + height - 1, chars.h);
-    }
+const BOX: [Band; 3] = [
+    Band::Top(Line::Single),
+    Band::Sides(Line::Single, None),
+    Band::Bottom(Line::Single),
+];
+
+const ROUNDED_BOX: [Band; 3] = [
+    Band::Top(Line::Rounded),
+    Band::Sides(Line::Single, None),
+    Band::Bottom(Line::Rounded),
+];
+
+const DOUBLE_BOX: [Band; 3] = [
+    Band::Top(Line::Double),
+    Band::Sides(Line::Double, None),
+    Band::Bottom(Line::Double),
+];
+
+/// `(x, y, width, height)` of a node's box
+fn bounds(node: &Node) -> (usize, usize, usize, usize) {
+    (node.x, node.y, node.width, node.height)
+}
 
-    // Vertical lines
-    for i in 1..height - 1 {
-        grid.set_if_empty(x, y + i, chars.v);
-        grid.set_if_empty(x + width - 1, y + i, chars.v);
+/// Draw an outline from its bands, so every row of a compound shape gets
+/// the line style its spec asks for
+fn draw_outline(
+    grid: &mut Grid,
+    (x, y, width, height): (usize, usize, usize, usize),
+    bands: &[Band],
+    chars: &CharSet,
+) {
+    let fixed: usize = bands
+        .iter()
+        .map(|band| match band {
+            Band::Sides(_, None) => 0,
+            Band::Sides(_, Some(rows)) => *rows,
+            _ => 1,
+        })
+        .sum();
+    let mut spare = height.saturating_sub(fixed);
+
+    let mut row = y;
+    for band in bands {
+        let (left, rule, right) = match *band {
+            Band::Top(Line::Single) => (chars.tl, chars.h, chars.tr),
+            Band::Top(Line::Double) => (chars.dtl, chars.dh, chars.dtr),
+            Band::Top(Line::Rounded) => (chars.rtl, chars.h, chars.rtr),
+            Band::Bottom(Line::Single) => (chars.bl, chars.h, chars.br),
+            Band::Bottom(Line::Double) => (chars.dbl, chars.dh, chars.dbr),
+            Band::Bottom(Line::Rounded) => (chars.rbl, chars.h, chars.rbr),
+            Band::Divider(Line::Double) => (chars.dml, chars.dh, chars.dmr),
+            Band::Divider(_) => (chars.ml, chars.h, chars.mr),
+            Band::Sides(line, rows) => {
+                let wall = if line == Line::Double {
+                    chars.dv
+                } else {
+                    chars.v
+                };
+                let rows = match rows {
+                    Some(rows) => rows,
+                    None => std::mem::take(&mut spare),
+                };
+                for r in row..(row + rows).min(y + height) {
+                    grid.set_if_empty(x, r, wall);
+                    grid.set_if_empty(x + width - 1, r, wall);
+                }
+                row += rows;
+                continue;
+            }
+        };
+        if row < y + height {
+            grid.set_if_empty(x, row, left);
+            grid.set_if_empty(x + width - 1, row, right);
+            for i in x + 1..x + width - 1 {
+                grid.set_if_empty(i, row, rule);
+            }
+        }
+        row += 1;
     }
+}
 
+/// Draw a rectangle node [Label]
+fn draw_rectangle(grid: &mut Grid, node: &Node, chars: &CharSet) {
+    draw_rectangle_border(grid, node, chars);
     draw_label(grid, node);
 }
 
+/// Draw the plain box outline of a node
+fn draw_rectangle_border(grid: &mut Grid, node: &Node, chars: &CharSet) {
+    draw_outline(grid, bounds(node), &BOX, chars);
+}
+
 /// Draw a rounded rectangle node (Label)
 fn draw_rounded(grid: &mut Grid, node: &Node, chars: &CharSet) {
-    let x = node.x;
-    let y = node.y;
-    let width = node.width;
-    let height = node.height;
-
-    // Rounded corners
-    grid.set_if_empty(x, y, chars.rtl);
-    grid.set_if_empty(x + width - 1, y, chars.rtr);
-    grid.set_if_empty(x, y + height - 1, chars.rbl);
-    grid.set_if_empty(x + width - 1, y + height - 1, chars.rbr);
-
-    // Horizontal lines
-    for i in 1..width - 1 {
-        grid.set_if_empty(x + i, y, chars.h);
-        grid.set_if_empty(x + i, y + height - 1, chars.h);
-    }
-
-    // Vertical lines
-    for i in 1..height - 1 {
-        grid.set_if_empty(x, y + i, chars.v);
-        grid.set_if_empty(x + width - 1, y + i, chars.v);
-    }
-
+    draw_outline(grid, bounds(node), &ROUNDED_BOX, chars);
     draw_label(grid, node);
 }
 
@@ -133,37 +317,55 @@ fn draw_circle(grid: &mut Grid, node: &Node, chars: &CharSet) {
     draw_label(grid, node);
 }
 
+/// Draw a double circle node (((Label)))
+///
+/// Two nested rounded outlines:
+/// ╭────────╮
+/// │╭──────╮│
+/// ││ Done ││
+/// │╰──────╯│
+/// ╰────────╯
+fn draw_double_circle(grid: &mut Grid, node: &Node, chars: &CharSet) {
+    let (x, y, width, height) = bounds(node);
+    draw_outline(grid, (x, y, width, height), &ROUNDED_BOX, chars);
+    draw_outline(
+        grid,
+        (x + 1, y + 1, width - 2, height - 2),
+        &ROUNDED_BOX,
+        chars,
+    );
+    draw_label(grid, node);
+}
+
 /// Draw a diamond node {Label}
+///
+/// A rhombus whose sides step two columns per row (layout sizes it to
+/// `4 * m + 2` by `2 * m + 1`):
+///     ╱╲
+///   ╱    ╲
+/// <Decide  >
+///   ╲    ╱
+///     ╲╱
 fn draw_diamond(grid: &mut Grid, node: &Node, chars: &CharSet) {
     let x = node.x;
     let y = node.y;
     let width = node.width;
     let height = node.height;
+    let mid = height / 2;
 
-    // Diamond shape with / and \
-    let mid_x = width / 2;
-
-    // Top point
-    grid.set_if_empty(x + mid_x, y, '/');
-    if mid_x + 1 < width {
-        grid.set_if_empty(x + mid_x + 1, y, '\\');
-    }
-
-    // Bottom point
-    grid.set_if_empty(x + mid_x, y + height - 1, '\\');
-    if mid_x + 1 < width {
-        grid.set_if_empty(x + mid_x + 1, y + height - 1, '/');
-    }
-
-    // Left and right edges
-    for i in 1..height - 1 {
-        grid.set_if_empty(x, y + i, '<');
-        grid.set_if_empty(x + width - 1, y + i, '>');
-    }
-
-    // Fill middle row with horizontal line
-    for i in 1..width - 1 {
-        grid.set_if_empty(x + i, y + 1, chars.h);
+    for i in 0..height {
+        let d = i.abs_diff(mid);
+        let (left, right) = (x + 2 * d, (x + width - 1).saturating_sub(2 * d));
+        if left >= right {
+            continue;
+        }
+        let (lc, rc) = match i.cmp(&mid) {
+            std::cmp::Ordering::Less => (chars.diag_up, chars.diag_down),
+            std::cmp::Ordering::Equal => ('<', '>'),
+            std::cmp::Ordering::Greater => (chars.diag_down, chars.diag_up),
+        };
+        grid.set_if_empty(left, y + i, lc);
+        grid.set_if_empty(right, y + i, rc);
     }
 
     draw_label(grid, node);
@@ -171,51 +373,24 @@ fn draw_diamond(grid: &mut Grid, node: &Node, chars: &CharSet) {
 
 /// Draw a cylinder/database node [(Label)]
 /// 5-row layout:
-///   ╭───╮  row 0: rtl + h + rtr
-///   ├───┤  row 1: ml + h + mr
-///   │ X │  row 2: v + label + v
-///   ├───┤  row 3: ml + h + mr
-///   ╰───╯  row 4: rbl + h + rbr
+///   ╭───╮  row 0: rounded top
+///   ├───┤  row 1: rim
+///   │ X │  row 2: sides + label
+///   ├───┤  row 3: rim
+///   ╰───╯  row 4: rounded bottom
 fn draw_cylinder(grid: &mut Grid, node: &Node, chars: &CharSet) {
-    let x = node.x;
-    let y = node.y;
-    let width = node.width;
-    let height = node.height;
-
-    // Row 0: top rounded
-    grid.set_if_empty(x, y, chars.rtl);
-    grid.set_if_empty(x + width - 1, y, chars.rtr);
-    for i in 1..width - 1 {
-        grid.set_if_empty(x + i, y, chars.h);
-    }
-
-    // Row 1: top separator
-    grid.set_if_empty(x, y + 1, chars.ml);
-    grid.set_if_empty(x + width - 1, y + 1, chars.mr);
-    for i in 1..width - 1 {
-        grid.set_if_empty(x + i, y + 1, chars.h);
-    }
-
-    // Middle rows: vertical sides
-    for i in 2..height - 2 {
-        grid.set_if_empty(x, y + i, chars.v);
-        grid.set_if_empty(x + width - 1, y + i, chars.v);
-    }
-
-    // Row height-2: bottom separator
-    grid.set_if_empty(x, y + height - 2, chars.ml);
-    grid.set_if_empty(x + width - 1, y + height - 2, chars.mr);
-    for i in 1..width - 1 {
-        grid.set_if_empty(x + i, y + height - 2, chars.h);
-    }
-
-    // Row height-1: bottom rounded
-    grid.set_if_empty(x, y + height - 1, chars.rbl);
-    grid.set_if_empty(x + width - 1, y + height - 1, chars.rbr);
-    for i in 1..width - 1 {
-        grid.set_if_empty(x + i, y + height - 1, chars.h);
-    }
-
+    draw_outline(
+        grid,
+        bounds(node),
+        &[
+            Band::Top(Line::Rounded),
+            Band::Divider(Line::Single),
+            Band::Sides(Line::Single, None),
+            Band::Divider(Line::Single),
+            Band::Bottom(Line::Rounded),
+        ],
+        chars,
+    );
     draw_label(grid, node);
 }
 
@@ -382,81 +557,118 @@ fn draw_trapezoid(grid: &mut Grid, node: &Node, chars: &CharSet, reverse: bool)
     draw_label(grid, node);
 }
 
-/// Draw a table node (D2 sql_table) - uses double borders with field rows
-fn draw_table(grid: &mut Grid, node: &Node, chars: &CharSet) {
+/// Draw an asymmetric flag node >Label]
+///
+/// Notched left side, flat right side:
+/// ```text
+/// ╲───────┐
+///  > Label│
+/// ╱───────┘
+/// ```
+fn draw_asymmetric(grid: &mut Grid, node: &Node, chars: &CharSet) {
     let x = node.x;
     let y = node.y;
     let width = node.width;
     let height = node.height;
 
-    if node.fields.is_empty() {
-        // Simple table without fields (original behavior)
-        grid.set_if_empty(x, y, chars.dtl);
-        grid.set_if_empty(x + width - 1, y, chars.dtr);
-        grid.set_if_empty(x, y + height - 1, chars.dbl);
-        grid.set_if_empty(x + width - 1, y + height - 1, chars.dbr);
-
-        for i in 1..width - 1 {
-            grid.set_if_empty(x + i, y, chars.dh);
-            grid.set_if_empty(x + i, y + height - 1, chars.dh);
-        }
+    grid.set_if_empty(x, y, chars.diag_down);
+    grid.set_if_empty(x, y + height - 1, chars.diag_up);
+    grid.set_if_empty(x + width - 1, y, chars.tr);
+    grid.set_if_empty(x + width - 1, y + height - 1, chars.br);
+    for i in 1..width - 1 {
+        grid.set_if_empty(x + i, y, chars.h);
+        grid.set_if_empty(x + i, y + height - 1, chars.h);
+    }
+
+    // Sides
+    for i in 1..height - 1 {
+        grid.set_if_empty(x + 1, y + i, '>');
+        grid.set_if_empty(x + width - 1, y + i, chars.v);
+    }
 
-        for i in 1..height - 1 {
-            grid.set_if_empty(x, y + i, chars.dv);
-            grid.set_if_empty(x + width - 1, y + i, chars.dv);
+    draw_label(grid, node);
+}
+
+/// Draw a compartment node (Mermaid `:::compartment`)
+///
+/// The first line is a centered title; the rest share a left edge:
+/// ┌────────┐
+/// │  User  │
+/// ├────────┤
+/// │+name   │
+/// ├────────┤
+/// │+login()│
+/// └────────┘
+fn draw_compartment(grid: &mut Grid, node: &Node, chars: &CharSet) {
+    let (x, y, width) = (node.x, node.y, node.width);
+    let lines = label_lines(node);
+
+    let mut bands = vec![Band::Top(Line::Single)];
+    for i in 0..lines.len() {
+        if i > 0 {
+            bands.push(Band::Divider(Line::Single));
         }
+        bands.push(Band::Sides(Line::Single, Some(1)));
+    }
+    bands.push(Band::Bottom(Line::Single));
+    draw_outline(grid, bounds(node), &bands, chars);
+
+    let members_w = lines
+        .iter()
+        .skip(1)
+        .map(|&(_, l)| display_width(l))
+        .max()
+        .unwrap_or(0);
+    for (i, &(offset, line)) in lines.iter().enumerate() {
+        let row = y + 1 + 2 * i;
+        let line_w = if i == 0 {
+            display_width(line)
+        } else {
+            members_w
+        };
+        let lx = x + width.saturating_sub(line_w) / 2;
+        draw_label_line(grid, node, lx, row, offset, line);
+    }
+}
 
+/// Draw a table node (D2 sql_table) - double borders all round, with the
+/// fields under a header row:
+/// ```text
+/// ╔═══════════╗
+/// ║   users   ║
+/// ╠═══════════╣
+/// ║ id: int   ║
+/// ╚═══════════╝
+/// ```
+fn draw_table(grid: &mut Grid, node: &Node, chars: &CharSet) {
+    if node.fields.is_empty() {
+        draw_outline(grid, bounds(node), &DOUBLE_BOX, chars);
         draw_label(grid, node);
         return;
     }
 
-    // Table with fields layout:
-    // Row 0: ╔═══════════╗  top border
-    // Row 1: ║   label   ║  label row
-    // Row 2: ╠═══════════╣  separator (using ╠/╣ for T-junctions)
-    // Row 3: ║ field 1   ║  field rows...
-    // Row N: ╚═══════════╝  bottom border
-
-    // Top border
-    grid.set_if_empty(x, y, chars.dtl);
-    grid.set_if_empty(x + width - 1, y, chars.dtr);
-    for i in 1..width - 1 {
-        grid.set_if_empty(x + i, y, chars.dh);
-    }
-
-    // Label row (row 1) - use first line for table header
-    grid.set_if_empty(x, y + 1, chars.dv);
-    grid.set_if_empty(x + width - 1, y + 1, chars.dv);
-    // Center label (use first line if multi-line)
+    draw_outline(
+        grid,
+        bounds(node),
+        &[
+            Band::Top(Line::Double),
+            Band::Sides(Line::Double, Some(1)),
+            Band::Divider(Line::Double),
+            Band::Sides(Line::Double, None),
+            Band::Bottom(Line::Double),
+        ],
+        chars,
+    );
+
+    // Header: first line of the label, centered
+    let (x, y, width) = (node.x, node.y, node.width);
     let first_line = node.label.split('\n').next().unwrap_or(&node.label);
     let label_x = x + (width.saturating_sub(display_width(first_line))) / 2;
-    draw_text(grid, label_x, y + 1, first_line);
-
-    // Separator (row 2)
-    grid.set_if_empty(x, y + 2, chars.ml);
-    grid.set_if_empty(x + width - 1, y + 2, chars.mr);
-    for i in 1..width - 1 {
-        grid.set_if_empty(x + i, y + 2, chars.h);
-    }
+    draw_label_line(grid, node, label_x, y + 1, 0, first_line);
 
-    // Field rows
     for (fi, field) in node.fields.iter().enumerate() {
-        let row_y = y + 3 + fi;
-        grid.set_if_empty(x, row_y, chars.v);
-        grid.set_if_empty(x + width - 1, row_y, chars.v);
-
-        // Format field text
         let field_text = format_field_text(field, width.saturating_sub(4));
-        let text_x = x + 2; // 1 for border + 1 padding
-        draw_text(grid, text_x, row_y, &field_text);
-    }
-
-    // Bottom border
-    let bot_y = y + height - 1;
-    grid.set_if_empty(x, bot_y, chars.bl);
-    grid.set_if_empty(x + width - 1, bot_y, chars.br);
-    for i in 1..width - 1 {
-        grid.set_if_empty(x + i, bot_y, chars.h);
+        grid.draw_text(x + 2, y + 3 + fi, &field_text);
     }
 }
 
@@ -477,33 +689,12 @@ fn format_field_text(field: &crate::types::TableField, max_width: usize) -> Stri
                 text.push_str(" [");
                 text.push_str(other);
                 text.push(']');
-                if display_width(&text) > max_width {
-                    truncate_to_width(&mut text, max_width);
-                }
-                return text;
+                return truncate_to_width(&text, max_width).to_string();
             }
         };
         text.push_str(abbrev);
     }
-    if display_width(&text) > max_width {
-        truncate_to_width(&mut text, max_width);
-    }
-    text
-}
-
-/// Truncate string to fit within display width
-fn truncate_to_width(s: &mut String, max_width: usize) {
-    let mut width = 0;
-    let mut byte_pos = 0;
-    for c in s.chars() {
-        let cw = UnicodeWidthChar::width(c).unwrap_or(1);
-        if width + cw > max_width {
-            break;
-        }
-        width += cw;
-        byte_pos += c.len_utf8();
-    }
-    s.truncate(byte_pos);
+    truncate_to_width(&text, max_width).to_string()
 }
 
 /// Draw a person/stick figure node (D2 person shape)
@@ -542,30 +733,18 @@ fn draw_person(grid: &mut Grid, node: &Node, chars: &CharSet) {
     }
 
     // Label centered below the figure
-    let label_lines: Vec<&str> = node.label.split('\n').collect();
     let label_start_y = y + 3;
-    for (li, line) in label_lines.iter().enumerate() {
+    for (li, (offset, line)) in label_lines(node).into_iter().enumerate() {
         let lw = display_width(line);
         let lx = x + (width.saturating_sub(lw)) / 2;
         let ly = label_start_y + li;
         if ly < y + height {
-            draw_text(grid, lx, ly, line);
+            draw_label_line(grid, node, lx, ly, offset, line);
         }
     }
 
     // Draw border box around the whole thing
-    grid.set_if_empty(x, y, chars.rtl);
-    grid.set_if_empty(x + width - 1, y, chars.rtr);
-    grid.set_if_empty(x, y + height - 1, chars.rbl);
-    grid.set_if_empty(x + width - 1, y + height - 1, chars.rbr);
-    for i in 1..width - 1 {
-        grid.set_if_empty(x + i, y, chars.h);
-        grid.set_if_empty(x + i, y + height - 1, chars.h);
-    }
-    for i in 1..height - 1 {
-        grid.set_if_empty(x, y + i, chars.v);
-        grid.set_if_empty(x + width - 1, y + i, chars.v);
-    }
+    draw_outline(grid, (x, y, width, height), &ROUNDED_BOX, chars);
 }
 
 /// Draw a cloud node (D2 cloud shape)
@@ -649,25 +828,182 @@ fn draw_document(grid: &mut Grid, node: &Node, chars: &CharSet) {
     draw_label(grid, node);
 }
 
+/// Draw an oval node (D2 oval/ellipse shape)
+///
+/// Top and bottom curve in one cell from the parenthesis sides:
+///  ╭───────╮
+/// (  Label  )
+///  ╰───────╯
+fn draw_oval(grid: &mut Grid, node: &Node, chars: &CharSet) {
+    let x = node.x;
+    let y = node.y;
+    let width = node.width;
+    let height = node.height;
+
+    // Inset curved top and bottom
+    grid.set_if_empty(x + 1, y, chars.rtl);
+    grid.set_if_empty(x + width - 2, y, chars.rtr);
+    grid.set_if_empty(x + 1, y + height - 1, chars.rbl);
+    grid.set_if_empty(x + width - 2, y + height - 1, chars.rbr);
+    for i in 2..width - 2 {
+        grid.set_if_empty(x + i, y, chars.h);
+        grid.set_if_empty(x + i, y + height - 1, chars.h);
+    }
+
+    // Sides
+    for i in 1..height - 1 {
+        grid.set_if_empty(x, y + i, '(');
+        grid.set_if_empty(x + width - 1, y + i, ')');
+    }
+
+    draw_label(grid, node);
+}
+
+/// Draw a package node (D2 package shape)
+///
+/// Folder with a tab over its left third:
+/// ┌───┐
+/// │   └─────┐
+/// │  Label  │
+/// └─────────┘
+fn draw_package(grid: &mut Grid, node: &Node, chars: &CharSet) {
+    let x = node.x;
+    let y = node.y;
+    let width = node.width;
+    let height = node.height;
+    let tab_right = x + (width / 3).max(2);
+
+    // Tab
+    grid.set_if_empty(x, y, chars.tl);
+    grid.set_if_empty(tab_right, y, chars.tr);
+    for i in x + 1..tab_right {
+        grid.set_if_empty(i, y, chars.h);
+    }
+
+    // Body top, stepping down from the tab
+    grid.set_if_empty(tab_right, y + 1, chars.bl);
+    grid.set_if_empty(x + width - 1, y + 1, chars.tr);
+    for i in tab_right + 1..x + width - 1 {
+        grid.set_if_empty(i, y + 1, chars.h);
+    }
+
+    // Bottom
+    grid.set_if_empty(x, y + height - 1, chars.bl);
+    grid.set_if_empty(x + width - 1, y + height - 1, chars.br);
+    for i in 1..width - 1 {
+        grid.set_if_empty(x + i, y + height - 1, chars.h);
+    }
+
+    // Sides
+    for i in 1..height - 1 {
+        grid.set_if_empty(x, y + i, chars.v);
+    }
+    for i in 2..height - 1 {
+        grid.set_if_empty(x + width - 1, y + i, chars.v);
+    }
+
+    draw_label_in(grid, node, node.y + 1, node.height - 1);
+}
+
+/// Draw a step node (D2 step shape)
+///
+/// Chevron with notched left side and pointed right side:
+/// ```text
+/// ╲───────╲
+///  > Label >
+/// ╱───────╱
+/// ```
+fn draw_step(grid: &mut Grid, node: &Node, chars: &CharSet) {
+    let x = node.x;
+    let y = node.y;
+    let width = node.width;
+    let height = node.height;
+
+    grid.set_if_empty(x, y, chars.diag_down);
+    grid.set_if_empty(x + width - 2, y, chars.diag_down);
+    grid.set_if_empty(x, y + height - 1, chars.diag_up);
+    grid.set_if_empty(x + width - 2, y + height - 1, chars.diag_up);
+    for i in 1..width - 2 {
+        grid.set_if_empty(x + i, y, chars.h);
+        grid.set_if_empty(x + i, y + height - 1, chars.h);
+    }
+
+    // Sides
+    for i in 1..height - 1 {
+        grid.set_if_empty(x + 1, y + i, '>');
+        grid.set_if_empty(x + width - 1, y + i, '>');
+    }
+
+    draw_label(grid, node);
+}
+
+/// Draw a callout node (D2 callout shape)
+///
+/// Box with a speech tail under its left side:
+/// ┌───────┐
+/// │ Label │
+/// └─┐ ┌───┘
+///   │╱
+fn draw_callout(grid: &mut Grid, node: &Node, chars: &CharSet) {
+    let x = node.x;
+    let y = node.y;
+    let width = node.width;
+    let height = node.height;
+    let bottom = y + height - 2;
+
+    grid.set_if_empty(x, y, chars.tl);
+    grid.set_if_empty(x + width - 1, y, chars.tr);
+    grid.set_if_empty(x, bottom, chars.bl);
+    grid.set_if_empty(x + width - 1, bottom, chars.br);
+    for i in 1..width - 1 {
+        grid.set_if_empty(x + i, y, chars.h);
+    }
+    for i in y + 1..bottom {
+        grid.set_if_empty(x, i, chars.v);
+        grid.set_if_empty(x + width - 1, i, chars.v);
+    }
+
+    // Bottom edge opens into the tail
+    for i in 1..width - 1 {
+        let c = match i {
+            2 => chars.tr,
+            3 => ' ',
+            4 => chars.tl,
+            _ => chars.h,
+        };
+        grid.set_if_empty(x + i, bottom, c);
+    }
+    grid.set_if_empty(x + 2, bottom + 1, chars.v);
+    grid.set_if_empty(x + 3, bottom + 1, chars.diag_up);
+
+    draw_label_in(grid, node, node.y, node.height - 1);
+}
+
 /// Draw the label centered in the node (supports multi-line via \n)
 fn draw_label(grid: &mut Grid, node: &Node) {
-    let lines: Vec<&str> = node.label.split('\n').collect();
+    draw_label_in(grid, node, node.y, node.height);
+}
+
+/// Draw the label centered in the `height` rows starting at `top`
+fn draw_label_in(grid: &mut Grid, node: &Node, top: usize, height: usize) {
+    let lines = label_lines(node);
     let line_count = lines.len();
-    // Vertically center the block of lines within the node
-    let block_start_y = node.y + (node.height.saturating_sub(line_count)) / 2;
-    for (line_idx, line) in lines.iter().enumerate() {
-        let line_w = display_width(line);
+    // Vertically center the block of lines within the rows
+    let block_start_y = top + (height.saturating_sub(line_count)) / 2;
+    // Verbatim text is centered as a block so its lines stay aligned
+    let block_w = lines
+        .iter()
+        .map(|&(_, l)| display_width(l))
+        .max()
+        .unwrap_or(0);
+    for (line_idx, &(offset, line)) in lines.iter().enumerate() {
+        let line_w = if node.verbatim {
+            block_w
+        } else {
+            display_width(line)
+        };
         let label_x = node.x + (node.width.saturating_sub(line_w)) / 2;
         let label_y = block_start_y + line_idx;
-        draw_text(grid, label_x, label_y, line);
-    }
-}
-
-/// Draw text at position, advancing x by per-char display width (CJK-aware)
-fn draw_text(grid: &mut Grid, x: usize, y: usize, text: &str) {
-    let mut dx = 0;
-    for c in text.chars() {
-        grid.set_if_empty(x + dx, y, c);
-        dx += UnicodeWidthChar::width(c).unwrap_or(1);
+        draw_label_line(grid, node, label_x, label_y, offset, line);
     }
 }
@@ -2,8 +2,7 @@
 
 use crate::grid::Grid;
 use crate::text::display_width;
-use crate::types::Subgraph;
-use unicode_width::UnicodeWidthChar;
+use crate::types::{Graph, Subgraph};
 
 use super::charset::CharSet;
 
@@ -37,19 +36,134 @@ pub fn draw_subgraph(grid: &mut Grid, sg: &Subgraph, chars: &CharSet) {
     }
 
     // Label (top center)
+    if let Some((label_x, _)) = label_span(sg) {
+        grid.draw_text(label_x, y, &sg.label);
+    }
+}
+
+/// Columns `[start, end)` of the top border taken by the label, if drawn
+fn label_span(sg: &Subgraph) -> Option<(usize, usize)> {
     let label_w = display_width(&sg.label);
-    if !sg.label.is_empty() && width > label_w + 2 {
-        let label_x = x + (width - label_w) / 2;
-        let mut dx = 0;
-        for c in sg.label.chars() {
-            grid.set(label_x + dx, y, c);
-            dx += UnicodeWidthChar::width(c).unwrap_or(1);
+    if !sg.label.is_empty() && sg.width > label_w + 2 {
+        let label_x = sg.x + (sg.width - label_w) / 2;
+        Some((label_x, label_x + label_w))
+    } else {
+        None
+    }
+}
+
+/// A border cell where edges may cross into or out of a subgraph
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Gateway {
+    pub x: usize,
+    pub y: usize,
+    /// On the top or bottom border (crossed vertically)
+    pub horizontal: bool,
+}
+
+/// Border cells edges may pass through.
+///
+/// Each side gets a gateway at its midpoint and where the centre line of
+/// every member node meets it, so edges to members can cross straight in.
+/// Corners and the label on the top border are never gateways.
+pub fn subgraph_gateways(sg: &Subgraph, graph: &Graph) -> Vec<Gateway> {
+    if sg.width < 3 || sg.height < 3 {
+        return Vec::new();
+    }
+    let (left, right) = (sg.x, sg.x + sg.width - 1);
+    let (top, bottom) = (sg.y, sg.y + sg.height - 1);
+    let members = sg.nodes.iter().filter_map(|id| graph.nodes.get(id));
+
+    let mut columns = vec![sg.x + sg.width / 2];
+    let mut rows = vec![sg.y + sg.height / 2];
+    for node in members {
+        columns.push(node.x + node.width / 2);
+        rows.push(node.y + node.height / 2);
+    }
+    columns.sort_unstable();
+    columns.dedup();
+    rows.sort_unstable();
+    rows.dedup();
+
+    // Top-border gateways under the label move to the nearer side of it,
+    // a cell clear of it where the border leaves room, so an edge crossing
+    // there doesn't read as part of the title
+    let label = label_span(sg);
+    let mut top_columns: Vec<usize> = columns
+        .iter()
+        .map(|&x| match label {
+            Some((start, end)) if x >= start && x < end => {
+                if x - start < end - x {
+                    let clear = start.saturating_sub(2);
+                    if clear > left {
+                        clear
+                    } else {
+                        start.saturating_sub(1)
+                    }
+                } else if end + 1 < right {
+                    end + 1
+                } else {
+                    end
+                }
+            }
+            _ => x,
+        })
+        .collect();
+    top_columns.dedup();
+
+    let mut gateways = Vec::new();
+    for &x in top_columns.iter().filter(|&&x| x > left && x < right) {
+        gateways.push(Gateway {
+            x,
+            y: top,
+            horizontal: true,
+        });
+    }
+    for &x in columns.iter().filter(|&&x| x > left && x < right) {
+        gateways.push(Gateway {
+            x,
+            y: bottom,
+            horizontal: true,
+        });
+    }
+    for &y in rows.iter().filter(|&&y| y > top && y < bottom) {
+        for x in [left, right] {
+            gateways.push(Gateway {
+                x,
+                y,
+                horizontal: false,
+            });
         }
     }
+    gateways
+}
+
+/// Redraw gateway cells once edges are in place.
+///
+/// A gateway an edge crossed shows the crossing glyph (`╪` or `╫`); one
+/// left unused, or only touched by text, gets its plain border back.
+pub fn draw_gateways(grid: &mut Grid, gateways: &[Gateway], chars: &CharSet) {
+    for gw in gateways {
+        let flags = grid.line_flags(gw.x, gw.y);
+        let c = if gw.horizontal {
+            if flags.up || flags.down {
+                chars.gate_h
+            } else {
+                chars.dh
+            }
+        } else if flags.left || flags.right {
+            chars.gate_v
+        } else {
+            chars.dv
+        };
+        grid.set_protected(gw.x, gw.y, c);
+    }
 }
 
-/// Protect subgraph border cells so nodes/edges can't overwrite them
-pub fn protect_subgraph_borders(grid: &mut Grid, sg: &Subgraph) {
+/// Protect subgraph border cells so nodes/edges can't overwrite them.
+///
+/// Gateway cells stay open so routed edges can cross there.
+pub fn protect_subgraph_borders(grid: &mut Grid, sg: &Subgraph, gateways: &[Gateway]) {
     if sg.width == 0 || sg.height == 0 {
         return;
     }
@@ -58,22 +172,62 @@ pub fn protect_subgraph_borders(grid: &mut Grid, sg: &Subgraph) {
     let y = sg.y;
     let width = sg.width;
     let height = sg.height;
+    let mut protect = |px: usize, py: usize| {
+        if !gateways.iter().any(|gw| gw.x == px && gw.y == py) {
+            grid.mark_protected(px, py);
+        }
+    };
 
     // Protect corners
-    grid.mark_protected(x, y);
-    grid.mark_protected(x + width - 1, y);
-    grid.mark_protected(x, y + height - 1);
-    grid.mark_protected(x + width - 1, y + height - 1);
+    protect(x, y);
+    protect(x + width - 1, y);
+    protect(x, y + height - 1);
+    protect(x + width - 1, y + height - 1);
 
     // Protect horizontal lines (top and bottom)
     for i in 1..width - 1 {
-        grid.mark_protected(x + i, y);
-        grid.mark_protected(x + i, y + height - 1);
+        protect(x + i, y);
+        protect(x + i, y + height - 1);
     }
 
     // Protect vertical lines (left and right)
     for i in 1..height - 1 {
-        grid.mark_protected(x, y + i);
-        grid.mark_protected(x + width - 1, y + i);
+        protect(x, y + i);
+        protect(x + width - 1, y + i);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Direction, Node};
+
+    #[test]
+    fn test_gateways_skip_label_and_corners() {
+        let mut graph = Graph::new(Direction::TB);
+        let mut node = Node::new("a".to_string(), "a".to_string());
+        node.x = 3;
+        node.y = 3;
+        node.width = 5;
+        node.height = 3;
+        graph.nodes.insert("a".to_string(), node);
+        let mut sg = Subgraph::new("s".to_string(), "Box".to_string());
+        sg.nodes.push("a".to_string());
+        sg.x = 0;
+        sg.y = 0;
+        sg.width = 11;
+        sg.height = 9;
+
+        let gateways = subgraph_gateways(&sg, &graph);
+        // Label "Box" spans columns 4..7, so the centre gateway moves a cell
+        // clear of it
+        let top: Vec<usize> = gateways.iter().filter(|g| g.y == 0).map(|g| g.x).collect();
+        assert_eq!(top, vec![2]);
+        let bottom: Vec<usize> = gateways.iter().filter(|g| g.y == 8).map(|g| g.x).collect();
+        assert_eq!(bottom, vec![5]);
+        assert!(gateways
+            .iter()
+            .filter(|g| !g.horizontal)
+            .all(|g| (g.x == 0 || g.x == 10) && g.y == 4));
     }
 }
@@ -0,0 +1,290 @@
+//! The same drawing as plain text, HTML and SVG, for notebook kernels and
+//! other frontends that can show more than a terminal
+
+use crate::error::MermaidError;
+use crate::render;
+use crate::text::{display_width, strip_ansi};
+use crate::types::{DiagramWarning, RenderOptions};
+
+/// Horizontal size of one character cell in SVG output, in pixels
+const CELL_WIDTH: usize = 9;
+/// Vertical size of one line in SVG output, in pixels
+const LINE_HEIGHT: usize = 18;
+
+/// A diagram rendered once in every format a frontend might display
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RichOutput {
+    /// The drawing as plain text, without color codes
+    pub text: String,
+    /// The drawing as a `<pre>` block, with colors as inline styles
+    pub html: String,
+    /// The drawing as a standalone SVG image of monospace text
+    pub svg: String,
+    /// Warnings generated during layout/rendering
+    pub warnings: Vec<DiagramWarning>,
+}
+
+/// Render a diagram as text, HTML and SVG at once, so a notebook kernel
+/// can pick the richest format its frontend supports.
+///
+/// `lang` is dispatched like [`render`]. Colors the renderer draws, like
+/// sequence diagram `rect` shading, carry over into the HTML and SVG.
+///
+/// # Example
+/// ```
+/// use graphs_tui::render_rich;
+///
+/// let rich = render_rich("mermaid", "flowchart LR\nA --> B").unwrap();
+/// assert!(rich.html.starts_with("<pre"));
+/// assert!(rich.svg.starts_with("<svg"));
+/// ```
+pub fn render_rich(lang: &str, code: &str) -> Result<RichOutput, MermaidError> {
+    let options = RenderOptions {
+        colors: true,
+        ..Default::default()
+    };
+    let result = render(lang, code, options)?;
+    Ok(RichOutput {
+        // Background fills pad shaded rows past the drawing
+        text: strip_ansi(&result.output)
+            .lines()
+            .map(str::trim_end)
+            .collect::<Vec<_>>()
+            .join("\n"),
+        html: to_html(&result.output),
+        svg: to_svg(&result.output),
+        warnings: result.warnings,
+    })
+}
+
+/// A stretch of text drawn in one color
+#[derive(Debug, Default, PartialEq)]
+struct Run {
+    text: String,
+    /// Foreground as `#rrggbb`
+    fg: Option<String>,
+    /// Background as `#rrggbb`
+    bg: Option<String>,
+}
+
+/// Split text with ANSI color codes into lines of same-colored runs
+fn styled_lines(text: &str) -> Vec<Vec<Run>> {
+    let mut lines = Vec::new();
+    let mut fg: Option<String> = None;
+    let mut bg: Option<String> = None;
+    for line in text.lines() {
+        let mut runs: Vec<Run> = Vec::new();
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c == '\x1b' && chars.peek() == Some(&'[') {
+                chars.next();
+                let mut params = String::new();
+                let mut command = None;
+                for c in chars.by_ref() {
+                    if ('\x40'..='\x7e').contains(&c) {
+                        command = Some(c);
+                        break;
+                    }
+                    params.push(c);
+                }
+                if command == Some('m') {
+                    apply_sgr(&params, &mut fg, &mut bg);
+                }
+                continue;
+            }
+            match runs.last_mut() {
+                Some(run) if run.fg == fg && run.bg == bg => run.text.push(c),
+                _ => runs.push(Run {
+                    text: c.to_string(),
+                    fg: fg.clone(),
+                    bg: bg.clone(),
+                }),
+            }
+        }
+        lines.push(runs);
+    }
+    lines
+}
+
+/// Update colors from the parameters of an SGR (`ESC [ ... m`) sequence;
+/// only resets and 24-bit colors are emitted by the renderers
+fn apply_sgr(params: &str, fg: &mut Option<String>, bg: &mut Option<String>) {
+    let codes: Vec<&str> = params.split(';').collect();
+    let mut i = 0;
+    while i < codes.len() {
+        match codes[i] {
+            "" | "0" => {
+                *fg = None;
+                *bg = None;
+            }
+            "39" => *fg = None,
+            "49" => *bg = None,
+            target @ ("38" | "48") if codes.get(i + 1) == Some(&"2") => {
+                let rgb: Vec<u8> = codes
+                    .iter()
+                    .skip(i + 2)
+                    .take(3)
+                    .filter_map(|c| c.parse().ok())
+                    .collect();
+                if let [r, g, b] = rgb[..] {
+                    let color = Some(format!("#{:02x}{:02x}{:02x}", r, g, b));
+                    if target == "38" {
+                        *fg = color;
+                    } else {
+                        *bg = color;
+                    }
+                }
+                i += 4;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+}
+
+/// Drawing as an HTML `<pre>` block with colored spans
+fn to_html(text: &str) -> String {
+    let mut html =
+        String::from("<pre style=\"font-family: monospace; line-height: 1.2; margin: 0\">");
+    for (i, runs) in styled_lines(text).iter().enumerate() {
+        if i > 0 {
+            html.push('\n');
+        }
+        for run in runs {
+            let mut style = Vec::new();
+            if let Some(fg) = &run.fg {
+                style.push(format!("color:{}", fg));
+            }
+            if let Some(bg) = &run.bg {
+                style.push(format!("background:{}", bg));
+            }
+            if style.is_empty() {
+                html.push_str(&escape_xml(&run.text));
+            } else {
+                html.push_str(&format!(
+                    "<span style=\"{}\">{}</span>",
+                    style.join(";"),
+                    escape_xml(&run.text)
+                ));
+            }
+        }
+    }
+    html.push_str("</pre>");
+    html
+}
+
+/// Drawing as an SVG of monospace lines, one character cell per column;
+/// colors become fills and background rectangles
+pub(crate) fn to_svg(text: &str) -> String {
+    let lines = styled_lines(text);
+    let line_width = |runs: &[Run]| runs.iter().map(|r| display_width(&r.text)).sum::<usize>();
+    let columns = lines.iter().map(|l| line_width(l)).max().unwrap_or(0);
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" \
+         font-family=\"monospace\" font-size=\"15\">\n",
+        columns * CELL_WIDTH,
+        lines.len() * LINE_HEIGHT
+    );
+    for (row, runs) in lines.iter().enumerate() {
+        let mut column = 0;
+        for run in runs {
+            let width = display_width(&run.text);
+            if let Some(bg) = &run.bg {
+                svg.push_str(&format!(
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>\n",
+                    column * CELL_WIDTH,
+                    row * LINE_HEIGHT,
+                    width * CELL_WIDTH,
+                    LINE_HEIGHT,
+                    bg
+                ));
+            }
+            column += width;
+        }
+    }
+    svg.push_str("<text xml:space=\"preserve\">");
+    for (row, runs) in lines.iter().enumerate() {
+        svg.push_str(&format!(
+            "<tspan x=\"0\" y=\"{}\" textLength=\"{}\">",
+            (row + 1) * LINE_HEIGHT - 4,
+            line_width(runs) * CELL_WIDTH
+        ));
+        for run in runs {
+            match &run.fg {
+                Some(fg) => svg.push_str(&format!(
+                    "<tspan fill=\"{}\">{}</tspan>",
+                    fg,
+                    escape_xml(&run.text)
+                )),
+                None => svg.push_str(&escape_xml(&run.text)),
+            }
+        }
+        svg.push_str("</tspan>");
+    }
+    svg.push_str("</text>\n</svg>\n");
+    svg
+}
+
+fn escape_xml(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_styled_lines_track_colors_across_lines() {
+        let lines = styled_lines("a\x1b[38;2;255;0;0mb\nc\x1b[0md");
+        assert_eq!(
+            lines,
+            vec![
+                vec![
+                    Run {
+                        text: "a".into(),
+                        ..Default::default()
+                    },
+                    Run {
+                        text: "b".into(),
+                        fg: Some("#ff0000".into()),
+                        bg: None
+                    },
+                ],
+                vec![
+                    Run {
+                        text: "c".into(),
+                        fg: Some("#ff0000".into()),
+                        bg: None
+                    },
+                    Run {
+                        text: "d".into(),
+                        ..Default::default()
+                    },
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_to_html_and_svg() {
+        let text = "<\x1b[48;2;0;0;255m数\x1b[0m>";
+        assert_eq!(
+            to_html(text),
+            "<pre style=\"font-family: monospace; line-height: 1.2; margin: 0\">&lt;<span style=\"background:#0000ff\">数</span>&gt;</pre>"
+        );
+        let svg = to_svg(text);
+        assert!(svg.contains("width=\"36\""));
+        assert!(svg.contains("<rect x=\"9\" y=\"0\" width=\"18\" height=\"18\" fill=\"#0000ff\"/>"));
+        assert!(svg.contains(">&lt;数&gt;</tspan>"));
+    }
+}
@@ -0,0 +1,244 @@
+//! Repairs for diagram source copied out of chat model replies
+//!
+//! Models wrap diagrams in Markdown fences, add a sentence before or after,
+//! and type typographic quotes and `→` arrows that no parser accepts.
+//! Removed lines are left blank so parse errors and source maps keep
+//! pointing at the caller's line numbers.
+
+use crate::error::MermaidError;
+use crate::text::truncate_with_ellipsis;
+use crate::types::{DiagramWarning, RenderOptions, RenderResult};
+use crate::{detect_format, DiagramFormat};
+
+/// Arrows models write in place of ASCII ones
+const ARROWS: &[char] = &['→', '⟶', '➔', '➜', '⇒', '⟹'];
+
+/// Columns of a removed line quoted in its warning
+const QUOTE_WIDTH: usize = 30;
+
+/// `input` with common model mistakes repaired, and an
+/// [`DiagramWarning::InputRepaired`] for each fix. `lang` is the language
+/// passed to [`render`](crate::render), which picks the arrow to write.
+pub(crate) fn sanitize(input: &str, lang: &str) -> (String, Vec<DiagramWarning>) {
+    let mut lines: Vec<String> = input.lines().map(String::from).collect();
+    let mut warnings = Vec::new();
+    let mut remove = |lines: &mut Vec<String>, i: usize, fix: String| {
+        lines[i].clear();
+        warnings.push(DiagramWarning::InputRepaired { line: i + 1, fix });
+    };
+
+    // Keep what's inside the first fenced block
+    let fences: Vec<usize> = (0..lines.len())
+        .filter(|&i| {
+            let line = lines[i].trim_start();
+            line.starts_with("```") || line.starts_with("~~~")
+        })
+        .collect();
+    let keep = match fences.as_slice() {
+        [] => 0..lines.len(),
+        // A lone fence may open or close the block, so only it goes
+        [fence] => {
+            remove(&mut lines, *fence, "removed Markdown code fence".into());
+            0..lines.len()
+        }
+        [open, close, ..] => *open + 1..*close,
+    };
+    for i in 0..lines.len() {
+        if fences.len() > 1 && fences.contains(&i) {
+            remove(&mut lines, i, "removed Markdown code fence".into());
+        } else if !keep.contains(&i) && !lines[i].trim().is_empty() {
+            let fix = commentary_fix(&lines[i]);
+            remove(&mut lines, i, fix);
+        }
+    }
+
+    // Sentences before and after the diagram
+    let content: Vec<usize> = (0..lines.len())
+        .filter(|&i| !lines[i].trim().is_empty())
+        .collect();
+    if !content.iter().all(|&i| is_prose(&lines[i])) {
+        let leading = content.iter().take_while(|&&i| is_prose(&lines[i]));
+        let trailing = content.iter().rev().take_while(|&&i| is_prose(&lines[i]));
+        let prose: Vec<usize> = leading.chain(trailing).copied().collect();
+        for i in prose {
+            let fix = commentary_fix(&lines[i]);
+            remove(&mut lines, i, fix);
+        }
+    }
+
+    let format = if lang.eq_ignore_ascii_case("d2") {
+        DiagramFormat::D2
+    } else {
+        detect_format(&lines.join("\n"))
+    };
+    let arrow = match format {
+        DiagramFormat::D2 => "->",
+        DiagramFormat::SequenceDiagram => "->>",
+        _ => "-->",
+    };
+    for (i, line) in lines.iter_mut().enumerate() {
+        if line.contains(['“', '”', '„', '‟', '‘', '’', '‚', '‛']) {
+            *line = line
+                .replace(['“', '”', '„', '‟'], "\"")
+                .replace(['‘', '’', '‚', '‛'], "'");
+            warnings.push(DiagramWarning::InputRepaired {
+                line: i + 1,
+                fix: "replaced smart quotes".into(),
+            });
+        }
+        if let Some((replaced, found)) = replace_arrows(line, arrow, format) {
+            *line = replaced;
+            warnings.push(DiagramWarning::InputRepaired {
+                line: i + 1,
+                fix: format!("replaced '{}' with '{}'", found, arrow),
+            });
+        }
+    }
+
+    warnings.sort_by_key(|w| match w {
+        DiagramWarning::InputRepaired { line, .. } => *line,
+        _ => 0,
+    });
+    let mut output = lines.join("\n");
+    if input.ends_with('\n') {
+        output.push('\n');
+    }
+    (output, warnings)
+}
+
+/// `render` run on `input` repaired, with the repairs listed first among
+/// the warnings. Entry points call this when [`RenderOptions::sanitize`]
+/// is set; the options passed on have it cleared, so entry points that call
+/// each other repair the source once.
+pub(crate) fn render_sanitized(
+    input: &str,
+    lang: &str,
+    options: RenderOptions,
+    render: impl FnOnce(&str, RenderOptions) -> Result<RenderResult, MermaidError>,
+) -> Result<RenderResult, MermaidError> {
+    let (input, repairs) = sanitize(input, lang);
+    let options = RenderOptions {
+        sanitize: false,
+        ..options
+    };
+    let mut result = render(&input, options)?;
+    result.warnings.splice(0..0, repairs);
+    Ok(result)
+}
+
+/// Fix message for a removed line of commentary
+fn commentary_fix(line: &str) -> String {
+    format!(
+        "removed commentary '{}'",
+        truncate_with_ellipsis(line.trim(), QUOTE_WIDTH)
+    )
+}
+
+/// A sentence rather than a statement: several words ending in `.`, `!`,
+/// `?` or `:`, and none of the symbols diagram syntax needs
+fn is_prose(line: &str) -> bool {
+    let Some(body) = line.trim().strip_suffix(['.', '!', '?', ':']) else {
+        return false;
+    };
+    body.split_whitespace().count() >= 3
+        && !body.contains("--")
+        && !body.contains(|c: char| "[]{}|<>:;=%#".contains(c) || ARROWS.contains(&c))
+}
+
+/// `line` with the arrows outside labels replaced by `arrow`, and the first
+/// arrow found; `None` when there are none.
+///
+/// Brackets, quotes and `|label|` text are left alone, as is everything
+/// after a `:` except in flowcharts, where `:` starts no label.
+fn replace_arrows(line: &str, arrow: &str, format: DiagramFormat) -> Option<(String, char)> {
+    if !line.contains(ARROWS) {
+        return None;
+    }
+    let mut out = String::with_capacity(line.len() + 8);
+    let mut found = None;
+    let mut depth = 0usize;
+    let mut quoted = false;
+    let mut piped = false;
+    let mut label = false;
+    // Sequence messages take no spaces around the arrow
+    let tight = format == DiagramFormat::SequenceDiagram;
+    let mut after_arrow = false;
+    for c in line.chars() {
+        if std::mem::take(&mut after_arrow) && tight && c == ' ' {
+            after_arrow = true;
+            continue;
+        }
+        match c {
+            '"' => quoted = !quoted,
+            '|' if !quoted => piped = !piped,
+            '[' | '(' | '{' if !quoted => depth += 1,
+            ']' | ')' | '}' if !quoted => depth = depth.saturating_sub(1),
+            ':' if format != DiagramFormat::Mermaid && depth == 0 && !quoted => label = true,
+            c if ARROWS.contains(&c) && depth == 0 && !quoted && !piped && !label => {
+                found.get_or_insert(c);
+                if tight {
+                    out.truncate(out.trim_end().len());
+                }
+                out.push_str(arrow);
+                after_arrow = true;
+                continue;
+            }
+            _ => {}
+        }
+        out.push(c);
+    }
+    found.map(|c| (out, c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixes(warnings: &[DiagramWarning]) -> Vec<String> {
+        warnings.iter().map(|w| w.to_string()).collect()
+    }
+
+    #[test]
+    fn test_fenced_reply() {
+        let reply = "Here is the diagram you asked for:\n\n```mermaid\nflowchart LR\nA[“Start”] → B\n```\n\nLet me know if you want changes!\n";
+        let (source, warnings) = sanitize(reply, "mermaid");
+        assert_eq!(source, "\n\n\nflowchart LR\nA[\"Start\"] --> B\n\n\n\n");
+        assert_eq!(
+            fixes(&warnings),
+            vec![
+                "Line 1 repaired: removed commentary 'Here is the diagram you asked…'",
+                "Line 3 repaired: removed Markdown code fence",
+                "Line 5 repaired: replaced smart quotes",
+                "Line 5 repaired: replaced '→' with '-->'",
+                "Line 6 repaired: removed Markdown code fence",
+                "Line 8 repaired: removed commentary 'Let me know if you want chang…'",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unfenced_commentary() {
+        let reply = "a → b: x → y\nb -> c\nThis shows how a reaches c.";
+        let (source, warnings) = sanitize(reply, "d2");
+        assert_eq!(source, "a -> b: x → y\nb -> c\n");
+        assert_eq!(warnings.len(), 2);
+
+        let (source, _) = sanitize("sequenceDiagram\nA → B: go → now", "mermaid");
+        assert_eq!(source, "sequenceDiagram\nA->>B: go → now");
+        let (source, _) = sanitize("flowchart LR\nA -->|x → y| B[p → q] → C", "mermaid");
+        assert_eq!(source, "flowchart LR\nA -->|x → y| B[p → q] --> C");
+    }
+
+    #[test]
+    fn test_clean_input_untouched() {
+        for (lang, input) in [
+            ("mermaid", "flowchart LR\nA --> B\n"),
+            ("d2", "my long node name here\nx: A label, with words."),
+            ("mermaid", "Just a sentence with no diagram."),
+        ] {
+            let (source, warnings) = sanitize(input, lang);
+            assert_eq!(source, input);
+            assert!(warnings.is_empty(), "{:?}", warnings);
+        }
+    }
+}
@@ -2,7 +2,8 @@
 //!
 //! Supports basic mermaid sequence diagram syntax
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
 
 use winnow::ascii::{space0, space1};
 use winnow::combinator::{alt, opt, preceded};
@@ -11,8 +12,14 @@ use winnow::ModalResult;
 use winnow::Parser;
 
 use crate::error::MermaidError;
-use crate::text::display_width;
-use crate::types::RenderOptions;
+use crate::grid::Grid;
+use crate::label::{strip_html, strip_html_line};
+use crate::renderer::{charset, CharSet};
+use crate::symbols::{find_word, source_lines, Symbol, SymbolKind};
+use crate::text::{
+    display_width, pad_to_width, strip_prefix_ignore_case, truncate_to_width, wrap_words,
+};
+use crate::types::{DiagramWarning, RenderOptions};
 
 /// A participant in the sequence diagram
 #[derive(Debug, Clone)]
@@ -71,6 +78,9 @@ pub enum FragmentKind {
     Alt,
     Opt,
     Par,
+    /// Background highlight `rect rgb(r, g, b)`; the fragment label holds
+    /// the color
+    Rect,
 }
 
 /// A section within a fragment (separated by else/and)
@@ -90,11 +100,12 @@ pub struct Fragment {
 
 /// Items in a sequence diagram (tree structure for nested fragments)
 #[derive(Debug, Clone)]
-#[allow(dead_code)]
 pub enum SequenceItem {
     Message(Message),
     Note(Note),
     Fragment(Fragment),
+    /// `...` or `...: label`: time passes between messages
+    Delay(Option<String>),
 }
 
 /// Sequence diagram data
@@ -105,12 +116,166 @@ pub struct SequenceDiagram {
     pub messages: Vec<Message>,
     /// Whether to auto-number messages
     pub autonumber: bool,
+    /// Wrap long message and note text (`%%{wrap}%%`)
+    pub wrap: bool,
     /// Notes attached after specific message indices (message_index, note)
     pub notes: Vec<(usize, Note)>,
     /// Active participant spans (participant_id, start_msg_idx, end_msg_idx)
     pub activations: Vec<(String, usize, usize)>,
+    /// Delays drawn before specific message indices (message_index, label);
+    /// an index past the last message draws after it
+    pub delays: Vec<(usize, Option<String>)>,
     /// Tree-structured items (includes fragments)
     pub items: Vec<SequenceItem>,
+    /// Statements that were recognised but skipped
+    pub warnings: Vec<DiagramWarning>,
+}
+
+impl SequenceDiagram {
+    /// Create an empty diagram, to be filled in with the `add_*` methods
+    pub fn new() -> Self {
+        Self {
+            title: None,
+            participants: Vec::new(),
+            messages: Vec::new(),
+            autonumber: false,
+            wrap: false,
+            notes: Vec::new(),
+            activations: Vec::new(),
+            delays: Vec::new(),
+            items: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Declare a participant; a second declaration of the same id is ignored
+    pub fn add_participant(&mut self, id: &str, label: &str) -> &mut Self {
+        if !self.participants.iter().any(|p| p.id == id) {
+            self.participants.push(Participant {
+                id: id.to_string(),
+                label: label.to_string(),
+            });
+        }
+        self
+    }
+
+    /// Append a message, declaring either end that isn't a participant yet
+    pub fn add_message(
+        &mut self,
+        from: &str,
+        to: &str,
+        style: ArrowStyle,
+        label: &str,
+    ) -> &mut Self {
+        self.add_participant(from, from).add_participant(to, to);
+        let msg = Message {
+            from: from.to_string(),
+            to: to.to_string(),
+            label: label.to_string(),
+            style,
+            activate_to: false,
+            deactivate_to: false,
+        };
+        self.items.push(SequenceItem::Message(msg.clone()));
+        self.messages.push(msg);
+        self
+    }
+
+    /// Append a note after the last message
+    pub fn add_note(&mut self, position: NotePosition, text: &str) -> &mut Self {
+        let note = Note {
+            position,
+            text: text.to_string(),
+        };
+        self.notes
+            .push((self.messages.len().saturating_sub(1), note.clone()));
+        self.items.push(SequenceItem::Note(note));
+        self
+    }
+}
+
+impl SequenceDiagram {
+    /// The diagram as it stands just before message `count`: the first
+    /// `count` messages and everything written between them, with
+    /// activations still open at that point left running
+    pub(crate) fn prefix(&self, count: usize) -> SequenceDiagram {
+        let mut budget = count;
+        let (items, _) = items_before(&self.items, &mut budget);
+        let shown = count.min(self.messages.len());
+        SequenceDiagram {
+            title: self.title.clone(),
+            participants: self.participants.clone(),
+            messages: self.messages[..shown].to_vec(),
+            autonumber: self.autonumber,
+            wrap: self.wrap,
+            notes: self
+                .notes
+                .iter()
+                .filter(|(at, _)| *at < shown.max(1))
+                .cloned()
+                .collect(),
+            activations: self
+                .activations
+                .iter()
+                .filter(|(_, start, _)| *start < shown)
+                .map(|(id, start, end)| (id.clone(), *start, (*end).min(shown)))
+                .collect(),
+            delays: self
+                .delays
+                .iter()
+                .filter(|(at, _)| *at <= shown)
+                .cloned()
+                .collect(),
+            items,
+            warnings: self.warnings.clone(),
+        }
+    }
+}
+
+/// Items up to the message after the first `budget` of them, and whether
+/// that cut fell inside `items`
+fn items_before(items: &[SequenceItem], budget: &mut usize) -> (Vec<SequenceItem>, bool) {
+    let mut out = Vec::new();
+    for item in items {
+        match item {
+            SequenceItem::Message(_) if *budget == 0 => return (out, true),
+            SequenceItem::Message(_) => {
+                *budget -= 1;
+                out.push(item.clone());
+            }
+            SequenceItem::Fragment(fragment) => {
+                let mut sections = Vec::new();
+                for section in &fragment.sections {
+                    let (items, cut) = items_before(&section.items, budget);
+                    sections.push(FragmentSection {
+                        label: section.label.clone(),
+                        items,
+                    });
+                    if cut {
+                        out.push(SequenceItem::Fragment(Fragment {
+                            kind: fragment.kind.clone(),
+                            label: fragment.label.clone(),
+                            sections,
+                        }));
+                        return (out, true);
+                    }
+                }
+                out.push(SequenceItem::Fragment(Fragment {
+                    kind: fragment.kind.clone(),
+                    label: fragment.label.clone(),
+                    sections,
+                }));
+            }
+            SequenceItem::Note(_) | SequenceItem::Delay(_) => out.push(item.clone()),
+        }
+    }
+    (out, false)
+}
+
+impl Default for SequenceDiagram {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 /// Content of a single line
@@ -119,6 +284,7 @@ enum SeqLine {
     Header,
     Title(String),
     AutoNumber,
+    Wrap,
     Participant {
         id: String,
         label: String,
@@ -127,12 +293,17 @@ enum SeqLine {
     Note(Note),
     Activate(String),
     Deactivate(String),
+    Delay(Option<String>),
     /// Start of a fragment block: loop, alt, opt, par
     FragmentStart(FragmentKind, String),
     /// Section divider within a fragment: else, and
     FragmentDivider(Option<String>),
     /// End of a fragment block
     FragmentEnd,
+    /// `link`, `links` or `properties`: actor menus a terminal can't show
+    Ignored(&'static str),
+    /// A line that isn't any known statement
+    Unknown,
     Empty,
 }
 
@@ -252,27 +423,18 @@ fn parse_message_line(input: &mut &str) -> ModalResult<Message> {
 
 /// Parse note line: Note right of A: text, Note left of A: text, Note over A,B: text
 fn parse_note_line(line: &str) -> Option<Note> {
-    let lower = line.to_lowercase();
-    if !lower.starts_with("note ") {
-        return None;
-    }
-    let rest = line[5..].trim();
+    let rest = strip_prefix_ignore_case(line, "note ")?.trim();
 
     // Find the colon separator for text
     let colon_idx = rest.find(':')?;
     let position_part = rest[..colon_idx].trim();
     let text = rest[colon_idx + 1..].trim().to_string();
 
-    let lower_pos = position_part.to_lowercase();
-
-    let position = if lower_pos.starts_with("right of ") {
-        let id = position_part[9..].trim().to_string();
-        NotePosition::RightOf(id)
-    } else if lower_pos.starts_with("left of ") {
-        let id = position_part[8..].trim().to_string();
-        NotePosition::LeftOf(id)
-    } else if lower_pos.starts_with("over ") {
-        let ids_str = position_part[5..].trim();
+    let position = if let Some(id) = strip_prefix_ignore_case(position_part, "right of ") {
+        NotePosition::RightOf(id.trim().to_string())
+    } else if let Some(id) = strip_prefix_ignore_case(position_part, "left of ") {
+        NotePosition::LeftOf(id.trim().to_string())
+    } else if let Some(ids_str) = strip_prefix_ignore_case(position_part, "over ") {
         let ids: Vec<String> = ids_str.split(',').map(|s| s.trim().to_string()).collect();
         NotePosition::Over(ids)
     } else {
@@ -284,25 +446,61 @@ fn parse_note_line(line: &str) -> Option<Note> {
 
 /// Parse activate/deactivate line
 fn parse_activate_line(line: &str) -> Option<(bool, String)> {
-    let lower = line.to_lowercase();
-    if lower.starts_with("activate ") {
-        Some((true, line[9..].trim().to_string()))
-    } else if lower.starts_with("deactivate ") {
-        Some((false, line[11..].trim().to_string()))
+    if let Some(id) = strip_prefix_ignore_case(line, "activate ") {
+        Some((true, id.trim().to_string()))
     } else {
-        None
+        strip_prefix_ignore_case(line, "deactivate ").map(|id| (false, id.trim().to_string()))
     }
 }
 
+/// Parse a delay line: `...`, `...: label` or `... label ...`
+fn parse_delay_line(line: &str) -> Option<Option<String>> {
+    let rest = line.strip_prefix("...")?;
+    let label = rest
+        .trim()
+        .trim_start_matches(':')
+        .trim_end_matches('.')
+        .trim();
+    Some((!label.is_empty()).then(|| label.to_string()))
+}
+
+/// Whether a line is the `%%{wrap}%%` directive
+fn is_wrap_directive(line: &str) -> bool {
+    line.strip_prefix("%%{")
+        .and_then(|rest| rest.strip_suffix("}%%"))
+        .is_some_and(|inner| inner.trim().eq_ignore_ascii_case("wrap"))
+}
+
+/// Match a block keyword (`loop`, `alt`, ...) alone or followed by a label
+fn parse_block_keyword(line: &str, keyword: &str) -> Option<Option<String>> {
+    if line.eq_ignore_ascii_case(keyword) {
+        return Some(None);
+    }
+    let rest = strip_prefix_ignore_case(line, keyword)?;
+    if !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let label = rest.trim();
+    Some((!label.is_empty()).then(|| label.to_string()))
+}
+
 /// Parse a single line and classify it
 fn parse_line(line: &str) -> SeqLine {
     let trimmed = line.trim();
 
-    // Empty or comment
+    // Directive, then empty or comment
+    if is_wrap_directive(trimmed) {
+        return SeqLine::Wrap;
+    }
     if trimmed.is_empty() || trimmed.starts_with("%%") {
         return SeqLine::Empty;
     }
 
+    // Delay
+    if let Some(label) = parse_delay_line(trimmed) {
+        return SeqLine::Delay(label);
+    }
+
     // Header
     if parse_header.parse(trimmed).is_ok() {
         return SeqLine::Header;
@@ -329,63 +527,38 @@ fn parse_line(line: &str) -> SeqLine {
     }
 
     // Fragment end
-    let lower = trimmed.to_lowercase();
-    if lower == "end" {
+    if trimmed.eq_ignore_ascii_case("end") {
         return SeqLine::FragmentEnd;
     }
 
-    // Fragment start: loop, alt, opt, par
-    if lower.starts_with("loop ") || lower == "loop" {
-        let label = if trimmed.len() > 5 {
-            trimmed[5..].trim().to_string()
-        } else {
-            String::new()
-        };
-        return SeqLine::FragmentStart(FragmentKind::Loop, label);
-    }
-    if lower.starts_with("alt ") || lower == "alt" {
-        let label = if trimmed.len() > 4 {
-            trimmed[4..].trim().to_string()
-        } else {
-            String::new()
-        };
-        return SeqLine::FragmentStart(FragmentKind::Alt, label);
-    }
-    if lower.starts_with("opt ") || lower == "opt" {
-        let label = if trimmed.len() > 4 {
-            trimmed[4..].trim().to_string()
-        } else {
-            String::new()
-        };
-        return SeqLine::FragmentStart(FragmentKind::Opt, label);
-    }
-    if lower.starts_with("par ") || lower == "par" {
-        let label = if trimmed.len() > 4 {
-            trimmed[4..].trim().to_string()
-        } else {
-            String::new()
-        };
-        return SeqLine::FragmentStart(FragmentKind::Par, label);
+    // Fragment start: loop, alt, opt, par, rect
+    for (keyword, kind) in [
+        ("loop", FragmentKind::Loop),
+        ("alt", FragmentKind::Alt),
+        ("opt", FragmentKind::Opt),
+        ("par", FragmentKind::Par),
+        ("rect", FragmentKind::Rect),
+    ] {
+        if let Some(label) = parse_block_keyword(trimmed, keyword) {
+            return SeqLine::FragmentStart(kind, label.unwrap_or_default());
+        }
     }
 
     // Fragment dividers: else, and
-    if lower.starts_with("else ") || lower == "else" {
-        let label = if trimmed.len() > 5 {
-            Some(trimmed[5..].trim().to_string())
-        } else {
-            None
-        };
+    if let Some(label) = parse_block_keyword(trimmed, "else") {
         return SeqLine::FragmentDivider(label);
     }
-    if lower.starts_with("and ") || lower == "and" {
-        let label = if trimmed.len() > 4 {
-            Some(trimmed[4..].trim().to_string())
-        } else {
-            None
-        };
+    if let Some(label) = parse_block_keyword(trimmed, "and") {
         return SeqLine::FragmentDivider(label);
     }
 
+    // Actor menu statements, before they can be misread as anything else
+    for keyword in ["links", "link", "properties"] {
+        if parse_block_keyword(trimmed, keyword).is_some() {
+            return SeqLine::Ignored(keyword);
+        }
+    }
+
     // Note
     if let Some(note) = parse_note_line(trimmed) {
         return SeqLine::Note(note);
@@ -405,7 +578,7 @@ fn parse_line(line: &str) -> SeqLine {
         return SeqLine::Message(msg);
     }
 
-    SeqLine::Empty
+    SeqLine::Unknown
 }
 
 /// Parse sequence diagram syntax
@@ -416,15 +589,7 @@ pub fn parse_sequence_diagram(input: &str) -> Result<SequenceDiagram, MermaidErr
         return Err(MermaidError::EmptyInput);
     }
 
-    let mut diagram = SequenceDiagram {
-        title: None,
-        participants: Vec::new(),
-        messages: Vec::new(),
-        autonumber: false,
-        notes: Vec::new(),
-        activations: Vec::new(),
-        items: Vec::new(),
-    };
+    let mut diagram = SequenceDiagram::new();
 
     let mut seen_participants: HashSet<String> = HashSet::new();
     let mut found_header = false;
@@ -457,24 +622,38 @@ pub fn parse_sequence_diagram(input: &str) -> Result<SequenceDiagram, MermaidErr
         }
     }
 
-    for line in lines.iter() {
+    for (i, line) in lines.iter().enumerate() {
         match parse_line(line) {
             SeqLine::Header => {
                 found_header = true;
             }
             SeqLine::Title(t) => {
-                diagram.title = Some(t);
+                diagram.title = Some(strip_html_line(&t));
             }
             SeqLine::AutoNumber => {
                 diagram.autonumber = true;
             }
+            SeqLine::Wrap => {
+                diagram.wrap = true;
+            }
+            SeqLine::Delay(label) => {
+                let label = label.as_deref().map(strip_html_line);
+                diagram.delays.push((diagram.messages.len(), label.clone()));
+                push_item(
+                    &mut diagram.items,
+                    &mut fragment_stack,
+                    SequenceItem::Delay(label),
+                );
+            }
             SeqLine::Participant { id, label } => {
                 if !seen_participants.contains(&id) {
                     seen_participants.insert(id.clone());
+                    let label = strip_html_line(&label);
                     diagram.participants.push(Participant { id, label });
                 }
             }
-            SeqLine::Note(note) => {
+            SeqLine::Note(mut note) => {
+                note.text = strip_html(&note.text);
                 // Attach to current message count (after the last message)
                 let idx = diagram.messages.len().saturating_sub(1);
                 diagram.notes.push((idx, note.clone()));
@@ -502,7 +681,7 @@ pub fn parse_sequence_diagram(input: &str) -> Result<SequenceDiagram, MermaidErr
             SeqLine::FragmentStart(kind, label) => {
                 fragment_stack.push(FragmentBuilder {
                     kind,
-                    label,
+                    label: strip_html_line(&label),
                     sections: Vec::new(),
                     current_label: None,
                     current_items: Vec::new(),
@@ -517,7 +696,7 @@ pub fn parse_sequence_diagram(input: &str) -> Result<SequenceDiagram, MermaidErr
                         label: prev_label,
                         items: prev_items,
                     });
-                    builder.current_label = label;
+                    builder.current_label = label.as_deref().map(strip_html_line);
                 }
             }
             SeqLine::FragmentEnd => {
@@ -539,7 +718,8 @@ pub fn parse_sequence_diagram(input: &str) -> Result<SequenceDiagram, MermaidErr
                     );
                 }
             }
-            SeqLine::Message(msg) => {
+            SeqLine::Message(mut msg) => {
+                msg.label = strip_html(&msg.label);
                 // Auto-add participants if not declared
                 if !seen_participants.contains(&msg.from) {
                     seen_participants.insert(msg.from.clone());
@@ -578,6 +758,18 @@ pub fn parse_sequence_diagram(input: &str) -> Result<SequenceDiagram, MermaidErr
                     }
                 }
             }
+            SeqLine::Ignored(keyword) => {
+                diagram.warnings.push(DiagramWarning::IgnoredStatement {
+                    statement: keyword.to_string(),
+                    line: i + 1,
+                });
+            }
+            SeqLine::Unknown => {
+                diagram.warnings.push(DiagramWarning::LineSkipped {
+                    line: i + 1,
+                    message: format!("Invalid syntax: \"{}\"", line.trim()),
+                });
+            }
             SeqLine::Empty => {}
         }
     }
@@ -627,652 +819,621 @@ pub fn parse_sequence_diagram(input: &str) -> Result<SequenceDiagram, MermaidErr
     Ok(diagram)
 }
 
-/// Render sequence diagram to ASCII representation
-#[allow(clippy::needless_range_loop)]
-pub fn render_sequence_diagram(diagram: &SequenceDiagram, options: &RenderOptions) -> String {
-    let mut output = String::new();
+/// Participants and messages with their source locations
+pub(crate) fn symbols(input: &str) -> Vec<Symbol> {
+    let mut symbols = Vec::new();
+    for (line, offset, text) in source_lines(input) {
+        let symbol = |name: &str, kind, span: Range<usize>| Symbol {
+            name: name.to_string(),
+            kind,
+            line,
+            span: offset + span.start..offset + span.end,
+        };
+        // Ids are only searched for before any `: text`, past the keyword
+        let head = text.find(':').map_or(text, |i| &text[..i]);
+        let indent = text.len() - text.trim_start().len();
+        let after_keyword = text[indent..]
+            .find(char::is_whitespace)
+            .map_or(indent, |i| indent + i);
+
+        let mut mentions: Vec<(String, usize)> = Vec::new();
+        let mut find_all = |ids: &[String], mut from: usize| {
+            for id in ids {
+                if let Some(at) = find_word(head, id, from) {
+                    mentions.push((id.clone(), at));
+                    from = at + id.len();
+                }
+            }
+        };
+        let edge = match parse_line(text) {
+            SeqLine::Participant { id, .. } => {
+                find_all(&[id], after_keyword);
+                None
+            }
+            SeqLine::Message(msg) => {
+                find_all(&[msg.from.clone(), msg.to.clone()], 0);
+                Some(format!("{} -> {}", msg.from, msg.to))
+            }
+            SeqLine::Note(note) => {
+                let ids = match note.position {
+                    NotePosition::RightOf(id) | NotePosition::LeftOf(id) => vec![id],
+                    NotePosition::Over(ids) => ids,
+                };
+                // Skip `note right of` / `note over`
+                let lower = head.to_ascii_lowercase();
+                let from = [" of ", " over "]
+                    .iter()
+                    .find_map(|k| lower.find(k).map(|i| i + k.len()))
+                    .unwrap_or(after_keyword);
+                find_all(&ids, from);
+                None
+            }
+            SeqLine::Activate(id) | SeqLine::Deactivate(id) => {
+                find_all(&[id], after_keyword);
+                None
+            }
+            _ => None,
+        };
 
-    if diagram.participants.is_empty() {
-        return "No participants".to_string();
+        let ends = mentions
+            .first()
+            .zip(mentions.last())
+            .map(|(first, last)| (first.1, last.1 + last.0.len()));
+        for (id, at) in &mentions {
+            symbols.push(symbol(id, SymbolKind::Participant, *at..*at + id.len()));
+        }
+        if let (Some(name), Some((start, end)), 2) = (edge, ends, mentions.len()) {
+            symbols.push(symbol(&name, SymbolKind::Edge, start..end));
+        }
     }
+    symbols
+}
 
-    // Character set
-    let (box_h, box_v, box_tl, box_tr, box_bl, box_br) = if options.ascii {
-        ('-', '|', '+', '+', '+', '+')
-    } else {
-        ('─', '│', '┌', '┐', '└', '┘')
+/// ANSI background escape for a `rect` color: `rgb(r, g, b)` or
+/// `rgba(r, g, b, a)`, whose alpha is ignored
+fn rect_background(color: &str) -> Option<String> {
+    let color = color.trim();
+    let args = strip_prefix_ignore_case(color, "rgba(")
+        .or_else(|| strip_prefix_ignore_case(color, "rgb("))?
+        .strip_suffix(')')?;
+    let channels: Vec<u8> = args
+        .split(',')
+        .take(3)
+        .map(|c| c.trim().parse().ok())
+        .collect::<Option<_>>()?;
+    let [r, g, b] = channels[..] else {
+        return None;
     };
+    Some(format!("\x1b[48;2;{};{};{}m", r, g, b))
+}
 
-    let arrow_r = if options.ascii { '>' } else { '▶' };
-    let arrow_l = if options.ascii { '<' } else { '◀' };
-    let active_v = if options.ascii { '#' } else { '┃' };
+/// Width past which message and note text wraps under `%%{wrap}%%`
+const WRAP_WIDTH: usize = 30;
 
-    // Calculate participant column widths
-    let min_col_width = 12;
-    let col_widths: Vec<usize> = diagram
-        .participants
-        .iter()
-        .map(|p| (display_width(&p.label) + 4).max(min_col_width))
-        .collect();
+/// Columns between a self-message's lifeline and the far side of its loop
+const SELF_LOOP_WIDTH: usize = 4;
 
-    // Calculate participant x positions (center of each column)
-    let mut positions: Vec<usize> = Vec::new();
-    let mut x = 0;
-    for width in &col_widths {
-        positions.push(x + width / 2);
-        x += width;
-    }
-    let total_width = x;
+/// A `loop`/`alt`/`opt`/`par`/`rect` block, by the messages it spans
+struct FragmentSpan {
+    kind: FragmentKind,
+    label: String,
+    start_msg: usize,
+    end_msg: usize,
+    /// Message index and label of each `else`/`and` divider
+    dividers: Vec<(usize, Option<String>)>,
+}
 
-    // Title
-    if let Some(ref title) = diagram.title {
-        let title_w = display_width(title);
-        let padding = (total_width.saturating_sub(title_w)) / 2;
-        output.push_str(&" ".repeat(padding));
-        output.push_str(title);
-        output.push('\n');
-        output.push_str(&" ".repeat(padding));
-        output.push_str(&"─".repeat(title_w));
-        output.push_str("\n\n");
-    }
-
-    // Draw participant boxes at top
-    // Box top line
-    let mut line = vec![' '; total_width];
-    for (i, p) in diagram.participants.iter().enumerate() {
-        let center = positions[i];
-        let box_width = display_width(&p.label) + 2;
-        let start = center.saturating_sub(box_width / 2);
-        let end = start + box_width;
-
-        if start < total_width {
-            line[start] = box_tl;
-        }
-        for j in (start + 1)..end.min(total_width).saturating_sub(1) {
-            line[j] = box_h;
-        }
-        if end > 0 && end - 1 < total_width {
-            line[end - 1] = box_tr;
+fn collect_fragment_spans(
+    items: &[SequenceItem],
+    msg_counter: &mut usize,
+    spans: &mut Vec<FragmentSpan>,
+) {
+    for item in items {
+        match item {
+            SequenceItem::Message(_) => {
+                *msg_counter += 1;
+            }
+            SequenceItem::Note(_) | SequenceItem::Delay(_) => {}
+            SequenceItem::Fragment(frag) => {
+                let start = *msg_counter;
+                let mut dividers = Vec::new();
+                for (si, section) in frag.sections.iter().enumerate() {
+                    if si > 0 {
+                        dividers.push((*msg_counter, section.label.clone()));
+                    }
+                    collect_fragment_spans(&section.items, msg_counter, spans);
+                }
+                spans.push(FragmentSpan {
+                    kind: frag.kind.clone(),
+                    label: frag.label.clone(),
+                    start_msg: start,
+                    end_msg: *msg_counter,
+                    dividers,
+                });
+            }
         }
     }
-    output.push_str(&line.iter().collect::<String>());
-    output.push('\n');
+}
 
-    // Box middle line (label)
-    let mut line = vec![' '; total_width];
-    for (i, p) in diagram.participants.iter().enumerate() {
-        let center = positions[i];
-        let box_width = display_width(&p.label) + 2;
-        let start = center.saturating_sub(box_width / 2);
-        let end = start + box_width;
+/// Draws a sequence diagram onto a [`Grid`] one row at a time, growing it
+/// as rows and labels are added
+struct SequenceCanvas<'a> {
+    diagram: &'a SequenceDiagram,
+    options: &'a RenderOptions,
+    chars: &'a CharSet,
+    grid: Grid,
+    /// Next row to draw
+    y: usize,
+    /// Lifeline column of each participant
+    positions: Vec<usize>,
+    /// Width of all participant columns together
+    total_width: usize,
+}
 
-        if start < total_width {
-            line[start] = box_v;
+impl SequenceCanvas<'_> {
+    /// Place each lifeline at the center of its participant's column, then
+    /// widen the gaps between lifelines until every message label fits
+    /// beside its arrow
+    fn layout_columns(&mut self) {
+        let min_col_width = 12;
+        let widths: Vec<usize> = self
+            .diagram
+            .participants
+            .iter()
+            .map(|p| (display_width(&p.label) + 4).max(min_col_width))
+            .collect();
+        // gaps[i] is the distance from lifeline i to lifeline i + 1
+        let mut gaps: Vec<usize> = widths
+            .windows(2)
+            .map(|w| w[0] - w[0] / 2 + w[1] / 2)
+            .collect();
+
+        let mut spans = Vec::new();
+        for (msg_idx, msg) in self.diagram.messages.iter().enumerate() {
+            let (Some(from), Some(to)) = (
+                self.participant_index(&msg.from),
+                self.participant_index(&msg.to),
+            ) else {
+                continue;
+            };
+            let label_width = self
+                .message_text(msg_idx, msg)
+                .iter()
+                .map(|t| display_width(t))
+                .max()
+                .unwrap_or(0);
+            if from != to {
+                spans.push((from.min(to), from.max(to), label_width + 4));
+            } else if let Some(gap) = gaps.get_mut(from) {
+                // Self-message labels sit between the loop and the next lifeline
+                *gap = (*gap).max(SELF_LOOP_WIDTH + label_width + 5);
+            }
         }
-        // Center label (advance by display width for CJK support)
-        let label_start = start + 1;
-        let mut dx = 0;
-        for c in p.label.chars() {
-            if label_start + dx < total_width {
-                line[label_start + dx] = c;
+        // Narrow spans first, so wider ones only grow by what the gaps they
+        // cross still lack, shared out evenly between those gaps
+        spans.sort_by_key(|(from, to, _)| to - from);
+        for (from, to, needed) in spans {
+            let current: usize = gaps[from..to].iter().sum();
+            let missing = needed.saturating_sub(current);
+            let count = to - from;
+            for (i, gap) in gaps[from..to].iter_mut().enumerate() {
+                *gap += missing / count + usize::from(i < missing % count);
             }
-            dx += unicode_width::UnicodeWidthChar::width(c).unwrap_or(1);
         }
-        if end > 0 && end - 1 < total_width {
-            line[end - 1] = box_v;
+
+        let mut x = widths.first().map_or(0, |w| w / 2);
+        self.positions = vec![x];
+        for gap in gaps {
+            x += gap;
+            self.positions.push(x);
         }
+        self.total_width = widths.last().map_or(0, |w| x + w - w / 2);
     }
-    output.push_str(&line.iter().collect::<String>());
-    output.push('\n');
 
-    // Box bottom line
-    let mut line = vec![' '; total_width];
-    for (i, p) in diagram.participants.iter().enumerate() {
-        let center = positions[i];
-        let box_width = display_width(&p.label) + 2;
-        let start = center.saturating_sub(box_width / 2);
-        let end = start + box_width;
+    /// Start a new row
+    fn next_row(&mut self) -> usize {
+        self.y += 1;
+        self.grid.grow(self.total_width, self.y);
+        self.y - 1
+    }
 
-        if start < total_width {
-            line[start] = box_bl;
-        }
-        for j in (start + 1)..end.min(total_width).saturating_sub(1) {
-            line[j] = box_h;
-        }
-        if end > 0 && end - 1 < total_width {
-            line[end - 1] = box_br;
-        }
+    fn put(&mut self, x: usize, y: usize, c: char) {
+        self.grid.grow(x + 1, y + 1);
+        self.grid.set(x, y, c);
     }
-    output.push_str(&line.iter().collect::<String>());
-    output.push('\n');
 
-    // Collect fragment spans for rendering
-    // Each span: (start_msg_idx, end_msg_idx, kind_label, section_labels)
-    struct FragmentSpan {
-        kind: FragmentKind,
-        label: String,
-        start_msg: usize,
-        end_msg: usize,
-        dividers: Vec<(usize, Option<String>)>, // (msg_idx, label) for else/and lines
+    fn text(&mut self, x: usize, y: usize, text: &str) {
+        self.grid.grow(x + display_width(text), y + 1);
+        self.grid.draw_text(x, y, text);
     }
 
-    fn collect_fragment_spans(
-        items: &[SequenceItem],
-        msg_counter: &mut usize,
-        spans: &mut Vec<FragmentSpan>,
-    ) {
-        for item in items {
-            match item {
-                SequenceItem::Message(_) => {
-                    *msg_counter += 1;
-                }
-                SequenceItem::Note(_) => {}
-                SequenceItem::Fragment(frag) => {
-                    let start = *msg_counter;
-                    let mut dividers = Vec::new();
-                    for (si, section) in frag.sections.iter().enumerate() {
-                        if si > 0 {
-                            dividers.push((*msg_counter, section.label.clone()));
-                        }
-                        collect_fragment_spans(&section.items, msg_counter, spans);
-                    }
-                    spans.push(FragmentSpan {
-                        kind: frag.kind.clone(),
-                        label: frag.label.clone(),
-                        start_msg: start,
-                        end_msg: *msg_counter,
-                        dividers,
-                    });
-                }
-            }
+    /// `c` across columns `from..to` of row `y`
+    fn hline(&mut self, from: usize, to: usize, y: usize, c: char) {
+        for x in from..to {
+            self.put(x, y, c);
         }
     }
 
-    let mut fragment_spans = Vec::new();
-    let mut msg_counter = 0;
-    collect_fragment_spans(&diagram.items, &mut msg_counter, &mut fragment_spans);
+    fn participant_index(&self, id: &str) -> Option<usize> {
+        self.diagram
+            .participants
+            .iter()
+            .position(|p| p.id == id || p.label == id)
+    }
 
-    // Helper: check if participant is active at a given message index
-    let is_active = |participant_id: &str, at_msg: usize| -> bool {
-        diagram
+    /// Lifeline glyph for participant `p_idx` at message `at_msg`, thick
+    /// while it is activated
+    fn lifeline_char(&self, p_idx: usize, at_msg: usize) -> char {
+        let id = &self.diagram.participants[p_idx].id;
+        let active = self
+            .diagram
             .activations
             .iter()
-            .any(|(id, start, end)| id == participant_id && at_msg >= *start && at_msg < *end)
-    };
+            .any(|(a, start, end)| a == id && at_msg >= *start && at_msg < *end);
+        match (active, self.options.ascii) {
+            (true, true) => '#',
+            (true, false) => '┃',
+            (false, _) => self.chars.v,
+        }
+    }
 
-    // Helper: get lifeline char for a participant at a given message index
-    let lifeline_char = |p_idx: usize, at_msg: usize| -> char {
-        let pid = &diagram.participants[p_idx].id;
-        if is_active(pid, at_msg) {
-            active_v
-        } else if options.ascii {
-            '|'
+    /// A new row holding just the lifelines at message `at_msg`
+    fn lifeline_row(&mut self, at_msg: usize) -> usize {
+        let y = self.next_row();
+        for p_idx in 0..self.positions.len() {
+            let c = self.lifeline_char(p_idx, at_msg);
+            self.put(self.positions[p_idx], y, c);
+        }
+        y
+    }
+
+    /// Text split into rows at line breaks, and wrapped under `%%{wrap}%%`
+    fn wrapped(&self, text: &str) -> Vec<String> {
+        text.split('\n')
+            .flat_map(|line| {
+                if self.diagram.wrap && display_width(line) > WRAP_WIDTH {
+                    wrap_words(line, WRAP_WIDTH)
+                } else {
+                    vec![line.to_string()]
+                }
+            })
+            .collect()
+    }
+
+    /// Rows of a message label with its optional autonumber prefix
+    fn message_text(&self, msg_idx: usize, msg: &Message) -> Vec<String> {
+        if !self.diagram.autonumber && msg.label.is_empty() {
+            return Vec::new();
+        }
+        let number = if self.diagram.autonumber {
+            format!("{}. ", msg_idx + 1)
         } else {
-            '│'
+            String::new()
+        };
+        self.wrapped(&format!("{}{}", number, msg.label))
+    }
+
+    /// Centered title, underlined, then a blank row
+    fn draw_title(&mut self, title: &str) {
+        let title_w = display_width(title);
+        let padding = self.total_width.saturating_sub(title_w) / 2;
+        let y = self.next_row();
+        self.text(padding, y, title);
+        let y = self.next_row();
+        self.hline(padding, padding + title_w, y, self.chars.h);
+        self.next_row();
+    }
+
+    /// A box around each participant's label, centered on its lifeline
+    fn draw_participants(&mut self) {
+        let top = self.next_row();
+        let middle = self.next_row();
+        let bottom = self.next_row();
+        let chars = self.chars;
+        for (i, p) in self.diagram.participants.iter().enumerate() {
+            let box_width = display_width(&p.label) + 2;
+            let start = self.positions[i].saturating_sub(box_width / 2);
+            let end = start + box_width;
+            self.put(start, top, chars.tl);
+            self.hline(start + 1, end - 1, top, chars.h);
+            self.put(end - 1, top, chars.tr);
+            self.put(start, middle, chars.v);
+            self.text(start + 1, middle, &p.label);
+            self.put(end - 1, middle, chars.v);
+            self.put(start, bottom, chars.bl);
+            self.hline(start + 1, end - 1, bottom, chars.h);
+            self.put(end - 1, bottom, chars.br);
         }
-    };
+    }
 
-    let (frag_h, frag_v, frag_tl, frag_tr, frag_bl, frag_br, frag_dashed) = if options.ascii {
-        ('-', '|', '+', '+', '+', '+', '-')
-    } else {
-        ('─', '│', '┌', '┐', '└', '┘', '╌')
-    };
+    /// Dotted lifelines for a delay, labelled on the middle row
+    fn draw_delay(&mut self, label: &Option<String>) {
+        let delay_v = if self.options.ascii { ':' } else { '┊' };
+        for row in 0..3 {
+            let y = self.next_row();
+            for p_idx in 0..self.positions.len() {
+                self.put(self.positions[p_idx], y, delay_v);
+            }
+            if let (1, Some(label)) = (row, label) {
+                self.text(self.total_width + 2, y, label);
+            }
+        }
+    }
 
-    // Helper to draw a fragment top border with label
-    let draw_fragment_top = |output: &mut String,
-                             total_width: usize,
-                             positions: &[usize],
-                             kind: &FragmentKind,
-                             label: &str,
-                             lifeline_fn: &dyn Fn(usize, usize) -> char,
-                             msg_idx: usize| {
-        let kind_str = match kind {
+    /// A fragment border row across the lifelines: `left`, `fill`, `right`
+    /// with a `[tag]` over its start
+    fn draw_fragment_rule(
+        &mut self,
+        at_msg: usize,
+        (left, fill, right): (char, char, char),
+        tag: Option<&str>,
+    ) {
+        let y = self.lifeline_row(at_msg);
+        let width = self.total_width;
+        if width <= 3 {
+            return;
+        }
+        self.put(1, y, left);
+        self.hline(2, width - 2, y, fill);
+        self.put(width - 2, y, right);
+        if let Some(tag) = tag {
+            self.text(2, y, truncate_to_width(tag, width - 4));
+        }
+    }
+
+    fn draw_fragment_top(&mut self, span: &FragmentSpan, at_msg: usize) {
+        let kind = match span.kind {
             FragmentKind::Loop => "loop",
             FragmentKind::Alt => "alt",
             FragmentKind::Opt => "opt",
             FragmentKind::Par => "par",
+            FragmentKind::Rect => "rect",
         };
-        let tag = if label.is_empty() {
-            format!("[{}]", kind_str)
+        let tag = if span.label.is_empty() {
+            format!("[{}]", kind)
         } else {
-            format!("[{} {}]", kind_str, label)
+            format!("[{} {}]", kind, span.label)
         };
-        let frag_width = total_width.saturating_sub(2);
+        let chars = self.chars;
+        self.draw_fragment_rule(at_msg, (chars.tl, chars.h, chars.tr), Some(&tag));
+    }
 
-        // Top border line
-        let mut line = vec![' '; total_width];
-        for (pi, &pos) in positions.iter().enumerate() {
-            if pos < total_width {
-                line[pos] = lifeline_fn(pi, msg_idx);
+    /// Dashed line between the sections of an `alt` or `par`
+    fn draw_fragment_divider(&mut self, label: &Option<String>, at_msg: usize) {
+        let dashed = if self.options.ascii { '-' } else { '╌' };
+        let tag = label.as_ref().map(|l| format!("[{}]", l));
+        let v = self.chars.v;
+        self.draw_fragment_rule(at_msg, (v, dashed, v), tag.as_deref());
+    }
+
+    fn draw_fragment_bottom(&mut self, at_msg: usize) {
+        let chars = self.chars;
+        self.draw_fragment_rule(at_msg, (chars.bl, chars.h, chars.br), None);
+    }
+
+    /// Loop out of and back into the lifeline at column `x`, labelled
+    /// beside it
+    fn draw_self_message(&mut self, msg_idx: usize, msg: &Message, x: usize) {
+        let chars = self.chars;
+        let right = x + SELF_LOOP_WIDTH + 1;
+
+        let y = self.lifeline_row(msg_idx);
+        self.put(x + 1, y, chars.rtl);
+        self.hline(x + 2, right, y, chars.h);
+        self.put(right, y, chars.rtr);
+
+        // Label rows continue between the loop's sides
+        let label_x = right + 2;
+        let texts = self.message_text(msg_idx, msg);
+        for i in 0..texts.len().max(1) {
+            let y = self.lifeline_row(msg_idx);
+            self.put(x + 1, y, chars.v);
+            self.put(right, y, chars.v);
+            if let Some(text) = texts.get(i) {
+                self.text(label_x, y, text);
             }
         }
-        // Draw top border over lifelines
-        if frag_width > 0 {
-            line[1] = frag_tl;
-            for i in 2..total_width.saturating_sub(1) {
-                line[i] = frag_h;
-            }
-            if total_width > 2 {
-                line[total_width - 2] = frag_tr;
+
+        let y = self.lifeline_row(msg_idx);
+        self.put(x + 1, y, chars.rbl);
+        self.put(x + 2, y, chars.arr_l);
+        self.hline(x + 3, right, y, chars.h);
+        self.put(right, y, chars.rbr);
+    }
+
+    /// Arrow between two lifelines, with its label centered above it
+    fn draw_message(&mut self, msg_idx: usize, msg: &Message, from_x: usize, to_x: usize) {
+        let (start_x, end_x) = (from_x.min(to_x), from_x.max(to_x));
+        let texts = self.message_text(msg_idx, msg);
+        for i in 0..texts.len().max(1) {
+            let y = self.lifeline_row(msg_idx);
+            if let Some(text) = texts.get(i) {
+                let inner = end_x - start_x - 1;
+                let x = start_x + 1 + inner.saturating_sub(display_width(text)) / 2;
+                self.text(x, y, text);
             }
         }
-        // Overlay the tag
-        for (i, c) in tag.chars().enumerate() {
-            if 2 + i < total_width - 2 {
-                line[2 + i] = c;
-            }
+        let y = self.lifeline_row(msg_idx);
+
+        let line = match (msg.style, self.options.ascii) {
+            (ArrowStyle::Dotted | ArrowStyle::DottedLine, false) => '·',
+            (ArrowStyle::Dotted | ArrowStyle::DottedLine, true) => '-',
+            _ => self.chars.h,
+        };
+        self.hline(start_x + 1, end_x, y, line);
+
+        let has_arrow = matches!(
+            msg.style,
+            ArrowStyle::Solid | ArrowStyle::Dotted | ArrowStyle::Async
+        );
+        if has_arrow && from_x < to_x {
+            self.put(end_x - 1, y, self.chars.arr_r);
+        } else if has_arrow {
+            self.put(start_x + 1, y, self.chars.arr_l);
         }
-        output.push_str(line.iter().collect::<String>().trim_end());
-        output.push('\n');
-    };
+    }
 
-    // Helper to draw a fragment section divider (dashed line for else/and)
-    let draw_fragment_divider = |output: &mut String,
-                                 total_width: usize,
-                                 positions: &[usize],
-                                 label: &Option<String>,
-                                 lifeline_fn: &dyn Fn(usize, usize) -> char,
-                                 msg_idx: usize| {
-        let mut line = vec![' '; total_width];
-        for (pi, &pos) in positions.iter().enumerate() {
-            if pos < total_width {
-                line[pos] = lifeline_fn(pi, msg_idx);
+    /// Boxed note beside, or over, its participants
+    fn draw_note(&mut self, note: &Note) {
+        let note_lines = self.wrapped(&note.text);
+        // "│ text │"
+        let note_width = note_lines
+            .iter()
+            .map(|l| display_width(l))
+            .max()
+            .unwrap_or(0)
+            + 4;
+
+        let note_x = match &note.position {
+            NotePosition::RightOf(id) => self
+                .participant_index(id)
+                .map_or(0, |pi| self.positions[pi] + 2),
+            NotePosition::LeftOf(id) => self
+                .participant_index(id)
+                .map_or(0, |pi| self.positions[pi].saturating_sub(note_width + 1)),
+            NotePosition::Over(ids) => {
+                let columns: Vec<usize> = ids
+                    .iter()
+                    .filter_map(|id| self.participant_index(id))
+                    .map(|pi| self.positions[pi])
+                    .collect();
+                match (columns.iter().min(), columns.iter().max()) {
+                    (Some(min_x), Some(max_x)) => {
+                        ((min_x + max_x) / 2).saturating_sub(note_width / 2)
+                    }
+                    _ => 0,
+                }
             }
-        }
-        // Dashed line
-        if total_width > 3 {
-            line[1] = frag_v;
-            for i in 2..total_width.saturating_sub(2) {
-                line[i] = frag_dashed;
+        };
+        let right = note_x + note_width - 1;
+
+        let chars = self.chars;
+        let rows = note_lines.len() + 2;
+        for row in 0..rows {
+            let y = self.next_row();
+            for p_idx in 0..self.positions.len() {
+                self.put(self.positions[p_idx], y, chars.v);
             }
-            line[total_width - 2] = frag_v;
-        }
-        // Overlay label if any
-        if let Some(lbl) = label {
-            let tag = format!("[{}]", lbl);
-            for (i, c) in tag.chars().enumerate() {
-                if 2 + i < total_width - 2 {
-                    line[2 + i] = c;
-                }
+            let (left, fill, end) = match row {
+                0 => (chars.tl, chars.h, chars.tr),
+                r if r == rows - 1 => (chars.bl, chars.h, chars.br),
+                _ => (chars.v, ' ', chars.v),
+            };
+            self.put(note_x, y, left);
+            self.hline(note_x + 1, right, y, fill);
+            self.put(right, y, end);
+            if row > 0 && row < rows - 1 {
+                self.text(note_x + 2, y, &note_lines[row - 1]);
             }
         }
-        output.push_str(line.iter().collect::<String>().trim_end());
-        output.push('\n');
+    }
+}
+
+/// Render sequence diagram to ASCII representation
+pub fn render_sequence_diagram(diagram: &SequenceDiagram, options: &RenderOptions) -> String {
+    if diagram.participants.is_empty() {
+        return "No participants".to_string();
+    }
+
+    let mut canvas = SequenceCanvas {
+        diagram,
+        options,
+        chars: charset(options),
+        grid: Grid::new(0, 0),
+        y: 0,
+        positions: Vec::new(),
+        total_width: 0,
     };
+    canvas.layout_columns();
 
-    // Helper to draw a fragment bottom border
-    let draw_fragment_bottom = |output: &mut String,
-                                total_width: usize,
-                                positions: &[usize],
-                                lifeline_fn: &dyn Fn(usize, usize) -> char,
-                                msg_idx: usize| {
-        let mut line = vec![' '; total_width];
-        for (pi, &pos) in positions.iter().enumerate() {
-            if pos < total_width {
-                line[pos] = lifeline_fn(pi, msg_idx);
-            }
+    if let Some(title) = &diagram.title {
+        canvas.draw_title(title);
+    }
+    canvas.draw_participants();
+
+    let mut fragment_spans = Vec::new();
+    collect_fragment_spans(&diagram.items, &mut 0, &mut fragment_spans);
+
+    // First row of each open rect block, by span index
+    let mut rect_starts: HashMap<usize, usize> = HashMap::new();
+    // Rows of each closed rect block with its background, innermost first
+    let mut shading: Vec<(Range<usize>, String)> = Vec::new();
+
+    for (msg_idx, msg) in diagram.messages.iter().enumerate() {
+        for (_, label) in diagram.delays.iter().filter(|(at, _)| *at == msg_idx) {
+            canvas.draw_delay(label);
         }
-        if total_width > 3 {
-            line[1] = frag_bl;
-            for i in 2..total_width.saturating_sub(2) {
-                line[i] = frag_h;
+        // Open rect blocks first so they shade the borders of fragments inside
+        for (span_idx, span) in fragment_spans.iter().enumerate() {
+            if span.kind == FragmentKind::Rect && span.start_msg == msg_idx {
+                rect_starts.insert(span_idx, canvas.y);
             }
-            line[total_width - 2] = frag_br;
         }
-        output.push_str(line.iter().collect::<String>().trim_end());
-        output.push('\n');
-    };
-
-    // Draw vertical lines (lifelines) and messages
-    for (msg_idx, msg) in diagram.messages.iter().enumerate() {
-        // Draw fragment starts at this message index
         for span in &fragment_spans {
-            if span.start_msg == msg_idx {
-                draw_fragment_top(
-                    &mut output,
-                    total_width,
-                    &positions,
-                    &span.kind,
-                    &span.label,
-                    &lifeline_char,
-                    msg_idx,
-                );
+            if span.start_msg == msg_idx && span.kind != FragmentKind::Rect {
+                canvas.draw_fragment_top(span, msg_idx);
             }
         }
-        // Draw fragment dividers at this message index
         for span in &fragment_spans {
             for (div_idx, div_label) in &span.dividers {
                 if *div_idx == msg_idx {
-                    draw_fragment_divider(
-                        &mut output,
-                        total_width,
-                        &positions,
-                        div_label,
-                        &lifeline_char,
-                        msg_idx,
-                    );
+                    canvas.draw_fragment_divider(div_label, msg_idx);
                 }
             }
         }
 
-        // Find participant indices
-        let from_idx = diagram
-            .participants
-            .iter()
-            .position(|p| p.id == msg.from || p.label == msg.from);
-        let to_idx = diagram
-            .participants
-            .iter()
-            .position(|p| p.id == msg.to || p.label == msg.to);
-
-        if let (Some(from_i), Some(to_i)) = (from_idx, to_idx) {
-            let from_x = positions[from_i];
-            let to_x = positions[to_i];
-
-            // Self-message loop (same participant)
+        let from = canvas.participant_index(&msg.from);
+        let to = canvas.participant_index(&msg.to);
+        if let (Some(from_i), Some(to_i)) = (from, to) {
+            let (from_x, to_x) = (canvas.positions[from_i], canvas.positions[to_i]);
             if from_i == to_i {
-                let loop_width = 4;
-                let (h_line, corner_tl, corner_tr, corner_bl, corner_br) = if options.ascii {
-                    ('-', '+', '+', '+', '+')
-                } else {
-                    ('─', '╭', '╮', '╰', '╯')
-                };
-
-                // Row 1: lifelines + top of loop
-                let mut line = vec![' '; total_width + loop_width + 2];
-                for (pi, &pos) in positions.iter().enumerate() {
-                    if pos < line.len() {
-                        line[pos] = lifeline_char(pi, msg_idx);
-                    }
-                }
-                // Draw top of loop: ╭──╮
-                if from_x + 1 < line.len() {
-                    line[from_x + 1] = corner_tl;
-                }
-                for i in 2..=loop_width {
-                    if from_x + i < line.len() {
-                        line[from_x + i] = h_line;
-                    }
-                }
-                if from_x + loop_width + 1 < line.len() {
-                    line[from_x + loop_width + 1] = corner_tr;
-                }
-                output.push_str(line.iter().collect::<String>().trim_end());
-                output.push('\n');
-
-                // Row 2: lifelines + vertical sides
-                let mut line = vec![' '; total_width + loop_width + 2];
-                for (pi, &pos) in positions.iter().enumerate() {
-                    if pos < line.len() {
-                        line[pos] = lifeline_char(pi, msg_idx);
-                    }
-                }
-                if from_x + 1 < line.len() {
-                    line[from_x + 1] = if options.ascii { '|' } else { '│' };
-                }
-                if from_x + loop_width + 1 < line.len() {
-                    line[from_x + loop_width + 1] = if options.ascii { '|' } else { '│' };
-                }
-                output.push_str(line.iter().collect::<String>().trim_end());
-                // Add label
-                if diagram.autonumber || !msg.label.is_empty() {
-                    output.push_str("  ");
-                    if diagram.autonumber {
-                        output.push_str(&format!("{}. ", msg_idx + 1));
-                    }
-                    output.push_str(&msg.label);
-                }
-                output.push('\n');
-
-                // Row 3: lifelines + bottom of loop with arrow
-                let mut line = vec![' '; total_width + loop_width + 2];
-                for (pi, &pos) in positions.iter().enumerate() {
-                    if pos < line.len() {
-                        line[pos] = lifeline_char(pi, msg_idx);
-                    }
-                }
-                if from_x + 1 < line.len() {
-                    line[from_x + 1] = corner_bl;
-                }
-                // Arrow pointing back
-                if from_x + 2 < line.len() {
-                    line[from_x + 2] = arrow_l;
-                }
-                for i in 3..=loop_width {
-                    if from_x + i < line.len() {
-                        line[from_x + i] = h_line;
-                    }
-                }
-                if from_x + loop_width + 1 < line.len() {
-                    line[from_x + loop_width + 1] = corner_br;
-                }
-                output.push_str(line.iter().collect::<String>().trim_end());
-                output.push('\n');
-
-                continue;
-            }
-
-            // Draw lifeline row with vertical lines at participant positions
-            let mut line = vec![' '; total_width];
-            for (pi, &pos) in positions.iter().enumerate() {
-                if pos < total_width {
-                    line[pos] = lifeline_char(pi, msg_idx);
-                }
-            }
-            output.push_str(&line.iter().collect::<String>());
-            output.push('\n');
-
-            // Draw message arrow
-            let mut line = vec![' '; total_width];
-            for (pi, &pos) in positions.iter().enumerate() {
-                if pos < total_width {
-                    line[pos] = lifeline_char(pi, msg_idx);
-                }
-            }
-
-            let (start_x, end_x, going_right) = if from_x < to_x {
-                (from_x, to_x, true)
+                canvas.draw_self_message(msg_idx, msg, from_x);
             } else {
-                (to_x, from_x, false)
-            };
-
-            // Draw arrow line
-            let arrow_char = match msg.style {
-                ArrowStyle::Dotted | ArrowStyle::DottedLine => {
-                    if options.ascii {
-                        '-'
-                    } else {
-                        '·'
-                    }
-                }
-                _ => {
-                    if options.ascii {
-                        '-'
-                    } else {
-                        '─'
-                    }
-                }
-            };
-
-            for x in (start_x + 1)..end_x {
-                if x < total_width {
-                    line[x] = arrow_char;
-                }
+                canvas.draw_message(msg_idx, msg, from_x, to_x);
             }
+        }
 
-            // Draw arrow head
-            let has_arrow = matches!(
-                msg.style,
-                ArrowStyle::Solid | ArrowStyle::Dotted | ArrowStyle::Async
-            );
-            if has_arrow {
-                if going_right && end_x > 0 && end_x - 1 < total_width {
-                    line[end_x - 1] = arrow_r;
-                } else if !going_right && start_x + 1 < total_width {
-                    line[start_x + 1] = arrow_l;
-                }
-            }
-
-            output.push_str(&line.iter().collect::<String>());
-
-            // Add label (with optional autonumber prefix)
-            if diagram.autonumber || !msg.label.is_empty() {
-                output.push_str("  ");
-                if diagram.autonumber {
-                    output.push_str(&format!("{}. ", msg_idx + 1));
-                }
-                output.push_str(&msg.label);
-            }
-            output.push('\n');
+        for (_, note) in diagram.notes.iter().filter(|(at, _)| *at == msg_idx) {
+            canvas.draw_note(note);
         }
 
-        // Draw notes attached to this message
-        for (note_idx, note) in &diagram.notes {
-            if *note_idx != msg_idx {
+        // Close fragments that end after this message
+        for (span_idx, span) in fragment_spans.iter().enumerate() {
+            if span.end_msg != msg_idx + 1 {
                 continue;
             }
-            let note_text = &note.text;
-            let note_width = display_width(note_text) + 4; // "│ text │"
-
-            // Determine note x position based on NotePosition
-            let note_x = match &note.position {
-                NotePosition::RightOf(id) => {
-                    let p_idx = diagram
-                        .participants
-                        .iter()
-                        .position(|p| p.id == *id || p.label == *id);
-                    if let Some(pi) = p_idx {
-                        positions[pi] + 2
-                    } else {
-                        0
-                    }
+            if span.kind != FragmentKind::Rect {
+                canvas.draw_fragment_bottom(msg_idx);
+            } else if let Some(start) = rect_starts.remove(&span_idx) {
+                if let Some(bg) = rect_background(&span.label).filter(|_| options.colors) {
+                    shading.push((start..canvas.y, bg));
                 }
-                NotePosition::LeftOf(id) => {
-                    let p_idx = diagram
-                        .participants
-                        .iter()
-                        .position(|p| p.id == *id || p.label == *id);
-                    if let Some(pi) = p_idx {
-                        positions[pi].saturating_sub(note_width + 1)
-                    } else {
-                        0
-                    }
-                }
-                NotePosition::Over(ids) => {
-                    let indices: Vec<usize> = ids
-                        .iter()
-                        .filter_map(|id| {
-                            diagram
-                                .participants
-                                .iter()
-                                .position(|p| p.id == *id || p.label == *id)
-                        })
-                        .collect();
-                    if indices.is_empty() {
-                        0
-                    } else {
-                        let min_x = indices.iter().map(|&i| positions[i]).min().unwrap();
-                        let max_x = indices.iter().map(|&i| positions[i]).max().unwrap();
-                        let center = (min_x + max_x) / 2;
-                        center.saturating_sub(note_width / 2)
-                    }
-                }
-            };
-
-            let render_width = total_width.max(note_x + note_width + 1);
-
-            // Note top border
-            let mut nline = vec![' '; render_width];
-            for &pos in &positions {
-                if pos < nline.len() {
-                    nline[pos] = if options.ascii { '|' } else { '│' };
-                }
-            }
-            if note_x < nline.len() {
-                nline[note_x] = box_tl;
-            }
-            for i in 1..note_width - 1 {
-                if note_x + i < nline.len() {
-                    nline[note_x + i] = box_h;
-                }
-            }
-            if note_x + note_width - 1 < nline.len() {
-                nline[note_x + note_width - 1] = box_tr;
-            }
-            output.push_str(nline.iter().collect::<String>().trim_end());
-            output.push('\n');
-
-            // Note content
-            let mut nline = vec![' '; render_width];
-            for &pos in &positions {
-                if pos < nline.len() {
-                    nline[pos] = if options.ascii { '|' } else { '│' };
-                }
-            }
-            if note_x < nline.len() {
-                nline[note_x] = box_v;
-            }
-            let text_start = note_x + 2;
-            for (i, c) in note_text.chars().enumerate() {
-                if text_start + i < nline.len() {
-                    nline[text_start + i] = c;
-                }
-            }
-            if note_x + note_width - 1 < nline.len() {
-                nline[note_x + note_width - 1] = box_v;
-            }
-            output.push_str(nline.iter().collect::<String>().trim_end());
-            output.push('\n');
-
-            // Note bottom border
-            let mut nline = vec![' '; render_width];
-            for &pos in &positions {
-                if pos < nline.len() {
-                    nline[pos] = if options.ascii { '|' } else { '│' };
-                }
-            }
-            if note_x < nline.len() {
-                nline[note_x] = box_bl;
-            }
-            for i in 1..note_width - 1 {
-                if note_x + i < nline.len() {
-                    nline[note_x + i] = box_h;
-                }
-            }
-            if note_x + note_width - 1 < nline.len() {
-                nline[note_x + note_width - 1] = box_br;
-            }
-            output.push_str(nline.iter().collect::<String>().trim_end());
-            output.push('\n');
-        }
-
-        // Draw fragment ends after this message
-        let next_msg = msg_idx + 1;
-        for span in &fragment_spans {
-            if span.end_msg == next_msg {
-                draw_fragment_bottom(
-                    &mut output,
-                    total_width,
-                    &positions,
-                    &lifeline_char,
-                    msg_idx,
-                );
             }
         }
     }
 
-    // Final lifeline row
+    // Final lifeline row, after any trailing delays
     let total_msgs = diagram.messages.len();
-    let mut line = vec![' '; total_width];
-    for (pi, &pos) in positions.iter().enumerate() {
-        if pos < total_width {
-            line[pos] = lifeline_char(pi, total_msgs);
-        }
+    for (_, label) in diagram.delays.iter().filter(|(at, _)| *at >= total_msgs) {
+        canvas.draw_delay(label);
     }
-    output.push_str(&line.iter().collect::<String>());
-    output.push('\n');
+    canvas.lifeline_row(total_msgs);
 
-    output
+    let drawing = canvas.grid.render(options.trim_trailing);
+    if shading.is_empty() {
+        return drawing;
+    }
+    drawing
+        .lines()
+        .enumerate()
+        .map(
+            |(y, row)| match shading.iter().find(|(rows, _)| rows.contains(&y)) {
+                Some((_, bg)) => format!("{}{}\x1b[0m", bg, pad_to_width(row, canvas.total_width)),
+                None => row.to_string(),
+            },
+        )
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 #[cfg(test)]
@@ -1344,9 +1505,12 @@ mod tests {
                 deactivate_to: false,
             }],
             autonumber: false,
+            wrap: false,
             notes: Vec::new(),
             activations: Vec::new(),
+            delays: Vec::new(),
             items: Vec::new(),
+            warnings: Vec::new(),
         };
         let output = render_sequence_diagram(&diagram, &RenderOptions::default());
         assert!(output.contains("Test"));
@@ -1447,6 +1611,67 @@ mod tests {
         assert!(output.contains("┌") || output.contains("+"));
     }
 
+    #[test]
+    fn test_columns_widen_to_fit_labels() {
+        let input = "sequenceDiagram\nA->>B: a rather long label\nA->>C: x\nC->>C: looping back";
+        let diagram = parse_sequence_diagram(input).unwrap();
+        let output = render_sequence_diagram(&diagram, &RenderOptions::default());
+        let lines: Vec<&str> = output.lines().collect();
+        let label = lines
+            .iter()
+            .position(|l| l.contains("a rather long label"))
+            .unwrap();
+        // Centered above the arrow, with the gap widened to fit it
+        assert_eq!(lines[label], "      │ a rather long label  │           │");
+        assert_eq!(
+            lines[label + 1],
+            "      │─────────────────────▶│           │"
+        );
+        // A short message across both gaps needs no more room, and the
+        // self-message label stays beside its loop
+        assert!(output.contains("│           │╭───╮\n"));
+        assert!(output.contains("││   │ looping back"));
+    }
+
+    #[test]
+    fn test_long_message_widens_every_gap_it_crosses() {
+        let input = "sequenceDiagram\nparticipant A\nparticipant B\nparticipant C\nA->>C: a message long enough to need room\nB->>C: x";
+        let diagram = parse_sequence_diagram(input).unwrap();
+        let output = render_sequence_diagram(&diagram, &RenderOptions::default());
+        let lifelines: Vec<usize> = output
+            .lines()
+            .map(|l| {
+                l.chars()
+                    .enumerate()
+                    .filter(|&(_, c)| c == '│')
+                    .map(|(i, _)| i)
+                    .collect::<Vec<_>>()
+            })
+            .find(|columns| columns.len() == 3)
+            .unwrap();
+        let (left, right) = (lifelines[1] - lifelines[0], lifelines[2] - lifelines[1]);
+        assert!(left.abs_diff(right) <= 1, "{output}");
+    }
+
+    #[test]
+    fn test_render_wide_labels_and_charset() {
+        let input = r#"sequenceDiagram
+    participant A as 数据
+    A->>B: 你好
+    Note over A: 注意
+"#;
+        let diagram = parse_sequence_diagram(input).unwrap();
+        let options = RenderOptions {
+            charset: Some(CharSet::heavy()),
+            ..Default::default()
+        };
+        let output = render_sequence_diagram(&diagram, &options);
+        assert!(output.contains("┃数据┃"));
+        assert!(output.contains("  你好"));
+        // Lifelines don't show through the note
+        assert!(output.contains("┃ 注意 ┃"));
+    }
+
     #[test]
     fn test_parse_activate_deactivate() {
         let input = r#"sequenceDiagram
@@ -1461,6 +1686,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn test_parse_inline_activation() {
         let input = r#"sequenceDiagram
     Alice->>+Bob: Hello
@@ -1567,4 +1793,165 @@ mod tests {
         // Should extend to end (total messages = 2)
         assert_eq!(diagram.activations[0].2, 2);
     }
+
+    #[test]
+    fn test_symbols_spans() {
+        let code = "sequenceDiagram\nparticipant Alice\nAlice->>Bob: Hi Bob\nNote right of Bob: ok";
+        let symbols = symbols(code);
+        let found: Vec<(&str, SymbolKind, usize, &str)> = symbols
+            .iter()
+            .map(|s| (s.name.as_str(), s.kind, s.line, &code[s.span.clone()]))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("Alice", SymbolKind::Participant, 2, "Alice"),
+                ("Alice", SymbolKind::Participant, 3, "Alice"),
+                ("Bob", SymbolKind::Participant, 3, "Bob"),
+                ("Alice -> Bob", SymbolKind::Edge, 3, "Alice->>Bob"),
+                ("Bob", SymbolKind::Participant, 4, "Bob"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_rect_block() {
+        let input = "sequenceDiagram\nrect rgb(200, 200, 255)\nAlice->>Bob: Hi\nend";
+        let diagram = parse_sequence_diagram(input).unwrap();
+        let SequenceItem::Fragment(rect) = &diagram.items[0] else {
+            panic!("expected a fragment");
+        };
+        assert_eq!(rect.kind, FragmentKind::Rect);
+        assert_eq!(rect.label, "rgb(200, 200, 255)");
+        assert_eq!(
+            rect_background(&rect.label).as_deref(),
+            Some("\x1b[48;2;200;200;255m")
+        );
+        assert_eq!(
+            rect_background("rgba(1,2,3,0.5)").as_deref(),
+            Some("\x1b[48;2;1;2;3m")
+        );
+        assert_eq!(rect_background("blue"), None);
+        assert_eq!(rect_background("rgb(300,0,0)"), None);
+    }
+
+    #[test]
+    fn test_render_rect_shades_rows_only_in_color() {
+        let input = "sequenceDiagram\nAlice->>Bob: Hi\nrect rgb(1, 2, 3)\nBob->>Alice: Yo\nend";
+        let diagram = parse_sequence_diagram(input).unwrap();
+
+        let plain = render_sequence_diagram(&diagram, &RenderOptions::default());
+        assert!(!plain.contains('\x1b'));
+
+        let options = RenderOptions {
+            colors: true,
+            ..Default::default()
+        };
+        let colored = render_sequence_diagram(&diagram, &options);
+        let shaded: Vec<&str> = colored
+            .lines()
+            .filter(|l| l.starts_with("\x1b[48;2;1;2;3m"))
+            .collect();
+        assert_eq!(shaded.len(), 2);
+        assert!(shaded[0].contains("Yo") && shaded[1].ends_with("\x1b[0m"));
+        assert!(!colored
+            .lines()
+            .any(|l| l.contains("Hi") && l.contains('\x1b')));
+    }
+
+    #[test]
+    fn test_links_and_properties_are_skipped_with_warnings() {
+        let input = r#"sequenceDiagram
+    participant Alice
+    link Alice: Dashboard @ https://dashboard.contoso.com/alice
+    links Alice: {"Wiki": "https://wiki.contoso.com/alice"}
+    properties Alice: {"class": "internal-service-actor"}
+    Alice->>Bob: Hi
+"#;
+        let diagram = parse_sequence_diagram(input).unwrap();
+        let ids: Vec<&str> = diagram.participants.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, ["Alice", "Bob"]);
+        assert_eq!(diagram.messages.len(), 1);
+        assert_eq!(
+            diagram.warnings,
+            [
+                DiagramWarning::IgnoredStatement {
+                    statement: "link".to_string(),
+                    line: 3
+                },
+                DiagramWarning::IgnoredStatement {
+                    statement: "links".to_string(),
+                    line: 4
+                },
+                DiagramWarning::IgnoredStatement {
+                    statement: "properties".to_string(),
+                    line: 5
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_delays_and_wrap_directive() {
+        let input =
+            "sequenceDiagram\n%%{wrap}%%\n...\nA->>B: x\n...: 5 minutes later\n... an hour ...";
+        let diagram = parse_sequence_diagram(input).unwrap();
+        assert!(diagram.wrap);
+        assert_eq!(
+            diagram.delays,
+            [
+                (0, None),
+                (1, Some("5 minutes later".to_string())),
+                (1, Some("an hour".to_string())),
+            ]
+        );
+        assert!(matches!(diagram.items[0], SequenceItem::Delay(None)));
+        assert!(
+            !parse_sequence_diagram("sequenceDiagram\n%% wrap\nA->>B: x")
+                .unwrap()
+                .wrap
+        );
+    }
+
+    #[test]
+    fn test_render_delay_and_wrapped_text() {
+        let input = "sequenceDiagram\n%%{wrap}%%\nA->>B: please send me the quarterly report\n...: later\nB-->>A: done";
+        let diagram = parse_sequence_diagram(input).unwrap();
+        let output = render_sequence_diagram(&diagram, &RenderOptions::default());
+        let lines: Vec<&str> = output.lines().collect();
+        let first = lines
+            .iter()
+            .position(|l| l.contains("please send me the quarterly"))
+            .unwrap();
+        // The continuation is centered between the same lifelines, above
+        // the arrow
+        assert_eq!(lines[first + 1].trim(), "│            report             │");
+        assert!(lines[first + 2].ends_with("▶│"));
+        assert!(lines[first + 4].contains('┊') && lines[first + 4].ends_with("  later"));
+
+        let ascii = render_sequence_diagram(
+            &diagram,
+            &RenderOptions {
+                ascii: true,
+                ..Default::default()
+            },
+        );
+        assert!(ascii.lines().any(|l| l.trim_start().starts_with(':')));
+    }
+
+    #[test]
+    fn test_prefix_cuts_inside_fragment() {
+        let diagram = parse_sequence_diagram(
+            "sequenceDiagram\nA->>+B: one\nloop retry\nB->>C: two\nNote over C: wait\nC->>B: three\nend\nB-->>-A: four",
+        )
+        .unwrap();
+        let prefix = diagram.prefix(2);
+        assert_eq!(prefix.messages.len(), 2);
+        assert_eq!(prefix.notes.len(), 1);
+        assert_eq!(prefix.activations, vec![("B".to_string(), 0, 2)]);
+        let SequenceItem::Fragment(fragment) = &prefix.items[1] else {
+            panic!("expected the loop to be kept");
+        };
+        assert_eq!(fragment.sections[0].items.len(), 2);
+    }
 }
@@ -0,0 +1,223 @@
+//! Kroki-compatible HTTP handler, behind the `server` feature
+//!
+//! [`handle_render`] takes and returns plain `http` types, so it can sit
+//! behind axum, hyper or any other server built on the `http` crate.
+//!
+//! # Example
+//! ```
+//! use graphs_tui::server::handle_render;
+//!
+//! let req = http::Request::post("/mermaid/txt")
+//!     .body("flowchart LR\nA --> B")
+//!     .unwrap();
+//! let res = handle_render(req);
+//! assert_eq!(res.status(), 200);
+//! ```
+
+use http::{header, Method, Request, Response, StatusCode};
+
+use crate::error::MermaidError;
+use crate::json::Json;
+use crate::rich::to_svg;
+use crate::types::RenderOptions;
+use crate::{is_supported, render};
+
+/// Output formats the handler can produce
+const FORMATS: &[&str] = &["txt", "utf8", "svg"];
+
+/// Render options [`handle_render`] uses for untrusted request bodies
+pub use crate::budget::limited_options;
+
+/// Handle a Kroki render request.
+///
+/// Accepts both forms of the Kroki POST API:
+/// - `POST /{diagram_type}/{output_format}` with the diagram source as the
+///   body
+/// - `POST /` with a JSON body holding `diagram_source`, `diagram_type`
+///   and `output_format`
+///
+/// `diagram_type` is `mermaid` or `d2`; `output_format` is `txt` (or
+/// `utf8`) for the text drawing, or `svg` for the same drawing as an
+/// image. Bad requests and diagrams that fail to parse get a
+/// `400 Bad Request` with the error as plain text; diagrams over the
+/// [`limited_options`] limits get a `413 Payload Too Large`.
+pub fn handle_render<B: AsRef<[u8]>>(req: Request<B>) -> Response<Vec<u8>> {
+    handle_render_with(req, limited_options())
+}
+
+/// [`handle_render`] with other render options, e.g. limits suited to the
+/// deployment. Requests are untrusted input, so keep `max_nodes`,
+/// `max_edges` and `time_budget` set.
+pub fn handle_render_with<B: AsRef<[u8]>>(
+    req: Request<B>,
+    options: RenderOptions,
+) -> Response<Vec<u8>> {
+    if req.method() != Method::POST {
+        return plain(StatusCode::METHOD_NOT_ALLOWED, "Only POST is supported");
+    }
+    let Ok(body) = std::str::from_utf8(req.body().as_ref()) else {
+        return plain(StatusCode::BAD_REQUEST, "Request body is not valid UTF-8");
+    };
+
+    let segments: Vec<&str> = req
+        .uri()
+        .path()
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .collect();
+    let (kind, format, source) = match segments.as_slice() {
+        [kind, format] => (kind.to_string(), format.to_string(), body.to_string()),
+        [] => {
+            let Ok(json) = Json::parse(body) else {
+                return plain(StatusCode::BAD_REQUEST, "Expected a JSON object body");
+            };
+            let field = |name: &str| json.get(name).and_then(Json::as_str).map(String::from);
+            match (
+                field("diagram_type"),
+                field("output_format"),
+                field("diagram_source"),
+            ) {
+                (Some(kind), Some(format), Some(source)) => (kind, format, source),
+                _ => {
+                    return plain(
+                        StatusCode::BAD_REQUEST,
+                        "JSON body needs diagram_source, diagram_type and output_format",
+                    )
+                }
+            }
+        }
+        _ => {
+            return plain(
+                StatusCode::NOT_FOUND,
+                "Expected POST /{diagram_type}/{output_format}",
+            )
+        }
+    };
+
+    if !is_supported(&kind) {
+        return plain(
+            StatusCode::BAD_REQUEST,
+            &format!("Unsupported diagram type: {}", kind),
+        );
+    }
+    let format = format.to_lowercase();
+    if !FORMATS.contains(&format.as_str()) {
+        return plain(
+            StatusCode::BAD_REQUEST,
+            &format!(
+                "Unsupported output format: {} (expected one of {})",
+                format,
+                FORMATS.join(", ")
+            ),
+        );
+    }
+
+    match render(&kind, &source, options) {
+        Ok(result) if format == "svg" => respond(
+            StatusCode::OK,
+            "image/svg+xml",
+            to_svg(&result.output).into_bytes(),
+        ),
+        Ok(result) => plain(StatusCode::OK, &result.output),
+        Err(err @ MermaidError::TooLarge { .. }) => {
+            plain(StatusCode::PAYLOAD_TOO_LARGE, &err.to_string())
+        }
+        Err(err) => plain(StatusCode::BAD_REQUEST, &err.to_string()),
+    }
+}
+
+fn plain(status: StatusCode, text: &str) -> Response<Vec<u8>> {
+    respond(
+        status,
+        "text/plain; charset=utf-8",
+        text.as_bytes().to_vec(),
+    )
+}
+
+fn respond(status: StatusCode, content_type: &str, body: Vec<u8>) -> Response<Vec<u8>> {
+    let mut res = Response::new(body);
+    *res.status_mut() = status;
+    if let Ok(value) = content_type.parse() {
+        res.headers_mut().insert(header::CONTENT_TYPE, value);
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body(res: &Response<Vec<u8>>) -> &str {
+        std::str::from_utf8(res.body()).unwrap()
+    }
+
+    #[test]
+    fn test_path_form_renders_text() {
+        let req = Request::post("/mermaid/txt")
+            .body("flowchart LR\nA --> B")
+            .unwrap();
+        let res = handle_render(req);
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers()[header::CONTENT_TYPE],
+            "text/plain; charset=utf-8"
+        );
+        assert!(body(&res).contains('A'));
+        assert!(body(&res).contains('▶'));
+    }
+
+    #[test]
+    fn test_json_form_renders_svg() {
+        let req = Request::post("/")
+            .body(
+                r#"{"diagram_source": "a: x<y é\na -> b", "diagram_type": "d2", "output_format": "svg"}"#,
+            )
+            .unwrap();
+        let res = handle_render(req);
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()[header::CONTENT_TYPE], "image/svg+xml");
+        assert!(body(&res).starts_with("<svg "));
+        assert!(body(&res).contains("x&lt;y é"));
+    }
+
+    #[test]
+    fn test_bad_requests() {
+        let status = |req: Request<&str>| handle_render(req).status();
+        assert_eq!(
+            status(Request::get("/mermaid/txt").body("").unwrap()),
+            StatusCode::METHOD_NOT_ALLOWED
+        );
+        assert_eq!(
+            status(Request::post("/plantuml/txt").body("A -> B").unwrap()),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            status(Request::post("/mermaid/png").body("A -> B").unwrap()),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            status(Request::post("/mermaid/txt").body("").unwrap()),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            status(Request::post("/").body("{\"diagram_type\": 1}").unwrap()),
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[test]
+    fn test_oversized_diagrams_are_refused() {
+        let edges: Vec<String> = (0..1001)
+            .map(|i| format!("N{} --> N{}", i, i + 1))
+            .collect();
+        let req = Request::post("/mermaid/txt")
+            .body(format!("flowchart LR\n{}", edges.join("\n")))
+            .unwrap();
+        assert_eq!(handle_render(req).status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let req = Request::post("/mermaid/txt")
+            .body("flowchart LR\n%% @pin A 3000000000 3000000000\nA --> B")
+            .unwrap();
+        assert_eq!(handle_render(req).status(), StatusCode::OK);
+    }
+}
@@ -4,12 +4,15 @@
 
 use winnow::ascii::{space0, space1};
 use winnow::combinator::{alt, delimited, opt, preceded};
-use winnow::token::{rest, take_until, take_while};
+use winnow::token::{rest, take_till, take_until, take_while};
 use winnow::ModalResult;
 use winnow::Parser;
 
 use crate::error::MermaidError;
-use crate::types::{Direction, Edge, EdgeStyle, Graph, Node, NodeShape, Subgraph};
+use crate::label::{parse_html, strip_html};
+use crate::symbols::{find_word, source_lines, Symbol, SymbolKind};
+use crate::text::{display_width, wrap_words};
+use crate::types::{DiagramWarning, Direction, Edge, EdgeStyle, Graph, Node, NodeShape, Subgraph};
 
 /// Content of a single line (after trimming)
 #[derive(Debug)]
@@ -31,9 +34,17 @@ enum StateLine {
         label: Option<String>,
     },
     SimpleState(String),
+    /// `note`, `classDef`, `class`, `style` or a `--` concurrency divider,
+    /// which have no effect in a terminal
+    Ignored(&'static str),
+    /// A line that isn't any known statement
+    Unknown,
     Empty,
 }
 
+/// Keywords of statements the renderer skips
+const IGNORED_KEYWORDS: &[&str] = &["note", "classDef", "class", "style"];
+
 /// Parse stateDiagram or stateDiagram-v2 header
 fn parse_header(input: &mut &str) -> ModalResult<()> {
     let _ = winnow::ascii::Caseless("statediagram").parse_next(input)?;
@@ -61,14 +72,15 @@ fn parse_state_id(input: &mut &str) -> ModalResult<String> {
         .parse_next(input)
 }
 
-/// Parse [*] special state marker
+/// Parse a pseudostate marker: [*] start/end, [H] history or [H*] deep
+/// history
 fn parse_special_state(input: &mut &str) -> ModalResult<String> {
-    delimited('[', '*', ']')
-        .map(|_| "[*]".to_string())
+    alt(("[*]", "[H*]", "[H]"))
+        .map(|s: &str| s.to_string())
         .parse_next(input)
 }
 
-/// Parse a state reference (either [*] or regular ID)
+/// Parse a state reference (a pseudostate marker or regular ID)
 fn parse_state_ref(input: &mut &str) -> ModalResult<String> {
     alt((parse_special_state, parse_state_id)).parse_next(input)
 }
@@ -113,13 +125,58 @@ fn parse_transition(input: &mut &str) -> ModalResult<(String, String, Option<Str
 
     // Check for label
     let _ = space0.parse_next(input)?;
-    let label = opt(preceded(':', preceded(space0, rest)))
-        .map(|o: Option<&str>| o.map(|s| s.trim().to_string()).filter(|s| !s.is_empty()))
+    let label = opt(preceded(':', rest))
+        .map(|o: Option<&str>| o.map(format_transition_label).filter(|s| !s.is_empty()))
         .parse_next(input)?;
 
     Ok((from, to, label))
 }
 
+/// Width past which a transition label wraps onto more lines
+const TRANSITION_LABEL_WIDTH: usize = 24;
+
+/// Parse a transition label `event [guard] / action`, each part optional,
+/// into its parts
+fn parse_transition_label<'a>(
+    input: &mut &'a str,
+) -> ModalResult<(&'a str, Option<&'a str>, Option<&'a str>)> {
+    let event = take_till(0.., ['[', '/']).parse_next(input)?;
+    let guard = opt(delimited('[', take_until(0.., "]"), ']')).parse_next(input)?;
+    let _ = space0.parse_next(input)?;
+    let action = opt(preceded('/', rest)).parse_next(input)?;
+    Ok((event, guard, action))
+}
+
+/// Normalize a transition label to `event [guard] / action`, one part per
+/// line once it is wider than `TRANSITION_LABEL_WIDTH`, and long parts
+/// wrapped at spaces. Text that isn't in that form is kept as written.
+fn format_transition_label(text: &str) -> String {
+    let text = strip_html(text);
+    let Ok((event, guard, action)) = parse_transition_label.parse(&text) else {
+        return text.split_whitespace().collect::<Vec<_>>().join(" ");
+    };
+    let parts: Vec<String> = [
+        Some(event.to_string()),
+        guard.map(|g| format!("[{}]", g.trim())),
+        action.map(|a| format!("/ {}", a.trim())),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|part| part.split_whitespace().collect::<Vec<_>>().join(" "))
+    .filter(|part| !part.is_empty())
+    .collect();
+
+    let label = parts.join(" ");
+    if display_width(&label) <= TRANSITION_LABEL_WIDTH {
+        return label;
+    }
+    parts
+        .iter()
+        .flat_map(|part| wrap_words(part, TRANSITION_LABEL_WIDTH))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 /// Parse a single line and classify it
 fn parse_line(line: &str) -> StateLine {
     let trimmed = line.trim();
@@ -167,6 +224,16 @@ fn parse_line(line: &str) -> StateLine {
         return StateLine::Transition { from, to, label };
     }
 
+    // Statements with no effect in a terminal
+    if trimmed == "--" {
+        return StateLine::Ignored("--");
+    }
+    if let Some((keyword, _)) = trimmed.split_once(char::is_whitespace) {
+        if let Some(ignored) = IGNORED_KEYWORDS.iter().find(|k| **k == keyword) {
+            return StateLine::Ignored(ignored);
+        }
+    }
+
     // Simple state declaration
     if let Ok(id) = parse_simple_state_decl.parse(trimmed) {
         return StateLine::StateDeclaration {
@@ -180,11 +247,18 @@ fn parse_line(line: &str) -> StateLine {
         return StateLine::SimpleState(trimmed.to_string());
     }
 
-    StateLine::Empty
+    StateLine::Unknown
 }
 
 /// Parse state diagram syntax into a Graph
 pub fn parse_state_diagram(input: &str) -> Result<Graph, MermaidError> {
+    parse_state_diagram_with_warnings(input).map(|(graph, _)| graph)
+}
+
+/// Parse a state diagram, reporting skipped and ignored lines as warnings
+pub(crate) fn parse_state_diagram_with_warnings(
+    input: &str,
+) -> Result<(Graph, Vec<DiagramWarning>), MermaidError> {
     let lines: Vec<&str> = input.lines().collect();
 
     if lines.is_empty() || lines.iter().all(|l| l.trim().is_empty()) {
@@ -192,56 +266,85 @@ pub fn parse_state_diagram(input: &str) -> Result<Graph, MermaidError> {
     }
 
     let mut graph = Graph::new(Direction::TB);
-    let mut current_composite: Option<String> = None;
+    // Composites open at this line, innermost last
+    let mut composites: Vec<String> = Vec::new();
     let mut state_counter = 0;
     let mut found_header = false;
-
-    for line in lines.iter() {
+    let mut warnings = Vec::new();
+    // Inside a multi-line `note ... end note`
+    let mut in_note = false;
+
+    for (i, line) in lines.iter().enumerate() {
+        if in_note {
+            in_note = !line.trim().eq_ignore_ascii_case("end note");
+            continue;
+        }
         match parse_line(line) {
             StateLine::Header => {
                 found_header = true;
             }
             StateLine::Direction => {}
             StateLine::StateDeclaration { id, label } => {
-                let mut node = Node::with_shape(id.clone(), label, NodeShape::Rounded);
-                node.subgraph = current_composite.clone();
-                graph.nodes.insert(id, node);
+                let (label, bold) = parse_html(&label);
+                let mut node = Node::with_shape(id, label, NodeShape::Rounded);
+                node.bold = bold;
+                node.subgraph = composites.last().cloned();
+                insert_state(&mut graph, node);
             }
             StateLine::CompositeStart { id, label } => {
-                let sg = Subgraph::new(id.clone(), label);
+                // A transition may have named the composite before its body
+                if let Some(node) = graph.nodes.remove(&id) {
+                    for sg in &mut graph.subgraphs {
+                        sg.nodes.retain(|n| *n != node.id);
+                    }
+                }
+                let mut sg = Subgraph::new(id.clone(), strip_html(&label));
+                sg.parent = composites.last().cloned();
                 graph.subgraphs.push(sg);
-                current_composite = Some(id);
+                composites.push(id);
             }
             StateLine::CompositeEnd => {
-                current_composite = None;
+                composites.pop();
             }
             StateLine::Transition { from, to, label } => {
                 let from_id = handle_state_ref(
                     &mut graph,
                     &from,
-                    current_composite.as_deref(),
+                    composites.last().map(String::as_str),
                     &mut state_counter,
                     true,
                 );
                 let to_id = handle_state_ref(
                     &mut graph,
                     &to,
-                    current_composite.as_deref(),
+                    composites.last().map(String::as_str),
                     &mut state_counter,
                     false,
                 );
                 graph.edges.push(Edge {
                     from: from_id,
                     to: to_id,
+                    weight: label.as_deref().and_then(Edge::parse_weight),
                     label,
                     style: EdgeStyle::Arrow,
+                    ..Default::default()
                 });
             }
             StateLine::SimpleState(id) => {
-                graph.nodes.entry(id.clone()).or_insert_with(|| {
-                    let mut node = Node::with_shape(id.clone(), id.clone(), NodeShape::Rounded);
-                    node.subgraph = current_composite.clone();
-                    node
+                let composite = composites.last().map(String::as_str);
+                ensure_state_exists(&mut graph, &id, composite);
+            }
+            StateLine::Ignored(keyword) => {
+                in_note = keyword == "note" && !line.contains(':');
+                warnings.push(DiagramWarning::IgnoredStatement {
+                    statement: keyword.to_string(),
+                    line: i + 1,
+                });
+            }
+            StateLine::Unknown => {
+                warnings.push(DiagramWarning::LineSkipped {
+                    line: i + 1,
+                    message: format!("Invalid syntax: \"{}\"", line.trim()),
                 });
             }
             StateLine::Empty => {}
@@ -264,10 +367,73 @@ pub fn parse_state_diagram(input: &str) -> Result<Graph, MermaidError> {
         });
     }
 
-    Ok(graph)
+    Ok((graph, warnings))
 }
 
-/// Handle a state reference, creating special nodes for [*]
+/// States, transitions and composite states with their source locations
+pub(crate) fn symbols(input: &str) -> Vec<Symbol> {
+    let mut symbols = Vec::new();
+    for (line, offset, text) in source_lines(input) {
+        // Pseudostates like `[*]` aren't words, so find them literally
+        let locate = |name: &str, from: usize| {
+            if name.starts_with('[') {
+                text.get(from..)?.find(name).map(|i| from + i)
+            } else {
+                find_word(text, name, from)
+            }
+        };
+        let mut push = |name: &str, kind, at: usize, end: usize| {
+            symbols.push(Symbol {
+                name: name.to_string(),
+                kind,
+                line,
+                span: offset + at..offset + end,
+            })
+        };
+        match parse_line(text) {
+            StateLine::StateDeclaration { id, .. } => {
+                // Skip past `"description"` in `state "..." as id`
+                let from = text.rfind('"').map_or(0, |i| i + 1);
+                if let Some(at) = locate(&id, from) {
+                    push(&id, SymbolKind::Node, at, at + id.len());
+                }
+            }
+            StateLine::CompositeStart { id, .. } => {
+                if let Some(at) = locate(&id, 0) {
+                    push(&id, SymbolKind::Container, at, at + id.len());
+                }
+            }
+            StateLine::Transition { from, to, .. } => {
+                let Some(arrow) = text.find("-->") else {
+                    continue;
+                };
+                let (Some(from_at), Some(to_at)) = (locate(&from, 0), locate(&to, arrow + 3))
+                else {
+                    continue;
+                };
+                for (id, at) in [(&from, from_at), (&to, to_at)] {
+                    if !id.starts_with('[') {
+                        push(id, SymbolKind::Node, at, at + id.len());
+                    }
+                }
+                let name = format!("{} -> {}", from, to);
+                push(&name, SymbolKind::Edge, from_at, to_at + to.len());
+            }
+            StateLine::SimpleState(id) => {
+                if let Some(at) = locate(&id, 0) {
+                    push(&id, SymbolKind::Node, at, at + id.len());
+                }
+            }
+            _ => {}
+        }
+    }
+    symbols
+}
+
+/// Handle a state reference, creating special nodes for pseudostates.
+///
+/// Every `[*]` gets a node of its own, while each composite has one `[H]`
+/// and one `[H*]` that all its transitions share.
 fn handle_state_ref(
     graph: &mut Graph,
     state_ref: &str,
@@ -284,7 +450,27 @@ fn handle_state_ref(
         };
         let mut node = Node::with_shape(id.clone(), label, NodeShape::Circle);
         node.subgraph = composite.map(String::from);
-        graph.nodes.insert(id.clone(), node);
+        insert_state(graph, node);
+        id
+    } else if let Some(deep) = match state_ref {
+        "[H]" => Some(false),
+        "[H*]" => Some(true),
+        _ => None,
+    } {
+        let (kind, label) = if deep {
+            ("deep_history", "H*")
+        } else {
+            ("history", "H")
+        };
+        let id = match composite {
+            Some(sg) => format!("__{}_{}", kind, sg),
+            None => format!("__{}", kind),
+        };
+        if !graph.nodes.contains_key(&id) {
+            let mut node = Node::with_shape(id.clone(), label.to_string(), NodeShape::Circle);
+            node.subgraph = composite.map(String::from);
+            insert_state(graph, node);
+        }
         id
     } else {
         ensure_state_exists(graph, state_ref, composite);
@@ -292,15 +478,30 @@ fn handle_state_ref(
     }
 }
 
-/// Ensure a state exists in the graph
+/// Ensure a state exists in the graph; a composite state is its
+/// container, so it gets no node of its own
 fn ensure_state_exists(graph: &mut Graph, id: &str, composite: Option<&str>) {
-    if !graph.nodes.contains_key(id) {
+    if !graph.nodes.contains_key(id) && !graph.subgraphs.iter().any(|sg| sg.id == id) {
         let mut node = Node::with_shape(id.to_string(), id.to_string(), NodeShape::Rounded);
         node.subgraph = composite.map(String::from);
-        graph.nodes.insert(id.to_string(), node);
+        insert_state(graph, node);
     }
 }
 
+/// Add a state, listing it as a member of its composite
+fn insert_state(graph: &mut Graph, node: Node) {
+    if let Some(sg) = graph
+        .subgraphs
+        .iter_mut()
+        .find(|sg| Some(&sg.id) == node.subgraph.as_ref())
+    {
+        if !sg.nodes.contains(&node.id) {
+            sg.nodes.push(node.id.clone());
+        }
+    }
+    graph.nodes.insert(node.id.clone(), node);
+}
+
 /// Check if string is a valid state ID
 fn is_valid_state_id(s: &str) -> bool {
     !s.is_empty()
@@ -355,10 +556,137 @@ mod tests {
         assert_eq!(graph.subgraphs[0].id, "Active");
     }
 
+    #[test]
+    fn test_parse_nested_composite_states() {
+        let input = "stateDiagram-v2
+    Idle --> Active
+    state Active {
+        [*] --> Loading
+        state Ready {
+            Waiting --> Busy
+        }
+        Loading --> Ready
+    }
+    Active --> Idle";
+        let graph = parse_state_diagram(input).unwrap();
+        // Composites are containers, not states of their own
+        assert!(!graph.nodes.contains_key("Active"));
+        assert!(!graph.nodes.contains_key("Ready"));
+        let active = &graph.subgraphs[0];
+        let ready = &graph.subgraphs[1];
+        assert_eq!(
+            (active.id.as_str(), active.parent.as_deref()),
+            ("Active", None)
+        );
+        assert_eq!(active.nodes, ["__start_1", "Loading"]);
+        assert_eq!(ready.parent.as_deref(), Some("Active"));
+        assert_eq!(ready.nodes, ["Waiting", "Busy"]);
+        assert_eq!(graph.nodes["Busy"].subgraph.as_deref(), Some("Ready"));
+        assert_eq!(graph.nodes["Idle"].subgraph, None);
+    }
+
+    #[test]
+    fn test_unsupported_lines_are_reported() {
+        let input = "stateDiagram-v2
+    A --> B
+    note right of A
+        Not a state
+    end note
+    classDef hot fill:#f00
+    B ?? C";
+        let (graph, warnings) = parse_state_diagram_with_warnings(input).unwrap();
+        assert!(!graph.nodes.contains_key("Not"));
+        assert_eq!(
+            warnings,
+            [
+                DiagramWarning::IgnoredStatement {
+                    statement: "note".to_string(),
+                    line: 3
+                },
+                DiagramWarning::IgnoredStatement {
+                    statement: "classDef".to_string(),
+                    line: 6
+                },
+                DiagramWarning::LineSkipped {
+                    line: 7,
+                    message: "Invalid syntax: \"B ?? C\"".to_string()
+                },
+            ]
+        );
+    }
+
     #[test]
     fn test_parse_state_ref() {
         assert_eq!(parse_state_ref.parse("[*]").unwrap(), "[*]");
+        assert_eq!(parse_state_ref.parse("[H]").unwrap(), "[H]");
+        assert_eq!(parse_state_ref.parse("[H*]").unwrap(), "[H*]");
         assert_eq!(parse_state_ref.parse("Idle").unwrap(), "Idle");
         assert_eq!(parse_state_ref.parse("state_1").unwrap(), "state_1");
     }
+
+    #[test]
+    fn test_symbols_spans() {
+        let code = "stateDiagram-v2\n[*] --> Idle\nstate \"Run it\" as Run\nstate Comp {\n  Idle --> Run\n}";
+        let symbols = symbols(code);
+        let found: Vec<(&str, SymbolKind, usize, &str)> = symbols
+            .iter()
+            .map(|s| (s.name.as_str(), s.kind, s.line, &code[s.span.clone()]))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("Idle", SymbolKind::Node, 2, "Idle"),
+                ("[*] -> Idle", SymbolKind::Edge, 2, "[*] --> Idle"),
+                ("Run", SymbolKind::Node, 3, "Run"),
+                ("Comp", SymbolKind::Container, 4, "Comp"),
+                ("Idle", SymbolKind::Node, 5, "Idle"),
+                ("Run", SymbolKind::Node, 5, "Run"),
+                ("Idle -> Run", SymbolKind::Edge, 5, "Idle --> Run"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_transition_guard_and_action() {
+        let label = |line: &str| match parse_line(line) {
+            StateLine::Transition { label, .. } => label,
+            other => panic!("expected a transition, got {:?}", other),
+        };
+        assert_eq!(
+            label("A --> B : go  [ x > 1 ]/ run()"),
+            Some("go [x > 1] / run()".to_string())
+        );
+        assert_eq!(label("A --> B: [ok]"), Some("[ok]".to_string()));
+        assert_eq!(
+            label("A --> B: / log: done"),
+            Some("/ log: done".to_string())
+        );
+        // Unclosed guard is kept as written
+        assert_eq!(label("A --> B: go [x"), Some("go [x".to_string()));
+        assert_eq!(
+            label("A --> B: stop [queue empty and no jobs left] / flush()"),
+            Some("stop\n[queue empty and no jobs\nleft]\n/ flush()".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_history_states() {
+        let input = "stateDiagram-v2\nstate Active {\n  [*] --> A\n  A --> [H]\n  B --> [H]\n  [H*] --> A\n}\nPaused --> [H]";
+        let graph = parse_state_diagram(input).unwrap();
+        let history = &graph.nodes["__history_Active"];
+        assert_eq!(history.label, "H");
+        assert_eq!(history.shape, NodeShape::Circle);
+        assert_eq!(history.subgraph.as_deref(), Some("Active"));
+        assert_eq!(graph.nodes["__deep_history_Active"].label, "H*");
+        // Both transitions into `[H]` share one node
+        let into: Vec<&str> = graph
+            .edges
+            .iter()
+            .filter(|e| e.to == "__history_Active")
+            .map(|e| e.from.as_str())
+            .collect();
+        assert_eq!(into, ["A", "B"]);
+        // Outside a composite there is a top-level history node
+        assert!(graph.nodes.contains_key("__history"));
+    }
 }
@@ -0,0 +1,174 @@
+//! Plain-English descriptions of diagrams for screen readers
+
+use std::collections::HashSet;
+
+use crate::pie_parser::PieChart;
+use crate::seq_parser::SequenceDiagram;
+use crate::types::Graph;
+
+/// "1 node" / "3 nodes"
+fn count(n: usize, noun: &str) -> String {
+    if n == 1 {
+        format!("1 {}", noun)
+    } else {
+        format!("{} {}s", n, noun)
+    }
+}
+
+/// Node counts followed by the edges as `A → B → C` chains, e.g.
+/// "4 nodes, 3 edges; Start → API → DB; API → Cache"
+pub(crate) fn graph_summary(graph: &Graph) -> String {
+    let label = |id: &str| {
+        graph
+            .nodes
+            .get(id)
+            .map(|n| n.label.split_whitespace().collect::<Vec<_>>().join(" "))
+            .unwrap_or_else(|| id.to_string())
+    };
+
+    // Start chains at nodes nothing points to, then pick up what's left
+    let targets: HashSet<&str> = graph.edges.iter().map(|e| e.to.as_str()).collect();
+    let mut starts: Vec<&str> = Vec::new();
+    for edge in &graph.edges {
+        if !targets.contains(edge.from.as_str()) && !starts.contains(&edge.from.as_str()) {
+            starts.push(&edge.from);
+        }
+    }
+    starts.extend(graph.edges.iter().map(|e| e.from.as_str()));
+
+    let mut used = vec![false; graph.edges.len()];
+    let mut chains: Vec<String> = Vec::new();
+    for start in starts {
+        while let Some(first) =
+            (0..graph.edges.len()).find(|&i| !used[i] && graph.edges[i].from == start)
+        {
+            let mut chain = vec![label(start)];
+            let mut next = Some(first);
+            while let Some(i) = next {
+                used[i] = true;
+                let to = graph.edges[i].to.as_str();
+                chain.push(label(to));
+                next = (0..graph.edges.len()).find(|&j| !used[j] && graph.edges[j].from == to);
+            }
+            chains.push(chain.join(" → "));
+        }
+    }
+
+    let mut summary = format!(
+        "{}, {}",
+        count(graph.nodes.len(), "node"),
+        count(graph.edges.len(), "edge")
+    );
+    for chain in chains {
+        summary.push_str("; ");
+        summary.push_str(&chain);
+    }
+
+    // Nodes without edges, in reading order
+    let mut alone: Vec<_> = graph
+        .nodes
+        .values()
+        .filter(|n| !graph.edges.iter().any(|e| e.from == n.id || e.to == n.id))
+        .collect();
+    alone.sort_by_key(|n| (n.y, n.x, n.id.clone()));
+    if !alone.is_empty() {
+        let names: Vec<String> = alone.iter().map(|n| label(&n.id)).collect();
+        summary.push_str(&format!("; unconnected: {}", names.join(", ")));
+    }
+    summary
+}
+
+/// Participant and message counts followed by each message, e.g.
+/// "2 participants, 1 message; Alice → Bob: Hello"
+pub(crate) fn sequence_summary(diagram: &SequenceDiagram) -> String {
+    let label = |id: &str| {
+        diagram
+            .participants
+            .iter()
+            .find(|p| p.id == id)
+            .map_or(id, |p| p.label.as_str())
+            .to_string()
+    };
+    let mut summary = format!(
+        "{}, {}",
+        count(diagram.participants.len(), "participant"),
+        count(diagram.messages.len(), "message")
+    );
+    for msg in &diagram.messages {
+        summary.push_str(&format!("; {} → {}", label(&msg.from), label(&msg.to)));
+        if !msg.label.is_empty() {
+            summary.push_str(&format!(": {}", msg.label));
+        }
+    }
+    summary
+}
+
+/// Slice count followed by each slice's share, e.g.
+/// "Browsers: 2 slices; Chrome 65%, Firefox 35%"
+pub(crate) fn pie_summary(chart: &PieChart) -> String {
+    let total: f64 = chart.slices.iter().map(|s| s.value).sum();
+    let shares: Vec<String> = chart
+        .slices
+        .iter()
+        .map(|s| {
+            let percent = if total > 0.0 {
+                s.value / total * 100.0
+            } else {
+                0.0
+            };
+            format!("{} {:.0}%", s.label, percent)
+        })
+        .collect();
+    let mut summary = String::new();
+    if let Some(title) = &chart.title {
+        summary.push_str(&format!("{}: ", title));
+    }
+    summary.push_str(&count(chart.slices.len(), "slice"));
+    if !shares.is_empty() {
+        summary.push_str(&format!("; {}", shares.join(", ")));
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_mermaid;
+    use crate::pie_parser::parse_pie_chart;
+    use crate::seq_parser::parse_sequence_diagram;
+
+    #[test]
+    fn test_graph_summary_follows_chains() {
+        let graph = parse_mermaid(
+            "flowchart LR\nS[Start] --> A[API]\nA --> D[(DB)]\nA --> C[Cache]\nX[Lonely]",
+        )
+        .unwrap();
+        assert_eq!(
+            graph_summary(&graph),
+            "5 nodes, 3 edges; Start → API → DB; API → Cache; unconnected: Lonely"
+        );
+    }
+
+    #[test]
+    fn test_graph_summary_covers_cycles() {
+        let graph = parse_mermaid("flowchart LR\nA --> B\nB --> A").unwrap();
+        assert_eq!(graph_summary(&graph), "2 nodes, 2 edges; A → B → A");
+    }
+
+    #[test]
+    fn test_sequence_and_pie_summaries() {
+        let diagram =
+            parse_sequence_diagram("sequenceDiagram\nAlice->>Bob: Hello\nBob-->>Alice: Hi")
+                .unwrap();
+        assert_eq!(
+            sequence_summary(&diagram),
+            "2 participants, 2 messages; Alice → Bob: Hello; Bob → Alice: Hi"
+        );
+        let chart =
+            parse_pie_chart("pie\ntitle Browsers\n\"Chrome\" : 65\n\"Firefox\" : 35").unwrap();
+        assert_eq!(
+            pie_summary(&chart),
+            "Browsers: 2 slices; Chrome 65%, Firefox 35%"
+        );
+    }
+}
@@ -0,0 +1,130 @@
+//! Symbol extraction for editor tooling: where each node, edge, container
+//! and participant is written in the source
+
+use std::ops::Range;
+
+use crate::{detect_format, DiagramFormat};
+
+/// What a [`Symbol`] names
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SymbolKind {
+    /// A flowchart node, state or D2 shape
+    Node,
+    /// A connection, named `from -> to`
+    Edge,
+    /// A subgraph, composite state or D2 container
+    Container,
+    /// A sequence diagram participant or actor
+    Participant,
+}
+
+/// One mention of a named diagram element in the source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// 1-based source line of the start of `span`
+    pub line: usize,
+    /// Byte range in the source. For nodes, containers and participants
+    /// it covers the id as written; for edges, both endpoints and the
+    /// operator between them.
+    pub span: Range<usize>,
+}
+
+/// List the nodes, edges, containers and participants of a diagram with
+/// their source locations.
+///
+/// Every mention is listed, in the order they appear, so the first symbol
+/// with a name is its definition and all of them together are what a
+/// rename has to touch. Lines that don't parse are skipped rather than
+/// failing the whole extraction.
+///
+/// Dispatches by `lang`: `"d2"` → D2, anything else → Mermaid auto-detect.
+///
+/// # Example
+/// ```
+/// use graphs_tui::{extract_symbols, SymbolKind};
+///
+/// let code = "flowchart LR\nA[Start] --> B";
+/// let symbols = extract_symbols("mermaid", code);
+/// let names: Vec<&str> = symbols.iter().map(|s| s.name.as_str()).collect();
+/// assert_eq!(names, ["A", "B", "A -> B"]);
+/// assert_eq!(symbols[1].kind, SymbolKind::Node);
+/// assert_eq!(&code[symbols[1].span.clone()], "B");
+/// assert_eq!(symbols[2].line, 2);
+/// ```
+pub fn extract_symbols(lang: &str, code: &str) -> Vec<Symbol> {
+    let format = match lang.to_lowercase().as_str() {
+        "d2" => DiagramFormat::D2,
+        _ => detect_format(code),
+    };
+    match format {
+        DiagramFormat::Mermaid => crate::parser::symbols(code),
+        DiagramFormat::StateDiagram => crate::state_parser::symbols(code),
+        DiagramFormat::SequenceDiagram => crate::seq_parser::symbols(code),
+        DiagramFormat::D2 => crate::d2_parser::symbols(code),
+        DiagramFormat::PieChart => Vec::new(),
+    }
+}
+
+/// Source lines as `(1-based number, byte offset, text)`, without line
+/// endings
+pub(crate) fn source_lines(code: &str) -> impl Iterator<Item = (usize, usize, &str)> {
+    code.split_inclusive('\n')
+        .scan(0, |offset, raw| {
+            let start = *offset;
+            *offset += raw.len();
+            Some((start, raw.trim_end_matches(['\n', '\r'])))
+        })
+        .enumerate()
+        .map(|(i, (offset, line))| (i + 1, offset, line))
+}
+
+/// Byte offset of the first whole-word `word` in `text` at or after `from`
+pub(crate) fn find_word(text: &str, word: &str, from: usize) -> Option<usize> {
+    if word.is_empty() {
+        return None;
+    }
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let mut start = from;
+    while let Some(i) = text.get(start..)?.find(word) {
+        let at = start + i;
+        let end = at + word.len();
+        let before = text[..at].chars().next_back();
+        let after = text[end..].chars().next();
+        if !before.is_some_and(is_word) && !after.is_some_and(is_word) {
+            return Some(at);
+        }
+        start = end;
+    }
+    None
+}
+
+/// 1-based line holding byte `offset`
+pub(crate) fn line_at(code: &str, offset: usize) -> usize {
+    code.as_bytes()[..offset.min(code.len())]
+        .iter()
+        .filter(|&&b| b == b'\n')
+        .count()
+        + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_word_skips_partial_matches() {
+        assert_eq!(find_word("AB --> A", "A", 0), Some(7));
+        assert_eq!(find_word("x_A A", "A", 0), Some(4));
+        assert_eq!(find_word("A A", "A", 1), Some(2));
+        assert_eq!(find_word("B", "A", 0), None);
+    }
+
+    #[test]
+    fn test_source_lines_offsets() {
+        let lines: Vec<_> = source_lines("a\r\nbc\n\nd").collect();
+        assert_eq!(lines, [(1, 0, "a"), (2, 3, "bc"), (3, 6, ""), (4, 7, "d")]);
+        assert_eq!(line_at("a\nb\nc", 4), 3);
+    }
+}
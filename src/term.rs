@@ -0,0 +1,171 @@
+//! Terminal capabilities read from the environment, so output suits the
+//! terminal without the user passing flags
+
+use crate::types::{RenderOptions, RenderOptionsBuilder};
+
+/// How many colors a terminal can show
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum ColorDepth {
+    /// No color, e.g. `NO_COLOR` is set or output isn't a terminal
+    #[default]
+    None,
+    /// The 16 standard ANSI colors
+    Basic,
+    /// The 256-color xterm palette
+    Ansi256,
+    /// 24-bit RGB
+    TrueColor,
+}
+
+/// What the terminal can draw, as far as the environment says
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TermCaps {
+    /// Box-drawing characters and arrows display correctly
+    pub unicode: bool,
+    /// Colors the terminal supports
+    pub colors: ColorDepth,
+    /// OSC 8 hyperlinks are clickable
+    pub hyperlinks: bool,
+}
+
+impl TermCaps {
+    /// Capabilities of the terminal this process runs in, from `TERM`,
+    /// `NO_COLOR`, `COLORTERM`, `WT_SESSION`, `TERM_PROGRAM`,
+    /// `VTE_VERSION` and the locale.
+    ///
+    /// # Example
+    /// ```
+    /// use graphs_tui::{render_mermaid_to_tui, TermCaps};
+    ///
+    /// let options = TermCaps::detect().builder().build().unwrap();
+    /// let result = render_mermaid_to_tui("flowchart LR\nA --> B", options).unwrap();
+    /// println!("{}", result.output);
+    /// ```
+    pub fn detect() -> Self {
+        Self::from_env(|name| std::env::var(name).ok())
+    }
+
+    /// Capabilities from environment variables looked up by `var`, for
+    /// detecting a remote or recorded session's terminal
+    pub fn from_env(var: impl Fn(&str) -> Option<String>) -> Self {
+        let var = |name: &str| var(name).filter(|value| !value.is_empty());
+        let term = var("TERM").unwrap_or_default();
+        let program = var("TERM_PROGRAM").unwrap_or_default();
+        let windows_terminal = var("WT_SESSION").is_some();
+        let dumb = matches!(term.as_str(), "dumb" | "vt52" | "vt100" | "vt102" | "vt220");
+
+        // Only a locale naming some other codeset rules Unicode out; an
+        // unset or plain `C` locale is common in terminals that show it fine
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"].into_iter().find_map(var);
+        let legacy_codeset = locale
+            .and_then(|l| {
+                let codeset = l.split_once('.')?.1.split('@').next()?.to_lowercase();
+                Some(codeset != "utf-8" && codeset != "utf8")
+            })
+            .unwrap_or(false);
+        let unicode = windows_terminal || !(dumb || legacy_codeset);
+
+        let colorterm = var("COLORTERM").unwrap_or_default();
+        let colors = if var("NO_COLOR").is_some() || term == "dumb" {
+            ColorDepth::None
+        } else if windows_terminal || colorterm == "truecolor" || colorterm == "24bit" {
+            ColorDepth::TrueColor
+        } else if term.contains("256color") {
+            ColorDepth::Ansi256
+        } else if !term.is_empty() {
+            ColorDepth::Basic
+        } else {
+            ColorDepth::None
+        };
+
+        let vte = var("VTE_VERSION")
+            .and_then(|v| v.parse::<u32>().ok())
+            .unwrap_or(0);
+        let hyperlinks = windows_terminal
+            || vte >= 5000
+            || matches!(program.as_str(), "iTerm.app" | "WezTerm" | "vscode")
+            || ["kitty", "foot", "wezterm", "alacritty"]
+                .iter()
+                .any(|name| term.contains(name));
+
+        TermCaps {
+            unicode,
+            colors,
+            hyperlinks,
+        }
+    }
+
+    /// Options builder suited to this terminal: ASCII when Unicode won't
+    /// display, and colors when the terminal can approximate the 24-bit
+    /// colors the renderers draw
+    pub fn builder(&self) -> RenderOptionsBuilder {
+        RenderOptions::builder()
+            .ascii(!self.unicode)
+            .colors(self.colors >= ColorDepth::Ansi256)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn caps(vars: &[(&str, &str)]) -> TermCaps {
+        TermCaps::from_env(|name| {
+            vars.iter()
+                .find(|(k, _)| *k == name)
+                .map(|(_, v)| v.to_string())
+        })
+    }
+
+    #[test]
+    fn test_detect_color_depth() {
+        assert_eq!(caps(&[]).colors, ColorDepth::None);
+        assert_eq!(caps(&[("TERM", "xterm")]).colors, ColorDepth::Basic);
+        assert_eq!(
+            caps(&[("TERM", "xterm-256color")]).colors,
+            ColorDepth::Ansi256
+        );
+        assert_eq!(
+            caps(&[("TERM", "xterm-256color"), ("COLORTERM", "truecolor")]).colors,
+            ColorDepth::TrueColor
+        );
+        assert_eq!(
+            caps(&[("TERM", "xterm-256color"), ("NO_COLOR", "1")]).colors,
+            ColorDepth::None
+        );
+        // An empty NO_COLOR doesn't count
+        assert_eq!(
+            caps(&[("TERM", "xterm"), ("NO_COLOR", "")]).colors,
+            ColorDepth::Basic
+        );
+    }
+
+    #[test]
+    fn test_detect_unicode_and_hyperlinks() {
+        let xterm = caps(&[("TERM", "xterm-256color"), ("LANG", "C")]);
+        assert!(xterm.unicode && !xterm.hyperlinks);
+        assert!(!caps(&[("TERM", "dumb")]).unicode);
+        assert!(!caps(&[("TERM", "xterm"), ("LANG", "en_US.ISO-8859-1")]).unicode);
+        assert!(caps(&[("TERM", "xterm"), ("LC_ALL", "de_DE.utf8@euro")]).unicode);
+
+        let windows = caps(&[("WT_SESSION", "1234")]);
+        assert_eq!(
+            windows,
+            TermCaps {
+                unicode: true,
+                colors: ColorDepth::TrueColor,
+                hyperlinks: true,
+            }
+        );
+        assert!(caps(&[("TERM", "xterm-kitty")]).hyperlinks);
+        assert!(caps(&[("VTE_VERSION", "6800")]).hyperlinks);
+    }
+
+    #[test]
+    fn test_builder_follows_caps() {
+        let options = caps(&[("TERM", "dumb")]).builder().build().unwrap();
+        assert!(options.ascii && !options.colors);
+        let options = caps(&[("WT_SESSION", "1")]).builder().build().unwrap();
+        assert!(!options.ascii && options.colors);
+    }
+}
@@ -0,0 +1,237 @@
+//! Property-testing helpers for crates that embed graphs-tui
+//!
+//! [`GraphGenerator`] yields random but valid flowchart graphs from a seed,
+//! and [`assert_renders_deterministically`] checks that a graph renders to
+//! the same text every time.
+//!
+//! # Example
+//! ```
+//! use graphs_tui::testing::{assert_renders_deterministically, GraphGenerator};
+//!
+//! for graph in GraphGenerator::new(42).take(5) {
+//!     assert_renders_deterministically(&graph);
+//! }
+//! ```
+
+use std::collections::HashMap;
+
+use crate::types::{Direction, Edge, EdgeStyle, Graph, Node, NodeShape, RenderOptions, Subgraph};
+use crate::{render_parsed, ParsedDiagram};
+
+/// Words node and edge labels are made from, including wide and
+/// multi-byte text
+const WORDS: &[&str] = &[
+    "API",
+    "db",
+    "Cache",
+    "queue",
+    "Load balancer",
+    "café",
+    "数据",
+    "x",
+    "auth?",
+    "Worker 2",
+];
+
+const SHAPES: &[NodeShape] = &[
+    NodeShape::Rectangle,
+    NodeShape::Rounded,
+    NodeShape::Circle,
+    NodeShape::Diamond,
+    NodeShape::Cylinder,
+    NodeShape::Stadium,
+    NodeShape::Subroutine,
+    NodeShape::Hexagon,
+];
+
+const STYLES: &[EdgeStyle] = &[
+    EdgeStyle::Arrow,
+    EdgeStyle::Line,
+    EdgeStyle::DottedArrow,
+    EdgeStyle::DottedLine,
+    EdgeStyle::ThickArrow,
+    EdgeStyle::ThickLine,
+];
+
+const DIRECTIONS: &[Direction] = &[Direction::TB, Direction::BT, Direction::LR, Direction::RL];
+
+/// Seeded generator of random valid graphs; the same seed always yields
+/// the same sequence of graphs
+#[derive(Debug, Clone)]
+pub struct GraphGenerator {
+    state: u64,
+    /// Largest number of nodes in a generated graph (at least 1)
+    pub max_nodes: usize,
+    /// Largest number of edges in a generated graph
+    pub max_edges: usize,
+    /// Whether nodes may be grouped into subgraphs
+    pub subgraphs: bool,
+}
+
+impl GraphGenerator {
+    /// Generator of graphs with up to 8 nodes and 10 edges
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: seed,
+            max_nodes: 8,
+            max_edges: 10,
+            subgraphs: true,
+        }
+    }
+
+    /// Next random graph
+    pub fn graph(&mut self) -> Graph {
+        let mut graph = Graph::new(*self.pick(DIRECTIONS));
+
+        let node_count = 1 + self.below(self.max_nodes.max(1));
+        let ids: Vec<String> = (0..node_count).map(|i| format!("n{}", i)).collect();
+        for id in &ids {
+            let label = self.label();
+            let shape = *self.pick(SHAPES);
+            graph
+                .nodes
+                .insert(id.clone(), Node::with_shape(id.clone(), label, shape));
+        }
+
+        if self.subgraphs && node_count > 1 && self.below(2) == 0 {
+            let mut sg = Subgraph::new("g0".to_string(), self.label());
+            for id in &ids {
+                if self.below(3) == 0 {
+                    sg.nodes.push(id.clone());
+                    if let Some(node) = graph.nodes.get_mut(id) {
+                        node.subgraph = Some(sg.id.clone());
+                    }
+                }
+            }
+            if !sg.nodes.is_empty() {
+                graph.subgraphs.push(sg);
+            }
+        }
+
+        let edge_count = self.below(self.max_edges + 1);
+        for _ in 0..edge_count {
+            let from = self.pick(&ids).clone();
+            let to = self.pick(&ids).clone();
+            let label = (self.below(3) == 0).then(|| self.label());
+            let style = *self.pick(STYLES);
+            graph.edges.push(Edge {
+                from,
+                to,
+                label,
+                style,
+                ..Default::default()
+            });
+        }
+        graph
+    }
+
+    /// One or two words
+    fn label(&mut self) -> String {
+        let first = *self.pick(WORDS);
+        if self.below(3) == 0 {
+            format!("{} {}", first, self.pick(WORDS))
+        } else {
+            first.to_string()
+        }
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+
+    /// Uniform-enough number in `0..n`
+    fn below(&mut self, n: usize) -> usize {
+        // splitmix64
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        (z % n.max(1) as u64) as usize
+    }
+}
+
+impl Iterator for GraphGenerator {
+    type Item = Graph;
+
+    fn next(&mut self) -> Option<Graph> {
+        Some(self.graph())
+    }
+}
+
+/// Panic unless `graph` renders without error, and to the same text each
+/// time, in both Unicode and ASCII.
+///
+/// Each render starts from a node map rebuilt with a fresh hasher, so
+/// output that depends on `HashMap` iteration order is caught.
+pub fn assert_renders_deterministically(graph: &Graph) {
+    for ascii in [false, true] {
+        let options = RenderOptions {
+            ascii,
+            ..Default::default()
+        };
+        let render = || {
+            let mut copy = graph.clone();
+            copy.nodes = graph.nodes.clone().into_iter().collect::<HashMap<_, _>>();
+            match render_parsed(ParsedDiagram::Flowchart(copy), options.clone()) {
+                Ok(result) => result.output,
+                Err(err) => panic!("graph failed to render: {}\n{:#?}", err, graph),
+            }
+        };
+        let first = render();
+        let second = render();
+        assert!(
+            first == second,
+            "graph rendered differently on a second run (ascii: {})\n--- first ---\n{}\n--- second ---\n{}\n{:#?}",
+            ascii,
+            first,
+            second,
+            graph
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_same_graphs() {
+        let edges = |seed| {
+            GraphGenerator::new(seed)
+                .take(10)
+                .map(|g| {
+                    g.edges
+                        .iter()
+                        .map(|e| (e.from.clone(), e.to.clone(), e.label.clone()))
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(edges(7), edges(7));
+        assert_ne!(edges(7), edges(8));
+    }
+
+    #[test]
+    fn test_generated_graphs_are_valid() {
+        for graph in GraphGenerator::new(1).take(50) {
+            assert!(!graph.nodes.is_empty());
+            for edge in &graph.edges {
+                assert!(graph.nodes.contains_key(&edge.from));
+                assert!(graph.nodes.contains_key(&edge.to));
+            }
+            for sg in &graph.subgraphs {
+                for id in &sg.nodes {
+                    assert_eq!(graph.nodes[id].subgraph.as_deref(), Some(sg.id.as_str()));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_generated_graphs_render_deterministically() {
+        for graph in GraphGenerator::new(3).take(40) {
+            assert_renders_deterministically(&graph);
+        }
+    }
+}
@@ -1,8 +1,311 @@
 //! Text display width utilities for proper Unicode handling
 
+use unicode_segmentation::UnicodeSegmentation;
 use unicode_width::UnicodeWidthStr;
 
 /// Return the display width of a string, accounting for CJK double-width characters.
 pub fn display_width(s: &str) -> usize {
     UnicodeWidthStr::width(s)
 }
+
+/// Longest prefix of `s` whose display width fits within `max_width`.
+///
+/// Cuts only between grapheme clusters, so combining marks and ZWJ emoji
+/// sequences are never split.
+pub fn truncate_to_width(s: &str, max_width: usize) -> &str {
+    let mut width = 0;
+    for (idx, g) in s.grapheme_indices(true) {
+        let gw = display_width(g);
+        if width + gw > max_width {
+            return &s[..idx];
+        }
+        width += gw;
+    }
+    s
+}
+
+/// Truncate `s` to `max_width` columns, ending in '…' when anything was cut
+pub fn truncate_with_ellipsis(s: &str, max_width: usize) -> String {
+    if display_width(s) <= max_width {
+        return s.to_string();
+    }
+    let mut truncated = truncate_to_width(s, max_width.saturating_sub(1)).to_string();
+    truncated.push('…');
+    truncated
+}
+
+/// Shorten `s` to `max_width` columns by replacing its middle with '…',
+/// keeping the start and end, which usually tell similar labels apart
+pub fn abbreviate_middle(s: &str, max_width: usize) -> String {
+    if display_width(s) <= max_width {
+        return s.to_string();
+    }
+    let budget = max_width.saturating_sub(1);
+    let head = truncate_to_width(s, budget.div_ceil(2));
+    let mut tail_width = budget - display_width(head);
+    let mut tail_start = s.len();
+    for (idx, g) in s.grapheme_indices(true).rev() {
+        let gw = display_width(g);
+        if gw > tail_width || idx < head.len() {
+            break;
+        }
+        tail_width -= gw;
+        tail_start = idx;
+    }
+    format!("{}…{}", head, &s[tail_start..])
+}
+
+/// Hard-wrap `s` into chunks of at most `max_width` columns
+pub fn split_to_width(s: &str, max_width: usize) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let mut rest = s;
+    while display_width(rest) > max_width {
+        let mut chunk = truncate_to_width(rest, max_width);
+        if chunk.is_empty() {
+            // A wide grapheme on its own is wider than `max_width`
+            chunk = rest.graphemes(true).next().unwrap_or(rest);
+        }
+        chunks.push(chunk);
+        rest = &rest[chunk.len()..];
+    }
+    if !rest.is_empty() || chunks.is_empty() {
+        chunks.push(rest);
+    }
+    chunks
+}
+
+/// Squeeze `s` from `from_width` to `to_width` columns by keeping only the
+/// graphemes whose scaled column advances
+pub fn scale_to_width(s: &str, from_width: usize, to_width: usize) -> String {
+    if from_width <= to_width {
+        return s.to_string();
+    }
+    let mut out = String::new();
+    let mut column = 0;
+    let mut next_target = 0;
+    for g in s.graphemes(true) {
+        let target = column * to_width / from_width;
+        if target >= next_target {
+            out.push_str(g);
+            next_target = target + display_width(g).max(1);
+        }
+        column += display_width(g);
+    }
+    truncate_to_width(&out, to_width).to_string()
+}
+
+/// Pad `s` with trailing spaces up to `width` display columns
+pub fn pad_to_width(s: &str, width: usize) -> String {
+    let mut padded = s.to_string();
+    padded.push_str(&" ".repeat(width.saturating_sub(display_width(s))));
+    padded
+}
+
+/// Remove ANSI CSI escape sequences (colors, cursor moves) from `s`
+pub fn strip_ansi(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' && chars.peek() == Some(&'[') {
+            chars.next();
+            for c in chars.by_ref() {
+                if ('\x40'..='\x7e').contains(&c) {
+                    break;
+                }
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// The `width` columns of `s` starting at column `start`, for showing part
+/// of a wide line. ANSI escape sequences are kept wherever they fall so
+/// colors carry over; a wide grapheme cut by the left edge becomes a space
+/// and one cut by the right edge is left out.
+pub fn slice_columns(s: &str, start: usize, width: usize) -> String {
+    let end = start.saturating_add(width);
+    let mut out = String::new();
+    let mut column = 0;
+    let mut rest = s;
+    while !rest.is_empty() {
+        if rest.starts_with("\x1b[") {
+            let len = rest[2..]
+                .find(|c: char| ('\x40'..='\x7e').contains(&c))
+                .map_or(rest.len(), |i| i + 3);
+            out.push_str(&rest[..len]);
+            rest = &rest[len..];
+            continue;
+        }
+        let g = rest.graphemes(true).next().unwrap_or(rest);
+        let gw = display_width(g);
+        if column >= start && column + gw <= end {
+            out.push_str(g);
+        } else if column < start && column + gw > start {
+            out.push_str(&" ".repeat((column + gw).min(end) - start));
+        }
+        column += gw;
+        rest = &rest[g.len()..];
+    }
+    out
+}
+
+/// Greedily wrap `s` at spaces into lines of at most `max_width` columns.
+///
+/// A word wider than `max_width` gets a line of its own rather than being
+/// split.
+pub fn wrap_words(s: &str, max_width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for word in s.split_whitespace() {
+        match lines.last_mut() {
+            Some(line) if display_width(line) + 1 + display_width(word) <= max_width => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.to_string()),
+        }
+    }
+    lines
+}
+
+/// Strip an ASCII keyword prefix case-insensitively.
+///
+/// Unlike lowercasing and slicing by byte index, this never splits a
+/// multi-byte character.
+pub fn strip_prefix_ignore_case<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = s.get(..prefix.len())?;
+    if head.eq_ignore_ascii_case(prefix) {
+        Some(&s[prefix.len()..])
+    } else {
+        None
+    }
+}
+
+/// Translate the escapes allowed in quoted labels: `\"`, `\'`, `\\` and
+/// `\n` (a line break). Any other backslash is kept as written.
+pub fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.peek() {
+            Some('n') => out.push('\n'),
+            Some(&e @ ('"' | '\'' | '\\')) => out.push(e),
+            _ => {
+                out.push(c);
+                continue;
+            }
+        }
+        chars.next();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unescape() {
+        assert_eq!(
+            unescape(r#"say \"hi\"\nC:\\dir \t"#),
+            "say \"hi\"\nC:\\dir \\t"
+        );
+    }
+
+    #[test]
+    fn test_truncate_to_width_wide_chars() {
+        assert_eq!(truncate_to_width("日本語", 4), "日本");
+        assert_eq!(truncate_to_width("日本語", 5), "日本");
+        assert_eq!(truncate_to_width("abc", 10), "abc");
+    }
+
+    #[test]
+    fn test_truncate_keeps_grapheme_clusters() {
+        // e + combining acute accent stays together
+        assert_eq!(truncate_to_width("e\u{301}e\u{301}", 1), "e\u{301}");
+        // ZWJ family emoji is one 2-column cluster
+        let family = "👨\u{200d}👩\u{200d}👧";
+        assert_eq!(truncate_to_width(&format!("{family}{family}"), 3), family);
+        assert_eq!(truncate_to_width(family, 1), "");
+        assert_eq!(truncate_with_ellipsis("🇯🇵🇯🇵🇯🇵", 5), "🇯🇵🇯🇵…");
+    }
+
+    #[test]
+    fn test_truncate_with_ellipsis() {
+        assert_eq!(truncate_with_ellipsis("abcdef", 4), "abc…");
+        assert_eq!(truncate_with_ellipsis("日本語テキスト", 6), "日本…");
+        assert_eq!(truncate_with_ellipsis("short", 10), "short");
+    }
+
+    #[test]
+    fn test_abbreviate_middle() {
+        assert_eq!(abbreviate_middle("Authentication", 7), "Aut…ion");
+        assert_eq!(abbreviate_middle("Authentication", 6), "Aut…on");
+        assert_eq!(abbreviate_middle("日本語テキスト", 7), "日…スト");
+        assert_eq!(abbreviate_middle("short", 5), "short");
+    }
+
+    #[test]
+    fn test_wrap_words() {
+        assert_eq!(
+            wrap_words("go [ready] / init()", 10),
+            ["go [ready]", "/ init()"]
+        );
+        assert_eq!(
+            wrap_words("a verylongword b", 4),
+            ["a", "verylongword", "b"]
+        );
+        assert!(wrap_words("  ", 4).is_empty());
+    }
+
+    #[test]
+    fn test_split_and_scale_to_width() {
+        assert_eq!(split_to_width("abcdefg", 3), ["abc", "def", "g"]);
+        assert_eq!(split_to_width("ab", 3), ["ab"]);
+        assert_eq!(split_to_width("漢字", 1), ["漢", "字"]);
+        assert_eq!(scale_to_width("abcdefgh", 8, 4), "aceg");
+        assert_eq!(scale_to_width("abc", 3, 10), "abc");
+        assert!(display_width(&scale_to_width("漢字漢字漢", 10, 5)) <= 5);
+    }
+
+    #[test]
+    fn test_pad_to_width() {
+        assert_eq!(pad_to_width("東京", 6), "東京  ");
+        assert_eq!(pad_to_width("Osaka", 6), "Osaka ");
+    }
+
+    #[test]
+    fn test_strip_ansi() {
+        assert_eq!(strip_ansi("\x1b[38;2;1;2;3mred\x1b[0m text"), "red text");
+        assert_eq!(strip_ansi("plain ┌─┐"), "plain ┌─┐");
+    }
+
+    #[test]
+    fn test_slice_columns() {
+        assert_eq!(slice_columns("abcdef", 2, 3), "cde");
+        assert_eq!(slice_columns("ab", 1, 5), "b");
+        assert_eq!(slice_columns("a日本b", 2, 3), " 本");
+        assert_eq!(slice_columns("a日本b", 0, 2), "a");
+        assert_eq!(
+            slice_columns("\x1b[31mred\x1b[0m ok", 1, 3),
+            "\x1b[31med\x1b[0m "
+        );
+    }
+
+    #[test]
+    fn test_strip_prefix_ignore_case() {
+        assert_eq!(
+            strip_prefix_ignore_case("Note over A", "note "),
+            Some("over A")
+        );
+        assert_eq!(strip_prefix_ignore_case("note", "note "), None);
+        // Multi-byte characters straddling the prefix length must not panic
+        assert_eq!(strip_prefix_ignore_case("noté x", "note "), None);
+        assert_eq!(strip_prefix_ignore_case("\u{212A}ey", "key"), None);
+    }
+}
@@ -0,0 +1,227 @@
+//! The renderer as a structured tool for AI assistants and chat TUIs
+//!
+//! [`RENDER_TOOL`] is a tool definition in the shape MCP `tools/list`
+//! returns, and [`call_render_tool`] answers a `tools/call` for it: JSON
+//! arguments in, JSON result out.
+//!
+//! # Example
+//! ```
+//! use graphs_tui::tools::call_render_tool;
+//!
+//! let result = call_render_tool(
+//!     r#"{"lang": "mermaid", "code": "flowchart LR\nA --> B", "options": {"ascii": true}}"#,
+//! )
+//! .unwrap();
+//! assert!(result.starts_with(r#"{"output":"#));
+//! ```
+
+use crate::json::{quote, Json};
+use crate::types::{EdgeRouting, RenderMode, RenderOptions};
+use crate::{is_supported, limited_options, render, SUPPORTED_LANGUAGES};
+
+/// Name of the render tool in [`RENDER_TOOL`]
+pub const RENDER_TOOL_NAME: &str = "render_diagram";
+
+/// Tool definition with a JSON Schema for the arguments
+pub const RENDER_TOOL: &str = r#"{
+  "name": "render_diagram",
+  "description": "Render a Mermaid or D2 diagram as Unicode or ASCII text for display in a terminal or chat. Returns the drawing and any warnings.",
+  "inputSchema": {
+    "type": "object",
+    "properties": {
+      "lang": {
+        "type": "string",
+        "enum": ["mermaid", "d2"],
+        "description": "Diagram language; mermaid covers flowcharts, state diagrams, sequence diagrams and pie charts"
+      },
+      "code": {
+        "type": "string",
+        "description": "Diagram source"
+      },
+      "options": {
+        "type": "object",
+        "properties": {
+          "ascii": { "type": "boolean", "description": "Draw with ASCII characters only" },
+          "colors": { "type": "boolean", "description": "Keep ANSI color codes" },
+          "max_width": { "type": "integer", "minimum": 20, "description": "Fit the drawing within this many columns" },
+          "mode": { "type": "string", "enum": ["diagram", "outline"], "description": "Draw graphs as boxes and arrows or as an indented adjacency list" },
+          "edge_routing": { "type": "string", "enum": ["orthogonal", "diagonal"] },
+          "bundle_edges": { "type": "boolean", "description": "Merge edges converging on a node" },
+          "lenient": { "type": "boolean", "description": "Skip lines that fail to parse instead of failing" },
+          "sanitize": { "type": "boolean", "description": "Repair Markdown fences, surrounding commentary, smart quotes and → arrows before parsing" }
+        },
+        "additionalProperties": false
+      }
+    },
+    "required": ["lang", "code"],
+    "additionalProperties": false
+  }
+}"#;
+
+/// Run the render tool on its JSON `arguments`.
+///
+/// Returns `{"output": ..., "warnings": [...]}`, or a message saying what
+/// was wrong with the arguments or the diagram, for the caller to report
+/// as a tool error.
+pub fn call_render_tool(arguments: &str) -> Result<String, String> {
+    let args = Json::parse(arguments)?;
+    let Json::Object(members) = &args else {
+        return Err("Arguments must be a JSON object".to_string());
+    };
+    if let Some((key, _)) = members
+        .iter()
+        .find(|(k, _)| !matches!(k.as_str(), "lang" | "code" | "options"))
+    {
+        return Err(format!("Unknown argument: {}", key));
+    }
+    let lang = args
+        .get("lang")
+        .and_then(Json::as_str)
+        .ok_or("Missing string argument: lang")?;
+    if !is_supported(lang) {
+        return Err(format!(
+            "Unsupported lang: {} (expected one of {})",
+            lang,
+            SUPPORTED_LANGUAGES.join(", ")
+        ));
+    }
+    let code = args
+        .get("code")
+        .and_then(Json::as_str)
+        .ok_or("Missing string argument: code")?;
+    let options = match args.get("options") {
+        Some(options) => tool_options(options)?,
+        None => limited_options(),
+    };
+
+    let result = render(lang, code, options).map_err(|e| e.to_string())?;
+    let warnings: Vec<String> = result
+        .warnings
+        .iter()
+        .map(|w| quote(&w.to_string()))
+        .collect();
+    Ok(format!(
+        "{{\"output\":{},\"warnings\":[{}]}}",
+        quote(&result.output),
+        warnings.join(",")
+    ))
+}
+
+/// Render options from the tool's `options` object. Tool arguments are
+/// untrusted, so these start from [`limited_options`] and no option
+/// loosens its limits.
+fn tool_options(json: &Json) -> Result<RenderOptions, String> {
+    let Json::Object(members) = json else {
+        return Err("options must be an object".to_string());
+    };
+    let mut options = limited_options();
+    for (key, value) in members {
+        let invalid = || format!("Invalid value for option {}", key);
+        match (key.as_str(), value) {
+            ("ascii", Json::Bool(b)) => options.ascii = *b,
+            ("colors", Json::Bool(b)) => options.colors = *b,
+            ("bundle_edges", Json::Bool(b)) => options.bundle_edges = *b,
+            ("lenient", Json::Bool(b)) => options.lenient = *b,
+            ("sanitize", Json::Bool(b)) => options.sanitize = *b,
+            ("max_width", Json::Number(n)) if *n >= 1.0 && n.fract() == 0.0 => {
+                options.max_width = Some(*n as usize)
+            }
+            ("mode", Json::String(s)) => {
+                options.mode = match s.as_str() {
+                    "diagram" => RenderMode::Diagram,
+                    "outline" => RenderMode::Outline,
+                    _ => return Err(invalid()),
+                }
+            }
+            ("edge_routing", Json::String(s)) => {
+                options.edge_routing = match s.as_str() {
+                    "orthogonal" => EdgeRouting::Orthogonal,
+                    "diagonal" => EdgeRouting::Diagonal,
+                    _ => return Err(invalid()),
+                }
+            }
+            (
+                "ascii" | "colors" | "bundle_edges" | "lenient" | "sanitize" | "max_width" | "mode"
+                | "edge_routing",
+                _,
+            ) => return Err(invalid()),
+            _ => return Err(format!("Unknown option: {}", key)),
+        }
+    }
+    // The same checks the library's builder makes
+    options.validate().map_err(|e| e.to_string())?;
+    Ok(options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::MermaidError;
+
+    #[test]
+    fn test_render_tool_definition_is_valid_json() {
+        let tool = Json::parse(RENDER_TOOL).unwrap();
+        assert_eq!(
+            tool.get("name").and_then(Json::as_str),
+            Some(RENDER_TOOL_NAME)
+        );
+        assert!(tool.get("inputSchema").is_some());
+    }
+
+    #[test]
+    fn test_call_render_tool() {
+        let result = call_render_tool(
+            r#"{"lang": "mermaid", "code": "flowchart LR\nA --> B\nB --> A", "options": {"ascii": true, "max_width": 40}}"#,
+        )
+        .unwrap();
+        let json = Json::parse(&result).unwrap();
+        let output = json.get("output").and_then(Json::as_str).unwrap();
+        assert!(output.contains('A') && output.is_ascii());
+        assert_eq!(
+            json.get("warnings"),
+            Some(&Json::Array(vec![Json::String(
+                "Cycle detected: A -> B -> A".to_string()
+            )]))
+        );
+    }
+
+    #[test]
+    fn test_call_render_tool_errors() {
+        assert!(call_render_tool("not json").is_err());
+        assert_eq!(
+            call_render_tool(r#"{"lang": "plantuml", "code": "A"}"#),
+            Err("Unsupported lang: plantuml (expected one of mermaid, d2)".to_string())
+        );
+        assert_eq!(
+            call_render_tool(r#"{"lang": "d2", "code": "a -> b", "options": {"width": 3}}"#),
+            Err("Unknown option: width".to_string())
+        );
+        assert_eq!(
+            call_render_tool(r#"{"lang": "d2", "code": "a -> b", "options": {"ascii": 1}}"#),
+            Err("Invalid value for option ascii".to_string())
+        );
+        assert!(call_render_tool(r#"{"lang": "mermaid", "code": ""}"#).is_err());
+        assert!(call_render_tool(
+            r#"{"lang": "d2", "code": "a -> b", "options": {"max_width": 5}}"#
+        )
+        .unwrap_err()
+        .contains("max_width"));
+    }
+
+    #[test]
+    fn test_call_render_tool_is_size_limited() {
+        let code: String = (0..1001).map(|i| format!("n{i}\n")).collect();
+        let args = format!(
+            "{{\"lang\": \"d2\", \"code\": {}, \"options\": {{\"ascii\": true}}}}",
+            quote(&code)
+        );
+        let expected = MermaidError::TooLarge {
+            resource: "nodes".to_string(),
+            limit: 1000,
+            actual: 1001,
+        };
+        assert_eq!(call_render_tool(&args), Err(expected.to_string()));
+        let args = format!("{{\"lang\": \"d2\", \"code\": {}}}", quote(&code));
+        assert_eq!(call_render_tool(&args), Err(expected.to_string()));
+    }
+}
@@ -1,5 +1,12 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
+use std::ops::Range;
+use std::time::Duration;
+
+use crate::error::MermaidError;
+use crate::hooks::RenderHooks;
+use crate::renderer::CharSet;
+use crate::symbols::{Symbol, SymbolKind};
 
 /// Node identifier type
 pub type NodeId = String;
@@ -45,6 +52,8 @@ pub enum NodeShape {
     Rounded,
     /// Circle ((Label))
     Circle,
+    /// Double circle (((Label)))
+    DoubleCircle,
     /// Diamond/rhombus {Label}
     Diamond,
     /// Cylinder/database [(Label)]
@@ -63,18 +72,31 @@ pub enum NodeShape {
     Trapezoid,
     /// Reverse Trapezoid [\Label/]
     TrapezoidAlt,
+    /// Asymmetric flag >Label]
+    Asymmetric,
     /// Table (D2 sql_table)
     Table,
+    /// Box with a separator between each line of its label, like a UML class
+    /// (Mermaid `:::compartment`)
+    Compartment,
     /// Person (D2 stick figure)
     Person,
     /// Cloud (D2 bumpy border)
     Cloud,
     /// Document/page (D2 wavy bottom)
     Document,
+    /// Oval/ellipse (D2 oval): curved top and bottom, parenthesis sides
+    Oval,
+    /// Folder with a tab on top (D2 package)
+    Package,
+    /// Chevron pointing along the flow (D2 step)
+    Step,
+    /// Speech bubble with a tail below (D2 callout)
+    Callout,
 }
 
 /// Style of an edge/link
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 pub enum EdgeStyle {
     /// Solid arrow -->
     #[default]
@@ -89,6 +111,27 @@ pub enum EdgeStyle {
     ThickArrow,
     /// Thick line ===
     ThickLine,
+    /// Invisible link ~~~, which places its nodes like any edge but isn't
+    /// drawn
+    Invisible,
+    /// Wavy arrow, for D2 animated connections
+    Wavy,
+}
+
+/// Terminator drawn at an end of an edge (D2 `source-arrowhead` /
+/// `target-arrowhead`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arrowhead {
+    /// Plain arrow ▶
+    Triangle,
+    /// Hollow circle ○
+    Circle,
+    /// Hollow diamond ◇
+    Diamond,
+    /// Crow's foot "one": a bar across the line
+    CfOne,
+    /// Crow's foot "many": prongs fanning out into the node
+    CfMany,
 }
 
 /// A field inside a sql_table or class node (D2)
@@ -148,6 +191,24 @@ pub struct Node {
     pub y: usize,
     /// Style class name applied to this node
     pub style_class: Option<String>,
+    /// Fixed top-left grid column and row that layout must keep; an axis
+    /// left `None` keeps the position layout gives it
+    pub pinned: (Option<usize>, Option<usize>),
+    /// Requested (width, height), grown to fit the label; 0 keeps the
+    /// natural size in that dimension
+    pub fixed_size: Option<(usize, usize)>,
+    /// Label is pre-formatted text (e.g. ASCII art) drawn as a left-aligned
+    /// block with its spacing intact
+    pub verbatim: bool,
+    /// Name of a shape added with [`register_shape`](crate::register_shape)
+    /// that draws this node in place of `shape`
+    pub custom_shape: Option<String>,
+    /// Application data that isn't drawn: D2 properties the renderer doesn't
+    /// use and Mermaid `%% @meta` pragmas
+    pub metadata: BTreeMap<String, String>,
+    /// Byte ranges of `label` drawn bold when colors are on, from `<b>` and
+    /// `<strong>` tags
+    pub bold: Vec<Range<usize>>,
 }
 
 impl Node {
@@ -164,9 +225,49 @@ impl Node {
             x: 0,
             y: 0,
             style_class: None,
+            pinned: (None, None),
+            fixed_size: None,
+            verbatim: false,
+            custom_shape: None,
+            metadata: BTreeMap::new(),
+            bold: Vec::new(),
         }
     }
 
+    /// Create a node whose label is pre-formatted multi-line text, drawn
+    /// verbatim inside a box sized to fit
+    pub fn with_verbatim(id: NodeId, text: &str) -> Self {
+        let mut node = Self::new(id, text.to_string());
+        node.verbatim = true;
+        node
+    }
+
+    /// Content-addressed key for this node, like `n1f0c…`: a hash of its
+    /// id, so it stays the same across renders while the label, shape or
+    /// position change, and holds only characters safe in any UI key.
+    ///
+    /// # Example
+    /// ```
+    /// use graphs_tui::Node;
+    ///
+    /// let before = Node::new("api".to_string(), "API".to_string());
+    /// let after = Node::new("api".to_string(), "API v2".to_string());
+    /// assert_eq!(before.stable_id(), after.stable_id());
+    /// assert_eq!(before.stable_id().len(), 17);
+    /// ```
+    pub fn stable_id(&self) -> String {
+        // FNV-1a, which unlike std's hashers is fixed across releases
+        let hash = self.id.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, b| {
+            (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+        });
+        format!("n{:016x}", hash)
+    }
+
+    /// Whether layout must keep this node in place on either axis
+    pub fn is_pinned(&self) -> bool {
+        self.pinned != (None, None)
+    }
+
     /// Create a new node with shape
     pub fn with_shape(id: NodeId, label: String, shape: NodeShape) -> Self {
         Self {
@@ -180,17 +281,92 @@ impl Node {
             x: 0,
             y: 0,
             style_class: None,
+            pinned: (None, None),
+            fixed_size: None,
+            verbatim: false,
+            custom_shape: None,
+            metadata: BTreeMap::new(),
+            bold: Vec::new(),
         }
     }
 }
 
 /// An edge connecting two nodes
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub struct Edge {
+    /// Explicit id from Mermaid's `e1@-->` syntax; see [`Graph::edge_id`]
+    pub id: Option<String>,
+    /// Id of a node in [`Graph::nodes`], or of a subgraph in
+    /// [`Graph::subgraphs`] for an edge that starts at a container as a
+    /// whole (as D2 containers and composite states do)
     pub from: NodeId,
+    /// Id of a node or, for an edge into a container, a subgraph, as for
+    /// `from`
     pub to: NodeId,
     pub label: Option<String>,
     pub style: EdgeStyle,
+    /// Terminator at the source end, if any
+    pub source_arrowhead: Option<Arrowhead>,
+    /// Terminator at the target end, replacing the style's arrow
+    pub target_arrowhead: Option<Arrowhead>,
+    /// Cells reserved for the edge in each layer it skips, in order from
+    /// source to target (set by layout)
+    pub waypoints: Vec<(usize, usize)>,
+    /// Where this edge's label goes, instead of
+    /// `RenderOptions::label_placement`
+    pub label_placement: Option<LabelPlacement>,
+    /// Cost of the edge for [`Graph::critical_path`], from a label suffix
+    /// like `(3d)`, in minutes
+    pub weight: Option<u64>,
+}
+
+impl Edge {
+    /// Weight in minutes from a label ending in `(N)` or `(N` + unit),
+    /// where the unit is `m`, `h`, `d` or `w`; a bare number counts as
+    /// minutes
+    pub(crate) fn parse_weight(label: &str) -> Option<u64> {
+        let inner = label.trim().strip_suffix(')')?;
+        let inner = inner[inner.rfind('(')? + 1..].trim();
+        let digits = inner.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+        let minutes = match inner[digits.len()..].to_ascii_lowercase().as_str() {
+            "" | "m" => 1,
+            "h" => 60,
+            "d" => 24 * 60,
+            "w" => 7 * 24 * 60,
+            _ => return None,
+        };
+        digits.trim().parse::<u64>().ok()?.checked_mul(minutes)
+    }
+}
+
+/// Where along an edge its label sits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LabelAlong {
+    /// Near the source node
+    Source,
+    /// Midway
+    #[default]
+    Center,
+    /// Near the target node, by the arrowhead
+    Target,
+}
+
+/// Which side of the line an edge label sits on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LabelSide {
+    /// Breaking horizontal lines, to the right of vertical ones
+    #[default]
+    Inline,
+    /// Above horizontal lines where there's room, to the right of vertical
+    /// ones
+    Above,
+}
+
+/// Placement of an edge label
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LabelPlacement {
+    pub along: LabelAlong,
+    pub side: LabelSide,
 }
 
 /// The complete graph structure
@@ -202,6 +378,12 @@ pub struct Graph {
     pub subgraphs: Vec<Subgraph>,
     /// Style class definitions (classDef name color:#hex)
     pub style_classes: HashMap<String, NodeStyle>,
+    /// Tooltips listed as footnotes under the diagram, as `(marker, text)`;
+    /// each marker also follows its node's label
+    pub tooltips: Vec<(String, String)>,
+    /// Nodes whose labels layout shortened to fit, with their full labels,
+    /// listed under the diagram
+    pub abbreviations: Vec<(NodeId, String)>,
 }
 
 impl Graph {
@@ -213,17 +395,196 @@ impl Graph {
             edges: Vec::new(),
             subgraphs: Vec::new(),
             style_classes: HashMap::new(),
+            tooltips: Vec::new(),
+            abbreviations: Vec::new(),
+        }
+    }
+
+    /// Stable id of the edge at `index` in `edges`: its explicit id, or
+    /// `#index` when it has none
+    pub fn edge_id(&self, index: usize) -> String {
+        match self.edges.get(index).and_then(|e| e.id.clone()) {
+            Some(id) => id,
+            None => format!("#{}", index),
+        }
+    }
+
+    /// Stable ids of all edges, in order
+    pub fn edge_ids(&self) -> Vec<String> {
+        (0..self.edges.len()).map(|i| self.edge_id(i)).collect()
+    }
+
+    /// Top-left grid position of each node, keyed by [`Node::stable_id`]
+    pub fn node_positions(&self) -> HashMap<String, (usize, usize)> {
+        self.nodes
+            .values()
+            .map(|node| (node.stable_id(), (node.x, node.y)))
+            .collect()
+    }
+
+    /// Node ids in the order `RenderOptions::number_nodes` numbers them:
+    /// as edges first mention them, then nodes without edges by id
+    pub fn node_numbers(&self) -> Vec<NodeId> {
+        let mut order: Vec<NodeId> = Vec::new();
+        for edge in &self.edges {
+            for id in [&edge.from, &edge.to] {
+                if self.nodes.contains_key(id) && !order.contains(id) {
+                    order.push(id.clone());
+                }
+            }
+        }
+        let mut rest: Vec<NodeId> = self
+            .nodes
+            .keys()
+            .filter(|id| !order.contains(id))
+            .cloned()
+            .collect();
+        rest.sort();
+        order.extend(rest);
+        order
+    }
+}
+
+/// How edges are routed between nodes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EdgeRouting {
+    /// Horizontal and vertical segments joined by right-angle corners
+    #[default]
+    Orthogonal,
+    /// Allow 45° diagonal segments (`╲` `╱`) for shorter, more direct edges
+    Diagonal,
+}
+
+/// How output wider than `RenderOptions::max_width` is brought within it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WidthStrategy {
+    /// Cut long lines, ending them in '…'
+    #[default]
+    Truncate,
+    /// Cut the drawing into bands of `max_width` columns, shown one under
+    /// another with a blank line between them
+    Wrap,
+    /// Squeeze every line horizontally by dropping evenly spaced columns
+    Scale,
+    /// Tighten gaps and shorten node labels with a middle '…', listing the
+    /// full labels under the drawing; anything still too wide is truncated
+    Abbreviate,
+}
+
+/// How edge labels are shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EdgeLabels {
+    /// On or beside their edges; labels that don't fit become `[n]`
+    /// markers listed under the drawing
+    #[default]
+    Inline,
+    /// Every label as an `[n]` marker listed under the drawing, which keeps
+    /// dense graphs readable
+    Legend,
+    /// Not shown
+    Hidden,
+}
+
+/// Where the legend for `[n]` edge label markers goes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LegendPosition {
+    /// Below the diagram
+    #[default]
+    Bottom,
+    /// Above the diagram
+    Top,
+    /// Only in `RenderResult::legend`, for hosts that show it themselves
+    Separate,
+    /// Left out
+    Hidden,
+}
+
+/// What ends each line of output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    /// `\n`
+    #[default]
+    Lf,
+    /// `\r\n`, for Windows tools
+    CrLf,
+}
+
+impl LineEnding {
+    /// The characters that end a line
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
         }
     }
 }
 
+/// What a graph diagram is drawn as
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RenderMode {
+    /// Boxes and routed edges on a 2D grid
+    #[default]
+    Diagram,
+    /// Indented adjacency list: each node followed by its outgoing edges,
+    /// nested under its subgraphs. Fits terminals too narrow for any layout.
+    Outline,
+}
+
+/// How nodes are positioned
+///
+/// Grid, radial and force layouts place each top-level subgraph as one
+/// block, with its members arranged as in the layered layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LayoutAlgorithm {
+    /// Layers along the flow direction (Sugiyama style)
+    #[default]
+    Layered,
+    /// Rows of equal cells in layer order, ignoring edge geometry
+    Grid,
+    /// Roots at the center, each further layer on a ring around them
+    Radial,
+    /// Spring embedding; suits undirected graphs with many cycles
+    Force,
+}
+
+/// How nodes are assigned to layers in the layered layout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Layering {
+    /// Each node one layer below its deepest predecessor; sources all start at the top
+    #[default]
+    LongestPath,
+    /// Move nodes between their neighbours to minimize total edge length
+    /// (network-simplex style), giving shorter diagrams for diamond-shaped DAGs
+    TightTree,
+}
+
+/// Where nodes sit across a layer narrower than the widest one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Alignment {
+    /// Pack toward the top (horizontal flow) or left (vertical flow)
+    Start,
+    /// Center each layer
+    #[default]
+    Center,
+    /// Pack toward the bottom (horizontal flow) or right (vertical flow)
+    End,
+}
+
 /// Options for rendering the diagram
 #[derive(Debug, Clone)]
 pub struct RenderOptions {
     /// Use ASCII characters instead of Unicode
     pub ascii: bool,
+    /// Glyphs to draw graph boxes, lines and arrows with, in place of the
+    /// set `ascii` picks (default: none)
+    pub charset: Option<CharSet>,
     /// Maximum width constraint for the diagram
     pub max_width: Option<usize>,
+    /// How to fit output wider than `max_width` (default: truncate)
+    pub width_strategy: WidthStrategy,
+    /// Draw graphs as a diagram or an outline (default: diagram); sequence
+    /// diagrams and pie charts are always drawn as-is
+    pub mode: RenderMode,
     /// Horizontal gap between nodes (default: 8)
     pub padding_x: usize,
     /// Vertical gap between nodes (default: 4)
@@ -232,48 +593,503 @@ pub struct RenderOptions {
     pub border_padding: usize,
     /// Enable ANSI color output (default: false)
     pub colors: bool,
+    /// Fail with `MermaidError::TooLarge` above this many nodes (default: unlimited)
+    pub max_nodes: Option<usize>,
+    /// Fail with `MermaidError::TooLarge` above this many edges (default: unlimited)
+    pub max_edges: Option<usize>,
+    /// Fail with `MermaidError::TooLarge` once rendering has taken this
+    /// long, checked between layout and routing passes (default: unlimited)
+    pub time_budget: Option<Duration>,
+    /// Edge routing style (default: orthogonal)
+    pub edge_routing: EdgeRouting,
+    /// Merge edges converging on the same node into one trunk (default: false)
+    pub bundle_edges: bool,
+    /// Node positioning algorithm (default: layered)
+    pub layout: LayoutAlgorithm,
+    /// Layer assignment strategy for the layered layout (default: longest path)
+    pub layering: Layering,
+    /// Placement of nodes within each layer (default: centered)
+    pub align: Alignment,
+    /// Grid positions (top-left) for specific nodes; the rest are laid out around them
+    pub pinned_nodes: HashMap<NodeId, (usize, usize)>,
+    /// (width, height) for specific nodes, e.g. to make a hub stand out;
+    /// never smaller than the label needs, and 0 keeps the natural size
+    pub node_sizes: HashMap<NodeId, (usize, usize)>,
+    /// Where edge labels go; `Edge::label_placement` overrides it per edge
+    /// (default: centered, inline)
+    pub label_placement: LabelPlacement,
+    /// How edge labels are shown in diagram mode (default: inline, with
+    /// labels that don't fit moved to a legend)
+    pub edge_labels: EdgeLabels,
+    /// Line above the label legend (default: "Labels:")
+    pub legend_heading: String,
+    /// Where the label legend goes (default: below the diagram)
+    pub legend_position: LegendPosition,
+    /// Render the lines that parsed and report the rest as
+    /// `DiagramWarning::LineSkipped` instead of failing (default: false)
+    pub lenient: bool,
+    /// Lay out graphs in this direction instead of the one the source
+    /// declares, e.g. TB for a tall, narrow terminal (default: none)
+    pub direction_override: Option<Direction>,
+    /// Draw a left-to-right graph top to bottom instead when it is wider
+    /// than `max_width` and the vertical layout fits, reporting
+    /// `DiagramWarning::Reoriented` (default: false)
+    pub auto_orient: bool,
+    /// Rows available to the drawing; `auto_orient` only switches to a
+    /// vertical layout that fits (default: unlimited)
+    pub max_height: Option<usize>,
+    /// Pad every line of the drawing to at least this many columns, for
+    /// fixed-width panes (default: none)
+    pub min_width: Option<usize>,
+    /// Where the drawing sits within `min_width`: left (`Start`), centered
+    /// or right (`End`) (default: left)
+    pub h_align: Alignment,
+    /// Prefix each graph node's label with its number, e.g. `3: DB`, so
+    /// text can refer to "node 3"; `RenderResult::node_numbers` holds the
+    /// mapping (default: false)
+    pub number_nodes: bool,
+    /// Draw the edges of [`Graph::critical_path`] thick (default: false)
+    pub emphasize_critical_path: bool,
+    /// Draw the shortest path between these two nodes highlighted: its
+    /// nodes with double borders and its edges thick (default: none)
+    pub highlight_path: Option<(NodeId, NodeId)>,
+    /// Repair common chat model mistakes before the source is parsed:
+    /// Markdown fences, commentary sentences around the diagram, smart
+    /// quotes and `→` arrows, each reported as
+    /// `DiagramWarning::InputRepaired` (default: false)
+    pub sanitize: bool,
+    /// `RenderResult::node_positions` of an earlier render; nodes keep
+    /// their order within each layer, so small edits to the source don't
+    /// reshuffle the drawing (default: none)
+    pub previous_positions: HashMap<String, (usize, usize)>,
+    /// Remove spaces at the end of output lines, except the padding
+    /// `min_width` asks for (default: true)
+    pub trim_trailing: bool,
+    /// What ends each line of output (default: `\n`)
+    pub line_ending: LineEnding,
+    /// Callbacks reporting the timing of each render stage (default: none)
+    pub hooks: RenderHooks,
 }
 
 impl Default for RenderOptions {
     fn default() -> Self {
         Self {
             ascii: false,
+            charset: None,
             max_width: None,
+            width_strategy: WidthStrategy::Truncate,
+            mode: RenderMode::Diagram,
             padding_x: 8,
             padding_y: 4,
             border_padding: 1,
             colors: false,
+            max_nodes: None,
+            max_edges: None,
+            time_budget: None,
+            edge_routing: EdgeRouting::Orthogonal,
+            bundle_edges: false,
+            layout: LayoutAlgorithm::Layered,
+            layering: Layering::LongestPath,
+            align: Alignment::Center,
+            pinned_nodes: HashMap::new(),
+            node_sizes: HashMap::new(),
+            label_placement: LabelPlacement::default(),
+            edge_labels: EdgeLabels::Inline,
+            legend_heading: "Labels:".to_string(),
+            legend_position: LegendPosition::Bottom,
+            lenient: false,
+            direction_override: None,
+            auto_orient: false,
+            max_height: None,
+            min_width: None,
+            h_align: Alignment::Start,
+            number_nodes: false,
+            emphasize_critical_path: false,
+            highlight_path: None,
+            sanitize: false,
+            previous_positions: HashMap::new(),
+            trim_trailing: true,
+            line_ending: LineEnding::Lf,
+            hooks: RenderHooks::default(),
         }
     }
 }
 
+/// Smallest `max_width` that leaves room for a box and an arrow
+const MIN_MAX_WIDTH: usize = 20;
+/// Largest gap between nodes the layout accepts
+const MAX_PADDING: usize = 100;
+/// Largest space between a label and its node border
+const MAX_BORDER_PADDING: usize = 10;
+
+impl RenderOptions {
+    /// Start building options from the defaults, checked by
+    /// [`RenderOptionsBuilder::build`].
+    ///
+    /// # Example
+    /// ```
+    /// use graphs_tui::RenderOptions;
+    ///
+    /// let options = RenderOptions::builder().ascii(true).max_width(80).build().unwrap();
+    /// assert_eq!(options.max_width, Some(80));
+    /// assert!(RenderOptions::builder().max_width(5).build().is_err());
+    /// ```
+    pub fn builder() -> RenderOptionsBuilder {
+        RenderOptionsBuilder::default()
+    }
+
+    /// Check for values that would corrupt the layout, like a `max_width`
+    /// too narrow for any box or a `min_width` above `max_width`
+    pub fn validate(&self) -> Result<(), MermaidError> {
+        let invalid = |option: &str, message: String| {
+            Err(MermaidError::InvalidOption {
+                option: option.to_string(),
+                message,
+            })
+        };
+        if let Some(width) = self.max_width.filter(|w| *w < MIN_MAX_WIDTH) {
+            return invalid(
+                "max_width",
+                format!("{} is below the minimum of {}", width, MIN_MAX_WIDTH),
+            );
+        }
+        if let (Some(min), Some(max)) = (self.min_width, self.max_width) {
+            if min > max {
+                return invalid(
+                    "min_width",
+                    format!("{} is wider than max_width {}", min, max),
+                );
+            }
+        }
+        if self.max_height == Some(0) {
+            return invalid("max_height", "must be at least 1".to_string());
+        }
+        for (option, padding) in [("padding_x", self.padding_x), ("padding_y", self.padding_y)] {
+            if !(1..=MAX_PADDING).contains(&padding) {
+                return invalid(
+                    option,
+                    format!("{} is outside 1..={}", padding, MAX_PADDING),
+                );
+            }
+        }
+        if self.border_padding > MAX_BORDER_PADDING {
+            return invalid(
+                "border_padding",
+                format!(
+                    "{} is above the maximum of {}",
+                    self.border_padding, MAX_BORDER_PADDING
+                ),
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Builder for [`RenderOptions`], validated on [`build`](Self::build)
+#[derive(Debug, Clone, Default)]
+pub struct RenderOptionsBuilder {
+    options: RenderOptions,
+}
+
+impl RenderOptionsBuilder {
+    /// The options, or the first invalid value (see [`RenderOptions::validate`])
+    pub fn build(self) -> Result<RenderOptions, MermaidError> {
+        self.options.validate()?;
+        Ok(self.options)
+    }
+
+    /// Use ASCII characters instead of Unicode
+    pub fn ascii(mut self, ascii: bool) -> Self {
+        self.options.ascii = ascii;
+        self
+    }
+
+    /// Draw graphs with these glyphs
+    pub fn charset(mut self, charset: CharSet) -> Self {
+        self.options.charset = Some(charset);
+        self
+    }
+
+    /// Fit the drawing within this many columns (at least 20)
+    pub fn max_width(mut self, width: usize) -> Self {
+        self.options.max_width = Some(width);
+        self
+    }
+
+    /// How to fit output wider than `max_width`
+    pub fn width_strategy(mut self, strategy: WidthStrategy) -> Self {
+        self.options.width_strategy = strategy;
+        self
+    }
+
+    /// Draw graphs as a diagram or an outline
+    pub fn mode(mut self, mode: RenderMode) -> Self {
+        self.options.mode = mode;
+        self
+    }
+
+    /// Horizontal gap between nodes (1 to 100)
+    pub fn padding_x(mut self, padding: usize) -> Self {
+        self.options.padding_x = padding;
+        self
+    }
+
+    /// Vertical gap between nodes (1 to 100)
+    pub fn padding_y(mut self, padding: usize) -> Self {
+        self.options.padding_y = padding;
+        self
+    }
+
+    /// Padding between text and node border (at most 10)
+    pub fn border_padding(mut self, padding: usize) -> Self {
+        self.options.border_padding = padding;
+        self
+    }
+
+    /// Enable ANSI color output
+    pub fn colors(mut self, colors: bool) -> Self {
+        self.options.colors = colors;
+        self
+    }
+
+    /// Fail with `MermaidError::TooLarge` above this many nodes
+    pub fn max_nodes(mut self, max: usize) -> Self {
+        self.options.max_nodes = Some(max);
+        self
+    }
+
+    /// Fail with `MermaidError::TooLarge` above this many edges
+    pub fn max_edges(mut self, max: usize) -> Self {
+        self.options.max_edges = Some(max);
+        self
+    }
+
+    /// Abort layout/routing once this much time has elapsed
+    pub fn time_budget(mut self, budget: Duration) -> Self {
+        self.options.time_budget = Some(budget);
+        self
+    }
+
+    /// Edge routing style
+    pub fn edge_routing(mut self, routing: EdgeRouting) -> Self {
+        self.options.edge_routing = routing;
+        self
+    }
+
+    /// Merge edges converging on the same node into one trunk
+    pub fn bundle_edges(mut self, bundle: bool) -> Self {
+        self.options.bundle_edges = bundle;
+        self
+    }
+
+    /// Node positioning algorithm
+    pub fn layout(mut self, layout: LayoutAlgorithm) -> Self {
+        self.options.layout = layout;
+        self
+    }
+
+    /// Layer assignment strategy for the layered layout
+    pub fn layering(mut self, layering: Layering) -> Self {
+        self.options.layering = layering;
+        self
+    }
+
+    /// Placement of nodes within each layer
+    pub fn align(mut self, align: Alignment) -> Self {
+        self.options.align = align;
+        self
+    }
+
+    /// Pin a node's top-left corner to a grid position
+    pub fn pin(mut self, id: &str, x: usize, y: usize) -> Self {
+        self.options.pinned_nodes.insert(id.to_string(), (x, y));
+        self
+    }
+
+    /// Draw a node at least this large
+    pub fn node_size(mut self, id: &str, width: usize, height: usize) -> Self {
+        self.options
+            .node_sizes
+            .insert(id.to_string(), (width, height));
+        self
+    }
+
+    /// Where edge labels go
+    pub fn label_placement(mut self, placement: LabelPlacement) -> Self {
+        self.options.label_placement = placement;
+        self
+    }
+
+    /// How edge labels are shown in diagram mode
+    pub fn edge_labels(mut self, labels: EdgeLabels) -> Self {
+        self.options.edge_labels = labels;
+        self
+    }
+
+    /// Line above the label legend
+    pub fn legend_heading(mut self, heading: &str) -> Self {
+        self.options.legend_heading = heading.to_string();
+        self
+    }
+
+    /// Where the label legend goes
+    pub fn legend_position(mut self, position: LegendPosition) -> Self {
+        self.options.legend_position = position;
+        self
+    }
+
+    /// Skip lines that fail to parse instead of failing
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.options.lenient = lenient;
+        self
+    }
+
+    /// Lay out graphs in this direction instead of the declared one
+    pub fn direction_override(mut self, direction: Direction) -> Self {
+        self.options.direction_override = Some(direction);
+        self
+    }
+
+    /// Draw wide left-to-right graphs top to bottom when that fits
+    pub fn auto_orient(mut self, auto_orient: bool) -> Self {
+        self.options.auto_orient = auto_orient;
+        self
+    }
+
+    /// Rows available to the drawing
+    pub fn max_height(mut self, height: usize) -> Self {
+        self.options.max_height = Some(height);
+        self
+    }
+
+    /// Pad every line to at least this many columns (at most `max_width`)
+    pub fn min_width(mut self, width: usize) -> Self {
+        self.options.min_width = Some(width);
+        self
+    }
+
+    /// Where the drawing sits within `min_width`
+    pub fn h_align(mut self, align: Alignment) -> Self {
+        self.options.h_align = align;
+        self
+    }
+
+    /// Prefix node labels with their numbers
+    pub fn number_nodes(mut self, number: bool) -> Self {
+        self.options.number_nodes = number;
+        self
+    }
+
+    /// Draw the critical path's edges thick
+    pub fn emphasize_critical_path(mut self, emphasize: bool) -> Self {
+        self.options.emphasize_critical_path = emphasize;
+        self
+    }
+
+    /// Highlight the shortest path from one node to another
+    pub fn highlight_path(mut self, from: &str, to: &str) -> Self {
+        self.options.highlight_path = Some((from.to_string(), to.to_string()));
+        self
+    }
+
+    /// Repair chat model mistakes like code fences and smart quotes before
+    /// parsing
+    pub fn sanitize(mut self, sanitize: bool) -> Self {
+        self.options.sanitize = sanitize;
+        self
+    }
+
+    /// Keep nodes in the order an earlier render placed them, from its
+    /// `RenderResult::node_positions`
+    pub fn previous_positions(mut self, positions: HashMap<String, (usize, usize)>) -> Self {
+        self.options.previous_positions = positions;
+        self
+    }
+
+    /// Remove spaces at the end of output lines
+    pub fn trim_trailing(mut self, trim: bool) -> Self {
+        self.options.trim_trailing = trim;
+        self
+    }
+
+    /// End output lines with `\n` or `\r\n`
+    pub fn line_ending(mut self, ending: LineEnding) -> Self {
+        self.options.line_ending = ending;
+        self
+    }
+
+    /// Report parse and layout timings to `hooks`
+    pub fn hooks(mut self, hooks: RenderHooks) -> Self {
+        self.options.hooks = hooks;
+        self
+    }
+}
+
 /// Structured warning emitted during layout or rendering
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum DiagramWarning {
     /// A cycle was detected involving the listed nodes
-    CycleDetected { nodes: Vec<String> },
+    CycleDetected {
+        nodes: Vec<String>,
+        /// Each cycle as a path back to its start, as given by
+        /// [`Graph::find_cycles`]
+        cycles: Vec<Vec<NodeId>>,
+    },
     /// An edge label was too long to render inline and was moved to a legend
     LabelDropped {
         marker: String,
+        /// Stable id of the edge, as given by [`Graph::edge_id`]
+        edge_id: String,
         edge_from: String,
         edge_to: String,
         label: String,
     },
     /// A D2 feature is not supported in TUI rendering
     UnsupportedFeature { feature: String, line: usize },
+    /// A statement was recognised but has no effect in TUI rendering, like
+    /// a sequence diagram actor menu link
+    IgnoredStatement { statement: String, line: usize },
+    /// A line failed to parse and was left out (lenient flowcharts; always
+    /// for sequence, state and pie diagrams)
+    LineSkipped { line: usize, message: String },
+    /// A graph too wide for `max_width` was laid out in another direction
+    /// (`auto_orient`)
+    Reoriented { from: Direction, to: Direction },
+    /// A node label was shortened to fit `max_width` and listed in full
+    /// under the diagram (`WidthStrategy::Abbreviate`)
+    LabelAbbreviated { node_id: String, label: String },
+    /// Two node boxes share cells, so parts of both are drawn over
+    NodeOverlap { nodes: Vec<String> },
+    /// A node runs past `max_width`, so its box and label are cut off
+    LabelTruncated { node_id: String },
+    /// The source was repaired before parsing (`RenderOptions::sanitize`),
+    /// e.g. a Markdown fence removed or smart quotes straightened
+    InputRepaired { line: usize, fix: String },
+    /// A node asked for a size larger than the layout allows (D2 `width`
+    /// and `height`, or `RenderOptions::node_sizes`) and was drawn smaller
+    NodeSizeClamped {
+        node_id: String,
+        width: usize,
+        height: usize,
+    },
 }
 
 impl fmt::Display for DiagramWarning {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            DiagramWarning::CycleDetected { nodes } => {
+            DiagramWarning::CycleDetected { nodes, cycles } if cycles.is_empty() => {
                 write!(f, "Cycle detected involving nodes: {}", nodes.join(", "))
             }
+            DiagramWarning::CycleDetected { cycles, .. } => {
+                let paths: Vec<String> = cycles.iter().map(|c| c.join(" -> ")).collect();
+                write!(f, "Cycle detected: {}", paths.join("; "))
+            }
             DiagramWarning::LabelDropped {
                 marker,
                 edge_from,
                 edge_to,
                 label,
+                ..
             } => {
                 write!(
                     f,
@@ -284,6 +1100,49 @@ impl fmt::Display for DiagramWarning {
             DiagramWarning::UnsupportedFeature { feature, line } => {
                 write!(f, "Unsupported D2 feature '{}' on line {}", feature, line)
             }
+            DiagramWarning::IgnoredStatement { statement, line } => {
+                write!(f, "Ignored '{}' statement on line {}", statement, line)
+            }
+            DiagramWarning::LineSkipped { line, message } => {
+                write!(f, "Line {} skipped: {}", line, message)
+            }
+            DiagramWarning::Reoriented { from, to } => {
+                write!(
+                    f,
+                    "Diagram too wide for {:?} layout, drawn {:?} instead",
+                    from, to
+                )
+            }
+            DiagramWarning::LabelAbbreviated { node_id, label } => {
+                write!(
+                    f,
+                    "Label '{}' of node {} abbreviated to fit",
+                    label, node_id
+                )
+            }
+            DiagramWarning::NodeOverlap { nodes } => {
+                write!(f, "Nodes overlap: {}", nodes.join(", "))
+            }
+            DiagramWarning::LabelTruncated { node_id } => {
+                write!(f, "Node {} cut off at the maximum width", node_id)
+            }
+            DiagramWarning::InputRepaired { line, fix } => {
+                write!(f, "Line {} repaired: {}", line, fix)
+            }
+            DiagramWarning::NodeSizeClamped {
+                node_id,
+                width,
+                height,
+            } => {
+                write!(
+                    f,
+                    "Node {} size {}x{} clamped to at most {} cells a side",
+                    node_id,
+                    width,
+                    height,
+                    crate::layout::MAX_NODE_SIZE
+                )
+            }
         }
     }
 }
@@ -295,4 +1154,226 @@ pub struct RenderResult {
     pub output: String,
     /// Warnings generated during layout/rendering
     pub warnings: Vec<DiagramWarning>,
+    /// Source line of each rendered node and edge
+    pub source_map: SourceMap,
+    /// Stable id of each edge in model order (see [`Graph::edge_id`])
+    pub edge_ids: Vec<String>,
+    /// Plain-English description of the diagram for screen readers, e.g.
+    /// "3 nodes, 2 edges; Start → API → DB"
+    pub summary: String,
+    /// Label legend entries like `[1] request`, when `legend_position` is
+    /// `Separate`; they are left out of `output`
+    pub legend: Vec<String>,
+    /// Numbered node ids when `number_nodes` is set: node `n` is
+    /// `node_numbers[n - 1]`
+    pub node_numbers: Vec<NodeId>,
+    /// Top-left grid position of each graph node, keyed by
+    /// [`Node::stable_id`], for animating changes between renders or
+    /// passing back as `RenderOptions::previous_positions`
+    pub node_positions: HashMap<String, (usize, usize)>,
+}
+
+impl RenderResult {
+    /// `output` in a form that stays stable across cosmetic rendering
+    /// changes, for snapshot tests: color codes removed, trailing
+    /// whitespace and blank lines trimmed, and label legend markers
+    /// renumbered in the order they appear in the diagram.
+    ///
+    /// # Example
+    /// ```
+    /// use graphs_tui::{render_mermaid_to_tui, RenderOptions};
+    ///
+    /// let options = RenderOptions { colors: true, ..Default::default() };
+    /// let result = render_mermaid_to_tui("flowchart LR\nA --> B", options).unwrap();
+    /// assert!(!result.normalized().contains('\x1b'));
+    /// ```
+    pub fn normalized(&self) -> String {
+        let plain = crate::text::strip_ansi(&self.output);
+        let mut lines: Vec<String> = plain.lines().map(|l| l.trim_end().to_string()).collect();
+        while lines.last().is_some_and(|l| l.is_empty()) {
+            lines.pop();
+        }
+        if let Some(legend) = lines.iter().position(|l| l == "Labels:") {
+            renumber_legend(&mut lines, legend);
+        }
+        lines.join("\n")
+    }
+
+    /// Lines of `output`, one per row, for pagers that show a diagram a
+    /// screen at a time
+    pub fn rows(&self) -> impl Iterator<Item = String> + '_ {
+        self.output.lines().map(String::from)
+    }
+
+    /// The `width` × `height` window of `output` whose top-left corner is
+    /// column `x` of row `y`, so a TUI can scroll a diagram larger than
+    /// its viewport. Rows are cut at display columns and keep their color
+    /// codes; rows and columns past the drawing are left out rather than
+    /// padded.
+    ///
+    /// # Example
+    /// ```
+    /// use graphs_tui::{render_mermaid_to_tui, RenderOptions};
+    ///
+    /// let result = render_mermaid_to_tui("flowchart LR\nA --> B", RenderOptions::default()).unwrap();
+    /// let region = result.render_region(0, 0, 3, 2);
+    /// assert_eq!(region, "┌──\n│ A");
+    /// ```
+    pub fn render_region(&self, x: usize, y: usize, width: usize, height: usize) -> String {
+        let ending = if self.output.contains("\r\n") {
+            "\r\n"
+        } else {
+            "\n"
+        };
+        self.output
+            .lines()
+            .skip(y)
+            .take(height)
+            .map(|line| crate::text::slice_columns(line, x, width))
+            .collect::<Vec<_>>()
+            .join(ending)
+    }
+}
+
+/// Renumber the `[n]` markers listed under the `Labels:` line at `legend`
+/// by first appearance in the lines above it, and list them in that order
+fn renumber_legend(lines: &mut [String], legend: usize) {
+    let end = lines[legend + 1..]
+        .iter()
+        .position(|l| !l.starts_with("  ["))
+        .map_or(lines.len(), |i| legend + 1 + i);
+    let entries: Vec<(String, String)> = lines[legend + 1..end]
+        .iter()
+        .filter_map(|l| {
+            let (marker, label) = l.trim_start().split_once(' ')?;
+            Some((marker.to_string(), label.to_string()))
+        })
+        .collect();
+
+    let mut order: Vec<&str> = Vec::new();
+    for line in &lines[..legend] {
+        let mut rest = line.as_str();
+        while let Some(open) = rest.find('[') {
+            let Some(close) = rest[open..].find(']') else {
+                break;
+            };
+            let marker = &rest[open..open + close + 1];
+            if let Some((m, _)) = entries.iter().find(|(m, _)| m == marker) {
+                if !order.contains(&m.as_str()) {
+                    order.push(m);
+                }
+            }
+            rest = &rest[open + close + 1..];
+        }
+    }
+    for (marker, _) in &entries {
+        if !order.contains(&marker.as_str()) {
+            order.push(marker);
+        }
+    }
+    let renamed: HashMap<&str, String> = order
+        .iter()
+        .enumerate()
+        .map(|(i, m)| (*m, format!("[{}]", i + 1)))
+        .collect();
+
+    let body: Vec<String> = lines[..legend]
+        .iter()
+        .map(|line| {
+            let mut out = String::with_capacity(line.len());
+            let mut rest = line.as_str();
+            while let Some(open) = rest.find('[') {
+                out.push_str(&rest[..open]);
+                let marker = rest[open..].find(']').map(|c| &rest[open..open + c + 1]);
+                match marker.and_then(|m| renamed.get(m).map(|new| (m, new))) {
+                    Some((old, new)) => {
+                        out.push_str(new);
+                        rest = &rest[open + old.len()..];
+                    }
+                    None => {
+                        out.push('[');
+                        rest = &rest[open + 1..];
+                    }
+                }
+            }
+            out.push_str(rest);
+            out
+        })
+        .collect();
+    let listed: Vec<String> = order
+        .iter()
+        .filter_map(|m| entries.iter().find(|(e, _)| e == m))
+        .map(|(m, label)| format!("  {} {}", renamed[m.as_str()], label))
+        .collect();
+
+    for (line, new) in lines.iter_mut().zip(body) {
+        *line = new;
+    }
+    for (line, new) in lines[legend + 1..end].iter_mut().zip(listed) {
+        *line = new;
+    }
+}
+
+/// Output of a renderer's draw step, before the shared width handling
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Drawing {
+    /// The diagram itself, fitted to `max_width` when finished
+    pub body: String,
+    /// Lines appended below the body as written, like tooltip footnotes
+    pub footer: Vec<String>,
+    /// Label legend entries like `[1] request`, placed by
+    /// `RenderOptions::legend_position` under `legend_heading`
+    pub legend: Vec<String>,
+}
+
+impl From<String> for Drawing {
+    fn from(body: String) -> Self {
+        Self {
+            body,
+            footer: Vec::new(),
+            legend: Vec::new(),
+        }
+    }
+}
+
+/// Source lines of a diagram's nodes and edges, so a previewer can jump
+/// from a rendered element back to the line that produced it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    /// 1-based line of the first mention of each node, container and
+    /// participant
+    pub nodes: HashMap<NodeId, usize>,
+    /// 1-based line of each edge in source order, keyed `from -> to` like
+    /// [`Symbol::name`]
+    pub edges: Vec<(String, usize)>,
+}
+
+impl SourceMap {
+    /// Build the map from [`extract_symbols`](crate::extract_symbols) output
+    pub(crate) fn from_symbols(symbols: &[Symbol]) -> Self {
+        let mut map = Self::default();
+        for symbol in symbols {
+            match symbol.kind {
+                SymbolKind::Edge => map.edges.push((symbol.name.clone(), symbol.line)),
+                _ => {
+                    map.nodes.entry(symbol.name.clone()).or_insert(symbol.line);
+                }
+            }
+        }
+        map
+    }
+
+    /// Line of the first mention of a node, container or participant
+    pub fn node_line(&self, id: &str) -> Option<usize> {
+        self.nodes.get(id).copied()
+    }
+
+    /// Line of the first edge from `from` to `to`
+    pub fn edge_line(&self, from: &str, to: &str) -> Option<usize> {
+        let name = format!("{} -> {}", from, to);
+        self.edges
+            .iter()
+            .find(|(edge, _)| *edge == name)
+            .map(|&(_, line)| line)
+    }
 }
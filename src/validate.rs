@@ -0,0 +1,374 @@
+//! Lint-only validation: parse a diagram and report problems with fixes
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+
+use crate::d2_parser::{self, parse_d2};
+use crate::error::MermaidError;
+use crate::parser::{self, parse_mermaid};
+use crate::pie_parser::parse_pie_chart;
+use crate::seq_parser::parse_sequence_diagram;
+use crate::state_parser::{self, parse_state_diagram_with_warnings};
+use crate::types::{DiagramWarning, Graph, SourceMap};
+use crate::{detect_format, DiagramFormat};
+
+/// How serious a [`Diagnostic`] is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The diagram can't be rendered
+    Error,
+    /// The diagram renders, but probably not as intended
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A problem found by [`validate`], with a suggested fix
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// 1-based source line, when known
+    pub line: Option<usize>,
+    pub message: String,
+    pub suggestion: Option<String>,
+}
+
+impl Diagnostic {
+    fn warning(line: Option<usize>, message: String, suggestion: &str) -> Self {
+        Self {
+            severity: Severity::Warning,
+            line,
+            message,
+            suggestion: Some(suggestion.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.severity)?;
+        if let Some(line) = self.line {
+            write!(f, "line {}: ", line)?;
+        }
+        write!(f, "{}", self.message)?;
+        if let Some(sug) = &self.suggestion {
+            write!(f, " (Suggestion: {})", sug)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<MermaidError> for Diagnostic {
+    fn from(err: MermaidError) -> Self {
+        match err {
+            MermaidError::ParseError {
+                line,
+                message,
+                suggestion,
+            } => Self {
+                severity: Severity::Error,
+                line: Some(line),
+                message,
+                suggestion,
+            },
+            other => Self {
+                severity: Severity::Error,
+                line: None,
+                message: other.to_string(),
+                suggestion: None,
+            },
+        }
+    }
+}
+
+/// Lint a diagram without laying it out or rendering it.
+///
+/// Reports parse errors, unsupported features, subgraph members that are
+/// never defined, duplicate edges, empty labels and nodes that can't be
+/// reached from any entry node. An empty result means the diagram is clean.
+///
+/// Dispatches by `lang`: `"d2"` → D2 parser, anything else → Mermaid
+/// auto-detect.
+///
+/// # Example
+/// ```
+/// use graphs_tui::{validate, Severity};
+///
+/// let diagnostics = validate("mermaid", "flowchart LR\nA --> B\nA --> B");
+/// assert_eq!(diagnostics.len(), 1);
+/// assert_eq!(diagnostics[0].severity, Severity::Warning);
+/// assert!(diagnostics[0].message.contains("Duplicate edge"));
+/// ```
+pub fn validate(lang: &str, code: &str) -> Vec<Diagnostic> {
+    let format = match lang.to_lowercase().as_str() {
+        "d2" => DiagramFormat::D2,
+        _ => detect_format(code),
+    };
+    let parsed = match format {
+        DiagramFormat::D2 => parse_d2(code).map(|r| (Some(r.graph), r.warnings)),
+        DiagramFormat::Mermaid => parse_mermaid(code).map(|g| (Some(g), Vec::new())),
+        DiagramFormat::StateDiagram => {
+            parse_state_diagram_with_warnings(code).map(|(g, w)| (Some(g), w))
+        }
+        DiagramFormat::SequenceDiagram => parse_sequence_diagram(code).map(|d| (None, d.warnings)),
+        DiagramFormat::PieChart => parse_pie_chart(code).map(|c| (None, c.warnings)),
+    };
+    let (graph, warnings) = match parsed {
+        Ok(parsed) => parsed,
+        Err(err) => return vec![err.into()],
+    };
+
+    let mut diagnostics: Vec<Diagnostic> = warnings
+        .into_iter()
+        .filter_map(|w| match w {
+            DiagramWarning::UnsupportedFeature { feature, line } => Some(Diagnostic {
+                severity: Severity::Warning,
+                line: Some(line),
+                message: format!("Unsupported D2 feature '{}' is ignored", feature),
+                suggestion: Some("Remove it, or render with the d2 CLI".to_string()),
+            }),
+            DiagramWarning::IgnoredStatement { statement, line } => Some(Diagnostic {
+                severity: Severity::Warning,
+                line: Some(line),
+                message: format!("'{}' statements are ignored", statement),
+                suggestion: Some("Remove it; it has no effect in a terminal".to_string()),
+            }),
+            DiagramWarning::LineSkipped { line, message } => Some(Diagnostic {
+                severity: Severity::Warning,
+                line: Some(line),
+                message: format!("{}, so the line is left out", message),
+                suggestion: None,
+            }),
+            _ => None,
+        })
+        .collect();
+    if let Some(graph) = graph {
+        let symbols = match format {
+            DiagramFormat::D2 => d2_parser::symbols(code),
+            DiagramFormat::StateDiagram => state_parser::symbols(code),
+            _ => parser::symbols(code),
+        };
+        diagnostics.extend(lint_graph(&graph, &SourceMap::from_symbols(&symbols)));
+    }
+    diagnostics
+}
+
+/// Structural checks on a parsed graph, placed on the lines `source` gives
+fn lint_graph(graph: &Graph, source: &SourceMap) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let subgraph_ids: HashSet<&str> = graph.subgraphs.iter().map(|sg| sg.id.as_str()).collect();
+    let defined = |id: &str| graph.nodes.contains_key(id) || subgraph_ids.contains(id);
+
+    for sg in &graph.subgraphs {
+        for member in sg.nodes.iter().filter(|m| !defined(m)) {
+            diagnostics.push(Diagnostic::warning(
+                source
+                    .node_line(member)
+                    .or_else(|| source.node_line(&sg.id)),
+                format!(
+                    "Subgraph '{}' lists member '{}', which is not defined",
+                    sg.id, member
+                ),
+                &format!("Declare '{}' or remove it from '{}'", member, sg.id),
+            ));
+        }
+    }
+
+    let mut seen = HashSet::new();
+    // How many edges between the same ends came before, to find this
+    // one's line among theirs
+    let mut repeats: HashMap<(&str, &str), usize> = HashMap::new();
+    for edge in &graph.edges {
+        let repeat = repeats.entry((&edge.from, &edge.to)).or_default();
+        let name = format!("{} -> {}", edge.from, edge.to);
+        let line = source
+            .edges
+            .iter()
+            .filter(|(n, _)| *n == name)
+            .nth(*repeat)
+            .map(|&(_, line)| line)
+            .or_else(|| source.edge_line(&edge.from, &edge.to));
+        *repeat += 1;
+        let key = (&edge.from, &edge.to, &edge.label, edge.style);
+        if !seen.insert(key) {
+            diagnostics.push(Diagnostic::warning(
+                line,
+                format!("Duplicate edge {} -> {}", edge.from, edge.to),
+                "Remove the repeated edge, or label the two differently",
+            ));
+        }
+        if edge.label.as_ref().is_some_and(|l| l.trim().is_empty()) {
+            diagnostics.push(Diagnostic::warning(
+                line,
+                format!("Edge {} -> {} has an empty label", edge.from, edge.to),
+                "Write the label text or drop the empty label",
+            ));
+        }
+    }
+
+    let mut ids: Vec<&String> = graph.nodes.keys().collect();
+    ids.sort();
+    for id in &ids {
+        if graph.nodes[*id].label.trim().is_empty() {
+            diagnostics.push(Diagnostic::warning(
+                source.node_line(id),
+                format!("Node '{}' has an empty label", id),
+                "Write the label text or drop the empty shape brackets",
+            ));
+        }
+    }
+
+    for id in unreachable_nodes(graph) {
+        diagnostics.push(Diagnostic::warning(
+            source.node_line(id),
+            format!("Node '{}' can't be reached from any entry node", id),
+            &format!("Add an edge into '{}' from the rest of the diagram", id),
+        ));
+    }
+
+    diagnostics
+}
+
+/// Nodes that no path from an entry node (one without incoming edges)
+/// reaches, sorted. Nothing is reported when the graph has no such path at
+/// all, as in a diagram that is a single cycle.
+fn unreachable_nodes(graph: &Graph) -> Vec<&str> {
+    let mut successors: HashMap<&str, Vec<&str>> = HashMap::new();
+    let mut has_incoming = HashSet::new();
+    for edge in &graph.edges {
+        successors
+            .entry(edge.from.as_str())
+            .or_default()
+            .push(edge.to.as_str());
+        has_incoming.insert(edge.to.as_str());
+    }
+
+    // Edge sources include D2 containers, which aren't in `graph.nodes`
+    let mut queue: VecDeque<&str> = successors
+        .keys()
+        .copied()
+        .filter(|id| !has_incoming.contains(id))
+        .collect();
+    if queue.is_empty() {
+        return Vec::new();
+    }
+    let mut reached: HashSet<&str> = queue.iter().copied().collect();
+    while let Some(id) = queue.pop_front() {
+        for &next in successors.get(id).into_iter().flatten() {
+            if reached.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+
+    let mut unreachable: Vec<&str> = graph
+        .nodes
+        .keys()
+        .map(String::as_str)
+        .filter(|id| has_incoming.contains(id) && !reached.contains(id))
+        .collect();
+    unreachable.sort();
+    unreachable
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(lang: &str, code: &str) -> Vec<String> {
+        validate(lang, code)
+            .into_iter()
+            .map(|d| d.message)
+            .collect()
+    }
+
+    #[test]
+    fn test_validate_clean_diagram() {
+        assert!(validate("mermaid", "flowchart LR\nA --> B --> C").is_empty());
+        assert!(validate("d2", "a -> b\nb -> a").is_empty());
+        assert!(validate("d2", "box {\n  x\n}\nbox -> y").is_empty());
+        assert!(validate("mermaid", "sequenceDiagram\nAlice->>Bob: Hi").is_empty());
+    }
+
+    #[test]
+    fn test_validate_parse_error_has_line() {
+        let diagnostics = validate("mermaid", "flowchart LR\nA --> B\nA[bad --> C");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(diagnostics[0].line, Some(3));
+        assert!(diagnostics[0].to_string().starts_with("error: line 3: "));
+    }
+
+    #[test]
+    fn test_validate_duplicate_edges_and_empty_labels() {
+        assert_eq!(
+            messages(
+                "mermaid",
+                "flowchart LR\nA --> B\nA -->|x| B\nA --> B\nC[ ] -->|| A"
+            ),
+            vec![
+                "Duplicate edge A -> B",
+                "Edge C -> A has an empty label",
+                "Node 'C' has an empty label",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_lint_diagnostics_have_lines() {
+        let lines = |lang: &str, code: &str| -> Vec<Option<usize>> {
+            validate(lang, code).into_iter().map(|d| d.line).collect()
+        };
+        assert_eq!(
+            lines(
+                "mermaid",
+                "flowchart LR\nA --> B\nA -->|x| B\nA --> B\nC[ ] -->|| A"
+            ),
+            vec![Some(4), Some(5), Some(5)]
+        );
+        assert_eq!(
+            lines("mermaid", "flowchart LR\nStart --> A\nB --> C\nC --> B"),
+            vec![Some(3), Some(3)]
+        );
+        assert_eq!(lines("d2", "box {\n  a -> b\n}\nbox.a: null")[0], Some(2));
+    }
+
+    #[test]
+    fn test_validate_undefined_subgraph_member() {
+        let diagnostics = validate("d2", "box {\n  a -> b\n}\nbox.a: null");
+        assert_eq!(
+            diagnostics[0].message,
+            "Subgraph 'box' lists member 'a', which is not defined"
+        );
+        assert_eq!(
+            diagnostics[0].suggestion.as_deref(),
+            Some("Declare 'a' or remove it from 'box'")
+        );
+    }
+
+    #[test]
+    fn test_validate_unreachable_nodes() {
+        assert_eq!(
+            messages("mermaid", "flowchart LR\nStart --> A\nB --> C\nC --> B"),
+            vec![
+                "Node 'B' can't be reached from any entry node",
+                "Node 'C' can't be reached from any entry node",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_unsupported_d2_feature() {
+        let diagnostics = validate("d2", "a -> b\n*.style.fill: red");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line, Some(2));
+        assert!(diagnostics[0].message.contains("'glob'"));
+    }
+}
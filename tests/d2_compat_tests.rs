@@ -0,0 +1,259 @@
+//! D2 compatibility tests
+//!
+//! Examples adapted from the D2 language tour, checked against the graph
+//! the parser builds. Rendering details are covered by the snapshot tests.
+use graphs_tui::{
+    parse_any, render_d2_to_tui, Arrowhead, DiagramWarning, Direction, EdgeStyle, Graph, NodeShape,
+    ParsedDiagram, RenderOptions,
+};
+
+fn parse(input: &str) -> Graph {
+    match parse_any(input.as_bytes()).unwrap() {
+        ParsedDiagram::D2(graph) => graph,
+        _ => panic!("expected D2 input"),
+    }
+}
+
+fn edge(graph: &Graph, from: &str, to: &str) -> bool {
+    graph.edges.iter().any(|e| e.from == from && e.to == to)
+}
+
+/// Tour: shapes declared with and without labels
+#[test]
+fn test_compat_shapes() {
+    let graph = parse(
+        r#"imAShape
+im_a_shape
+im a shape
+i'm a shape
+a-shape: "A shape"
+pg: PostgreSQL
+Cloud: my cloud
+Cloud.shape: cloud
+"#,
+    );
+    for id in ["imAShape", "im_a_shape", "im a shape", "i'm a shape"] {
+        assert_eq!(graph.nodes[id].label, id);
+    }
+    assert_eq!(graph.nodes["a-shape"].label, "A shape");
+    assert_eq!(graph.nodes["pg"].label, "PostgreSQL");
+    assert_eq!(graph.nodes["Cloud"].label, "my cloud");
+    assert_eq!(graph.nodes["Cloud"].shape, NodeShape::Cloud);
+}
+
+/// Tour: every connection operator
+#[test]
+fn test_compat_connections() {
+    let graph = parse("Write Replica Canada <-> Write Replica Australia\nRead Replica <- Master\nx -- y\nsuper long shape id here -> super long shape id even longer here");
+    assert!(edge(
+        &graph,
+        "Write Replica Canada",
+        "Write Replica Australia"
+    ));
+    assert!(edge(&graph, "Master", "Read Replica"));
+    assert!(graph
+        .edges
+        .iter()
+        .any(|e| e.from == "x" && e.to == "y" && e.style == EdgeStyle::Line));
+    assert!(edge(
+        &graph,
+        "super long shape id here",
+        "super long shape id even longer here"
+    ));
+}
+
+/// Tour: repeated connections stay separate edges
+#[test]
+fn test_compat_repeated_connections() {
+    let graph = parse("Database -> S3: backup\nDatabase -> S3\nDatabase -> S3: backup");
+    assert_eq!(graph.edges.len(), 3);
+    assert_eq!(graph.edges[1].label, None);
+}
+
+/// Tour: chains with a label on each hop
+#[test]
+fn test_compat_connection_chain() {
+    let graph = parse("High Mem Instance -> EC2 <- High CPU Instance: Hosted By");
+    assert!(edge(&graph, "High Mem Instance", "EC2"));
+    assert!(edge(&graph, "High CPU Instance", "EC2"));
+    assert_eq!(graph.edges[1].label.as_deref(), Some("Hosted By"));
+}
+
+/// Tour: containers, dotted paths and labels set from inside
+#[test]
+fn test_compat_containers() {
+    let graph = parse(
+        r#"server
+# Declares a shape inside of another shape
+server.process
+
+# Can declare the container and child in same line
+im a parent.im a child
+
+# Since connections can also declare keys, this works too
+apartment.Bedroom.Bathroom -> office.Spare Room.Bathroom: Portal
+
+clouds: {
+  aws: {
+    label: Amazon Web Services
+    load_balancer -> api
+  }
+  gcloud: Google Cloud {
+    auth -> db
+  }
+  gcloud -> aws
+}
+"#,
+    );
+    assert!(graph.subgraphs.iter().any(|sg| sg.id == "server"));
+    assert!(graph.nodes.contains_key("process"));
+    assert!(graph.nodes.contains_key("im a child"));
+    assert!(edge(&graph, "Bathroom", "Bathroom"));
+
+    let aws = graph.subgraphs.iter().find(|sg| sg.id == "aws").unwrap();
+    assert_eq!(aws.label, "Amazon Web Services");
+    assert_eq!(aws.parent.as_deref(), Some("clouds"));
+    let gcloud = graph.subgraphs.iter().find(|sg| sg.id == "gcloud").unwrap();
+    assert_eq!(gcloud.label, "Google Cloud");
+    assert!(edge(&graph, "load_balancer", "api"));
+    assert!(edge(&graph, "gcloud", "aws"));
+}
+
+/// Tour: sql_table fields with constraints
+#[test]
+fn test_compat_sql_table() {
+    let graph = parse(
+        r#"users: {
+  shape: sql_table
+  id: int {constraint: primary_key}
+  name: "varchar(255)"
+  email: string; last_login: datetime
+}
+"#,
+    );
+    let users = &graph.nodes["users"];
+    assert_eq!(users.shape, NodeShape::Table);
+    let fields: Vec<(&str, Option<&str>, Option<&str>)> = users
+        .fields
+        .iter()
+        .map(|f| {
+            (
+                f.name.as_str(),
+                f.type_info.as_deref(),
+                f.constraint.as_deref(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        fields,
+        vec![
+            ("id", Some("int"), Some("primary_key")),
+            ("name", Some("varchar(255)"), None),
+            ("email", Some("string"), None),
+            ("last_login", Some("datetime"), None),
+        ]
+    );
+}
+
+/// Tour: connection maps with arrowheads and styles
+#[test]
+fn test_compat_connection_map() {
+    let graph = parse(
+        r#"a -> b: To err is human, to moo bovine {
+  source-arrowhead: 1
+  target-arrowhead: * {
+    shape: diamond
+  }
+}
+c -> d: {
+  style.stroke-dash: 3
+  target-arrowhead.shape: cf-many
+}
+"#,
+    );
+    assert_eq!(
+        graph.edges[0].label.as_deref(),
+        Some("To err is human, to moo bovine")
+    );
+    assert_eq!(graph.edges[0].target_arrowhead, Some(Arrowhead::Diamond));
+    assert_eq!(graph.edges[1].style, EdgeStyle::DottedArrow);
+    assert_eq!(graph.edges[1].target_arrowhead, Some(Arrowhead::CfMany));
+}
+
+/// Tour: direction, styles and null deletion
+#[test]
+fn test_compat_direction_style_null() {
+    let graph = parse(
+        r##"direction: right
+x: {style.fill: "#f4a261"}
+x.style.stroke: red
+y -> x
+z
+z: null
+"##,
+    );
+    assert_eq!(graph.direction, Direction::LR);
+    assert!(!graph.nodes.contains_key("style"));
+    assert!(!graph.nodes.contains_key("z"));
+    assert!(edge(&graph, "y", "x"));
+}
+
+/// Tour: markdown and code block strings
+#[test]
+fn test_compat_block_strings() {
+    let graph = parse(
+        r#"explanation: |md
+  # I can do headers
+  - lists
+|
+code: |||ts
+  const a = b || c
+|||
+explanation -> code
+"#,
+    );
+    assert_eq!(
+        graph.nodes["explanation"].label,
+        "# I can do headers\n- lists"
+    );
+    assert_eq!(graph.nodes["code"].label, "const a = b || c");
+    assert!(edge(&graph, "explanation", "code"));
+}
+
+/// Braces, semicolons and arrows inside quotes belong to the string
+#[test]
+fn test_compat_quoted_punctuation() {
+    let graph = parse(
+        r#"a: "x{y}"
+"a;b" -> c
+"d -> e": 'label: {z}'
+c -> "d -> e": "hop; one"
+"#,
+    );
+    assert_eq!(graph.nodes["a"].label, "x{y}");
+    assert!(edge(&graph, "a;b", "c"));
+    assert_eq!(graph.nodes["d -> e"].label, "label: {z}");
+    assert_eq!(graph.edges.len(), 2);
+    assert_eq!(graph.edges[1].label.as_deref(), Some("hop; one"));
+}
+
+/// Globs, imports and layers are skipped with a warning
+#[test]
+fn test_compat_unsupported_features_warn() {
+    let result = render_d2_to_tui(
+        "*.style.fill: red\nx: @x.d2\n...@base\nlayers: {\n  inner: { a -> b }\n}\nc -> d",
+        RenderOptions::default(),
+    )
+    .unwrap();
+    let features: Vec<&str> = result
+        .warnings
+        .iter()
+        .filter_map(|w| match w {
+            DiagramWarning::UnsupportedFeature { feature, .. } => Some(feature.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(features, vec!["glob", "import", "import", "layers"]);
+    assert!(result.output.contains('c'));
+    assert!(!result.output.contains("inner"));
+}
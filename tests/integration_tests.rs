@@ -1,6 +1,15 @@
 use graphs_tui::{
-    detect_format, render_d2_to_tui, render_diagram, render_mermaid_to_tui, render_pie_chart,
-    render_sequence_diagram, render_state_diagram, DiagramFormat, MermaidError, RenderOptions,
+    compute_layout, compute_layout_with_options, detect_format, diff_lines, parse_any,
+    parse_diagram, render, render_d2_to_tui, render_diagram, render_frames, render_mermaid_to_tui,
+    render_parsed, render_partial, render_pie_chart, render_rich, render_sequence_diagram,
+    render_state_diagram, render_with_error_overlay, render_with_path, Alignment, DiagramFormat,
+    Direction, EdgeLabels, EdgeRouting, LabelAlong, LabelPlacement, LabelSide, Layering,
+    LayoutAlgorithm, LegendPosition, LineEnding, MermaidError, ParsedDiagram, RenderMode,
+    RenderOptions, WidthStrategy,
+};
+use graphs_tui::{
+    register_shape, Budget, CharSet, DiagramRenderer, DiagramWarning, Drawing, NodeShape, Registry,
+    RenderHooks, RenderResult, ShapeRenderer,
 };
 
 #[test]
@@ -171,6 +180,26 @@ E --> F([Stadium])"#;
     insta::assert_snapshot!(result.output);
 }
 
+/// Test double circle and asymmetric flag shapes
+#[test]
+fn test_double_circle_and_asymmetric_shapes() {
+    let input = "flowchart LR\nStart(((Start))) --> Flag>Notify]\nFlag --> Stop(((Stop)))";
+    let result = render_mermaid_to_tui(input, RenderOptions::default()).unwrap();
+    assert!(result.output.contains("││Start││"));
+    assert!(result.output.contains(" > Notify"));
+    insta::assert_snapshot!(result.output);
+}
+
+/// Test compartment nodes draw a separator between label sections
+#[test]
+fn test_compartment_node() {
+    let input = "flowchart TB\nAccount[Account<br/>-balance: u64<br/>+deposit()<br/>+withdraw()]:::compartment --> Ledger";
+    let result = render_mermaid_to_tui(input, RenderOptions::default()).unwrap();
+    assert_eq!(result.output.matches('├').count(), 3);
+    assert!(result.output.contains("│+deposit()   │"));
+    insta::assert_snapshot!(result.output);
+}
+
 /// Test subgraph parsing (layout not yet implemented)
 #[test]
 fn test_subgraph_parsing() {
@@ -185,6 +214,18 @@ API --> DB"#;
     insta::assert_snapshot!(result.output);
 }
 
+/// A node above a subgraph starts on the first row, and only the gap the
+/// subgraph's border falls in is widened
+#[test]
+fn test_subgraph_below_outside_node() {
+    let input = "flowchart TB\nI --> R\nsubgraph X\nR --> P\nend";
+    let result = render_mermaid_to_tui(input, RenderOptions::default()).unwrap();
+    let lines: Vec<&str> = result.output.lines().collect();
+    assert!(lines[0].contains('┌'));
+    assert!(!result.output.contains("╪X") && !result.output.contains("X╪"));
+    insta::assert_snapshot!(result.output);
+}
+
 /// Test hexagon shape
 #[test]
 fn test_hexagon_shape() {
@@ -272,6 +313,100 @@ db -> circle_node
     insta::assert_snapshot!(result.output);
 }
 
+/// Test D2 oval shape renders distinctly from a rounded rectangle
+#[test]
+fn test_d2_oval_shape() {
+    let input = "start: Start\nstart.shape: oval\nend: End\nend.shape: ellipse\nstart -> end";
+    let result = render_d2_to_tui(input, RenderOptions::default()).unwrap();
+    assert!(result.output.contains("( Start )"));
+    insta::assert_snapshot!(result.output);
+}
+
+/// Test D2 package, step, callout and diamond shapes
+#[test]
+fn test_d2_package_step_callout_diamond() {
+    let input = r#"
+lib: Library
+lib.shape: package
+build: Build
+build.shape: step
+check: Passed?
+check.shape: diamond
+note: Ship it
+note.shape: callout
+lib -> build -> check -> note
+"#;
+    let result = render_d2_to_tui(input, RenderOptions::default()).unwrap();
+    // The diamond label sits inside the rhombus, between its side points
+    assert!(result
+        .output
+        .lines()
+        .any(|l| l.trim().starts_with('<') && l.contains(" Passed? ") && l.ends_with('>')));
+    insta::assert_snapshot!(result.output);
+}
+
+/// Test D2 block string rendered verbatim, lines left-aligned as a block
+#[test]
+fn test_d2_block_string_ascii_art() {
+    let input = "server: |\n  .-----.\n  | [_] |\n  |  o  |\n  '--'\n    ||\n|\nclient -> server";
+    let result = render_d2_to_tui(input, RenderOptions::default()).unwrap();
+    assert!(result.output.contains("│'--'   │"));
+    assert!(result.output.contains("│  ||   │"));
+    insta::assert_snapshot!(result.output);
+}
+
+/// Test D2 arrowhead blocks draw crow's-foot terminators
+#[test]
+fn test_d2_crows_foot_arrowheads() {
+    let input = r#"
+users -> orders: places {
+  source-arrowhead: {
+    shape: cf-one
+  }
+  target-arrowhead.shape: cf-many
+}
+orders -> items: {target-arrowhead.shape: diamond}
+"#;
+    let result = render_d2_to_tui(input, RenderOptions::default()).unwrap();
+    assert!(result.output.contains('┼'));
+    assert!(result.output.contains('∧'));
+    assert!(result.output.contains('◇'));
+    assert!(!result.output.contains("arrowhead"));
+    insta::assert_snapshot!(result.output);
+}
+
+/// Test D2 edge with a label and an inline style block on one line
+#[test]
+fn test_d2_edge_label_with_style_block() {
+    let input = "A -> B: retry {style.stroke-dash: 3}";
+    let result = render_d2_to_tui(input, RenderOptions::default()).unwrap();
+    assert!(result.output.contains("retry"));
+    assert!(result.output.contains('·'));
+    assert!(!result.output.contains("stroke"));
+}
+
+/// Test escaped quotes and line breaks in quoted D2 and Mermaid labels
+#[test]
+fn test_quoted_label_escapes() {
+    let d2 = render_d2_to_tui(
+        r#"a: "say \"hi\"\nthen go"
+a -> b"#,
+        RenderOptions::default(),
+    )
+    .unwrap();
+    let mermaid = render_mermaid_to_tui(
+        r#"flowchart TB
+a["say \"hi\"\nthen go"] --> b"#,
+        RenderOptions::default(),
+    )
+    .unwrap();
+    for output in [&d2.output, &mermaid.output] {
+        assert!(output.contains("│say \"hi\"│"));
+        assert!(output.contains("│then go │"));
+    }
+    insta::assert_snapshot!(d2.output);
+}
+
 /// Test D2 containers
 #[test]
 fn test_d2_containers() {
@@ -427,6 +562,35 @@ fn test_state_diagram_v1() {
     insta::assert_snapshot!(result.output);
 }
 
+/// Test state transitions with guards and actions; long labels wrap
+#[test]
+fn test_state_diagram_guards_and_actions() {
+    let input = r#"stateDiagram-v2
+    Idle --> Running: start [ready] / init()
+    Running --> Done: stop [queue empty and no pending jobs left] / flush()
+"#;
+    let result = render_state_diagram(input, RenderOptions::default()).unwrap();
+    assert!(result.output.contains("start [ready] / init()"));
+    assert!(result.output.contains("pending jobs left]"));
+    insta::assert_snapshot!(result.output);
+}
+
+/// Test history pseudostates render as circled H nodes
+#[test]
+fn test_state_diagram_history() {
+    let input = r#"stateDiagram-v2
+    state Editing {
+        [*] --> Typing
+        Typing --> [H]
+    }
+    [H*] --> Saved
+"#;
+    let result = render_state_diagram(input, RenderOptions::default()).unwrap();
+    assert!(result.output.contains("( H )"));
+    assert!(result.output.contains("(H* )"));
+    insta::assert_snapshot!(result.output);
+}
+
 // ============================================
 // Pie Chart Tests (TDD - write failing tests first)
 // ============================================
@@ -584,6 +748,19 @@ fn test_d2_document_shape() {
     insta::assert_snapshot!(result.output);
 }
 
+// ============================================
+// D2 Tooltip Tests
+// ============================================
+
+/// Tooltips are numbered next to their labels and listed under the diagram
+#[test]
+fn test_d2_tooltip_footnotes() {
+    let input = "api: API {tooltip: owned by payments}\ndb.tooltip: backed up nightly\napi -> db";
+    let result = render_d2_to_tui(input, RenderOptions::default()).unwrap();
+    assert!(result.warnings.is_empty());
+    insta::assert_snapshot!(result.output);
+}
+
 // ============================================
 // Multi-line Label Tests
 // ============================================
@@ -661,3 +838,2044 @@ fn test_sequence_diagram_activation() {
     assert!(result.output.contains('┃'));
     insta::assert_snapshot!(result.output);
 }
+
+// ============================================
+// Size / Time Budget Tests
+// ============================================
+
+#[test]
+fn test_max_nodes_budget() {
+    let input = "flowchart LR\nA --> B\nB --> C\nC --> D";
+    let options = RenderOptions {
+        max_nodes: Some(3),
+        ..Default::default()
+    };
+    let result = render_mermaid_to_tui(input, options);
+    assert!(matches!(
+        result,
+        Err(MermaidError::TooLarge {
+            limit: 3,
+            actual: 4,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_max_edges_budget_d2_and_sequence() {
+    let options = RenderOptions {
+        max_edges: Some(1),
+        ..Default::default()
+    };
+    assert!(matches!(
+        render_d2_to_tui("a -> b\nb -> c", options.clone()),
+        Err(MermaidError::TooLarge { .. })
+    ));
+    let seq = "sequenceDiagram\n    A->>B: one\n    B->>A: two";
+    assert!(matches!(
+        render_sequence_diagram(seq, options),
+        Err(MermaidError::TooLarge { .. })
+    ));
+}
+
+#[test]
+fn test_budget_within_limits_renders() {
+    let input = "flowchart LR\nA --> B";
+    let options = RenderOptions {
+        max_nodes: Some(2),
+        max_edges: Some(1),
+        time_budget: Some(std::time::Duration::from_secs(10)),
+        ..Default::default()
+    };
+    let result = render_mermaid_to_tui(input, options).unwrap();
+    assert!(result.output.contains('▶'));
+}
+
+// ============================================
+// parse_any / Fuzz-safety Tests
+// ============================================
+
+#[test]
+fn test_parse_any_dispatches_all_formats() {
+    assert!(matches!(
+        parse_any(b"flowchart LR\nA --> B"),
+        Ok(ParsedDiagram::Flowchart(_))
+    ));
+    assert!(matches!(
+        parse_any(b"stateDiagram-v2\n[*] --> A"),
+        Ok(ParsedDiagram::State(_))
+    ));
+    assert!(matches!(
+        parse_any(b"sequenceDiagram\nA->>B: hi"),
+        Ok(ParsedDiagram::Sequence(_))
+    ));
+    assert!(matches!(
+        parse_any(b"pie\n\"A\" : 1"),
+        Ok(ParsedDiagram::Pie(_))
+    ));
+    assert!(matches!(parse_any(b"a -> b"), Ok(ParsedDiagram::D2(_))));
+}
+
+#[test]
+fn test_parse_any_rejects_invalid_utf8() {
+    let result = parse_any(b"flowchart LR\nA --> \xff");
+    assert!(matches!(
+        result,
+        Err(MermaidError::ParseError { line: 2, .. })
+    ));
+    assert!(matches!(parse_any(b"  \n "), Err(MermaidError::EmptyInput)));
+}
+
+/// Models from parse_diagram can be edited before rendering
+#[test]
+fn test_parse_diagram_then_render_edited_model() {
+    let mut parsed = parse_diagram("flowchart LR\nA[api] --> B[db]").unwrap();
+    if let ParsedDiagram::Flowchart(graph) = &mut parsed {
+        graph.nodes.get_mut("B").unwrap().label = "db 3ms".to_string();
+    }
+    let output = render_parsed(parsed, RenderOptions::default())
+        .unwrap()
+        .output;
+    assert!(output.contains("db 3ms"));
+
+    let mut parsed = parse_diagram("pie\n\"A\" : 1\n\"B\" : 1").unwrap();
+    if let ParsedDiagram::Pie(chart) = &mut parsed {
+        chart.slices[0].value = 3.0;
+    }
+    let output = render_parsed(parsed, RenderOptions::default())
+        .unwrap()
+        .output;
+    assert!(output.contains("75"));
+
+    assert!(matches!(parse_diagram(" "), Err(MermaidError::EmptyInput)));
+}
+
+/// Node metadata survives layout
+#[test]
+fn test_node_metadata_survives_layout() {
+    let ParsedDiagram::D2(mut graph) = parse_diagram("a.link: https://a.io\na -> b").unwrap()
+    else {
+        panic!("expected a D2 diagram");
+    };
+    compute_layout(&mut graph);
+    assert_eq!(graph.nodes["a"].metadata["link"], "https://a.io");
+}
+
+#[test]
+fn test_parse_any_multibyte_keyword_edges() {
+    // Inputs that used to slice through multi-byte characters
+    let inputs = [
+        "flowchart LR\nsubgraph x][\nA --> B",
+        "flowchart LR\nclassDef c fill:#aéééb\nA:::c --> B",
+        "sequenceDiagram\nNote ri\u{212A}ht of A: x\nnoté over A: y\nloopé\nA->>B: z",
+        "sequenceDiagram\n\u{130}ctivate A\nactivate é",
+        "'é': null",
+    ];
+    for input in inputs {
+        let _ = parse_any(input.as_bytes());
+        let _ = render_diagram(input, RenderOptions::default());
+    }
+}
+
+#[test]
+fn test_parse_any_never_panics_on_token_soup() {
+    const TOKENS: &[&str] = &[
+        "flowchart LR\n",
+        "sequenceDiagram\n",
+        "stateDiagram-v2\n",
+        "pie\n",
+        "A",
+        "é",
+        "日本",
+        "👨\u{200d}👩",
+        "\u{212A}",
+        "-->",
+        "->",
+        "<->",
+        "|",
+        "[",
+        "]",
+        "(",
+        ")",
+        "{",
+        "}",
+        ":",
+        ";",
+        "\"",
+        "\n",
+        "Note over ",
+        "activate ",
+        "loop ",
+        "else",
+        "end",
+        "[*]",
+        "state ",
+        "subgraph ",
+        "class ",
+        ":::",
+        "#",
+        "%%",
+        "&",
+        " as ",
+        "shape: ",
+        "\u{301}",
+        "x.y",
+        "null",
+        "3.5",
+        "->>",
+    ];
+    let mut seed: u64 = 0x9E37_79B9_7F4A_7C15;
+    for _ in 0..2000 {
+        let mut input = String::new();
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        for i in 0..(seed % 24) {
+            input.push_str(TOKENS[((seed >> (i % 48)) as usize + i as usize) % TOKENS.len()]);
+        }
+        let _ = parse_any(input.as_bytes());
+    }
+}
+
+// ============================================
+// Full-width / CJK Label Tests
+// ============================================
+
+fn display_widths(output: &str) -> Vec<usize> {
+    output
+        .lines()
+        .map(unicode_width::UnicodeWidthStr::width)
+        .collect()
+}
+
+#[test]
+fn test_japanese_labels_fit_boxes() {
+    let input = "flowchart LR\nA[日本語ラベル] -->|接続| B[終了]";
+    let result = render_mermaid_to_tui(input, RenderOptions::default()).unwrap();
+    let widths = display_widths(&result.output);
+    // Top border, label row and bottom border all line up
+    assert_eq!(widths[0], widths[1]);
+    assert_eq!(widths[1], widths[2]);
+    assert!(result.output.contains("│日本語ラベル│"));
+    insta::assert_snapshot!(result.output);
+}
+
+#[test]
+fn test_emoji_labels_fit_boxes() {
+    let input = "flowchart TB\nA[🚀 Launch] --> B[✅ Done]";
+    let result = render_mermaid_to_tui(input, RenderOptions::default()).unwrap();
+    assert!(result.output.contains("│🚀 Launch│"));
+    insta::assert_snapshot!(result.output);
+}
+
+#[test]
+fn test_cjk_max_width_counts_columns() {
+    let input = "flowchart LR\nA[日本語ラベル] --> B[終了]";
+    let options = RenderOptions {
+        max_width: Some(20),
+        ..Default::default()
+    };
+    let result = render_mermaid_to_tui(input, options).unwrap();
+    for w in display_widths(&result.output) {
+        assert!(w <= 20, "line is {} columns wide", w);
+    }
+}
+
+#[test]
+fn test_cjk_d2_container_and_edge_label() {
+    let input = "東京: 東京駅\n大阪: 大阪駅\n東京 -> 大阪: 新幹線";
+    let result = render_d2_to_tui(input, RenderOptions::default()).unwrap();
+    assert!(result.output.contains("│東京駅│"));
+    assert!(result.output.contains("新幹線"));
+    insta::assert_snapshot!(result.output);
+}
+
+#[test]
+fn test_cjk_pie_alignment() {
+    let input = "pie\n    title 日本\n    \"東京\" : 40\n    \"Osaka\" : 60";
+    let result = render_pie_chart(input, RenderOptions::default()).unwrap();
+    let bar_columns: Vec<usize> = result
+        .output
+        .lines()
+        .filter(|l| l.contains('│'))
+        .map(|l| unicode_width::UnicodeWidthStr::width(&l[..l.find('│').unwrap()]))
+        .collect();
+    assert_eq!(bar_columns.len(), 2);
+    assert_eq!(bar_columns[0], bar_columns[1]);
+    assert!(result.output.contains("  日本\n  ────\n"));
+}
+
+#[test]
+fn test_zwj_and_combining_labels_stay_intact() {
+    let family = "👨\u{200d}👩\u{200d}👧";
+    let input = format!("flowchart LR\nA[{family} Family] -->|cafe\u{301}| B[e\u{301}te\u{301}]");
+    let result = render_mermaid_to_tui(&input, RenderOptions::default()).unwrap();
+    assert!(result.output.contains(&format!("│{family} Family│")));
+    assert!(result.output.contains("cafe\u{301}"));
+    assert!(result.output.contains("│e\u{301}te\u{301}│"));
+    let widths = display_widths(&result.output);
+    assert_eq!(widths[0], widths[1]);
+    insta::assert_snapshot!(result.output);
+}
+
+#[test]
+fn test_max_width_truncation_keeps_clusters() {
+    let flag = "🇯🇵";
+    let input = format!("flowchart LR\nA[{flag}{flag}{flag}{flag}{flag}]");
+    let options = RenderOptions {
+        max_width: Some(8),
+        ..Default::default()
+    };
+    let result = render_mermaid_to_tui(&input, options).unwrap();
+    for line in result.output.lines() {
+        // Never a lone regional indicator left over from a split flag
+        assert_eq!(
+            line.matches('\u{1F1EF}').count(),
+            line.matches('\u{1F1F5}').count()
+        );
+        assert!(unicode_width::UnicodeWidthStr::width(line) <= 8);
+    }
+}
+
+// ============================================
+// Diagonal Edge Routing Tests
+// ============================================
+
+#[test]
+fn test_diagonal_routing_fan_out() {
+    let input = "flowchart TB\nA[Start] --> B[Left]\nA --> C[Middle]\nA --> D[Right side]\nB --> E[End]\nD -->|yes| E";
+    let options = RenderOptions {
+        edge_routing: EdgeRouting::Diagonal,
+        ..Default::default()
+    };
+    let result = render_mermaid_to_tui(input, options).unwrap();
+    assert!(result.output.contains('╱'));
+    assert!(result.output.contains('╲'));
+    assert!(result.output.contains("yes"));
+    insta::assert_snapshot!(result.output);
+}
+
+#[test]
+fn test_diagonal_routing_lr_ascii() {
+    let input = "flowchart LR\nA[Start] --> B[Up]\nA --> C[Down]\nA --> X[Mid]";
+    let options = RenderOptions {
+        ascii: true,
+        edge_routing: EdgeRouting::Diagonal,
+        ..Default::default()
+    };
+    let result = render_mermaid_to_tui(input, options).unwrap();
+    assert!(result.output.contains('/'));
+    assert!(result.output.contains('\\'));
+    assert!(result.output.is_ascii());
+}
+
+#[test]
+fn test_diagonal_routing_keeps_straight_edges() {
+    let input = "flowchart LR\nA --> B --> C";
+    let orthogonal = render_mermaid_to_tui(input, RenderOptions::default()).unwrap();
+    let diagonal = render_mermaid_to_tui(
+        input,
+        RenderOptions {
+            edge_routing: EdgeRouting::Diagonal,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(orthogonal.output, diagonal.output);
+}
+
+/// Diagonal edges leave the source border with a straight step, keep
+/// their style's glyphs and keep labels off the line
+#[test]
+fn test_diagonal_routing_style_anchor_and_label() {
+    let input = "flowchart TB\nA[Start] -.-> B[Left]\nA ==> C[Right side]\nB -->|yes| D[End]";
+    let options = RenderOptions {
+        edge_routing: EdgeRouting::Diagonal,
+        ..Default::default()
+    };
+    let result = render_mermaid_to_tui(input, options).unwrap();
+    let lines: Vec<&str> = result.output.lines().collect();
+    let below = lines.iter().position(|l| l.contains("└─────┘")).unwrap() + 1;
+    assert!(lines[below].contains('│') || lines[below].contains('║'));
+    assert!(result.output.contains('⋰') || result.output.contains('⋱'));
+    assert!(result.output.contains('⧸') || result.output.contains('⧹'));
+    let label_row = lines.iter().find(|l| l.contains("yes")).unwrap();
+    assert!(!label_row.contains("yes─") && !label_row.contains("─yes"));
+    assert!(!label_row.contains("╲yes") && !label_row.contains("╱yes"));
+}
+
+// ============================================
+// Edge Bundling Tests
+// ============================================
+
+#[test]
+fn test_bundled_fan_in_has_single_arrow() {
+    let input = "flowchart TB\nA --> Z[Sink]\nB --> Z\nC --> Z\nD -->|x| Z\nE -.-> Z";
+    let options = RenderOptions {
+        bundle_edges: true,
+        ..Default::default()
+    };
+    let result = render_mermaid_to_tui(input, options).unwrap();
+    assert_eq!(result.output.matches('▼').count(), 1);
+    assert_eq!(result.output.matches('┴').count(), 2);
+    assert!(result.output.contains('┼'));
+    assert!(result.output.contains('x'));
+    insta::assert_snapshot!(result.output);
+}
+
+#[test]
+fn test_bundled_fan_in_lr() {
+    let input = "flowchart LR\nA --> Z\nB --> Z\nC --> Z";
+    let options = RenderOptions {
+        bundle_edges: true,
+        ..Default::default()
+    };
+    let result = render_mermaid_to_tui(input, options).unwrap();
+    assert_eq!(result.output.matches('▶').count(), 1);
+    assert!(result.output.contains("─┼─────▶"));
+    assert!(result.output.contains('┐'));
+    assert!(result.output.contains('┘'));
+}
+
+/// Where plain routing merges the edges just above the target, bundling
+/// joins them right under the sources and carries them down one trunk
+#[test]
+fn test_bundling_draws_a_shared_trunk() {
+    let input = "flowchart TB\nA --> Z\nB --> Z\nC --> Z\nD --> Z\nE --> Z";
+    let plain = render_mermaid_to_tui(input, RenderOptions::default()).unwrap();
+    let bundled = render_mermaid_to_tui(
+        input,
+        RenderOptions {
+            bundle_edges: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_ne!(plain.output, bundled.output);
+    let lines: Vec<&str> = bundled.output.lines().collect();
+    let arrow = lines.iter().position(|l| l.contains('▼')).unwrap();
+    assert_eq!(lines[arrow - 1].trim(), "│");
+    assert!(lines[arrow - 2].contains('┼'));
+    insta::assert_snapshot!(bundled.output);
+}
+
+#[test]
+fn test_bundling_leaves_single_edges_alone() {
+    let input = "flowchart TB\nA --> B\nB --> C";
+    let plain = render_mermaid_to_tui(input, RenderOptions::default()).unwrap();
+    let bundled = render_mermaid_to_tui(
+        input,
+        RenderOptions {
+            bundle_edges: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(plain.output, bundled.output);
+}
+
+/// Edges whose straight branch would run through another node keep their
+/// own routes
+#[test]
+fn test_bundling_skips_branches_through_nodes() {
+    let input = "flowchart TB\nA --> W[A very wide middle node]\nW --> Z\nA --> Z\nB --> Z";
+    let plain = render_mermaid_to_tui(input, RenderOptions::default()).unwrap();
+    let bundled = render_mermaid_to_tui(
+        input,
+        RenderOptions {
+            bundle_edges: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(plain.output, bundled.output);
+}
+
+// ============================================
+// Junction Glyph Tests
+// ============================================
+
+#[test]
+fn test_fan_out_joins_with_tees() {
+    let input = "flowchart TB\nA[Start] --> B[Left]\nA --> C[Middle]\nA --> D[Right side]";
+    let result = render_mermaid_to_tui(input, RenderOptions::default()).unwrap();
+    let split_row = result
+        .output
+        .lines()
+        .find(|l| l.contains('┴'))
+        .expect("edges leaving Start should join at a tee");
+    assert_eq!(split_row.trim(), "┌──────────────┬─┴───────────────┐");
+}
+
+#[test]
+fn test_fan_in_joins_with_tee_ascii() {
+    let input = "flowchart TB\nA --> C\nB --> C";
+    let options = RenderOptions {
+        ascii: true,
+        ..Default::default()
+    };
+    let result = render_mermaid_to_tui(input, options).unwrap();
+    assert!(result.output.is_ascii());
+    assert!(result.output.contains("+-----+------+"));
+}
+
+// ============================================
+// Arrowhead Tests
+// ============================================
+
+#[test]
+fn test_arrowhead_on_border_when_nodes_touch() {
+    let options = RenderOptions {
+        padding_x: 0,
+        padding_y: 0,
+        ..Default::default()
+    };
+    let lr = render_mermaid_to_tui("flowchart LR\nA --> B", options.clone()).unwrap();
+    assert!(lr.output.contains("│ A ▶│ B │"));
+    let tb = render_mermaid_to_tui("flowchart TB\nA --> B", options).unwrap();
+    assert!(tb.output.contains("└─▼─┘"));
+}
+
+#[test]
+fn test_arrowheads_survive_later_edges() {
+    // The back edge C --> A is drawn last and runs over the A --> B --> C arrows
+    let input = "flowchart LR\nA --> B\nB --> C\nC --> A";
+    let result = render_mermaid_to_tui(input, RenderOptions::default()).unwrap();
+    assert_eq!(result.output.matches('▶').count(), 2);
+}
+
+#[test]
+fn test_arrowhead_touches_target_rl_bt() {
+    let rl = render_mermaid_to_tui("flowchart RL\nA --> B", RenderOptions::default()).unwrap();
+    assert!(rl.output.contains("│ B │◀─"));
+    let bt = render_mermaid_to_tui("flowchart BT\nA --> B", RenderOptions::default()).unwrap();
+    let lines: Vec<&str> = bt.output.lines().collect();
+    assert_eq!(lines[3].trim(), "▲");
+}
+
+// ============================================
+// Subgraph Gateway Tests
+// ============================================
+
+#[test]
+fn test_edges_cross_container_at_gateways() {
+    let input = "direction: right\na -> c.b\nc.b -> d\nc: {\n  b\n  e\n}\nx -> c.e";
+    let result = render_d2_to_tui(input, RenderOptions::default()).unwrap();
+    // Two edges enter the container and one leaves, each through a gateway
+    assert_eq!(result.output.matches('╫').count(), 3);
+    assert_eq!(result.output.matches('▶').count(), 3);
+    insta::assert_snapshot!(result.output);
+}
+
+#[test]
+fn test_d2_container_to_container_edge() {
+    let input = "backend {\n  api\n  db\n}\nfrontend {\n  web\n}\nbackend -> frontend";
+    let result = render_d2_to_tui(input, RenderOptions::default()).unwrap();
+    let lines: Vec<&str> = result.output.lines().collect();
+    // No stand-in node boxes for the containers themselves
+    assert!(!result.output.contains("│backend│"));
+    assert!(!result.output.contains("│frontend│"));
+    // The arrow lands right on top of the frontend box
+    let arrow_row = lines.iter().position(|l| l.contains('▼')).unwrap();
+    assert!(lines[arrow_row + 1].contains("═frontend═"));
+    insta::assert_snapshot!(result.output);
+}
+
+#[test]
+fn test_gateway_crossing_ascii() {
+    let input = "direction: right\na -> c.b\nc: {\n  b\n}";
+    let options = RenderOptions {
+        ascii: true,
+        ..Default::default()
+    };
+    let result = render_d2_to_tui(input, options).unwrap();
+    assert!(result.output.is_ascii());
+    assert!(result.output.contains("-+>"));
+}
+
+// ============================================
+// Layer Alignment Tests
+// ============================================
+
+#[test]
+fn test_align_start_org_chart() {
+    let input = "flowchart TB\nCEO --> CTO\nCEO --> CFO\nCTO --> Dev";
+    let options = RenderOptions {
+        align: Alignment::Start,
+        ..Default::default()
+    };
+    let result = render_mermaid_to_tui(input, options).unwrap();
+    // Every layer starts at the left margin
+    let at_margin = result.output.lines().filter(|l| l.starts_with('┌'));
+    assert_eq!(at_margin.count(), 3);
+    insta::assert_snapshot!(result.output);
+}
+
+#[test]
+fn test_align_end_lr() {
+    let input = "flowchart LR\nA --> B\nA --> C";
+    let options = RenderOptions {
+        align: Alignment::End,
+        ..Default::default()
+    };
+    let result = render_mermaid_to_tui(input, options).unwrap();
+    let lines: Vec<&str> = result.output.lines().collect();
+    // A is packed to the bottom, level with C
+    let a_row = lines.iter().position(|l| l.contains("│ A │")).unwrap();
+    assert!(lines[a_row].contains("│ C │"));
+}
+
+// ============================================
+// Pinned Position Tests
+// ============================================
+
+#[test]
+fn test_d2_pinned_node() {
+    let input = "a -> b\na -> c\nc.x: 20\nc.y: 0";
+    let result = render_d2_to_tui(input, RenderOptions::default()).unwrap();
+    let line = result.output.lines().nth(1).unwrap();
+    let col = line.find("│ c │").map(|i| line[..i].chars().count());
+    assert_eq!(col, Some(20));
+}
+
+#[test]
+fn test_mermaid_pin_pragma_and_options() {
+    let input = "flowchart LR\n%% @pin B 16 8\nA --> B\nA --> C";
+    let mut options = RenderOptions::default();
+    options.pinned_nodes.insert("C".to_string(), (30, 0));
+    let result = render_mermaid_to_tui(input, options).unwrap();
+    let lines: Vec<&str> = result.output.lines().collect();
+    assert!(lines[9].contains("▶│ B │"));
+    assert!(lines[1].ends_with("▶│ C │"));
+    insta::assert_snapshot!(result.output);
+}
+
+// ============================================
+// Layout Algorithm Tests
+// ============================================
+
+#[test]
+fn test_radial_layout() {
+    let input = "hub -> a\nhub -> b\nhub -> c\nhub -> d\na -> a1\nc -> c1";
+    let options = RenderOptions {
+        layout: LayoutAlgorithm::Radial,
+        ..Default::default()
+    };
+    let result = render_d2_to_tui(input, options).unwrap();
+    assert_eq!(result.output.matches("│hub│").count(), 1);
+    insta::assert_snapshot!(result.output);
+}
+
+#[test]
+fn test_force_layout_cyclic_d2() {
+    let input = "a -- b\nb -- c\nc -- a\nc -- d\nd -- a";
+    let options = RenderOptions {
+        layout: LayoutAlgorithm::Force,
+        ..Default::default()
+    };
+    let result = render_d2_to_tui(input, options).unwrap();
+    assert!(result.warnings.is_empty());
+    for id in ["a", "b", "c", "d"] {
+        assert!(result.output.contains(&format!("│ {} │", id)));
+    }
+}
+
+#[test]
+fn test_grid_layout() {
+    let input = "flowchart LR\nA --> B\nC --> D\nE --> F";
+    let options = RenderOptions {
+        layout: LayoutAlgorithm::Grid,
+        ..Default::default()
+    };
+    let result = render_mermaid_to_tui(input, options).unwrap();
+    insta::assert_snapshot!(result.output);
+}
+
+// ============================================
+// Layering Strategy Tests
+// ============================================
+
+#[test]
+fn test_tight_tree_layering() {
+    let input = "flowchart TB\nStart --> Parse\nParse --> Check\nCheck --> Done\nConfig --> Done";
+    let options = RenderOptions {
+        layering: Layering::TightTree,
+        ..Default::default()
+    };
+    let result = render_mermaid_to_tui(input, options).unwrap();
+    // Config sits one layer above Done instead of beside Start
+    let row = |label: &str| {
+        result
+            .output
+            .lines()
+            .position(|l| l.contains(label))
+            .unwrap()
+    };
+    assert_eq!(row("Config"), row("Check"));
+    insta::assert_snapshot!(result.output);
+}
+
+// ============================================
+// Long Edge Waypoint Tests
+// ============================================
+
+#[test]
+fn test_long_edge_reserved_channel() {
+    let input = "flowchart LR\nLoad --> Parse\nParse --> Check\nCheck --> Save\nLoad --> Save";
+    let result = render_mermaid_to_tui(input, RenderOptions::default()).unwrap();
+    // The skipping edge runs in its own row below the chain
+    let rows: Vec<&str> = result.output.lines().collect();
+    let chain = rows.iter().position(|l| l.contains("Parse")).unwrap();
+    assert!(rows[chain + 2..].iter().any(|l| l.contains('─')));
+    insta::assert_snapshot!(result.output);
+}
+
+// ============================================
+// Source Map Tests
+// ============================================
+
+#[test]
+fn test_source_map_lines() {
+    let input = "flowchart LR\n\nA[Start] --> B\nB --> C\nA --> C";
+    let map = render_mermaid_to_tui(input, RenderOptions::default())
+        .unwrap()
+        .source_map;
+    assert_eq!(map.node_line("A"), Some(3));
+    assert_eq!(map.node_line("C"), Some(4));
+    assert_eq!(map.edge_line("A", "C"), Some(5));
+    assert_eq!(map.edge_line("C", "A"), None);
+
+    let map = render_d2_to_tui("x\nbox: {\n  y\n}\nx -> box.y", RenderOptions::default())
+        .unwrap()
+        .source_map;
+    assert_eq!(map.node_line("box"), Some(2));
+    assert_eq!(map.node_line("y"), Some(3));
+    assert_eq!(map.edge_line("x", "y"), Some(5));
+
+    let map = render_sequence_diagram("sequenceDiagram\nAlice->>Bob: Hi", RenderOptions::default())
+        .unwrap()
+        .source_map;
+    assert_eq!(map.edge_line("Alice", "Bob"), Some(2));
+}
+
+// ============================================
+// Outline Mode Tests
+// ============================================
+
+/// Outline mode lists nodes and their outgoing edges instead of a layout
+#[test]
+fn test_outline_mode() {
+    let input =
+        "a: Client\ncloud: {\n  api: API\n  db: DB\n  api -> db: SQL\n}\na -> cloud.api: REST";
+    let options = RenderOptions {
+        mode: RenderMode::Outline,
+        ..Default::default()
+    };
+    let result = render_d2_to_tui(input, options).unwrap();
+    insta::assert_snapshot!(result.output);
+}
+
+// ============================================
+// Accessible Summary Tests
+// ============================================
+
+/// Every diagram kind comes with a screen-reader summary
+#[test]
+fn test_render_result_summary() {
+    let cases = [
+        ("a: Start\na -> b -> c", "3 nodes, 2 edges; Start → b → c"),
+        (
+            "stateDiagram-v2\nIdle --> Busy",
+            "2 nodes, 1 edge; Idle → Busy",
+        ),
+        (
+            "sequenceDiagram\nA->>B: ping",
+            "2 participants, 1 message; A → B: ping",
+        ),
+        ("pie\n\"Yes\" : 3\n\"No\" : 1", "2 slices; Yes 75%, No 25%"),
+    ];
+    for (input, summary) in cases {
+        let result = render_diagram(input, RenderOptions::default()).unwrap();
+        assert_eq!(result.summary, summary);
+    }
+}
+
+// ============================================
+// Lenient Parsing Tests
+// ============================================
+
+#[test]
+fn test_lenient_renders_lines_that_parsed() {
+    let input = "flowchart LR\nA --> B\nB -->|oops C";
+    assert!(render_mermaid_to_tui(input, RenderOptions::default()).is_err());
+
+    let options = RenderOptions {
+        lenient: true,
+        ..Default::default()
+    };
+    let result = render_mermaid_to_tui(input, options).unwrap();
+    assert!(result.output.contains('A') && result.output.contains('B'));
+    assert_eq!(result.warnings.len(), 1);
+    assert!(result.warnings[0]
+        .to_string()
+        .starts_with("Line 3 skipped: "));
+}
+
+// ============================================
+// Width Strategy Tests
+// ============================================
+
+#[test]
+fn test_max_width_applies_to_pie_and_sequence() {
+    let pie = "pie\n\"Chrome\" : 65\n\"Firefox\" : 35";
+    let seq = "sequenceDiagram\nAlice->>Bob: a fairly long message label";
+    for strategy in [
+        WidthStrategy::Truncate,
+        WidthStrategy::Wrap,
+        WidthStrategy::Scale,
+    ] {
+        let options = RenderOptions {
+            max_width: Some(20),
+            width_strategy: strategy,
+            ..Default::default()
+        };
+        for output in [
+            render_pie_chart(pie, options.clone()).unwrap().output,
+            render_sequence_diagram(seq, options.clone())
+                .unwrap()
+                .output,
+        ] {
+            for line in output.lines() {
+                assert!(
+                    line.chars().count() <= 20,
+                    "{:?} line too wide: {:?}",
+                    strategy,
+                    line
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_wrap_strategy_keeps_all_text() {
+    let seq = "sequenceDiagram\nAlice->>Bob: label";
+    let options = RenderOptions {
+        max_width: Some(20),
+        width_strategy: WidthStrategy::Wrap,
+        ..Default::default()
+    };
+    let wrapped = render_sequence_diagram(seq, options).unwrap().output;
+    let full = render_sequence_diagram(seq, RenderOptions::default())
+        .unwrap()
+        .output;
+    let letters = |s: &str| s.chars().filter(|c| c.is_alphabetic()).count();
+    assert_eq!(letters(&wrapped), letters(&full));
+}
+
+/// Wrapping cuts the drawing into column bands, each with every row
+#[test]
+fn test_wrap_strategy_bands_columns() {
+    let input = "flowchart LR\nA[Start] --> B[Middle] --> C[Finish]";
+    let options = RenderOptions {
+        max_width: Some(16),
+        width_strategy: WidthStrategy::Wrap,
+        ..Default::default()
+    };
+    let result = render_mermaid_to_tui(input, options).unwrap();
+    let full = render_mermaid_to_tui(input, RenderOptions::default()).unwrap();
+    let rows = full.output.lines().count();
+    let bands: Vec<&str> = result.output.split("\n\n").collect();
+    assert!(bands.len() > 1);
+    for band in &bands {
+        assert_eq!(band.lines().count(), rows);
+    }
+    insta::assert_snapshot!(result.output);
+}
+
+// ============================================
+// Diagram Registry Tests
+// ============================================
+
+/// A downstream diagram kind: one colored row per comma-separated word
+struct Words;
+
+impl DiagramRenderer for Words {
+    type Model = Vec<String>;
+
+    fn detect(&self, input: &str) -> bool {
+        input.starts_with("words:")
+    }
+
+    fn parse(
+        &self,
+        input: &str,
+        _options: &RenderOptions,
+    ) -> Result<(Vec<String>, Vec<DiagramWarning>), MermaidError> {
+        let words = input["words:".len()..]
+            .split(',')
+            .map(|w| w.trim().to_string())
+            .collect();
+        Ok((words, Vec::new()))
+    }
+
+    fn render(
+        &self,
+        words: &Vec<String>,
+        _options: &RenderOptions,
+        _budget: &Budget,
+        _warnings: &mut Vec<DiagramWarning>,
+    ) -> Result<Drawing, MermaidError> {
+        Ok(Drawing {
+            body: words
+                .iter()
+                .map(|w| format!("\x1b[31m{}\x1b[0m", w.repeat(3)))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            footer: vec![format!("{} words in total", words.len())],
+            legend: Vec::new(),
+        })
+    }
+}
+
+/// Registered kinds get max_width and color handling from the pipeline
+#[test]
+fn test_registry_plugin_shares_option_handling() {
+    let mut registry = Registry::default();
+    registry.register("words", Words);
+    let input = "words: alpha, beta";
+
+    let result = registry
+        .render_detected(
+            input,
+            RenderOptions {
+                max_width: Some(8),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    assert_eq!(result.output, "alphaal…\nbetabet…\n2 words in total");
+
+    let colored = registry
+        .render(
+            "words",
+            input,
+            RenderOptions {
+                colors: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    assert!(colored.output.contains("\x1b[31m"));
+
+    // Built-in kinds are still detected alongside the plugin
+    let flowchart = registry
+        .render_detected("flowchart LR\nA --> B", RenderOptions::default())
+        .unwrap();
+    assert!(flowchart.output.contains('A'));
+}
+
+// ============================================
+// Normalized Output Tests
+// ============================================
+
+/// normalized() drops colors and trailing space and renumbers the legend by
+/// where each marker first appears
+#[test]
+fn test_render_result_normalized() {
+    let result = RenderResult {
+        output:
+            "\x1b[31mA\x1b[0m ──[2]──▶ B   \n└─[1]─▶ C\n\nLabels:\n  [1] second\n  [2] first\n\n"
+                .to_string(),
+        warnings: Vec::new(),
+        source_map: Default::default(),
+        edge_ids: Vec::new(),
+        summary: String::new(),
+        legend: Vec::new(),
+        node_numbers: Vec::new(),
+        node_positions: Default::default(),
+    };
+    assert_eq!(
+        result.normalized(),
+        "A ──[1]──▶ B\n└─[2]─▶ C\n\nLabels:\n  [1] first\n  [2] second"
+    );
+}
+
+// ============================================
+// Rich Output Tests
+// ============================================
+
+/// render_rich gives the same drawing as text, HTML and SVG, with colors
+/// carried into the markup
+#[test]
+fn test_render_rich_formats_agree() {
+    let code = "sequenceDiagram\nrect rgb(0, 0, 255)\nAlice->>Bob: Hi\nend";
+    let rich = render_rich("mermaid", code).unwrap();
+    let plain = render_sequence_diagram(code, RenderOptions::default()).unwrap();
+
+    assert_eq!(rich.text, plain.output);
+    assert!(rich.html.contains("<span style=\"background:#0000ff\">"));
+    assert!(rich.svg.contains("fill=\"#0000ff\"/>"));
+    assert_eq!(
+        rich.svg.matches("<tspan x=").count(),
+        plain.output.lines().count()
+    );
+}
+
+// ============================================
+// Animation Frame Tests
+// ============================================
+
+/// Each D2 step adds to the previous frame
+#[test]
+fn test_render_frames_d2_steps() {
+    let input = "a -> b\nsteps: {\n  1: { b -> c }\n  2: { c -> d }\n}";
+    let frames = render_frames(input, RenderOptions::default()).unwrap();
+    assert_eq!(frames.len(), 3);
+    assert!(!frames[0].contains('c'));
+    assert!(frames[1].contains('c') && !frames[1].contains('d'));
+    assert!(frames[2].contains('d'));
+}
+
+/// Sequence frames reveal one message at a time; other kinds give one frame
+#[test]
+fn test_render_frames_sequence_and_single() {
+    let input = "sequenceDiagram\nA->>B: ping\nB-->>A: pong\nA->>B: bye";
+    let frames = render_frames(input, RenderOptions::default()).unwrap();
+    assert_eq!(frames.len(), 3);
+    assert!(frames[1].contains("pong") && !frames[1].contains("bye"));
+    assert_eq!(
+        frames[2],
+        render_sequence_diagram(input, RenderOptions::default())
+            .unwrap()
+            .output
+    );
+
+    let flowchart = render_frames("flowchart LR\nA --> B", RenderOptions::default()).unwrap();
+    assert_eq!(flowchart.len(), 1);
+}
+
+// ============================================
+// Direction Override Tests
+// ============================================
+
+/// An LR diagram rendered with a TB override matches the TB source
+#[test]
+fn test_direction_override() {
+    let options = RenderOptions {
+        direction_override: Some(Direction::TB),
+        ..Default::default()
+    };
+    let overridden = render_mermaid_to_tui("flowchart LR\nA --> B --> C", options).unwrap();
+    let tb =
+        render_mermaid_to_tui("flowchart TB\nA --> B --> C", RenderOptions::default()).unwrap();
+    assert_eq!(overridden.output, tb.output);
+    assert!(overridden.output.lines().count() > 6);
+}
+
+/// A long LR chain is drawn top to bottom when it only fits that way
+#[test]
+fn test_auto_orient_transposes_wide_diagram() {
+    let input = "flowchart LR\nAlpha --> Bravo --> Charlie --> Delta --> Echo";
+    let options = |max_height| RenderOptions {
+        auto_orient: true,
+        max_width: Some(30),
+        max_height,
+        ..Default::default()
+    };
+    let result = render_mermaid_to_tui(input, options(None)).unwrap();
+    assert_eq!(
+        result.warnings,
+        vec![DiagramWarning::Reoriented {
+            from: Direction::LR,
+            to: Direction::TB
+        }]
+    );
+    assert!(result.output.lines().all(|l| l.chars().count() <= 30));
+    assert!(result.output.lines().count() > 20);
+
+    // Not enough rows for the vertical layout: keep LR, cut off
+    let result = render_mermaid_to_tui(input, options(Some(10))).unwrap();
+    assert!(!result
+        .warnings
+        .iter()
+        .any(|w| matches!(w, DiagramWarning::Reoriented { .. })));
+    assert!(result.warnings.contains(&DiagramWarning::LabelTruncated {
+        node_id: "Echo".to_string()
+    }));
+    assert!(result.output.lines().count() <= 10);
+}
+
+// ============================================
+// Label Abbreviation Tests
+// ============================================
+
+/// Abbreviate shortens only as much as needed and lists full labels
+#[test]
+fn test_width_strategy_abbreviate() {
+    let input =
+        "flowchart LR\nA[Authentication service] --> B[Session store] --> C[Audit log writer]";
+    let options = RenderOptions {
+        max_width: Some(45),
+        width_strategy: WidthStrategy::Abbreviate,
+        ..Default::default()
+    };
+    let result = render_mermaid_to_tui(input, options).unwrap();
+    let (body, legend) = result.output.split_once("Abbreviations:\n").unwrap();
+    assert!(body.lines().all(|l| l.chars().count() <= 45));
+    assert!(body.contains('…'));
+    assert!(legend.contains("= Authentication service"));
+    assert!(result.warnings.contains(&DiagramWarning::LabelAbbreviated {
+        node_id: "A".to_string(),
+        label: "Authentication service".to_string(),
+    }));
+
+    // Wide enough: nothing abbreviated
+    let options = RenderOptions {
+        max_width: Some(120),
+        width_strategy: WidthStrategy::Abbreviate,
+        ..Default::default()
+    };
+    let result = render_mermaid_to_tui(input, options).unwrap();
+    assert!(result.warnings.is_empty());
+    assert!(result.output.contains("Authentication service"));
+}
+
+// ============================================
+// Minimum Width Tests
+// ============================================
+
+/// min_width pads every line, with h_align placing the block
+#[test]
+fn test_min_width_and_h_align() {
+    let input = "flowchart LR\nA --> B";
+    let plain = render_mermaid_to_tui(input, RenderOptions::default()).unwrap();
+    let block = plain
+        .output
+        .lines()
+        .map(|l| l.chars().count())
+        .max()
+        .unwrap();
+    for (align, indent) in [
+        (Alignment::Start, 0),
+        (Alignment::Center, (40 - block) / 2),
+        (Alignment::End, 40 - block),
+    ] {
+        let options = RenderOptions {
+            min_width: Some(40),
+            h_align: align,
+            ..Default::default()
+        };
+        let result = render_mermaid_to_tui(input, options).unwrap();
+        for (line, original) in result.output.lines().zip(plain.output.lines()) {
+            assert_eq!(line.chars().count(), 40);
+            assert_eq!(&line[indent..indent + original.len()], original);
+        }
+    }
+}
+
+// ============================================
+// Node Size Override Tests
+// ============================================
+
+/// node_sizes enlarges a node but never shrinks it below its label
+#[test]
+fn test_node_size_overrides() {
+    let input = "flowchart TB\nHub --> A\nHub --> B";
+    let mut options = RenderOptions::default();
+    options.node_sizes.insert("Hub".to_string(), (15, 5));
+    options.node_sizes.insert("A".to_string(), (1, 1));
+    let mut graph = match parse_diagram(input).unwrap() {
+        ParsedDiagram::Flowchart(graph) => graph,
+        other => panic!("expected a flowchart, got {:?}", other),
+    };
+    compute_layout_with_options(&mut graph, &options);
+    assert_eq!(
+        (graph.nodes["Hub"].width, graph.nodes["Hub"].height),
+        (15, 5)
+    );
+    assert_eq!(
+        (graph.nodes["A"].width, graph.nodes["A"].height),
+        (graph.nodes["B"].width, graph.nodes["B"].height)
+    );
+
+    let output = render_mermaid_to_tui(input, options).unwrap().output;
+    assert!(output.lines().next().unwrap().contains("┌─────────────┐"));
+}
+
+// ============================================
+// Edge Label Placement Tests
+// ============================================
+
+/// Labels move toward either end, or above the line
+#[test]
+fn test_label_placement() {
+    let input = "flowchart LR\nA -->|req| B\nB -->|resp| C";
+    let render = |along, side| {
+        let options = RenderOptions {
+            label_placement: LabelPlacement { along, side },
+            ..Default::default()
+        };
+        render_mermaid_to_tui(input, options).unwrap().output
+    };
+    let center = render(LabelAlong::Center, LabelSide::Inline);
+    assert!(center.contains("│ A │──req──▶│ B │"));
+    assert!(render(LabelAlong::Source, LabelSide::Inline).contains("│ A │─req───▶│ B │"));
+    assert!(render(LabelAlong::Target, LabelSide::Inline).contains("│ A │───req─▶│ B │"));
+    let above = render(LabelAlong::Center, LabelSide::Above);
+    assert!(above.lines().next().unwrap().contains("  req  "));
+    assert!(above.contains("│ A │───────▶│ B │"));
+
+    // A per-edge placement wins over the global one
+    let mut graph = match parse_diagram(input).unwrap() {
+        ParsedDiagram::Flowchart(graph) => graph,
+        other => panic!("expected a flowchart, got {:?}", other),
+    };
+    graph.edges[1].label_placement = Some(LabelPlacement {
+        along: LabelAlong::Target,
+        side: LabelSide::Inline,
+    });
+    let output = render_parsed(ParsedDiagram::Flowchart(graph), RenderOptions::default())
+        .unwrap()
+        .output;
+    assert!(output.contains("│ A │──req──▶│ B │──resp─▶│ C │"));
+}
+
+// ============================================
+// Edge Label Mode Tests
+// ============================================
+
+/// Legend mode numbers every label; Hidden mode drops them
+#[test]
+fn test_edge_labels_legend_and_hidden() {
+    let input = "flowchart TB\nA -->|request| B\nB -->|response| C\nA --> C";
+    let render = |edge_labels| {
+        let options = RenderOptions {
+            edge_labels,
+            ..Default::default()
+        };
+        render_mermaid_to_tui(input, options).unwrap()
+    };
+
+    let legend = render(EdgeLabels::Legend);
+    let (body, listing) = legend.output.split_once("Labels:\n").unwrap();
+    assert!(body.contains("[1]") && body.contains("[2]"));
+    assert!(!body.contains("request"));
+    assert_eq!(listing, "  [1] request\n  [2] response");
+    assert!(legend.warnings.is_empty());
+
+    let hidden = render(EdgeLabels::Hidden);
+    assert!(!hidden.output.contains("request") && !hidden.output.contains('['));
+    assert!(render(EdgeLabels::Inline).output.contains("request"));
+}
+
+// ============================================
+// Legend Placement Tests
+// ============================================
+
+/// The label legend can move above the diagram, into its own field, or away
+#[test]
+fn test_legend_heading_and_position() {
+    let input = "flowchart LR\nA -->|request| B";
+    let render = |legend_position| {
+        let options = RenderOptions {
+            edge_labels: EdgeLabels::Legend,
+            legend_heading: "Key".to_string(),
+            legend_position,
+            ..Default::default()
+        };
+        render_mermaid_to_tui(input, options).unwrap()
+    };
+
+    let bottom = render(LegendPosition::Bottom);
+    assert!(bottom.output.ends_with("\nKey\n  [1] request"));
+    assert!(bottom.legend.is_empty());
+
+    let top = render(LegendPosition::Top);
+    assert!(top.output.starts_with("Key\n  [1] request\n"));
+
+    let separate = render(LegendPosition::Separate);
+    assert!(!separate.output.contains("request"));
+    assert_eq!(separate.legend, ["[1] request"]);
+
+    let hidden = render(LegendPosition::Hidden);
+    assert!(!hidden.output.contains("request") && hidden.legend.is_empty());
+    assert!(hidden.output.contains("[1]"));
+}
+
+// ============================================
+// Overlap and Truncation Warning Tests
+// ============================================
+
+/// Pinned nodes drawn over each other are reported
+#[test]
+fn test_node_overlap_warning() {
+    let input = "flowchart LR\nA --> B\nC";
+    let mut options = RenderOptions::default();
+    options.pinned_nodes.insert("A".to_string(), (0, 0));
+    options.pinned_nodes.insert("C".to_string(), (2, 1));
+    let result = render_mermaid_to_tui(input, options).unwrap();
+    assert_eq!(
+        result.warnings,
+        vec![DiagramWarning::NodeOverlap {
+            nodes: vec!["A".to_string(), "C".to_string()]
+        }]
+    );
+    assert!(render_mermaid_to_tui(input, RenderOptions::default())
+        .unwrap()
+        .warnings
+        .is_empty());
+}
+
+// ============================================
+// Custom Shape Tests
+// ============================================
+
+/// A gear glyph beside the label, on a row of its own size
+struct Gear;
+
+impl ShapeRenderer for Gear {
+    fn size(&self, label: &str) -> (usize, usize) {
+        (label.len() + 2, 1)
+    }
+
+    fn draw(&self, label: &str, _width: usize, _height: usize, ascii: bool) -> Vec<String> {
+        let gear = if ascii { '*' } else { '⚙' };
+        vec![format!("{} {}", gear, label)]
+    }
+}
+
+/// D2 `shape:` resolves to a registered shape, which sizes and draws the node
+#[test]
+fn test_register_shape() {
+    register_shape("Gear", Box::new(Gear));
+    let input = "svc.shape: gear
+svc -> db";
+    let result = render_d2_to_tui(input, RenderOptions::default()).unwrap();
+    assert!(result.output.starts_with("⚙ svc\n"));
+
+    let ascii = RenderOptions {
+        ascii: true,
+        ..Default::default()
+    };
+    let result = render_d2_to_tui(input, ascii).unwrap();
+    assert!(result.output.starts_with("* svc\n"));
+
+    // Built-in shape names keep their meaning
+    register_shape("circle", Box::new(Gear));
+    let graph = match parse_diagram("a.shape: circle").unwrap() {
+        ParsedDiagram::D2(graph) => graph,
+        other => panic!("expected a D2 graph, got {:?}", other),
+    };
+    assert_eq!(graph.nodes["a"].shape, NodeShape::Circle);
+    assert_eq!(graph.nodes["a"].custom_shape, None);
+}
+
+// ============================================
+// Charset Tests
+// ============================================
+
+/// A supplied charset replaces the one `ascii` would pick
+#[test]
+fn test_custom_charset() {
+    let input = "flowchart LR\nA --> B";
+    let heavy = RenderOptions {
+        charset: Some(CharSet::heavy()),
+        ascii: true,
+        ..Default::default()
+    };
+    let output = render_mermaid_to_tui(input, heavy).unwrap().output;
+    assert_eq!(
+        output.lines().next().unwrap().trim_end(),
+        "┏━━━┓        ┏━━━┓"
+    );
+    assert!(output.contains("┃ A ┃━━━━━━━▶┃ B ┃"));
+
+    let custom = RenderOptions {
+        charset: Some(CharSet {
+            tl: '*',
+            tr: '*',
+            bl: '*',
+            br: '*',
+            arr_r: '>',
+            ..CharSet::unicode()
+        }),
+        ..Default::default()
+    };
+    let output = render_mermaid_to_tui(input, custom).unwrap().output;
+    assert!(output.starts_with("*───*"));
+    assert!(output.contains("│ A │───────>│ B │"));
+}
+
+// ============================================
+// Invisible and Wavy Edge Tests
+// ============================================
+
+/// `~~~` places its nodes like any edge but draws nothing
+#[test]
+fn test_invisible_edge_not_drawn() {
+    let linked = render_mermaid_to_tui("flowchart LR\nA ~~~ B", RenderOptions::default())
+        .unwrap()
+        .output;
+    assert_eq!(
+        linked,
+        "┌───┐        ┌───┐\n│ A │        │ B │\n└───┘        └───┘"
+    );
+    // Without the link, B would stack under A instead
+    let unlinked = render_mermaid_to_tui("flowchart LR\nA\nB", RenderOptions::default())
+        .unwrap()
+        .output;
+    assert_ne!(linked, unlinked);
+}
+
+/// D2 animated connections are drawn wavy
+#[test]
+fn test_wavy_edge_glyphs() {
+    let input = "direction: right\na -> b {style.animated: true}";
+    let output = render_d2_to_tui(input, RenderOptions::default())
+        .unwrap()
+        .output;
+    assert!(output.contains("│ a │∿∿∿∿∿∿∿▶│ b │"));
+    let ascii = RenderOptions {
+        ascii: true,
+        ..Default::default()
+    };
+    let output = render_d2_to_tui(input, ascii).unwrap().output;
+    assert!(output.contains("| a |~~~~~~~>| b |"));
+}
+
+// ============================================
+// Parser Warning Tests
+// ============================================
+
+/// Every Mermaid diagram type reports the lines it could not use
+#[test]
+fn test_unrecognized_lines_warn_in_every_diagram() {
+    for input in [
+        "sequenceDiagram\nA->>B: hi\nA ?? B",
+        "stateDiagram-v2\nA --> B\nA ?? B",
+        "pie\n\"A\" : 1\nA ?? B",
+    ] {
+        let result = render_diagram(input, RenderOptions::default()).unwrap();
+        assert_eq!(
+            result.warnings,
+            vec![DiagramWarning::LineSkipped {
+                line: 3,
+                message: "Invalid syntax: \"A ?? B\"".to_string()
+            }],
+            "{}",
+            input
+        );
+    }
+}
+
+// ============================================
+// Options Builder Tests
+// ============================================
+
+/// The builder sets fields on top of the defaults
+#[test]
+fn test_options_builder() {
+    let options = RenderOptions::builder()
+        .ascii(true)
+        .max_width(80)
+        .padding_x(4)
+        .pin("A", 0, 0)
+        .legend_heading("Notes:")
+        .build()
+        .unwrap();
+    assert!(options.ascii);
+    assert_eq!(options.max_width, Some(80));
+    assert_eq!(options.padding_x, 4);
+    assert_eq!(options.padding_y, RenderOptions::default().padding_y);
+    assert_eq!(options.pinned_nodes["A"], (0, 0));
+    assert_eq!(options.legend_heading, "Notes:");
+}
+
+/// Nonsense values are rejected with the option they came from
+#[test]
+fn test_options_builder_rejects_invalid_values() {
+    let option = |result: Result<RenderOptions, MermaidError>| match result {
+        Err(MermaidError::InvalidOption { option, .. }) => option,
+        other => panic!("expected an invalid option, got {:?}", other),
+    };
+    assert_eq!(
+        option(RenderOptions::builder().max_width(19).build()),
+        "max_width"
+    );
+    assert_eq!(
+        option(RenderOptions::builder().max_width(40).min_width(60).build()),
+        "min_width"
+    );
+    assert_eq!(
+        option(RenderOptions::builder().padding_y(0).build()),
+        "padding_y"
+    );
+    assert_eq!(
+        option(RenderOptions::builder().border_padding(50).build()),
+        "border_padding"
+    );
+    assert_eq!(
+        RenderOptions::builder()
+            .max_width(10)
+            .build()
+            .unwrap_err()
+            .to_string(),
+        "Invalid option max_width: 10 is below the minimum of 20"
+    );
+    assert!(RenderOptions::default().validate().is_ok());
+}
+
+// ============================================
+// Node Numbering Tests
+// ============================================
+
+/// Numbers follow the edges, then unconnected nodes, and the result maps
+/// them back to ids
+#[test]
+fn test_number_nodes() {
+    let input = "flowchart LR\nZ[Lonely]\nB[Web] --> C[DB]\nA[User] --> B";
+    let options = RenderOptions::builder().number_nodes(true).build().unwrap();
+    let result = render_mermaid_to_tui(input, options.clone()).unwrap();
+    assert_eq!(result.node_numbers, vec!["B", "C", "A", "Z"]);
+    for label in ["1: Web", "2: DB", "3: User", "4: Lonely"] {
+        assert!(result.output.contains(label), "missing {label}");
+    }
+
+    // Same numbering through the registry, and none when the option is off
+    let registry = Registry::default();
+    let via_registry = registry.render("flowchart", input, options).unwrap();
+    assert_eq!(via_registry.output, result.output);
+    assert_eq!(via_registry.node_numbers, result.node_numbers);
+    let plain = render_mermaid_to_tui(input, RenderOptions::default()).unwrap();
+    assert!(plain.node_numbers.is_empty());
+    assert!(!plain.output.contains("1: Web"));
+}
+
+// ============================================
+// Path Highlight Tests
+// ============================================
+
+/// Only the nodes and edges on the shortest path are emphasized
+#[test]
+fn test_render_with_path() {
+    let input = "flowchart LR\nUser --> Web --> Api --> DB\nWeb --> DB\nUser --> Cache";
+    let result = render_with_path(input, "User", "DB", RenderOptions::default()).unwrap();
+    let output = &result.output;
+    // User, Web and DB get double borders; Api and Cache keep light ones
+    assert_eq!(output.matches('╔').count(), 3);
+    assert!(output.contains("║Web║") && output.contains("│Api│") && output.contains("│Cache│"));
+    assert!(output.contains("══▶"));
+
+    let plain = render_diagram(input, RenderOptions::default()).unwrap();
+    assert!(!plain.output.contains('╔'));
+}
+
+/// Missing nodes, unreachable targets and non-graph diagrams are errors
+#[test]
+fn test_render_with_path_errors() {
+    let message = |input: &str, from: &str, to: &str| match render_with_path(
+        input,
+        from,
+        to,
+        RenderOptions::default(),
+    ) {
+        Err(MermaidError::InvalidOption { message, .. }) => message,
+        other => panic!("expected an invalid option, got {:?}", other),
+    };
+    assert_eq!(message("flowchart LR\nA --> B", "A", "Z"), "no node Z");
+    assert_eq!(
+        message("flowchart LR\nA --> B", "B", "A"),
+        "no path from B to A"
+    );
+    assert!(message("sequenceDiagram\nA->>B: hi", "A", "B").contains("only"));
+}
+
+/// A composite state is a path end of its own, reached by the edge into it
+#[test]
+fn test_render_with_path_to_container() {
+    let input =
+        "stateDiagram-v2\nIdle --> Active\nstate Active {\n  [*] --> Run\n}\nActive --> Idle";
+    for (from, to) in [("Idle", "Active"), ("Active", "Idle")] {
+        let result = render_with_path(input, from, to, RenderOptions::default()).unwrap();
+        assert!(result.output.contains("║Idle║"), "{}", result.output);
+    }
+}
+
+/// Weighted edges on the critical path are drawn thick when asked
+#[test]
+fn test_emphasize_critical_path() {
+    let input = "flowchart TB\nA -->|(2d)| B\nB -->|(3d)| D\nA -->|(1d)| C\nC -->|(1d)| D";
+    let options = RenderOptions::builder()
+        .emphasize_critical_path(true)
+        .build()
+        .unwrap();
+    let output = render_mermaid_to_tui(input, options).unwrap().output;
+    assert!(output.contains('║'));
+    let plain = render_mermaid_to_tui(input, RenderOptions::default()).unwrap();
+    assert!(!plain.output.contains('║'));
+}
+
+// ============================================================================
+// Sanitize Tests
+// ============================================================================
+
+/// A chat reply with a fence, commentary and smart quotes renders once
+/// sanitized, with each repair listed before other warnings
+#[test]
+fn test_sanitize_chat_reply() {
+    let reply =
+        "Here's the flow:\n```mermaid\ngraph LR\nA[“Login”] → B\nB → A\n```\nHope this helps!";
+    let options = RenderOptions::builder().sanitize(true).build().unwrap();
+    let result = render("mermaid", reply, options).unwrap();
+    assert!(result.output.contains("│Login│"));
+    let repairs: Vec<usize> = result
+        .warnings
+        .iter()
+        .map_while(|w| match w {
+            DiagramWarning::InputRepaired { line, .. } => Some(*line),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(repairs, vec![1, 2, 4, 4, 5, 6, 7]);
+    assert!(matches!(
+        result.warnings.last(),
+        Some(DiagramWarning::CycleDetected { .. })
+    ));
+}
+
+/// Every entry point that takes options repairs the source, once
+#[test]
+fn test_sanitize_in_every_entry_point() {
+    let reply = "```mermaid\ngraph LR\nA → B\n```";
+    let options = RenderOptions::builder().sanitize(true).build().unwrap();
+    let expected = render("mermaid", reply, options.clone()).unwrap();
+    assert_eq!(expected.warnings.len(), 3);
+    for result in [
+        render_diagram(reply, options.clone()),
+        render_mermaid_to_tui(reply, options.clone()),
+        render_partial(reply, options.clone()),
+        render_with_path(reply, "A", "B", options.clone()),
+        Registry::default().render("flowchart", reply, options.clone()),
+        Registry::default().render_detected(reply, options.clone()),
+    ] {
+        let result = result.unwrap();
+        assert!(!result.output.contains("```") && result.output.contains('B'));
+        assert_eq!(result.warnings, expected.warnings);
+    }
+    let frames = render_frames(reply, options.clone()).unwrap();
+    assert_eq!(frames, vec![expected.output.clone()]);
+
+    let d2 = "```d2\na → b\n```";
+    let result = render_d2_to_tui(d2, options).unwrap();
+    assert!(result.output.contains('b') && !result.output.contains('→'));
+    assert_eq!(result.warnings.len(), 3);
+}
+
+// ============================================================================
+// Partial Rendering Tests
+// ============================================================================
+
+/// Every prefix of a streamed diagram from its first statement on
+/// renders, and the finished text renders like a complete diagram
+#[test]
+fn test_render_partial_streamed_prefixes() {
+    let inputs = [
+        "flowchart TD\nsubgraph API\nGateway -->|routes| Users[User service]\nend\nUsers --> DB[(Postgres)]\n",
+        "sequenceDiagram\nClient->>Server: hello\nloop Poll\nClient->>Server: status?\nServer-->>Client: ok\nend\n",
+        "stateDiagram-v2\n[*] --> Active\nstate Active {\nIdle --> Busy: job\n}\n",
+        "api: {\n  gateway -> users: routes\n}\nusers -> db\n",
+    ];
+    for input in inputs {
+        let header = input.find('\n').unwrap() + 1;
+        let first_statement = header + input[header..].find('\n').unwrap() + 1;
+        for end in (first_statement..=input.len()).filter(|&i| input.is_char_boundary(i)) {
+            let result = render_partial(&input[..end], RenderOptions::default());
+            assert!(result.is_ok(), "{:?} failed: {:?}", &input[..end], result);
+        }
+        assert_eq!(
+            render_partial(input, RenderOptions::default())
+                .unwrap()
+                .output,
+            render_diagram(input, RenderOptions::default())
+                .unwrap()
+                .output
+        );
+    }
+}
+
+/// An unfinished last line is left out rather than reported
+#[test]
+fn test_render_partial_drops_unfinished_line() {
+    let result =
+        render_partial("sequenceDiagram\nA->>B: hi\nB-", RenderOptions::default()).unwrap();
+    assert!(result.warnings.is_empty());
+    let result =
+        render_partial("flowchart LR\nA --> B\nB -->|lab", RenderOptions::default()).unwrap();
+    assert!(result.output.contains("│ B │"));
+    // A finished line that fails still fails
+    assert!(render_partial("flowchart LR\nB -->|lab\n", RenderOptions::default()).is_err());
+}
+
+// ============================================
+// Stable Layout Tests
+// ============================================
+
+/// Passing a render's positions back reproduces it, and an edited graph
+/// keeps its nodes in the same order
+#[test]
+fn test_previous_positions_round_trip() {
+    let input = "flowchart LR\nStart --> Zeta\nStart --> Beta";
+    let first = render_diagram(input, RenderOptions::default()).unwrap();
+    let graph = match parse_diagram(input).unwrap() {
+        ParsedDiagram::Flowchart(graph) => graph,
+        other => panic!("expected a flowchart, got {:?}", other),
+    };
+    assert_eq!(first.node_positions.len(), 3);
+    assert!(first
+        .node_positions
+        .contains_key(&graph.nodes["Zeta"].stable_id()));
+
+    let options = RenderOptions::builder()
+        .previous_positions(first.node_positions.clone())
+        .build()
+        .unwrap();
+    let again = render_diagram(input, options.clone()).unwrap();
+    assert_eq!(again.output, first.output);
+
+    // The new node lands after Beta, its neighbour by id
+    let edited = render_diagram(&format!("{}\nStart --> Gamma", input), options).unwrap();
+    let rows: Vec<usize> = ["Beta", "Gamma", "Zeta"]
+        .iter()
+        .map(|name| {
+            edited
+                .output
+                .lines()
+                .position(|line| line.contains(name))
+                .unwrap()
+        })
+        .collect();
+    assert!(rows.windows(2).all(|w| w[0] < w[1]), "{}", edited.output);
+}
+
+// ============================================
+// HTML Label Tests
+// ============================================
+
+/// Mermaid's HTML in labels becomes plain text, with bold kept for color
+/// output
+#[test]
+fn test_html_labels() {
+    let input = "flowchart LR\nA[<b>API</b><br/>v2 &amp; more] --> B[<font color=red>DB</font>]";
+    let plain = render_diagram(input, RenderOptions::default()).unwrap();
+    assert!(plain.output.contains("│v2 & more│"));
+    assert!(plain.output.contains("DB") && !plain.output.contains("font"));
+    assert!(!plain.output.contains('\x1b'));
+
+    let options = RenderOptions::builder().colors(true).build().unwrap();
+    let colored = render_diagram(input, options).unwrap();
+    assert!(colored.output.contains("\x1b[1mAPI\x1b[22m"));
+    assert_eq!(colored.normalized(), plain.normalized());
+
+    let input = "sequenceDiagram\nAlice->>Bob: <i>hi</i><br/>there\nNote over Bob: &quot;ok&quot;";
+    let result = render_diagram(input, RenderOptions::default()).unwrap();
+    assert!(result.output.contains(" hi "));
+    assert!(result.output.contains("there"));
+    assert!(result.output.contains("\"ok\""));
+}
+
+/// Only the cells of the `<b>` span are bold, not other text that matches
+/// it, and numbering or shortcodes don't shift them
+#[test]
+fn test_bold_follows_its_span() {
+    let input = "flowchart LR\nA[:star: <b>a</b> banana]";
+    let options = RenderOptions::builder()
+        .colors(true)
+        .number_nodes(true)
+        .build()
+        .unwrap();
+    let result = render_diagram(input, options).unwrap();
+    assert!(result.output.contains("1: ⭐ \x1b[1ma\x1b[22m banana"));
+    assert_eq!(result.output.matches("\x1b[1m").count(), 1);
+}
+
+// ============================================
+// Emoji Shortcode Tests
+// ============================================
+
+/// Shortcodes become emoji, sized as two columns, or `[name]` in ASCII
+#[test]
+fn test_emoji_shortcodes() {
+    let input = "flowchart LR\nA[:rocket: Deploy] --> B[Done at 10:30:00]";
+    let result = render_diagram(input, RenderOptions::default()).unwrap();
+    assert!(result.output.contains("│🚀 Deploy│"));
+    assert!(result.output.contains("10:30:00"));
+    // Nine columns: the emoji takes two
+    assert!(result.output.starts_with("┌─────────┐"));
+
+    let options = RenderOptions::builder().ascii(true).build().unwrap();
+    let result = render_diagram("x: :bug: Bugs\nx -> y", options).unwrap();
+    assert!(result.output.contains("|[bug] Bugs|"));
+}
+
+// ============================================
+// Line Ending Tests
+// ============================================
+
+/// CRLF output has the same lines, each ended with `\r\n`
+#[test]
+fn test_crlf_line_endings() {
+    let options = RenderOptions::builder()
+        .line_ending(LineEnding::CrLf)
+        .build()
+        .unwrap();
+    for input in [
+        "flowchart LR\nA -->|a long label| B",
+        "sequenceDiagram\nAlice->>Bob: hi",
+        "pie\n\"Dogs\" : 3",
+    ] {
+        let lf = render_diagram(input, RenderOptions::default()).unwrap();
+        let crlf = render_diagram(input, options.clone()).unwrap();
+        assert_eq!(crlf.output, lf.output.replace('\n', "\r\n"));
+        assert!(lf.output.lines().all(|line| !line.ends_with(' ')));
+    }
+    let overlay =
+        render_with_error_overlay("mermaid", "sequenceDiagram\nA->>B: hi\nA->>", options).unwrap();
+    assert!(!overlay.output.replace("\r\n", "").contains('\n'));
+}
+
+/// Without trimming, graph and sequence rows keep their spaces out to the
+/// drawing's full width
+#[test]
+fn test_untrimmed_rows_keep_full_width() {
+    let options = RenderOptions::builder()
+        .trim_trailing(false)
+        .build()
+        .unwrap();
+    for input in [
+        "flowchart TB\nA --> B[A much wider node]",
+        "sequenceDiagram\nAlice->>Bob: hi",
+    ] {
+        let trimmed = render_diagram(input, RenderOptions::default()).unwrap();
+        let untrimmed = render_diagram(input, options.clone()).unwrap();
+        assert!(untrimmed.output.lines().any(|line| line.ends_with(' ')));
+        let widths: Vec<usize> = untrimmed
+            .output
+            .lines()
+            .map(|l| l.chars().count())
+            .collect();
+        assert!(
+            widths.windows(2).all(|w| w[0] == w[1]),
+            "{}",
+            untrimmed.output
+        );
+        let retrimmed: Vec<&str> = untrimmed.output.lines().map(str::trim_end).collect();
+        assert_eq!(retrimmed.join("\n"), trimmed.output);
+    }
+}
+
+// ============================================
+// Viewport Tests
+// ============================================
+
+/// Rows and regions are windows onto the same output
+#[test]
+fn test_rows_and_render_region() {
+    let input = "flowchart TD\nA --> B\nB --> C\nC --> D\nA --> E";
+    let result = render_mermaid_to_tui(input, RenderOptions::default()).unwrap();
+    let rows: Vec<String> = result.rows().collect();
+    assert_eq!(rows.join("\n"), result.output);
+    assert_eq!(
+        result.render_region(0, 0, usize::MAX, usize::MAX),
+        result.output
+    );
+
+    // Every character here is one column wide
+    let expected: Vec<String> = rows[3..7]
+        .iter()
+        .map(|row| row.chars().skip(2).take(6).collect())
+        .collect();
+    assert_eq!(result.render_region(2, 3, 6, 4), expected.join("\n"));
+    assert_eq!(result.render_region(0, rows.len(), 10, 10), "");
+}
+
+// ============================================
+// Diff Tests
+// ============================================
+
+/// Applying the changed rows to the old screen gives the new one
+#[test]
+fn test_diff_lines_patches_old_screen() {
+    let render = |input: &str| render_mermaid_to_tui(input, RenderOptions::default()).unwrap();
+    let tall = render("flowchart TD\nA --> B\nB --> C");
+    let short = render("flowchart TD\nA --> B");
+    for (old, new) in [(&tall, &short), (&short, &tall)] {
+        let mut screen: Vec<String> = old.rows().collect();
+        for (row, line) in diff_lines(old, new) {
+            if row >= screen.len() {
+                screen.resize(row + 1, String::new());
+            }
+            screen[row] = line;
+        }
+        while screen.last().is_some_and(|line| line.is_empty()) {
+            screen.pop();
+        }
+        assert_eq!(screen.join("\n"), new.output);
+    }
+    assert!(diff_lines(&tall, &tall).is_empty());
+}
+
+// ============================================
+// Render Hook Tests
+// ============================================
+
+/// Each render reports its parse once and its output size once
+#[test]
+fn test_render_hooks_report_each_stage() {
+    use std::sync::{Arc, Mutex};
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let (parsed, laid_out) = (events.clone(), events.clone());
+    let hooks = RenderHooks::new()
+        .on_parse_complete(move |m| {
+            parsed
+                .lock()
+                .unwrap()
+                .push(format!("parse {}", m.input_lines))
+        })
+        .on_layout_complete(move |m| {
+            laid_out
+                .lock()
+                .unwrap()
+                .push(format!("layout {}x{}", m.width, m.height))
+        });
+    let options = RenderOptions::builder().hooks(hooks).build().unwrap();
+    for input in [
+        "flowchart LR\nA --> B",
+        "stateDiagram-v2\n[*] --> A",
+        "sequenceDiagram\nAlice->>Bob: hi",
+        "pie\n\"Dogs\" : 3",
+        "a -> b",
+    ] {
+        events.lock().unwrap().clear();
+        let result = render_diagram(input, options.clone()).unwrap();
+        let width = result.rows().map(|row| row.chars().count()).max().unwrap();
+        let height = result.rows().count();
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                format!("parse {}", input.lines().count()),
+                format!("layout {}x{}", width, height),
+            ],
+            "{}",
+            input
+        );
+    }
+
+    events.lock().unwrap().clear();
+    Registry::default()
+        .render("flowchart", "flowchart LR\nA --> B", options)
+        .unwrap();
+    assert_eq!(events.lock().unwrap().len(), 2);
+}
+
+/// A partial render that retries without its unfinished last line reports
+/// only the render it returns
+#[test]
+fn test_render_partial_runs_hooks_once() {
+    use std::sync::{Arc, Mutex};
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let (parsed, laid_out) = (events.clone(), events.clone());
+    let hooks = RenderHooks::new()
+        .on_parse_complete(move |m| {
+            parsed
+                .lock()
+                .unwrap()
+                .push(format!("parse {}", m.input_lines))
+        })
+        .on_layout_complete(move |m| {
+            laid_out
+                .lock()
+                .unwrap()
+                .push(format!("layout {}", m.height))
+        });
+    // Lenient parsing draws the first attempt, skipping the last line
+    let options = RenderOptions::builder()
+        .hooks(hooks)
+        .lenient(true)
+        .build()
+        .unwrap();
+
+    let result = render_partial("flowchart LR\nA --> B\nB --> C[Red", options.clone()).unwrap();
+    assert!(!result.output.contains("Red"));
+    assert_eq!(
+        *events.lock().unwrap(),
+        vec![
+            "parse 2".to_string(),
+            format!("layout {}", result.rows().count())
+        ]
+    );
+
+    events.lock().unwrap().clear();
+    render_partial("flowchart LR\nA --> B\nB --> C", options).unwrap();
+    assert_eq!(events.lock().unwrap().len(), 2);
+}
+
+// ============================================
+// Oversized Input Tests
+// ============================================
+
+/// A pin far outside the drawing is pulled in rather than sizing the grid
+#[test]
+fn test_huge_pin_renders() {
+    for input in [
+        "flowchart LR\n%% @pin A 3000000000 3000000000\nA --> B",
+        "flowchart LR\n%% @pin A 30000 30000\nA --> B",
+        "a.x: 3000000000\na.y: 3000000000\na -> b",
+    ] {
+        let result = render_diagram(input, RenderOptions::default()).unwrap();
+        assert!(result.rows().count() < 300, "{}", input);
+        assert!(result.output.contains('A') || result.output.contains('a'));
+    }
+}
+
+/// A D2 size far past the limit is drawn at the limit, with a warning
+#[test]
+fn test_huge_d2_width_renders() {
+    let result = render_d2_to_tui("a.width: 3000000000\na -> b", RenderOptions::default()).unwrap();
+    assert!(result
+        .warnings
+        .iter()
+        .any(|w| matches!(w, DiagramWarning::NodeSizeClamped { node_id, .. } if node_id == "a")));
+    assert!(result.rows().all(|row| row.chars().count() < 300));
+}
+
+/// A drawing too large to hold in memory fails instead of aborting
+#[test]
+fn test_grid_cell_limit() {
+    let input = format!(
+        "flowchart LR\n{}",
+        (0..150)
+            .map(|i| format!("N{} --> N{}", i, i + 1))
+            .collect::<Vec<_>>()
+            .join("\n")
+    );
+    let options = RenderOptions {
+        node_sizes: (0..=150).map(|i| (format!("N{}", i), (200, 200))).collect(),
+        ..Default::default()
+    };
+    assert!(matches!(
+        render_mermaid_to_tui(&input, options),
+        Err(MermaidError::TooLarge { ref resource, .. }) if resource == "grid cells"
+    ));
+}
+
+/// Options with hooks can still be used inside `catch_unwind`
+#[test]
+fn test_options_with_hooks_are_unwind_safe() {
+    let hooks = RenderHooks::new().on_layout_complete(|_| {});
+    let options = RenderOptions::builder().hooks(hooks).build().unwrap();
+    let result = std::panic::catch_unwind(|| render_diagram("flowchart LR\nA --> B", options));
+    assert!(result.unwrap().is_ok());
+}
@@ -246,6 +246,24 @@ fn test_issue_9_edge_label_legend() {
     insta::assert_snapshot!(result.output);
 }
 
+/// Dropped labels name the edge they came from by its stable id
+#[test]
+fn test_label_dropped_carries_edge_id() {
+    let input =
+        "flowchart LR\nA --> B\nA slow@-->|This is a very long label that will not fit| C\nB --> C";
+    let result = render_mermaid_to_tui(input, RenderOptions::default()).unwrap();
+    assert_eq!(result.edge_ids, ["#0", "slow", "#2"]);
+    let ids: Vec<&str> = result
+        .warnings
+        .iter()
+        .filter_map(|w| match w {
+            DiagramWarning::LabelDropped { edge_id, .. } => Some(edge_id.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(ids, ["slow"]);
+}
+
 /// Issue #9: Cycle warning includes node names
 #[test]
 fn test_issue_9_cycle_warning_nodes() {
@@ -257,7 +275,7 @@ Z --> X"#;
     assert_eq!(result.warnings.len(), 1);
 
     match &result.warnings[0] {
-        DiagramWarning::CycleDetected { nodes } => {
+        DiagramWarning::CycleDetected { nodes, .. } => {
             assert!(nodes.contains(&"X".to_string()), "Should contain X");
             assert!(nodes.contains(&"Y".to_string()), "Should contain Y");
             assert!(nodes.contains(&"Z".to_string()), "Should contain Z");
@@ -274,11 +292,19 @@ Z --> X"#;
 fn test_issue_9_warning_display() {
     let w = DiagramWarning::CycleDetected {
         nodes: vec!["A".into(), "B".into()],
+        cycles: Vec::new(),
     };
     assert_eq!(w.to_string(), "Cycle detected involving nodes: A, B");
 
+    let w = DiagramWarning::CycleDetected {
+        nodes: vec!["A".into(), "B".into()],
+        cycles: vec![vec!["A".into(), "B".into(), "A".into()]],
+    };
+    assert_eq!(w.to_string(), "Cycle detected: A -> B -> A");
+
     let w2 = DiagramWarning::LabelDropped {
         marker: "[1]".into(),
+        edge_id: "#0".into(),
         edge_from: "X".into(),
         edge_to: "Y".into(),
         label: "my label".into(),
@@ -406,7 +432,8 @@ fn test_issue_13_check_sequence_valid() {
 /// Issue #13: check returns Err on invalid input
 #[test]
 fn test_issue_13_check_invalid() {
-    let result = check("mermaid", "flowchart\n");
+    // A bare `flowchart` header is valid (direction defaults to TB)
+    let result = check("mermaid", "flowchart\nA --> B[unclosed\n");
     assert!(result.is_err(), "Should fail on invalid input");
 }
 